@@ -0,0 +1,290 @@
+use cosmwasm_std::{ReadonlyStorage, StdError, StdResult, Storage};
+
+use cosmwasm_storage::{PrefixedStorage, ReadonlyPrefixedStorage};
+
+use secret_toolkit::storage::{AppendStore, AppendStoreMut};
+
+use crate::state::{may_load, remove, save, Bid, BidRecord, State};
+
+/// prefix for storage of the record of every bid placed, in the order the bids were placed.  A
+/// record is only ever appended or marked inactive, so a position in it never moves
+pub const PREFIX_BID_RECORDS: &[u8] = b"bidrecords";
+
+/// prefix for storage of the position of each active bidder's current bid record
+pub const PREFIX_ACTIVE_RECORDS: &[u8] = b"activerecords";
+
+/// storage key for the position of the last bid record return_all has processed
+pub const REFUND_CURSOR_KEY: &[u8] = b"refundcursor";
+
+/// prefix for storage of the viewing keys allowance bidders gave to show their balances
+pub const PREFIX_BALANCE_KEYS: &[u8] = b"balancekeys";
+
+/// prefix for storage of the list of addresses that committed to sealed bids, in the order they
+/// committed.  An address that commits again after retracting is appended again
+pub const PREFIX_COMMITTER_LIST: &[u8] = b"committerlist";
+
+/// prefix for storage of the position in the list of committers of each address with an
+/// unrevealed commitment
+pub const PREFIX_COMMITTERS: &[u8] = b"committers";
+
+/// Returns StdResult<bool>
+///
+/// true if the address has an active bid
+///
+/// # Arguments
+///
+/// * `storage` - a reference to the contract's storage
+/// * `bidder` - the bidder's canonical address as a byte slice
+pub fn is_bidder<S: ReadonlyStorage>(storage: &S, bidder: &[u8]) -> StdResult<bool> {
+    let positions = ReadonlyPrefixedStorage::new(PREFIX_ACTIVE_RECORDS, storage);
+    Ok(may_load::<u32, _>(&positions, bidder)?.is_some())
+}
+
+/// Returns StdResult<()>
+///
+/// appends the record of a bid that was just saved, marks the record of any bid it replaced as
+/// inactive, and counts a new bidder
+///
+/// # Arguments
+///
+/// * `storage` - a mutable reference to the contract's storage
+/// * `state` - a mutable reference to the auction state, whose bidder count is updated
+/// * `bidder` - the bidder's canonical address as a byte slice
+/// * `bid` - a reference to the bid that was saved
+pub fn record_bid<S: Storage>(
+    storage: &mut S,
+    state: &mut State,
+    bidder: &[u8],
+    bid: &Bid,
+) -> StdResult<()> {
+    let replaced: Option<u32> = may_load(
+        &ReadonlyPrefixedStorage::new(PREFIX_ACTIVE_RECORDS, storage),
+        bidder,
+    )?;
+    let mut record_store = PrefixedStorage::new(PREFIX_BID_RECORDS, storage);
+    let mut records = AppendStoreMut::<BidRecord, _>::attach_or_create(&mut record_store)?;
+    let position = records.len();
+    if position.checked_add(1).is_none() {
+        return Err(StdError::generic_err(
+            "This auction can not record any more bids",
+        ));
+    }
+    if let Some(old) = replaced {
+        let mut record = records.get_at(old)?;
+        record.active = false;
+        records.set_at(old, &record)?;
+    }
+    records.push(&BidRecord {
+        bidder: bidder.to_vec(),
+        amount: bid.amount,
+        timestamp: bid.timestamp,
+        active: true,
+    })?;
+    save(
+        &mut PrefixedStorage::new(PREFIX_ACTIVE_RECORDS, storage),
+        bidder,
+        &position,
+    )?;
+    if replaced.is_none() {
+        state.num_bidders += 1;
+    }
+    Ok(())
+}
+
+/// Returns StdResult<bool>
+///
+/// marks the record of an address' bid as inactive, forgets any viewing key it gave to show its
+/// balance, and returns true if it had an active bid
+///
+/// # Arguments
+///
+/// * `storage` - a mutable reference to the contract's storage
+/// * `state` - a mutable reference to the auction state, whose bidder count is updated
+/// * `bidder` - the bidder's canonical address as a byte slice
+pub fn remove_bidder<S: Storage>(
+    storage: &mut S,
+    state: &mut State,
+    bidder: &[u8],
+) -> StdResult<bool> {
+    let mut positions = PrefixedStorage::new(PREFIX_ACTIVE_RECORDS, storage);
+    let position: u32 = match may_load(&positions, bidder)? {
+        Some(p) => p,
+        None => return Ok(false),
+    };
+    remove(&mut positions, bidder);
+    remove(
+        &mut PrefixedStorage::new(PREFIX_BALANCE_KEYS, storage),
+        bidder,
+    );
+    let mut record_store = PrefixedStorage::new(PREFIX_BID_RECORDS, storage);
+    let mut records = AppendStoreMut::<BidRecord, _>::attach_or_create(&mut record_store)?;
+    let mut record = records.get_at(position)?;
+    record.active = false;
+    records.set_at(position, &record)?;
+    state.num_bidders -= 1;
+    Ok(true)
+}
+
+/// Returns StdResult<(Vec<BidRecord>, Option<u32>)>
+///
+/// reads up to `limit` bid records after the given position, and returns the active ones in the
+/// order they were placed, along with the position to start after to resume, or None if every
+/// record has been read
+///
+/// # Arguments
+///
+/// * `storage` - a reference to the contract's storage
+/// * `start_after` - optional position of the last record already read
+/// * `limit` - maximum number of records to read
+pub fn bid_records_page<S: ReadonlyStorage>(
+    storage: &S,
+    start_after: Option<u32>,
+    limit: u32,
+) -> StdResult<(Vec<BidRecord>, Option<u32>)> {
+    let record_store = ReadonlyPrefixedStorage::new(PREFIX_BID_RECORDS, storage);
+    let records = match AppendStore::<BidRecord, _>::attach(&record_store) {
+        Some(store) => store?,
+        None => return Ok((Vec::new(), None)),
+    };
+    let start = start_after.map_or(0, |p| p.saturating_add(1));
+    // always read at least one record so the page makes progress
+    let end = start.saturating_add(limit.max(1)).min(records.len());
+    let mut page = Vec::new();
+    for position in start..end {
+        let record = records.get_at(position)?;
+        if record.active {
+            page.push(record);
+        }
+    }
+    let resume = if end < records.len() {
+        Some(end - 1)
+    } else {
+        None
+    };
+    Ok((page, resume))
+}
+
+/// Returns StdResult<Vec<BidRecord>>
+///
+/// lists the records of the active bids in the order they were placed
+///
+/// # Arguments
+///
+/// * `storage` - a reference to the contract's storage
+pub fn active_records<S: ReadonlyStorage>(storage: &S) -> StdResult<Vec<BidRecord>> {
+    let (records, _) = bid_records_page(storage, None, u32::MAX)?;
+    Ok(records)
+}
+
+/// Returns StdResult<Vec<Vec<u8>>>
+///
+/// lists the canonical addresses of every bidder with an active bid, in the order their bids
+/// were placed
+///
+/// # Arguments
+///
+/// * `storage` - a reference to the contract's storage
+pub fn list_bidders<S: ReadonlyStorage>(storage: &S) -> StdResult<Vec<Vec<u8>>> {
+    Ok(active_records(storage)?
+        .into_iter()
+        .map(|r| r.bidder)
+        .collect())
+}
+
+/// Returns StdResult<bool>
+///
+/// true if the address has an unrevealed sealed commitment
+///
+/// # Arguments
+///
+/// * `storage` - a reference to the contract's storage
+/// * `committer` - the committer's canonical address as a byte slice
+pub fn is_committer<S: ReadonlyStorage>(storage: &S, committer: &[u8]) -> StdResult<bool> {
+    let positions = ReadonlyPrefixedStorage::new(PREFIX_COMMITTERS, storage);
+    Ok(may_load::<u32, _>(&positions, committer)?.is_some())
+}
+
+/// Returns StdResult<()>
+///
+/// adds an address that just committed to a sealed bid to the list of committers, and counts it
+/// if it did not already have an unrevealed commitment
+///
+/// # Arguments
+///
+/// * `storage` - a mutable reference to the contract's storage
+/// * `state` - a mutable reference to the auction state, whose committer count is updated
+/// * `committer` - the committer's canonical address as a byte slice
+pub fn add_committer<S: Storage>(
+    storage: &mut S,
+    state: &mut State,
+    committer: &[u8],
+) -> StdResult<()> {
+    if is_committer(storage, committer)? {
+        return Ok(());
+    }
+    let mut list_store = PrefixedStorage::new(PREFIX_COMMITTER_LIST, storage);
+    let mut list = AppendStoreMut::<Vec<u8>, _>::attach_or_create(&mut list_store)?;
+    let position = list.len();
+    if position.checked_add(1).is_none() {
+        return Err(StdError::generic_err(
+            "This auction can not record any more commitments",
+        ));
+    }
+    list.push(&committer.to_vec())?;
+    save(
+        &mut PrefixedStorage::new(PREFIX_COMMITTERS, storage),
+        committer,
+        &position,
+    )?;
+    state.num_committers += 1;
+    Ok(())
+}
+
+/// Returns StdResult<bool>
+///
+/// removes an address from the committers, and returns true if it had an unrevealed commitment
+///
+/// # Arguments
+///
+/// * `storage` - a mutable reference to the contract's storage
+/// * `state` - a mutable reference to the auction state, whose committer count is updated
+/// * `committer` - the committer's canonical address as a byte slice
+pub fn remove_committer<S: Storage>(
+    storage: &mut S,
+    state: &mut State,
+    committer: &[u8],
+) -> StdResult<bool> {
+    if !is_committer(storage, committer)? {
+        return Ok(false);
+    }
+    remove(
+        &mut PrefixedStorage::new(PREFIX_COMMITTERS, storage),
+        committer,
+    );
+    state.num_committers -= 1;
+    Ok(true)
+}
+
+/// Returns StdResult<Vec<Vec<u8>>>
+///
+/// lists the canonical addresses with unrevealed commitments, in the order they committed
+///
+/// # Arguments
+///
+/// * `storage` - a reference to the contract's storage
+pub fn list_committers<S: ReadonlyStorage>(storage: &S) -> StdResult<Vec<Vec<u8>>> {
+    let list_store = ReadonlyPrefixedStorage::new(PREFIX_COMMITTER_LIST, storage);
+    let positions = ReadonlyPrefixedStorage::new(PREFIX_COMMITTERS, storage);
+    let mut committers = Vec::new();
+    if let Some(list) = AppendStore::<Vec<u8>, _>::attach(&list_store) {
+        for (position, committer) in list?.iter().enumerate() {
+            let committer = committer?;
+            // skip entries left by an address that retracted, or that committed again later
+            let current: Option<u32> = may_load(&positions, &committer)?;
+            if current == Some(position as u32) {
+                committers.push(committer);
+            }
+        }
+    }
+    Ok(committers)
+}
@@ -0,0 +1,278 @@
+use cosmwasm_std::{
+    to_binary, Api, Env, Extern, HandleResponse, HandleResult, HumanAddr, Querier, QueryResult,
+    StdError, Storage,
+};
+
+use cosmwasm_storage::{PrefixedStorage, ReadonlyPrefixedStorage};
+
+use secret_toolkit::storage::{AppendStore, AppendStoreMut};
+
+use crate::bidders::is_bidder;
+use crate::contract::{is_key_valid, is_seller_or_operator, CONFIG_KEY, PREFIX_OUTCOMES};
+use crate::msg::{BoardMessage, HandleAnswer, QueryAnswer, ResponseStatus::Success};
+use crate::state::{load, may_load, Outcome, State, StoredMessage};
+
+/// prefix for storage of the message board
+pub const PREFIX_MESSAGES: &[u8] = b"messages";
+
+/// maximum length of a message in bytes
+pub const MAX_MESSAGE_LEN: usize = 500;
+
+/// number of messages listed if no page size is given
+pub const DEFAULT_PAGE_SIZE: u32 = 20;
+
+/// Returns HandleResult
+///
+/// posts a message to the auction's message board
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `text` - text of the message
+pub fn try_post_message<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    text: String,
+) -> HandleResult {
+    let state: State = load(&deps.storage, CONFIG_KEY)?;
+    if !state.message_board {
+        return Err(StdError::generic_err(
+            "This auction does not have a message board",
+        ));
+    }
+    if state.is_completed {
+        return Err(StdError::generic_err(
+            "The message board is read only once the auction has closed",
+        ));
+    }
+    if text.trim().is_empty() || text.len() > MAX_MESSAGE_LEN {
+        return Err(StdError::generic_err(format!(
+            "A message must not be empty or longer than {} bytes",
+            MAX_MESSAGE_LEN
+        )));
+    }
+    let author = deps.api.canonical_address(&env.message.sender)?;
+    let from_seller = is_seller_or_operator(&deps.api, &state, &env.message.sender)?;
+    if !from_seller && !is_bidder(&deps.storage, author.as_slice())? {
+        return Err(StdError::generic_err(
+            "Only the seller, its operators, and bidders with an active bid may post messages",
+        ));
+    }
+    let mut store = PrefixedStorage::new(PREFIX_MESSAGES, &mut deps.storage);
+    let mut board = AppendStoreMut::<StoredMessage, _>::attach_or_create(&mut store)?;
+    let id = board.len();
+    board.push(&StoredMessage {
+        author: author.as_slice().to_vec(),
+        from_seller,
+        text,
+        timestamp: env.block.time,
+    })?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: Some(to_binary(&HandleAnswer::PostMessage {
+            status: Success,
+            id,
+        })?),
+    })
+}
+
+/// Returns QueryResult listing the message board in reverse chronological order
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `address` - a reference to the address reading the board
+/// * `viewing_key` - String holding the viewing key
+/// * `before` - optionally only show messages with id less than specified value
+/// * `page_size` - optional number of messages to return
+pub fn try_view_messages<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    address: &HumanAddr,
+    viewing_key: String,
+    before: Option<u32>,
+    page_size: Option<u32>,
+) -> QueryResult {
+    let state: State = load(&deps.storage, CONFIG_KEY)?;
+    let key_valid = is_key_valid(deps, &state, address, viewing_key)?;
+    let reader = deps.api.canonical_address(address)?;
+    // only the seller and those who have bid may read the board
+    let may_read = is_seller_or_operator(&deps.api, &state, address)?
+        || is_bidder(&deps.storage, reader.as_slice())?
+        || may_load::<Outcome, _>(
+            &ReadonlyPrefixedStorage::new(PREFIX_OUTCOMES, &deps.storage),
+            reader.as_slice(),
+        )?
+        .is_some();
+    if !key_valid || !may_read {
+        return to_binary(&QueryAnswer::ViewingKeyError {
+            error: "Address and/or viewing key does not match the seller or a bidder of this \
+                    auction"
+                .to_string(),
+        });
+    }
+    let store = ReadonlyPrefixedStorage::new(PREFIX_MESSAGES, &deps.storage);
+    let mut messages = Vec::new();
+    let mut count = 0u32;
+    if let Some(board) = AppendStore::<StoredMessage, _>::attach(&store).and_then(|r| r.ok()) {
+        count = board.len();
+        let end = before.map_or(count, |b| b.min(count));
+        let take = page_size.unwrap_or(DEFAULT_PAGE_SIZE);
+        for id in (end.saturating_sub(take)..end).rev() {
+            let stored = board.get_at(id)?;
+            messages.push(BoardMessage {
+                id,
+                from_seller: stored.from_seller,
+                is_yours: stored.author == reader.as_slice(),
+                text: stored.text,
+                timestamp: stored.timestamp,
+            });
+        }
+    }
+
+    to_binary(&QueryAnswer::Messages { messages, count })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::contract::tests::default_init_msg;
+    use crate::contract::{derive_key_hash, handle, init, query};
+    use crate::msg::{HandleMsg, InitMsg, QueryMsg};
+    use cosmwasm_std::testing::{mock_dependencies, mock_env, MockApi, MockQuerier, MockStorage};
+    use cosmwasm_std::{from_binary, Binary, StdResult, Uint128};
+
+    fn init_helper(message_board: bool) -> Extern<MockStorage, MockApi, MockQuerier> {
+        let mut deps = mock_dependencies(20, &[]);
+        let mut env = mock_env("factory", &[]);
+        env.block.time = 0;
+        let init_msg = InitMsg {
+            message_board,
+            ..default_init_msg()
+        };
+        init(&mut deps, env, init_msg).unwrap();
+        deps
+    }
+
+    fn env_at(sender: &str, time: u64) -> Env {
+        let mut env = mock_env(sender, &[]);
+        env.block.time = time;
+        env
+    }
+
+    fn post(
+        deps: &mut Extern<MockStorage, MockApi, MockQuerier>,
+        sender: &str,
+        text: &str,
+    ) -> StdResult<HandleResponse> {
+        let handle_msg = HandleMsg::PostMessage {
+            text: text.to_string(),
+        };
+        handle(deps, env_at(sender, 500), handle_msg)
+    }
+
+    /// lets the address authenticate with the viewing key "key" without querying the factory
+    fn share_key(deps: &mut Extern<MockStorage, MockApi, MockQuerier>, address: &str) {
+        let handle_msg = HandleMsg::ShareKeyHash {
+            address: HumanAddr(address.to_string()),
+            key_hash: Binary(derive_key_hash(
+                "key",
+                &HumanAddr("cosmos2contract".to_string()),
+            )),
+            epoch: 1,
+        };
+        handle(deps, env_at("factoryaddr", 500), handle_msg).unwrap();
+    }
+
+    fn read(
+        deps: &Extern<MockStorage, MockApi, MockQuerier>,
+        address: &str,
+        before: Option<u32>,
+        page_size: Option<u32>,
+    ) -> QueryAnswer {
+        let query_msg = QueryMsg::Messages {
+            address: HumanAddr(address.to_string()),
+            viewing_key: "key".to_string(),
+            before,
+            page_size,
+        };
+        from_binary(&query(deps, query_msg).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_message_board() {
+        // the board is optional
+        let mut deps = init_helper(false);
+        let error = post(&mut deps, "alice", "hello").unwrap_err();
+        assert!(error.to_string().contains("does not have a message board"));
+
+        let mut deps = init_helper(true);
+        // only bidders may ask questions
+        let error = post(&mut deps, "bob", "is it real?").unwrap_err();
+        assert!(error.to_string().contains("Only the seller"));
+        let handle_msg = HandleMsg::Receive {
+            sender: HumanAddr("blah".to_string()),
+            from: HumanAddr("bob".to_string()),
+            amount: Uint128(100),
+            msg: None,
+        };
+        handle(&mut deps, env_at("bidaddr", 500), handle_msg).unwrap();
+        let error = post(&mut deps, "bob", "").unwrap_err();
+        assert!(error.to_string().contains("must not be empty"));
+        let error = post(&mut deps, "bob", &"a".repeat(MAX_MESSAGE_LEN + 1)).unwrap_err();
+        assert!(error.to_string().contains("longer than"));
+        let resp = post(&mut deps, "bob", "is it real?").unwrap();
+        let answer: HandleAnswer = from_binary(&resp.data.unwrap()).unwrap();
+        assert!(matches!(answer, HandleAnswer::PostMessage { id: 0, .. }));
+        post(&mut deps, "alice", "yes").unwrap();
+        post(&mut deps, "alice", "consigning soon").unwrap();
+
+        // outsiders can not read the board, even with a valid key
+        for address in ["alice", "bob", "charlie"].iter() {
+            share_key(&mut deps, address);
+        }
+        assert!(matches!(
+            read(&deps, "charlie", None, None),
+            QueryAnswer::ViewingKeyError { .. }
+        ));
+        // bidders see the seller's posts, but not who else posted
+        match read(&deps, "bob", None, Some(2)) {
+            QueryAnswer::Messages { messages, count } => {
+                assert_eq!(count, 3);
+                let ids: Vec<u32> = messages.iter().map(|m| m.id).collect();
+                assert_eq!(ids, vec![2, 1]);
+                assert!(messages.iter().all(|m| m.from_seller && !m.is_yours));
+            }
+            _ => panic!("Unexpected QueryAnswer"),
+        }
+        match read(&deps, "alice", Some(1), None) {
+            QueryAnswer::Messages { messages, .. } => {
+                assert_eq!(
+                    messages,
+                    vec![BoardMessage {
+                        id: 0,
+                        from_seller: false,
+                        is_yours: false,
+                        text: "is it real?".to_string(),
+                        timestamp: 500,
+                    }]
+                );
+            }
+            _ => panic!("Unexpected QueryAnswer"),
+        }
+
+        // a bidder who retracts may still read, but may no longer post
+        handle(&mut deps, env_at("bob", 600), HandleMsg::RetractBid {}).unwrap();
+        share_key(&mut deps, "bob");
+        let error = post(&mut deps, "bob", "never mind").unwrap_err();
+        assert!(error.to_string().contains("Only the seller"));
+        match read(&deps, "bob", None, None) {
+            QueryAnswer::Messages { messages, .. } => {
+                assert!(messages.iter().any(|m| m.id == 0 && m.is_yours));
+            }
+            _ => panic!("Unexpected QueryAnswer"),
+        }
+    }
+}
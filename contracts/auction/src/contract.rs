@@ -1,31 +1,92 @@
 use serde::{Deserialize, Serialize};
 
 use cosmwasm_std::{
-    log, to_binary, Api, CanonicalAddr, Env, Extern, HandleResponse, HandleResult, HumanAddr,
-    InitResponse, InitResult, Querier, QueryResult, StdError, Storage, Uint128,
+    from_binary, log, to_binary, Api, Binary, CanonicalAddr, Env, Extern, HandleResponse,
+    HandleResult, HumanAddr, InitResponse, InitResult, Querier, QueryResult, ReadonlyStorage,
+    StdError, StdResult, Storage, Uint128,
 };
 
-use std::collections::HashSet;
+use std::{
+    cmp::Ordering,
+    collections::{BTreeMap, HashSet},
+};
 
 use serde_json_wasm as serde_json;
 
+use cosmwasm_storage::{PrefixedStorage, ReadonlyPrefixedStorage};
+
 use secret_toolkit::utils::{pad_handle_result, pad_query_result, HandleCallback, Query};
 
+use auction_common::crypto::sha_256;
+use auction_common::math::{
+    checked_add, checked_mul, mul_div, split, total_for_unit_price, unit_price_for_total, Rounding,
+};
+use auction_common::MAX_BUNDLE_ITEMS;
+
+use crate::bidders::{
+    add_committer, bid_records_page, is_bidder, is_committer, list_bidders, list_committers,
+    record_bid, remove_bidder, remove_committer, PREFIX_BALANCE_KEYS, REFUND_CURSOR_KEY,
+};
+use crate::board::{try_post_message, try_view_messages};
+use crate::factory_only::{factory_only, FORCE_FINALIZE_GRACE};
 use crate::msg::{
-    ContractInfo, HandleAnswer, HandleMsg, InitMsg, QueryAnswer, QueryMsg, ResponseStatus,
+    BidResult, BundleItem, BundleToken, ConsignmentInfo, ContractInfo, FactoryInfo, HandleAnswer,
+    HandleMsg, InitMsg, MinBidDecayInfo, QueryAnswer, QueryMsg, ReceiveMsg, ResponseStatus,
     ResponseStatus::{Failure, Success},
-    Token,
+    ReturnedAmount, SettlementMode, SettlementPhase, TieBreak, Token,
+};
+use crate::notify::{notification_logs, try_register_notification};
+use crate::state::{
+    load, may_load, remove, save, Bid, Commitment, ConsignItem, Outcome, State, StoredDecay,
 };
-use crate::state::{load, may_load, remove, save, Bid, State};
 
 use chrono::NaiveDateTime;
 
 /// storage key for auction state
 pub const CONFIG_KEY: &[u8] = b"config";
+/// prefix for storage of the outcomes of bids that are no longer active
+pub const PREFIX_OUTCOMES: &[u8] = b"outcome";
+/// prefix for storage of sealed commitments that have not been revealed
+pub const PREFIX_COMMITMENTS: &[u8] = b"commitments";
+/// prefix for storage of the cache of viewing key hashes the factory vouched for.  Each entry is
+/// keyed by the cache generation, the address, and the key hash, and holds the address' factory
+/// key epoch the hash was valid in
+pub const PREFIX_KEY_CACHE: &[u8] = b"keycache";
+/// prefix for storage of the latest factory key epoch the auction has seen for each address.  The
+/// factory starts a new epoch every time an address sets a viewing key, so cached hashes from an
+/// earlier epoch no longer validate
+pub const PREFIX_KEY_EPOCHS: &[u8] = b"keyepochs";
+/// storage key for the generation of the viewing key cache.  Every cache entry is keyed by the
+/// generation it was written in, so bumping the generation forgets the whole cache without
+/// iterating over it.  This is done when the auction closes or moves to a new factory, because
+/// the factory will not share later key changes with it
+pub const KEY_CACHE_GENERATION_KEY: &[u8] = b"keycachegen";
+/// storage key for the ranked list of bidders left to settle with after a winner defaulted
+pub const STANDBY_KEY: &[u8] = b"standby";
+
+/// maximum length of a language code in bytes
+pub const MAX_LANG_LEN: usize = 16;
+
+/// maximum length of a bid memo in bytes once escaped as JSON, so a bid answer that includes it
+/// still fits in BID_ANSWER_SIZE
+pub const MAX_MEMO_LEN: usize = 256;
+
+/// maximum length of a bid rejection reason in bytes once escaped as JSON, so an outcome answer
+/// that includes it still fits in BID_ANSWER_SIZE
+pub const MAX_REASON_LEN: usize = 256;
 
 /// pad handle responses and log attributes to blocks of 256 bytes to prevent leaking info based on
 /// response size
 pub const BLOCK_SIZE: usize = 256;
+/// storage key for the block size the auction pads its responses to
+pub const BLOCK_SIZE_KEY: &[u8] = b"blocksize";
+/// size in bytes that responses revealing the amount of a bid are padded to, so the number of
+/// digits in the bid can not be inferred from the number of blocks in the response
+pub const BID_ANSWER_SIZE: usize = 4 * BLOCK_SIZE;
+
+/// message given when tokens are sent to an auction its factory has halted
+const HALTED_MSG: &str =
+    "This auction has been halted by its factory.  Your tokens have been returned";
 
 /// auction info needed by factory
 #[derive(Serialize)]
@@ -34,10 +95,6 @@ pub struct FactoryAuctionInfo {
     pub index: u32,
     /// auction label
     pub label: String,
-    /// sell symbol index
-    pub sell_symbol: u16,
-    /// bid symbol index
-    pub bid_symbol: u16,
     /// sell amount
     pub sell_amount: Uint128,
     /// minimum bid
@@ -59,6 +116,14 @@ pub enum FactoryHandleMsg {
         auction: FactoryAuctionInfo,
         /// sell token contract info
         sell_contract: ContractInfo,
+        /// additional tokens sold in the same lot
+        bundle: Vec<BundleItem>,
+        /// bid token contract info
+        bid_contract: ContractInfo,
+        /// optional deposit the seller posts in bid tokens
+        deposit: Option<Uint128>,
+        /// key the factory gave this auction to authenticate its callbacks
+        callback_key: String,
     },
     /// registers the closure of this auction with the factory
     CloseAuction {
@@ -70,13 +135,25 @@ pub enum FactoryHandleMsg {
         bidder: Option<HumanAddr>,
         /// winning bid if the auction ended in a swap
         winning_bid: Option<Uint128>,
+        /// other bidders that split the lot with the winner because of a tie
+        co_winners: Vec<HumanAddr>,
+        /// address that referred the winning bidder
+        referrer: Option<HumanAddr>,
+        /// number of distinct bidders the auction needed but did not reach
+        unmet_min_bidders: Option<u32>,
+        /// key the factory gave this auction to authenticate its callbacks
+        callback_key: String,
     },
-    /// registers a new bidder with the factory
+    /// registers a bidder, and the amount of their bid if it is known, with the factory
     RegisterBidder {
         /// auction index
         index: u32,
         /// bidder's address
         bidder: HumanAddr,
+        /// amount of the bidder's active bid.  None for a sealed bid that has not been revealed
+        amount: Option<Uint128>,
+        /// key the factory gave this auction to authenticate its callbacks
+        callback_key: String,
     },
     /// tells factory the address is no longer a bidder in this auction
     RemoveBidder {
@@ -84,8 +161,10 @@ pub enum FactoryHandleMsg {
         index: u32,
         /// bidder's address
         bidder: HumanAddr,
+        /// key the factory gave this auction to authenticate its callbacks
+        callback_key: String,
     },
-    /// tells factory the closing time and/or minimum bid changed
+    /// tells factory the closing time, minimum bid, and/or sell amount changed
     ChangeAuctionInfo {
         /// auction index
         index: u32,
@@ -93,6 +172,21 @@ pub enum FactoryHandleMsg {
         ends_at: Option<u64>,
         /// optional new minimum bid
         minimum_bid: Option<Uint128>,
+        /// optional new sell amount
+        sell_amount: Option<Uint128>,
+        /// key the factory gave this auction to authenticate its callbacks
+        callback_key: String,
+    },
+    /// tells factory the auction has a new seller
+    ChangeSeller {
+        /// auction index
+        index: u32,
+        /// previous seller's address
+        seller: HumanAddr,
+        /// new seller's address
+        new_seller: HumanAddr,
+        /// key the factory gave this auction to authenticate its callbacks
+        callback_key: String,
     },
 }
 
@@ -148,11 +242,181 @@ pub fn init<S: Storage, A: Api, Q: Querier>(
     if msg.sell_amount == Uint128(0) {
         return Err(StdError::generic_err("Sell amount must be greater than 0"));
     }
-    if msg.sell_contract.address == msg.bid_contract.address {
+    if msg.sell_contract.is_same_token(&msg.bid_contract) {
         return Err(StdError::generic_err(
             "Sell contract and bid contract must be different",
         ));
     }
+    if msg.ends_at <= env.block.time {
+        return Err(StdError::generic_err(format!(
+            "Closing time {} must be later than the current block time {}",
+            msg.ends_at, env.block.time
+        )));
+    }
+    if let Some(consign_by) = msg.consign_by {
+        if consign_by <= env.block.time || consign_by > msg.ends_at {
+            return Err(StdError::generic_err(format!(
+                "Consignment deadline {} must be later than the current block time {} and no \
+                 later than the closing time {}",
+                consign_by, env.block.time, msg.ends_at
+            )));
+        }
+    }
+    if msg
+        .buy_now_price
+        .map_or(false, |p| p == Uint128(0) || p < msg.minimum_bid)
+    {
+        return Err(StdError::generic_err(
+            "Buy now price must be greater than 0 and at least the minimum bid",
+        ));
+    }
+    if let Some(decay) = msg.min_bid_decay.as_ref() {
+        if decay.interval == 0 || decay.step == Uint128(0) {
+            return Err(StdError::generic_err(
+                "The minimum bid decay step and interval must be greater than 0",
+            ));
+        }
+        if decay.floor > msg.minimum_bid {
+            return Err(StdError::generic_err(
+                "The minimum bid decay floor can not be more than the minimum bid",
+            ));
+        }
+    }
+    if msg.seller_confirmation == Some(0) {
+        return Err(StdError::generic_err(
+            "The seller confirmation window must be greater than 0",
+        ));
+    }
+    if msg.max_bidders == Some(0) {
+        return Err(StdError::generic_err(
+            "The maximum number of bidders must be greater than 0",
+        ));
+    }
+    if let Some(min_bidders) = msg.min_bidders {
+        if min_bidders == 0 || msg.max_bidders.map_or(false, |m| min_bidders > m) {
+            return Err(StdError::generic_err(
+                "The minimum number of bidders must be greater than 0 and no more than the \
+                 maximum number of bidders",
+            ));
+        }
+    }
+    if msg.reveal_window == Some(0) {
+        return Err(StdError::generic_err(
+            "The reveal window must be greater than 0",
+        ));
+    }
+    if msg.reveal_window.is_some() && (msg.allowance_bids || msg.buy_now_price.is_some()) {
+        return Err(StdError::generic_err(
+            "An auction with sealed bids can not take allowance bids or have a buy now price",
+        ));
+    }
+    if msg.settlement == SettlementMode::Raffle {
+        if msg.raffle_seed.as_ref().map_or(true, |s| s.is_empty()) {
+            return Err(StdError::generic_err(
+                "A raffle needs a seed from the factory to draw the winner",
+            ));
+        }
+        // the lot goes to a random bid, so no price can guarantee a buyer the lot
+        if msg.allowance_bids || msg.buy_now_price.is_some() || msg.tie_break == TieBreak::ProRata {
+            return Err(StdError::generic_err(
+                "A raffle can not take allowance bids, have a buy now price, or split the lot \
+                 among tied bids",
+            ));
+        }
+    }
+    if msg.settlement == SettlementMode::Crowdfund {
+        if msg.funding_target.map_or(true, |t| t == Uint128(0)) {
+            return Err(StdError::generic_err(
+                "A crowdfund needs a funding target greater than 0",
+            ));
+        }
+        if !msg.bundle.is_empty()
+            || msg.allowance_bids
+            || msg.buy_now_price.is_some()
+            || msg.tie_break == TieBreak::ProRata
+        {
+            return Err(StdError::generic_err(
+                "A crowdfund can not have a bundled lot, allowance bids, a buy now price, or \
+                 split the lot among tied bids",
+            ));
+        }
+    } else if msg.funding_target.is_some() {
+        return Err(StdError::generic_err(
+            "A funding target can only be set for a crowdfund",
+        ));
+    }
+    if msg.tie_break == TieBreak::ProRata && !msg.bundle.is_empty() {
+        return Err(StdError::generic_err(
+            "A bundled lot can not be split among tied bids",
+        ));
+    }
+    if msg.allowance_bids {
+        if msg.bid_contract.token_id.is_some() {
+            return Err(StdError::generic_err(
+                "Bids can only be backed by allowances if the bid token is a SNIP-20",
+            ));
+        }
+        if msg.buy_now_price.is_some() || msg.tie_break == TieBreak::ProRata {
+            return Err(StdError::generic_err(
+                "An auction with allowance bids can not have a buy now price or split the lot \
+                 among tied bids",
+            ));
+        }
+    }
+    if msg.bundle.len() > MAX_BUNDLE_ITEMS {
+        return Err(StdError::generic_err(format!(
+            "An auction can not bundle more than {} additional tokens",
+            MAX_BUNDLE_ITEMS
+        )));
+    }
+    // a smaller block would let the size of a padded response reveal more about its contents
+    let block_size = msg.block_size.unwrap_or(BLOCK_SIZE as u16);
+    if usize::from(block_size) < BLOCK_SIZE {
+        return Err(StdError::generic_err(format!(
+            "Block size must be at least {}",
+            BLOCK_SIZE
+        )));
+    }
+    // every bundled token must be distinct from the sell token, the bid token, and each other
+    for (i, item) in msg.bundle.iter().enumerate() {
+        if item.amount == Uint128(0) {
+            return Err(StdError::generic_err(
+                "Bundled token amounts must be greater than 0",
+            ));
+        }
+        if item.contract.is_same_token(&msg.bid_contract)
+            || item.contract.is_same_token(&msg.sell_contract)
+            || msg.bundle[..i]
+                .iter()
+                .any(|prev| prev.contract.is_same_token(&item.contract))
+        {
+            return Err(StdError::generic_err(
+                "Each bundled token must be different from the sell token, the bid token, and \
+                 the other bundled tokens",
+            ));
+        }
+    }
+    let mut descriptions = BTreeMap::new();
+    for translation in msg.descriptions.into_iter() {
+        check_lang(&translation.lang)?;
+        if descriptions
+            .insert(translation.lang, translation.text)
+            .is_some()
+        {
+            return Err(StdError::generic_err(
+                "A language can only be given one description",
+            ));
+        }
+    }
+    // the auction needs its own viewing key with the bid token to check bidders' allowances
+    let allowance_key = if msg.allowance_bids {
+        let mut entropy = msg.callback_key.as_bytes().to_vec();
+        entropy.extend_from_slice(env.contract.address.0.as_bytes());
+        entropy.extend_from_slice(&env.block.time.to_be_bytes());
+        Some(base64::encode(sha_256(&entropy)))
+    } else {
+        None
+    };
     let state = State {
         factory: msg.factory.clone(),
         index: msg.index,
@@ -165,45 +429,101 @@ pub fn init<S: Storage, A: Api, Q: Querier>(
         sell_amount: msg.sell_amount.u128(),
         minimum_bid: msg.minimum_bid.u128(),
         currently_consigned: 0,
-        bidders: HashSet::new(),
+        num_bidders: 0,
         ends_at: msg.ends_at,
         is_completed: false,
+        is_cancelled: false,
+        is_force_closed: false,
+        is_halted: false,
+        phase: SettlementPhase::Open,
+        bidding_paused: false,
         tokens_consigned: false,
         description: msg.description,
+        descriptions,
         winning_bid: 0,
+        bundle: msg.bundle.iter().cloned().map(ConsignItem::from).collect(),
+        deposit: msg.deposit.map_or(0, |d| d.u128()),
+        deposit_posted: 0,
+        consign_by: msg.consign_by,
+        buy_now_price: msg.buy_now_price.map(|p| p.u128()),
+        no_self_bid: msg.no_self_bid,
+        only_increasing_rebids: msg.only_increasing_rebids,
+        operators: HashSet::new(),
+        last_bid_at: None,
+        tie_break: msg.tie_break,
+        callback_key: msg.callback_key.clone(),
+        allowance_key,
+        message_board: msg.message_board,
+        winner_memos: Vec::new(),
+        deprecation_notice: None,
+        min_bid_decay: msg.min_bid_decay.map(|d| StoredDecay {
+            floor: d.floor.u128(),
+            step: d.step.u128(),
+            interval: d.interval,
+            start: env.block.time,
+        }),
+        seller_confirmation: msg.seller_confirmation,
+        accept_by: None,
+        is_rejected: false,
+        unit_price_bids: msg.unit_price_bids,
+        max_bidders: msg.max_bidders,
+        min_bidders: msg.min_bidders,
+        too_few_bidders: false,
+        reveal_window: msg.reveal_window,
+        num_committers: 0,
+        settlement: msg.settlement,
+        raffle_seed: msg.raffle_seed.map(|s| s.0),
+        funding_target: msg.funding_target.map(|t| t.u128()),
+        accepted_bidder: None,
     };
 
     save(&mut deps.storage, CONFIG_KEY, &state)?;
+    save(&mut deps.storage, BLOCK_SIZE_KEY, &block_size)?;
 
     let auction = FactoryAuctionInfo {
         label: msg.label,
         index: msg.index,
-        sell_symbol: msg.sell_symbol,
-        bid_symbol: msg.bid_symbol,
         sell_amount: msg.sell_amount,
         minimum_bid: msg.minimum_bid,
         ends_at: msg.ends_at,
     };
 
+    // register receive with the bid/sell token contracts
+    let mut messages = vec![
+        state
+            .sell_contract
+            .register_receive_msg(env.contract_code_hash.clone())?,
+        state
+            .bid_contract
+            .register_receive_msg(env.contract_code_hash.clone())?,
+    ];
+    // and any bundled token contracts
+    for item in &state.bundle {
+        messages.push(
+            item.contract
+                .register_receive_msg(env.contract_code_hash.clone())?,
+        );
+    }
+    if let Some(key) = state.allowance_key.as_ref() {
+        messages.push(state.bid_contract.set_viewing_key_msg(key.clone())?);
+    }
     let reg_auction_msg = FactoryHandleMsg::RegisterAuction {
         seller: msg.seller,
         auction,
         sell_contract: msg.sell_contract,
+        bundle: msg.bundle,
+        bid_contract: state.bid_contract.clone(),
+        deposit: msg.deposit.filter(|d| d.u128() > 0),
+        callback_key: msg.callback_key,
     };
     // perform factory register callback
-    let cosmos_msg =
-        reg_auction_msg.to_cosmos_msg(msg.factory.code_hash, msg.factory.address, None)?;
-    // and register receive with the bid/sell token contracts
+    messages.push(reg_auction_msg.to_cosmos_msg(
+        msg.factory.code_hash,
+        msg.factory.address,
+        None,
+    )?);
     Ok(InitResponse {
-        messages: vec![
-            state
-                .sell_contract
-                .register_receive_msg(env.contract_code_hash.clone())?,
-            state
-                .bid_contract
-                .register_receive_msg(env.contract_code_hash)?,
-            cosmos_msg,
-        ],
+        messages,
         log: vec![],
     })
 }
@@ -222,16 +542,149 @@ pub fn handle<S: Storage, A: Api, Q: Querier>(
     msg: HandleMsg,
 ) -> HandleResult {
     let response = match msg {
-        HandleMsg::RetractBid { .. } => try_retract(deps, env.message.sender),
+        HandleMsg::PlaceAllowanceBid {
+            amount,
+            balance_key,
+            expiration,
+            memo,
+            referrer,
+        } => try_allowance_bid(deps, env, amount, balance_key, expiration, memo, referrer),
+        HandleMsg::RevealBid { amount, salt } => try_reveal_bid(deps, env, amount, salt),
+        HandleMsg::RetractBid { .. } => try_retract(deps, env),
+        HandleMsg::ReclaimExpiredBid { .. } => try_reclaim_expired(deps, env),
         HandleMsg::Finalize {
             new_ends_at,
             new_minimum_bid,
-        } => try_finalize(deps, env, new_ends_at, new_minimum_bid, false),
-        HandleMsg::ReturnAll { .. } => try_finalize(deps, env, None, None, true),
-        HandleMsg::Receive { from, amount, .. } => try_receive(deps, env, from, amount),
-        HandleMsg::ChangeMinimumBid { minimum_bid } => try_change_min_bid(deps, env, minimum_bid),
+        } => try_finalize(deps, env, new_ends_at, new_minimum_bid),
+        HandleMsg::AcceptCurrentBest {} => try_accept_current_best(deps, env),
+        HandleMsg::AcceptBid { bidder } => try_accept_bid(deps, env, bidder),
+        HandleMsg::RejectBid { bidder, reason } => try_reject_bid(deps, env, bidder, reason),
+        HandleMsg::PauseBidding {} => try_pause_bidding(deps, env, true),
+        HandleMsg::ResumeBidding {} => try_pause_bidding(deps, env, false),
+        HandleMsg::ReturnAll { page_size } => try_return_all(deps, env, page_size),
+        HandleMsg::CancelUnconsigned { .. } => try_cancel_unconsigned(deps, env),
+        HandleMsg::ForceClose { .. } => try_force_close(deps, env),
+        HandleMsg::Receive {
+            from, amount, msg, ..
+        } => try_receive(deps, env, from, amount, None, msg),
+        HandleMsg::Snip1155Receive {
+            from,
+            amount,
+            token_id,
+            msg,
+            ..
+        } => match from {
+            Some(owner) => try_receive(deps, env, owner, amount, Some(&token_id), msg),
+            None => Err(StdError::generic_err(
+                "Tokens sent to the auction must have an owner",
+            )),
+        },
+        HandleMsg::ChangeMinimumBid {
+            minimum_bid,
+            refund_lower_bids,
+        } => try_change_min_bid(deps, env, minimum_bid, refund_lower_bids),
+        HandleMsg::ChangeSellAmount { new_amount } => try_change_sell_amount(deps, env, new_amount),
+        HandleMsg::RetrySettlement {} => try_retry_settlement(deps, env),
+        HandleMsg::RespondToWinner { accept } => try_respond_to_winner(deps, env, accept),
+        HandleMsg::SetDescription { description, lang } => {
+            try_set_description(deps, env, description, lang)
+        }
+        HandleMsg::PostMessage { text } => try_post_message(deps, env, text),
+        HandleMsg::RegisterNotification { key } => try_register_notification(deps, env, key),
+        HandleMsg::TransferSellerRights { new_seller } => {
+            try_transfer_seller(deps, env, new_seller)
+        }
+        HandleMsg::AddOperators { operators } => try_set_operators(deps, env, &operators, true),
+        HandleMsg::RemoveOperators { operators } => try_set_operators(deps, env, &operators, false),
+        HandleMsg::Halt { halted } => try_halt(deps, env, halted),
+        HandleMsg::ForceFinalize {} => try_force_finalize(deps, env),
+        HandleMsg::Deprecate { notice } => try_deprecate(deps, env, notice),
+        HandleMsg::Migrate {
+            factory,
+            index,
+            callback_key,
+        } => try_migrate(deps, env, factory, index, callback_key),
+        HandleMsg::MatchSwap {
+            recipient,
+            received,
+        } => try_match_swap(deps, env, recipient, received),
+        HandleMsg::ShareKeyHash {
+            address,
+            key_hash,
+            epoch,
+        } => try_share_key_hash(deps, env, &address, key_hash, epoch),
     };
-    pad_handle_result(response, BLOCK_SIZE)
+    pad_handle_result(response, padding_block_size(&deps.storage))
+}
+
+/// Returns HandleResult
+///
+/// allows seller to change the default description or one of its translations
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `description` - new description.  An empty description removes it
+/// * `lang` - optional language code of the translation to change
+fn try_set_description<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    description: String,
+    lang: Option<String>,
+) -> HandleResult {
+    let mut state: State = load(&deps.storage, CONFIG_KEY)?;
+    if !is_seller_or_operator(&deps.api, &state, &env.message.sender)? {
+        return Err(StdError::generic_err(
+            "Only the auction seller can change the description",
+        ));
+    }
+    if state.is_completed {
+        return Err(StdError::generic_err(
+            "Can not change the description of an auction that has ended",
+        ));
+    }
+    if let Some(code) = lang {
+        check_lang(&code)?;
+        if description.is_empty() {
+            state.descriptions.remove(&code);
+        } else {
+            state.descriptions.insert(code, description);
+        }
+    } else {
+        state.description = if description.is_empty() {
+            None
+        } else {
+            Some(description)
+        };
+    }
+    save(&mut deps.storage, CONFIG_KEY, &state)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: Some(to_binary(&HandleAnswer::SetDescription {
+            status: Success,
+            languages: state.descriptions.keys().cloned().collect(),
+        })?),
+    })
+}
+
+/// Returns StdResult<()>
+///
+/// checks that a language code is not empty and not longer than MAX_LANG_LEN
+///
+/// # Arguments
+///
+/// * `lang` - language code to check
+fn check_lang(lang: &str) -> StdResult<()> {
+    if lang.is_empty() || lang.len() > MAX_LANG_LEN {
+        return Err(StdError::generic_err(format!(
+            "A language code must be 1 to {} bytes long",
+            MAX_LANG_LEN
+        )));
+    }
+    Ok(())
 }
 
 /// Returns HandleResult
@@ -243,14 +696,16 @@ pub fn handle<S: Storage, A: Api, Q: Querier>(
 /// * `deps` - mutable reference to Extern containing all the contract's external dependencies
 /// * `env` - Env of contract's environment
 /// * `minimum_bid` - new minimum bid
+/// * `refund_lower_bids` - true if bids below the new minimum bid should be returned
 fn try_change_min_bid<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
     env: Env,
     minimum_bid: Uint128,
+    refund_lower_bids: bool,
 ) -> HandleResult {
     let mut state: State = load(&deps.storage, CONFIG_KEY)?;
-    // only allow the seller to change the minimum bid
-    if env.message.sender != state.seller {
+    // only allow the seller or its operators to change the minimum bid
+    if !is_seller_or_operator(&deps.api, &state, &env.message.sender)? {
         return Err(StdError::generic_err(
             "Only the auction seller can change the minimum bid",
         ));
@@ -261,2636 +716,11913 @@ fn try_change_min_bid<S: Storage, A: Api, Q: Querier>(
             "Can not change the minimum bid of an auction that has ended",
         ));
     }
-    // save the min bid change
+    // the seller must accept or reject the winning bid instead of refunding it
+    if state.accept_by.is_some() {
+        return Err(StdError::generic_err(
+            "Can not change the minimum bid while the winning bid awaits the seller's acceptance",
+        ));
+    }
+    // save the min bid change, and restart any decline from the new minimum bid
     state.minimum_bid = minimum_bid.u128();
+    if let Some(decay) = state.min_bid_decay.as_mut() {
+        decay.start = env.block.time;
+    }
+    let mut messages = Vec::new();
+    let mut bids_refunded = None;
+    // return any bids below the new minimum if requested
+    if refund_lower_bids {
+        let mut refunded = 0u32;
+        let bidders = list_bidders(&deps.storage)?;
+        for bidder_raw in bidders.iter() {
+            let may_bid: Option<Bid> = may_load(&deps.storage, bidder_raw)?;
+            if let Some(bid) = may_bid.filter(|b| b.amount < state.minimum_bid) {
+                remove(&mut deps.storage, bidder_raw);
+                remove_bidder(&mut deps.storage, &mut state, bidder_raw)?;
+                forget_key_hash(&mut deps.storage, bidder_raw)?;
+                let returned = escrowed(&state, &bid);
+                save_outcome(
+                    &mut deps.storage,
+                    &env,
+                    bidder_raw,
+                    BidResult::Refunded,
+                    &bid,
+                    returned,
+                    None,
+                )?;
+                let bidder = deps
+                    .api
+                    .human_address(&CanonicalAddr(Binary(bidder_raw.clone())))?;
+                if returned > 0 {
+                    messages.push(state.bid_contract.transfer_msg(
+                        &state.auction_addr,
+                        bidder.clone(),
+                        Uint128(returned),
+                    )?);
+                }
+                // let factory know the bid was removed
+                let rem_bid_msg = FactoryHandleMsg::RemoveBidder {
+                    index: state.index,
+                    bidder,
+                    callback_key: state.callback_key.clone(),
+                };
+                messages.push(rem_bid_msg.to_cosmos_msg(
+                    state.factory.code_hash.clone(),
+                    state.factory.address.clone(),
+                    None,
+                )?);
+                refunded += 1;
+            }
+        }
+        bids_refunded = Some(refunded);
+    }
     save(&mut deps.storage, CONFIG_KEY, &state)?;
     // register change with factory
     let change_min_msg = FactoryHandleMsg::ChangeAuctionInfo {
         index: state.index,
         ends_at: None,
         minimum_bid: Some(minimum_bid),
+        sell_amount: None,
+        callback_key: state.callback_key.clone(),
     };
     // perform factory callback
-    let cosmos_msg =
-        change_min_msg.to_cosmos_msg(state.factory.code_hash, state.factory.address, None)?;
+    messages.push(change_min_msg.to_cosmos_msg(
+        state.factory.code_hash,
+        state.factory.address,
+        None,
+    )?);
 
     Ok(HandleResponse {
-        messages: vec![cosmos_msg],
+        messages,
         log: vec![],
         data: Some(to_binary(&HandleAnswer::ChangeMinimumBid {
             status: Success,
             minimum_bid,
             bid_decimals: state.bid_decimals,
+            bids_refunded,
         })?),
     })
 }
 
 /// Returns HandleResult
 ///
-/// process the Receive message sent after either bid or sell token contract sent tokens to
-/// auction escrow
+/// allows seller to change the sell amount before anything has been consigned
 ///
 /// # Arguments
 ///
 /// * `deps` - mutable reference to Extern containing all the contract's external dependencies
 /// * `env` - Env of contract's environment
-/// * `from` - address of owner of tokens sent to escrow
-/// * `amount` - Uint128 amount sent to escrow
-fn try_receive<S: Storage, A: Api, Q: Querier>(
+/// * `new_amount` - new sell amount
+fn try_change_sell_amount<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
     env: Env,
-    from: HumanAddr,
-    amount: Uint128,
+    new_amount: Uint128,
 ) -> HandleResult {
     let mut state: State = load(&deps.storage, CONFIG_KEY)?;
-
-    if env.message.sender == state.sell_contract.address {
-        try_consign(deps, from, amount, &mut state)
-    } else if env.message.sender == state.bid_contract.address {
-        try_bid(deps, env, from, amount, &mut state)
-    } else {
-        let message = format!(
-            "Address: {} is not a token in this auction",
-            env.message.sender
-        );
-        Err(StdError::generic_err(message))
+    if env.message.sender != state.seller {
+        return Err(StdError::generic_err(
+            "Only the auction seller can change the sell amount",
+        ));
+    }
+    if state.is_completed {
+        return Err(StdError::generic_err(
+            "Can not change the sell amount of an auction that has ended",
+        ));
+    }
+    if state.currently_consigned > 0 || state.bundle.iter().any(|i| i.currently_consigned > 0) {
+        return Err(StdError::generic_err(
+            "The sell amount can only be changed before any tokens have been consigned",
+        ));
+    }
+    if state.has_bids() {
+        return Err(StdError::generic_err(
+            "The sell amount can not be changed once bids have been placed",
+        ));
     }
+    if new_amount == Uint128(0) {
+        return Err(StdError::generic_err("Sell amount must be greater than 0"));
+    }
+    state.sell_amount = new_amount.u128();
+    save(&mut deps.storage, CONFIG_KEY, &state)?;
+    // register change with factory
+    let change_msg = FactoryHandleMsg::ChangeAuctionInfo {
+        index: state.index,
+        ends_at: None,
+        minimum_bid: None,
+        sell_amount: Some(new_amount),
+        callback_key: state.callback_key.clone(),
+    };
+    let factory_msg =
+        change_msg.to_cosmos_msg(state.factory.code_hash, state.factory.address, None)?;
+
+    Ok(HandleResponse {
+        messages: vec![factory_msg],
+        log: vec![],
+        data: Some(to_binary(&HandleAnswer::ChangeSellAmount {
+            status: Success,
+            sell_amount: new_amount,
+            sell_decimals: state.sell_decimals,
+        })?),
+    })
 }
 
 /// Returns HandleResult
 ///
-/// process the attempt to consign sale tokens to auction escrow
+/// allows seller to hand the auction over to a new seller
 ///
 /// # Arguments
 ///
 /// * `deps` - mutable reference to Extern containing all the contract's external dependencies
-/// * `owner` - address of owner of tokens sent to escrow
-/// * `amount` - Uint128 amount sent to escrow
-/// * `state` - mutable reference to the state of the auction
-fn try_consign<S: Storage, A: Api, Q: Querier>(
+/// * `env` - Env of contract's environment
+/// * `new_seller` - address of the new seller
+fn try_transfer_seller<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
-    owner: HumanAddr,
-    amount: Uint128,
-    state: &mut State,
+    env: Env,
+    new_seller: HumanAddr,
 ) -> HandleResult {
-    // if not the auction owner, send the tokens back
-    if owner != state.seller {
+    let mut state: State = load(&deps.storage, CONFIG_KEY)?;
+    // only allow the seller to transfer the seller rights
+    if env.message.sender != state.seller {
         return Err(StdError::generic_err(
-            "Only auction creator can consign tokens for sale.  Your tokens have been returned",
+            "Only the auction seller can transfer the seller rights",
         ));
     }
-    // if auction is over, send the tokens back
     if state.is_completed {
         return Err(StdError::generic_err(
-            "Auction has ended. Your tokens have been returned",
+            "Can not transfer the seller rights of an auction that has ended",
         ));
     }
-    // if tokens to be sold have already been consigned, return these tokens
     if state.tokens_consigned {
         return Err(StdError::generic_err(
-            "Tokens to be sold have already been consigned. Your tokens have been returned",
+            "Seller rights can only be transferred before the tokens for sale have been fully \
+             consigned",
         ));
     }
-
-    let consign_total = state.currently_consigned + amount.u128();
-    let mut log_msg = String::new();
-    let mut cos_msg = Vec::new();
-    let status: ResponseStatus;
-    let mut excess: Option<Uint128> = None;
-    let mut needed: Option<Uint128> = None;
-    // if consignment amount < auction sell amount, ask for remaining balance
-    if consign_total < state.sell_amount {
-        state.currently_consigned = consign_total;
-        needed = Some(Uint128(state.sell_amount - consign_total));
-        status = Failure;
-        log_msg.push_str(
-            "You have not consigned the full amount to be sold.  You need to consign additional \
-             tokens",
-        );
-    // all tokens to be sold have been consigned
-    } else {
-        state.tokens_consigned = true;
-        state.currently_consigned = state.sell_amount;
-        status = Success;
-        log_msg.push_str("Tokens to be sold have been consigned to the auction");
-        // if consigned more than needed, return excess tokens
-        if consign_total > state.sell_amount {
-            excess = Some(Uint128(consign_total - state.sell_amount));
-            cos_msg.push(state.sell_contract.transfer_msg(owner, excess.unwrap())?);
-            log_msg.push_str(".  Excess tokens have been returned");
-        }
+    if new_seller == state.seller {
+        return Err(StdError::generic_err(format!(
+            "{} is already the seller of this auction",
+            new_seller
+        )));
     }
-
+    // the new seller can not already be bidding if the seller is not allowed to bid
+    let new_raw = deps.api.canonical_address(&new_seller)?;
+    if state.no_self_bid && is_bidder(&deps.storage, new_raw.as_slice())? {
+        return Err(StdError::generic_err(format!(
+            "{} has an active bid, and the seller is not allowed to bid in this auction",
+            new_seller
+        )));
+    }
+    let old_seller = state.seller;
+    state.seller = new_seller.clone();
+    // operators were authorized by the previous seller
+    state.operators.clear();
     save(&mut deps.storage, CONFIG_KEY, &state)?;
-
-    let resp = serde_json::to_string(&HandleAnswer::Consign {
-        status,
-        message: log_msg,
-        amount_consigned: Uint128(state.currently_consigned),
-        amount_needed: needed,
-        amount_returned: excess,
-        sell_decimals: state.sell_decimals,
-    })
-    .unwrap();
+    let old_raw = deps.api.canonical_address(&old_seller)?;
+    forget_key_hash(&mut deps.storage, old_raw.as_slice())?;
+    // register change with factory
+    let change_msg = FactoryHandleMsg::ChangeSeller {
+        index: state.index,
+        seller: old_seller,
+        new_seller: new_seller.clone(),
+        callback_key: state.callback_key.clone(),
+    };
+    // perform factory callback
+    let cosmos_msg =
+        change_msg.to_cosmos_msg(state.factory.code_hash, state.factory.address, None)?;
 
     Ok(HandleResponse {
-        messages: cos_msg,
-        log: vec![log("response", resp)],
-        data: None,
+        messages: vec![cosmos_msg],
+        log: vec![],
+        data: Some(to_binary(&HandleAnswer::TransferSellerRights {
+            status: Success,
+            seller: new_seller,
+        })?),
     })
 }
 
 /// Returns HandleResult
 ///
-/// process the bid attempt
+/// allows seller to authorize or revoke operators that may act on the seller's behalf
 ///
 /// # Arguments
 ///
 /// * `deps` - mutable reference to Extern containing all the contract's external dependencies
 /// * `env` - Env of contract's environment
-/// * `bidder` - address of owner of tokens sent to escrow
-/// * `amount` - Uint128 amount sent to escrow
-/// * `state` - mutable reference to auction state
-fn try_bid<S: Storage, A: Api, Q: Querier>(
+/// * `operators` - list of operator addresses
+/// * `add` - true if the operators are being authorized, false if they are being revoked
+fn try_set_operators<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
     env: Env,
-    bidder: HumanAddr,
-    amount: Uint128,
-    state: &mut State,
+    operators: &[HumanAddr],
+    add: bool,
 ) -> HandleResult {
-    // if auction is over, send the tokens back
-    if state.is_completed {
+    let mut state: State = load(&deps.storage, CONFIG_KEY)?;
+    if env.message.sender != state.seller {
         return Err(StdError::generic_err(
-            "Auction has ended. Bid tokens have been returned",
+            "Only the auction seller can change the operators",
         ));
     }
-    // don't accept a 0 bid
-    if amount == Uint128(0) {
-        return Err(StdError::generic_err("Bid must be greater than 0"));
+    for operator in operators.iter() {
+        let raw = deps.api.canonical_address(operator)?.as_slice().to_vec();
+        if add {
+            state.operators.insert(raw);
+        } else {
+            state.operators.remove(&raw);
+        }
     }
-    // if bid is less than the minimum accepted bid, send the tokens back
-    if amount.u128() < state.minimum_bid {
-        let message =
-            String::from("Bid was less than minimum allowed.  Bid tokens have been returned");
-
-        let resp = serde_json::to_string(&HandleAnswer::Bid {
-            status: Failure,
-            message,
-            previous_bid: None,
-            minimum_bid: Some(Uint128(state.minimum_bid)),
-            amount_bid: None,
-            amount_returned: Some(amount),
-            bid_decimals: state.bid_decimals,
-        })
-        .unwrap();
+    save(&mut deps.storage, CONFIG_KEY, &state)?;
 
-        return Ok(HandleResponse {
-            messages: vec![state.bid_contract.transfer_msg(bidder, amount)?],
-            log: vec![log("response", resp)],
-            data: None,
-        });
-    }
-    let mut return_amount: Option<Uint128> = None;
-    let bidder_raw = &deps.api.canonical_address(&bidder)?;
-    let mut cosmos_msg = Vec::new();
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: Some(to_binary(&HandleAnswer::Operators {
+            status: Success,
+            operators: human_operators(&deps.api, &state)?,
+        })?),
+    })
+}
 
-    // if there is an active bid from this address
-    if state.bidders.contains(&bidder_raw.as_slice().to_vec()) {
-        let bid: Option<Bid> = may_load(&deps.storage, bidder_raw.as_slice())?;
-        if let Some(old_bid) = bid {
-            // if new bid is == the old bid, keep old bid and return this one
-            if amount.u128() == old_bid.amount {
-                let message = String::from(
-                    "New bid is the same as previous bid.  Retaining previous timestamp",
-                );
+/// Returns StdResult<bool>
+///
+/// returns true if the address is the seller or one of the seller's operators
+///
+/// # Arguments
+///
+/// * `api` - reference to the Api used to convert human and canonical addresses
+/// * `state` - a reference to the auction state
+/// * `address` - a reference to the address to check
+pub fn is_seller_or_operator<A: Api>(
+    api: &A,
+    state: &State,
+    address: &HumanAddr,
+) -> StdResult<bool> {
+    if *address == state.seller {
+        return Ok(true);
+    }
+    if state.operators.is_empty() {
+        return Ok(false);
+    }
+    let raw = api.canonical_address(address)?;
+    Ok(state.operators.contains(&raw.as_slice().to_vec()))
+}
 
-                let resp = serde_json::to_string(&HandleAnswer::Bid {
-                    status: Failure,
-                    message,
-                    previous_bid: Some(amount),
-                    minimum_bid: None,
-                    amount_bid: Some(amount),
-                    amount_returned: Some(amount),
-                    bid_decimals: state.bid_decimals,
-                })
-                .unwrap();
+/// Returns StdResult<Vec<HumanAddr>>
+///
+/// lists the seller's operators sorted by address
+///
+/// # Arguments
+///
+/// * `api` - reference to the Api used to convert human and canonical addresses
+/// * `state` - a reference to the auction state
+fn human_operators<A: Api>(api: &A, state: &State) -> StdResult<Vec<HumanAddr>> {
+    let mut operators = state
+        .operators
+        .iter()
+        .map(|o| api.human_address(&CanonicalAddr(Binary(o.clone()))))
+        .collect::<StdResult<Vec<HumanAddr>>>()?;
+    operators.sort_by(|a, b| a.0.cmp(&b.0));
+    Ok(operators)
+}
 
-                return Ok(HandleResponse {
-                    messages: vec![state.bid_contract.transfer_msg(bidder, amount)?],
-                    log: vec![log("response", resp)],
-                    data: None,
-                });
-            // new bid is different, save the new bid, and return the old one, so mark for return
-            } else {
-                return_amount = Some(Uint128(old_bid.amount));
-            }
-        }
-    // address did not have an active bid
-    } else {
-        // insert in list of bidders and save
-        state.bidders.insert(bidder_raw.as_slice().to_vec());
-        save(&mut deps.storage, CONFIG_KEY, &state)?;
-        // register new bidder with the factory
-        let reg_bid_msg = FactoryHandleMsg::RegisterBidder {
-            index: state.index,
-            bidder: bidder.clone(),
-        };
-        // perform register bidder callback
-        cosmos_msg.push(reg_bid_msg.to_cosmos_msg(
-            state.factory.code_hash.clone(),
-            state.factory.address.clone(),
-            None,
-        )?);
+/// Returns HandleResult
+///
+/// allows the factory to halt or resume the auction
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `halted` - true if the auction should be halted
+fn try_halt<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    halted: bool,
+) -> HandleResult {
+    let mut state: State = load(&deps.storage, CONFIG_KEY)?;
+    factory_only(&env, &state, "halt this auction")?;
+    if state.is_completed {
+        return Err(StdError::generic_err("The auction has already closed"));
     }
-    let new_bid = Bid {
-        amount: amount.u128(),
-        timestamp: env.block.time,
+    state.is_halted = halted;
+    save(&mut deps.storage, CONFIG_KEY, &state)?;
+    let message = if halted {
+        "The auction has been halted"
+    } else {
+        "The auction has resumed"
     };
-    save(&mut deps.storage, bidder_raw.as_slice(), &new_bid)?;
 
-    let mut message = String::from("Bid accepted");
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: Some(to_binary(&HandleAnswer::FactoryAction {
+            status: Success,
+            message: message.to_string(),
+        })?),
+    })
+}
 
-    // if need to return the old bid
-    if let Some(returned) = return_amount {
-        cosmos_msg.push(state.bid_contract.transfer_msg(bidder, returned)?);
-        message.push_str(". Previously bid tokens have been returned");
+/// Returns HandleResult
+///
+/// allows the factory to close an auction that is still open long after its closing time
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+fn try_force_finalize<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+) -> HandleResult {
+    let mut state: State = load(&deps.storage, CONFIG_KEY)?;
+    factory_only(&env, &state, "force this auction to close")?;
+    if state.is_completed {
+        return Err(StdError::generic_err("The auction has already closed"));
     }
-    let resp = serde_json::to_string(&HandleAnswer::Bid {
-        status: Success,
-        message,
-        previous_bid: None,
-        minimum_bid: None,
-        amount_bid: Some(amount),
-        amount_returned: return_amount,
-        bid_decimals: state.bid_decimals,
-    })
-    .unwrap();
+    let allowed_at = state.ends_at.saturating_add(FORCE_FINALIZE_GRACE);
+    if env.block.time < allowed_at {
+        return Err(StdError::generic_err(format!(
+            "The auction can not be forced to close until {}",
+            allowed_at
+        )));
+    }
+    // a halted auction must still be able to close
+    state.is_halted = false;
+    close_auction(deps, env, &mut state)
+}
+
+/// Returns HandleResult
+///
+/// allows the factory to warn that the auction was created with an outdated contract version.
+/// The factory broadcasts the notice to its old active auctions a page at a time, so an auction
+/// that has already closed does not fail the broadcast
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `notice` - deprecation warning to display
+fn try_deprecate<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    notice: String,
+) -> HandleResult {
+    let mut state: State = load(&deps.storage, CONFIG_KEY)?;
+    factory_only(&env, &state, "deprecate this auction")?;
+    state.deprecation_notice = Some(notice);
+    save(&mut deps.storage, CONFIG_KEY, &state)?;
 
     Ok(HandleResponse {
-        messages: cosmos_msg,
-        log: vec![log("response", resp)],
-        data: None,
+        messages: vec![],
+        log: vec![],
+        data: Some(to_binary(&HandleAnswer::FactoryAction {
+            status: Success,
+            message: "The auction has been deprecated".to_string(),
+        })?),
     })
 }
 
 /// Returns HandleResult
 ///
-/// attempt to retract current bid
+/// allows the factory to link the auction to a new factory
 ///
 /// # Arguments
 ///
 /// * `deps` - mutable reference to Extern containing all the contract's external dependencies
-/// * `bidder` - address of bidder
-fn try_retract<S: Storage, A: Api, Q: Querier>(
+/// * `env` - Env of contract's environment
+/// * `factory` - code hash and address of the new factory
+/// * `index` - index of the auction with the new factory
+/// * `callback_key` - key the new factory gave the auction to authenticate its callbacks
+fn try_migrate<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
-    bidder: HumanAddr,
+    env: Env,
+    factory: FactoryInfo,
+    index: u32,
+    callback_key: String,
 ) -> HandleResult {
     let mut state: State = load(&deps.storage, CONFIG_KEY)?;
+    factory_only(&env, &state, "migrate this auction")?;
+    if state.is_completed {
+        return Err(StdError::generic_err("The auction has already closed"));
+    }
+    let message = format!("The auction is now linked to factory {}", factory.address);
+    state.factory = ContractInfo {
+        code_hash: factory.code_hash,
+        address: factory.address,
+        token_id: None,
+    };
+    state.index = index;
+    state.callback_key = callback_key;
+    save(&mut deps.storage, CONFIG_KEY, &state)?;
+    // hashes of the old factory's viewing keys are no longer valid
+    forget_all_key_hashes(&mut deps.storage)?;
 
-    let bidder_raw = &deps.api.canonical_address(&bidder)?;
-    let mut cos_msg = Vec::new();
-    let sent: Option<Uint128>;
-    let mut log_msg = String::new();
-    let status: ResponseStatus;
-    let bid_decimals = state.bid_decimals;
-    // if there was a active bid from this address, remove the bid and return tokens
-    if state.bidders.contains(&bidder_raw.as_slice().to_vec()) {
-        let bid: Option<Bid> = may_load(&deps.storage, bidder_raw.as_slice())?;
-        if let Some(old_bid) = bid {
-            remove(&mut deps.storage, bidder_raw.as_slice());
-            state.bidders.remove(&bidder_raw.as_slice().to_vec());
-            save(&mut deps.storage, CONFIG_KEY, &state)?;
-            cos_msg.push(
-                state
-                    .bid_contract
-                    .transfer_msg(bidder.clone(), Uint128(old_bid.amount))?,
-            );
-            status = Success;
-            sent = Some(Uint128(old_bid.amount));
-            log_msg.push_str("Bid retracted.  Tokens have been returned");
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: Some(to_binary(&HandleAnswer::FactoryAction {
+            status: Success,
+            message,
+        })?),
+    })
+}
 
-            // let factory know bid was retracted
-            let rem_bid_msg = FactoryHandleMsg::RemoveBidder {
-                index: state.index,
-                bidder,
-            };
-            // perform callback
-            cos_msg.push(rem_bid_msg.to_cosmos_msg(
-                state.factory.code_hash,
-                state.factory.address,
-                None,
-            )?);
-        } else {
-            status = Failure;
-            sent = None;
-            log_msg.push_str(&format!("No active bid for address: {}", bidder));
-        }
-    // no active bid found
+/// Returns HandleResult
+///
+/// allows the factory to share a hash derived from an address' viewing key.  The hash is cached
+/// as valid in the given key epoch, and a newer epoch invalidates every hash cached for the
+/// address in an earlier one
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `address` - a reference to the address whose viewing key the hash was derived from
+/// * `key_hash` - hash derived from the address' viewing key
+/// * `epoch` - the address' factory key epoch the hash was derived in
+fn try_share_key_hash<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    address: &HumanAddr,
+    key_hash: Binary,
+    epoch: u32,
+) -> HandleResult {
+    let state: State = load(&deps.storage, CONFIG_KEY)?;
+    factory_only(&env, &state, "share viewing key hashes")?;
+    let address_raw = deps.api.canonical_address(address)?;
+    let epoch_key = key_epoch_key(&deps.storage, address_raw.as_slice())?;
+    let mut epoch_store = PrefixedStorage::new(PREFIX_KEY_EPOCHS, &mut deps.storage);
+    let latest: Option<u32> = may_load(&epoch_store, &epoch_key)?;
+    // a hash from an older epoch is stale
+    let message = if latest.map_or(false, |l| epoch < l) {
+        "The viewing key hash is older than the latest one shared".to_string()
     } else {
-        status = Failure;
-        sent = None;
-        log_msg.push_str(&format!("No active bid for address: {}", bidder));
-    }
+        save(&mut epoch_store, &epoch_key, &epoch)?;
+        let mut cache = PrefixedStorage::new(PREFIX_KEY_CACHE, &mut deps.storage);
+        save(
+            &mut cache,
+            &[&epoch_key[..], &key_hash.0[..]].concat(),
+            &epoch,
+        )?;
+        "The viewing key hash has been saved".to_string()
+    };
+
     Ok(HandleResponse {
-        messages: cos_msg,
+        messages: vec![],
         log: vec![],
-        data: Some(to_binary(&HandleAnswer::RetractBid {
-            status,
-            message: log_msg,
-            amount_returned: sent,
-            bid_decimals: sent.map(|_a| bid_decimals),
+        data: Some(to_binary(&HandleAnswer::FactoryAction {
+            status: Success,
+            message,
         })?),
     })
 }
 
 /// Returns HandleResult
 ///
-/// closes the auction and sends all the tokens in escrow to where they belong
+/// process the Receive message sent after either bid or sell token contract sent tokens to
+/// auction escrow
 ///
 /// # Arguments
 ///
 /// * `deps` - mutable reference to Extern containing all the contract's external dependencies
 /// * `env` - Env of contract's environment
-/// * `new_ends_at` - optional epoch timestamp to extend closing time to if there are no bids
-/// * `new_minimum_bid` - optional minimum bid update if there are no bids
-/// * `return_all` - true if being called from the return_all fallback plan
-fn try_finalize<S: Storage, A: Api, Q: Querier>(
+/// * `from` - address of owner of tokens sent to escrow
+/// * `amount` - Uint128 amount sent to escrow
+/// * `token_id` - optional id of the tokens if they were sent by a SNIP-1155 contract
+/// * `msg` - optional base64 encoded ReceiveMsg sent with the tokens
+fn try_receive<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
     env: Env,
-    new_ends_at: Option<u64>,
-    new_minimum_bid: Option<Uint128>,
-    return_all: bool,
+    from: HumanAddr,
+    amount: Uint128,
+    token_id: Option<&str>,
+    msg: Option<Binary>,
 ) -> HandleResult {
     let mut state: State = load(&deps.storage, CONFIG_KEY)?;
-
-    // can only do a return_all if the auction is closed
-    if return_all && !state.is_completed {
-        return Err(StdError::generic_err(
-            "return_all can only be executed after the auction has ended",
-        ));
-    }
-    let is_seller = env.message.sender == state.seller;
-    let update_ends_at = new_ends_at.is_some();
-    let update_min_bid = new_minimum_bid.is_some();
-    // can not change minimum bid or closing time if not the owner
-    if !is_seller && (update_ends_at || update_min_bid) {
-        return Err(StdError::generic_err(
-            "Only the auction seller can change the closing time or the minimum bid",
-        ));
-    }
-    // if not the auction owner, can't finalize before the closing time, but you can return_all
-    if !return_all && !is_seller && (env.block.time < state.ends_at) {
-        return Err(StdError::generic_err(
-            "Only auction creator can finalize the sale before the closing time",
-        ));
-    }
-    let no_bids = state.bidders.is_empty();
-    // if there are no active bids, and closer wants to extend the auction
-    if no_bids && !state.is_completed && (update_ends_at || update_min_bid) {
-        if let Some(ends_at) = new_ends_at {
-            state.ends_at = ends_at;
+    // read the action requested with the tokens, if any
+    let action = match msg {
+        Some(bin) => Some(from_binary::<ReceiveMsg>(&bin).map_err(|_| {
+            StdError::generic_err(
+                "Unable to parse the msg sent with the tokens.  Your tokens have been returned",
+            )
+        })?),
+        None => None,
+    };
+    let item = state
+        .bundle
+        .iter()
+        .position(|i| i.contract.is_token(&env.message.sender, token_id));
+
+    if state.sell_contract.is_token(&env.message.sender, token_id) || item.is_some() {
+        if state.is_halted {
+            return match action {
+                Some(ReceiveMsg::IncreaseLot {}) if item.is_none() => {
+                    refuse_increase(&state, from, amount, HALTED_MSG)
+                }
+                _ => refuse_consign(&state, from, amount, item, HALTED_MSG),
+            };
         }
-        if let Some(minimum_bid) = new_minimum_bid {
-            state.minimum_bid = minimum_bid.u128();
+        match action {
+            None | Some(ReceiveMsg::Consign {}) => {
+                try_consign(deps, env, from, amount, &mut state, item)
+            }
+            Some(ReceiveMsg::IncreaseLot {}) if item.is_none() => {
+                try_increase_lot(deps, from, amount, &mut state)
+            }
+            // bundled tokens go back through their own contract with the consignment answer
+            Some(ReceiveMsg::IncreaseLot {}) => refuse_consign(
+                &state,
+                from,
+                amount,
+                item,
+                "Only the sell token can be used to increase the lot.  Your tokens have been \
+                 returned",
+            ),
+            Some(_) => refuse_consign(
+                &state,
+                from,
+                amount,
+                item,
+                "Only bid tokens can be used to bid or buy now.  Your tokens have been returned",
+            ),
         }
-        save(&mut deps.storage, CONFIG_KEY, &state)?;
-        // register change with factory
-        let change_min_msg = FactoryHandleMsg::ChangeAuctionInfo {
-            index: state.index,
-            ends_at: new_ends_at,
-            minimum_bid: new_minimum_bid,
-        };
-        // perform factory callback
-        let factory_msg =
-            change_min_msg.to_cosmos_msg(state.factory.code_hash, state.factory.address, None)?;
-        let time_str = if update_ends_at { " closing time" } else { "" };
-        let bid_str = if update_min_bid { " minimum bid" } else { "" };
-        let and_str = if update_ends_at && update_min_bid {
-            " and"
-        } else {
-            ""
-        };
-        let message = format!(
-            "There were no active bids.  The{}{}{} has been updated",
-            time_str, and_str, bid_str
-        );
-        return Ok(HandleResponse {
-            messages: vec![factory_msg],
-            log: vec![],
-            data: Some(to_binary(&HandleAnswer::CloseAuction {
-                status: Failure,
-                message,
-                winning_bid: None,
-                bid_decimals: None,
-                sell_tokens_received: None,
-                sell_decimals: None,
-                bid_tokens_received: None,
-            })?),
-        });
-    }
-    let mut cos_msg = Vec::new();
-    let mut update_state = false;
-    let mut winning_amount: Option<Uint128> = None;
-    let mut bid_decimals: Option<u8> = None;
-    let mut winner: Option<HumanAddr> = None;
-    let mut sell_tokens_received: Option<Uint128> = None;
-    let mut sell_decimals: Option<u8> = None;
-    let mut bid_tokens_received: Option<Uint128> = None;
-    let mut is_winner = false;
-    let mut is_loser = false;
-
-    // if there were bids
-    if !no_bids {
-        // load all the bids
-        struct OwnedBid {
-            pub bidder: CanonicalAddr,
-            pub bid: Bid,
+    } else if state.bid_contract.is_token(&env.message.sender, token_id) {
+        if state.is_halted {
+            return match action {
+                Some(ReceiveMsg::Consign {}) => refuse_deposit(&state, from, amount, HALTED_MSG),
+                Some(ReceiveMsg::CommitBid { .. }) => {
+                    refuse_commit(&state, from, amount, HALTED_MSG)
+                }
+                _ => refuse_bid(&state, from, amount, HALTED_MSG),
+            };
         }
-        let mut bid_list: Vec<OwnedBid> = Vec::new();
-        for bidder in &state.bidders {
-            let bid: Option<Bid> = may_load(&deps.storage, bidder.as_slice())?;
-            if let Some(found_bid) = bid {
-                bid_list.push(OwnedBid {
-                    bidder: CanonicalAddr::from(bidder.as_slice()),
-                    bid: found_bid,
-                });
-            }
+        let is_seller = from == state.seller;
+        // only the seller's deposit is sent to an auction that takes bids backed by allowances
+        let is_deposit = matches!(action, Some(ReceiveMsg::Consign {}));
+        if state.allowance_key.is_some() && !is_deposit {
+            let message = "This auction takes bids backed by allowances.  Use \
+                           place_allowance_bid instead of sending tokens.  Your tokens have been \
+                           returned";
+            return match action {
+                Some(ReceiveMsg::CommitBid { .. }) => refuse_commit(&state, from, amount, message),
+                _ => refuse_bid(&state, from, amount, message),
+            };
         }
-        // closing an auction that has been fully consigned
-        if state.tokens_consigned && !state.is_completed {
-            bid_list.sort_by(|a, b| {
-                a.bid
-                    .amount
-                    .cmp(&b.bid.amount)
-                    .then(b.bid.timestamp.cmp(&a.bid.timestamp))
-            });
-            // if there was a winner, swap the tokens
-            if let Some(winning_bid) = bid_list.pop() {
-                cos_msg.push(
-                    state
-                        .bid_contract
-                        .transfer_msg(state.seller.clone(), Uint128(winning_bid.bid.amount))?,
-                );
-                let human_winner = deps.api.human_address(&winning_bid.bidder)?;
-                cos_msg.push(
-                    state
-                        .sell_contract
-                        .transfer_msg(human_winner.clone(), Uint128(state.sell_amount))?,
-                );
-                winning_amount = Some(Uint128(winning_bid.bid.amount));
-                if is_seller {
-                    bid_tokens_received = winning_amount;
+        // an auction with a reveal window only takes bids as sealed commitments
+        if state.reveal_window.is_some() && !is_deposit {
+            return match action {
+                Some(ReceiveMsg::CommitBid { commitment }) => {
+                    try_commit_bid(deps, env, from, amount, commitment, &mut state)
                 }
-                if human_winner == env.message.sender {
-                    is_winner = true;
-                    sell_tokens_received = Some(Uint128(state.sell_amount));
-                    sell_decimals = Some(state.sell_decimals);
+                _ => refuse_commit(
+                    &state,
+                    from,
+                    amount,
+                    "This auction takes sealed bids.  Send a commit_bid msg with your deposit, \
+                     and reveal the bid after the closing time.  Your tokens have been returned",
+                ),
+            };
+        }
+        match action {
+            // bid tokens from the seller could be a deposit or a bid, so the intent must be given
+            None if is_seller => refuse_bid(
+                &state,
+                from,
+                amount,
+                "The seller must send bid tokens with either a consign msg to post the deposit, \
+                 or a place_bid msg to bid.  Your tokens have been returned",
+            ),
+            None => try_bid(deps, env, from, amount, None, None, None, None, &mut state),
+            Some(ReceiveMsg::Consign {}) => {
+                if !is_seller {
+                    refuse_deposit(
+                        &state,
+                        from,
+                        amount,
+                        "Bid tokens can only be consigned by the seller as a deposit.  Your \
+                         tokens have been returned",
+                    )
+                } else if state.is_completed || state.deposit_posted >= state.deposit {
+                    refuse_deposit(
+                        &state,
+                        from,
+                        amount,
+                        "This auction is not expecting a deposit.  Your tokens have been returned",
+                    )
+                } else {
+                    try_deposit(deps, from, amount, &mut state)
                 }
-                state.currently_consigned = 0;
-                update_state = true;
-                winner = Some(human_winner);
-                state.winning_bid = winning_bid.bid.amount;
-                remove(&mut deps.storage, &winning_bid.bidder.as_slice());
-                state
-                    .bidders
-                    .remove(&winning_bid.bidder.as_slice().to_vec());
             }
-        }
-        // loops through all remaining bids to return them to the bidders
-        for losing_bid in &bid_list {
-            let human_loser = deps.api.human_address(&losing_bid.bidder)?;
-            if human_loser == env.message.sender {
-                is_loser = true;
-                // if the seller also placed a losing bid, add them
-                bid_tokens_received = Some(
-                    bid_tokens_received.unwrap_or(Uint128(0)) + Uint128(losing_bid.bid.amount),
-                );
-                bid_decimals = Some(state.bid_decimals);
+            Some(ReceiveMsg::PlaceBid {
+                expiration,
+                max_bid,
+                unit_price,
+                memo,
+                referrer,
+            }) => {
+                // a unit price bids the total cost of the lot at that price
+                let max_bid = match unit_price {
+                    Some(price) => match unit_price_total(&state, price, amount, max_bid) {
+                        Ok(total) => Some(total),
+                        Err(StdError::GenericErr { msg, .. }) => {
+                            return refuse_bid(&state, from, amount, &msg);
+                        }
+                        Err(e) => return Err(e),
+                    },
+                    None => max_bid,
+                };
+                try_bid(
+                    deps, env, from, amount, expiration, max_bid, memo, referrer, &mut state,
+                )
             }
-            cos_msg.push(
-                state
-                    .bid_contract
-                    .transfer_msg(human_loser, Uint128(losing_bid.bid.amount))?,
-            );
-            remove(&mut deps.storage, &losing_bid.bidder.as_slice());
-            update_state = true;
-            state.bidders.remove(&losing_bid.bidder.as_slice().to_vec());
+            Some(ReceiveMsg::BuyNow {}) => try_buy_now(deps, env, from, amount, &mut state),
+            Some(ReceiveMsg::CommitBid { .. }) => refuse_commit(
+                &state,
+                from,
+                amount,
+                "This auction does not take sealed bids.  Your tokens have been returned",
+            ),
+            Some(ReceiveMsg::IncreaseLot {}) => refuse_bid(
+                &state,
+                from,
+                amount,
+                "Only the sell token can be used to increase the lot.  Your tokens have been \
+                 returned",
+            ),
         }
+    } else {
+        let message = format!(
+            "Address: {} is not a token in this auction",
+            env.message.sender
+        );
+        Err(StdError::generic_err(message))
     }
-    // return any tokens that have been consigned to the auction owner (can happen if owner
-    // finalized the auction before consigning the full sale amount or if there were no bids)
-    if state.currently_consigned > 0 {
-        cos_msg.push(
-            state
-                .sell_contract
-                .transfer_msg(state.seller.clone(), Uint128(state.currently_consigned))?,
+}
+
+/// Returns HandleResult
+///
+/// process the attempt to consign sale tokens to auction escrow
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `owner` - address of owner of tokens sent to escrow
+/// * `amount` - Uint128 amount sent to escrow
+/// * `state` - mutable reference to the state of the auction
+/// * `item` - optional position of the bundled token being consigned, None for the sell token
+fn try_consign<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    owner: HumanAddr,
+    amount: Uint128,
+    state: &mut State,
+    item: Option<usize>,
+) -> HandleResult {
+    // if not the auction owner, send the tokens back
+    if owner != state.seller {
+        return refuse_consign(
+            state,
+            owner,
+            amount,
+            item,
+            "Only auction creator can consign tokens for sale.  Your tokens have been returned",
         );
-        if is_seller {
-            sell_tokens_received = Some(Uint128(state.currently_consigned));
-            sell_decimals = Some(state.sell_decimals);
+    }
+    // if auction is over, send the tokens back
+    if state.is_completed {
+        return refuse_consign(
+            state,
+            owner,
+            amount,
+            item,
+            "Auction has ended. Your tokens have been returned",
+        );
+    }
+    // if the consignment deadline has passed, send the tokens back
+    if state.consign_by.map_or(false, |c| env.block.time > c) {
+        return refuse_consign(
+            state,
+            owner,
+            amount,
+            item,
+            "The consignment deadline has passed. Your tokens have been returned",
+        );
+    }
+    let (contract, sell_amount, currently_consigned, decimals) = match item {
+        Some(i) => {
+            let bundled = &state.bundle[i];
+            (
+                &bundled.contract,
+                bundled.amount,
+                bundled.currently_consigned,
+                bundled.decimals,
+            )
         }
-        state.currently_consigned = 0;
-        update_state = true;
+        None => (
+            &state.sell_contract,
+            state.sell_amount,
+            state.currently_consigned,
+            state.sell_decimals,
+        ),
+    };
+    // if tokens to be sold have already been consigned, return these tokens
+    if currently_consigned >= sell_amount {
+        return refuse_consign(
+            state,
+            owner,
+            amount,
+            item,
+            "Tokens to be sold have already been consigned. Your tokens have been returned",
+        );
     }
-    // mark that auction had ended
-    if !state.is_completed {
-        state.is_completed = true;
-        update_state = true;
-        // let factory know
-        let close_msg = FactoryHandleMsg::CloseAuction {
-            index: state.index,
-            seller: state.seller.clone(),
-            bidder: winner,
-            winning_bid: winning_amount,
+
+    let consign_total = checked_add(currently_consigned, amount.u128())?;
+    let mut log_msg = String::new();
+    let mut cos_msg = Vec::new();
+    let status: ResponseStatus;
+    let mut excess: Option<Uint128> = None;
+    let mut needed: Option<Uint128> = None;
+    let now_consigned: u128;
+    // if consignment amount < auction sell amount, ask for remaining balance
+    if consign_total < sell_amount {
+        now_consigned = consign_total;
+        needed = Some(Uint128(sell_amount - consign_total));
+        status = Failure;
+        log_msg.push_str(
+            "You have not consigned the full amount to be sold.  You need to consign additional \
+             tokens",
+        );
+    // all tokens to be sold have been consigned
+    } else {
+        now_consigned = sell_amount;
+        status = Success;
+        log_msg.push_str("Tokens to be sold have been consigned to the auction");
+        // if consigned more than needed, return excess tokens
+        if consign_total > sell_amount {
+            excess = Some(Uint128(consign_total - sell_amount));
+            cos_msg.push(contract.transfer_msg(&state.auction_addr, owner, excess.unwrap())?);
+            log_msg.push_str(".  Excess tokens have been returned");
         }
-        .to_cosmos_msg(
-            state.factory.code_hash.clone(),
-            state.factory.address.clone(),
-            None,
-        )?;
-        cos_msg.push(close_msg);
     }
-    if update_state {
-        save(&mut deps.storage, CONFIG_KEY, &state)?;
+    match item {
+        Some(i) => {
+            state.bundle[i].currently_consigned = now_consigned;
+            state.bundle[i].consigned = now_consigned == sell_amount;
+        }
+        None => state.currently_consigned = now_consigned,
+    }
+    // the lot is only ready once the sell tokens and every bundled token have been consigned
+    state.tokens_consigned =
+        state.currently_consigned == state.sell_amount && state.bundle.iter().all(|i| i.consigned);
+    if now_consigned == sell_amount && !state.tokens_consigned {
+        log_msg.push_str(".  Other tokens in the bundle still need to be consigned");
     }
 
-    let log_msg = if winning_amount.is_some() {
-        bid_decimals = Some(state.bid_decimals);
-        let seller_msg = if is_seller {
-            ".  You have been sent the winning bid"
-        } else {
-            ""
-        };
-        let bidder_msg = if is_winner {
-            ".  Your bid won! You have been sent the sale token(s)"
-        } else if is_loser {
-            ".  Your bid did not win and has been returned"
-        } else {
-            ""
-        };
-        format!("Sale has been finalized{}{}", seller_msg, bidder_msg)
-    } else if return_all {
-        "Outstanding funds have been returned".to_string()
-    } else {
-        let consign_msg = if no_bids && sell_tokens_received.is_some() {
-            ".  Consigned tokens have been returned because there were no active bids"
-        } else {
-            ""
-        };
-        format!("Auction has been closed{}", consign_msg)
-    };
+    save(&mut deps.storage, CONFIG_KEY, &state)?;
+
+    let resp = serde_json::to_string(&HandleAnswer::Consign {
+        status,
+        message: log_msg,
+        amount_consigned: Uint128(now_consigned),
+        amount_needed: needed,
+        amount_returned: excess,
+        sell_decimals: decimals,
+        amount_consigned_humanized: humanize(Some(Uint128(now_consigned)), decimals),
+        amount_needed_humanized: humanize(needed, decimals),
+        amount_returned_humanized: humanize(excess, decimals),
+    })
+    .unwrap();
 
     Ok(HandleResponse {
         messages: cos_msg,
-        log: vec![],
-        data: Some(to_binary(&HandleAnswer::CloseAuction {
-            status: Success,
-            message: log_msg,
-            winning_bid: winning_amount,
-            bid_decimals,
-            sell_tokens_received,
-            sell_decimals,
-            bid_tokens_received,
-        })?),
+        log: vec![log("response", resp)],
+        data: None,
     })
 }
 
-/////////////////////////////////////// Query /////////////////////////////////////
-/// Returns QueryResult
+/// Returns HandleResult
+///
+/// process the seller adding more sell tokens to the lot before any bids have been placed
 ///
 /// # Arguments
 ///
-/// * `deps` - reference to Extern containing all the contract's external dependencies
-/// * `msg` - QueryMsg passed in with the query call
-pub fn query<S: Storage, A: Api, Q: Querier>(deps: &Extern<S, A, Q>, msg: QueryMsg) -> QueryResult {
-    let response = match msg {
-        QueryMsg::AuctionInfo {} => try_query_info(deps),
-        QueryMsg::ViewBid {
-            address,
-            viewing_key,
-        } => try_view_bid(deps, &address, viewing_key),
-        QueryMsg::HasBids {
-            address,
-            viewing_key,
-        } => try_has_bids(deps, &address, viewing_key),
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `owner` - address of owner of tokens sent to escrow
+/// * `amount` - Uint128 amount sent to escrow
+/// * `state` - mutable reference to the state of the auction
+fn try_increase_lot<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    owner: HumanAddr,
+    amount: Uint128,
+    state: &mut State,
+) -> HandleResult {
+    if owner != state.seller {
+        return refuse_increase(
+            state,
+            owner,
+            amount,
+            "Only the auction seller can increase the lot.  Your tokens have been returned",
+        );
+    }
+    if state.is_completed {
+        return refuse_increase(
+            state,
+            owner,
+            amount,
+            "Auction has ended. Your tokens have been returned",
+        );
+    }
+    if state.currently_consigned < state.sell_amount {
+        return refuse_increase(
+            state,
+            owner,
+            amount,
+            "The sell amount must be fully consigned before the lot can be increased.  Your \
+             tokens have been returned",
+        );
+    }
+    // bidders bid on the lot as it was when they placed their bids
+    if state.has_bids() {
+        return refuse_increase(
+            state,
+            owner,
+            amount,
+            "The lot can not be increased once bids have been placed.  Your tokens have been \
+             returned",
+        );
+    }
+    state.sell_amount = state
+        .sell_amount
+        .checked_add(amount.u128())
+        .ok_or_else(|| StdError::generic_err("The increased sell amount is too large"))?;
+    state.currently_consigned = state.sell_amount;
+    save(&mut deps.storage, CONFIG_KEY, &state)?;
+    // keep the factory's listing of the sell amount accurate
+    let change_msg = FactoryHandleMsg::ChangeAuctionInfo {
+        index: state.index,
+        ends_at: None,
+        minimum_bid: None,
+        sell_amount: Some(Uint128(state.sell_amount)),
+        callback_key: state.callback_key.clone(),
     };
-    pad_query_result(response, BLOCK_SIZE)
+    let factory_msg = change_msg.to_cosmos_msg(
+        state.factory.code_hash.clone(),
+        state.factory.address.clone(),
+        None,
+    )?;
+
+    let resp = serde_json::to_string(&HandleAnswer::IncreaseLot {
+        status: Success,
+        message: String::from("The lot has been increased"),
+        sell_amount: Uint128(state.sell_amount),
+        amount_returned: None,
+        sell_decimals: state.sell_decimals,
+    })
+    .unwrap();
+
+    Ok(HandleResponse {
+        messages: vec![factory_msg],
+        log: vec![log("response", resp)],
+        data: None,
+    })
 }
 
-/// Returns QueryResult displaying the auction information
+/// Returns HandleResult
+///
+/// process the seller posting a deposit in bid tokens
 ///
 /// # Arguments
 ///
-/// * `deps` - reference to Extern containing all the contract's external dependencies
-fn try_query_info<S: Storage, A: Api, Q: Querier>(deps: &Extern<S, A, Q>) -> QueryResult {
-    let state: State = load(&deps.storage, CONFIG_KEY)?;
-
-    // get sell token info
-    let sell_token_info = state.sell_contract.token_info_query(&deps.querier)?;
-    // get bid token info
-    let bid_token_info = state.bid_contract.token_info_query(&deps.querier)?;
-
-    // build status string
-    let status = if state.is_completed {
-        let locked = if !state.bidders.is_empty() || state.currently_consigned > 0 {
-            ", but found outstanding balances.  Please run either retract_bid to \
-                retrieve your non-winning bid, or return_all to return all outstanding bids/\
-                consignment."
-        } else {
-            ""
-        };
-        format!("Closed{}", locked)
-    } else {
-        let consign = if !state.tokens_consigned { " NOT" } else { "" };
-        format!(
-            "Accepting bids: Token(s) to be sold have{} been consigned to the auction",
-            consign
-        )
-    };
-
-    let winning_bid = if state.winning_bid == 0 {
-        None
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `seller` - address of the seller
+/// * `amount` - Uint128 amount sent to escrow
+/// * `state` - mutable reference to the state of the auction
+fn try_deposit<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    seller: HumanAddr,
+    amount: Uint128,
+    state: &mut State,
+) -> HandleResult {
+    let deposit_total = checked_add(state.deposit_posted, amount.u128())?;
+    let mut log_msg = String::new();
+    let mut cos_msg = Vec::new();
+    let status: ResponseStatus;
+    let mut excess: Option<Uint128> = None;
+    let mut needed: Option<Uint128> = None;
+    // if less than the required deposit, ask for remaining balance
+    if deposit_total < state.deposit {
+        state.deposit_posted = deposit_total;
+        needed = Some(Uint128(state.deposit - deposit_total));
+        status = Failure;
+        log_msg.push_str(
+            "You have not posted the full deposit.  You need to send additional bid tokens",
+        );
+    // the full deposit has been posted
     } else {
-        Some(Uint128(state.winning_bid))
-    };
+        state.deposit_posted = state.deposit;
+        status = Success;
+        log_msg.push_str("Deposit has been posted to the auction");
+        // if sent more than needed, return excess tokens
+        if deposit_total > state.deposit {
+            excess = Some(Uint128(deposit_total - state.deposit));
+            cos_msg.push(state.bid_contract.transfer_msg(
+                &state.auction_addr,
+                seller,
+                excess.unwrap(),
+            )?);
+            log_msg.push_str(".  Excess tokens have been returned");
+        }
+    }
 
-    let ends_at = format!(
-        "{} UTC",
-        NaiveDateTime::from_timestamp(state.ends_at as i64, 0).format("%Y-%m-%d %H:%M:%S")
-    );
+    save(&mut deps.storage, CONFIG_KEY, &state)?;
 
-    to_binary(&QueryAnswer::AuctionInfo {
-        sell_token: Token {
-            contract_address: state.sell_contract.address,
-            token_info: sell_token_info,
-        },
-        bid_token: Token {
-            contract_address: state.bid_contract.address,
-            token_info: bid_token_info,
-        },
-        sell_amount: Uint128(state.sell_amount),
-        minimum_bid: Uint128(state.minimum_bid),
-        description: state.description,
-        auction_address: state.auction_addr,
-        ends_at,
+    let resp = serde_json::to_string(&HandleAnswer::Deposit {
         status,
-        winning_bid,
+        message: log_msg,
+        amount_posted: Uint128(state.deposit_posted),
+        amount_needed: needed,
+        amount_returned: excess,
+        bid_decimals: state.bid_decimals,
+    })
+    .unwrap();
+
+    Ok(HandleResponse {
+        messages: cos_msg,
+        log: vec![log("response", resp)],
+        data: None,
     })
 }
 
-/// Returns QueryResult displaying the bid information
+/// Returns StdResult<CosmosMsg>
+///
+/// creates the callback telling the factory an address is bidding, so the factory can list the
+/// auction, and the amount of the bid, in the bidder's auctions
 ///
 /// # Arguments
 ///
-/// * `deps` - reference to Extern containing all the contract's external dependencies
-/// * `bidder` - reference to address wanting to view its bid
-/// * `key` - String holding the viewing key
-fn try_view_bid<S: Storage, A: Api, Q: Querier>(
-    deps: &Extern<S, A, Q>,
+/// * `state` - a reference to the auction state
+/// * `bidder` - a reference to the bidder's address
+/// * `amount` - amount of the bidder's active bid, if it is known
+fn register_bidder_msg(
+    state: &State,
     bidder: &HumanAddr,
-    key: String,
-) -> QueryResult {
-    let state: State = load(&deps.storage, CONFIG_KEY)?;
-    let key_valid_msg = FactoryQueryMsg::IsKeyValid {
-        address: bidder.clone(),
-        viewing_key: key,
-    };
-    let key_valid_response: IsKeyValidWrapper = key_valid_msg.query(
-        &deps.querier,
-        state.factory.code_hash,
-        state.factory.address,
-    )?;
+    amount: Option<Uint128>,
+) -> StdResult<CosmosMsg> {
+    FactoryHandleMsg::RegisterBidder {
+        index: state.index,
+        bidder: bidder.clone(),
+        amount,
+        callback_key: state.callback_key.clone(),
+    }
+    .to_cosmos_msg(
+        state.factory.code_hash.clone(),
+        state.factory.address.clone(),
+        None,
+    )
+}
 
-    // if authenticated
-    if key_valid_response.is_key_valid.is_valid {
-        let decimals = state.bid_decimals;
-        let bidder_raw = &deps.api.canonical_address(bidder)?;
-        let mut amount_bid: Option<Uint128> = None;
-        let mut message = String::new();
-        let status: ResponseStatus;
+/// why a bid is refused before it is saved
+enum BidRejection {
+    /// the factory has halted the auction
+    Halted,
+    /// the auction has closed
+    Completed,
+    /// the closing time has passed, so a bid finalizes the auction instead
+    Closed,
+    /// the seller has paused bidding
+    Paused,
+    /// the auction only takes sealed commitments
+    Sealed,
+    /// the seller may not bid in this auction
+    SelfBid,
+    /// the auction already has as many bidders as it accepts
+    BidderCap,
+    /// the bid is 0
+    Zero,
+    /// the bid is less than the held minimum bid
+    BelowMinimum(u128),
+    /// the bid would lower the held current bid, and the auction only allows higher rebids
+    NotIncreasing(u128),
+    /// the held allowance does not cover the bid
+    AllowanceShort(Uint128),
+    /// the allowance expires before the closing time
+    AllowanceExpires,
+}
 
-        if state.bidders.contains(&bidder_raw.as_slice().to_vec()) {
-            let bid: Option<Bid> = may_load(&deps.storage, bidder_raw.as_slice())?;
-            if let Some(found_bid) = bid {
-                status = Success;
-                amount_bid = Some(Uint128(found_bid.amount));
-                message.push_str(&format!(
-                    "Bid placed {} UTC",
-                    NaiveDateTime::from_timestamp(found_bid.timestamp as i64, 0)
-                        .format("%Y-%m-%d %H:%M:%S")
-                ));
-            } else {
-                status = Failure;
-                message.push_str(&format!("No active bid for address: {}", bidder));
+impl BidRejection {
+    /// Returns String
+    ///
+    /// describes why the bid is refused
+    ///
+    /// # Arguments
+    ///
+    /// * `state` - a reference to the auction state
+    fn reason(&self, state: &State) -> String {
+        match self {
+            BidRejection::Halted => "This auction has been halted by its factory".to_string(),
+            BidRejection::Completed => "Auction has ended".to_string(),
+            BidRejection::Closed => {
+                "The closing time has passed.  A bid would finalize the auction instead".to_string()
             }
-        // no active bid found
-        } else {
-            status = Failure;
-            message.push_str(&format!("No active bid for address: {}", bidder));
+            BidRejection::Paused => "Bidding has been paused by the seller".to_string(),
+            BidRejection::Sealed => "This auction takes sealed bids.  Send a commit_bid msg with \
+                                     your deposit instead"
+                .to_string(),
+            BidRejection::SelfBid => "The seller is not allowed to bid in this auction".to_string(),
+            BidRejection::BidderCap => format!(
+                "This auction only accepts bids from {} bidders",
+                state.max_bidders.unwrap_or_default()
+            ),
+            BidRejection::Zero => "Bid must be greater than 0".to_string(),
+            BidRejection::BelowMinimum(minimum) => {
+                format!("Bid was less than the minimum bid of {}", minimum)
+            }
+            BidRejection::NotIncreasing(current) => format!(
+                "This auction only allows a bid to be replaced with a higher bid.  Your current \
+                 bid of {} remains active",
+                current
+            ),
+            BidRejection::AllowanceShort(allowance) => format!(
+                "Your allowance of {} for this auction does not cover your bid",
+                allowance
+            ),
+            BidRejection::AllowanceExpires => format!(
+                "Your allowance for this auction must not expire before the closing time {}",
+                state.ends_at
+            ),
         }
-        return to_binary(&QueryAnswer::Bid {
-            status,
-            message,
-            amount_bid,
-            bid_decimals: amount_bid.map(|_a| decimals),
-        });
     }
-
-    to_binary(&QueryAnswer::ViewingKeyError {
-        error: "Wrong viewing key for this address or viewing key not set".to_string(),
-    })
 }
 
-/// Returns QueryResult displaying the presence of active bids
+/// Returns StdResult<Option<BidRejection>>
+///
+/// makes the checks a bid goes through before it is saved, whether it is sent to escrow, backed
+/// by an allowance, or only previewed, and returns why the bid would be refused.  None if it
+/// passes them
 ///
 /// # Arguments
 ///
 /// * `deps` - reference to Extern containing all the contract's external dependencies
-/// * `address` - a reference to the address claiming to be the seller
-/// * `viewing_key` - String holding the viewing key
-fn try_has_bids<S: Storage, A: Api, Q: Querier>(
+/// * `state` - a reference to the auction state
+/// * `bidder` - a reference to the address placing the bid
+/// * `amount` - Uint128 amount bid
+/// * `current_time` - optional current time used to check the closing time.  The check is
+///                    skipped if it is not known
+/// * `minimum_bid` - minimum bid that is accepted
+/// * `current_bid` - optional reference to the address' active bid
+fn check_bid<S: Storage, A: Api, Q: Querier>(
     deps: &Extern<S, A, Q>,
-    address: &HumanAddr,
-    viewing_key: String,
-) -> QueryResult {
-    let state: State = load(&deps.storage, CONFIG_KEY)?;
-    let key_valid_msg = FactoryQueryMsg::IsKeyValid {
-        address: address.clone(),
-        viewing_key,
+    state: &State,
+    bidder: &HumanAddr,
+    amount: Uint128,
+    current_time: Option<u64>,
+    minimum_bid: u128,
+    current_bid: Option<&Bid>,
+) -> StdResult<Option<BidRejection>> {
+    let rejection = if state.is_halted {
+        BidRejection::Halted
+    } else if state.is_completed {
+        BidRejection::Completed
+    } else if current_time.map_or(false, |t| t >= state.ends_at) {
+        BidRejection::Closed
+    } else if state.bidding_paused {
+        BidRejection::Paused
+    } else if state.reveal_window.is_some() {
+        BidRejection::Sealed
+    } else if state.no_self_bid && *bidder == state.seller {
+        BidRejection::SelfBid
+    } else if is_bidder_cap_reached(&deps.storage, &deps.api, state, bidder)? {
+        BidRejection::BidderCap
+    } else if amount == Uint128(0) {
+        BidRejection::Zero
+    } else if amount.u128() < minimum_bid {
+        BidRejection::BelowMinimum(minimum_bid)
+    } else if let Some(current) = current_bid
+        .map(|b| b.amount)
+        .filter(|c| state.only_increasing_rebids && amount.u128() < *c)
+    {
+        BidRejection::NotIncreasing(current)
+    } else if let Some(key) = state.allowance_key.as_ref() {
+        // the allowance must cover the bid until the auction can be closed
+        let allowance = state.bid_contract.allowance_query(
+            &deps.querier,
+            bidder.clone(),
+            state.auction_addr.clone(),
+            key.clone(),
+        )?;
+        if allowance.allowance < amount {
+            BidRejection::AllowanceShort(allowance.allowance)
+        } else if allowance.expiration.map_or(false, |e| e < state.ends_at) {
+            BidRejection::AllowanceExpires
+        } else {
+            return Ok(None);
+        }
+    } else {
+        return Ok(None);
     };
-    let key_valid_response: IsKeyValidWrapper = key_valid_msg.query(
-        &deps.querier,
-        state.factory.code_hash,
-        state.factory.address,
-    )?;
-
-    // if authenticated
-    if state.seller == *address && key_valid_response.is_key_valid.is_valid {
-        return to_binary(&QueryAnswer::HasBids {
-            has_bids: !state.bidders.is_empty(),
-        });
-    }
-
-    to_binary(&QueryAnswer::ViewingKeyError {
-        error: "Address and/or viewing key does not match auction creator's information"
-            .to_string(),
-    })
+    Ok(Some(rejection))
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::msg::ContractInfo;
-    use cosmwasm_std::{
-        from_binary, testing::*, BlockInfo, MessageInfo, QuerierResult, QueryResponse, StdResult,
+/// Returns HandleResult
+///
+/// process the bid attempt
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `bidder` - address of owner of tokens sent to escrow
+/// * `amount` - Uint128 amount sent to escrow
+/// * `expiration` - optional time after which the bid is no longer valid
+/// * `max_bid` - optional maximum amount to bid.  Any tokens sent above it are returned
+/// * `memo` - optional private memo for the seller
+/// * `referrer` - optional address that referred the bidder
+/// * `state` - mutable reference to auction state
+#[allow(clippy::too_many_arguments)]
+fn try_bid<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    bidder: HumanAddr,
+    amount: Uint128,
+    expiration: Option<u64>,
+    max_bid: Option<Uint128>,
+    memo: Option<String>,
+    referrer: Option<HumanAddr>,
+    state: &mut State,
+) -> HandleResult {
+    // only bid up to the maximum, any tokens sent above it will be returned
+    let bid_amount = max_bid.map_or(amount, |m| Uint128(amount.u128().min(m.u128())));
+    let excess = amount.u128() - bid_amount.u128();
+    let bidder_raw = &deps.api.canonical_address(&bidder)?;
+    let current_bid: Option<Bid> = if is_bidder(&deps.storage, bidder_raw.as_slice())? {
+        may_load(&deps.storage, bidder_raw.as_slice())?
+    } else {
+        None
     };
-    use std::any::Any;
-
-    fn init_helper() -> (
-        StdResult<InitResponse>,
-        Extern<MockStorage, MockApi, MockQuerier>,
-    ) {
-        let mut deps = mock_dependencies(20, &[]);
-        let env = mock_env("factory", &[]);
+    let minimum_bid = state.effective_minimum_bid(env.block.time);
+    match check_bid(
+        deps,
+        state,
+        &bidder,
+        bid_amount,
+        Some(env.block.time),
+        minimum_bid,
+        current_bid.as_ref(),
+    )? {
+        None => {}
+        // the first bid placed after the closing time finalizes the auction, and the bid is
+        // returned
+        Some(BidRejection::Closed) => {
+            let mut resp = try_finalize(deps, env, None, None)?;
+            resp.messages.push(state.bid_contract.transfer_msg(
+                &state.auction_addr,
+                bidder,
+                amount,
+            )?);
+            let message = String::from(
+                "Auction closing time has passed.  The auction has been finalized and bid tokens \
+                 have been returned",
+            );
+            let bid_resp = to_bid_answer(&HandleAnswer::Bid {
+                status: Failure,
+                message,
+                previous_bid: None,
+                minimum_bid: None,
+                amount_bid: None,
+                unit_price: None,
+                amount_returned: Some(amount),
+                bid_decimals: state.bid_decimals,
+                previous_bid_humanized: None,
+                minimum_bid_humanized: None,
+                amount_bid_humanized: None,
+                amount_returned_humanized: humanize(Some(amount), state.bid_decimals),
+            })?;
+            resp.log = vec![log("response", bid_resp)];
+            resp.data = None;
+            return Ok(resp);
+        }
+        // don't accept a 0 bid
+        Some(BidRejection::Zero) => {
+            return Err(StdError::generic_err(BidRejection::Zero.reason(state)));
+        }
+        // if bid is less than the minimum accepted bid, send the tokens back
+        Some(BidRejection::BelowMinimum(minimum_bid)) => {
+            let message =
+                String::from("Bid was less than minimum allowed.  Bid tokens have been returned");
 
-        let factory = ContractInfo {
-            code_hash: "factoryhash".to_string(),
-            address: HumanAddr("factoryaddr".to_string()),
-        };
-        let sell_contract = ContractInfo {
-            code_hash: "sellhash".to_string(),
-            address: HumanAddr("selladdr".to_string()),
-        };
-        let bid_contract = ContractInfo {
-            code_hash: "bidhash".to_string(),
-            address: HumanAddr("bidaddr".to_string()),
-        };
-        let init_msg = InitMsg {
-            factory,
-            index: 0,
-            label: "auction".to_string(),
-            sell_symbol: 0,
-            sell_decimals: 4,
-            bid_symbol: 1,
-            bid_decimals: 8,
-            seller: HumanAddr("alice".to_string()),
-            sell_contract,
-            bid_contract,
-            sell_amount: Uint128(10),
-            minimum_bid: Uint128(10),
-            ends_at: 1000,
-            description: None,
-        };
-        (init(&mut deps, env, init_msg), deps)
-    }
+            let resp = to_bid_answer(&HandleAnswer::Bid {
+                status: Failure,
+                message,
+                previous_bid: None,
+                minimum_bid: Some(Uint128(minimum_bid)),
+                amount_bid: None,
+                unit_price: None,
+                amount_returned: Some(amount),
+                bid_decimals: state.bid_decimals,
+                previous_bid_humanized: None,
+                minimum_bid_humanized: humanize(Some(Uint128(minimum_bid)), state.bid_decimals),
+                amount_bid_humanized: None,
+                amount_returned_humanized: humanize(Some(amount), state.bid_decimals),
+            })?;
+
+            return Ok(HandleResponse {
+                messages: vec![state.bid_contract.transfer_msg(
+                    &state.auction_addr,
+                    bidder,
+                    amount,
+                )?],
+                log: vec![log("response", resp)],
+                data: None,
+            });
+        }
+        // if the auction only allows a bid to be replaced with a higher bid, keep the old bid
+        Some(BidRejection::NotIncreasing(current)) => {
+            let message = format!(
+                "This auction only allows a bid to be replaced with a higher bid.  Your current \
+                 bid of {} remains active and the new bid tokens have been returned",
+                current
+            );
 
-    fn extract_error_msg<T: Any>(error: StdResult<T>) -> String {
-        match error {
-            Ok(response) => {
-                let bin_err = (&response as &dyn Any)
-                    .downcast_ref::<QueryResponse>()
-                    .expect("An error was expected, but no error could be extracted");
-                match from_binary(bin_err).unwrap() {
-                    QueryAnswer::ViewingKeyError { error } => error,
-                    _ => panic!("Unexpected query answer"),
-                }
-            }
-            Err(err) => match err {
-                StdError::GenericErr { msg, .. } => msg,
-                _ => panic!("Unexpected result from init"),
-            },
+            let resp = to_bid_answer(&HandleAnswer::Bid {
+                status: Failure,
+                message,
+                previous_bid: Some(Uint128(current)),
+                minimum_bid: None,
+                amount_bid: None,
+                unit_price: None,
+                amount_returned: Some(amount),
+                bid_decimals: state.bid_decimals,
+                previous_bid_humanized: humanize(Some(Uint128(current)), state.bid_decimals),
+                minimum_bid_humanized: None,
+                amount_bid_humanized: None,
+                amount_returned_humanized: humanize(Some(amount), state.bid_decimals),
+            })?;
+
+            return Ok(HandleResponse {
+                messages: vec![state.bid_contract.transfer_msg(
+                    &state.auction_addr,
+                    bidder,
+                    amount,
+                )?],
+                log: vec![log("response", resp)],
+                data: None,
+            });
+        }
+        // for any other reason, send the tokens back
+        Some(rejection) => {
+            let message = format!(
+                "{}.  Bid tokens have been returned",
+                rejection.reason(state)
+            );
+            return refuse_bid(state, bidder, amount, &message);
         }
     }
-
-    fn extract_log(resp: StdResult<HandleResponse>) -> String {
-        match resp {
-            Ok(response) => response.log[0].value.clone(),
-            Err(_err) => "These are not the logs you are looking for".to_string(),
+    if let Some(expires) = expiration {
+        if expires <= env.block.time {
+            let message = format!(
+                "Bid expiration {} must be later than the current block time {}.  Your tokens \
+                 have been returned",
+                expires, env.block.time
+            );
+            return refuse_bid(state, bidder, amount, &message);
         }
     }
+    let referrer = match check_memo(memo.as_deref())
+        .and_then(|_| check_referrer(&deps.api, referrer.as_ref(), &bidder))
+    {
+        Ok(referrer) => referrer,
+        Err(StdError::GenericErr { msg, .. }) => {
+            let message = format!("{}.  Your tokens have been returned", msg);
+            return refuse_bid(state, bidder, amount, &message);
+        }
+        Err(e) => return Err(e),
+    };
+    let mut return_amount: Option<Uint128> = None;
+    let mut cosmos_msg = Vec::new();
 
-    fn extract_msg(resp: &StdResult<HandleResponse>) -> String {
-        let handle_answer: HandleAnswer =
-            from_binary(&resp.as_ref().unwrap().data.as_ref().unwrap()).unwrap();
-        match handle_answer {
-            HandleAnswer::Bid { message, .. } => message.clone(),
-            HandleAnswer::Consign { message, .. } => message.clone(),
-            HandleAnswer::CloseAuction { message, .. } => message.clone(),
-            HandleAnswer::RetractBid { message, .. } => message.clone(),
-            _ => panic!("Unexpected HandleAnswer"),
+    // if there is an active bid from this address
+    if let Some(old_bid) = current_bid {
+        // if new bid is == the old bid, keep old bid (with the new expiration) and return this one
+        if bid_amount.u128() == old_bid.amount {
+            save(
+                &mut deps.storage,
+                bidder_raw.as_slice(),
+                &Bid {
+                    expiration,
+                    memo,
+                    referrer,
+                    ..old_bid
+                },
+            )?;
+            let message =
+                String::from("New bid is the same as previous bid.  Retaining previous timestamp");
+
+            let resp = to_bid_answer(&HandleAnswer::Bid {
+                status: Failure,
+                message,
+                previous_bid: Some(bid_amount),
+                minimum_bid: None,
+                amount_bid: Some(bid_amount),
+                unit_price: unit_price(state, bid_amount.u128()),
+                amount_returned: Some(amount),
+                bid_decimals: state.bid_decimals,
+                previous_bid_humanized: humanize(Some(bid_amount), state.bid_decimals),
+                minimum_bid_humanized: None,
+                amount_bid_humanized: humanize(Some(bid_amount), state.bid_decimals),
+                amount_returned_humanized: humanize(Some(amount), state.bid_decimals),
+            })?;
+
+            return Ok(HandleResponse {
+                messages: vec![state.bid_contract.transfer_msg(
+                    &state.auction_addr,
+                    bidder,
+                    amount,
+                )?],
+                log: vec![log("response", resp)],
+                data: None,
+            });
+        }
+        // new bid is different, save the new bid, and return the old one, so mark for return
+        return_amount = Some(Uint128(old_bid.amount));
+    // address did not have an active bid
+    } else {
+        // clear the outcome of any previous bid
+        let mut outcomes = PrefixedStorage::new(PREFIX_OUTCOMES, &mut deps.storage);
+        remove(&mut outcomes, bidder_raw.as_slice());
+    }
+    let new_bid = Bid {
+        amount: bid_amount.u128(),
+        timestamp: env.block.time,
+        expiration,
+        memo,
+        referrer,
+    };
+    save(&mut deps.storage, bidder_raw.as_slice(), &new_bid)?;
+    record_bid(&mut deps.storage, state, bidder_raw.as_slice(), &new_bid)?;
+    state.last_bid_at = Some(env.block.time);
+    save(&mut deps.storage, CONFIG_KEY, &state)?;
+    // let the factory list the bid in the bidder's auctions
+    cosmos_msg.push(register_bidder_msg(state, &bidder, Some(bid_amount))?);
+
+    let mut message = String::from("Bid accepted");
+
+    // if need to return the old bid
+    if return_amount.is_some() {
+        message.push_str(". Previously bid tokens have been returned");
+    }
+    // and any tokens sent above the maximum bid
+    if excess > 0 {
+        return_amount = Some(Uint128(checked_add(
+            return_amount.map_or(0, |r| r.u128()),
+            excess,
+        )?));
+        message.push_str(".  Tokens sent above your maximum bid have been returned");
+    }
+    if let Some(returned) = return_amount {
+        cosmos_msg.push(
+            state
+                .bid_contract
+                .transfer_msg(&state.auction_addr, bidder, returned)?,
+        );
+    }
+    let resp = to_bid_answer(&HandleAnswer::Bid {
+        status: Success,
+        message,
+        previous_bid: None,
+        minimum_bid: None,
+        amount_bid: Some(bid_amount),
+        unit_price: unit_price(state, bid_amount.u128()),
+        amount_returned: return_amount,
+        bid_decimals: state.bid_decimals,
+        previous_bid_humanized: None,
+        minimum_bid_humanized: None,
+        amount_bid_humanized: humanize(Some(bid_amount), state.bid_decimals),
+        amount_returned_humanized: humanize(return_amount, state.bid_decimals),
+    })?;
+
+    Ok(HandleResponse {
+        messages: cosmos_msg,
+        log: vec![log("response", resp)],
+        data: None,
+    })
+}
+
+/// Returns HandleResult
+///
+/// process a sealed commitment to a bid.  The tokens sent are held as a deposit until the bid is
+/// revealed
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `bidder` - address of owner of tokens sent to escrow
+/// * `amount` - Uint128 amount sent to escrow as the deposit
+/// * `commitment` - sha256 hash of the bidder's address, the bid amount, and a salt
+/// * `state` - mutable reference to auction state
+fn try_commit_bid<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    bidder: HumanAddr,
+    amount: Uint128,
+    commitment: Binary,
+    state: &mut State,
+) -> HandleResult {
+    if commitment.len() != 32 {
+        return Err(StdError::generic_err(
+            "A commitment must be a sha256 hash.  Bid tokens have been returned",
+        ));
+    }
+    if amount == Uint128(0) {
+        return Err(StdError::generic_err("Deposit must be greater than 0"));
+    }
+    if state.is_completed {
+        return refuse_commit(
+            state,
+            bidder,
+            amount,
+            "Auction has ended. Bid tokens have been returned",
+        );
+    }
+    if env.block.time >= state.ends_at {
+        return refuse_commit(
+            state,
+            bidder,
+            amount,
+            "Commitments are not accepted after the closing time.  Bid tokens have been returned",
+        );
+    }
+    if state.bidding_paused {
+        return refuse_commit(
+            state,
+            bidder,
+            amount,
+            "Bidding has been paused by the seller.  Bid tokens have been returned",
+        );
+    }
+    if state.no_self_bid && bidder == state.seller {
+        return refuse_commit(
+            state,
+            bidder,
+            amount,
+            "The seller is not allowed to bid in this auction.  Bid tokens have been returned",
+        );
+    }
+    if is_bidder_cap_reached(&deps.storage, &deps.api, state, &bidder)? {
+        let message = format!(
+            "This auction only accepts bids from {} bidders.  Bid tokens have been returned",
+            state.max_bidders.unwrap_or_default()
+        );
+        return refuse_commit(state, bidder, amount, &message);
+    }
+    // the deposit must be able to cover a valid bid
+    let minimum_bid = state.effective_minimum_bid(env.block.time);
+    if amount.u128() < minimum_bid {
+        let message = format!(
+            "The deposit must be at least the minimum bid of {}.  Bid tokens have been returned",
+            minimum_bid
+        );
+        return refuse_commit(state, bidder, amount, &message);
+    }
+    let bidder_raw = &deps.api.canonical_address(&bidder)?;
+    let mut cosmos_msg = Vec::new();
+    let mut commitments = PrefixedStorage::new(PREFIX_COMMITMENTS, &mut deps.storage);
+    // a new commitment replaces the old one, and the old deposit is returned
+    let return_amount =
+        may_load::<Commitment, _>(&commitments, bidder_raw.as_slice())?.map(|c| Uint128(c.deposit));
+    save(
+        &mut commitments,
+        bidder_raw.as_slice(),
+        &Commitment {
+            hash: commitment.0,
+            deposit: amount.u128(),
+            timestamp: env.block.time,
+        },
+    )?;
+    let mut message = format!(
+        "Commitment accepted.  Reveal your bid after the closing time, and before {}",
+        state.reveal_ends_at().unwrap_or_default()
+    );
+    if let Some(returned) = return_amount {
+        message.push_str(".  The deposit of your previous commitment has been returned");
+        cosmos_msg.push(
+            state
+                .bid_contract
+                .transfer_msg(&state.auction_addr, bidder, returned)?,
+        );
+    } else {
+        add_committer(&mut deps.storage, &mut state, bidder_raw.as_slice())?;
+        // clear the outcome of any previous bid
+        let mut outcomes = PrefixedStorage::new(PREFIX_OUTCOMES, &mut deps.storage);
+        remove(&mut outcomes, bidder_raw.as_slice());
+        // register new bidder with the factory.  The amount stays sealed until it is revealed
+        cosmos_msg.push(register_bidder_msg(&state, &bidder, None)?);
+    }
+    state.last_bid_at = Some(env.block.time);
+    save(&mut deps.storage, CONFIG_KEY, &state)?;
+
+    let resp = to_bid_answer(&HandleAnswer::CommitBid {
+        status: Success,
+        message,
+        deposit: Some(amount),
+        amount_returned: return_amount,
+        bid_decimals: state.bid_decimals,
+    })?;
+
+    Ok(HandleResponse {
+        messages: cosmos_msg,
+        log: vec![log("response", resp)],
+        data: None,
+    })
+}
+
+/// Returns HandleResult
+///
+/// reveals the bid behind the calling address' sealed commitment.  A bid that matches the
+/// commitment becomes active, and any deposit above it is returned
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `amount` - Uint128 amount that was bid
+/// * `salt` - salt that was hashed with the bid to create the commitment
+fn try_reveal_bid<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    amount: Uint128,
+    salt: String,
+) -> HandleResult {
+    let mut state: State = load(&deps.storage, CONFIG_KEY)?;
+    let reveal_ends = state
+        .reveal_ends_at()
+        .ok_or_else(|| StdError::generic_err("This auction does not take sealed bids"))?;
+    if state.is_halted {
+        return Err(StdError::generic_err(
+            "This auction has been halted by its factory",
+        ));
+    }
+    if state.is_completed {
+        return Err(StdError::generic_err("The auction has already closed"));
+    }
+    if env.block.time < state.ends_at || env.block.time >= reveal_ends {
+        return Err(StdError::generic_err(format!(
+            "Bids can only be revealed after the closing time {}, and before {}",
+            state.ends_at, reveal_ends
+        )));
+    }
+    let bidder = env.message.sender;
+    let bidder_raw = &deps.api.canonical_address(&bidder)?;
+    let mut commitments = PrefixedStorage::new(PREFIX_COMMITMENTS, &mut deps.storage);
+    let commitment: Commitment =
+        may_load(&commitments, bidder_raw.as_slice())?.ok_or_else(|| {
+            StdError::generic_err(format!("No sealed commitment for address: {}", bidder))
+        })?;
+    let preimage = format!("{}:{}:{}", bidder, amount, salt);
+    if sha_256(preimage.as_bytes())[..] != commitment.hash[..] {
+        return Err(StdError::generic_err(
+            "The amount and salt do not match your commitment",
+        ));
+    }
+    // a bid the deposit does not cover can not be revealed, so its deposit will be forfeited
+    if amount.u128() > commitment.deposit {
+        return Err(StdError::generic_err(format!(
+            "The bid of {} is more than the deposit of {}",
+            amount, commitment.deposit
+        )));
+    }
+    remove(&mut commitments, bidder_raw.as_slice());
+    remove_committer(&mut deps.storage, &mut state, bidder_raw.as_slice())?;
+    let mut cosmos_msg = Vec::new();
+    let status: ResponseStatus;
+    let amount_bid: Option<Uint128>;
+    let returned: u128;
+    let mut message: String;
+    // bids are held to the minimum in effect at the closing time
+    if amount.u128() == 0 || amount.u128() < state.effective_minimum_bid(state.ends_at) {
+        status = Failure;
+        amount_bid = None;
+        returned = commitment.deposit;
+        message =
+            String::from("Bid was less than minimum allowed.  Your deposit has been returned");
+        // let factory know the address is no longer bidding
+        let rem_bid_msg = FactoryHandleMsg::RemoveBidder {
+            index: state.index,
+            bidder: bidder.clone(),
+            callback_key: state.callback_key.clone(),
+        };
+        cosmos_msg.push(rem_bid_msg.to_cosmos_msg(
+            state.factory.code_hash.clone(),
+            state.factory.address.clone(),
+            None,
+        )?);
+    } else {
+        status = Success;
+        amount_bid = Some(amount);
+        returned = commitment.deposit - amount.u128();
+        message = String::from("Bid revealed");
+        // the bid keeps the time it was committed, so earlier commitments still win ties
+        let revealed = Bid {
+            amount: amount.u128(),
+            timestamp: commitment.timestamp,
+            expiration: None,
+            memo: None,
+            referrer: None,
+        };
+        save(&mut deps.storage, bidder_raw.as_slice(), &revealed)?;
+        record_bid(
+            &mut deps.storage,
+            &mut state,
+            bidder_raw.as_slice(),
+            &revealed,
+        )?;
+        cosmos_msg.push(register_bidder_msg(&state, &bidder, Some(amount))?);
+        if returned > 0 {
+            message.push_str(".  Tokens deposited above your bid have been returned");
+        }
+    }
+    save(&mut deps.storage, CONFIG_KEY, &state)?;
+    let amount_returned = if returned > 0 {
+        cosmos_msg.push(state.bid_contract.transfer_msg(
+            &state.auction_addr,
+            bidder,
+            Uint128(returned),
+        )?);
+        Some(Uint128(returned))
+    } else {
+        None
+    };
+
+    Ok(HandleResponse {
+        messages: cosmos_msg,
+        log: vec![],
+        data: Some(Binary(
+            to_bid_answer(&HandleAnswer::RevealBid {
+                status,
+                message,
+                amount_bid,
+                amount_returned,
+                bid_decimals: state.bid_decimals,
+            })?
+            .into_bytes(),
+        )),
+    })
+}
+
+/// Returns HandleResult
+///
+/// process a bid backed by an allowance the bidder gave the auction.  No tokens are moved until
+/// the auction closes, and then only the winning bid is transferred
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `amount` - Uint128 amount to bid
+/// * `balance_key` - the bidder's viewing key with the bid token
+/// * `expiration` - optional time after which the bid is no longer valid
+/// * `memo` - optional private memo for the seller
+/// * `referrer` - optional address that referred the bidder
+#[allow(clippy::too_many_arguments)]
+fn try_allowance_bid<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    amount: Uint128,
+    balance_key: String,
+    expiration: Option<u64>,
+    memo: Option<String>,
+    referrer: Option<HumanAddr>,
+) -> HandleResult {
+    let mut state: State = load(&deps.storage, CONFIG_KEY)?;
+    if state.allowance_key.is_none() {
+        return Err(StdError::generic_err(
+            "This auction only accepts bids sent to escrow.  Send bid tokens with a place_bid msg",
+        ));
+    }
+    let bidder = env.message.sender.clone();
+    let bidder_raw = &deps.api.canonical_address(&bidder)?;
+    let previous: Option<Bid> = if is_bidder(&deps.storage, bidder_raw.as_slice())? {
+        may_load(&deps.storage, bidder_raw.as_slice())?
+    } else {
+        None
+    };
+    let minimum_bid = state.effective_minimum_bid(env.block.time);
+    match check_bid(
+        deps,
+        &state,
+        &bidder,
+        amount,
+        Some(env.block.time),
+        minimum_bid,
+        previous.as_ref(),
+    )? {
+        None => {}
+        // the first bid placed after the closing time finalizes the auction instead
+        Some(BidRejection::Closed) => return try_finalize(deps, env, None, None),
+        Some(rejection) => return Err(StdError::generic_err(rejection.reason(&state))),
+    }
+    if let Some(expires) = expiration {
+        if expires <= env.block.time {
+            return Err(StdError::generic_err(format!(
+                "Bid expiration {} must be later than the current block time {}",
+                expires, env.block.time
+            )));
+        }
+    }
+    check_memo(memo.as_deref())?;
+    let referrer = check_referrer(&deps.api, referrer.as_ref(), &bidder)?;
+    // and so must the balance behind it
+    let balance = state
+        .bid_contract
+        .balance_query(&deps.querier, bidder.clone(), balance_key.clone())
+        .map_err(|_| {
+            StdError::generic_err("Unable to check your balance with the viewing key provided")
+        })?;
+    if balance.amount < amount {
+        return Err(StdError::generic_err(format!(
+            "Your balance of {} does not cover your bid",
+            balance.amount
+        )));
+    }
+    let mut cosmos_msg = Vec::new();
+    let mut timestamp = env.block.time;
+    let mut message = String::from("Bid accepted");
+    if let Some(old_bid) = previous {
+        // an unchanged bid keeps its place in line
+        if amount.u128() == old_bid.amount {
+            timestamp = old_bid.timestamp;
+            message =
+                String::from("New bid is the same as previous bid.  Retaining previous timestamp");
+        }
+    } else {
+        // clear the outcome of any previous bid
+        let mut outcomes = PrefixedStorage::new(PREFIX_OUTCOMES, &mut deps.storage);
+        remove(&mut outcomes, bidder_raw.as_slice());
+    }
+    let new_bid = Bid {
+        amount: amount.u128(),
+        timestamp,
+        expiration,
+        memo,
+        referrer,
+    };
+    save(&mut deps.storage, bidder_raw.as_slice(), &new_bid)?;
+    record_bid(
+        &mut deps.storage,
+        &mut state,
+        bidder_raw.as_slice(),
+        &new_bid,
+    )?;
+    let mut key_store = PrefixedStorage::new(PREFIX_BALANCE_KEYS, &mut deps.storage);
+    save(&mut key_store, bidder_raw.as_slice(), &balance_key)?;
+    state.last_bid_at = Some(env.block.time);
+    save(&mut deps.storage, CONFIG_KEY, &state)?;
+    cosmos_msg.push(register_bidder_msg(&state, &bidder, Some(amount))?);
+    message.push_str(".  Your tokens will only be transferred if your bid wins");
+
+    let resp = to_bid_answer(&HandleAnswer::Bid {
+        status: Success,
+        message,
+        previous_bid: None,
+        minimum_bid: None,
+        amount_bid: Some(amount),
+        unit_price: unit_price(&state, amount.u128()),
+        amount_returned: None,
+        bid_decimals: state.bid_decimals,
+        previous_bid_humanized: None,
+        minimum_bid_humanized: None,
+        amount_bid_humanized: humanize(Some(amount), state.bid_decimals),
+        amount_returned_humanized: None,
+    })?;
+
+    Ok(HandleResponse {
+        messages: cosmos_msg,
+        log: vec![log("response", resp)],
+        data: None,
+    })
+}
+
+/// Returns HandleResult
+///
+/// process an attempt to buy the lot at the buy now price, closing the auction immediately
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `buyer` - address of owner of tokens sent to escrow
+/// * `amount` - Uint128 amount sent to escrow
+/// * `state` - mutable reference to auction state
+fn try_buy_now<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    buyer: HumanAddr,
+    amount: Uint128,
+    state: &mut State,
+) -> HandleResult {
+    // a closed auction returns the tokens the same way it would for a late bid
+    if state.is_completed || env.block.time >= state.ends_at {
+        return try_bid(deps, env, buyer, amount, None, None, None, None, state);
+    }
+    let price = match state.buy_now_price {
+        Some(p) => p,
+        None => {
+            return refuse_bid(
+                state,
+                buyer,
+                amount,
+                "This auction does not have a buy now price.  Your tokens have been returned",
+            );
+        }
+    };
+    if state.bidding_paused {
+        return refuse_bid(
+            state,
+            buyer,
+            amount,
+            "Bidding has been paused by the seller.  Your tokens have been returned",
+        );
+    }
+    if state.no_self_bid && buyer == state.seller {
+        return refuse_bid(
+            state,
+            buyer,
+            amount,
+            "The seller is not allowed to bid in this auction.  Your tokens have been returned",
+        );
+    }
+    if is_bidder_cap_reached(&deps.storage, &deps.api, state, &buyer)? {
+        let message = format!(
+            "This auction only accepts bids from {} bidders.  Your tokens have been returned",
+            state.max_bidders.unwrap_or_default()
+        );
+        return refuse_bid(state, buyer, amount, &message);
+    }
+    if !state.tokens_consigned {
+        return refuse_bid(
+            state,
+            buyer,
+            amount,
+            "The tokens for sale have not been consigned.  Your tokens have been returned",
+        );
+    }
+    if amount.u128() < price {
+        let message = format!(
+            "The buy now price is {}.  Your tokens have been returned",
+            price
+        );
+        return refuse_bid(state, buyer, amount, &message);
+    }
+    let buyer_raw = deps.api.canonical_address(&buyer)?;
+    let buyer_key = buyer_raw.as_slice().to_vec();
+    // closing without enough bidders would return every bid instead of selling the lot
+    if let Some(min_bidders) = state.min_bidders {
+        let with_buyer = if is_bidder(&deps.storage, &buyer_key)? {
+            state.num_bidders
+        } else {
+            state.num_bidders.saturating_add(1)
+        };
+        if with_buyer < min_bidders {
+            let message = format!(
+                "This auction needs bids from {} bidders before the lot can be bought at the buy \
+                 now price.  Your tokens have been returned",
+                min_bidders
+            );
+            return refuse_bid(state, buyer, amount, &message);
+        }
+    }
+    // the buyer must be the highest bidder
+    for bidder in list_bidders(&deps.storage)?
+        .iter()
+        .filter(|b| **b != buyer_key)
+    {
+        let bid: Option<Bid> = may_load(&deps.storage, bidder.as_slice())?;
+        if bid.map_or(false, |b| b.amount >= price) {
+            return refuse_bid(
+                state,
+                buyer,
+                amount,
+                "A bid at or above the buy now price has already been placed.  Your tokens have \
+                 been returned",
+            );
+        }
+    }
+    let mut cos_msg = Vec::new();
+    let mut returned = amount.u128() - price;
+    // replace any previous bid from the buyer
+    if is_bidder(&deps.storage, &buyer_key)? {
+        let bid: Option<Bid> = may_load(&deps.storage, buyer_raw.as_slice())?;
+        if let Some(old_bid) = bid {
+            returned = checked_add(returned, old_bid.amount)?;
         }
+    } else {
+        // clear the outcome of any previous bid
+        let mut outcomes = PrefixedStorage::new(PREFIX_OUTCOMES, &mut deps.storage);
+        remove(&mut outcomes, buyer_raw.as_slice());
+        // register new bidder with the factory
+        cos_msg.push(register_bidder_msg(state, &buyer, Some(Uint128(price)))?);
+    }
+    let new_bid = Bid {
+        amount: price,
+        timestamp: env.block.time,
+        expiration: None,
+        memo: None,
+        referrer: None,
+    };
+    save(&mut deps.storage, buyer_raw.as_slice(), &new_bid)?;
+    record_bid(&mut deps.storage, state, &buyer_key, &new_bid)?;
+    // the buyer now holds the highest bid, so closing the auction sells them the lot
+    let mut resp = close_auction(deps, env, state)?;
+    cos_msg.append(&mut resp.messages);
+    let mut message = String::from("You have bought the lot at the buy now price");
+    let amount_returned = if returned > 0 {
+        cos_msg.push(state.bid_contract.transfer_msg(
+            &state.auction_addr,
+            buyer,
+            Uint128(returned),
+        )?);
+        message.push_str(".  Tokens sent above the buy now price have been returned");
+        Some(Uint128(returned))
+    } else {
+        None
+    };
+    let buy_resp = to_bid_answer(&HandleAnswer::Bid {
+        status: Success,
+        message,
+        previous_bid: None,
+        minimum_bid: None,
+        amount_bid: Some(Uint128(price)),
+        unit_price: unit_price(state, price),
+        amount_returned,
+        bid_decimals: state.bid_decimals,
+        previous_bid_humanized: None,
+        minimum_bid_humanized: None,
+        amount_bid_humanized: humanize(Some(Uint128(price)), state.bid_decimals),
+        amount_returned_humanized: humanize(amount_returned, state.bid_decimals),
+    })?;
+
+    Ok(HandleResponse {
+        messages: cos_msg,
+        log: vec![log("response", buy_resp)],
+        data: None,
+    })
+}
+
+/// Returns HandleResult
+///
+/// attempt to retract current bid.  If the closing time has passed, the auction will be
+/// finalized instead
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+fn try_retract<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+) -> HandleResult {
+    let state: State = load(&deps.storage, CONFIG_KEY)?;
+    // the first retraction after the closing time finalizes the auction
+    if !state.is_completed && env.block.time >= state.ends_at {
+        return try_finalize(deps, env, None, None);
+    }
+    let bidder = env.message.sender.clone();
+    let bidder_raw = deps.api.canonical_address(&bidder)?;
+    if is_committer(&deps.storage, bidder_raw.as_slice())? {
+        return retract_commitment(deps, state, bidder, &bidder_raw);
+    }
+    retract_bid(deps, &env, state, bidder)
+}
+
+/// Returns HandleResult
+///
+/// removes the bidder's sealed commitment and returns the deposit
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `state` - the auction state
+/// * `bidder` - address of the bidder whose commitment should be returned
+/// * `bidder_raw` - a reference to the bidder's canonical address
+fn retract_commitment<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    mut state: State,
+    bidder: HumanAddr,
+    bidder_raw: &CanonicalAddr,
+) -> HandleResult {
+    let mut commitments = PrefixedStorage::new(PREFIX_COMMITMENTS, &mut deps.storage);
+    let commitment: Option<Commitment> = may_load(&commitments, bidder_raw.as_slice())?;
+    remove(&mut commitments, bidder_raw.as_slice());
+    remove_committer(&mut deps.storage, &mut state, bidder_raw.as_slice())?;
+    save(&mut deps.storage, CONFIG_KEY, &state)?;
+    let mut cos_msg = Vec::new();
+    let sent = commitment.map(|c| Uint128(c.deposit));
+    if let Some(deposit) = sent {
+        cos_msg.push(state.bid_contract.transfer_msg(
+            &state.auction_addr,
+            bidder.clone(),
+            deposit,
+        )?);
+    }
+    // let factory know bid was retracted
+    let rem_bid_msg = FactoryHandleMsg::RemoveBidder {
+        index: state.index,
+        bidder,
+        callback_key: state.callback_key.clone(),
+    };
+    cos_msg.push(rem_bid_msg.to_cosmos_msg(
+        state.factory.code_hash,
+        state.factory.address,
+        None,
+    )?);
+
+    Ok(HandleResponse {
+        messages: cos_msg,
+        log: vec![],
+        data: Some(Binary(
+            to_bid_answer(&HandleAnswer::RetractBid {
+                status: Success,
+                message: "Commitment retracted.  Your deposit has been returned".to_string(),
+                amount_returned: sent,
+                bid_decimals: sent.map(|_a| state.bid_decimals),
+            })?
+            .into_bytes(),
+        )),
+    })
+}
+
+/// Returns HandleResult
+///
+/// attempt to reclaim the calling address' bid if it has expired
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+fn try_reclaim_expired<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+) -> HandleResult {
+    let state: State = load(&deps.storage, CONFIG_KEY)?;
+    let bidder_raw = &deps.api.canonical_address(&env.message.sender)?;
+    if is_bidder(&deps.storage, bidder_raw.as_slice())? {
+        let bid: Option<Bid> = may_load(&deps.storage, bidder_raw.as_slice())?;
+        if let Some(found_bid) = bid {
+            if is_unexpired(&state, &found_bid, env.block.time) {
+                return Err(StdError::generic_err(
+                    "Your bid has not expired.  Use retract_bid to retract an active bid",
+                ));
+            }
+        }
+    }
+    let bidder = env.message.sender.clone();
+    retract_bid(deps, &env, state, bidder)
+}
+
+/// Returns HandleResult
+///
+/// removes the bidder's bid and returns the tokens
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - a reference to the Env of contract's environment
+/// * `state` - the auction state
+/// * `bidder` - address of the bidder whose bid should be returned
+fn retract_bid<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    mut state: State,
+    bidder: HumanAddr,
+) -> HandleResult {
+    let bidder_raw = &deps.api.canonical_address(&bidder)?;
+    let mut cos_msg = Vec::new();
+    let sent: Option<Uint128>;
+    let mut log_msg = String::new();
+    let status: ResponseStatus;
+    let bid_decimals = state.bid_decimals;
+    // if there was a active bid from this address, remove the bid and return tokens
+    if is_bidder(&deps.storage, bidder_raw.as_slice())? {
+        let bid: Option<Bid> = may_load(&deps.storage, bidder_raw.as_slice())?;
+        if let Some(old_bid) = bid {
+            remove(&mut deps.storage, bidder_raw.as_slice());
+            remove_bidder(&mut deps.storage, &mut state, bidder_raw.as_slice())?;
+            save(&mut deps.storage, CONFIG_KEY, &state)?;
+            forget_key_hash(&mut deps.storage, bidder_raw.as_slice())?;
+            let returned = escrowed(&state, &old_bid);
+            save_outcome(
+                &mut deps.storage,
+                env,
+                bidder_raw.as_slice(),
+                BidResult::Retracted,
+                &old_bid,
+                returned,
+                None,
+            )?;
+            status = Success;
+            log_msg.push_str("Bid retracted");
+            // a bid backed by an allowance never sent any tokens
+            if returned > 0 {
+                cos_msg.push(state.bid_contract.transfer_msg(
+                    &state.auction_addr,
+                    bidder.clone(),
+                    Uint128(returned),
+                )?);
+                sent = Some(Uint128(returned));
+                log_msg.push_str(".  Tokens have been returned");
+            } else {
+                sent = None;
+            }
+
+            // let factory know bid was retracted
+            let rem_bid_msg = FactoryHandleMsg::RemoveBidder {
+                index: state.index,
+                bidder,
+                callback_key: state.callback_key.clone(),
+            };
+            // perform callback
+            cos_msg.push(rem_bid_msg.to_cosmos_msg(
+                state.factory.code_hash,
+                state.factory.address,
+                None,
+            )?);
+        } else {
+            status = Failure;
+            sent = None;
+            log_msg.push_str(&format!("No active bid for address: {}", bidder));
+        }
+    // no active bid found
+    } else {
+        status = Failure;
+        sent = None;
+        log_msg.push_str(&format!("No active bid for address: {}", bidder));
+    }
+    Ok(HandleResponse {
+        messages: cos_msg,
+        log: vec![],
+        data: Some(Binary(
+            to_bid_answer(&HandleAnswer::RetractBid {
+                status,
+                message: log_msg,
+                amount_returned: sent,
+                bid_decimals: sent.map(|_a| bid_decimals),
+            })?
+            .into_bytes(),
+        )),
+    })
+}
+
+/// Returns HandleResult
+///
+/// closes the auction and sends all the tokens in escrow to where they belong
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `new_ends_at` - optional epoch timestamp to extend closing time to if there are no bids
+/// * `new_minimum_bid` - optional minimum bid update if there are no bids
+fn try_finalize<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    new_ends_at: Option<u64>,
+    new_minimum_bid: Option<Uint128>,
+) -> HandleResult {
+    let mut state: State = load(&deps.storage, CONFIG_KEY)?;
+    // the factory finalizes expired auctions in batches, so an auction that can not close yet
+    // answers with a failure instead of reverting the finalize messages sent to other auctions
+    let from_factory = env.message.sender == state.factory.address;
+    if state.is_halted {
+        let message = "This auction has been halted by its factory".to_string();
+        return if from_factory {
+            finalize_deferred(message)
+        } else {
+            Err(StdError::generic_err(message))
+        };
+    }
+
+    // sealed bids can not be settled while they are still being revealed
+    if let Some(reveal_ends) = state.reveal_ends_at() {
+        if !state.is_completed && env.block.time >= state.ends_at && env.block.time < reveal_ends {
+            let message = format!(
+                "Sealed bids are being revealed until {}.  The auction can be finalized after that",
+                reveal_ends
+            );
+            return if from_factory {
+                finalize_deferred(message)
+            } else {
+                Err(StdError::generic_err(message))
+            };
+        }
+    }
+    let is_seller = is_seller_or_operator(&deps.api, &state, &env.message.sender)?;
+    let update_ends_at = new_ends_at.is_some();
+    let update_min_bid = new_minimum_bid.is_some();
+    // can not change minimum bid or closing time if not the owner
+    if !is_seller && (update_ends_at || update_min_bid) {
+        return Err(StdError::generic_err(
+            "Only the auction seller can change the closing time or the minimum bid",
+        ));
+    }
+    // if not the auction owner, can't finalize before the closing time
+    if !is_seller && (env.block.time < state.ends_at) {
+        return Err(StdError::generic_err(
+            "Only auction creator can finalize the sale before the closing time",
+        ));
+    }
+    let no_bids = !state.has_bids();
+    // if there are no active bids, and closer wants to extend the auction
+    if no_bids && !state.is_completed && (update_ends_at || update_min_bid) {
+        if let Some(ends_at) = new_ends_at {
+            state.ends_at = ends_at;
+        }
+        if let Some(minimum_bid) = new_minimum_bid {
+            state.minimum_bid = minimum_bid.u128();
+            if let Some(decay) = state.min_bid_decay.as_mut() {
+                decay.start = env.block.time;
+            }
+        }
+        save(&mut deps.storage, CONFIG_KEY, &state)?;
+        // register change with factory
+        let change_min_msg = FactoryHandleMsg::ChangeAuctionInfo {
+            index: state.index,
+            ends_at: new_ends_at,
+            minimum_bid: new_minimum_bid,
+            sell_amount: None,
+            callback_key: state.callback_key.clone(),
+        };
+        // perform factory callback
+        let factory_msg =
+            change_min_msg.to_cosmos_msg(state.factory.code_hash, state.factory.address, None)?;
+        let time_str = if update_ends_at { " closing time" } else { "" };
+        let bid_str = if update_min_bid { " minimum bid" } else { "" };
+        let and_str = if update_ends_at && update_min_bid {
+            " and"
+        } else {
+            ""
+        };
+        let message = format!(
+            "There were no active bids.  The{}{}{} has been updated",
+            time_str, and_str, bid_str
+        );
+        return Ok(HandleResponse {
+            messages: vec![factory_msg],
+            log: vec![],
+            data: Some(to_binary(&HandleAnswer::CloseAuction {
+                status: Failure,
+                message,
+                winning_bid: None,
+                winning_unit_price: None,
+                settlement: None,
+                unmet_min_bidders: None,
+                bid_decimals: None,
+                sell_tokens_received: None,
+                sell_decimals: None,
+                bid_tokens_received: None,
+                winning_bid_humanized: None,
+                sell_tokens_received_humanized: None,
+                bid_tokens_received_humanized: None,
+            })?),
+        });
+    }
+    // a sale finalized by someone other than the seller waits for the seller to accept the winner
+    if !is_seller
+        && !no_bids
+        && state.tokens_consigned
+        && !state.is_completed
+        && !state.lacks_min_bidders()
+    {
+        if let Some(window) = state.seller_confirmation {
+            match state.accept_by {
+                None => {
+                    let accept_by = env.block.time.saturating_add(window);
+                    state.accept_by = Some(accept_by);
+                    state.phase = SettlementPhase::Settling;
+                    save(&mut deps.storage, CONFIG_KEY, &state)?;
+                    return Ok(HandleResponse {
+                        messages: vec![],
+                        log: vec![],
+                        data: Some(to_binary(&HandleAnswer::CloseAuction {
+                            status: Failure,
+                            message: format!(
+                                "The auction is awaiting the seller's acceptance of the winning \
+                                 bid.  The seller has until {} to accept or reject it",
+                                accept_by
+                            ),
+                            winning_bid: None,
+                            winning_unit_price: None,
+                            settlement: None,
+                            unmet_min_bidders: None,
+                            bid_decimals: None,
+                            sell_tokens_received: None,
+                            sell_decimals: None,
+                            bid_tokens_received: None,
+                            winning_bid_humanized: None,
+                            sell_tokens_received_humanized: None,
+                            bid_tokens_received_humanized: None,
+                        })?),
+                    });
+                }
+                Some(accept_by) if env.block.time < accept_by => {
+                    let message = format!(
+                        "The seller has until {} to accept or reject the winning bid",
+                        accept_by
+                    );
+                    return if from_factory {
+                        finalize_deferred(message)
+                    } else {
+                        Err(StdError::generic_err(message))
+                    };
+                }
+                // the seller did not respond in time, so the sale goes through
+                Some(_) => (),
+            }
+        }
+    }
+    close_auction(deps, env, &mut state)
+}
+
+/// Returns HandleResult
+///
+/// answers a factory finalize that can not close the auction yet with a failure, so the rest of
+/// the factory's batch is still executed
+///
+/// # Arguments
+///
+/// * `message` - the reason the auction could not be finalized
+fn finalize_deferred(message: String) -> HandleResult {
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: Some(to_binary(&HandleAnswer::CloseAuction {
+            status: Failure,
+            message,
+            winning_bid: None,
+            winning_unit_price: None,
+            settlement: None,
+            unmet_min_bidders: None,
+            bid_decimals: None,
+            sell_tokens_received: None,
+            sell_decimals: None,
+            bid_tokens_received: None,
+            winning_bid_humanized: None,
+            sell_tokens_received_humanized: None,
+            bid_tokens_received_humanized: None,
+        })?),
+    })
+}
+
+/// Returns HandleResult
+///
+/// allows the seller to sell to the current highest bid before the closing time
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+fn try_accept_current_best<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+) -> HandleResult {
+    let mut state: State = load(&deps.storage, CONFIG_KEY)?;
+    if !is_seller_or_operator(&deps.api, &state, &env.message.sender)? {
+        return Err(StdError::generic_err(
+            "Only the auction seller can accept the current best bid",
+        ));
+    }
+    if state.is_completed {
+        return Err(StdError::generic_err("The auction has already closed"));
+    }
+    if state.is_halted {
+        return Err(StdError::generic_err(
+            "This auction has been halted by its factory",
+        ));
+    }
+    if env.block.time >= state.ends_at {
+        return Err(StdError::generic_err(
+            "The closing time has passed.  Use finalize to close the auction",
+        ));
+    }
+    if state.settlement != SettlementMode::HighestBid {
+        return Err(StdError::generic_err(
+            "Only an auction that sells to the highest bid can accept the current best bid",
+        ));
+    }
+    if !state.tokens_consigned {
+        return Err(StdError::generic_err(
+            "The tokens for sale must be consigned before a bid can be accepted",
+        ));
+    }
+    if state.num_bidders == 0 {
+        return Err(StdError::generic_err("There are no active bids to accept"));
+    }
+    if state.lacks_min_bidders() {
+        return Err(StdError::generic_err(format!(
+            "The auction needs at least {} bidders to sell",
+            state.min_bidders.unwrap_or_default()
+        )));
+    }
+    close_auction(deps, env, &mut state)
+}
+
+/// Returns HandleResult
+///
+/// allows the seller to sell to a chosen bid, even if it is not the highest
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `bidder` - address of the bidder whose bid is accepted
+fn try_accept_bid<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    bidder: HumanAddr,
+) -> HandleResult {
+    let mut state: State = load(&deps.storage, CONFIG_KEY)?;
+    if !is_seller_or_operator(&deps.api, &state, &env.message.sender)? {
+        return Err(StdError::generic_err(
+            "Only the auction seller can accept a bid",
+        ));
+    }
+    if state.is_completed {
+        return Err(StdError::generic_err("The auction has already closed"));
+    }
+    if state.is_halted {
+        return Err(StdError::generic_err(
+            "This auction has been halted by its factory",
+        ));
+    }
+    if state.settlement != SettlementMode::HighestBid {
+        return Err(StdError::generic_err(
+            "Only an auction that sells to the highest bid can accept a specific bid",
+        ));
+    }
+    if state.reveal_ends_at().map_or(false, |r| {
+        env.block.time >= state.ends_at && env.block.time < r
+    }) {
+        return Err(StdError::generic_err(
+            "A bid can not be accepted while sealed bids are being revealed",
+        ));
+    }
+    if !state.tokens_consigned {
+        return Err(StdError::generic_err(
+            "The tokens for sale must be consigned before a bid can be accepted",
+        ));
+    }
+    if bidder == state.seller {
+        return Err(StdError::generic_err(
+            "The seller can not accept their own bid",
+        ));
+    }
+    let bidder_raw = deps.api.canonical_address(&bidder)?;
+    let bid: Option<Bid> = if is_bidder(&deps.storage, bidder_raw.as_slice())? {
+        may_load(&deps.storage, bidder_raw.as_slice())?
+    } else {
+        None
+    };
+    let bid = bid
+        .filter(|b| is_unexpired(&state, b, env.block.time))
+        .ok_or_else(|| StdError::generic_err(format!("No active bid for address: {}", bidder)))?;
+    // a bid backed by an allowance can only be accepted if the bidder can still pay it
+    if let Some(key) = state.allowance_key.as_ref() {
+        if !can_pay(deps, &state, key, &bidder_raw, bid.amount, env.block.time)? {
+            return Err(StdError::generic_err(format!(
+                "The allowance or balance of {} no longer covers their bid",
+                bidder
+            )));
+        }
+    }
+    if state.lacks_min_bidders() {
+        return Err(StdError::generic_err(format!(
+            "The auction needs at least {} bidders to sell",
+            state.min_bidders.unwrap_or_default()
+        )));
+    }
+    state.accepted_bidder = Some(bidder_raw);
+    close_auction(deps, env, &mut state)
+}
+
+/// Returns HandleResult
+///
+/// allows the seller to return a particular bid and remove it from the auction
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `bidder` - address of the bidder whose bid is rejected
+/// * `reason` - reason for the rejection
+fn try_reject_bid<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    bidder: HumanAddr,
+    reason: String,
+) -> HandleResult {
+    let mut state: State = load(&deps.storage, CONFIG_KEY)?;
+    if !is_seller_or_operator(&deps.api, &state, &env.message.sender)? {
+        return Err(StdError::generic_err(
+            "Only the auction seller can reject a bid",
+        ));
+    }
+    if state.is_completed {
+        return Err(StdError::generic_err("The auction has already closed"));
+    }
+    // the seller must accept or reject the winning bid instead of refunding it
+    if state.accept_by.is_some() {
+        return Err(StdError::generic_err(
+            "Can not reject a bid while the winning bid awaits the seller's acceptance",
+        ));
+    }
+    if escaped_len(&reason)? > MAX_REASON_LEN {
+        return Err(StdError::generic_err(format!(
+            "A rejection reason must not be longer than {} bytes",
+            MAX_REASON_LEN
+        )));
+    }
+    let bidder_raw = deps.api.canonical_address(&bidder)?;
+    let bid: Option<Bid> = if is_bidder(&deps.storage, bidder_raw.as_slice())? {
+        may_load(&deps.storage, bidder_raw.as_slice())?
+    } else {
+        None
+    };
+    let bid =
+        bid.ok_or_else(|| StdError::generic_err(format!("No active bid for address: {}", bidder)))?;
+    remove(&mut deps.storage, bidder_raw.as_slice());
+    remove_bidder(&mut deps.storage, &mut state, bidder_raw.as_slice())?;
+    save(&mut deps.storage, CONFIG_KEY, &state)?;
+    forget_key_hash(&mut deps.storage, bidder_raw.as_slice())?;
+    let returned = escrowed(&state, &bid);
+    save_outcome(
+        &mut deps.storage,
+        &env,
+        bidder_raw.as_slice(),
+        BidResult::Rejected,
+        &bid,
+        returned,
+        Some(reason),
+    )?;
+    let mut messages = Vec::new();
+    // a bid backed by an allowance never sent any tokens
+    if returned > 0 {
+        messages.push(state.bid_contract.transfer_msg(
+            &state.auction_addr,
+            bidder.clone(),
+            Uint128(returned),
+        )?);
+    }
+    let message = format!("The bid from {} has been rejected and returned", bidder);
+    // let factory know the bid was removed
+    let rem_bid_msg = FactoryHandleMsg::RemoveBidder {
+        index: state.index,
+        bidder,
+        callback_key: state.callback_key.clone(),
+    };
+    messages.push(rem_bid_msg.to_cosmos_msg(
+        state.factory.code_hash,
+        state.factory.address,
+        None,
+    )?);
+
+    Ok(HandleResponse {
+        messages,
+        log: vec![],
+        data: Some(to_binary(&HandleAnswer::RejectBid {
+            status: Success,
+            message,
+        })?),
+    })
+}
+
+/// Returns HandleResult
+///
+/// allows the seller to pause or resume new bids
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `paused` - true if new bids should be paused
+fn try_pause_bidding<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    paused: bool,
+) -> HandleResult {
+    let mut state: State = load(&deps.storage, CONFIG_KEY)?;
+    if !is_seller_or_operator(&deps.api, &state, &env.message.sender)? {
+        return Err(StdError::generic_err(
+            "Only the auction seller can pause or resume bidding",
+        ));
+    }
+    if state.is_completed {
+        return Err(StdError::generic_err("The auction has already closed"));
+    }
+    state.bidding_paused = paused;
+    save(&mut deps.storage, CONFIG_KEY, &state)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: Some(to_binary(&HandleAnswer::PauseBidding {
+            status: Success,
+            bidding_paused: paused,
+        })?),
+    })
+}
+
+/// Returns HandleResult
+///
+/// allows the seller to accept or reject the winning bid of an auction that is awaiting the
+/// seller's confirmation.  Rejecting closes the auction without a sale
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `accept` - true if the winning bid is accepted
+fn try_respond_to_winner<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    accept: bool,
+) -> HandleResult {
+    let mut state: State = load(&deps.storage, CONFIG_KEY)?;
+    if !is_seller_or_operator(&deps.api, &state, &env.message.sender)? {
+        return Err(StdError::generic_err(
+            "Only the auction seller can accept or reject the winning bid",
+        ));
+    }
+    if state.is_completed {
+        return Err(StdError::generic_err("The auction has already closed"));
+    }
+    if state.is_halted {
+        return Err(StdError::generic_err(
+            "This auction has been halted by its factory",
+        ));
+    }
+    let accept_by = state.accept_by.ok_or_else(|| {
+        StdError::generic_err("The auction is not awaiting the seller's acceptance")
+    })?;
+    if env.block.time >= accept_by {
+        return Err(StdError::generic_err(format!(
+            "The window to accept or reject the winning bid ended at {}.  Finalizing the auction \
+             will complete the sale",
+            accept_by
+        )));
+    }
+    state.is_rejected = !accept;
+    close_auction(deps, env, &mut state)
+}
+
+/// Returns StdResult<bool>
+///
+/// true if the auction's allowance from a bidder covers their bid and has not expired, and the
+/// balance shown by the viewing key the bidder gave covers it as well.  A viewing key that no
+/// longer works counts as a balance that does not cover the bid
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `state` - reference to auction state
+/// * `key` - the auction's viewing key with the bid token
+/// * `bidder` - reference to the bidder's canonical address
+/// * `amount` - amount of the bid
+/// * `now` - current block time
+fn can_pay<S: ReadonlyStorage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    state: &State,
+    key: &str,
+    bidder: &CanonicalAddr,
+    amount: u128,
+    now: u64,
+) -> StdResult<bool> {
+    let human_bidder = deps.api.human_address(bidder)?;
+    let allowance = state.bid_contract.allowance_query(
+        &deps.querier,
+        human_bidder.clone(),
+        state.auction_addr.clone(),
+        key.to_string(),
+    )?;
+    if allowance.allowance.u128() < amount || allowance.expiration.map_or(false, |e| e <= now) {
+        return Ok(false);
+    }
+    let key_store = ReadonlyPrefixedStorage::new(PREFIX_BALANCE_KEYS, &deps.storage);
+    let may_key: Option<String> = may_load(&key_store, bidder.as_slice())?;
+    Ok(may_key.map_or(false, |k| {
+        state
+            .bid_contract
+            .balance_query(&deps.querier, human_bidder, k)
+            .map_or(false, |b| b.amount.u128() >= amount)
+    }))
+}
+
+/// Returns HandleResult
+///
+/// settles the auction by sending all the tokens in escrow to where they belong
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `state` - mutable reference to auction state
+fn close_auction<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    state: &mut State,
+) -> HandleResult {
+    let is_seller = env.message.sender == state.seller;
+    let no_bids = state.num_bidders == 0;
+    let mut cos_msg = Vec::new();
+    let mut update_state = false;
+    let mut winning_amount: Option<Uint128> = None;
+    let mut bid_decimals: Option<u8> = None;
+    let mut winner: Option<HumanAddr> = None;
+    let mut co_winners: Vec<HumanAddr> = Vec::new();
+    let mut referrer: Option<HumanAddr> = None;
+    let mut sell_tokens_received: Option<Uint128> = None;
+    let mut sell_decimals: Option<u8> = None;
+    let mut bid_tokens_received: Option<Uint128> = None;
+    let mut is_winner = false;
+    let mut is_loser = false;
+    let mut compensated = false;
+    // amount a crowdfund raised if it fell short of its target
+    let mut underfunded: Option<u128> = None;
+    // bidders whose outcomes are saved, who may have registered for a notification
+    let mut closed_bidders: Vec<Vec<u8>> = Vec::new();
+    if !state.is_completed {
+        // a copy of the state loaded before another settlement completed must not pay out again
+        let saved: State = load(&deps.storage, CONFIG_KEY)?;
+        if saved.phase == SettlementPhase::Completed {
+            return Err(StdError::generic_err(
+                "The auction has already been settled",
+            ));
+        }
+        // record that the settlement has started before any transfer is built
+        state.phase = SettlementPhase::Settling;
+        save(&mut deps.storage, CONFIG_KEY, &state)?;
+    }
+    // an auction with too few bidders returns every bid instead of selling
+    if !state.is_completed && state.lacks_min_bidders() {
+        state.too_few_bidders = true;
+    }
+
+    // if there were bids
+    if !no_bids {
+        // load all the bids
+        struct OwnedBid {
+            pub bidder: CanonicalAddr,
+            pub bid: Bid,
+        }
+        let mut bid_list: Vec<OwnedBid> = Vec::new();
+        for bidder in list_bidders(&deps.storage)? {
+            let bid: Option<Bid> = may_load(&deps.storage, bidder.as_slice())?;
+            if let Some(found_bid) = bid {
+                bid_list.push(OwnedBid {
+                    bidder: CanonicalAddr::from(bidder.as_slice()),
+                    bid: found_bid,
+                });
+            }
+        }
+        // bids that expired before the auction closed are not considered and will just be returned
+        let (mut bid_list, expired): (Vec<OwnedBid>, Vec<OwnedBid>) = bid_list
+            .into_iter()
+            .partition(|b| is_unexpired(state, &b.bid, env.block.time));
+        // bids that would have won if their allowance still covered them
+        let mut defaulted: Vec<OwnedBid> = Vec::new();
+        let can_sell = state.tokens_consigned
+            && !state.is_completed
+            && !state.is_force_closed
+            && !state.is_rejected
+            && !state.too_few_bidders;
+        // a funded crowdfund sells every bid a slice of the lot proportional to its size
+        if can_sell && state.settlement == SettlementMode::Crowdfund {
+            let mut raised = 0u128;
+            for backer in bid_list.iter() {
+                raised = checked_add(raised, backer.bid.amount)?;
+            }
+            if raised > 0 && raised >= state.funding_target.unwrap_or_default() {
+                cos_msg.push(state.bid_contract.transfer_msg(
+                    &state.auction_addr,
+                    state.seller.clone(),
+                    Uint128(raised),
+                )?);
+                // the largest backer is reported as the winner
+                bid_list.sort_by(|a, b| cmp_bids(state.tie_break, &b.bid, &a.bid));
+                let mut distributed = 0u128;
+                for (i, backer) in bid_list.iter().enumerate() {
+                    let slice =
+                        mul_div(state.sell_amount, backer.bid.amount, raised, Rounding::Down)?;
+                    distributed = checked_add(distributed, slice)?;
+                    let human_backer = deps.api.human_address(&backer.bidder)?;
+                    if slice > 0 {
+                        cos_msg.push(state.sell_contract.transfer_msg(
+                            &state.auction_addr,
+                            human_backer.clone(),
+                            Uint128(slice),
+                        )?);
+                    }
+                    if human_backer == env.message.sender {
+                        is_winner = true;
+                        sell_tokens_received = Some(Uint128(slice));
+                        sell_decimals = Some(state.sell_decimals);
+                    }
+                    remove(&mut deps.storage, &backer.bidder.as_slice());
+                    remove_bidder(&mut deps.storage, state, backer.bidder.as_slice())?;
+                    state.winner_memos.extend(backer.bid.memo.clone());
+                    save_outcome(
+                        &mut deps.storage,
+                        &env,
+                        backer.bidder.as_slice(),
+                        BidResult::Won,
+                        &backer.bid,
+                        0,
+                        None,
+                    )?;
+                    closed_bidders.push(backer.bidder.as_slice().to_vec());
+                    if i == 0 {
+                        winner = Some(human_backer);
+                        referrer = backer
+                            .bid
+                            .referrer
+                            .as_ref()
+                            .map(|r| deps.api.human_address(r))
+                            .transpose()?;
+                    } else {
+                        co_winners.push(human_backer);
+                    }
+                }
+                bid_list.clear();
+                // the rounding dust of the lot is returned to the seller with any excess below
+                state.currently_consigned = state.currently_consigned.saturating_sub(distributed);
+                winning_amount = Some(Uint128(raised));
+                if is_seller {
+                    bid_tokens_received = Some(Uint128(raised));
+                }
+                update_state = true;
+                state.winning_bid = raised;
+            } else {
+                underfunded = Some(raised);
+            }
+        }
+        // closing an auction that has been fully consigned
+        if can_sell && state.settlement != SettlementMode::Crowdfund {
+            // highest bid wins, ties are ordered so the preferred bid is popped first
+            bid_list.sort_by(|a, b| cmp_bids(state.tie_break, &a.bid, &b.bid));
+            // a bid the seller accepted wins regardless of its amount
+            if let Some(accepted) = state.accepted_bidder.as_ref() {
+                if let Some(pos) = bid_list.iter().position(|b| b.bidder == *accepted) {
+                    let accepted_bid = bid_list.remove(pos);
+                    bid_list.push(accepted_bid);
+                }
+            }
+            // a bidder whose allowance or balance no longer covers their bid can not win, so the
+            // next best bid does
+            if let Some(key) = state.allowance_key.as_ref() {
+                while let Some(best) = bid_list.last() {
+                    if can_pay(
+                        deps,
+                        state,
+                        key,
+                        &best.bidder,
+                        best.bid.amount,
+                        env.block.time,
+                    )? {
+                        break;
+                    }
+                    defaulted.extend(bid_list.pop());
+                }
+            }
+            // a raffle moves the drawn bid to the end, so it is popped as the winner
+            if state.settlement == SettlementMode::Raffle {
+                // the draw must not depend on the order the bidders were stored in
+                bid_list.sort_by(|a, b| a.bidder.as_slice().cmp(b.bidder.as_slice()));
+                let weights: Vec<u128> = bid_list.iter().map(|b| b.bid.amount).collect();
+                let seed = state.raffle_seed.as_deref().unwrap_or_default();
+                if let Some(drawn) = draw_raffle(seed, &env, &weights)? {
+                    let winning_bid = bid_list.remove(drawn);
+                    bid_list.push(winning_bid);
+                }
+            }
+            // if there was a winner, swap the tokens
+            if let Some(winning_bid) = bid_list.pop() {
+                let mut winners = vec![winning_bid];
+                // split the lot among all tied bids if it divides evenly, otherwise the earliest
+                // bid wins
+                if state.tie_break == TieBreak::ProRata && state.accepted_bidder.is_none() {
+                    let mut tied = Vec::new();
+                    while bid_list
+                        .last()
+                        .map_or(false, |b| b.bid.amount == winners[0].bid.amount)
+                    {
+                        tied.extend(bid_list.pop());
+                    }
+                    if state.sell_amount % (tied.len() as u128 + 1) == 0 {
+                        winners.append(&mut tied);
+                    } else {
+                        bid_list.append(&mut tied);
+                    }
+                }
+                let amount = winners[0].bid.amount;
+                let paid = checked_mul(amount, winners.len() as u128)?;
+                let (share, _) = split(state.sell_amount, winners.len())?;
+                // a bid backed by an allowance is only transferred from the winner now
+                let payment = if state.allowance_key.is_some() {
+                    state.bid_contract.transfer_from_msg(
+                        deps.api.human_address(&winners[0].bidder)?,
+                        state.seller.clone(),
+                        Uint128(paid),
+                    )?
+                } else {
+                    state.bid_contract.transfer_msg(
+                        &state.auction_addr,
+                        state.seller.clone(),
+                        Uint128(paid),
+                    )?
+                };
+                cos_msg.push(payment);
+                for (i, won) in winners.iter().enumerate() {
+                    let human_winner = deps.api.human_address(&won.bidder)?;
+                    cos_msg.push(state.sell_contract.transfer_msg(
+                        &state.auction_addr,
+                        human_winner.clone(),
+                        Uint128(share),
+                    )?);
+                    if human_winner == env.message.sender {
+                        is_winner = true;
+                        sell_tokens_received = Some(Uint128(share));
+                        sell_decimals = Some(state.sell_decimals);
+                    }
+                    remove(&mut deps.storage, &won.bidder.as_slice());
+                    remove_bidder(&mut deps.storage, state, won.bidder.as_slice())?;
+                    // the seller may still need the winner's memo to settle off chain
+                    state.winner_memos.extend(won.bid.memo.clone());
+                    save_outcome(
+                        &mut deps.storage,
+                        &env,
+                        won.bidder.as_slice(),
+                        BidResult::Won,
+                        &won.bid,
+                        0,
+                        None,
+                    )?;
+                    closed_bidders.push(won.bidder.as_slice().to_vec());
+                    if i == 0 {
+                        winner = Some(human_winner);
+                        referrer = won
+                            .bid
+                            .referrer
+                            .as_ref()
+                            .map(|r| deps.api.human_address(r))
+                            .transpose()?;
+                    } else {
+                        co_winners.push(human_winner);
+                    }
+                }
+                // send every bundled token to the winner as well (pro-rata does not allow bundles)
+                if let Some(human_winner) = winner.as_ref() {
+                    for bundled in state.bundle.iter_mut() {
+                        cos_msg.push(bundled.contract.transfer_msg(
+                            &state.auction_addr,
+                            human_winner.clone(),
+                            Uint128(bundled.amount),
+                        )?);
+                        bundled.currently_consigned = 0;
+                    }
+                }
+                winning_amount = Some(Uint128(amount));
+                if is_seller {
+                    bid_tokens_received = Some(Uint128(paid));
+                }
+                state.currently_consigned = 0;
+                update_state = true;
+                state.winning_bid = amount;
+            }
+        }
+        // if the sell tokens were never consigned, split the seller's deposit among the bidders
+        let mut share = 0u128;
+        let mut remainder = 0u128;
+        if !state.tokens_consigned
+            && !state.is_completed
+            && !state.is_force_closed
+            && state.deposit_posted > 0
+            && !bid_list.is_empty()
+        {
+            let (split_share, split_remainder) = split(state.deposit_posted, bid_list.len())?;
+            share = split_share;
+            remainder = split_remainder;
+            state.deposit_posted = 0;
+            compensated = true;
+        }
+        // loops through all remaining bids to return them to the bidders
+        for (losing_bid, compensation, result) in bid_list
+            .iter()
+            .map(|b| (b, share, BidResult::Lost))
+            .chain(expired.iter().map(|b| (b, 0, BidResult::Expired)))
+            .chain(defaulted.iter().map(|b| (b, 0, BidResult::Defaulted)))
+        {
+            let human_loser = deps.api.human_address(&losing_bid.bidder)?;
+            // any remainder of the split goes to the first bidder returned
+            let returned = checked_add(
+                checked_add(escrowed(state, &losing_bid.bid), compensation)?,
+                remainder,
+            )?;
+            remainder = 0;
+            if human_loser == env.message.sender {
+                is_loser = true;
+                // if the seller also placed a losing bid, add them
+                if returned > 0 {
+                    bid_tokens_received = Some(Uint128(checked_add(
+                        bid_tokens_received.map_or(0, |r| r.u128()),
+                        returned,
+                    )?));
+                    bid_decimals = Some(state.bid_decimals);
+                }
+            }
+            // a bid backed by an allowance has nothing to return unless it is compensated
+            if returned > 0 {
+                cos_msg.push(state.bid_contract.transfer_msg(
+                    &state.auction_addr,
+                    human_loser,
+                    Uint128(returned),
+                )?);
+            }
+            remove(&mut deps.storage, &losing_bid.bidder.as_slice());
+            update_state = true;
+            remove_bidder(&mut deps.storage, state, losing_bid.bidder.as_slice())?;
+            save_outcome(
+                &mut deps.storage,
+                &env,
+                losing_bid.bidder.as_slice(),
+                result,
+                &losing_bid.bid,
+                returned,
+                None,
+            )?;
+            closed_bidders.push(losing_bid.bidder.as_slice().to_vec());
+        }
+    }
+    // unrevealed commitments are forfeited to the seller once the reveal window has passed.  If
+    // the auction closes before then, they never had a chance to be revealed and are returned
+    let forfeit = state
+        .reveal_ends_at()
+        .map_or(false, |r| env.block.time >= r);
+    let mut forfeited = 0u128;
+    let committers = list_committers(&deps.storage)?;
+    for committer in committers.iter() {
+        remove_committer(&mut deps.storage, state, committer)?;
+        let mut commitments = PrefixedStorage::new(PREFIX_COMMITMENTS, &mut deps.storage);
+        let commitment: Option<Commitment> = may_load(&commitments, committer)?;
+        remove(&mut commitments, committer);
+        update_state = true;
+        if let Some(found) = commitment {
+            if forfeit {
+                forfeited = checked_add(forfeited, found.deposit)?;
+            } else {
+                let human_committer = deps
+                    .api
+                    .human_address(&CanonicalAddr::from(committer.as_slice()))?;
+                if human_committer == env.message.sender {
+                    bid_tokens_received = Some(Uint128(checked_add(
+                        bid_tokens_received.map_or(0, |r| r.u128()),
+                        found.deposit,
+                    )?));
+                    bid_decimals = Some(state.bid_decimals);
+                }
+                cos_msg.push(state.bid_contract.transfer_msg(
+                    &state.auction_addr,
+                    human_committer,
+                    Uint128(found.deposit),
+                )?);
+            }
+        }
+    }
+    if forfeited > 0 {
+        cos_msg.push(state.bid_contract.transfer_msg(
+            &state.auction_addr,
+            state.seller.clone(),
+            Uint128(forfeited),
+        )?);
+        if is_seller {
+            bid_tokens_received = Some(Uint128(checked_add(
+                bid_tokens_received.map_or(0, |r| r.u128()),
+                forfeited,
+            )?));
+            bid_decimals = Some(state.bid_decimals);
+        }
+    }
+    // return any tokens that have been consigned to the auction owner (can happen if owner
+    // finalized the auction before consigning the full sale amount or if there were no bids)
+    if state.currently_consigned > 0 {
+        cos_msg.push(state.sell_contract.transfer_msg(
+            &state.auction_addr,
+            state.seller.clone(),
+            Uint128(state.currently_consigned),
+        )?);
+        if is_seller {
+            sell_tokens_received = Some(Uint128(state.currently_consigned));
+            sell_decimals = Some(state.sell_decimals);
+        }
+        state.currently_consigned = 0;
+        update_state = true;
+    }
+    // return the seller's deposit if it was not used as compensation
+    if state.deposit_posted > 0 {
+        cos_msg.push(state.bid_contract.transfer_msg(
+            &state.auction_addr,
+            state.seller.clone(),
+            Uint128(state.deposit_posted),
+        )?);
+        if is_seller {
+            bid_tokens_received = Some(Uint128(checked_add(
+                bid_tokens_received.map_or(0, |r| r.u128()),
+                state.deposit_posted,
+            )?));
+            bid_decimals = Some(state.bid_decimals);
+        }
+        state.deposit_posted = 0;
+        update_state = true;
+    }
+    // return any bundled tokens that have been consigned
+    for bundled in state.bundle.iter_mut() {
+        if bundled.currently_consigned > 0 {
+            cos_msg.push(bundled.contract.transfer_msg(
+                &state.auction_addr,
+                state.seller.clone(),
+                Uint128(bundled.currently_consigned),
+            )?);
+            bundled.currently_consigned = 0;
+            update_state = true;
+        }
+    }
+    // mark that auction had ended
+    if !state.is_completed {
+        state.is_completed = true;
+        state.phase = SettlementPhase::Completed;
+        update_state = true;
+        // the factory stops sharing key changes once the auction closes
+        forget_all_key_hashes(&mut deps.storage)?;
+        remove(&mut deps.storage, STANDBY_KEY);
+        // let factory know
+        let close_msg = FactoryHandleMsg::CloseAuction {
+            index: state.index,
+            seller: state.seller.clone(),
+            bidder: winner,
+            winning_bid: winning_amount,
+            co_winners,
+            referrer,
+            unmet_min_bidders: state.min_bidders.filter(|_| state.too_few_bidders),
+            callback_key: state.callback_key.clone(),
+        }
+        .to_cosmos_msg(
+            state.factory.code_hash.clone(),
+            state.factory.address.clone(),
+            None,
+        )?;
+        cos_msg.push(close_msg);
+    }
+    if update_state {
+        save(&mut deps.storage, CONFIG_KEY, &state)?;
+    }
+
+    let mut log_msg = if winning_amount.is_some() {
+        bid_decimals = Some(state.bid_decimals);
+        let seller_msg = if is_seller {
+            ".  You have been sent the winning bid"
+        } else {
+            ""
+        };
+        let bidder_msg = if is_winner {
+            ".  Your bid won! You have been sent the sale token(s)"
+        } else if is_loser && state.allowance_key.is_some() {
+            ".  Your bid did not win"
+        } else if is_loser {
+            ".  Your bid did not win and has been returned"
+        } else {
+            ""
+        };
+        let mode_msg = match state.settlement {
+            SettlementMode::Raffle => {
+                ".  The winner was drawn at random with odds weighted by bid size"
+            }
+            SettlementMode::Crowdfund => {
+                ".  The crowdfund met its target, and every bid has been sent its share of the lot"
+            }
+            SettlementMode::HighestBid if state.accepted_bidder.is_some() => {
+                ".  The seller accepted the winning bid"
+            }
+            SettlementMode::HighestBid => "",
+        };
+        format!(
+            "Sale has been finalized{}{}{}",
+            mode_msg, seller_msg, bidder_msg
+        )
+    } else {
+        let consign_msg = if no_bids && sell_tokens_received.is_some() {
+            ".  Consigned tokens have been returned because there were no active bids"
+        } else {
+            ""
+        };
+        let deposit_msg = if compensated {
+            ".  The seller's deposit has been split among the bidders because the tokens for sale \
+             were never consigned"
+        } else {
+            ""
+        };
+        let closed = if state.is_cancelled {
+            "Auction has been cancelled because the tokens for sale were not consigned by the \
+             consignment deadline.  All bids have been returned"
+                .to_string()
+        } else if state.is_rejected {
+            "The seller rejected the winning bid.  All bids and consigned tokens have been \
+             returned"
+                .to_string()
+        } else if state.too_few_bidders {
+            format!(
+                "Auction has been closed without a sale because it needed at least {} bidders.  \
+                 All bids and consigned tokens have been returned",
+                state.min_bidders.unwrap_or_default()
+            )
+        } else if let Some(raised) = underfunded {
+            format!(
+                "The crowdfund raised {} of its {} target.  All bids and consigned tokens have \
+                 been returned",
+                raised,
+                state.funding_target.unwrap_or_default()
+            )
+        } else if state.is_force_closed {
+            format!(
+                "Auction has been force closed without a sale because it was still open {} days \
+                 after its closing time.  All bids, consigned tokens, and any deposit have been \
+                 returned",
+                FORCE_FINALIZE_GRACE / (24 * 60 * 60)
+            )
+        } else {
+            "Auction has been closed".to_string()
+        };
+        format!("{}{}{}", closed, consign_msg, deposit_msg)
+    };
+    if forfeited > 0 {
+        log_msg.push_str(&format!(
+            ".  The deposits of {} unrevealed commitments have been forfeited to the seller",
+            committers.len()
+        ));
+    } else if !committers.is_empty() {
+        log_msg.push_str(".  The deposits of unrevealed commitments have been returned");
+    }
+    let log = notification_logs(
+        &mut deps.storage,
+        &state.auction_addr,
+        winning_amount,
+        &closed_bidders,
+    )?;
+
+    Ok(HandleResponse {
+        messages: cos_msg,
+        log,
+        data: Some(to_binary(&HandleAnswer::CloseAuction {
+            status: Success,
+            message: log_msg,
+            winning_bid: winning_amount,
+            winning_unit_price: winning_amount.and_then(|w| unit_price(state, w.u128())),
+            settlement: winning_amount.map(|_| state.settlement),
+            unmet_min_bidders: state.min_bidders.filter(|_| state.too_few_bidders),
+            bid_decimals,
+            sell_tokens_received,
+            sell_decimals,
+            bid_tokens_received,
+            winning_bid_humanized: humanize(winning_amount, state.bid_decimals),
+            sell_tokens_received_humanized: humanize(sell_tokens_received, state.sell_decimals),
+            bid_tokens_received_humanized: humanize(bid_tokens_received, state.bid_decimals),
+        })?),
+    })
+}
+
+/// Returns HandleResult
+///
+/// disqualifies the winning allowance bid of an auction that could not be settled with its winner,
+/// and promotes the next best bid.  The winner is only disqualified if the bid token shows their
+/// allowance or balance no longer covers the bid.  The ranking is saved the first time, so later
+/// retries continue down the same list
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+fn try_retry_settlement<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+) -> HandleResult {
+    let mut state: State = load(&deps.storage, CONFIG_KEY)?;
+    if !is_seller_or_operator(&deps.api, &state, &env.message.sender)? {
+        return Err(StdError::generic_err(
+            "Only the auction seller can retry the settlement",
+        ));
+    }
+    // escrowed bids are already held by the auction, so only a bid backed by an allowance can
+    // fail to settle
+    let key = state.allowance_key.clone().ok_or_else(|| {
+        StdError::generic_err(
+            "Only an auction with allowance bids can retry the settlement with the next bid",
+        )
+    })?;
+    if state.is_completed {
+        return Err(StdError::generic_err("The auction has already closed"));
+    }
+    if state.is_halted {
+        return Err(StdError::generic_err(
+            "This auction has been halted by its factory",
+        ));
+    }
+    // once the closing time has passed, no bids can be placed or retracted to change the ranking
+    if env.block.time < state.ends_at {
+        return Err(StdError::generic_err(format!(
+            "The settlement can not be retried before the closing time {}",
+            state.ends_at
+        )));
+    }
+    if !state.tokens_consigned {
+        return Err(StdError::generic_err(
+            "The tokens for sale have not been consigned, so there is no winner to settle with",
+        ));
+    }
+    if state.accept_by.is_some() {
+        return Err(StdError::generic_err(
+            "The winning bid awaits the seller's acceptance.  Use respond_to_winner to reject it",
+        ));
+    }
+    let ranked: Vec<Vec<u8>> = match may_load(&deps.storage, STANDBY_KEY)? {
+        Some(list) => list,
+        None => ranked_bidders(&deps.storage, &state, env.block.time)?,
+    };
+    // skip any bid that has been removed since the list was ranked
+    let mut standby = Vec::new();
+    for bidder in ranked.into_iter() {
+        if is_bidder(&deps.storage, &bidder)? {
+            standby.push(bidder);
+        }
+    }
+    if standby.is_empty() {
+        return Err(StdError::generic_err(
+            "There are no bids left to settle with.  Finalize the auction to close it",
+        ));
+    }
+    let defaulted_raw = standby.remove(0);
+    let bid: Bid = load(&deps.storage, &defaulted_raw)?;
+    // the winner is only disqualified if the token shows they can no longer pay
+    let winner_raw = CanonicalAddr::from(defaulted_raw.as_slice());
+    if can_pay(deps, &state, &key, &winner_raw, bid.amount, env.block.time)? {
+        return Err(StdError::generic_err(format!(
+            "The allowance and balance of {} still cover their bid.  Finalize the auction to \
+             settle with them",
+            deps.api.human_address(&winner_raw)?
+        )));
+    }
+    remove(&mut deps.storage, &defaulted_raw);
+    remove_bidder(&mut deps.storage, &mut state, &defaulted_raw)?;
+    forget_key_hash(&mut deps.storage, &defaulted_raw)?;
+    let returned = escrowed(&state, &bid);
+    save_outcome(
+        &mut deps.storage,
+        &env,
+        &defaulted_raw,
+        BidResult::Defaulted,
+        &bid,
+        returned,
+        None,
+    )?;
+    save(&mut deps.storage, STANDBY_KEY, &standby)?;
+    state.phase = SettlementPhase::Settling;
+    save(&mut deps.storage, CONFIG_KEY, &state)?;
+    let defaulted = deps
+        .api
+        .human_address(&CanonicalAddr::from(defaulted_raw.as_slice()))?;
+    let mut messages = Vec::new();
+    if returned > 0 {
+        messages.push(state.bid_contract.transfer_msg(
+            &state.auction_addr,
+            defaulted.clone(),
+            Uint128(returned),
+        )?);
+    }
+    // let factory know the bid was removed
+    let rem_bid_msg = FactoryHandleMsg::RemoveBidder {
+        index: state.index,
+        bidder: defaulted.clone(),
+        callback_key: state.callback_key.clone(),
+    };
+    messages.push(rem_bid_msg.to_cosmos_msg(
+        state.factory.code_hash.clone(),
+        state.factory.address.clone(),
+        None,
+    )?);
+    let promoted = standby
+        .first()
+        .map(|b| deps.api.human_address(&CanonicalAddr::from(b.as_slice())))
+        .transpose()?;
+    let message = match promoted.as_ref() {
+        Some(next) => format!(
+            "The winning bid from {} has been disqualified.  {} is now the winning bidder.  \
+             Finalize the auction to settle with them",
+            defaulted, next
+        ),
+        None => format!(
+            "The winning bid from {} has been disqualified.  No bids remain, so finalizing the \
+             auction will close it without a sale",
+            defaulted
+        ),
+    };
+
+    Ok(HandleResponse {
+        messages,
+        log: vec![],
+        data: Some(to_binary(&HandleAnswer::RetrySettlement {
+            status: Success,
+            message,
+            defaulted,
+            promoted,
+            standby: standby.len() as u32,
+        })?),
+    })
+}
+
+/// Returns StdResult<Vec<Vec<u8>>>
+///
+/// canonical addresses of the bidders whose bids have not expired, ranked in the order the
+/// auction would pick them as the winner
+///
+/// # Arguments
+///
+/// * `storage` - a reference to the auction's storage
+/// * `state` - a reference to the auction state
+/// * `now` - current block time
+fn ranked_bidders<S: ReadonlyStorage>(
+    storage: &S,
+    state: &State,
+    now: u64,
+) -> StdResult<Vec<Vec<u8>>> {
+    let mut bids: Vec<(Vec<u8>, Bid)> = Vec::new();
+    for bidder in list_bidders(storage)? {
+        let bid: Option<Bid> = may_load(storage, &bidder)?;
+        if let Some(found) = bid.filter(|b| is_unexpired(state, b, now)) {
+            bids.push((bidder, found));
+        }
+    }
+    // best bid first
+    bids.sort_by(|a, b| cmp_bids(state.tie_break, &b.1, &a.1));
+    Ok(bids.into_iter().map(|(bidder, _)| bidder).collect())
+}
+
+/// Returns HandleResult
+///
+/// cancels an auction whose seller did not consign the full lot by the consignment deadline,
+/// returning all bids and notifying the factory
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+fn try_cancel_unconsigned<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+) -> HandleResult {
+    let mut state: State = load(&deps.storage, CONFIG_KEY)?;
+
+    if state.is_completed {
+        return Err(StdError::generic_err("Auction has already been closed"));
+    }
+    let consign_by = state.consign_by.ok_or_else(|| {
+        StdError::generic_err("This auction does not have a consignment deadline")
+    })?;
+    if state.tokens_consigned {
+        return Err(StdError::generic_err(
+            "The tokens for sale have been consigned.  The auction can not be cancelled",
+        ));
+    }
+    if env.block.time <= consign_by {
+        return Err(StdError::generic_err(format!(
+            "The seller has until {} to consign the tokens for sale",
+            consign_by
+        )));
+    }
+    state.is_cancelled = true;
+    // closing an unconsigned auction returns the bids (with any deposit as compensation) and
+    // any partial consignment, and lets the factory know the auction has closed
+    close_auction(deps, env, &mut state)
+}
+
+/// Returns HandleResult
+///
+/// closes an auction that is still open long after its closing time without a sale, returning
+/// all bids, consigned tokens, and any deposit
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+fn try_force_close<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+) -> HandleResult {
+    let mut state: State = load(&deps.storage, CONFIG_KEY)?;
+
+    if state.is_completed {
+        return Err(StdError::generic_err("Auction has already been closed"));
+    }
+    // anyone may do what the factory could already have done with ForceFinalize
+    let allowed_at = state.ends_at.saturating_add(FORCE_FINALIZE_GRACE);
+    if env.block.time < allowed_at {
+        return Err(StdError::generic_err(format!(
+            "The auction can not be force closed until {}",
+            allowed_at
+        )));
+    }
+    state.is_force_closed = true;
+    // a halted auction must still be able to close
+    state.is_halted = false;
+    close_auction(deps, env, &mut state)
+}
+
+/// Returns HandleResult
+///
+/// returns any funds still held by a closed auction to their owners
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `page_size` - optional number of bid records to read before stopping
+fn try_return_all<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    page_size: Option<u32>,
+) -> HandleResult {
+    let mut state: State = load(&deps.storage, CONFIG_KEY)?;
+
+    // can only do a return_all if the auction is closed
+    if !state.is_completed {
+        return Err(StdError::generic_err(
+            "return_all can only be executed after the auction has ended",
+        ));
+    }
+    // nothing to do if a closed auction does not hold any funds
+    if !has_outstanding_balances(&state) {
+        return Ok(HandleResponse {
+            messages: vec![],
+            log: vec![],
+            data: Some(to_binary(&HandleAnswer::ReturnAll {
+                status: Success,
+                message: "There are no outstanding funds to return".to_string(),
+                bids_returned: 0,
+                bids_remaining: 0,
+                consignment_returned: false,
+                amounts: vec![],
+            })?),
+        });
+    }
+    let mut cos_msg = Vec::new();
+    let mut bid_tokens_returned = 0u128;
+    let mut bids_returned = 0u32;
+    let mut consignment_returned = false;
+    let mut amounts = Vec::new();
+
+    // return the remaining bids, resuming after the last record a previous call read
+    let cursor: Option<u32> = may_load(&deps.storage, REFUND_CURSOR_KEY)?;
+    let (page, resume) = bid_records_page(&deps.storage, cursor, page_size.unwrap_or(u32::MAX))?;
+    match resume {
+        Some(position) => save(&mut deps.storage, REFUND_CURSOR_KEY, &position)?,
+        None => remove(&mut deps.storage, REFUND_CURSOR_KEY),
+    }
+    for bidder in page.into_iter().map(|r| r.bidder) {
+        remove_bidder(&mut deps.storage, &mut state, &bidder)?;
+        let bid: Option<Bid> = may_load(&deps.storage, &bidder)?;
+        if let Some(found_bid) = bid {
+            let returned = escrowed(&state, &found_bid);
+            if returned > 0 {
+                cos_msg.push(
+                    state.bid_contract.transfer_msg(
+                        &state.auction_addr,
+                        deps.api
+                            .human_address(&CanonicalAddr::from(bidder.as_slice()))?,
+                        Uint128(returned),
+                    )?,
+                );
+            }
+            bid_tokens_returned = checked_add(bid_tokens_returned, returned)?;
+            bids_returned += 1;
+            save_outcome(
+                &mut deps.storage,
+                &env,
+                &bidder,
+                BidResult::Lost,
+                &found_bid,
+                returned,
+                None,
+            )?;
+        }
+        remove(&mut deps.storage, &bidder);
+    }
+    // return the seller's deposit
+    if state.deposit_posted > 0 {
+        cos_msg.push(state.bid_contract.transfer_msg(
+            &state.auction_addr,
+            state.seller.clone(),
+            Uint128(state.deposit_posted),
+        )?);
+        bid_tokens_returned = checked_add(bid_tokens_returned, state.deposit_posted)?;
+        state.deposit_posted = 0;
+    }
+    if bid_tokens_returned > 0 {
+        amounts.push(ReturnedAmount {
+            contract_address: state.bid_contract.address.clone(),
+            amount: Uint128(bid_tokens_returned),
+            decimals: state.bid_decimals,
+        });
+    }
+    // return any consigned tokens to the seller
+    if state.currently_consigned > 0 {
+        cos_msg.push(state.sell_contract.transfer_msg(
+            &state.auction_addr,
+            state.seller.clone(),
+            Uint128(state.currently_consigned),
+        )?);
+        amounts.push(ReturnedAmount {
+            contract_address: state.sell_contract.address.clone(),
+            amount: Uint128(state.currently_consigned),
+            decimals: state.sell_decimals,
+        });
+        state.currently_consigned = 0;
+        consignment_returned = true;
+    }
+    for bundled in state.bundle.iter_mut() {
+        if bundled.currently_consigned > 0 {
+            cos_msg.push(bundled.contract.transfer_msg(
+                &state.auction_addr,
+                state.seller.clone(),
+                Uint128(bundled.currently_consigned),
+            )?);
+            amounts.push(ReturnedAmount {
+                contract_address: bundled.contract.address.clone(),
+                amount: Uint128(bundled.currently_consigned),
+                decimals: bundled.decimals,
+            });
+            bundled.currently_consigned = 0;
+            consignment_returned = true;
+        }
+    }
+    save(&mut deps.storage, CONFIG_KEY, &state)?;
+
+    let consign_msg = if consignment_returned {
+        ".  Consigned tokens have been returned to the seller"
+    } else {
+        ""
+    };
+    let remaining_msg = if state.num_bidders > 0 {
+        format!(
+            ".  {} bid(s) remain, and will be returned by the next return_all",
+            state.num_bidders
+        )
+    } else {
+        String::new()
+    };
+    Ok(HandleResponse {
+        messages: cos_msg,
+        log: vec![],
+        data: Some(to_binary(&HandleAnswer::ReturnAll {
+            status: Success,
+            message: format!(
+                "Outstanding funds have been returned.  {} bid(s) have been returned{}{}",
+                bids_returned, consign_msg, remaining_msg
+            ),
+            bids_returned,
+            bids_remaining: state.num_bidders,
+            consignment_returned,
+            amounts,
+        })?),
+    })
+}
+
+/// Returns HandleResult
+///
+/// settles the auction by sending the lot to the seller of a complementary auction
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `recipient` - seller of the complementary auction
+/// * `received` - amount of bid tokens this auction's seller receives from the other auction
+fn try_match_swap<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    recipient: HumanAddr,
+    received: Uint128,
+) -> HandleResult {
+    let mut state: State = load(&deps.storage, CONFIG_KEY)?;
+    factory_only(&env, &state, "match this auction")?;
+    if state.is_completed {
+        return Err(StdError::generic_err("The auction has already closed"));
+    }
+    if !state.tokens_consigned {
+        return Err(StdError::generic_err(
+            "The tokens for sale have not been consigned",
+        ));
+    }
+    if state.has_bids() {
+        return Err(StdError::generic_err(
+            "An auction with active bids can not be matched",
+        ));
+    }
+    let minimum_bid = state.effective_minimum_bid(env.block.time);
+    if received.u128() < minimum_bid {
+        return Err(StdError::generic_err(format!(
+            "The matched amount {} is less than the minimum bid {}",
+            received, minimum_bid
+        )));
+    }
+    // send the lot to the other seller
+    let mut cos_msg = vec![state.sell_contract.transfer_msg(
+        &state.auction_addr,
+        recipient.clone(),
+        Uint128(state.currently_consigned),
+    )?];
+    for bundled in state.bundle.iter_mut() {
+        cos_msg.push(bundled.contract.transfer_msg(
+            &state.auction_addr,
+            recipient.clone(),
+            Uint128(bundled.currently_consigned),
+        )?);
+        bundled.currently_consigned = 0;
+    }
+    // return the seller's deposit
+    if state.deposit_posted > 0 {
+        cos_msg.push(state.bid_contract.transfer_msg(
+            &state.auction_addr,
+            state.seller.clone(),
+            Uint128(state.deposit_posted),
+        )?);
+        state.deposit_posted = 0;
+    }
+    state.currently_consigned = 0;
+    state.winning_bid = received.u128();
+    state.is_completed = true;
+    state.phase = SettlementPhase::Completed;
+    let winning_unit_price = unit_price(&state, state.winning_bid);
+    save(&mut deps.storage, CONFIG_KEY, &state)?;
+    // the factory stops sharing key changes once the auction closes
+    forget_all_key_hashes(&mut deps.storage)?;
+    // let factory know
+    cos_msg.push(
+        FactoryHandleMsg::CloseAuction {
+            index: state.index,
+            seller: state.seller,
+            bidder: Some(recipient),
+            winning_bid: Some(received),
+            co_winners: vec![],
+            referrer: None,
+            unmet_min_bidders: None,
+            callback_key: state.callback_key,
+        }
+        .to_cosmos_msg(state.factory.code_hash, state.factory.address, None)?,
+    );
+
+    Ok(HandleResponse {
+        messages: cos_msg,
+        log: vec![],
+        data: Some(to_binary(&HandleAnswer::CloseAuction {
+            status: Success,
+            message: "Auction has been settled with a complementary auction".to_string(),
+            winning_bid: Some(received),
+            winning_unit_price,
+            settlement: None,
+            unmet_min_bidders: None,
+            bid_decimals: Some(state.bid_decimals),
+            sell_tokens_received: None,
+            sell_decimals: None,
+            bid_tokens_received: None,
+            winning_bid_humanized: humanize(Some(received), state.bid_decimals),
+            sell_tokens_received_humanized: None,
+            bid_tokens_received_humanized: None,
+        })?),
+    })
+}
+
+/////////////////////////////////////// Query /////////////////////////////////////
+/// Returns QueryResult
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `msg` - QueryMsg passed in with the query call
+pub fn query<S: Storage, A: Api, Q: Querier>(deps: &Extern<S, A, Q>, msg: QueryMsg) -> QueryResult {
+    let response = match msg {
+        QueryMsg::AuctionInfo { lang, current_time } => try_query_info(deps, lang, current_time),
+        QueryMsg::ViewBid {
+            address,
+            viewing_key,
+        } => try_view_bid(deps, &address, viewing_key),
+        QueryMsg::PreviewBid {
+            address,
+            viewing_key,
+            amount,
+            current_time,
+        } => try_preview_bid(deps, &address, viewing_key, amount, current_time),
+        QueryMsg::HasBids {
+            address,
+            viewing_key,
+        } => try_has_bids(deps, &address, viewing_key),
+        QueryMsg::Operators {
+            address,
+            viewing_key,
+        } => try_operators(deps, &address, viewing_key),
+        QueryMsg::BidStats {
+            address,
+            viewing_key,
+        } => try_bid_stats(deps, &address, viewing_key),
+        QueryMsg::BidMemos {
+            address,
+            viewing_key,
+        } => try_bid_memos(deps, &address, viewing_key),
+        QueryMsg::ExportAuction {
+            address,
+            viewing_key,
+        } => try_export_auction(deps, &address, viewing_key),
+        QueryMsg::Status {} => try_status(deps),
+        QueryMsg::Messages {
+            address,
+            viewing_key,
+            before,
+            page_size,
+        } => try_view_messages(deps, &address, viewing_key, before, page_size),
+        QueryMsg::ViewOutcome {
+            address,
+            viewing_key,
+        } => try_view_outcome(deps, &address, viewing_key),
+        QueryMsg::WinProof {
+            address,
+            viewing_key,
+        } => try_win_proof(deps, &address, viewing_key),
+    };
+    pad_query_result(response, padding_block_size(&deps.storage))
+}
+
+/// Returns usize
+///
+/// the block size responses are padded to.  Auctions instantiated before the block size was
+/// configurable have none stored, and pad to the default BLOCK_SIZE.  If the stored size can not
+/// be read, BLOCK_SIZE is used as well, so a response is never left unpadded.  Instantiation
+/// rejects anything smaller than BLOCK_SIZE, so this never pads less than the default
+///
+/// # Arguments
+///
+/// * `storage` - a reference to the contract's storage
+fn padding_block_size<S: ReadonlyStorage>(storage: &S) -> usize {
+    may_load::<u16, _>(storage, BLOCK_SIZE_KEY)
+        .ok()
+        .flatten()
+        .map_or(BLOCK_SIZE, usize::from)
+}
+
+/// Returns StdResult<bool>
+///
+/// validates an address' viewing key against the cache of hashes the factory shared with the
+/// auction, and falls back to querying the factory if the key's hash is not cached for the
+/// address' latest key epoch
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `state` - a reference to the auction State
+/// * `address` - a reference to the address whose viewing key is being validated
+/// * `viewing_key` - String holding the viewing key
+pub fn is_key_valid<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    state: &State,
+    address: &HumanAddr,
+    viewing_key: String,
+) -> StdResult<bool> {
+    let address_raw = deps.api.canonical_address(address)?;
+    let epoch_key = key_epoch_key(&deps.storage, address_raw.as_slice())?;
+    let epoch_store = ReadonlyPrefixedStorage::new(PREFIX_KEY_EPOCHS, &deps.storage);
+    let may_latest: Option<u32> = may_load(&epoch_store, &epoch_key)?;
+    if let Some(latest) = may_latest {
+        let key_hash = derive_key_hash(&viewing_key, &state.auction_addr);
+        let cache = ReadonlyPrefixedStorage::new(PREFIX_KEY_CACHE, &deps.storage);
+        let cached: Option<u32> = may_load(&cache, &[&epoch_key[..], &key_hash[..]].concat())?;
+        if cached == Some(latest) {
+            return Ok(true);
+        }
+    }
+    let key_valid_msg = FactoryQueryMsg::IsKeyValid {
+        address: address.clone(),
+        viewing_key,
+    };
+    let key_valid_response: IsKeyValidWrapper = key_valid_msg.query(
+        &deps.querier,
+        state.factory.code_hash.clone(),
+        state.factory.address.clone(),
+    )?;
+    Ok(key_valid_response.is_key_valid.is_valid)
+}
+
+/// Returns StdResult<Vec<u8>>
+///
+/// storage key of an address' latest key epoch in the current cache generation.  Cached hashes
+/// are keyed by it followed by the hash
+///
+/// # Arguments
+///
+/// * `storage` - a reference to the contract's storage
+/// * `address_raw` - canonical address whose key epoch is being stored or read
+fn key_epoch_key<S: ReadonlyStorage>(storage: &S, address_raw: &[u8]) -> StdResult<Vec<u8>> {
+    let generation: u32 = may_load(storage, KEY_CACHE_GENERATION_KEY)?.unwrap_or(0);
+    let mut key = generation.to_le_bytes().to_vec();
+    key.extend_from_slice(address_raw);
+    Ok(key)
+}
+
+/// Returns StdResult<()>
+///
+/// forgets an address' cached viewing key hashes once the factory no longer lists the address
+/// as active in this auction, by forgetting the address' latest key epoch
+///
+/// # Arguments
+///
+/// * `storage` - a mutable reference to the contract's storage
+/// * `address_raw` - canonical address whose key hashes should be forgotten
+fn forget_key_hash<S: Storage>(storage: &mut S, address_raw: &[u8]) -> StdResult<()> {
+    let epoch_key = key_epoch_key(storage, address_raw)?;
+    let mut epoch_store = PrefixedStorage::new(PREFIX_KEY_EPOCHS, storage);
+    epoch_store.remove(&epoch_key);
+    Ok(())
+}
+
+/// Returns StdResult<()>
+///
+/// forgets every cached viewing key hash by starting a new cache generation
+///
+/// # Arguments
+///
+/// * `storage` - a mutable reference to the contract's storage
+fn forget_all_key_hashes<S: Storage>(storage: &mut S) -> StdResult<()> {
+    let generation: u32 = may_load(storage, KEY_CACHE_GENERATION_KEY)?.unwrap_or(0);
+    save(
+        storage,
+        KEY_CACHE_GENERATION_KEY,
+        &generation.wrapping_add(1),
+    )
+}
+
+/// Returns Vec<u8>
+///
+/// derives the hash the factory shares with an auction from a viewing key.  It is the sha256
+/// hash of the key's sha256 hash (which is what the factory stores) followed by the auction's
+/// address
+///
+/// # Arguments
+///
+/// * `viewing_key` - string slice of the viewing key
+/// * `auction` - a reference to the auction's address
+pub fn derive_key_hash(viewing_key: &str, auction: &HumanAddr) -> Vec<u8> {
+    let hashed_key = sha_256(viewing_key.as_bytes());
+    sha_256(&[&hashed_key[..], auction.0.as_bytes()].concat()).to_vec()
+}
+
+/// Returns QueryResult displaying the auction information
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `lang` - optional language code of the description to display
+/// * `current_time` - optional current time used to display a declining minimum bid
+fn try_query_info<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    lang: Option<String>,
+    current_time: Option<u64>,
+) -> QueryResult {
+    let mut state: State = load(&deps.storage, CONFIG_KEY)?;
+
+    // get sell token info
+    let sell_token_info = state.sell_contract.token_info_query(&deps.querier)?;
+    // get bid token info
+    let bid_token_info = state.bid_contract.token_info_query(&deps.querier)?;
+
+    // build status string
+    let status = if state.is_completed {
+        let locked = if has_outstanding_balances(&state) {
+            ", but found outstanding balances.  Please run either retract_bid to \
+                retrieve your non-winning bid, or return_all to return all outstanding bids/\
+                consignment."
+        } else {
+            ""
+        };
+        let closed = if state.is_cancelled {
+            "Cancelled"
+        } else if state.is_rejected {
+            "Rejected by the seller"
+        } else if state.too_few_bidders {
+            "Closed without enough bidders"
+        } else if state.is_force_closed {
+            "Force closed"
+        } else {
+            "Closed"
+        };
+        format!("{}{}", closed, locked)
+    } else if state.accept_by.is_some() {
+        "Awaiting the seller's acceptance of the winning bid".to_string()
+    } else {
+        let accepting = if state.bidding_paused {
+            "Bidding paused by the seller"
+        } else {
+            "Accepting bids"
+        };
+        let consign = if !state.tokens_consigned { " NOT" } else { "" };
+        format!(
+            "{}: Token(s) to be sold have{} been consigned to the auction",
+            accepting, consign
+        )
+    };
+
+    let winning_bid = if state.winning_bid == 0 {
+        None
+    } else {
+        Some(Uint128(state.winning_bid))
+    };
+
+    let ends_at = format!(
+        "{} UTC",
+        NaiveDateTime::from_timestamp(state.ends_at as i64, 0).format("%Y-%m-%d %H:%M:%S")
+    );
+
+    // get info of any bundled tokens
+    let bundle = if state.bundle.is_empty() {
+        None
+    } else {
+        let mut tokens = Vec::new();
+        for item in state.bundle {
+            tokens.push(BundleToken {
+                token: Token {
+                    token_info: item.contract.token_info_query(&deps.querier)?,
+                    contract_address: item.contract.address,
+                },
+                amount: Uint128(item.amount),
+            });
+        }
+        Some(tokens)
+    };
+
+    // show the requested translation if there is one, otherwise the default description
+    let languages: Vec<String> = state.descriptions.keys().cloned().collect();
+    let (description, description_lang) =
+        match lang.and_then(|l| state.descriptions.remove_entry(&l)) {
+            Some((l, text)) => (Some(text), Some(l)),
+            None => (state.description, None),
+        };
+
+    let minimum_bid = current_time.map_or(state.minimum_bid, |t| state.effective_minimum_bid(t));
+    let minimum_unit_price = unit_price(&state, minimum_bid);
+
+    to_binary(&QueryAnswer::AuctionInfo {
+        sell_token: Token {
+            contract_address: state.sell_contract.address,
+            token_info: sell_token_info,
+        },
+        bid_token: Token {
+            contract_address: state.bid_contract.address,
+            token_info: bid_token_info,
+        },
+        sell_amount: Uint128(state.sell_amount),
+        minimum_bid: Uint128(minimum_bid),
+        min_bid_decay: state.min_bid_decay.as_ref().map(|d| MinBidDecayInfo {
+            floor: Uint128(d.floor),
+            step: Uint128(d.step),
+            interval: d.interval,
+            start: d.start,
+        }),
+        description,
+        description_lang,
+        languages,
+        auction_address: state.auction_addr,
+        ends_at,
+        ends_at_timestamp: state.ends_at,
+        consign_by: state.consign_by,
+        buy_now_price: state.buy_now_price.map(Uint128),
+        seller_confirmation: state.seller_confirmation,
+        accept_by: if state.is_completed {
+            None
+        } else {
+            state.accept_by
+        },
+        bidding_paused: state.bidding_paused,
+        settlement_phase: state.phase,
+        no_self_bid: state.no_self_bid,
+        only_increasing_rebids: state.only_increasing_rebids,
+        allowance_bids: state.allowance_key.is_some(),
+        message_board: state.message_board,
+        unit_price_bids: state.unit_price_bids,
+        minimum_unit_price,
+        max_bidders: state.max_bidders,
+        bidder_slots_remaining: state
+            .max_bidders
+            .map(|m| m.saturating_sub(state.bidder_count())),
+        min_bidders: state.min_bidders,
+        reveal_window: state.reveal_window,
+        status,
+        winning_bid,
+        bundle,
+        seller_deposit: if state.deposit_posted > 0 {
+            Some(Uint128(state.deposit_posted))
+        } else {
+            None
+        },
+        tie_break: state.tie_break,
+        settlement: state.settlement,
+        funding_target: state.funding_target.map(Uint128),
+        factory_index: state.index,
+        factory_address: state.factory.address.clone(),
+        factory_info: FactoryInfo {
+            code_hash: state.factory.code_hash,
+            address: state.factory.address,
+        },
+        deprecation_notice: state.deprecation_notice,
+    })
+}
+
+/// Returns QueryResult displaying the internal state flags of the auction
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+fn try_status<S: Storage, A: Api, Q: Querier>(deps: &Extern<S, A, Q>) -> QueryResult {
+    let state: State = load(&deps.storage, CONFIG_KEY)?;
+
+    to_binary(&QueryAnswer::Status {
+        is_completed: state.is_completed,
+        is_cancelled: state.is_cancelled,
+        is_force_closed: state.is_force_closed,
+        is_rejected: state.is_rejected,
+        awaiting_acceptance: !state.is_completed && state.accept_by.is_some(),
+        is_halted: state.is_halted,
+        settlement_phase: state.phase,
+        tokens_consigned: state.tokens_consigned,
+        deposit_posted: state.deposit > 0 && state.deposit_posted >= state.deposit,
+        bidders: state.num_bidders,
+        outstanding_balances: state.is_completed && has_outstanding_balances(&state),
+        contract_version: env!("CARGO_PKG_VERSION").to_string(),
+        factory_index: state.index,
+        factory_info: FactoryInfo {
+            code_hash: state.factory.code_hash,
+            address: state.factory.address,
+        },
+    })
+}
+
+/// Returns bool indicating whether the auction still holds any bids, consigned tokens, or
+/// deposit.  Once the auction is closed, these are balances that should have been returned
+///
+/// # Arguments
+///
+/// * `state` - a reference to the auction state
+fn has_outstanding_balances(state: &State) -> bool {
+    state.has_bids()
+        || state.currently_consigned > 0
+        || state.deposit_posted > 0
+        || state.bundle.iter().any(|i| i.currently_consigned > 0)
+}
+
+/// Returns Ordering
+///
+/// compares two bids so that the bid that should win is the greater one
+///
+/// # Arguments
+///
+/// * `tie_break` - policy used to pick the winner when the bids are tied
+/// * `a` - a reference to the first bid
+/// * `b` - a reference to the second bid
+fn cmp_bids(tie_break: TieBreak, a: &Bid, b: &Bid) -> Ordering {
+    let by_time = if tie_break == TieBreak::LatestWins {
+        a.timestamp.cmp(&b.timestamp)
+    } else {
+        b.timestamp.cmp(&a.timestamp)
+    };
+    a.amount.cmp(&b.amount).then(by_time)
+}
+
+/// Returns StdResult<Option<usize>>
+///
+/// draws the position of a raffle's winning bid, with odds proportional to each bid's weight.
+/// The draw mixes the secret seed the factory gave the auction with the block it closes in, so
+/// bidders can not predict it
+///
+/// # Arguments
+///
+/// * `seed` - secret seed from the factory's prng
+/// * `env` - a reference to the Env of contract's environment
+/// * `weights` - weight of each bid in the draw
+fn draw_raffle(seed: &[u8], env: &Env, weights: &[u128]) -> StdResult<Option<usize>> {
+    let mut total = 0u128;
+    for weight in weights.iter() {
+        total = checked_add(total, *weight)?;
+    }
+    if total == 0 {
+        return Ok(None);
+    }
+    let hash = sha_256(
+        &[
+            seed,
+            &env.block.height.to_be_bytes(),
+            &env.block.time.to_be_bytes(),
+            env.block.chain_id.as_bytes(),
+            env.contract.address.0.as_bytes(),
+        ]
+        .concat(),
+    );
+    let mut bytes = [0u8; 16];
+    bytes.copy_from_slice(&hash[..16]);
+    let mut ticket = u128::from_be_bytes(bytes) % total;
+    for (i, weight) in weights.iter().enumerate() {
+        if ticket < *weight {
+            return Ok(Some(i));
+        }
+        ticket -= weight;
+    }
+    Ok(None)
+}
+
+/// Returns bool
+///
+/// true if a bid had not expired when the auction closed, or has not expired yet if the auction
+/// has not reached its closing time.  Judging bids at the closing time means a bid that was
+/// active when bidding ended stays in the running no matter when the auction is finalized
+///
+/// # Arguments
+///
+/// * `state` - a reference to the auction state
+/// * `bid` - a reference to the bid
+/// * `now` - current block time
+fn is_unexpired(state: &State, bid: &Bid, now: u64) -> bool {
+    bid.expiration.map_or(true, |e| e > now.min(state.ends_at))
+}
+
+/// Returns u128
+///
+/// amount of bid tokens the auction holds in escrow for a bid, which is 0 if bids are backed by
+/// allowances
+///
+/// # Arguments
+///
+/// * `state` - a reference to the auction state
+/// * `bid` - a reference to the bid
+fn escrowed(state: &State, bid: &Bid) -> u128 {
+    if state.allowance_key.is_some() {
+        0
+    } else {
+        bid.amount
+    }
+}
+
+/// Returns StdResult<usize>
+///
+/// length of the text in bytes once it has been escaped as a JSON string, without its quotes
+///
+/// # Arguments
+///
+/// * `text` - the text to measure
+fn escaped_len(text: &str) -> StdResult<usize> {
+    let escaped = serde_json::to_string(text).map_err(|e| StdError::generic_err(e.to_string()))?;
+    // the serialized string includes its quotes
+    Ok(escaped.len() - 2)
+}
+
+/// Returns StdResult<()>
+///
+/// checks that a bid memo is short enough to be included in a padded bid answer
+///
+/// # Arguments
+///
+/// * `memo` - optional memo attached to a bid
+fn check_memo(memo: Option<&str>) -> StdResult<()> {
+    if let Some(text) = memo {
+        if escaped_len(text)? > MAX_MEMO_LEN {
+            return Err(StdError::generic_err(format!(
+                "A bid memo must not be longer than {} bytes",
+                MAX_MEMO_LEN
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Returns StdResult<bool>
+///
+/// true if the auction already has as many bidders as it allows and the address is not one of
+/// them.  Existing bidders may always replace their bids
+///
+/// # Arguments
+///
+/// * `storage` - a reference to the auction's storage
+/// * `api` - a reference to the Api used to convert human and canonical addresses
+/// * `state` - a reference to the auction state
+/// * `bidder` - a reference to the address trying to bid
+fn is_bidder_cap_reached<S: ReadonlyStorage, A: Api>(
+    storage: &S,
+    api: &A,
+    state: &State,
+    bidder: &HumanAddr,
+) -> StdResult<bool> {
+    match state.max_bidders {
+        Some(cap) if state.bidder_count() >= cap => {
+            let bidder_raw = api.canonical_address(bidder)?.as_slice().to_vec();
+            Ok(!is_bidder(storage, &bidder_raw)? && !is_committer(storage, &bidder_raw)?)
+        }
+        _ => Ok(false),
+    }
+}
+
+/// Returns StdResult<Option<CanonicalAddr>>
+///
+/// checks that a bidder is not referring themselves and returns the referrer's canonical address
+///
+/// # Arguments
+///
+/// * `api` - a reference to the Api used to convert human and canonical addresses
+/// * `referrer` - optional address that referred the bidder
+/// * `bidder` - a reference to the bidder's address
+fn check_referrer<A: Api>(
+    api: &A,
+    referrer: Option<&HumanAddr>,
+    bidder: &HumanAddr,
+) -> StdResult<Option<CanonicalAddr>> {
+    if referrer == Some(bidder) {
+        return Err(StdError::generic_err("A bidder can not refer themselves"));
+    }
+    referrer.map(|r| api.canonical_address(r)).transpose()
+}
+
+/// Returns Option<String>
+///
+/// formats an amount in the token's smallest denomination as a decimal string, dropping any
+/// trailing zeros after the decimal point
+///
+/// # Arguments
+///
+/// * `amount` - optional amount in the token's smallest denomination
+/// * `decimals` - number of decimal places the token uses
+pub fn humanize(amount: Option<Uint128>, decimals: u8) -> Option<String> {
+    amount.map(|a| {
+        let raw = a.u128().to_string();
+        let places = decimals as usize;
+        if places == 0 {
+            return raw;
+        }
+        let padded = format!("{:0>width$}", raw, width = places + 1);
+        let (whole, fraction) = padded.split_at(padded.len() - places);
+        let fraction = fraction.trim_end_matches('0');
+        if fraction.is_empty() {
+            whole.to_string()
+        } else {
+            format!("{}.{}", whole, fraction)
+        }
+    })
+}
+
+/// Returns Option<Uint128>
+///
+/// the price per whole sell token of a bid, if the auction takes unit price bids
+///
+/// # Arguments
+///
+/// * `state` - a reference to the auction state
+/// * `amount` - total amount of the bid
+fn unit_price(state: &State, amount: u128) -> Option<Uint128> {
+    if !state.unit_price_bids {
+        return None;
+    }
+    unit_price_for_total(amount, state.sell_amount, state.sell_decimals)
+        .ok()
+        .map(Uint128)
+}
+
+/// Returns StdResult<Uint128>
+///
+/// the total bid for a price per whole sell token, which must be covered by the tokens sent
+///
+/// # Arguments
+///
+/// * `state` - a reference to the auction state
+/// * `price` - price per whole sell token
+/// * `sent` - amount of bid tokens sent with the bid
+/// * `max_bid` - optional maximum bid, which can not be combined with a unit price
+fn unit_price_total(
+    state: &State,
+    price: Uint128,
+    sent: Uint128,
+    max_bid: Option<Uint128>,
+) -> StdResult<Uint128> {
+    if !state.unit_price_bids {
+        return Err(StdError::generic_err(
+            "This auction does not take unit price bids.  Your tokens have been returned",
+        ));
+    }
+    if max_bid.is_some() {
+        return Err(StdError::generic_err(
+            "A bid can specify either a unit price or a maximum bid, not both.  Your tokens have \
+             been returned",
+        ));
+    }
+    let total = total_for_unit_price(price.u128(), state.sell_amount, state.sell_decimals)?;
+    if total > sent.u128() {
+        return Err(StdError::generic_err(format!(
+            "The total cost of your unit price is {}, but only {} was sent.  Your tokens have \
+             been returned",
+            total, sent
+        )));
+    }
+    Ok(Uint128(total))
+}
+
+/// Returns StdResult<String>
+///
+/// serializes an answer that reveals the amount of a bid and pads it with spaces to
+/// BID_ANSWER_SIZE bytes
+///
+/// # Arguments
+///
+/// * `answer` - a reference to the answer to serialize
+pub fn to_bid_answer<T: Serialize>(answer: &T) -> StdResult<String> {
+    let mut json =
+        serde_json::to_string(answer).map_err(|e| StdError::generic_err(e.to_string()))?;
+    if json.len() < BID_ANSWER_SIZE {
+        json.push_str(&" ".repeat(BID_ANSWER_SIZE - json.len()));
+    }
+    Ok(json)
+}
+
+/// Returns HandleResult
+///
+/// returns bid tokens the auction can not accept right now with a Failure bid answer
+///
+/// # Arguments
+///
+/// * `state` - a reference to the auction state
+/// * `bidder` - address the tokens are returned to
+/// * `amount` - Uint128 amount returned
+/// * `message` - why the tokens were returned
+fn refuse_bid(state: &State, bidder: HumanAddr, amount: Uint128, message: &str) -> HandleResult {
+    let resp = to_bid_answer(&HandleAnswer::Bid {
+        status: Failure,
+        message: message.to_string(),
+        previous_bid: None,
+        minimum_bid: None,
+        amount_bid: None,
+        unit_price: None,
+        amount_returned: Some(amount),
+        bid_decimals: state.bid_decimals,
+        previous_bid_humanized: None,
+        minimum_bid_humanized: None,
+        amount_bid_humanized: None,
+        amount_returned_humanized: humanize(Some(amount), state.bid_decimals),
+    })?;
+
+    Ok(HandleResponse {
+        messages: vec![state
+            .bid_contract
+            .transfer_msg(&state.auction_addr, bidder, amount)?],
+        log: vec![log("response", resp)],
+        data: None,
+    })
+}
+
+/// Returns HandleResult
+///
+/// returns a sealed bid deposit the auction can not accept right now with a Failure commitment
+/// answer
+///
+/// # Arguments
+///
+/// * `state` - a reference to the auction state
+/// * `bidder` - address the tokens are returned to
+/// * `amount` - Uint128 amount returned
+/// * `message` - why the tokens were returned
+fn refuse_commit(state: &State, bidder: HumanAddr, amount: Uint128, message: &str) -> HandleResult {
+    let resp = to_bid_answer(&HandleAnswer::CommitBid {
+        status: Failure,
+        message: message.to_string(),
+        deposit: None,
+        amount_returned: Some(amount),
+        bid_decimals: state.bid_decimals,
+    })?;
+
+    Ok(HandleResponse {
+        messages: vec![state
+            .bid_contract
+            .transfer_msg(&state.auction_addr, bidder, amount)?],
+        log: vec![log("response", resp)],
+        data: None,
+    })
+}
+
+/// Returns HandleResult
+///
+/// returns sell or bundled tokens the auction can not accept as a consignment right now with a
+/// Failure consign answer
+///
+/// # Arguments
+///
+/// * `state` - a reference to the auction state
+/// * `owner` - address the tokens are returned to
+/// * `amount` - Uint128 amount returned
+/// * `item` - optional position of the bundled token that was sent, None for the sell token
+/// * `message` - why the tokens were returned
+fn refuse_consign(
+    state: &State,
+    owner: HumanAddr,
+    amount: Uint128,
+    item: Option<usize>,
+    message: &str,
+) -> HandleResult {
+    let (contract, consigned, decimals) = match item {
+        Some(i) => {
+            let bundled = &state.bundle[i];
+            (
+                &bundled.contract,
+                bundled.currently_consigned,
+                bundled.decimals,
+            )
+        }
+        None => (
+            &state.sell_contract,
+            state.currently_consigned,
+            state.sell_decimals,
+        ),
+    };
+    let resp = serde_json::to_string(&HandleAnswer::Consign {
+        status: Failure,
+        message: message.to_string(),
+        amount_consigned: Uint128(consigned),
+        amount_needed: None,
+        amount_returned: Some(amount),
+        sell_decimals: decimals,
+        amount_consigned_humanized: humanize(Some(Uint128(consigned)), decimals),
+        amount_needed_humanized: None,
+        amount_returned_humanized: humanize(Some(amount), decimals),
+    })
+    .unwrap();
+
+    Ok(HandleResponse {
+        messages: vec![contract.transfer_msg(&state.auction_addr, owner, amount)?],
+        log: vec![log("response", resp)],
+        data: None,
+    })
+}
+
+/// Returns HandleResult
+///
+/// returns bid tokens the auction can not accept as the seller's deposit with a Failure deposit
+/// answer
+///
+/// # Arguments
+///
+/// * `state` - a reference to the auction state
+/// * `sender` - address the tokens are returned to
+/// * `amount` - Uint128 amount returned
+/// * `message` - why the tokens were returned
+fn refuse_deposit(
+    state: &State,
+    sender: HumanAddr,
+    amount: Uint128,
+    message: &str,
+) -> HandleResult {
+    let resp = serde_json::to_string(&HandleAnswer::Deposit {
+        status: Failure,
+        message: message.to_string(),
+        amount_posted: Uint128(state.deposit_posted),
+        amount_needed: None,
+        amount_returned: Some(amount),
+        bid_decimals: state.bid_decimals,
+    })
+    .unwrap();
+
+    Ok(HandleResponse {
+        messages: vec![state
+            .bid_contract
+            .transfer_msg(&state.auction_addr, sender, amount)?],
+        log: vec![log("response", resp)],
+        data: None,
+    })
+}
+
+/// Returns HandleResult
+///
+/// returns sell tokens the auction can not add to the lot right now with a Failure increase lot
+/// answer
+///
+/// # Arguments
+///
+/// * `state` - a reference to the auction state
+/// * `owner` - address the tokens are returned to
+/// * `amount` - Uint128 amount returned
+/// * `message` - why the tokens were returned
+fn refuse_increase(
+    state: &State,
+    owner: HumanAddr,
+    amount: Uint128,
+    message: &str,
+) -> HandleResult {
+    let resp = serde_json::to_string(&HandleAnswer::IncreaseLot {
+        status: Failure,
+        message: message.to_string(),
+        sell_amount: Uint128(state.sell_amount),
+        amount_returned: Some(amount),
+        sell_decimals: state.sell_decimals,
+    })
+    .unwrap();
+
+    Ok(HandleResponse {
+        messages: vec![state
+            .sell_contract
+            .transfer_msg(&state.auction_addr, owner, amount)?],
+        log: vec![log("response", resp)],
+        data: None,
+    })
+}
+
+/// Returns StdResult<()>
+///
+/// saves how a bid ended so the bidder can still view it after the bid has been removed
+///
+/// # Arguments
+///
+/// * `storage` - mutable reference to the contract's storage
+/// * `env` - a reference to the Env of contract's environment
+/// * `bidder` - the bidder's canonical address as a byte slice
+/// * `result` - how the bid ended
+/// * `bid` - a reference to the bid that ended
+/// * `returned` - amount of bid tokens returned to the bidder
+/// * `reason` - reason the seller gave for rejecting the bid, if it was rejected
+fn save_outcome<S: Storage>(
+    storage: &mut S,
+    env: &Env,
+    bidder: &[u8],
+    result: BidResult,
+    bid: &Bid,
+    returned: u128,
+    reason: Option<String>,
+) -> StdResult<()> {
+    let mut outcomes = PrefixedStorage::new(PREFIX_OUTCOMES, storage);
+    save(
+        &mut outcomes,
+        bidder,
+        &Outcome {
+            result,
+            amount: bid.amount,
+            bid_timestamp: bid.timestamp,
+            returned,
+            block_height: env.block.height,
+            block_time: env.block.time,
+            reason,
+        },
+    )
+}
+
+/// Returns QueryResult displaying the bid information
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `bidder` - reference to address wanting to view its bid
+/// * `key` - String holding the viewing key
+fn try_view_bid<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    bidder: &HumanAddr,
+    key: String,
+) -> QueryResult {
+    let state: State = load(&deps.storage, CONFIG_KEY)?;
+    let key_valid = is_key_valid(deps, &state, bidder, key)?;
+
+    // if authenticated
+    if key_valid {
+        let decimals = state.bid_decimals;
+        let bidder_raw = &deps.api.canonical_address(bidder)?;
+        let mut amount_bid: Option<Uint128> = None;
+        let mut unit_price_bid: Option<Uint128> = None;
+        let mut bid_timestamp: Option<u64> = None;
+        let mut bid_expiration: Option<u64> = None;
+        let mut memo: Option<String> = None;
+        let mut message = String::new();
+        let status: ResponseStatus;
+
+        if is_bidder(&deps.storage, bidder_raw.as_slice())? {
+            let bid: Option<Bid> = may_load(&deps.storage, bidder_raw.as_slice())?;
+            if let Some(found_bid) = bid {
+                status = Success;
+                amount_bid = Some(Uint128(found_bid.amount));
+                unit_price_bid = unit_price(&state, found_bid.amount);
+                bid_timestamp = Some(found_bid.timestamp);
+                bid_expiration = found_bid.expiration;
+                memo = found_bid.memo;
+                message.push_str(&format!(
+                    "Bid placed {} UTC",
+                    NaiveDateTime::from_timestamp(found_bid.timestamp as i64, 0)
+                        .format("%Y-%m-%d %H:%M:%S")
+                ));
+            } else {
+                status = Failure;
+                message.push_str(&format!("No active bid for address: {}", bidder));
+            }
+        // a sealed commitment only becomes a bid once it is revealed
+        } else if is_committer(&deps.storage, bidder_raw.as_slice())? {
+            let commitments = ReadonlyPrefixedStorage::new(PREFIX_COMMITMENTS, &deps.storage);
+            let commitment: Option<Commitment> = may_load(&commitments, bidder_raw.as_slice())?;
+            status = Failure;
+            if let Some(found) = commitment {
+                bid_timestamp = Some(found.timestamp);
+                message.push_str(&format!(
+                    "Sealed commitment with a deposit of {} placed {} UTC.  It must be revealed \
+                     between the closing time and {} to become an active bid",
+                    found.deposit,
+                    NaiveDateTime::from_timestamp(found.timestamp as i64, 0)
+                        .format("%Y-%m-%d %H:%M:%S"),
+                    state.reveal_ends_at().unwrap_or_default()
+                ));
+            } else {
+                message.push_str(&format!("No active bid for address: {}", bidder));
+            }
+        // no active bid found
+        } else {
+            status = Failure;
+            message.push_str(&format!("No active bid for address: {}", bidder));
+        }
+        return Ok(Binary(
+            to_bid_answer(&QueryAnswer::Bid {
+                status,
+                message,
+                amount_bid,
+                unit_price: unit_price_bid,
+                bid_decimals: amount_bid.map(|_a| decimals),
+                bid_timestamp,
+                bid_expiration,
+                memo,
+            })?
+            .into_bytes(),
+        ));
+    }
+
+    to_binary(&QueryAnswer::ViewingKeyError {
+        error: "Wrong viewing key for this address or viewing key not set".to_string(),
+    })
+}
+
+/// Returns QueryResult displaying whether a bid would be accepted
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `bidder` - reference to address that would place the bid
+/// * `key` - String holding the viewing key
+/// * `amount` - Uint128 amount that would be bid
+/// * `current_time` - optional current time used to check the closing time and minimum bid
+fn try_preview_bid<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    bidder: &HumanAddr,
+    key: String,
+    amount: Uint128,
+    current_time: Option<u64>,
+) -> QueryResult {
+    let state: State = load(&deps.storage, CONFIG_KEY)?;
+    if !is_key_valid(deps, &state, bidder, key)? {
+        return to_binary(&QueryAnswer::ViewingKeyError {
+            error: "Wrong viewing key for this address or viewing key not set".to_string(),
+        });
+    }
+    let bidder_raw = &deps.api.canonical_address(bidder)?;
+    let current_bid: Option<Bid> = if is_bidder(&deps.storage, bidder_raw.as_slice())? {
+        may_load(&deps.storage, bidder_raw.as_slice())?
+    } else {
+        None
+    };
+    let minimum_bid = current_time.map_or(state.minimum_bid, |t| state.effective_minimum_bid(t));
+    let escrowed = state.allowance_key.is_none();
+    let rejection = check_bid(
+        deps,
+        &state,
+        bidder,
+        amount,
+        current_time,
+        minimum_bid,
+        current_bid.as_ref(),
+    )?
+    .map(|r| r.reason(&state))
+    .or_else(|| {
+        // an unchanged bid sent to escrow is returned, and the current bid is kept
+        current_bid
+            .as_ref()
+            .filter(|b| escrowed && amount.u128() == b.amount)
+            .map(|_| {
+                "Bid is the same as your current bid.  The bid tokens would be returned and your \
+                 current bid would keep its place in line"
+                    .to_string()
+            })
+    });
+    let (accepted, message, amount_returned) = match rejection {
+        Some(reason) => (false, reason, Some(amount).filter(|_| escrowed)),
+        None => {
+            let returned = current_bid
+                .as_ref()
+                .filter(|_| escrowed)
+                .map(|b| Uint128(b.amount));
+            let message = match (current_bid.is_some(), escrowed) {
+                (true, true) => "Bid would be accepted.  Your current bid would be returned",
+                (true, false) => "Bid would be accepted and replace your current bid",
+                (false, _) => "Bid would be accepted",
+            };
+            (true, message.to_string(), returned)
+        }
+    };
+
+    Ok(Binary(
+        to_bid_answer(&QueryAnswer::PreviewBid {
+            accepted,
+            message,
+            minimum_bid: Uint128(minimum_bid),
+            current_bid: current_bid.map(|b| Uint128(b.amount)),
+            amount_returned,
+            bid_decimals: state.bid_decimals,
+        })?
+        .into_bytes(),
+    ))
+}
+
+/// Returns QueryResult displaying how the address' last bid ended
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `bidder` - reference to address wanting to view its bid outcome
+/// * `key` - String holding the viewing key
+fn try_view_outcome<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    bidder: &HumanAddr,
+    key: String,
+) -> QueryResult {
+    let state: State = load(&deps.storage, CONFIG_KEY)?;
+    let key_valid = is_key_valid(deps, &state, bidder, key)?;
+
+    // if authenticated
+    if key_valid {
+        let bidder_raw = &deps.api.canonical_address(bidder)?;
+        let outcomes = ReadonlyPrefixedStorage::new(PREFIX_OUTCOMES, &deps.storage);
+        let may_outcome: Option<Outcome> = may_load(&outcomes, bidder_raw.as_slice())?;
+        let answer = match may_outcome {
+            Some(outcome) => {
+                let verb = match outcome.result {
+                    BidResult::Won => "won",
+                    BidResult::Lost => "did not win",
+                    BidResult::Retracted => "was retracted",
+                    BidResult::Expired => "expired",
+                    BidResult::Refunded => "was below the new minimum bid and was returned",
+                    BidResult::Defaulted => "was disqualified because its allowance was gone",
+                    BidResult::Rejected => "was rejected by the seller",
+                };
+                QueryAnswer::Outcome {
+                    status: Success,
+                    message: format!(
+                        "Bid {} {} UTC",
+                        verb,
+                        NaiveDateTime::from_timestamp(outcome.block_time as i64, 0)
+                            .format("%Y-%m-%d %H:%M:%S")
+                    ),
+                    result: Some(outcome.result),
+                    amount_bid: Some(Uint128(outcome.amount)),
+                    amount_returned: Some(Uint128(outcome.returned)),
+                    bid_decimals: Some(state.bid_decimals),
+                    block_height: Some(outcome.block_height),
+                    block_time: Some(outcome.block_time),
+                    reason: outcome.reason,
+                }
+            }
+            None => QueryAnswer::Outcome {
+                status: Failure,
+                message: format!("No bid outcome for address: {}", bidder),
+                result: None,
+                amount_bid: None,
+                amount_returned: None,
+                bid_decimals: None,
+                block_height: None,
+                block_time: None,
+                reason: None,
+            },
+        };
+        return Ok(Binary(to_bid_answer(&answer)?.into_bytes()));
+    }
+
+    to_binary(&QueryAnswer::ViewingKeyError {
+        error: "Wrong viewing key for this address or viewing key not set".to_string(),
+    })
+}
+
+/// Returns QueryResult displaying proof that the address won the auction
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `winner` - reference to address claiming to have won the auction
+/// * `key` - String holding the viewing key
+fn try_win_proof<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    winner: &HumanAddr,
+    key: String,
+) -> QueryResult {
+    let state: State = load(&deps.storage, CONFIG_KEY)?;
+    let key_valid = is_key_valid(deps, &state, winner, key)?;
+
+    // if authenticated
+    if key_valid {
+        let failure = |message: String| QueryAnswer::WinProof {
+            status: Failure,
+            message,
+            auction_address: None,
+            winner: None,
+            sell_amount: None,
+            winning_bid: None,
+            bid_decimals: None,
+            bid_timestamp: None,
+            closed_at: None,
+            commitment: None,
+        };
+        let answer = if !state.is_completed {
+            failure("The auction has not closed yet".to_string())
+        } else {
+            let winner_raw = &deps.api.canonical_address(winner)?;
+            let outcomes = ReadonlyPrefixedStorage::new(PREFIX_OUTCOMES, &deps.storage);
+            match may_load::<Outcome, _>(&outcomes, winner_raw.as_slice())?
+                .filter(|o| o.result == BidResult::Won)
+            {
+                Some(outcome) => {
+                    // commit to the fields the factory also records when the auction closes
+                    let preimage = format!(
+                        "{}:{}:{}:{}:{}",
+                        state.auction_addr,
+                        winner,
+                        state.sell_amount,
+                        state.winning_bid,
+                        outcome.block_time
+                    );
+                    QueryAnswer::WinProof {
+                        status: Success,
+                        message: format!("Address {} won this auction", winner),
+                        auction_address: Some(state.auction_addr),
+                        winner: Some(winner.clone()),
+                        sell_amount: Some(Uint128(state.sell_amount)),
+                        winning_bid: Some(Uint128(state.winning_bid)),
+                        bid_decimals: Some(state.bid_decimals),
+                        bid_timestamp: Some(outcome.bid_timestamp),
+                        closed_at: Some(outcome.block_time),
+                        commitment: Some(Binary::from(&sha_256(preimage.as_bytes())[..])),
+                    }
+                }
+                None => failure(format!("Address {} did not win this auction", winner)),
+            }
+        };
+        return Ok(Binary(to_bid_answer(&answer)?.into_bytes()));
+    }
+
+    to_binary(&QueryAnswer::ViewingKeyError {
+        error: "Wrong viewing key for this address or viewing key not set".to_string(),
+    })
+}
+
+/// Returns QueryResult displaying the presence of active bids
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `address` - a reference to the address claiming to be the seller
+/// * `viewing_key` - String holding the viewing key
+fn try_has_bids<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    address: &HumanAddr,
+    viewing_key: String,
+) -> QueryResult {
+    let state: State = load(&deps.storage, CONFIG_KEY)?;
+    let key_valid = is_key_valid(deps, &state, address, viewing_key)?;
+
+    // if authenticated
+    if state.seller == *address && key_valid {
+        return to_binary(&QueryAnswer::HasBids {
+            has_bids: state.has_bids(),
+            bid_count: state.num_bidders,
+            last_bid_time: state.last_bid_at,
+        });
+    }
+
+    to_binary(&QueryAnswer::ViewingKeyError {
+        error: "Address and/or viewing key does not match auction creator's information"
+            .to_string(),
+    })
+}
+
+/// Returns QueryResult displaying the auction's complete internal state to the seller
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `address` - a reference to the address claiming to be the seller
+/// * `viewing_key` - String holding the viewing key
+fn try_export_auction<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    address: &HumanAddr,
+    viewing_key: String,
+) -> QueryResult {
+    let state: State = load(&deps.storage, CONFIG_KEY)?;
+    let key_valid = is_key_valid(deps, &state, address, viewing_key)?;
+
+    // if authenticated
+    if state.seller == *address && key_valid {
+        let operators = human_operators(&deps.api, &state)?;
+        let winning_bid = if state.is_completed && state.winning_bid > 0 {
+            Some(Uint128(state.winning_bid))
+        } else {
+            None
+        };
+        return to_binary(&QueryAnswer::ExportAuction {
+            auction_address: state.auction_addr,
+            factory_index: state.index,
+            factory_info: FactoryInfo {
+                code_hash: state.factory.code_hash,
+                address: state.factory.address,
+            },
+            seller: state.seller,
+            operators,
+            sell_contract: state.sell_contract,
+            sell_decimals: state.sell_decimals,
+            bid_contract: state.bid_contract,
+            bid_decimals: state.bid_decimals,
+            sell_amount: Uint128(state.sell_amount),
+            currently_consigned: Uint128(state.currently_consigned),
+            tokens_consigned: state.tokens_consigned,
+            bundle: state
+                .bundle
+                .into_iter()
+                .map(|i| ConsignmentInfo {
+                    contract: i.contract,
+                    amount: Uint128(i.amount),
+                    decimals: i.decimals,
+                    currently_consigned: Uint128(i.currently_consigned),
+                })
+                .collect(),
+            minimum_bid: Uint128(state.minimum_bid),
+            min_bid_decay: state.min_bid_decay.map(|d| MinBidDecayInfo {
+                floor: Uint128(d.floor),
+                step: Uint128(d.step),
+                interval: d.interval,
+                start: d.start,
+            }),
+            deposit: Uint128(state.deposit),
+            deposit_posted: Uint128(state.deposit_posted),
+            buy_now_price: state.buy_now_price.map(Uint128),
+            ends_at: state.ends_at,
+            consign_by: state.consign_by,
+            seller_confirmation: state.seller_confirmation,
+            accept_by: state.accept_by,
+            bidder_count: state.num_bidders,
+            last_bid_at: state.last_bid_at,
+            winning_bid,
+            tie_break: state.tie_break,
+            settlement: state.settlement,
+            funding_target: state.funding_target.map(Uint128),
+            no_self_bid: state.no_self_bid,
+            only_increasing_rebids: state.only_increasing_rebids,
+            unit_price_bids: state.unit_price_bids,
+            max_bidders: state.max_bidders,
+            min_bidders: state.min_bidders,
+            too_few_bidders: state.too_few_bidders,
+            reveal_window: state.reveal_window,
+            unrevealed_commitments: state.num_committers,
+            message_board: state.message_board,
+            allowance_bids: state.allowance_key.is_some(),
+            is_completed: state.is_completed,
+            is_cancelled: state.is_cancelled,
+            is_force_closed: state.is_force_closed,
+            is_halted: state.is_halted,
+            is_rejected: state.is_rejected,
+            deprecation_notice: state.deprecation_notice,
+        });
+    }
+
+    to_binary(&QueryAnswer::ViewingKeyError {
+        error: "Address and/or viewing key does not match auction creator's information"
+            .to_string(),
+    })
+}
+
+/// Returns QueryResult displaying aggregate statistics of the active bids without revealing
+/// who placed them
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `address` - a reference to the address claiming to be the seller or an operator
+/// * `viewing_key` - String holding the viewing key
+fn try_bid_stats<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    address: &HumanAddr,
+    viewing_key: String,
+) -> QueryResult {
+    let state: State = load(&deps.storage, CONFIG_KEY)?;
+    let key_valid = is_key_valid(deps, &state, address, viewing_key)?;
+
+    // if authenticated
+    if key_valid && is_seller_or_operator(&deps.api, &state, address)? {
+        let mut amounts = Vec::new();
+        for bidder in list_bidders(&deps.storage)?.iter() {
+            let may_bid: Option<Bid> = may_load(&deps.storage, bidder)?;
+            if let Some(bid) = may_bid {
+                amounts.push(bid.amount);
+            }
+        }
+        amounts.sort_unstable();
+        let median = if amounts.is_empty() {
+            None
+        } else {
+            Some(amounts[(amounts.len() - 1) / 2])
+        };
+        let to_bucket = |a: Option<&u128>| a.map(|n| Uint128(bucket(*n)));
+        return to_binary(&QueryAnswer::BidStats {
+            bid_count: amounts.len() as u32,
+            min_bid: to_bucket(amounts.first()),
+            median_bid: to_bucket(median.as_ref()),
+            max_bid: to_bucket(amounts.last()),
+            bid_decimals: state.bid_decimals,
+            last_bid_at: state.last_bid_at,
+        });
+    }
+
+    to_binary(&QueryAnswer::ViewingKeyError {
+        error: "Address and/or viewing key does not match the auction seller or its operators"
+            .to_string(),
+    })
+}
+
+/// Returns QueryResult listing the memos attached to the active bids without revealing who
+/// sent them, and the memos of the winning bids once the auction has closed
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `address` - a reference to the address claiming to be the seller or an operator
+/// * `viewing_key` - String holding the viewing key
+fn try_bid_memos<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    address: &HumanAddr,
+    viewing_key: String,
+) -> QueryResult {
+    let state: State = load(&deps.storage, CONFIG_KEY)?;
+    let key_valid = is_key_valid(deps, &state, address, viewing_key)?;
+
+    // if authenticated
+    if key_valid && is_seller_or_operator(&deps.api, &state, address)? {
+        let mut memos = Vec::new();
+        for bidder in list_bidders(&deps.storage)?.iter() {
+            let may_bid: Option<Bid> = may_load(&deps.storage, bidder)?;
+            if let Some(memo) = may_bid.and_then(|b| b.memo) {
+                memos.push(memo);
+            }
+        }
+        // sorted so the order does not reveal which bidder sent which memo
+        memos.sort_unstable();
+        return to_binary(&QueryAnswer::BidMemos {
+            memos,
+            winner_memos: state.winner_memos,
+        });
+    }
+
+    to_binary(&QueryAnswer::ViewingKeyError {
+        error: "Address and/or viewing key does not match the auction seller or its operators"
+            .to_string(),
+    })
+}
+
+/// Returns u128
+///
+/// rounds an amount down to its two most significant digits so bid statistics do not reveal
+/// exact bids
+///
+/// # Arguments
+///
+/// * `amount` - amount to round
+fn bucket(amount: u128) -> u128 {
+    let mut scale = 1u128;
+    while amount / scale >= 100 {
+        scale *= 10;
+    }
+    amount / scale * scale
+}
+
+/// Returns QueryResult listing the seller's operators
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `address` - a reference to the address claiming to be the seller or an operator
+/// * `viewing_key` - String holding the viewing key
+fn try_operators<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    address: &HumanAddr,
+    viewing_key: String,
+) -> QueryResult {
+    let state: State = load(&deps.storage, CONFIG_KEY)?;
+    let key_valid = is_key_valid(deps, &state, address, viewing_key)?;
+
+    // if authenticated
+    if key_valid && is_seller_or_operator(&deps.api, &state, address)? {
+        return to_binary(&QueryAnswer::Operators {
+            operators: human_operators(&deps.api, &state)?,
+        });
+    }
+
+    to_binary(&QueryAnswer::ViewingKeyError {
+        error: "Address and/or viewing key does not match the auction seller or its operators"
+            .to_string(),
+    })
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::bidders::{active_records, PREFIX_BID_RECORDS};
+    use crate::msg::{BundleItem, ContractInfo};
+    use crate::state::BidRecord;
+    use cosmwasm_std::{
+        from_binary, testing::*, BlockInfo, CosmosMsg, MessageInfo, QuerierResult, QueryResponse,
+        StdResult, WasmMsg,
+    };
+    use secret_toolkit::storage::AppendStore;
+    use std::any::Any;
+
+    /// init msg of a plain 10 for 10 auction closing at 1000 that the unit tests override with
+    /// struct update syntax
+    pub(crate) fn default_init_msg() -> InitMsg {
+        let contract = |code_hash: &str, address: &str| ContractInfo {
+            code_hash: code_hash.to_string(),
+            address: HumanAddr(address.to_string()),
+            token_id: None,
+        };
+        InitMsg {
+            factory: contract("factoryhash", "factoryaddr"),
+            index: 0,
+            label: "auction".to_string(),
+            sell_decimals: 4,
+            bid_decimals: 8,
+            seller: HumanAddr("alice".to_string()),
+            sell_contract: contract("sellhash", "selladdr"),
+            bid_contract: contract("bidhash", "bidaddr"),
+            sell_amount: Uint128(10),
+            minimum_bid: Uint128(10),
+            ends_at: 1000,
+            description: None,
+            descriptions: vec![],
+            bundle: vec![],
+            deposit: None,
+            consign_by: None,
+            buy_now_price: None,
+            no_self_bid: false,
+            only_increasing_rebids: false,
+            tie_break: TieBreak::EarliestWins,
+            allowance_bids: false,
+            message_board: false,
+            callback_key: "callbackkey".to_string(),
+            block_size: None,
+            min_bid_decay: None,
+            seller_confirmation: None,
+            unit_price_bids: false,
+            max_bidders: None,
+            min_bidders: None,
+            reveal_window: None,
+            settlement: SettlementMode::HighestBid,
+            raffle_seed: None,
+            funding_target: None,
+        }
+    }
+
+    fn init_helper() -> (
+        StdResult<InitResponse>,
+        Extern<MockStorage, MockApi, MockQuerier>,
+    ) {
+        let mut deps = mock_dependencies(20, &[]);
+        let mut env = mock_env("factory", &[]);
+        env.block.time = 0;
+
+        (init(&mut deps, env, default_init_msg()), deps)
+    }
+
+    fn before_close_env(sender: &str) -> Env {
+        let mut env = mock_env(sender, &[]);
+        env.block.time = 500;
+        env
+    }
+
+    fn extract_error_msg<T: Any>(error: StdResult<T>) -> String {
+        match error {
+            Ok(response) => {
+                let bin_err = (&response as &dyn Any)
+                    .downcast_ref::<QueryResponse>()
+                    .expect("An error was expected, but no error could be extracted");
+                match from_binary(bin_err).unwrap() {
+                    QueryAnswer::ViewingKeyError { error } => error,
+                    _ => panic!("Unexpected query answer"),
+                }
+            }
+            Err(err) => match err {
+                StdError::GenericErr { msg, .. } => msg,
+                _ => panic!("Unexpected result from init"),
+            },
+        }
+    }
+
+    fn extract_log(resp: StdResult<HandleResponse>) -> String {
+        match resp {
+            Ok(response) => response.log[0].value.clone(),
+            Err(_err) => "These are not the logs you are looking for".to_string(),
+        }
+    }
+
+    fn extract_msg(resp: &StdResult<HandleResponse>) -> String {
+        let handle_answer: HandleAnswer =
+            from_binary(&resp.as_ref().unwrap().data.as_ref().unwrap()).unwrap();
+        match handle_answer {
+            HandleAnswer::Bid { message, .. } => message.clone(),
+            HandleAnswer::Consign { message, .. } => message.clone(),
+            HandleAnswer::CloseAuction { message, .. } => message.clone(),
+            HandleAnswer::RetractBid { message, .. } => message.clone(),
+            HandleAnswer::ReturnAll { message, .. } => message.clone(),
+            _ => panic!("Unexpected HandleAnswer"),
+        }
+    }
+
+    fn extract_finalize_fields(
+        resp: &StdResult<HandleResponse>,
+    ) -> (
+        String,
+        Option<Uint128>,
+        Option<u8>,
+        Option<Uint128>,
+        Option<u8>,
+        Option<Uint128>,
+    ) {
+        let handle_answer: HandleAnswer =
+            from_binary(&resp.as_ref().unwrap().data.as_ref().unwrap()).unwrap();
+        match handle_answer {
+            HandleAnswer::CloseAuction {
+                message,
+                winning_bid,
+                bid_decimals,
+                sell_tokens_received,
+                sell_decimals,
+                bid_tokens_received,
+                ..
+            } => (
+                message,
+                winning_bid,
+                bid_decimals,
+                sell_tokens_received,
+                sell_decimals,
+                bid_tokens_received,
+            ),
+            _ => panic!("Unexpected HandleAnswer"),
+        }
+    }
+
+    fn extract_amount_returned(resp: &StdResult<HandleResponse>) -> (Option<Uint128>, Option<u8>) {
+        let handle_answer: HandleAnswer =
+            from_binary(&resp.as_ref().unwrap().data.as_ref().unwrap()).unwrap();
+        match handle_answer {
+            HandleAnswer::RetractBid {
+                amount_returned,
+                bid_decimals,
+                ..
+            } => (amount_returned, bid_decimals),
+            _ => panic!("Unexpected HandleAnswer"),
+        }
+    }
+
+    #[test]
+    fn test_init_sanity() {
+        let (init_result, deps) = init_helper();
+        assert!(
+            init_result.is_ok(),
+            "Init failed: {}",
+            init_result.err().unwrap()
+        );
+        let state: State = load(&deps.storage, CONFIG_KEY).unwrap();
+        let factory = ContractInfo {
+            code_hash: "factoryhash".to_string(),
+            address: HumanAddr("factoryaddr".to_string()),
+            token_id: None,
+        };
+        let sell_contract = ContractInfo {
+            code_hash: "sellhash".to_string(),
+            address: HumanAddr("selladdr".to_string()),
+            token_id: None,
+        };
+        let bid_contract = ContractInfo {
+            code_hash: "bidhash".to_string(),
+            address: HumanAddr("bidaddr".to_string()),
+            token_id: None,
+        };
+
+        assert_eq!(factory, state.factory);
+        assert_eq!(HumanAddr("alice".to_string()), state.seller);
+        assert_eq!(sell_contract, state.sell_contract);
+        assert_eq!(4, state.sell_decimals);
+        assert_eq!(bid_contract, state.bid_contract);
+        assert_eq!(8, state.bid_decimals);
+        assert_eq!("callbackkey".to_string(), state.callback_key);
+        assert_eq!(10, state.sell_amount);
+        assert_eq!(10, state.minimum_bid);
+        assert_eq!(0, state.currently_consigned);
+        assert_eq!(state.num_bidders, 0);
+        assert_eq!(false, state.is_completed);
+        assert_eq!(false, state.tokens_consigned);
+        assert_eq!(1000, state.ends_at);
+        assert_eq!(None, state.description);
+        assert_eq!(0, state.winning_bid);
+    }
+
+    #[test]
+    fn test_init_ends_at_in_past() {
+        let mut deps = mock_dependencies(20, &[]);
+        let mut env = mock_env("factory", &[]);
+        env.block.time = 2000;
+
+        let init_result = init(&mut deps, env, default_init_msg());
+        let error = extract_error_msg(init_result);
+        assert!(error.contains("Closing time 1000 must be later than the current block time 2000"));
+    }
+
+    #[test]
+    fn test_init_block_size() {
+        let block_init = |block_size: Option<u16>| InitMsg {
+            block_size,
+            ..default_init_msg()
+        };
+
+        // a block size smaller than the default is rejected
+        for small in [0, BLOCK_SIZE as u16 - 1].iter() {
+            let mut deps = mock_dependencies(20, &[]);
+            let init_result = init(
+                &mut deps,
+                mock_env("factory", &[]),
+                block_init(Some(*small)),
+            );
+            let error = extract_error_msg(init_result);
+            assert!(error.contains("Block size must be at least 256"));
+        }
+
+        // responses are padded to the configured block size
+        let mut deps = mock_dependencies(20, &[]);
+        init(&mut deps, mock_env("factory", &[]), block_init(Some(1000))).unwrap();
+        let block_size: u16 = load(&deps.storage, BLOCK_SIZE_KEY).unwrap();
+        assert_eq!(block_size, 1000);
+        let query_result = query(&deps, QueryMsg::Status {}).unwrap();
+        assert_eq!(query_result.as_slice().len(), 1000);
+        let handle_msg = HandleMsg::Finalize {
+            new_ends_at: None,
+            new_minimum_bid: None,
+        };
+        let handle_result = handle(&mut deps, before_close_env("alice"), handle_msg).unwrap();
+        assert_eq!(handle_result.data.unwrap().as_slice().len() % 1000, 0);
+
+        // and default to BLOCK_SIZE
+        let mut deps = mock_dependencies(20, &[]);
+        init(&mut deps, mock_env("factory", &[]), block_init(None)).unwrap();
+        let block_size: u16 = load(&deps.storage, BLOCK_SIZE_KEY).unwrap();
+        assert_eq!(block_size as usize, BLOCK_SIZE);
+    }
+
+    #[test]
+    fn test_consign() {
+        let (init_result, mut deps) = init_helper();
+        assert!(
+            init_result.is_ok(),
+            "Init failed: {}",
+            init_result.err().unwrap()
+        );
+
+        // try to consign if not seller
+        let handle_msg = HandleMsg::Receive {
+            sender: HumanAddr("blah".to_string()),
+            from: HumanAddr("bob".to_string()),
+            amount: Uint128(2500),
+            msg: None,
+        };
+        let handle_result = handle(&mut deps, mock_env("selladdr", &[]), handle_msg);
+        let log = extract_log(handle_result);
+        assert!(log.contains("Only auction creator can consign tokens for sale"));
+        assert!(log.contains("\"status\":\"failure\""));
+        assert!(log.contains("\"amount_returned\":\"2500\""));
+
+        // try already consigned
+        let handle_msg = HandleMsg::Receive {
+            sender: HumanAddr("blah".to_string()),
+            from: HumanAddr("alice".to_string()),
+            amount: Uint128(2500),
+            msg: None,
+        };
+        let _handle_result = handle(&mut deps, mock_env("selladdr", &[]), handle_msg);
+        let handle_msg = HandleMsg::Receive {
+            sender: HumanAddr("blah".to_string()),
+            from: HumanAddr("alice".to_string()),
+            amount: Uint128(2500),
+            msg: None,
+        };
+        let handle_result = handle(&mut deps, mock_env("selladdr", &[]), handle_msg);
+        let log = extract_log(handle_result);
+        assert!(log.contains("Tokens to be sold have already been consigned."));
+        assert!(log.contains("\"status\":\"failure\""));
+        assert!(log.contains("\"amount_returned\":\"2500\""));
+
+        // try to consign after closing
+        let handle_msg = HandleMsg::Finalize {
+            new_ends_at: None,
+            new_minimum_bid: None,
+        };
+        let _used = handle(&mut deps, mock_env("alice", &[]), handle_msg);
+        let handle_msg = HandleMsg::Receive {
+            sender: HumanAddr("blah".to_string()),
+            from: HumanAddr("alice".to_string()),
+            amount: Uint128(2500),
+            msg: None,
+        };
+
+        let handle_result = handle(&mut deps, mock_env("selladdr", &[]), handle_msg);
+        let log = extract_log(handle_result);
+        assert!(log.contains("Auction has ended. Your tokens have been returned"));
+        assert!(log.contains("\"status\":\"failure\""));
+        assert!(log.contains("\"amount_returned\":\"2500\""));
+
+        // try consign too little
+        let (init_result, mut deps) = init_helper();
+        assert!(
+            init_result.is_ok(),
+            "Init failed: {}",
+            init_result.err().unwrap()
+        );
+        let handle_msg = HandleMsg::Receive {
+            sender: HumanAddr("blah".to_string()),
+            from: HumanAddr("alice".to_string()),
+            amount: Uint128(2),
+            msg: None,
+        };
+        let handle_result = handle(&mut deps, mock_env("selladdr", &[]), handle_msg);
+        let log = extract_log(handle_result);
+        assert!(log.contains("not consigned the full amount"));
+        assert!(log.contains("\"amount_needed\":\"8\""));
+        assert!(log.contains("\"sell_decimals\":4"));
+        let state: State = load(&deps.storage, CONFIG_KEY).unwrap();
+        assert_eq!(false, state.tokens_consigned);
+
+        // try consign too much
+        let handle_msg = HandleMsg::Receive {
+            sender: HumanAddr("blah".to_string()),
+            from: HumanAddr("alice".to_string()),
+            amount: Uint128(20),
+            msg: None,
+        };
+        let handle_result = handle(&mut deps, mock_env("selladdr", &[]), handle_msg);
+        let log = extract_log(handle_result);
+        assert!(log.contains("Excess tokens have been returned"));
+        assert!(log.contains("\"amount_returned\":\"12\""));
+        assert!(log.contains("\"sell_decimals\":4"));
+        let state: State = load(&deps.storage, CONFIG_KEY).unwrap();
+        assert!(state.tokens_consigned);
+    }
+
+    #[test]
+    fn test_accounting_overflow() {
+        let (init_result, mut deps) = init_helper();
+        assert!(
+            init_result.is_ok(),
+            "Init failed: {}",
+            init_result.err().unwrap()
+        );
+        let mut state: State = load(&deps.storage, CONFIG_KEY).unwrap();
+        state.sell_amount = u128::MAX;
+        state.currently_consigned = u128::MAX - 1;
+        save(&mut deps.storage, CONFIG_KEY, &state).unwrap();
+        // a consignment that would overflow the running total is rejected instead of wrapping
+        let handle_msg = HandleMsg::Receive {
+            sender: HumanAddr("blah".to_string()),
+            from: HumanAddr("alice".to_string()),
+            amount: Uint128(2),
+            msg: None,
+        };
+        let handle_result = handle(&mut deps, before_close_env("selladdr"), handle_msg);
+        let error = extract_error_msg(handle_result);
+        assert!(error.contains("Overflow adding 2 to"));
+        let state: State = load(&deps.storage, CONFIG_KEY).unwrap();
+        assert_eq!(state.currently_consigned, u128::MAX - 1);
+    }
+
+    #[test]
+    fn test_snip1155_receive() {
+        let (init_result, mut deps) = init_helper();
+        assert!(
+            init_result.is_ok(),
+            "Init failed: {}",
+            init_result.err().unwrap()
+        );
+        let mut state: State = load(&deps.storage, CONFIG_KEY).unwrap();
+        state.sell_contract.token_id = Some("gold".to_string());
+        save(&mut deps.storage, CONFIG_KEY, &state).unwrap();
+
+        // try a SNIP-20 receive from the SNIP-1155 contract
+        let handle_msg = HandleMsg::Receive {
+            sender: HumanAddr("blah".to_string()),
+            from: HumanAddr("alice".to_string()),
+            amount: Uint128(10),
+            msg: None,
+        };
+        let handle_result = handle(&mut deps, mock_env("selladdr", &[]), handle_msg);
+        let error = extract_error_msg(handle_result);
+        assert!(error.contains("Address: selladdr is not a token in this auction"));
+
+        // try the wrong token id
+        let handle_msg = HandleMsg::Snip1155Receive {
+            sender: HumanAddr("blah".to_string()),
+            token_id: "silver".to_string(),
+            from: Some(HumanAddr("alice".to_string())),
+            amount: Uint128(10),
+            memo: None,
+            msg: None,
+        };
+        let handle_result = handle(&mut deps, mock_env("selladdr", &[]), handle_msg);
+        let error = extract_error_msg(handle_result);
+        assert!(error.contains("Address: selladdr is not a token in this auction"));
+
+        // sanity check
+        let handle_msg = HandleMsg::Snip1155Receive {
+            sender: HumanAddr("blah".to_string()),
+            token_id: "gold".to_string(),
+            from: Some(HumanAddr("alice".to_string())),
+            amount: Uint128(20),
+            memo: None,
+            msg: None,
+        };
+        let handle_result = handle(&mut deps, mock_env("selladdr", &[]), handle_msg);
+        let response = handle_result.unwrap();
+        assert!(response.log[0]
+            .value
+            .contains("Excess tokens have been returned"));
+        match &response.messages[0] {
+            CosmosMsg::Wasm(WasmMsg::Execute { msg, .. }) => {
+                let transfer = String::from_utf8(msg.as_slice().to_vec()).unwrap();
+                assert!(transfer.contains("\"token_id\":\"gold\""));
+                assert!(transfer.contains("\"from\":\"cosmos2contract\""));
+                assert!(transfer.contains("\"amount\":\"10\""));
+            }
+            _ => panic!("Unexpected message"),
+        }
+        let state: State = load(&deps.storage, CONFIG_KEY).unwrap();
+        assert!(state.tokens_consigned);
+    }
+
+    #[test]
+    fn test_bundle() {
+        let mut deps = mock_dependencies(20, &[]);
+        let mut env = mock_env("factory", &[]);
+        env.block.time = 0;
+        let gem_contract = ContractInfo {
+            code_hash: "gemhash".to_string(),
+            address: HumanAddr("gemaddr".to_string()),
+            token_id: None,
+        };
+        let bundle_init = |bundled: ContractInfo| InitMsg {
+            bundle: vec![BundleItem {
+                contract: bundled,
+                amount: Uint128(5),
+                decimals: 2,
+            }],
+            ..default_init_msg()
+        };
+
+        // try to bundle the bid token
+        let bid_contract = ContractInfo {
+            code_hash: "bidhash".to_string(),
+            address: HumanAddr("bidaddr".to_string()),
+            token_id: None,
+        };
+        let init_result = init(&mut deps, env.clone(), bundle_init(bid_contract));
+        let error = extract_error_msg(init_result);
+        assert!(error.contains("Each bundled token must be different"));
+
+        // sanity check
+        let init_result = init(&mut deps, env, bundle_init(gem_contract.clone()));
+        assert_eq!(init_result.unwrap().messages.len(), 4);
+
+        // consign the sell tokens
+        let handle_msg = HandleMsg::Receive {
+            sender: HumanAddr("blah".to_string()),
+            from: HumanAddr("alice".to_string()),
+            amount: Uint128(10),
+            msg: None,
+        };
+        let handle_result = handle(&mut deps, mock_env("selladdr", &[]), handle_msg);
+        let log = extract_log(handle_result);
+        assert!(log.contains("Other tokens in the bundle still need to be consigned"));
+        let state: State = load(&deps.storage, CONFIG_KEY).unwrap();
+        assert_eq!(false, state.tokens_consigned);
+
+        // consign the bundled tokens
+        let handle_msg = HandleMsg::Receive {
+            sender: HumanAddr("blah".to_string()),
+            from: HumanAddr("alice".to_string()),
+            amount: Uint128(7),
+            msg: None,
+        };
+        let handle_result = handle(&mut deps, mock_env("gemaddr", &[]), handle_msg);
+        let log = extract_log(handle_result);
+        assert!(log.contains("\"amount_returned\":\"2\""));
+        assert!(log.contains("\"sell_decimals\":2"));
+        let state: State = load(&deps.storage, CONFIG_KEY).unwrap();
+        assert!(state.tokens_consigned);
+        assert!(state.bundle[0].consigned);
+
+        // bid and close
+        let handle_msg = HandleMsg::Receive {
+            sender: HumanAddr("blah".to_string()),
+            from: HumanAddr("bob".to_string()),
+            amount: Uint128(10),
+            msg: None,
+        };
+        let _used = handle(&mut deps, before_close_env("bidaddr"), handle_msg);
+        let handle_msg = HandleMsg::Finalize {
+            new_ends_at: None,
+            new_minimum_bid: None,
+        };
+        let handle_result = handle(&mut deps, mock_env("alice", &[]), handle_msg);
+        let messages = handle_result.unwrap().messages;
+        let gem_transfer = messages.iter().any(|m| match m {
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr, msg, ..
+            }) => {
+                let transfer = String::from_utf8(msg.as_slice().to_vec()).unwrap();
+                *contract_addr == gem_contract.address
+                    && transfer.contains("\"recipient\":\"bob\"")
+                    && transfer.contains("\"amount\":\"5\"")
+            }
+            _ => false,
+        });
+        assert!(gem_transfer);
+        let state: State = load(&deps.storage, CONFIG_KEY).unwrap();
+        assert_eq!(0, state.bundle[0].currently_consigned);
+    }
+
+    #[test]
+    fn test_deposit() {
+        let deposit_init = || InitMsg {
+            deposit: Some(Uint128(5)),
+            ..default_init_msg()
+        };
+        let mut deps = mock_dependencies(20, &[]);
+        let mut env = mock_env("factory", &[]);
+        env.block.time = 0;
+        let init_result = init(&mut deps, env.clone(), deposit_init());
+        assert!(
+            init_result.is_ok(),
+            "Init failed: {}",
+            init_result.err().unwrap()
+        );
+
+        // try posting the deposit without stating the intent
+        let handle_msg = HandleMsg::Receive {
+            sender: HumanAddr("blah".to_string()),
+            from: HumanAddr("alice".to_string()),
+            amount: Uint128(7),
+            msg: None,
+        };
+        let handle_result = handle(&mut deps, before_close_env("bidaddr"), handle_msg);
+        let log = extract_log(handle_result);
+        assert!(log.contains("The seller must send bid tokens with either a consign msg"));
+        assert!(log.contains("\"status\":\"failure\""));
+        assert!(log.contains("\"amount_returned\":\"7\""));
+
+        // post too much deposit
+        let handle_msg = HandleMsg::Receive {
+            sender: HumanAddr("blah".to_string()),
+            from: HumanAddr("alice".to_string()),
+            amount: Uint128(7),
+            msg: Some(to_binary(&ReceiveMsg::Consign {}).unwrap()),
+        };
+        let handle_result = handle(&mut deps, before_close_env("bidaddr"), handle_msg);
+        let log = extract_log(handle_result);
+        assert!(log.contains("Deposit has been posted"));
+        assert!(log.contains("\"amount_returned\":\"2\""));
+        let state: State = load(&deps.storage, CONFIG_KEY).unwrap();
+        assert_eq!(5, state.deposit_posted);
+
+        // close with bids before consigning
+        let handle_msg = HandleMsg::Receive {
+            sender: HumanAddr("blah".to_string()),
+            from: HumanAddr("bob".to_string()),
+            amount: Uint128(10),
+            msg: None,
+        };
+        let _used = handle(&mut deps, before_close_env("bidaddr"), handle_msg);
+        let handle_msg = HandleMsg::Receive {
+            sender: HumanAddr("blah".to_string()),
+            from: HumanAddr("charlie".to_string()),
+            amount: Uint128(12),
+            msg: None,
+        };
+        let _used = handle(&mut deps, before_close_env("bidaddr"), handle_msg);
+        let handle_msg = HandleMsg::Finalize {
+            new_ends_at: None,
+            new_minimum_bid: None,
+        };
+        let handle_result = handle(&mut deps, mock_env("alice", &[]), handle_msg);
+        assert!(extract_msg(&handle_result).contains("split among the bidders"));
+        let mut returned: u128 = 0;
+        for message in handle_result.unwrap().messages.iter() {
+            if let CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr, msg, ..
+            }) = message
+            {
+                let transfer = String::from_utf8(msg.as_slice().to_vec()).unwrap();
+                if contract_addr.0 == "bidaddr" {
+                    assert!(!transfer.contains("\"recipient\":\"alice\""));
+                    let amount: u128 = transfer
+                        .split("\"amount\":\"")
+                        .nth(1)
+                        .and_then(|a| a.split('"').next())
+                        .unwrap()
+                        .parse()
+                        .unwrap();
+                    returned += amount;
+                }
+            }
+        }
+        assert_eq!(27, returned);
+        let state: State = load(&deps.storage, CONFIG_KEY).unwrap();
+        assert_eq!(0, state.deposit_posted);
+
+        // deposit is returned after a sale
+        let mut deps = mock_dependencies(20, &[]);
+        let _used = init(&mut deps, env, deposit_init());
+        let handle_msg = HandleMsg::Receive {
+            sender: HumanAddr("blah".to_string()),
+            from: HumanAddr("alice".to_string()),
+            amount: Uint128(5),
+            msg: Some(to_binary(&ReceiveMsg::Consign {}).unwrap()),
+        };
+        let _used = handle(&mut deps, before_close_env("bidaddr"), handle_msg);
+        let handle_msg = HandleMsg::Receive {
+            sender: HumanAddr("blah".to_string()),
+            from: HumanAddr("alice".to_string()),
+            amount: Uint128(10),
+            msg: None,
+        };
+        let _used = handle(&mut deps, mock_env("selladdr", &[]), handle_msg);
+        let handle_msg = HandleMsg::Receive {
+            sender: HumanAddr("blah".to_string()),
+            from: HumanAddr("bob".to_string()),
+            amount: Uint128(10),
+            msg: None,
+        };
+        let _used = handle(&mut deps, before_close_env("bidaddr"), handle_msg);
+        let handle_msg = HandleMsg::Finalize {
+            new_ends_at: None,
+            new_minimum_bid: None,
+        };
+        let handle_result = handle(&mut deps, mock_env("alice", &[]), handle_msg);
+        let (_, winning_bid, _, _, _, bid_tokens_received) =
+            extract_finalize_fields(&handle_result);
+        assert_eq!(Some(Uint128(10)), winning_bid);
+        assert_eq!(Some(Uint128(15)), bid_tokens_received);
+    }
+
+    #[test]
+    fn test_tie_break() {
+        let tie_init = |tie_break: TieBreak| InitMsg {
+            tie_break,
+            ..default_init_msg()
+        };
+        let setup = |tie_break: TieBreak| {
+            let mut deps = mock_dependencies(20, &[]);
+            let mut env = mock_env("factory", &[]);
+            env.block.time = 0;
+            let _used = init(&mut deps, env, tie_init(tie_break));
+            let handle_msg = HandleMsg::Receive {
+                sender: HumanAddr("blah".to_string()),
+                from: HumanAddr("alice".to_string()),
+                amount: Uint128(10),
+                msg: None,
+            };
+            let _used = handle(&mut deps, mock_env("selladdr", &[]), handle_msg);
+            for (bidder, time) in &[("bob", 100), ("charlie", 200)] {
+                let handle_msg = HandleMsg::Receive {
+                    sender: HumanAddr("blah".to_string()),
+                    from: HumanAddr(bidder.to_string()),
+                    amount: Uint128(10),
+                    msg: None,
+                };
+                let mut env = mock_env("bidaddr", &[]);
+                env.block.time = *time;
+                let _used = handle(&mut deps, env, handle_msg);
+            }
+            deps
+        };
+        let finalize = |deps: &mut Extern<MockStorage, MockApi, MockQuerier>, closer: &str| {
+            let handle_msg = HandleMsg::Finalize {
+                new_ends_at: None,
+                new_minimum_bid: None,
+            };
+            handle(deps, mock_env(closer, &[]), handle_msg)
+        };
+
+        // earliest wins
+        let mut deps = setup(TieBreak::EarliestWins);
+        let handle_result = finalize(&mut deps, "bob");
+        let (_, _, _, sell_tokens_received, _, _) = extract_finalize_fields(&handle_result);
+        assert_eq!(Some(Uint128(10)), sell_tokens_received);
+
+        // latest wins
+        let mut deps = setup(TieBreak::LatestWins);
+        let handle_result = finalize(&mut deps, "charlie");
+        let (_, _, _, sell_tokens_received, _, _) = extract_finalize_fields(&handle_result);
+        assert_eq!(Some(Uint128(10)), sell_tokens_received);
+
+        // pro-rata split
+        let mut deps = setup(TieBreak::ProRata);
+        let handle_result = finalize(&mut deps, "alice");
+        let (_, winning_bid, _, _, _, bid_tokens_received) =
+            extract_finalize_fields(&handle_result);
+        assert_eq!(Some(Uint128(10)), winning_bid);
+        assert_eq!(Some(Uint128(20)), bid_tokens_received);
+        let messages = handle_result.unwrap().messages;
+        let close = messages
+            .iter()
+            .find_map(|m| match m {
+                CosmosMsg::Wasm(WasmMsg::Execute {
+                    contract_addr, msg, ..
+                }) if contract_addr.0 == "factoryaddr" => {
+                    Some(String::from_utf8(msg.as_slice().to_vec()).unwrap())
+                }
+                _ => None,
+            })
+            .unwrap();
+        assert!(close.contains("\"co_winners\":[\"charlie\"]"));
+        let state: State = load(&deps.storage, CONFIG_KEY).unwrap();
+        assert_eq!(state.num_bidders, 0);
+        let handle_result = finalize(&mut deps, "charlie");
+        assert!(handle_result.is_ok());
+    }
+
+    #[test]
+    fn test_bid() {
+        let (init_result, mut deps) = init_helper();
+        assert!(
+            init_result.is_ok(),
+            "Init failed: {}",
+            init_result.err().unwrap()
+        );
+
+        // try bid 0
+        let handle_msg = HandleMsg::Receive {
+            sender: HumanAddr("blah".to_string()),
+            from: HumanAddr("bob".to_string()),
+            amount: Uint128(0),
+            msg: None,
+        };
+        let handle_result = handle(&mut deps, before_close_env("bidaddr"), handle_msg);
+        let error = extract_error_msg(handle_result);
+        assert!(error.contains("Bid must be greater than 0"));
+
+        // try bid less than minimum
+        let (init_result, mut deps) = init_helper();
+        assert!(
+            init_result.is_ok(),
+            "Init failed: {}",
+            init_result.err().unwrap()
+        );
+        let handle_msg = HandleMsg::Receive {
+            sender: HumanAddr("blah".to_string()),
+            from: HumanAddr("bob".to_string()),
+            amount: Uint128(9),
+            msg: None,
+        };
+        let handle_result = handle(&mut deps, before_close_env("bidaddr"), handle_msg);
+        let log = extract_log(handle_result);
+        assert!(log.contains("Bid was less than minimum allowed"));
+        assert!(log.contains("\"minimum_bid\":\"10\""));
+        assert!(log.contains("\"amount_returned\":\"9\""));
+        assert!(log.contains("\"bid_decimals\":8"));
+
+        let state: State = load(&deps.storage, CONFIG_KEY).unwrap();
+        assert_eq!(state.num_bidders, 0);
+
+        // sanity check
+        let handle_msg = HandleMsg::Receive {
+            sender: HumanAddr("blah".to_string()),
+            from: HumanAddr("bob".to_string()),
+            amount: Uint128(100),
+            msg: None,
+        };
+        let handle_result = handle(&mut deps, before_close_env("bidaddr"), handle_msg);
+        let log = extract_log(handle_result);
+        assert!(log.contains("\"amount_bid\":\"100\""));
+        assert!(log.contains("\"bid_decimals\":8"));
+        let state: State = load(&deps.storage, CONFIG_KEY).unwrap();
+        assert_eq!(state.num_bidders, 1);
+        let bid: Bid = load(
+            &deps.storage,
+            deps.api
+                .canonical_address(&HumanAddr("bob".to_string()))
+                .unwrap()
+                .as_slice(),
+        )
+        .unwrap();
+        assert_eq!(bid.amount, 100);
+
+        // test bid equal to  previous bid
+        let handle_msg = HandleMsg::Receive {
+            sender: HumanAddr("blah".to_string()),
+            from: HumanAddr("bob".to_string()),
+            amount: Uint128(100),
+            msg: None,
+        };
+        let handle_result = handle(&mut deps, before_close_env("bidaddr"), handle_msg);
+        let log = extract_log(handle_result);
+        assert!(log.contains("New bid is the same as previous bid.  Retaining previous timestamp"));
+        assert!(log.contains("\"previous_bid\":\"100\""));
+        assert!(log.contains("\"amount_bid\":\"100\""));
+        assert!(log.contains("\"amount_returned\":\"100\""));
+        assert!(log.contains("\"bid_decimals\":8"));
+        let state: State = load(&deps.storage, CONFIG_KEY).unwrap();
+        assert_eq!(state.num_bidders, 1);
+
+        // test bid less than previous bid
+        let handle_msg = HandleMsg::Receive {
+            sender: HumanAddr("blah".to_string()),
+            from: HumanAddr("bob".to_string()),
+            amount: Uint128(25),
+            msg: None,
+        };
+        let handle_result = handle(&mut deps, before_close_env("bidaddr"), handle_msg);
+        let log = extract_log(handle_result);
+        assert!(log.contains("Previously bid tokens have been returned"));
+        assert!(log.contains("\"amount_bid\":\"25\""));
+        assert!(log.contains("\"amount_returned\":\"100\""));
+        assert!(log.contains("\"bid_decimals\":8"));
+        let state: State = load(&deps.storage, CONFIG_KEY).unwrap();
+        assert_eq!(state.num_bidders, 1);
+
+        // test bid more than previous bid
+        let handle_msg = HandleMsg::Receive {
+            sender: HumanAddr("blah".to_string()),
+            from: HumanAddr("bob".to_string()),
+            amount: Uint128(250),
+            msg: None,
+        };
+        let handle_result = handle(&mut deps, before_close_env("bidaddr"), handle_msg);
+        let log = extract_log(handle_result);
+        assert!(log.contains("Previously bid tokens have been returned"));
+        assert!(log.contains("\"amount_bid\":\"250\""));
+        assert!(log.contains("\"amount_returned\":\"25\""));
+        assert!(log.contains("\"bid_decimals\":8"));
+        let state: State = load(&deps.storage, CONFIG_KEY).unwrap();
+        assert_eq!(state.num_bidders, 1);
+
+        // try bid after close
+        let handle_msg = HandleMsg::Finalize {
+            new_ends_at: Some(2000),
+            new_minimum_bid: Some(Uint128(1000)),
+        };
+        let _used = handle(&mut deps, mock_env("alice", &[]), handle_msg);
+        let handle_msg = HandleMsg::Receive {
+            sender: HumanAddr("blah".to_string()),
+            from: HumanAddr("bob".to_string()),
+            amount: Uint128(2500),
+            msg: None,
+        };
+
+        let handle_result = handle(&mut deps, before_close_env("bidaddr"), handle_msg);
+        let log = extract_log(handle_result);
+        assert!(log.contains("Auction has ended"));
+        assert!(log.contains("\"status\":\"failure\""));
+        assert!(log.contains("\"amount_returned\":\"2500\""));
+    }
+
+    #[test]
+    fn test_change_min_bid() {
+        let (init_result, mut deps) = init_helper();
+        assert!(
+            init_result.is_ok(),
+            "Init failed: {}",
+            init_result.err().unwrap()
+        );
+        // try change min bid not seller
+        let handle_msg = HandleMsg::ChangeMinimumBid {
+            minimum_bid: Uint128(20),
+            refund_lower_bids: false,
+        };
+        let handle_result = handle(&mut deps, mock_env("bob", &[]), handle_msg);
+        let error = extract_error_msg(handle_result);
+        assert!(error.contains("Only the auction seller can change the minimum bid"));
+        let state: State = load(&deps.storage, CONFIG_KEY).unwrap();
+        assert_eq!(state.minimum_bid, 10);
+
+        // try change min bid after close
+        let handle_msg = HandleMsg::Finalize {
+            new_ends_at: None,
+            new_minimum_bid: None,
+        };
+        let handle_result = handle(
+            &mut deps,
+            Env {
+                block: BlockInfo {
+                    height: 12_345,
+                    time: 1000,
+                    chain_id: "cosmos-testnet-14002".to_string(),
+                },
+                message: MessageInfo {
+                    sender: HumanAddr("bob".to_string()),
+                    sent_funds: vec![],
+                },
+                contract: cosmwasm_std::ContractInfo {
+                    address: HumanAddr::from(MOCK_CONTRACT_ADDR),
+                },
+                contract_key: Some("".to_string()),
+                contract_code_hash: "".to_string(),
+            },
+            handle_msg,
+        );
+        let (
+            message,
+            winning_bid,
+            bid_decimals,
+            sell_tokens_received,
+            sell_decimals,
+            bid_tokens_received,
+        ) = extract_finalize_fields(&handle_result);
+        assert_eq!(winning_bid, None);
+        assert_eq!(bid_decimals, None);
+        assert_eq!(sell_tokens_received, None);
+        assert_eq!(sell_decimals, None);
+        assert_eq!(bid_tokens_received, None);
+        assert!(message.contains("Auction has been closed"));
+        assert!(!message.contains("Auction has been closed."));
+
+        let handle_msg = HandleMsg::ChangeMinimumBid {
+            minimum_bid: Uint128(20),
+            refund_lower_bids: false,
+        };
+        let handle_result = handle(&mut deps, mock_env("alice", &[]), handle_msg);
+        let error = extract_error_msg(handle_result);
+        assert!(error.contains("Can not change the minimum bid of an auction that has ended"));
+        let state: State = load(&deps.storage, CONFIG_KEY).unwrap();
+        assert_eq!(state.minimum_bid, 10);
+
+        // sanity check
+        let (init_result, mut deps) = init_helper();
+        assert!(
+            init_result.is_ok(),
+            "Init failed: {}",
+            init_result.err().unwrap()
+        );
+        let handle_msg = HandleMsg::Receive {
+            sender: HumanAddr("blah".to_string()),
+            from: HumanAddr("bob".to_string()),
+            amount: Uint128(10),
+            msg: None,
+        };
+        let handle_result = handle(&mut deps, before_close_env("bidaddr"), handle_msg);
+        let log = extract_log(handle_result);
+        assert!(log.contains("\"amount_bid\":\"10\""));
+        assert!(log.contains("\"bid_decimals\":8"));
+        let state: State = load(&deps.storage, CONFIG_KEY).unwrap();
+        assert_eq!(state.num_bidders, 1);
+
+        let handle_msg = HandleMsg::ChangeMinimumBid {
+            minimum_bid: Uint128(20),
+            refund_lower_bids: false,
+        };
+        let _handle_result = handle(&mut deps, mock_env("alice", &[]), handle_msg);
+        let state: State = load(&deps.storage, CONFIG_KEY).unwrap();
+        assert_eq!(state.minimum_bid, 20);
+
+        let handle_msg = HandleMsg::Receive {
+            sender: HumanAddr("blah".to_string()),
+            from: HumanAddr("charlie".to_string()),
+            amount: Uint128(15),
+            msg: None,
+        };
+        let handle_result = handle(&mut deps, before_close_env("bidaddr"), handle_msg);
+        let log = extract_log(handle_result);
+        assert!(log.contains("Bid was less than minimum allowed"));
+        assert!(log.contains("\"minimum_bid\":\"20\""));
+        assert!(log.contains("\"amount_returned\":\"15\""));
+        assert!(log.contains("\"bid_decimals\":8"));
+        let state: State = load(&deps.storage, CONFIG_KEY).unwrap();
+        assert_eq!(state.num_bidders, 1);
+    }
+
+    #[test]
+    fn test_change_min_bid_refund_lower_bids() {
+        let (init_result, mut deps) = init_helper();
+        assert!(
+            init_result.is_ok(),
+            "Init failed: {}",
+            init_result.err().unwrap()
+        );
+        for (bidder, amount) in [("bob", 10), ("dave", 25)].iter() {
+            let handle_msg = HandleMsg::Receive {
+                sender: HumanAddr("blah".to_string()),
+                from: HumanAddr(bidder.to_string()),
+                amount: Uint128(*amount),
+                msg: None,
+            };
+            let _handle_result = handle(&mut deps, before_close_env("bidaddr"), handle_msg);
+        }
+        let state: State = load(&deps.storage, CONFIG_KEY).unwrap();
+        assert_eq!(state.num_bidders, 2);
+
+        // refund the bids below the new minimum
+        let handle_msg = HandleMsg::ChangeMinimumBid {
+            minimum_bid: Uint128(22),
+            refund_lower_bids: true,
+        };
+        let handle_result = handle(&mut deps, mock_env("alice", &[]), handle_msg);
+        let response = handle_result.unwrap();
+        // bob's refund, the factory's remove bidder callback, and the change info callback
+        assert_eq!(response.messages.len(), 3);
+        let handle_answer: HandleAnswer = from_binary(&response.data.unwrap()).unwrap();
+        match handle_answer {
+            HandleAnswer::ChangeMinimumBid { bids_refunded, .. } => {
+                assert_eq!(bids_refunded, Some(1))
+            }
+            _ => panic!("Unexpected HandleAnswer"),
+        }
+        let state: State = load(&deps.storage, CONFIG_KEY).unwrap();
+        assert_eq!(state.minimum_bid, 22);
+        assert_eq!(state.num_bidders, 1);
+        let bob_raw = deps
+            .api
+            .canonical_address(&HumanAddr("bob".to_string()))
+            .unwrap();
+        let bid: Option<Bid> = may_load(&deps.storage, bob_raw.as_slice()).unwrap();
+        assert!(bid.is_none());
+    }
+
+    #[test]
+    fn test_failure_answers_return_tokens() {
+        let (init_result, mut deps) = init_helper();
+        assert!(
+            init_result.is_ok(),
+            "Init failed: {}",
+            init_result.err().unwrap()
+        );
+        // asserts the tokens were sent back with a transfer and reported in a failure answer
+        let assert_returned =
+            |handle_result: StdResult<HandleResponse>, token: &str, owner: &str, amount: u128| {
+                let response = handle_result.unwrap();
+                let transferred = response.messages.iter().any(|m| match m {
+                    CosmosMsg::Wasm(WasmMsg::Execute {
+                        contract_addr, msg, ..
+                    }) => {
+                        let transfer = String::from_utf8(msg.as_slice().to_vec()).unwrap();
+                        *contract_addr == HumanAddr(token.to_string())
+                            && transfer.contains("\"transfer\"")
+                            && transfer.contains(&format!("\"recipient\":\"{}\"", owner))
+                            && transfer.contains(&format!("\"amount\":\"{}\"", amount))
+                    }
+                    _ => false,
+                });
+                assert!(transferred);
+                let log = &response.log[0].value;
+                assert!(log.contains("\"status\":\"failure\""));
+                assert!(log.contains(&format!("\"amount_returned\":\"{}\"", amount)));
+            };
+        let receive = |from: &str, amount: u128| HandleMsg::Receive {
+            sender: HumanAddr("blah".to_string()),
+            from: HumanAddr(from.to_string()),
+            amount: Uint128(amount),
+            msg: None,
+        };
+
+        // a bid below the minimum
+        let handle_result = handle(&mut deps, before_close_env("bidaddr"), receive("bob", 9));
+        assert_returned(handle_result, "bidaddr", "bob", 9);
+
+        // a consignment that is no longer needed
+        let handle_result = handle(
+            &mut deps,
+            before_close_env("selladdr"),
+            receive("alice", 10),
+        );
+        assert!(handle_result.is_ok());
+        let handle_result = handle(&mut deps, before_close_env("selladdr"), receive("alice", 3));
+        assert_returned(handle_result, "selladdr", "alice", 3);
+
+        // and a bid after the closing time, which also finalizes the auction
+        let mut env = mock_env("bidaddr", &[]);
+        env.block.time = 1001;
+        let handle_result = handle(&mut deps, env, receive("bob", 20));
+        assert_returned(handle_result, "bidaddr", "bob", 20);
+        let state: State = load(&deps.storage, CONFIG_KEY).unwrap();
+        assert!(state.is_completed);
+        assert_eq!(state.num_bidders, 0);
+    }
+
+    #[test]
+    fn test_retract_bid() {
+        let (init_result, mut deps) = init_helper();
+        assert!(
+            init_result.is_ok(),
+            "Init failed: {}",
+            init_result.err().unwrap()
+        );
+
+        // try no bid placed
+        let handle_msg = HandleMsg::RetractBid {};
+        let handle_result = handle(&mut deps, before_close_env("bob"), handle_msg);
+        let message = extract_msg(&handle_result);
+        assert!(message.contains("No active bid for address"));
+        let (amount, decimals) = extract_amount_returned(&handle_result);
+        assert_eq!(amount, None);
+        assert_eq!(decimals, None);
+
+        // sanity check
+        let handle_msg = HandleMsg::Receive {
+            sender: HumanAddr("blah".to_string()),
+            from: HumanAddr("bob".to_string()),
+            amount: Uint128(100),
+            msg: None,
+        };
+        let _handle_result = handle(&mut deps, before_close_env("bidaddr"), handle_msg);
+        let state: State = load(&deps.storage, CONFIG_KEY).unwrap();
+        assert_eq!(state.num_bidders, 1);
+        let bid: Bid = load(
+            &deps.storage,
+            deps.api
+                .canonical_address(&HumanAddr("bob".to_string()))
+                .unwrap()
+                .as_slice(),
+        )
+        .unwrap();
+        assert_eq!(bid.amount, 100);
+
+        let handle_msg = HandleMsg::RetractBid {};
+        let handle_result = handle(&mut deps, before_close_env("bob"), handle_msg);
+        let message = extract_msg(&handle_result);
+        assert!(message.contains("Bid retracted.  Tokens have been returned"));
+        let (amount, decimals) = extract_amount_returned(&handle_result);
+        assert_eq!(amount, Some(Uint128(100)));
+        assert_eq!(decimals, Some(8));
+        let state: State = load(&deps.storage, CONFIG_KEY).unwrap();
+        assert_eq!(state.num_bidders, 0);
+    }
+
+    #[test]
+    fn test_finalize() {
+        let (init_result, mut deps) = init_helper();
+        assert!(
+            init_result.is_ok(),
+            "Init failed: {}",
+            init_result.err().unwrap()
+        );
+
+        // try return all before closing
+        let handle_msg = HandleMsg::ReturnAll { page_size: None };
+        let handle_result = handle(&mut deps, mock_env("bob", &[]), handle_msg);
+        let error = extract_error_msg(handle_result);
+        assert!(error.contains("return_all can only be executed after the auction has ended"));
+
+        // try non-seller closing before end time
+        let handle_msg = HandleMsg::Finalize {
+            new_ends_at: None,
+            new_minimum_bid: None,
+        };
+        let handle_result = handle(
+            &mut deps,
+            Env {
+                block: BlockInfo {
+                    height: 12_345,
+                    time: 1,
+                    chain_id: "cosmos-testnet-14002".to_string(),
+                },
+                message: MessageInfo {
+                    sender: HumanAddr("bob".to_string()),
+                    sent_funds: vec![],
+                },
+                contract: cosmwasm_std::ContractInfo {
+                    address: HumanAddr::from(MOCK_CONTRACT_ADDR),
+                },
+                contract_key: Some("".to_string()),
+                contract_code_hash: "".to_string(),
+            },
+            handle_msg,
+        );
+        let error = extract_error_msg(handle_result);
+        assert!(
+            error.contains("Only auction creator can finalize the sale before the closing time")
+        );
+
+        // try non-seller closing after end time
+        let (init_result, mut deps) = init_helper();
+        assert!(
+            init_result.is_ok(),
+            "Init failed: {}",
+            init_result.err().unwrap()
+        );
+
+        let handle_msg = HandleMsg::Finalize {
+            new_ends_at: None,
+            new_minimum_bid: None,
+        };
+        let handle_result = handle(
+            &mut deps,
+            Env {
+                block: BlockInfo {
+                    height: 12_345,
+                    time: 1000,
+                    chain_id: "cosmos-testnet-14002".to_string(),
+                },
+                message: MessageInfo {
+                    sender: HumanAddr("bob".to_string()),
+                    sent_funds: vec![],
+                },
+                contract: cosmwasm_std::ContractInfo {
+                    address: HumanAddr::from(MOCK_CONTRACT_ADDR),
+                },
+                contract_key: Some("".to_string()),
+                contract_code_hash: "".to_string(),
+            },
+            handle_msg,
+        );
+        let (
+            message,
+            winning_bid,
+            bid_decimals,
+            sell_tokens_received,
+            sell_decimals,
+            bid_tokens_received,
+        ) = extract_finalize_fields(&handle_result);
+        assert_eq!(winning_bid, None);
+        assert_eq!(bid_decimals, None);
+        assert_eq!(sell_tokens_received, None);
+        assert_eq!(sell_decimals, None);
+        assert_eq!(bid_tokens_received, None);
+        assert!(message.contains("Auction has been closed"));
+        assert!(!message.contains("Auction has been closed."));
+
+        let (init_result, mut deps) = init_helper();
+        assert!(
+            init_result.is_ok(),
+            "Init failed: {}",
+            init_result.err().unwrap()
+        );
+        // try stranger not wanting to close without bids
+        let handle_msg = HandleMsg::Finalize {
+            new_ends_at: Some(2000),
+            new_minimum_bid: None,
+        };
+        let handle_result = handle(
+            &mut deps,
+            Env {
+                block: BlockInfo {
+                    height: 12_345,
+                    time: 1100,
+                    chain_id: "cosmos-testnet-14002".to_string(),
+                },
+                message: MessageInfo {
+                    sender: HumanAddr("bob".to_string()),
+                    sent_funds: vec![],
+                },
+                contract: cosmwasm_std::ContractInfo {
+                    address: HumanAddr::from(MOCK_CONTRACT_ADDR),
+                },
+                contract_key: Some("".to_string()),
+                contract_code_hash: "".to_string(),
+            },
+            handle_msg,
+        );
+        let error = extract_error_msg(handle_result);
+        assert!(error
+            .contains("Only the auction seller can change the closing time or the minimum bid"));
+        // try stranger not wanting to close without bids
+        let handle_msg = HandleMsg::Finalize {
+            new_ends_at: None,
+            new_minimum_bid: Some(Uint128(1000)),
+        };
+        let handle_result = handle(
+            &mut deps,
+            Env {
+                block: BlockInfo {
+                    height: 12_345,
+                    time: 1100,
+                    chain_id: "cosmos-testnet-14002".to_string(),
+                },
+                message: MessageInfo {
+                    sender: HumanAddr("bob".to_string()),
+                    sent_funds: vec![],
+                },
+                contract: cosmwasm_std::ContractInfo {
+                    address: HumanAddr::from(MOCK_CONTRACT_ADDR),
+                },
+                contract_key: Some("".to_string()),
+                contract_code_hash: "".to_string(),
+            },
+            handle_msg,
+        );
+        let error = extract_error_msg(handle_result);
+        assert!(error
+            .contains("Only the auction seller can change the closing time or the minimum bid"));
+        // try stranger not wanting to close without bids
+        let handle_msg = HandleMsg::Finalize {
+            new_ends_at: Some(2000),
+            new_minimum_bid: Some(Uint128(1000)),
+        };
+        let handle_result = handle(
+            &mut deps,
+            Env {
+                block: BlockInfo {
+                    height: 12_345,
+                    time: 1100,
+                    chain_id: "cosmos-testnet-14002".to_string(),
+                },
+                message: MessageInfo {
+                    sender: HumanAddr("bob".to_string()),
+                    sent_funds: vec![],
+                },
+                contract: cosmwasm_std::ContractInfo {
+                    address: HumanAddr::from(MOCK_CONTRACT_ADDR),
+                },
+                contract_key: Some("".to_string()),
+                contract_code_hash: "".to_string(),
+            },
+            handle_msg,
+        );
+        let error = extract_error_msg(handle_result);
+        assert!(error
+            .contains("Only the auction seller can change the closing time or the minimum bid"));
+
+        // try seller not wanting to close without bids
+        let handle_msg = HandleMsg::Finalize {
+            new_ends_at: Some(2000),
+            new_minimum_bid: None,
+        };
+        let handle_result = handle(
+            &mut deps,
+            Env {
+                block: BlockInfo {
+                    height: 12_345,
+                    time: 1,
+                    chain_id: "cosmos-testnet-14002".to_string(),
+                },
+                message: MessageInfo {
+                    sender: HumanAddr("alice".to_string()),
+                    sent_funds: vec![],
+                },
+                contract: cosmwasm_std::ContractInfo {
+                    address: HumanAddr::from(MOCK_CONTRACT_ADDR),
+                },
+                contract_key: Some("".to_string()),
+                contract_code_hash: "".to_string(),
+            },
+            handle_msg,
+        );
+        let (
+            message,
+            winning_bid,
+            bid_decimals,
+            sell_tokens_received,
+            sell_decimals,
+            bid_tokens_received,
+        ) = extract_finalize_fields(&handle_result);
+        assert_eq!(winning_bid, None);
+        assert_eq!(bid_decimals, None);
+        assert_eq!(sell_tokens_received, None);
+        assert_eq!(sell_decimals, None);
+        assert_eq!(bid_tokens_received, None);
+        assert!(message.contains("There were no active bids.  The closing time has been updated"));
+        let state: State = load(&deps.storage, CONFIG_KEY).unwrap();
+        assert_eq!(state.ends_at, 2000);
+        assert_eq!(state.minimum_bid, 10);
+
+        // try seller not wanting to close without bids
+        let handle_msg = HandleMsg::Finalize {
+            new_ends_at: None,
+            new_minimum_bid: Some(Uint128(1000)),
+        };
+        let handle_result = handle(
+            &mut deps,
+            Env {
+                block: BlockInfo {
+                    height: 12_345,
+                    time: 1,
+                    chain_id: "cosmos-testnet-14002".to_string(),
+                },
+                message: MessageInfo {
+                    sender: HumanAddr("alice".to_string()),
+                    sent_funds: vec![],
+                },
+                contract: cosmwasm_std::ContractInfo {
+                    address: HumanAddr::from(MOCK_CONTRACT_ADDR),
+                },
+                contract_key: Some("".to_string()),
+                contract_code_hash: "".to_string(),
+            },
+            handle_msg,
+        );
+        let (
+            message,
+            winning_bid,
+            bid_decimals,
+            sell_tokens_received,
+            sell_decimals,
+            bid_tokens_received,
+        ) = extract_finalize_fields(&handle_result);
+        assert_eq!(winning_bid, None);
+        assert_eq!(bid_decimals, None);
+        assert_eq!(sell_tokens_received, None);
+        assert_eq!(sell_decimals, None);
+        assert_eq!(bid_tokens_received, None);
+        assert!(message.contains("There were no active bids.  The minimum bid has been updated"));
+        let state: State = load(&deps.storage, CONFIG_KEY).unwrap();
+        assert_eq!(state.ends_at, 2000);
+        assert_eq!(state.minimum_bid, 1000);
+
+        // try seller not wanting to close without bids
+        let handle_msg = HandleMsg::Finalize {
+            new_ends_at: Some(10000),
+            new_minimum_bid: Some(Uint128(100000)),
+        };
+        let handle_result = handle(
+            &mut deps,
+            Env {
+                block: BlockInfo {
+                    height: 12_345,
+                    time: 1,
+                    chain_id: "cosmos-testnet-14002".to_string(),
+                },
+                message: MessageInfo {
+                    sender: HumanAddr("alice".to_string()),
+                    sent_funds: vec![],
+                },
+                contract: cosmwasm_std::ContractInfo {
+                    address: HumanAddr::from(MOCK_CONTRACT_ADDR),
+                },
+                contract_key: Some("".to_string()),
+                contract_code_hash: "".to_string(),
+            },
+            handle_msg,
+        );
+        let (
+            message,
+            winning_bid,
+            bid_decimals,
+            sell_tokens_received,
+            sell_decimals,
+            bid_tokens_received,
+        ) = extract_finalize_fields(&handle_result);
+        assert_eq!(winning_bid, None);
+        assert_eq!(bid_decimals, None);
+        assert_eq!(sell_tokens_received, None);
+        assert_eq!(sell_decimals, None);
+        assert_eq!(bid_tokens_received, None);
+        assert!(message.contains(
+            "There were no active bids.  The closing time and minimum bid has been updated"
+        ));
+        let state: State = load(&deps.storage, CONFIG_KEY).unwrap();
+        assert_eq!(state.ends_at, 10000);
+        assert_eq!(state.minimum_bid, 100000);
+
+        // test 3 bidders, highest retracts, other two are tied with time tie-breaker
+        // winner closes after end time
+        let (init_result, mut deps) = init_helper();
+        assert!(
+            init_result.is_ok(),
+            "Init failed: {}",
+            init_result.err().unwrap()
+        );
+
+        let handle_msg = HandleMsg::Receive {
+            sender: HumanAddr("blah".to_string()),
+            from: HumanAddr("bob".to_string()),
+            amount: Uint128(100),
+            msg: None,
+        };
+        let _handle_result = handle(
+            &mut deps,
+            Env {
+                block: BlockInfo {
+                    height: 12_345,
+                    time: 10,
+                    chain_id: "cosmos-testnet-14002".to_string(),
+                },
+                message: MessageInfo {
+                    sender: HumanAddr("bidaddr".to_string()),
+                    sent_funds: vec![],
+                },
+                contract: cosmwasm_std::ContractInfo {
+                    address: HumanAddr::from(MOCK_CONTRACT_ADDR),
+                },
+                contract_key: Some("".to_string()),
+                contract_code_hash: "".to_string(),
+            },
+            handle_msg,
+        );
+        let handle_msg = HandleMsg::Receive {
+            sender: HumanAddr("blah".to_string()),
+            from: HumanAddr("charlie".to_string()),
+            amount: Uint128(100),
+            msg: None,
+        };
+        let _handle_result = handle(
+            &mut deps,
+            Env {
+                block: BlockInfo {
+                    height: 12_345,
+                    time: 12,
+                    chain_id: "cosmos-testnet-14002".to_string(),
+                },
+                message: MessageInfo {
+                    sender: HumanAddr("bidaddr".to_string()),
+                    sent_funds: vec![],
+                },
+                contract: cosmwasm_std::ContractInfo {
+                    address: HumanAddr::from(MOCK_CONTRACT_ADDR),
+                },
+                contract_key: Some("".to_string()),
+                contract_code_hash: "".to_string(),
+            },
+            handle_msg,
+        );
+        let handle_msg = HandleMsg::Receive {
+            sender: HumanAddr("blah".to_string()),
+            from: HumanAddr("david".to_string()),
+            amount: Uint128(1000),
+            msg: None,
+        };
+        let _handle_result = handle(
+            &mut deps,
+            Env {
+                block: BlockInfo {
+                    height: 12_345,
+                    time: 5,
+                    chain_id: "cosmos-testnet-14002".to_string(),
+                },
+                message: MessageInfo {
+                    sender: HumanAddr("bidaddr".to_string()),
+                    sent_funds: vec![],
+                },
+                contract: cosmwasm_std::ContractInfo {
+                    address: HumanAddr::from(MOCK_CONTRACT_ADDR),
+                },
+                contract_key: Some("".to_string()),
+                contract_code_hash: "".to_string(),
+            },
+            handle_msg,
+        );
+        let state: State = load(&deps.storage, CONFIG_KEY).unwrap();
+        assert_eq!(state.num_bidders, 3);
+        let handle_msg = HandleMsg::RetractBid {};
+        let _handle_result = handle(&mut deps, before_close_env("david"), handle_msg);
+        let state: State = load(&deps.storage, CONFIG_KEY).unwrap();
+        assert_eq!(state.num_bidders, 2);
+
+        let handle_msg = HandleMsg::Receive {
+            sender: HumanAddr("blah".to_string()),
+            from: HumanAddr("alice".to_string()),
+            amount: Uint128(20),
+            msg: None,
+        };
+        let _handle_result = handle(&mut deps, mock_env("selladdr", &[]), handle_msg);
+        let handle_msg = HandleMsg::Finalize {
+            new_ends_at: None,
+            new_minimum_bid: None,
+        };
+        let handle_result = handle(
+            &mut deps,
+            Env {
+                block: BlockInfo {
+                    height: 12_345,
+                    time: 2000,
+                    chain_id: "cosmos-testnet-14002".to_string(),
+                },
+                message: MessageInfo {
+                    sender: HumanAddr("bob".to_string()),
+                    sent_funds: vec![],
+                },
+                contract: cosmwasm_std::ContractInfo {
+                    address: HumanAddr::from(MOCK_CONTRACT_ADDR),
+                },
+                contract_key: Some("".to_string()),
+                contract_code_hash: "".to_string(),
+            },
+            handle_msg,
+        );
+        let (
+            message,
+            winning_bid,
+            bid_decimals,
+            sell_tokens_received,
+            sell_decimals,
+            bid_tokens_received,
+        ) = extract_finalize_fields(&handle_result);
+        assert_eq!(winning_bid, Some(Uint128(100)));
+        assert_eq!(bid_decimals, Some(8));
+        assert_eq!(sell_tokens_received, Some(Uint128(10)));
+        assert_eq!(sell_decimals, Some(4));
+        assert_eq!(bid_tokens_received, None);
+        assert!(message.contains(
+            "Sale has been finalized.  Your bid won! You have been sent the sale token(s)"
+        ));
+
+        // test 3 bidders, highest is seller, seller closes
+        let (init_result, mut deps) = init_helper();
+        assert!(
+            init_result.is_ok(),
+            "Init failed: {}",
+            init_result.err().unwrap()
+        );
+
+        let handle_msg = HandleMsg::Receive {
+            sender: HumanAddr("blah".to_string()),
+            from: HumanAddr("bob".to_string()),
+            amount: Uint128(100),
+            msg: None,
+        };
+        let _handle_result = handle(
+            &mut deps,
+            Env {
+                block: BlockInfo {
+                    height: 12_345,
+                    time: 10,
+                    chain_id: "cosmos-testnet-14002".to_string(),
+                },
+                message: MessageInfo {
+                    sender: HumanAddr("bidaddr".to_string()),
+                    sent_funds: vec![],
+                },
+                contract: cosmwasm_std::ContractInfo {
+                    address: HumanAddr::from(MOCK_CONTRACT_ADDR),
+                },
+                contract_key: Some("".to_string()),
+                contract_code_hash: "".to_string(),
+            },
+            handle_msg,
+        );
+        let handle_msg = HandleMsg::Receive {
+            sender: HumanAddr("blah".to_string()),
+            from: HumanAddr("charlie".to_string()),
+            amount: Uint128(200),
+            msg: None,
+        };
+        let _handle_result = handle(
+            &mut deps,
+            Env {
+                block: BlockInfo {
+                    height: 12_345,
+                    time: 12,
+                    chain_id: "cosmos-testnet-14002".to_string(),
+                },
+                message: MessageInfo {
+                    sender: HumanAddr("bidaddr".to_string()),
+                    sent_funds: vec![],
+                },
+                contract: cosmwasm_std::ContractInfo {
+                    address: HumanAddr::from(MOCK_CONTRACT_ADDR),
+                },
+                contract_key: Some("".to_string()),
+                contract_code_hash: "".to_string(),
+            },
+            handle_msg,
+        );
+        let handle_msg = HandleMsg::Receive {
+            sender: HumanAddr("blah".to_string()),
+            from: HumanAddr("alice".to_string()),
+            amount: Uint128(1000),
+            msg: None,
+        };
+        let _handle_result = handle(
+            &mut deps,
+            Env {
+                block: BlockInfo {
+                    height: 12_345,
+                    time: 5,
+                    chain_id: "cosmos-testnet-14002".to_string(),
+                },
+                message: MessageInfo {
+                    sender: HumanAddr("bidaddr".to_string()),
+                    sent_funds: vec![],
+                },
+                contract: cosmwasm_std::ContractInfo {
+                    address: HumanAddr::from(MOCK_CONTRACT_ADDR),
+                },
+                contract_key: Some("".to_string()),
+                contract_code_hash: "".to_string(),
+            },
+            handle_msg,
+        );
+        let state: State = load(&deps.storage, CONFIG_KEY).unwrap();
+        assert_eq!(state.num_bidders, 3);
+
+        let handle_msg = HandleMsg::Receive {
+            sender: HumanAddr("blah".to_string()),
+            from: HumanAddr("alice".to_string()),
+            amount: Uint128(20),
+            msg: None,
+        };
+        let _handle_result = handle(&mut deps, mock_env("selladdr", &[]), handle_msg);
+        let handle_msg = HandleMsg::Finalize {
+            new_ends_at: Some(2000),
+            new_minimum_bid: Some(Uint128(1000)),
+        };
+        let handle_result = handle(
+            &mut deps,
+            Env {
+                block: BlockInfo {
+                    height: 12_345,
+                    time: 1,
+                    chain_id: "cosmos-testnet-14002".to_string(),
+                },
+                message: MessageInfo {
+                    sender: HumanAddr("alice".to_string()),
+                    sent_funds: vec![],
+                },
+                contract: cosmwasm_std::ContractInfo {
+                    address: HumanAddr::from(MOCK_CONTRACT_ADDR),
+                },
+                contract_key: Some("".to_string()),
+                contract_code_hash: "".to_string(),
+            },
+            handle_msg,
+        );
+        let (
+            message,
+            winning_bid,
+            bid_decimals,
+            sell_tokens_received,
+            sell_decimals,
+            bid_tokens_received,
+        ) = extract_finalize_fields(&handle_result);
+        assert_eq!(winning_bid, Some(Uint128(1000)));
+        assert_eq!(bid_decimals, Some(8));
+        assert_eq!(sell_tokens_received, Some(Uint128(10)));
+        assert_eq!(sell_decimals, Some(4));
+        assert_eq!(bid_tokens_received, Some(Uint128(1000)));
+        assert!(message.contains("Sale has been finalized.  You have been sent the winning bid.  Your bid won! You have been sent the sale token(s)"));
+
+        // test 3 bidders, seller loses, seller closes
+        let (init_result, mut deps) = init_helper();
+        assert!(
+            init_result.is_ok(),
+            "Init failed: {}",
+            init_result.err().unwrap()
+        );
+
+        let handle_msg = HandleMsg::Receive {
+            sender: HumanAddr("blah".to_string()),
+            from: HumanAddr("bob".to_string()),
+            amount: Uint128(1000),
+            msg: None,
+        };
+        let _handle_result = handle(
+            &mut deps,
+            Env {
+                block: BlockInfo {
+                    height: 12_345,
+                    time: 10,
+                    chain_id: "cosmos-testnet-14002".to_string(),
+                },
+                message: MessageInfo {
+                    sender: HumanAddr("bidaddr".to_string()),
+                    sent_funds: vec![],
+                },
+                contract: cosmwasm_std::ContractInfo {
+                    address: HumanAddr::from(MOCK_CONTRACT_ADDR),
+                },
+                contract_key: Some("".to_string()),
+                contract_code_hash: "".to_string(),
+            },
+            handle_msg,
+        );
+        let handle_msg = HandleMsg::Receive {
+            sender: HumanAddr("blah".to_string()),
+            from: HumanAddr("charlie".to_string()),
+            amount: Uint128(200),
+            msg: None,
+        };
+        let _handle_result = handle(
+            &mut deps,
+            Env {
+                block: BlockInfo {
+                    height: 12_345,
+                    time: 12,
+                    chain_id: "cosmos-testnet-14002".to_string(),
+                },
+                message: MessageInfo {
+                    sender: HumanAddr("bidaddr".to_string()),
+                    sent_funds: vec![],
+                },
+                contract: cosmwasm_std::ContractInfo {
+                    address: HumanAddr::from(MOCK_CONTRACT_ADDR),
+                },
+                contract_key: Some("".to_string()),
+                contract_code_hash: "".to_string(),
+            },
+            handle_msg,
+        );
+        let handle_msg = HandleMsg::Receive {
+            sender: HumanAddr("blah".to_string()),
+            from: HumanAddr("alice".to_string()),
+            amount: Uint128(25),
+            msg: None,
+        };
+        let _handle_result = handle(
+            &mut deps,
+            Env {
+                block: BlockInfo {
+                    height: 12_345,
+                    time: 5,
+                    chain_id: "cosmos-testnet-14002".to_string(),
+                },
+                message: MessageInfo {
+                    sender: HumanAddr("bidaddr".to_string()),
+                    sent_funds: vec![],
+                },
+                contract: cosmwasm_std::ContractInfo {
+                    address: HumanAddr::from(MOCK_CONTRACT_ADDR),
+                },
+                contract_key: Some("".to_string()),
+                contract_code_hash: "".to_string(),
+            },
+            handle_msg,
+        );
+        let state: State = load(&deps.storage, CONFIG_KEY).unwrap();
+        assert_eq!(state.num_bidders, 3);
+
+        let handle_msg = HandleMsg::Receive {
+            sender: HumanAddr("blah".to_string()),
+            from: HumanAddr("alice".to_string()),
+            amount: Uint128(20),
+            msg: None,
+        };
+        let _handle_result = handle(&mut deps, mock_env("selladdr", &[]), handle_msg);
+        let handle_msg = HandleMsg::Finalize {
+            new_ends_at: Some(2000),
+            new_minimum_bid: None,
+        };
+        let handle_result = handle(
+            &mut deps,
+            Env {
+                block: BlockInfo {
+                    height: 12_345,
+                    time: 1,
+                    chain_id: "cosmos-testnet-14002".to_string(),
+                },
+                message: MessageInfo {
+                    sender: HumanAddr("alice".to_string()),
+                    sent_funds: vec![],
+                },
+                contract: cosmwasm_std::ContractInfo {
+                    address: HumanAddr::from(MOCK_CONTRACT_ADDR),
+                },
+                contract_key: Some("".to_string()),
+                contract_code_hash: "".to_string(),
+            },
+            handle_msg,
+        );
+        let (
+            message,
+            winning_bid,
+            bid_decimals,
+            sell_tokens_received,
+            sell_decimals,
+            bid_tokens_received,
+        ) = extract_finalize_fields(&handle_result);
+        assert_eq!(winning_bid, Some(Uint128(1000)));
+        assert_eq!(bid_decimals, Some(8));
+        assert_eq!(sell_tokens_received, None);
+        assert_eq!(sell_decimals, None);
+        assert_eq!(bid_tokens_received, Some(Uint128(1025)));
+        assert!(message.contains("Sale has been finalized.  You have been sent the winning bid.  Your bid did not win and has been returned"));
+
+        // test 3 bidders, loser closes
+        let (init_result, mut deps) = init_helper();
+        assert!(
+            init_result.is_ok(),
+            "Init failed: {}",
+            init_result.err().unwrap()
+        );
+
+        let handle_msg = HandleMsg::Receive {
+            sender: HumanAddr("blah".to_string()),
+            from: HumanAddr("bob".to_string()),
+            amount: Uint128(500),
+            msg: None,
+        };
+        let _handle_result = handle(
+            &mut deps,
+            Env {
+                block: BlockInfo {
+                    height: 12_345,
+                    time: 10,
+                    chain_id: "cosmos-testnet-14002".to_string(),
+                },
+                message: MessageInfo {
+                    sender: HumanAddr("bidaddr".to_string()),
+                    sent_funds: vec![],
+                },
+                contract: cosmwasm_std::ContractInfo {
+                    address: HumanAddr::from(MOCK_CONTRACT_ADDR),
+                },
+                contract_key: Some("".to_string()),
+                contract_code_hash: "".to_string(),
+            },
+            handle_msg,
+        );
+        let handle_msg = HandleMsg::Receive {
+            sender: HumanAddr("blah".to_string()),
+            from: HumanAddr("charlie".to_string()),
+            amount: Uint128(500),
+            msg: None,
+        };
+        let _handle_result = handle(
+            &mut deps,
+            Env {
+                block: BlockInfo {
+                    height: 12_345,
+                    time: 12,
+                    chain_id: "cosmos-testnet-14002".to_string(),
+                },
+                message: MessageInfo {
+                    sender: HumanAddr("bidaddr".to_string()),
+                    sent_funds: vec![],
+                },
+                contract: cosmwasm_std::ContractInfo {
+                    address: HumanAddr::from(MOCK_CONTRACT_ADDR),
+                },
+                contract_key: Some("".to_string()),
+                contract_code_hash: "".to_string(),
+            },
+            handle_msg,
+        );
+        let handle_msg = HandleMsg::Receive {
+            sender: HumanAddr("blah".to_string()),
+            from: HumanAddr("david".to_string()),
+            amount: Uint128(25),
+            msg: None,
+        };
+        let _handle_result = handle(
+            &mut deps,
+            Env {
+                block: BlockInfo {
+                    height: 12_345,
+                    time: 5,
+                    chain_id: "cosmos-testnet-14002".to_string(),
+                },
+                message: MessageInfo {
+                    sender: HumanAddr("bidaddr".to_string()),
+                    sent_funds: vec![],
+                },
+                contract: cosmwasm_std::ContractInfo {
+                    address: HumanAddr::from(MOCK_CONTRACT_ADDR),
+                },
+                contract_key: Some("".to_string()),
+                contract_code_hash: "".to_string(),
+            },
+            handle_msg,
+        );
+        let state: State = load(&deps.storage, CONFIG_KEY).unwrap();
+        assert_eq!(state.num_bidders, 3);
+
+        let handle_msg = HandleMsg::Receive {
+            sender: HumanAddr("blah".to_string()),
+            from: HumanAddr("alice".to_string()),
+            amount: Uint128(20),
+            msg: None,
+        };
+        let _handle_result = handle(&mut deps, mock_env("selladdr", &[]), handle_msg);
+        let handle_msg = HandleMsg::Finalize {
+            new_ends_at: None,
+            new_minimum_bid: None,
+        };
+        let handle_result = handle(
+            &mut deps,
+            Env {
+                block: BlockInfo {
+                    height: 12_345,
+                    time: 1000,
+                    chain_id: "cosmos-testnet-14002".to_string(),
+                },
+                message: MessageInfo {
+                    sender: HumanAddr("charlie".to_string()),
+                    sent_funds: vec![],
+                },
+                contract: cosmwasm_std::ContractInfo {
+                    address: HumanAddr::from(MOCK_CONTRACT_ADDR),
+                },
+                contract_key: Some("".to_string()),
+                contract_code_hash: "".to_string(),
+            },
+            handle_msg,
+        );
+        let (
+            message,
+            winning_bid,
+            bid_decimals,
+            sell_tokens_received,
+            sell_decimals,
+            bid_tokens_received,
+        ) = extract_finalize_fields(&handle_result);
+        assert_eq!(winning_bid, Some(Uint128(500)));
+        assert_eq!(bid_decimals, Some(8));
+        assert_eq!(sell_tokens_received, None);
+        assert_eq!(sell_decimals, None);
+        assert_eq!(bid_tokens_received, Some(Uint128(500)));
+        assert!(message
+            .contains("Sale has been finalized.  Your bid did not win and has been returned"));
+
+        // test 3 bidders, seller closes
+        let (init_result, mut deps) = init_helper();
+        assert!(
+            init_result.is_ok(),
+            "Init failed: {}",
+            init_result.err().unwrap()
+        );
+
+        let handle_msg = HandleMsg::Receive {
+            sender: HumanAddr("blah".to_string()),
+            from: HumanAddr("bob".to_string()),
+            amount: Uint128(1000),
+            msg: None,
+        };
+        let _handle_result = handle(
+            &mut deps,
+            Env {
+                block: BlockInfo {
+                    height: 12_345,
+                    time: 10,
+                    chain_id: "cosmos-testnet-14002".to_string(),
+                },
+                message: MessageInfo {
+                    sender: HumanAddr("bidaddr".to_string()),
+                    sent_funds: vec![],
+                },
+                contract: cosmwasm_std::ContractInfo {
+                    address: HumanAddr::from(MOCK_CONTRACT_ADDR),
+                },
+                contract_key: Some("".to_string()),
+                contract_code_hash: "".to_string(),
+            },
+            handle_msg,
+        );
+        let handle_msg = HandleMsg::Receive {
+            sender: HumanAddr("blah".to_string()),
+            from: HumanAddr("charlie".to_string()),
+            amount: Uint128(200),
+            msg: None,
+        };
+        let _handle_result = handle(
+            &mut deps,
+            Env {
+                block: BlockInfo {
+                    height: 12_345,
+                    time: 12,
+                    chain_id: "cosmos-testnet-14002".to_string(),
+                },
+                message: MessageInfo {
+                    sender: HumanAddr("bidaddr".to_string()),
+                    sent_funds: vec![],
+                },
+                contract: cosmwasm_std::ContractInfo {
+                    address: HumanAddr::from(MOCK_CONTRACT_ADDR),
+                },
+                contract_key: Some("".to_string()),
+                contract_code_hash: "".to_string(),
+            },
+            handle_msg,
+        );
+        let handle_msg = HandleMsg::Receive {
+            sender: HumanAddr("blah".to_string()),
+            from: HumanAddr("david".to_string()),
+            amount: Uint128(25),
+            msg: None,
+        };
+        let _handle_result = handle(
+            &mut deps,
+            Env {
+                block: BlockInfo {
+                    height: 12_345,
+                    time: 5,
+                    chain_id: "cosmos-testnet-14002".to_string(),
+                },
+                message: MessageInfo {
+                    sender: HumanAddr("bidaddr".to_string()),
+                    sent_funds: vec![],
+                },
+                contract: cosmwasm_std::ContractInfo {
+                    address: HumanAddr::from(MOCK_CONTRACT_ADDR),
+                },
+                contract_key: Some("".to_string()),
+                contract_code_hash: "".to_string(),
+            },
+            handle_msg,
+        );
+        let state: State = load(&deps.storage, CONFIG_KEY).unwrap();
+        assert_eq!(state.num_bidders, 3);
+
+        let handle_msg = HandleMsg::Receive {
+            sender: HumanAddr("blah".to_string()),
+            from: HumanAddr("alice".to_string()),
+            amount: Uint128(20),
+            msg: None,
+        };
+        let _handle_result = handle(&mut deps, mock_env("selladdr", &[]), handle_msg);
+        let handle_msg = HandleMsg::Finalize {
+            new_ends_at: None,
+            new_minimum_bid: None,
+        };
+        let handle_result = handle(
+            &mut deps,
+            Env {
+                block: BlockInfo {
+                    height: 12_345,
+                    time: 1000,
+                    chain_id: "cosmos-testnet-14002".to_string(),
+                },
+                message: MessageInfo {
+                    sender: HumanAddr("alice".to_string()),
+                    sent_funds: vec![],
+                },
+                contract: cosmwasm_std::ContractInfo {
+                    address: HumanAddr::from(MOCK_CONTRACT_ADDR),
+                },
+                contract_key: Some("".to_string()),
+                contract_code_hash: "".to_string(),
+            },
+            handle_msg,
+        );
+        let (
+            message,
+            winning_bid,
+            bid_decimals,
+            sell_tokens_received,
+            sell_decimals,
+            bid_tokens_received,
+        ) = extract_finalize_fields(&handle_result);
+        assert_eq!(winning_bid, Some(Uint128(1000)));
+        assert_eq!(bid_decimals, Some(8));
+        assert_eq!(sell_tokens_received, None);
+        assert_eq!(sell_decimals, None);
+        assert_eq!(bid_tokens_received, Some(Uint128(1000)));
+        assert!(message.contains("Sale has been finalized.  You have been sent the winning bid"));
+        assert!(!message.contains("Sale has been finalized.  You have been sent the winning bid."));
+
+        // return all response when nothing is outstanding
+        let handle_msg = HandleMsg::ReturnAll { page_size: None };
+        let handle_result = handle(&mut deps, mock_env("bob", &[]), handle_msg);
+        assert!(handle_result.as_ref().unwrap().messages.is_empty());
+        let message = extract_msg(&handle_result);
+        assert!(message.contains("There are no outstanding funds to return"));
+
+        // return all response with a stranded bid and consignment
+        let mut state: State = load(&deps.storage, CONFIG_KEY).unwrap();
+        let stranded = deps
+            .api
+            .canonical_address(&HumanAddr("charlie".to_string()))
+            .unwrap();
+        let stranded_bid = Bid {
+            amount: 100,
+            timestamp: 0,
+            expiration: None,
+            memo: None,
+            referrer: None,
+        };
+        save(&mut deps.storage, stranded.as_slice(), &stranded_bid).unwrap();
+        record_bid(
+            &mut deps.storage,
+            &mut state,
+            stranded.as_slice(),
+            &stranded_bid,
+        )
+        .unwrap();
+        state.currently_consigned = 5;
+        save(&mut deps.storage, CONFIG_KEY, &state).unwrap();
+        let handle_msg = HandleMsg::ReturnAll { page_size: None };
+        let handle_result = handle(&mut deps, mock_env("bob", &[]), handle_msg);
+        assert_eq!(handle_result.as_ref().unwrap().messages.len(), 2);
+        let handle_answer: HandleAnswer =
+            from_binary(&handle_result.as_ref().unwrap().data.as_ref().unwrap()).unwrap();
+        let (message, bids_returned, consignment_returned, amounts) = match handle_answer {
+            HandleAnswer::ReturnAll {
+                message,
+                bids_returned,
+                consignment_returned,
+                amounts,
+                ..
+            } => (message, bids_returned, consignment_returned, amounts),
+            _ => panic!("Unexpected HandleAnswer"),
+        };
+        assert!(message.contains("Outstanding funds have been returned"));
+        assert_eq!(bids_returned, 1);
+        assert!(consignment_returned);
+        assert_eq!(amounts.len(), 2);
+        assert_eq!(
+            amounts[0].contract_address,
+            HumanAddr("bidaddr".to_string())
+        );
+        assert_eq!(amounts[0].amount, Uint128(100));
+        assert_eq!(
+            amounts[1].contract_address,
+            HumanAddr("selladdr".to_string())
+        );
+        assert_eq!(amounts[1].amount, Uint128(5));
+        let state: State = load(&deps.storage, CONFIG_KEY).unwrap();
+        assert_eq!(state.num_bidders, 0);
+        assert_eq!(state.currently_consigned, 0);
+
+        // test 3 bidders, stranger closes
+        let (init_result, mut deps) = init_helper();
+        assert!(
+            init_result.is_ok(),
+            "Init failed: {}",
+            init_result.err().unwrap()
+        );
+
+        let handle_msg = HandleMsg::Receive {
+            sender: HumanAddr("blah".to_string()),
+            from: HumanAddr("bob".to_string()),
+            amount: Uint128(1000),
+            msg: None,
+        };
+        let _handle_result = handle(
+            &mut deps,
+            Env {
+                block: BlockInfo {
+                    height: 12_345,
+                    time: 10,
+                    chain_id: "cosmos-testnet-14002".to_string(),
+                },
+                message: MessageInfo {
+                    sender: HumanAddr("bidaddr".to_string()),
+                    sent_funds: vec![],
+                },
+                contract: cosmwasm_std::ContractInfo {
+                    address: HumanAddr::from(MOCK_CONTRACT_ADDR),
+                },
+                contract_key: Some("".to_string()),
+                contract_code_hash: "".to_string(),
+            },
+            handle_msg,
+        );
+        let handle_msg = HandleMsg::Receive {
+            sender: HumanAddr("blah".to_string()),
+            from: HumanAddr("charlie".to_string()),
+            amount: Uint128(200),
+            msg: None,
+        };
+        let _handle_result = handle(
+            &mut deps,
+            Env {
+                block: BlockInfo {
+                    height: 12_345,
+                    time: 12,
+                    chain_id: "cosmos-testnet-14002".to_string(),
+                },
+                message: MessageInfo {
+                    sender: HumanAddr("bidaddr".to_string()),
+                    sent_funds: vec![],
+                },
+                contract: cosmwasm_std::ContractInfo {
+                    address: HumanAddr::from(MOCK_CONTRACT_ADDR),
+                },
+                contract_key: Some("".to_string()),
+                contract_code_hash: "".to_string(),
+            },
+            handle_msg,
+        );
+        let handle_msg = HandleMsg::Receive {
+            sender: HumanAddr("blah".to_string()),
+            from: HumanAddr("david".to_string()),
+            amount: Uint128(25),
+            msg: None,
+        };
+        let _handle_result = handle(
+            &mut deps,
+            Env {
+                block: BlockInfo {
+                    height: 12_345,
+                    time: 5,
+                    chain_id: "cosmos-testnet-14002".to_string(),
+                },
+                message: MessageInfo {
+                    sender: HumanAddr("bidaddr".to_string()),
+                    sent_funds: vec![],
+                },
+                contract: cosmwasm_std::ContractInfo {
+                    address: HumanAddr::from(MOCK_CONTRACT_ADDR),
+                },
+                contract_key: Some("".to_string()),
+                contract_code_hash: "".to_string(),
+            },
+            handle_msg,
+        );
+        let state: State = load(&deps.storage, CONFIG_KEY).unwrap();
+        assert_eq!(state.num_bidders, 3);
+
+        let handle_msg = HandleMsg::Receive {
+            sender: HumanAddr("blah".to_string()),
+            from: HumanAddr("alice".to_string()),
+            amount: Uint128(20),
+            msg: None,
+        };
+        let _handle_result = handle(&mut deps, mock_env("selladdr", &[]), handle_msg);
+        let handle_msg = HandleMsg::Finalize {
+            new_ends_at: None,
+            new_minimum_bid: None,
+        };
+        let handle_result = handle(
+            &mut deps,
+            Env {
+                block: BlockInfo {
+                    height: 12_345,
+                    time: 1000,
+                    chain_id: "cosmos-testnet-14002".to_string(),
+                },
+                message: MessageInfo {
+                    sender: HumanAddr("edmund".to_string()),
+                    sent_funds: vec![],
+                },
+                contract: cosmwasm_std::ContractInfo {
+                    address: HumanAddr::from(MOCK_CONTRACT_ADDR),
+                },
+                contract_key: Some("".to_string()),
+                contract_code_hash: "".to_string(),
+            },
+            handle_msg,
+        );
+        let (
+            message,
+            winning_bid,
+            bid_decimals,
+            sell_tokens_received,
+            sell_decimals,
+            bid_tokens_received,
+        ) = extract_finalize_fields(&handle_result);
+        assert_eq!(winning_bid, Some(Uint128(1000)));
+        assert_eq!(bid_decimals, Some(8));
+        assert_eq!(sell_tokens_received, None);
+        assert_eq!(sell_decimals, None);
+        assert_eq!(bid_tokens_received, None);
+        assert!(message.contains("Sale has been finalized"));
+        assert!(!message.contains("Sale has been finalized."));
+
+        // test already closed, stranger closes
+        let handle_msg = HandleMsg::Finalize {
+            new_ends_at: None,
+            new_minimum_bid: None,
+        };
+        let handle_result = handle(
+            &mut deps,
+            Env {
+                block: BlockInfo {
+                    height: 12_345,
+                    time: 1000,
+                    chain_id: "cosmos-testnet-14002".to_string(),
+                },
+                message: MessageInfo {
+                    sender: HumanAddr("bob".to_string()),
+                    sent_funds: vec![],
+                },
+                contract: cosmwasm_std::ContractInfo {
+                    address: HumanAddr::from(MOCK_CONTRACT_ADDR),
+                },
+                contract_key: Some("".to_string()),
+                contract_code_hash: "".to_string(),
+            },
+            handle_msg,
+        );
+        let (
+            message,
+            winning_bid,
+            bid_decimals,
+            sell_tokens_received,
+            sell_decimals,
+            bid_tokens_received,
+        ) = extract_finalize_fields(&handle_result);
+        assert_eq!(winning_bid, None);
+        assert_eq!(bid_decimals, None);
+        assert_eq!(sell_tokens_received, None);
+        assert_eq!(sell_decimals, None);
+        assert_eq!(bid_tokens_received, None);
+        assert!(message.contains("Auction has been closed"));
+        assert!(!message.contains("Auction has been closed."));
+
+        // test no bidders, seller closes
+        let (init_result, mut deps) = init_helper();
+        assert!(
+            init_result.is_ok(),
+            "Init failed: {}",
+            init_result.err().unwrap()
+        );
+
+        let handle_msg = HandleMsg::Receive {
+            sender: HumanAddr("blah".to_string()),
+            from: HumanAddr("alice".to_string()),
+            amount: Uint128(20),
+            msg: None,
+        };
+        let _handle_result = handle(&mut deps, mock_env("selladdr", &[]), handle_msg);
+        let handle_msg = HandleMsg::Finalize {
+            new_ends_at: None,
+            new_minimum_bid: None,
+        };
+        let handle_result = handle(
+            &mut deps,
+            Env {
+                block: BlockInfo {
+                    height: 12_345,
+                    time: 10,
+                    chain_id: "cosmos-testnet-14002".to_string(),
+                },
+                message: MessageInfo {
+                    sender: HumanAddr("alice".to_string()),
+                    sent_funds: vec![],
+                },
+                contract: cosmwasm_std::ContractInfo {
+                    address: HumanAddr::from(MOCK_CONTRACT_ADDR),
+                },
+                contract_key: Some("".to_string()),
+                contract_code_hash: "".to_string(),
+            },
+            handle_msg,
+        );
+        let (
+            message,
+            winning_bid,
+            bid_decimals,
+            sell_tokens_received,
+            sell_decimals,
+            bid_tokens_received,
+        ) = extract_finalize_fields(&handle_result);
+        assert_eq!(winning_bid, None);
+        assert_eq!(bid_decimals, None);
+        assert_eq!(sell_tokens_received, Some(Uint128(10)));
+        assert_eq!(sell_decimals, Some(4));
+        assert_eq!(bid_tokens_received, None);
+        assert!(message.contains("Auction has been closed.  Consigned tokens have been returned because there were no active bids"));
+
+        // test no bidders, stranger closes
+        let (init_result, mut deps) = init_helper();
+        assert!(
+            init_result.is_ok(),
+            "Init failed: {}",
+            init_result.err().unwrap()
+        );
+
+        let handle_msg = HandleMsg::Receive {
+            sender: HumanAddr("blah".to_string()),
+            from: HumanAddr("alice".to_string()),
+            amount: Uint128(20),
+            msg: None,
+        };
+        let _handle_result = handle(&mut deps, mock_env("selladdr", &[]), handle_msg);
+        let handle_msg = HandleMsg::Finalize {
+            new_ends_at: None,
+            new_minimum_bid: None,
+        };
+        let handle_result = handle(
+            &mut deps,
+            Env {
+                block: BlockInfo {
+                    height: 12_345,
+                    time: 1000,
+                    chain_id: "cosmos-testnet-14002".to_string(),
+                },
+                message: MessageInfo {
+                    sender: HumanAddr("bob".to_string()),
+                    sent_funds: vec![],
+                },
+                contract: cosmwasm_std::ContractInfo {
+                    address: HumanAddr::from(MOCK_CONTRACT_ADDR),
+                },
+                contract_key: Some("".to_string()),
+                contract_code_hash: "".to_string(),
+            },
+            handle_msg,
+        );
+        let (
+            message,
+            winning_bid,
+            bid_decimals,
+            sell_tokens_received,
+            sell_decimals,
+            bid_tokens_received,
+        ) = extract_finalize_fields(&handle_result);
+        assert_eq!(winning_bid, None);
+        assert_eq!(bid_decimals, None);
+        assert_eq!(sell_tokens_received, None);
+        assert_eq!(sell_decimals, None);
+        assert_eq!(bid_tokens_received, None);
+        assert!(message.contains("Auction has been closed"));
+        assert!(!message.contains("Auction has been closed."));
+    }
+
+    #[test]
+    fn test_query_view_bid() {
+        let (init_result, deps) = init_helper();
+        assert!(
+            init_result.is_ok(),
+            "Init failed: {}",
+            init_result.err().unwrap()
+        );
+
+        #[derive(Debug)]
+        struct MyMockQuerier {
+            pub is_valid: bool,
+        }
+        impl Querier for MyMockQuerier {
+            fn raw_query(&self, _request: &[u8]) -> QuerierResult {
+                Ok(to_binary(&IsKeyValidWrapper {
+                    is_key_valid: IsKeyValid {
+                        is_valid: self.is_valid,
+                    },
+                }))
+            }
+        }
+        let invalid_deps = deps.change_querier(|_| MyMockQuerier { is_valid: false });
+        // try wrong key
+        let query_msg = QueryMsg::ViewBid {
+            address: HumanAddr("bob".to_string()),
+            viewing_key: "wrong_key".to_string(),
+        };
+        let query_result = query(&invalid_deps, query_msg);
+        let error = extract_error_msg(query_result);
+        assert!(error.contains("Wrong viewing key"));
+
+        let (init_result, deps) = init_helper();
+        assert!(
+            init_result.is_ok(),
+            "Init failed: {}",
+            init_result.err().unwrap()
+        );
+        let valid_deps = deps.change_querier(|_| MyMockQuerier { is_valid: true });
+        // try no bid
+        let query_msg = QueryMsg::ViewBid {
+            address: HumanAddr("bob".to_string()),
+            viewing_key: "key".to_string(),
+        };
+        let query_result = query(&valid_deps, query_msg);
+        let (message, bid_decimals) = match from_binary(&query_result.unwrap()).unwrap() {
+            QueryAnswer::Bid {
+                message,
+                bid_decimals,
+                ..
+            } => (message, bid_decimals),
+            _ => panic!("Unexpected"),
+        };
+        assert!(message.contains("No active bid for address: bob"));
+        assert_eq!(bid_decimals, None);
+
+        // sanity check
+        let (init_result, deps) = init_helper();
+        assert!(
+            init_result.is_ok(),
+            "Init failed: {}",
+            init_result.err().unwrap()
+        );
+        let mut valid_deps = deps.change_querier(|_| MyMockQuerier { is_valid: true });
+        let handle_msg = HandleMsg::Receive {
+            sender: HumanAddr("blah".to_string()),
+            from: HumanAddr("bob".to_string()),
+            amount: Uint128(100),
+            msg: None,
+        };
+        let _handle_result = handle(&mut valid_deps, before_close_env("bidaddr"), handle_msg);
+        let query_msg = QueryMsg::ViewBid {
+            address: HumanAddr("bob".to_string()),
+            viewing_key: "key".to_string(),
+        };
+        let query_result = query(&valid_deps, query_msg);
+        let (message, amount_bid, bid_decimals, bid_timestamp) =
+            match from_binary(&query_result.unwrap()).unwrap() {
+                QueryAnswer::Bid {
+                    message,
+                    amount_bid,
+                    bid_decimals,
+                    bid_timestamp,
+                    ..
+                } => (message, amount_bid, bid_decimals, bid_timestamp),
+                _ => panic!("Unexpected"),
+            };
+        assert!(message.contains("Bid placed"));
+        assert_eq!(amount_bid, Some(Uint128(100)));
+        assert_eq!(bid_decimals, Some(8));
+        assert_eq!(bid_timestamp, Some(500));
+    }
+
+    #[test]
+    fn test_query_preview_bid() {
+        #[derive(Debug)]
+        struct MyMockQuerier {
+            pub is_valid: bool,
+        }
+        impl Querier for MyMockQuerier {
+            fn raw_query(&self, _request: &[u8]) -> QuerierResult {
+                Ok(to_binary(&IsKeyValidWrapper {
+                    is_key_valid: IsKeyValid {
+                        is_valid: self.is_valid,
+                    },
+                }))
+            }
+        }
+        let preview = |deps: &Extern<MockStorage, MockApi, MyMockQuerier>,
+                       amount: u128,
+                       current_time: Option<u64>| {
+            let query_msg = QueryMsg::PreviewBid {
+                address: HumanAddr("bob".to_string()),
+                viewing_key: "key".to_string(),
+                amount: Uint128(amount),
+                current_time,
+            };
+            let answer = query(deps, query_msg).unwrap();
+            assert_eq!(answer.len(), BID_ANSWER_SIZE);
+            match from_binary(&answer).unwrap() {
+                QueryAnswer::PreviewBid {
+                    accepted,
+                    message,
+                    minimum_bid,
+                    current_bid,
+                    amount_returned,
+                    ..
+                } => (accepted, message, minimum_bid, current_bid, amount_returned),
+                _ => panic!("Unexpected"),
+            }
+        };
+
+        // try wrong key
+        let (_init_result, deps) = init_helper();
+        let invalid_deps = deps.change_querier(|_| MyMockQuerier { is_valid: false });
+        let query_msg = QueryMsg::PreviewBid {
+            address: HumanAddr("bob".to_string()),
+            viewing_key: "wrong_key".to_string(),
+            amount: Uint128(100),
+            current_time: None,
+        };
+        let error = extract_error_msg(query(&invalid_deps, query_msg));
+        assert!(error.contains("Wrong viewing key"));
+
+        // a bid below the minimum would be returned
+        let (_init_result, deps) = init_helper();
+        let mut deps = deps.change_querier(|_| MyMockQuerier { is_valid: true });
+        let (accepted, message, minimum_bid, current_bid, returned) = preview(&deps, 5, None);
+        assert!(!accepted);
+        assert!(message.contains("less than the minimum bid"));
+        assert_eq!(minimum_bid, Uint128(10));
+        assert_eq!(current_bid, None);
+        assert_eq!(returned, Some(Uint128(5)));
+
+        // a new bid would replace the current one, which would be returned
+        let handle_msg = HandleMsg::Receive {
+            sender: HumanAddr("blah".to_string()),
+            from: HumanAddr("bob".to_string()),
+            amount: Uint128(100),
+            msg: None,
+        };
+        handle(&mut deps, before_close_env("bidaddr"), handle_msg).unwrap();
+        let (accepted, message, _, current_bid, returned) = preview(&deps, 50, Some(500));
+        assert!(accepted);
+        assert!(message.contains("current bid would be returned"));
+        assert_eq!(current_bid, Some(Uint128(100)));
+        assert_eq!(returned, Some(Uint128(100)));
+
+        // an unchanged bid would be returned
+        let (accepted, message, ..) = preview(&deps, 100, Some(500));
+        assert!(!accepted);
+        assert!(message.contains("same as your current bid"));
+
+        // and no bid is accepted once the closing time has passed
+        let (accepted, message, ..) = preview(&deps, 200, Some(1000));
+        assert!(!accepted);
+        assert!(message.contains("closing time has passed"));
+        let (accepted, ..) = preview(&deps, 200, None);
+        assert!(accepted);
+    }
+
+    #[test]
+    fn test_share_key_hash() {
+        let (init_result, mut deps) = init_helper();
+        assert!(
+            init_result.is_ok(),
+            "Init failed: {}",
+            init_result.err().unwrap()
+        );
+        let state: State = load(&deps.storage, CONFIG_KEY).unwrap();
+        let key_hash = Binary(derive_key_hash("key", &state.auction_addr));
+
+        // try sharing when not the factory
+        let handle_msg = HandleMsg::ShareKeyHash {
+            address: HumanAddr("bob".to_string()),
+            key_hash: key_hash.clone(),
+            epoch: 1,
+        };
+        let handle_result = handle(&mut deps, mock_env("bob", &[]), handle_msg);
+        let error = extract_error_msg(handle_result);
+        assert!(error.contains("Only the factory can share viewing key hashes"));
+
+        // place a bid
+        let handle_msg = HandleMsg::Receive {
+            sender: HumanAddr("blah".to_string()),
+            from: HumanAddr("bob".to_string()),
+            amount: Uint128(100),
+            msg: None,
+        };
+        let handle_result = handle(&mut deps, before_close_env("bidaddr"), handle_msg);
+        assert!(handle_result.is_ok());
+
+        // without a shared hash, the auction has to ask the factory, which does not exist here
+        let query_msg = QueryMsg::ViewBid {
+            address: HumanAddr("bob".to_string()),
+            viewing_key: "key".to_string(),
+        };
+        assert!(query(&deps, query_msg).is_err());
+
+        // share the hash and view the bid without asking the factory
+        let handle_msg = HandleMsg::ShareKeyHash {
+            address: HumanAddr("bob".to_string()),
+            key_hash,
+            epoch: 1,
+        };
+        let handle_result = handle(&mut deps, mock_env("factoryaddr", &[]), handle_msg);
+        assert!(handle_result.is_ok());
+        let query_msg = QueryMsg::ViewBid {
+            address: HumanAddr("bob".to_string()),
+            viewing_key: "key".to_string(),
+        };
+        let query_result = query(&deps, query_msg);
+        let amount_bid = match from_binary(&query_result.unwrap()).unwrap() {
+            QueryAnswer::Bid { amount_bid, .. } => amount_bid,
+            _ => panic!("Unexpected"),
+        };
+        assert_eq!(amount_bid, Some(Uint128(100)));
+
+        // a key that does not match the shared hash falls back to asking the factory
+        #[derive(Debug)]
+        struct MyMockQuerier {
+            pub is_valid: bool,
+        }
+        impl Querier for MyMockQuerier {
+            fn raw_query(&self, _request: &[u8]) -> QuerierResult {
+                Ok(to_binary(&IsKeyValidWrapper {
+                    is_key_valid: IsKeyValid {
+                        is_valid: self.is_valid,
+                    },
+                }))
+            }
+        }
+        let deps = deps.change_querier(|_| MyMockQuerier { is_valid: false });
+        let query_msg = QueryMsg::ViewBid {
+            address: HumanAddr("bob".to_string()),
+            viewing_key: "wrong_key".to_string(),
+        };
+        let query_result = query(&deps, query_msg);
+        let error = extract_error_msg(query_result);
+        assert!(error.contains("Wrong viewing key"));
+    }
+
+    #[test]
+    fn test_key_cache_epochs() {
+        let (init_result, mut deps) = init_helper();
+        assert!(
+            init_result.is_ok(),
+            "Init failed: {}",
+            init_result.err().unwrap()
+        );
+        let state: State = load(&deps.storage, CONFIG_KEY).unwrap();
+        let bob = HumanAddr("bob".to_string());
+        let share = |deps: &mut Extern<MockStorage, MockApi, MockQuerier>, key: &str, epoch| {
+            let handle_msg = HandleMsg::ShareKeyHash {
+                address: HumanAddr("bob".to_string()),
+                key_hash: Binary(derive_key_hash(key, &state.auction_addr)),
+                epoch,
+            };
+            let handle_result = handle(deps, mock_env("factoryaddr", &[]), handle_msg);
+            match from_binary(&handle_result.unwrap().data.unwrap()).unwrap() {
+                HandleAnswer::FactoryAction { message, .. } => message,
+                _ => panic!("Unexpected"),
+            }
+        };
+
+        // the mock querier has no factory, so only keys found in the cache are valid
+        share(&mut deps, "key", 1);
+        assert!(is_key_valid(&deps, &state, &bob, "key".to_string()).unwrap());
+        // sharing the same epoch again keeps the cached hash
+        share(&mut deps, "key", 1);
+        assert!(is_key_valid(&deps, &state, &bob, "key".to_string()).unwrap());
+
+        // a newer epoch invalidates the hashes cached before it
+        share(&mut deps, "new key", 2);
+        assert!(is_key_valid(&deps, &state, &bob, "new key".to_string()).unwrap());
+        assert!(is_key_valid(&deps, &state, &bob, "key".to_string()).is_err());
+
+        // and a hash from an older epoch is ignored
+        let message = share(&mut deps, "key", 1);
+        assert!(message.contains("older than the latest one shared"));
+        assert!(is_key_valid(&deps, &state, &bob, "key".to_string()).is_err());
+        assert!(is_key_valid(&deps, &state, &bob, "new key".to_string()).unwrap());
+    }
+
+    #[test]
+    fn test_forget_key_hashes() {
+        let (init_result, mut deps) = init_helper();
+        assert!(
+            init_result.is_ok(),
+            "Init failed: {}",
+            init_result.err().unwrap()
+        );
+        let state: State = load(&deps.storage, CONFIG_KEY).unwrap();
+        let bob = HumanAddr("bob".to_string());
+        let alice = HumanAddr("alice".to_string());
+        let handle_msg = HandleMsg::Receive {
+            sender: HumanAddr("blah".to_string()),
+            from: bob.clone(),
+            amount: Uint128(100),
+            msg: None,
+        };
+        let handle_result = handle(&mut deps, before_close_env("bidaddr"), handle_msg);
+        assert!(handle_result.is_ok());
+        for address in [&bob, &alice].iter() {
+            let handle_msg = HandleMsg::ShareKeyHash {
+                address: (*address).clone(),
+                key_hash: Binary(derive_key_hash("key", &state.auction_addr)),
+                epoch: 1,
+            };
+            let handle_result = handle(&mut deps, mock_env("factoryaddr", &[]), handle_msg);
+            assert!(handle_result.is_ok());
+        }
+        // the mock querier has no factory, so only keys checked locally are valid
+        assert!(is_key_valid(&deps, &state, &bob, "key".to_string()).unwrap());
+        assert!(is_key_valid(&deps, &state, &alice, "key".to_string()).unwrap());
+
+        // a bidder who retracts is no longer active, so the factory would not share a new key
+        let handle_result = handle(&mut deps, before_close_env("bob"), HandleMsg::RetractBid {});
+        assert!(handle_result.is_ok());
+        assert!(is_key_valid(&deps, &state, &bob, "key".to_string()).is_err());
+        assert!(is_key_valid(&deps, &state, &alice, "key".to_string()).unwrap());
+
+        // hashes shared by the old factory are forgotten when the auction migrates
+        let handle_msg = HandleMsg::Migrate {
+            factory: FactoryInfo {
+                code_hash: "newhash".to_string(),
+                address: HumanAddr("newfactory".to_string()),
+            },
+            index: 7,
+            callback_key: "newkey".to_string(),
+        };
+        let handle_result = handle(&mut deps, mock_env("factoryaddr", &[]), handle_msg);
+        assert!(handle_result.is_ok());
+        let state: State = load(&deps.storage, CONFIG_KEY).unwrap();
+        assert!(is_key_valid(&deps, &state, &alice, "key".to_string()).is_err());
+    }
+
+    #[test]
+    fn test_auto_finalize() {
+        let (init_result, mut deps) = init_helper();
+        assert!(
+            init_result.is_ok(),
+            "Init failed: {}",
+            init_result.err().unwrap()
+        );
+        let handle_msg = HandleMsg::Receive {
+            sender: HumanAddr("blah".to_string()),
+            from: HumanAddr("alice".to_string()),
+            amount: Uint128(10),
+            msg: None,
+        };
+        let _handle_result = handle(&mut deps, before_close_env("selladdr"), handle_msg);
+        let handle_msg = HandleMsg::Receive {
+            sender: HumanAddr("blah".to_string()),
+            from: HumanAddr("bob".to_string()),
+            amount: Uint128(100),
+            msg: None,
+        };
+        let _handle_result = handle(&mut deps, before_close_env("bidaddr"), handle_msg);
+
+        // a bid after the closing time finalizes the auction and is returned
+        let handle_msg = HandleMsg::Receive {
+            sender: HumanAddr("blah".to_string()),
+            from: HumanAddr("charlie".to_string()),
+            amount: Uint128(200),
+            msg: None,
+        };
+        let mut env = mock_env("bidaddr", &[]);
+        env.block.time = 1000;
+        let handle_result = handle(&mut deps, env, handle_msg);
+        // pay seller, send lot to winner, close with factory, and return the late bid
+        assert_eq!(handle_result.as_ref().unwrap().messages.len(), 4);
+        let log = extract_log(handle_result);
+        assert!(log.contains("The auction has been finalized and bid tokens have been returned"));
+        assert!(log.contains("\"amount_returned\":\"200\""));
+        let state: State = load(&deps.storage, CONFIG_KEY).unwrap();
+        assert!(state.is_completed);
+        assert_eq!(state.winning_bid, 100);
+        assert_eq!(state.num_bidders, 0);
+
+        // a retraction after the closing time finalizes the auction
+        let (init_result, mut deps) = init_helper();
+        assert!(
+            init_result.is_ok(),
+            "Init failed: {}",
+            init_result.err().unwrap()
+        );
+        let handle_msg = HandleMsg::Receive {
+            sender: HumanAddr("blah".to_string()),
+            from: HumanAddr("bob".to_string()),
+            amount: Uint128(100),
+            msg: None,
+        };
+        let _handle_result = handle(&mut deps, before_close_env("bidaddr"), handle_msg);
+        let handle_msg = HandleMsg::RetractBid {};
+        let mut env = mock_env("bob", &[]);
+        env.block.time = 1000;
+        let handle_result = handle(&mut deps, env, handle_msg);
+        let (message, _, _, _, _, bid_tokens_received) = extract_finalize_fields(&handle_result);
+        assert!(message.contains("Auction has been closed"));
+        assert_eq!(bid_tokens_received, Some(Uint128(100)));
+        let state: State = load(&deps.storage, CONFIG_KEY).unwrap();
+        assert!(state.is_completed);
+        assert_eq!(state.num_bidders, 0);
+    }
+
+    #[test]
+    fn test_cancel_unconsigned() {
+        let (init_result, mut deps) = init_helper();
+        assert!(
+            init_result.is_ok(),
+            "Init failed: {}",
+            init_result.err().unwrap()
+        );
+        // try an auction without a consignment deadline
+        let handle_msg = HandleMsg::CancelUnconsigned {};
+        let mut env = mock_env("charlie", &[]);
+        env.block.time = 700;
+        let handle_result = handle(&mut deps, env, handle_msg);
+        let error = extract_error_msg(handle_result);
+        assert!(error.contains("This auction does not have a consignment deadline"));
+
+        let mut state: State = load(&deps.storage, CONFIG_KEY).unwrap();
+        state.consign_by = Some(600);
+        save(&mut deps.storage, CONFIG_KEY, &state).unwrap();
+        // partially consign and place a bid
+        let handle_msg = HandleMsg::Receive {
+            sender: HumanAddr("blah".to_string()),
+            from: HumanAddr("alice".to_string()),
+            amount: Uint128(5),
+            msg: None,
+        };
+        let _handle_result = handle(&mut deps, before_close_env("selladdr"), handle_msg);
+        let handle_msg = HandleMsg::Receive {
+            sender: HumanAddr("blah".to_string()),
+            from: HumanAddr("bob".to_string()),
+            amount: Uint128(100),
+            msg: None,
+        };
+        let _handle_result = handle(&mut deps, before_close_env("bidaddr"), handle_msg);
+
+        // try before the deadline
+        let handle_msg = HandleMsg::CancelUnconsigned {};
+        let handle_result = handle(&mut deps, before_close_env("charlie"), handle_msg);
+        let error = extract_error_msg(handle_result);
+        assert!(error.contains("The seller has until 600 to consign the tokens for sale"));
+
+        // try consigning after the deadline
+        let handle_msg = HandleMsg::Receive {
+            sender: HumanAddr("blah".to_string()),
+            from: HumanAddr("alice".to_string()),
+            amount: Uint128(5),
+            msg: None,
+        };
+        let mut env = mock_env("selladdr", &[]);
+        env.block.time = 601;
+        let handle_result = handle(&mut deps, env, handle_msg);
+        let log = extract_log(handle_result);
+        assert!(log.contains("The consignment deadline has passed"));
+        assert!(log.contains("\"status\":\"failure\""));
+        assert!(log.contains("\"amount_returned\":\"5\""));
+
+        // anyone may cancel after the deadline
+        let handle_msg = HandleMsg::CancelUnconsigned {};
+        let mut env = mock_env("charlie", &[]);
+        env.block.time = 601;
+        let handle_result = handle(&mut deps, env, handle_msg);
+        // return the bid, return the partial consignment, and close with the factory
+        assert_eq!(handle_result.as_ref().unwrap().messages.len(), 3);
+        let message = extract_msg(&handle_result);
+        assert!(message.contains("Auction has been cancelled"));
+        let state: State = load(&deps.storage, CONFIG_KEY).unwrap();
+        assert!(state.is_completed);
+        assert!(state.is_cancelled);
+        assert_eq!(state.num_bidders, 0);
+        assert_eq!(state.currently_consigned, 0);
+
+        // try cancelling again
+        let handle_msg = HandleMsg::CancelUnconsigned {};
+        let mut env = mock_env("charlie", &[]);
+        env.block.time = 700;
+        let handle_result = handle(&mut deps, env, handle_msg);
+        let error = extract_error_msg(handle_result);
+        assert!(error.contains("Auction has already been closed"));
+    }
+
+    #[test]
+    fn test_force_close() {
+        let (init_result, mut deps) = init_helper();
+        assert!(
+            init_result.is_ok(),
+            "Init failed: {}",
+            init_result.err().unwrap()
+        );
+        // consign, post a deposit, and place a bid
+        let mut state: State = load(&deps.storage, CONFIG_KEY).unwrap();
+        state.deposit = 50;
+        save(&mut deps.storage, CONFIG_KEY, &state).unwrap();
+        let handle_msg = HandleMsg::Receive {
+            sender: HumanAddr("blah".to_string()),
+            from: HumanAddr("alice".to_string()),
+            amount: Uint128(10),
+            msg: None,
+        };
+        let _handle_result = handle(&mut deps, before_close_env("selladdr"), handle_msg);
+        let handle_msg = HandleMsg::Receive {
+            sender: HumanAddr("blah".to_string()),
+            from: HumanAddr("alice".to_string()),
+            amount: Uint128(50),
+            msg: Some(to_binary(&ReceiveMsg::Consign {}).unwrap()),
+        };
+        let _handle_result = handle(&mut deps, before_close_env("bidaddr"), handle_msg);
+        let handle_msg = HandleMsg::Receive {
+            sender: HumanAddr("blah".to_string()),
+            from: HumanAddr("bob".to_string()),
+            amount: Uint128(100),
+            msg: None,
+        };
+        let _handle_result = handle(&mut deps, before_close_env("bidaddr"), handle_msg);
+        let state: State = load(&deps.storage, CONFIG_KEY).unwrap();
+        assert!(state.tokens_consigned);
+        assert_eq!(state.deposit_posted, 50);
+        assert_eq!(state.num_bidders, 1);
+
+        // try before the timeout
+        let force_at = 1000 + FORCE_FINALIZE_GRACE;
+        let handle_msg = HandleMsg::ForceClose {};
+        let mut env = mock_env("charlie", &[]);
+        env.block.time = force_at - 1;
+        let handle_result = handle(&mut deps, env, handle_msg);
+        let error = extract_error_msg(handle_result);
+        assert!(error.contains(&format!(
+            "The auction can not be force closed until {}",
+            force_at
+        )));
+
+        // anyone may force close after the timeout
+        let handle_msg = HandleMsg::ForceClose {};
+        let mut env = mock_env("charlie", &[]);
+        env.block.time = force_at;
+        let handle_result = handle(&mut deps, env, handle_msg);
+        // return the bid, the lot, the deposit, and close with the factory
+        assert_eq!(handle_result.as_ref().unwrap().messages.len(), 4);
+        let (message, winning_bid, ..) = extract_finalize_fields(&handle_result);
+        assert!(message.contains("Auction has been force closed without a sale"));
+        assert_eq!(winning_bid, None);
+        let state: State = load(&deps.storage, CONFIG_KEY).unwrap();
+        assert!(state.is_completed);
+        assert!(state.is_force_closed);
+        assert_eq!(state.num_bidders, 0);
+        assert_eq!(state.currently_consigned, 0);
+        assert_eq!(state.deposit_posted, 0);
+        assert_eq!(state.winning_bid, 0);
+
+        // try force closing again
+        let handle_msg = HandleMsg::ForceClose {};
+        let mut env = mock_env("charlie", &[]);
+        env.block.time = force_at + 1;
+        let handle_result = handle(&mut deps, env, handle_msg);
+        let error = extract_error_msg(handle_result);
+        assert!(error.contains("Auction has already been closed"));
+    }
+
+    #[test]
+    fn test_bid_expiration() {
+        let (init_result, mut deps) = init_helper();
+        assert!(
+            init_result.is_ok(),
+            "Init failed: {}",
+            init_result.err().unwrap()
+        );
+        let handle_msg = HandleMsg::Receive {
+            sender: HumanAddr("blah".to_string()),
+            from: HumanAddr("alice".to_string()),
+            amount: Uint128(10),
+            msg: None,
+        };
+        let _handle_result = handle(&mut deps, before_close_env("selladdr"), handle_msg);
+
+        // try an expiration that has already passed
+        let handle_msg = HandleMsg::Receive {
+            sender: HumanAddr("blah".to_string()),
+            from: HumanAddr("bob".to_string()),
+            amount: Uint128(100),
+            msg: Some(
+                to_binary(&ReceiveMsg::PlaceBid {
+                    expiration: Some(400),
+                    max_bid: None,
+                    unit_price: None,
+                    memo: None,
+                    referrer: None,
+                })
+                .unwrap(),
+            ),
+        };
+        let handle_result = handle(&mut deps, before_close_env("bidaddr"), handle_msg);
+        let log = extract_log(handle_result);
+        assert!(log.contains("Bid expiration 400 must be later than the current block time"));
+        assert!(log.contains("\"status\":\"failure\""));
+        assert!(log.contains("\"amount_returned\":\"100\""));
+
+        // place an expiring bid and one that does not expire
+        let handle_msg = HandleMsg::Receive {
+            sender: HumanAddr("blah".to_string()),
+            from: HumanAddr("bob".to_string()),
+            amount: Uint128(100),
+            msg: Some(
+                to_binary(&ReceiveMsg::PlaceBid {
+                    expiration: Some(600),
+                    max_bid: None,
+                    unit_price: None,
+                    memo: None,
+                    referrer: None,
+                })
+                .unwrap(),
+            ),
+        };
+        let handle_result = handle(&mut deps, before_close_env("bidaddr"), handle_msg);
+        assert!(extract_log(handle_result).contains("Bid accepted"));
+        let handle_msg = HandleMsg::Receive {
+            sender: HumanAddr("blah".to_string()),
+            from: HumanAddr("charlie".to_string()),
+            amount: Uint128(50),
+            msg: None,
+        };
+        let _handle_result = handle(&mut deps, before_close_env("bidaddr"), handle_msg);
+        let bob_raw = deps
+            .api
+            .canonical_address(&HumanAddr("bob".to_string()))
+            .unwrap();
+        let bid: Bid = load(&deps.storage, bob_raw.as_slice()).unwrap();
+        assert_eq!(bid.expiration, Some(600));
+
+        // try reclaiming before the bid expired
+        let handle_msg = HandleMsg::ReclaimExpiredBid {};
+        let handle_result = handle(&mut deps, before_close_env("bob"), handle_msg);
+        let error = extract_error_msg(handle_result);
+        assert!(error.contains("Your bid has not expired"));
+
+        // the expired bid is ignored at settlement and returned
+        let handle_msg = HandleMsg::Finalize {
+            new_ends_at: None,
+            new_minimum_bid: None,
+        };
+        let mut env = mock_env("david", &[]);
+        env.block.time = 1000;
+        let handle_result = handle(&mut deps, env, handle_msg);
+        // pay seller, send lot to winner, return the expired bid, and close with the factory
+        assert_eq!(handle_result.as_ref().unwrap().messages.len(), 4);
+        let (message, winning_bid, _, _, _, _) = extract_finalize_fields(&handle_result);
+        assert!(message.contains("Sale has been finalized"));
+        assert_eq!(winning_bid, Some(Uint128(50)));
+        let state: State = load(&deps.storage, CONFIG_KEY).unwrap();
+        assert_eq!(state.num_bidders, 0);
+
+        // reclaim an expired bid
+        let (init_result, mut deps) = init_helper();
+        assert!(
+            init_result.is_ok(),
+            "Init failed: {}",
+            init_result.err().unwrap()
+        );
+        let handle_msg = HandleMsg::Receive {
+            sender: HumanAddr("blah".to_string()),
+            from: HumanAddr("bob".to_string()),
+            amount: Uint128(100),
+            msg: Some(
+                to_binary(&ReceiveMsg::PlaceBid {
+                    expiration: Some(600),
+                    max_bid: None,
+                    unit_price: None,
+                    memo: None,
+                    referrer: None,
+                })
+                .unwrap(),
+            ),
+        };
+        let _handle_result = handle(&mut deps, before_close_env("bidaddr"), handle_msg);
+        let handle_msg = HandleMsg::ReclaimExpiredBid {};
+        let mut env = mock_env("bob", &[]);
+        env.block.time = 700;
+        let handle_result = handle(&mut deps, env, handle_msg);
+        assert_eq!(handle_result.as_ref().unwrap().messages.len(), 2);
+        let message = extract_msg(&handle_result);
+        assert!(message.contains("Bid retracted.  Tokens have been returned"));
+        let state: State = load(&deps.storage, CONFIG_KEY).unwrap();
+        assert_eq!(state.num_bidders, 0);
+    }
+
+    #[test]
+    fn test_bid_expiring_after_close() {
+        let (init_result, mut deps) = init_helper();
+        assert!(
+            init_result.is_ok(),
+            "Init failed: {}",
+            init_result.err().unwrap()
+        );
+        let handle_msg = HandleMsg::Receive {
+            sender: HumanAddr("blah".to_string()),
+            from: HumanAddr("alice".to_string()),
+            amount: Uint128(10),
+            msg: None,
+        };
+        let _handle_result = handle(&mut deps, before_close_env("selladdr"), handle_msg);
+        // bob's bid expires after the closing time, but before the auction is finalized
+        let handle_msg = HandleMsg::Receive {
+            sender: HumanAddr("blah".to_string()),
+            from: HumanAddr("bob".to_string()),
+            amount: Uint128(100),
+            msg: Some(
+                to_binary(&ReceiveMsg::PlaceBid {
+                    expiration: Some(1100),
+                    max_bid: None,
+                    unit_price: None,
+                    memo: None,
+                    referrer: None,
+                })
+                .unwrap(),
+            ),
+        };
+        let handle_result = handle(&mut deps, before_close_env("bidaddr"), handle_msg);
+        assert!(extract_log(handle_result).contains("Bid accepted"));
+        let handle_msg = HandleMsg::Receive {
+            sender: HumanAddr("blah".to_string()),
+            from: HumanAddr("charlie".to_string()),
+            amount: Uint128(50),
+            msg: None,
+        };
+        let _handle_result = handle(&mut deps, before_close_env("bidaddr"), handle_msg);
+
+        // the bid was active when bidding ended, so it can not be reclaimed as expired
+        let mut env = mock_env("bob", &[]);
+        env.block.time = 1200;
+        let handle_result = handle(&mut deps, env, HandleMsg::ReclaimExpiredBid {});
+        let error = extract_error_msg(handle_result);
+        assert!(error.contains("Your bid has not expired"));
+
+        // and it still wins when the auction is finalized later
+        let handle_msg = HandleMsg::Finalize {
+            new_ends_at: None,
+            new_minimum_bid: None,
+        };
+        let mut env = mock_env("david", &[]);
+        env.block.time = 1200;
+        let handle_result = handle(&mut deps, env, handle_msg);
+        let (message, winning_bid, _, _, _, _) = extract_finalize_fields(&handle_result);
+        assert!(message.contains("Sale has been finalized"));
+        assert_eq!(winning_bid, Some(Uint128(100)));
+    }
+
+    #[test]
+    fn test_receive_msg() {
+        let (init_result, mut deps) = init_helper();
+        assert!(
+            init_result.is_ok(),
+            "Init failed: {}",
+            init_result.err().unwrap()
+        );
+        let mut state: State = load(&deps.storage, CONFIG_KEY).unwrap();
+        state.buy_now_price = Some(200);
+        save(&mut deps.storage, CONFIG_KEY, &state).unwrap();
+
+        // try a msg that can not be parsed
+        let handle_msg = HandleMsg::Receive {
+            sender: HumanAddr("blah".to_string()),
+            from: HumanAddr("alice".to_string()),
+            amount: Uint128(10),
+            msg: Some(Binary::from(b"not a receive msg".to_vec())),
+        };
+        let handle_result = handle(&mut deps, before_close_env("selladdr"), handle_msg);
+        let error = extract_error_msg(handle_result);
+        assert!(error.contains("Unable to parse the msg sent with the tokens"));
+
+        // try bidding with sell tokens
+        let handle_msg = HandleMsg::Receive {
+            sender: HumanAddr("blah".to_string()),
+            from: HumanAddr("alice".to_string()),
+            amount: Uint128(10),
+            msg: Some(
+                to_binary(&ReceiveMsg::PlaceBid {
+                    expiration: None,
+                    max_bid: None,
+                    unit_price: None,
+                    memo: None,
+                    referrer: None,
+                })
+                .unwrap(),
+            ),
+        };
+        let handle_result = handle(&mut deps, before_close_env("selladdr"), handle_msg);
+        let log = extract_log(handle_result);
+        assert!(log.contains("Only bid tokens can be used to bid or buy now"));
+        assert!(log.contains("\"status\":\"failure\""));
+        assert!(log.contains("\"amount_returned\":\"10\""));
+
+        // consign explicitly
+        let handle_msg = HandleMsg::Receive {
+            sender: HumanAddr("blah".to_string()),
+            from: HumanAddr("alice".to_string()),
+            amount: Uint128(10),
+            msg: Some(to_binary(&ReceiveMsg::Consign {}).unwrap()),
+        };
+        let handle_result = handle(&mut deps, before_close_env("selladdr"), handle_msg);
+        assert!(extract_log(handle_result)
+            .contains("Tokens to be sold have been consigned to the auction"));
+
+        // try consigning bid tokens as a bidder
+        let handle_msg = HandleMsg::Receive {
+            sender: HumanAddr("blah".to_string()),
+            from: HumanAddr("charlie".to_string()),
+            amount: Uint128(100),
+            msg: Some(to_binary(&ReceiveMsg::Consign {}).unwrap()),
+        };
+        let handle_result = handle(&mut deps, before_close_env("bidaddr"), handle_msg);
+        let log = extract_log(handle_result);
+        assert!(log.contains("Bid tokens can only be consigned by the seller as a deposit"));
+        assert!(log.contains("\"status\":\"failure\""));
+        assert!(log.contains("\"amount_returned\":\"100\""));
+
+        // try posting a deposit the auction does not need
+        let handle_msg = HandleMsg::Receive {
+            sender: HumanAddr("blah".to_string()),
+            from: HumanAddr("alice".to_string()),
+            amount: Uint128(100),
+            msg: Some(to_binary(&ReceiveMsg::Consign {}).unwrap()),
+        };
+        let handle_result = handle(&mut deps, before_close_env("bidaddr"), handle_msg);
+        let log = extract_log(handle_result);
+        assert!(log.contains("This auction is not expecting a deposit"));
+        assert!(log.contains("\"status\":\"failure\""));
+        assert!(log.contains("\"amount_returned\":\"100\""));
+
+        // bid with a maximum
+        let handle_msg = HandleMsg::Receive {
+            sender: HumanAddr("blah".to_string()),
+            from: HumanAddr("bob".to_string()),
+            amount: Uint128(80),
+            msg: Some(
+                to_binary(&ReceiveMsg::PlaceBid {
+                    expiration: None,
+                    max_bid: Some(Uint128(50)),
+                    unit_price: None,
+                    memo: None,
+                    referrer: None,
+                })
+                .unwrap(),
+            ),
+        };
+        let handle_result = handle(&mut deps, before_close_env("bidaddr"), handle_msg);
+        let log = extract_log(handle_result);
+        assert!(log.contains("Tokens sent above your maximum bid have been returned"));
+        assert!(log.contains("\"amount_returned\":\"30\""));
+        let bob_raw = deps
+            .api
+            .canonical_address(&HumanAddr("bob".to_string()))
+            .unwrap();
+        let bid: Bid = load(&deps.storage, bob_raw.as_slice()).unwrap();
+        assert_eq!(bid.amount, 50);
+
+        // try buying below the buy now price
+        let handle_msg = HandleMsg::Receive {
+            sender: HumanAddr("blah".to_string()),
+            from: HumanAddr("david".to_string()),
+            amount: Uint128(150),
+            msg: Some(to_binary(&ReceiveMsg::BuyNow {}).unwrap()),
+        };
+        let handle_result = handle(&mut deps, before_close_env("bidaddr"), handle_msg);
+        let log = extract_log(handle_result);
+        assert!(log.contains("The buy now price is 200"));
+        assert!(log.contains("\"status\":\"failure\""));
+        assert!(log.contains("\"amount_returned\":\"150\""));
+
+        // buy now
+        let handle_msg = HandleMsg::Receive {
+            sender: HumanAddr("blah".to_string()),
+            from: HumanAddr("david".to_string()),
+            amount: Uint128(250),
+            msg: Some(to_binary(&ReceiveMsg::BuyNow {}).unwrap()),
+        };
+        let handle_result = handle(&mut deps, before_close_env("bidaddr"), handle_msg);
+        // register the buyer, pay seller, send lot to the buyer, return the other bid, close with
+        // the factory, and return the excess
+        assert_eq!(handle_result.as_ref().unwrap().messages.len(), 6);
+        let log = extract_log(handle_result);
+        assert!(log.contains("You have bought the lot at the buy now price"));
+        assert!(log.contains("\"amount_returned\":\"50\""));
+        let state: State = load(&deps.storage, CONFIG_KEY).unwrap();
+        assert!(state.is_completed);
+        assert_eq!(state.winning_bid, 200);
+        assert_eq!(state.num_bidders, 0);
     }
 
-    fn extract_finalize_fields(
-        resp: &StdResult<HandleResponse>,
-    ) -> (
-        String,
-        Option<Uint128>,
-        Option<u8>,
-        Option<Uint128>,
-        Option<u8>,
-        Option<Uint128>,
-    ) {
-        let handle_answer: HandleAnswer =
-            from_binary(&resp.as_ref().unwrap().data.as_ref().unwrap()).unwrap();
-        match handle_answer {
-            HandleAnswer::CloseAuction {
-                message,
-                winning_bid,
-                bid_decimals,
-                sell_tokens_received,
-                sell_decimals,
-                bid_tokens_received,
-                ..
-            } => (
-                message,
-                winning_bid,
-                bid_decimals,
-                sell_tokens_received,
-                sell_decimals,
-                bid_tokens_received,
-            ),
-            _ => panic!("Unexpected HandleAnswer"),
+    #[test]
+    fn test_increase_lot() {
+        let (init_result, mut deps) = init_helper();
+        assert!(
+            init_result.is_ok(),
+            "Init failed: {}",
+            init_result.err().unwrap()
+        );
+        let increase = |from: &str, amount: u128| HandleMsg::Receive {
+            sender: HumanAddr("blah".to_string()),
+            from: HumanAddr(from.to_string()),
+            amount: Uint128(amount),
+            msg: Some(to_binary(&ReceiveMsg::IncreaseLot {}).unwrap()),
+        };
+
+        // try increasing before the lot is consigned
+        let handle_result = handle(
+            &mut deps,
+            before_close_env("selladdr"),
+            increase("alice", 5),
+        );
+        let log = extract_log(handle_result);
+        assert!(log.contains("must be fully consigned before the lot can be increased"));
+        assert!(log.contains("\"status\":\"failure\""));
+        assert!(log.contains("\"amount_returned\":\"5\""));
+        let handle_msg = HandleMsg::Receive {
+            sender: HumanAddr("blah".to_string()),
+            from: HumanAddr("alice".to_string()),
+            amount: Uint128(10),
+            msg: None,
+        };
+        let _used = handle(&mut deps, before_close_env("selladdr"), handle_msg);
+
+        // try increasing as someone other than the seller, or with bid tokens
+        let handle_result = handle(&mut deps, before_close_env("selladdr"), increase("bob", 5));
+        let log = extract_log(handle_result);
+        assert!(log.contains("Only the auction seller can increase the lot"));
+        assert!(log.contains("\"status\":\"failure\""));
+        assert!(log.contains("\"amount_returned\":\"5\""));
+        let handle_result = handle(&mut deps, before_close_env("bidaddr"), increase("alice", 5));
+        let log = extract_log(handle_result);
+        assert!(log.contains("Only the sell token can be used to increase the lot"));
+        assert!(log.contains("\"status\":\"failure\""));
+        assert!(log.contains("\"amount_returned\":\"5\""));
+
+        // increase the lot and tell the factory
+        let handle_result = handle(
+            &mut deps,
+            before_close_env("selladdr"),
+            increase("alice", 5),
+        );
+        let messages = handle_result.as_ref().unwrap().messages.clone();
+        assert_eq!(messages.len(), 1);
+        if let CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr, msg, ..
+        }) = &messages[0]
+        {
+            assert_eq!(contract_addr.0, "factoryaddr");
+            let callback = String::from_utf8(msg.as_slice().to_vec()).unwrap();
+            assert!(callback.contains("\"sell_amount\":\"15\""));
+        } else {
+            panic!("Expected a factory callback");
         }
+        let log = extract_log(handle_result);
+        assert!(log.contains("\"sell_amount\":\"15\""));
+        let state: State = load(&deps.storage, CONFIG_KEY).unwrap();
+        assert_eq!(state.sell_amount, 15);
+        assert_eq!(state.currently_consigned, 15);
+        assert!(state.tokens_consigned);
+
+        // try increasing once there is a bid
+        let handle_msg = HandleMsg::Receive {
+            sender: HumanAddr("blah".to_string()),
+            from: HumanAddr("bob".to_string()),
+            amount: Uint128(20),
+            msg: None,
+        };
+        let _used = handle(&mut deps, before_close_env("bidaddr"), handle_msg);
+        let handle_result = handle(
+            &mut deps,
+            before_close_env("selladdr"),
+            increase("alice", 5),
+        );
+        let log = extract_log(handle_result);
+        assert!(log.contains("can not be increased once bids have been placed"));
+        assert!(log.contains("\"status\":\"failure\""));
+        assert!(log.contains("\"amount_returned\":\"5\""));
     }
 
-    fn extract_amount_returned(resp: &StdResult<HandleResponse>) -> (Option<Uint128>, Option<u8>) {
+    #[test]
+    fn test_change_sell_amount() {
+        let (init_result, mut deps) = init_helper();
+        assert!(
+            init_result.is_ok(),
+            "Init failed: {}",
+            init_result.err().unwrap()
+        );
+        let change = |amount: u128| HandleMsg::ChangeSellAmount {
+            new_amount: Uint128(amount),
+        };
+
+        // only the seller may change it, and not to 0
+        let handle_result = handle(&mut deps, mock_env("bob", &[]), change(5));
+        let error = extract_error_msg(handle_result);
+        assert!(error.contains("Only the auction seller can change the sell amount"));
+        let handle_result = handle(&mut deps, mock_env("alice", &[]), change(0));
+        let error = extract_error_msg(handle_result);
+        assert!(error.contains("Sell amount must be greater than 0"));
+
+        // lower the sell amount and tell the factory
+        let handle_result = handle(&mut deps, mock_env("alice", &[]), change(6));
+        assert_eq!(handle_result.as_ref().unwrap().messages.len(), 1);
         let handle_answer: HandleAnswer =
-            from_binary(&resp.as_ref().unwrap().data.as_ref().unwrap()).unwrap();
+            from_binary(&handle_result.unwrap().data.unwrap()).unwrap();
         match handle_answer {
-            HandleAnswer::RetractBid {
-                amount_returned,
-                bid_decimals,
-                ..
-            } => (amount_returned, bid_decimals),
+            HandleAnswer::ChangeSellAmount { sell_amount, .. } => {
+                assert_eq!(sell_amount, Uint128(6))
+            }
             _ => panic!("Unexpected HandleAnswer"),
         }
+        let state: State = load(&deps.storage, CONFIG_KEY).unwrap();
+        assert_eq!(state.sell_amount, 6);
+
+        // try changing once there is a bid
+        let handle_msg = HandleMsg::Receive {
+            sender: HumanAddr("blah".to_string()),
+            from: HumanAddr("bob".to_string()),
+            amount: Uint128(20),
+            msg: None,
+        };
+        let _used = handle(&mut deps, before_close_env("bidaddr"), handle_msg);
+        let handle_result = handle(&mut deps, mock_env("alice", &[]), change(8));
+        let error = extract_error_msg(handle_result);
+        assert!(error.contains("can not be changed once bids have been placed"));
+
+        // try changing after a partial consignment
+        let (_, mut deps) = init_helper();
+        let handle_msg = HandleMsg::Receive {
+            sender: HumanAddr("blah".to_string()),
+            from: HumanAddr("alice".to_string()),
+            amount: Uint128(4),
+            msg: None,
+        };
+        let _used = handle(&mut deps, mock_env("selladdr", &[]), handle_msg);
+        let handle_result = handle(&mut deps, mock_env("alice", &[]), change(4));
+        let error = extract_error_msg(handle_result);
+        assert!(error.contains("only be changed before any tokens have been consigned"));
     }
 
     #[test]
-    fn test_init_sanity() {
-        let (init_result, deps) = init_helper();
+    fn test_min_bid_decay() {
+        let (init_result, mut deps) = init_helper();
         assert!(
             init_result.is_ok(),
             "Init failed: {}",
             init_result.err().unwrap()
         );
-        let state: State = load(&deps.storage, CONFIG_KEY).unwrap();
-        let factory = ContractInfo {
-            code_hash: "factoryhash".to_string(),
-            address: HumanAddr("factoryaddr".to_string()),
-        };
-        let sell_contract = ContractInfo {
-            code_hash: "sellhash".to_string(),
-            address: HumanAddr("selladdr".to_string()),
+        let mut state: State = load(&deps.storage, CONFIG_KEY).unwrap();
+        state.min_bid_decay = Some(StoredDecay {
+            floor: 4,
+            step: 2,
+            interval: 100,
+            start: 0,
+        });
+        save(&mut deps.storage, CONFIG_KEY, &state).unwrap();
+        assert_eq!(state.effective_minimum_bid(0), 10);
+        assert_eq!(state.effective_minimum_bid(199), 8);
+        assert_eq!(state.effective_minimum_bid(200), 6);
+        assert_eq!(state.effective_minimum_bid(900), 4);
+
+        let bid = |from: &str, amount: u128| HandleMsg::Receive {
+            sender: HumanAddr("blah".to_string()),
+            from: HumanAddr(from.to_string()),
+            amount: Uint128(amount),
+            msg: None,
         };
-        let bid_contract = ContractInfo {
-            code_hash: "bidhash".to_string(),
-            address: HumanAddr("bidaddr".to_string()),
+        let mut env = mock_env("bidaddr", &[]);
+        env.block.time = 150;
+        let handle_result = handle(&mut deps, env.clone(), bid("bob", 7));
+        let log = extract_log(handle_result);
+        assert!(log.contains("Bid was less than minimum allowed"));
+        assert!(log.contains("\"minimum_bid\":\"8\""));
+        env.block.time = 300;
+        let handle_result = handle(&mut deps, env.clone(), bid("bob", 7));
+        assert!(extract_log(handle_result).contains("Bid accepted"));
+
+        // changing the minimum bid restarts the decline
+        let mut env = mock_env("alice", &[]);
+        env.block.time = 300;
+        let handle_msg = HandleMsg::ChangeMinimumBid {
+            minimum_bid: Uint128(20),
+            refund_lower_bids: false,
         };
+        let _used = handle(&mut deps, env, handle_msg);
+        let state: State = load(&deps.storage, CONFIG_KEY).unwrap();
+        assert_eq!(state.effective_minimum_bid(300), 20);
+        assert_eq!(state.effective_minimum_bid(400), 18);
 
-        assert_eq!(factory, state.factory);
-        assert_eq!(HumanAddr("alice".to_string()), state.seller);
-        assert_eq!(sell_contract, state.sell_contract);
-        assert_eq!(4, state.sell_decimals);
-        assert_eq!(bid_contract, state.bid_contract);
-        assert_eq!(8, state.bid_decimals);
-        assert_eq!(10, state.sell_amount);
-        assert_eq!(10, state.minimum_bid);
-        assert_eq!(0, state.currently_consigned);
-        assert_eq!(HashSet::new(), state.bidders);
-        assert_eq!(false, state.is_completed);
-        assert_eq!(false, state.tokens_consigned);
-        assert_eq!(1000, state.ends_at);
-        assert_eq!(None, state.description);
-        assert_eq!(0, state.winning_bid);
+        // a minimum bid below the floor is not raised to it
+        let mut state = state;
+        state.minimum_bid = 3;
+        assert_eq!(state.effective_minimum_bid(1000), 3);
     }
 
     #[test]
-    fn test_consign() {
+    fn test_unit_price_bids() {
         let (init_result, mut deps) = init_helper();
         assert!(
             init_result.is_ok(),
             "Init failed: {}",
             init_result.err().unwrap()
         );
+        let unit_bid =
+            |amount: u128, unit_price: u128, max_bid: Option<Uint128>| HandleMsg::Receive {
+                sender: HumanAddr("blah".to_string()),
+                from: HumanAddr("bob".to_string()),
+                amount: Uint128(amount),
+                msg: Some(
+                    to_binary(&ReceiveMsg::PlaceBid {
+                        expiration: None,
+                        max_bid,
+                        unit_price: Some(Uint128(unit_price)),
+                        memo: None,
+                        referrer: None,
+                    })
+                    .unwrap(),
+                ),
+            };
+        // an auction must opt in to unit price bids
+        let handle_result = handle(
+            &mut deps,
+            before_close_env("bidaddr"),
+            unit_bid(10, 4, None),
+        );
+        let log = extract_log(handle_result);
+        assert!(log.contains("This auction does not take unit price bids"));
+        assert!(log.contains("\"status\":\"failure\""));
+
+        // sell 2.5 tokens with 4 decimals
+        let mut state: State = load(&deps.storage, CONFIG_KEY).unwrap();
+        state.unit_price_bids = true;
+        state.sell_amount = 25000;
+        save(&mut deps.storage, CONFIG_KEY, &state).unwrap();
+
+        // can not combine a unit price with a maximum bid
+        let handle_result = handle(
+            &mut deps,
+            before_close_env("bidaddr"),
+            unit_bid(10, 4, Some(Uint128(10))),
+        );
+        let log = extract_log(handle_result);
+        assert!(log.contains("either a unit price or a maximum bid"));
+        assert!(log.contains("\"status\":\"failure\""));
 
-        // try to consign if not seller
+        // the tokens sent must cover the total cost
+        let handle_result = handle(&mut deps, before_close_env("bidaddr"), unit_bid(9, 4, None));
+        let log = extract_log(handle_result);
+        assert!(log.contains("The total cost of your unit price is 10, but only 9 was sent"));
+        assert!(log.contains("\"amount_returned\":\"9\""));
+
+        // the total is bid and the rest is returned
+        let handle_result = handle(
+            &mut deps,
+            before_close_env("bidaddr"),
+            unit_bid(15, 4, None),
+        );
+        // register the bidder with the factory and return the excess
+        assert_eq!(handle_result.as_ref().unwrap().messages.len(), 2);
+        let log = extract_log(handle_result);
+        assert!(log.contains("Bid accepted"));
+        assert!(log.contains("\"amount_bid\":\"10\""));
+        assert!(log.contains("\"unit_price\":\"4\""));
+        assert!(log.contains("\"amount_returned\":\"5\""));
+        let bid: Bid = load(
+            &deps.storage,
+            deps.api
+                .canonical_address(&HumanAddr("bob".to_string()))
+                .unwrap()
+                .as_slice(),
+        )
+        .unwrap();
+        assert_eq!(bid.amount, 10);
+
+        // a total bid reports its unit price rounded down
         let handle_msg = HandleMsg::Receive {
             sender: HumanAddr("blah".to_string()),
-            from: HumanAddr("bob".to_string()),
-            amount: Uint128(2500),
+            from: HumanAddr("charlie".to_string()),
+            amount: Uint128(12),
             msg: None,
         };
-        let handle_result = handle(&mut deps, mock_env("selladdr", &[]), handle_msg);
+        let handle_result = handle(&mut deps, before_close_env("bidaddr"), handle_msg);
+        assert!(extract_log(handle_result).contains("\"unit_price\":\"4\""));
+    }
+
+    #[test]
+    fn test_seller_confirmation() {
+        // consign, place a bid, and have a bidder finalize after the closing time
+        let setup = || {
+            let (init_result, mut deps) = init_helper();
+            assert!(
+                init_result.is_ok(),
+                "Init failed: {}",
+                init_result.err().unwrap()
+            );
+            let mut state: State = load(&deps.storage, CONFIG_KEY).unwrap();
+            state.seller_confirmation = Some(100);
+            save(&mut deps.storage, CONFIG_KEY, &state).unwrap();
+            let handle_msg = HandleMsg::Receive {
+                sender: HumanAddr("blah".to_string()),
+                from: HumanAddr("alice".to_string()),
+                amount: Uint128(10),
+                msg: None,
+            };
+            let _handle_result = handle(&mut deps, before_close_env("selladdr"), handle_msg);
+            let handle_msg = HandleMsg::Receive {
+                sender: HumanAddr("blah".to_string()),
+                from: HumanAddr("bob".to_string()),
+                amount: Uint128(100),
+                msg: None,
+            };
+            let _handle_result = handle(&mut deps, before_close_env("bidaddr"), handle_msg);
+            let handle_msg = HandleMsg::Finalize {
+                new_ends_at: None,
+                new_minimum_bid: None,
+            };
+            let mut env = mock_env("bob", &[]);
+            env.block.time = 1000;
+            let handle_result = handle(&mut deps, env, handle_msg);
+            assert!(handle_result.as_ref().unwrap().messages.is_empty());
+            let (message, winning_bid, ..) = extract_finalize_fields(&handle_result);
+            assert!(message.contains("The seller has until 1100 to accept or reject it"));
+            assert_eq!(winning_bid, None);
+            deps
+        };
+        let respond = |sender: &str, time: u64, accept: bool| {
+            let mut env = mock_env(sender, &[]);
+            env.block.time = time;
+            (env, HandleMsg::RespondToWinner { accept })
+        };
+
+        let mut deps = setup();
+        let state: State = load(&deps.storage, CONFIG_KEY).unwrap();
+        assert_eq!(state.accept_by, Some(1100));
+        assert!(!state.is_completed);
+        let query_result = query(&deps, QueryMsg::Status {});
+        match from_binary(&query_result.unwrap()).unwrap() {
+            QueryAnswer::Status {
+                awaiting_acceptance,
+                settlement_phase,
+                ..
+            } => {
+                assert!(awaiting_acceptance);
+                assert_eq!(settlement_phase, SettlementPhase::Settling);
+            }
+            _ => panic!("Unexpected"),
+        }
+
+        // bidders can not finalize or retract during the window
+        let mut env = mock_env("bob", &[]);
+        env.block.time = 1099;
+        let handle_result = handle(&mut deps, env, HandleMsg::RetractBid {});
         let error = extract_error_msg(handle_result);
-        assert!(error.contains("Only auction creator can consign tokens for sale"));
+        assert!(error.contains("The seller has until 1100 to accept or reject the winning bid"));
 
-        // try already consigned
+        // only the seller can respond
+        let (env, handle_msg) = respond("bob", 1050, true);
+        let handle_result = handle(&mut deps, env, handle_msg);
+        let error = extract_error_msg(handle_result);
+        assert!(error.contains("Only the auction seller can accept or reject the winning bid"));
+
+        // accepting completes the sale
+        let (env, handle_msg) = respond("alice", 1050, true);
+        let handle_result = handle(&mut deps, env, handle_msg);
+        assert_eq!(handle_result.as_ref().unwrap().messages.len(), 3);
+        let (message, winning_bid, ..) = extract_finalize_fields(&handle_result);
+        assert!(message.contains("Sale has been finalized"));
+        assert_eq!(winning_bid, Some(Uint128(100)));
+        let state: State = load(&deps.storage, CONFIG_KEY).unwrap();
+        assert!(state.is_completed);
+        assert!(!state.is_rejected);
+
+        // rejecting returns the bid and the lot
+        let mut deps = setup();
+        let (env, handle_msg) = respond("alice", 1050, false);
+        let handle_result = handle(&mut deps, env, handle_msg);
+        assert_eq!(handle_result.as_ref().unwrap().messages.len(), 3);
+        let (message, winning_bid, ..) = extract_finalize_fields(&handle_result);
+        assert!(message.contains("The seller rejected the winning bid"));
+        assert_eq!(winning_bid, None);
+        let state: State = load(&deps.storage, CONFIG_KEY).unwrap();
+        assert!(state.is_completed);
+        assert!(state.is_rejected);
+        assert_eq!(state.num_bidders, 0);
+        assert_eq!(state.currently_consigned, 0);
+        assert_eq!(state.winning_bid, 0);
+
+        // the sale goes through if the seller does not respond in time
+        let mut deps = setup();
+        let (env, handle_msg) = respond("alice", 1100, false);
+        let handle_result = handle(&mut deps, env, handle_msg);
+        let error = extract_error_msg(handle_result);
+        assert!(error.contains("The window to accept or reject the winning bid ended at 1100"));
+        let mut env = mock_env("charlie", &[]);
+        env.block.time = 1100;
+        let handle_msg = HandleMsg::Finalize {
+            new_ends_at: None,
+            new_minimum_bid: None,
+        };
+        let handle_result = handle(&mut deps, env, handle_msg);
+        let (message, winning_bid, ..) = extract_finalize_fields(&handle_result);
+        assert!(message.contains("Sale has been finalized"));
+        assert_eq!(winning_bid, Some(Uint128(100)));
+    }
+
+    #[test]
+    fn test_no_self_bid() {
+        let (init_result, mut deps) = init_helper();
+        assert!(
+            init_result.is_ok(),
+            "Init failed: {}",
+            init_result.err().unwrap()
+        );
+        let place_bid = || {
+            Some(
+                to_binary(&ReceiveMsg::PlaceBid {
+                    expiration: None,
+                    max_bid: None,
+                    unit_price: None,
+                    memo: None,
+                    referrer: None,
+                })
+                .unwrap(),
+            )
+        };
+        // the seller may bid by default
         let handle_msg = HandleMsg::Receive {
             sender: HumanAddr("blah".to_string()),
             from: HumanAddr("alice".to_string()),
-            amount: Uint128(2500),
-            msg: None,
+            amount: Uint128(100),
+            msg: place_bid(),
         };
-        let _handle_result = handle(&mut deps, mock_env("selladdr", &[]), handle_msg);
+        let handle_result = handle(&mut deps, before_close_env("bidaddr"), handle_msg);
+        assert!(extract_log(handle_result).contains("Bid accepted"));
+
+        let (init_result, mut deps) = init_helper();
+        assert!(
+            init_result.is_ok(),
+            "Init failed: {}",
+            init_result.err().unwrap()
+        );
+        let mut state: State = load(&deps.storage, CONFIG_KEY).unwrap();
+        state.no_self_bid = true;
+        save(&mut deps.storage, CONFIG_KEY, &state).unwrap();
+        let handle_msg = HandleMsg::Receive {
+            sender: HumanAddr("blah".to_string()),
+            from: HumanAddr("alice".to_string()),
+            amount: Uint128(100),
+            msg: place_bid(),
+        };
+        let handle_result = handle(&mut deps, before_close_env("bidaddr"), handle_msg);
+        let log = extract_log(handle_result);
+        assert!(log.contains("The seller is not allowed to bid in this auction"));
+        assert!(log.contains("\"status\":\"failure\""));
+        assert!(log.contains("\"amount_returned\":\"100\""));
+        let state: State = load(&deps.storage, CONFIG_KEY).unwrap();
+        assert_eq!(state.num_bidders, 0);
+
+        // other bidders are unaffected
         let handle_msg = HandleMsg::Receive {
             sender: HumanAddr("blah".to_string()),
-            from: HumanAddr("alice".to_string()),
-            amount: Uint128(2500),
+            from: HumanAddr("bob".to_string()),
+            amount: Uint128(100),
             msg: None,
         };
-        let handle_result = handle(&mut deps, mock_env("selladdr", &[]), handle_msg);
-        let error = extract_error_msg(handle_result);
-        assert!(error.contains("Tokens to be sold have already been consigned."));
+        let handle_result = handle(&mut deps, before_close_env("bidaddr"), handle_msg);
+        assert!(extract_log(handle_result).contains("Bid accepted"));
+    }
 
-        // try to consign after closing
-        let handle_msg = HandleMsg::Finalize {
-            new_ends_at: None,
-            new_minimum_bid: None,
+    #[test]
+    fn test_max_bidders() {
+        let (init_result, mut deps) = init_helper();
+        assert!(
+            init_result.is_ok(),
+            "Init failed: {}",
+            init_result.err().unwrap()
+        );
+        let mut state: State = load(&deps.storage, CONFIG_KEY).unwrap();
+        state.max_bidders = Some(2);
+        save(&mut deps.storage, CONFIG_KEY, &state).unwrap();
+        let bid = |deps: &mut Extern<MockStorage, MockApi, MockQuerier>, bidder: &str, amount| {
+            let handle_msg = HandleMsg::Receive {
+                sender: HumanAddr("blah".to_string()),
+                from: HumanAddr(bidder.to_string()),
+                amount: Uint128(amount),
+                msg: None,
+            };
+            handle(deps, before_close_env("bidaddr"), handle_msg)
         };
-        let _used = handle(&mut deps, mock_env("alice", &[]), handle_msg);
-        let handle_msg = HandleMsg::Receive {
-            sender: HumanAddr("blah".to_string()),
-            from: HumanAddr("alice".to_string()),
-            amount: Uint128(2500),
-            msg: None,
+        assert!(extract_log(bid(&mut deps, "bob", 100)).contains("Bid accepted"));
+        assert!(extract_log(bid(&mut deps, "charlie", 100)).contains("Bid accepted"));
+
+        // a new bidder is turned away once the cap is reached
+        let log = extract_log(bid(&mut deps, "dave", 200));
+        assert!(log.contains("only accepts bids from 2 bidders"));
+        assert!(log.contains("\"amount_returned\":\"200\""));
+        // but existing bidders may still rebid
+        assert!(extract_log(bid(&mut deps, "bob", 150)).contains("Bid accepted"));
+        let state: State = load(&deps.storage, CONFIG_KEY).unwrap();
+        assert_eq!(state.num_bidders, 2);
+
+        // retracting a bid frees a slot
+        let handle_result = handle(
+            &mut deps,
+            before_close_env("charlie"),
+            HandleMsg::RetractBid {},
+        );
+        assert!(handle_result.is_ok());
+        assert!(extract_log(bid(&mut deps, "dave", 200)).contains("Bid accepted"));
+    }
+
+    #[test]
+    fn test_min_bidders() {
+        // consign, place bids, and have a bidder finalize after the closing time
+        let close_with = |bidders: &[&str]| {
+            let (init_result, mut deps) = init_helper();
+            assert!(
+                init_result.is_ok(),
+                "Init failed: {}",
+                init_result.err().unwrap()
+            );
+            let mut state: State = load(&deps.storage, CONFIG_KEY).unwrap();
+            state.min_bidders = Some(2);
+            save(&mut deps.storage, CONFIG_KEY, &state).unwrap();
+            let handle_msg = HandleMsg::Receive {
+                sender: HumanAddr("blah".to_string()),
+                from: HumanAddr("alice".to_string()),
+                amount: Uint128(10),
+                msg: None,
+            };
+            let _handle_result = handle(&mut deps, before_close_env("selladdr"), handle_msg);
+            for bidder in bidders {
+                let handle_msg = HandleMsg::Receive {
+                    sender: HumanAddr("blah".to_string()),
+                    from: HumanAddr(bidder.to_string()),
+                    amount: Uint128(100),
+                    msg: None,
+                };
+                let _handle_result = handle(&mut deps, before_close_env("bidaddr"), handle_msg);
+            }
+            let handle_msg = HandleMsg::Finalize {
+                new_ends_at: None,
+                new_minimum_bid: None,
+            };
+            let mut env = mock_env("bob", &[]);
+            env.block.time = 1000;
+            let handle_result = handle(&mut deps, env, handle_msg);
+            (deps, handle_result)
         };
 
-        let handle_result = handle(&mut deps, mock_env("selladdr", &[]), handle_msg);
-        let error = extract_error_msg(handle_result);
-        assert!(error.contains("Auction has ended. Your tokens have been returned"));
+        // too few bidders returns every bid and the consignment
+        let (deps, handle_result) = close_with(&["bob"]);
+        // bid refund, consignment return, and the factory callback
+        assert_eq!(handle_result.as_ref().unwrap().messages.len(), 3);
+        match from_binary(&handle_result.unwrap().data.unwrap()).unwrap() {
+            HandleAnswer::CloseAuction {
+                message,
+                winning_bid,
+                unmet_min_bidders,
+                ..
+            } => {
+                assert!(message.contains("needed at least 2 bidders"));
+                assert_eq!(winning_bid, None);
+                assert_eq!(unmet_min_bidders, Some(2));
+            }
+            _ => panic!("Unexpected HandleAnswer"),
+        }
+        let state: State = load(&deps.storage, CONFIG_KEY).unwrap();
+        assert!(state.is_completed);
+        assert!(state.too_few_bidders);
+        assert_eq!(state.num_bidders, 0);
 
-        // try consign too little
+        // meeting the threshold sells as usual
+        let (deps, handle_result) = close_with(&["bob", "charlie"]);
+        let (message, winning_bid, ..) = extract_finalize_fields(&handle_result);
+        assert!(message.contains("Sale has been finalized"));
+        assert_eq!(winning_bid, Some(Uint128(100)));
+        let state: State = load(&deps.storage, CONFIG_KEY).unwrap();
+        assert!(!state.too_few_bidders);
+    }
+
+    #[test]
+    fn test_buy_now_min_bidders() {
         let (init_result, mut deps) = init_helper();
         assert!(
             init_result.is_ok(),
             "Init failed: {}",
             init_result.err().unwrap()
         );
+        let mut state: State = load(&deps.storage, CONFIG_KEY).unwrap();
+        state.buy_now_price = Some(200);
+        state.min_bidders = Some(2);
+        save(&mut deps.storage, CONFIG_KEY, &state).unwrap();
         let handle_msg = HandleMsg::Receive {
             sender: HumanAddr("blah".to_string()),
             from: HumanAddr("alice".to_string()),
-            amount: Uint128(2),
+            amount: Uint128(10),
             msg: None,
         };
-        let handle_result = handle(&mut deps, mock_env("selladdr", &[]), handle_msg);
+        let _handle_result = handle(&mut deps, before_close_env("selladdr"), handle_msg);
+        let buy_now = || HandleMsg::Receive {
+            sender: HumanAddr("blah".to_string()),
+            from: HumanAddr("bob".to_string()),
+            amount: Uint128(200),
+            msg: Some(to_binary(&ReceiveMsg::BuyNow {}).unwrap()),
+        };
+
+        // buying as the only bidder would not meet the threshold
+        let handle_result = handle(&mut deps, before_close_env("bidaddr"), buy_now());
+        // only the refund
+        assert_eq!(handle_result.as_ref().unwrap().messages.len(), 1);
         let log = extract_log(handle_result);
-        assert!(log.contains("not consigned the full amount"));
-        assert!(log.contains("\"amount_needed\":\"8\""));
-        assert!(log.contains("\"sell_decimals\":4"));
+        assert!(log.contains("needs bids from 2 bidders"));
+        assert!(log.contains("\"status\":\"failure\""));
+        assert!(log.contains("\"amount_returned\":\"200\""));
         let state: State = load(&deps.storage, CONFIG_KEY).unwrap();
-        assert_eq!(false, state.tokens_consigned);
+        assert!(!state.is_completed);
+        assert_eq!(state.num_bidders, 0);
 
-        // try consign too much
+        // another bid lets the buyer meet the threshold
         let handle_msg = HandleMsg::Receive {
             sender: HumanAddr("blah".to_string()),
-            from: HumanAddr("alice".to_string()),
-            amount: Uint128(20),
+            from: HumanAddr("charlie".to_string()),
+            amount: Uint128(50),
             msg: None,
         };
-        let handle_result = handle(&mut deps, mock_env("selladdr", &[]), handle_msg);
+        let _handle_result = handle(&mut deps, before_close_env("bidaddr"), handle_msg);
+        let handle_result = handle(&mut deps, before_close_env("bidaddr"), buy_now());
         let log = extract_log(handle_result);
-        assert!(log.contains("Excess tokens have been returned"));
-        assert!(log.contains("\"amount_returned\":\"12\""));
-        assert!(log.contains("\"sell_decimals\":4"));
+        assert!(log.contains("You have bought the lot at the buy now price"));
         let state: State = load(&deps.storage, CONFIG_KEY).unwrap();
-        assert!(state.tokens_consigned);
+        assert!(state.is_completed);
+        assert_eq!(state.winning_bid, 200);
     }
 
     #[test]
-    fn test_bid() {
+    fn test_commit_unsealed() {
         let (init_result, mut deps) = init_helper();
         assert!(
             init_result.is_ok(),
             "Init failed: {}",
             init_result.err().unwrap()
         );
-
-        // try bid 0
         let handle_msg = HandleMsg::Receive {
             sender: HumanAddr("blah".to_string()),
             from: HumanAddr("bob".to_string()),
-            amount: Uint128(0),
-            msg: None,
+            amount: Uint128(100),
+            msg: Some(
+                to_binary(&ReceiveMsg::CommitBid {
+                    commitment: Binary::from(&sha_256(b"100salt")[..]),
+                })
+                .unwrap(),
+            ),
         };
-        let handle_result = handle(&mut deps, mock_env("bidaddr", &[]), handle_msg);
-        let error = extract_error_msg(handle_result);
-        assert!(error.contains("Bid must be greater than 0"));
+        let handle_result = handle(&mut deps, before_close_env("bidaddr"), handle_msg);
+        assert_eq!(handle_result.as_ref().unwrap().messages.len(), 1);
+        let log = extract_log(handle_result);
+        assert!(log.contains("This auction does not take sealed bids"));
+        assert!(log.contains("\"status\":\"failure\""));
+        assert!(log.contains("\"amount_returned\":\"100\""));
+    }
 
-        // try bid less than minimum
+    #[test]
+    fn test_sealed_bids() {
         let (init_result, mut deps) = init_helper();
         assert!(
             init_result.is_ok(),
             "Init failed: {}",
             init_result.err().unwrap()
         );
+        let mut state: State = load(&deps.storage, CONFIG_KEY).unwrap();
+        state.reveal_window = Some(100);
+        save(&mut deps.storage, CONFIG_KEY, &state).unwrap();
         let handle_msg = HandleMsg::Receive {
             sender: HumanAddr("blah".to_string()),
-            from: HumanAddr("bob".to_string()),
-            amount: Uint128(9),
+            from: HumanAddr("alice".to_string()),
+            amount: Uint128(10),
             msg: None,
         };
-        let handle_result = handle(&mut deps, mock_env("bidaddr", &[]), handle_msg);
-        let log = extract_log(handle_result);
-        assert!(log.contains("Bid was less than minimum allowed"));
-        assert!(log.contains("\"minimum_bid\":\"10\""));
-        assert!(log.contains("\"amount_returned\":\"9\""));
-        assert!(log.contains("\"bid_decimals\":8"));
-
-        let state: State = load(&deps.storage, CONFIG_KEY).unwrap();
-        assert_eq!(state.bidders.len(), 0);
-
-        // sanity check
-        let handle_msg = HandleMsg::Receive {
+        let _handle_result = handle(&mut deps, before_close_env("selladdr"), handle_msg);
+        let commit = |bidder: &str, preimage: &str, deposit: u128| HandleMsg::Receive {
             sender: HumanAddr("blah".to_string()),
-            from: HumanAddr("bob".to_string()),
-            amount: Uint128(100),
-            msg: None,
+            from: HumanAddr(bidder.to_string()),
+            amount: Uint128(deposit),
+            msg: Some(
+                to_binary(&ReceiveMsg::CommitBid {
+                    commitment: Binary::from(&sha_256(preimage.as_bytes())[..]),
+                })
+                .unwrap(),
+            ),
+        };
+        let reveal = |amount: u128, salt: &str| HandleMsg::RevealBid {
+            amount: Uint128(amount),
+            salt: salt.to_string(),
+        };
+        let at = |sender: &str, time: u64| {
+            let mut env = mock_env(sender, &[]);
+            env.block.time = time;
+            env
         };
-        let handle_result = handle(&mut deps, mock_env("bidaddr", &[]), handle_msg);
-        let log = extract_log(handle_result);
-        assert!(log.contains("\"amount_bid\":\"100\""));
-        assert!(log.contains("\"bid_decimals\":8"));
-        let state: State = load(&deps.storage, CONFIG_KEY).unwrap();
-        assert_eq!(state.bidders.len(), 1);
-        let bid: Bid = load(
-            &deps.storage,
-            deps.api
-                .canonical_address(&HumanAddr("bob".to_string()))
-                .unwrap()
-                .as_slice(),
-        )
-        .unwrap();
-        assert_eq!(bid.amount, 100);
 
-        // test bid equal to  previous bid
+        // plain bids are not accepted
         let handle_msg = HandleMsg::Receive {
             sender: HumanAddr("blah".to_string()),
             from: HumanAddr("bob".to_string()),
             amount: Uint128(100),
             msg: None,
         };
-        let handle_result = handle(&mut deps, mock_env("bidaddr", &[]), handle_msg);
+        let handle_result = handle(&mut deps, before_close_env("bidaddr"), handle_msg);
         let log = extract_log(handle_result);
-        assert!(log.contains("New bid is the same as previous bid.  Retaining previous timestamp"));
-        assert!(log.contains("\"previous_bid\":\"100\""));
-        assert!(log.contains("\"amount_bid\":\"100\""));
+        assert!(log.contains("This auction takes sealed bids"));
+        assert!(log.contains("\"status\":\"failure\""));
         assert!(log.contains("\"amount_returned\":\"100\""));
-        assert!(log.contains("\"bid_decimals\":8"));
+
+        // commit three sealed bids, and replace one of them
+        for (bidder, preimage, deposit) in [
+            ("bob", "bob:100:salt", 120),
+            ("bob", "bob:100:salt", 150),
+            ("charlie", "charlie:50:pepper", 60),
+            ("dave", "dave:20:never", 20),
+        ]
+        .iter()
+        {
+            let handle_result = handle(
+                &mut deps,
+                before_close_env("bidaddr"),
+                commit(bidder, preimage, *deposit),
+            );
+            assert!(handle_result.is_ok());
+        }
         let state: State = load(&deps.storage, CONFIG_KEY).unwrap();
-        assert_eq!(state.bidders.len(), 1);
+        assert_eq!(state.num_committers, 3);
+        assert_eq!(state.num_bidders, 0);
 
-        // test bid less than previous bid
+        // bids can not be revealed before the closing time
+        let handle_result = handle(&mut deps, at("bob", 500), reveal(100, "salt"));
+        assert!(extract_error_msg(handle_result).contains("Bids can only be revealed"));
+
+        // commitments sent after the closing time are returned
+        let handle_result = handle(
+            &mut deps,
+            at("bidaddr", 1000),
+            commit("erin", "erin:30:salt", 30),
+        );
+        let log = extract_log(handle_result);
+        assert!(log.contains("Commitments are not accepted after the closing time"));
+        assert!(log.contains("\"amount_returned\":\"30\""));
+
+        // nobody can finalize during the reveal window
+        let handle_msg = HandleMsg::Finalize {
+            new_ends_at: None,
+            new_minimum_bid: None,
+        };
+        let handle_result = handle(&mut deps, at("alice", 1000), handle_msg);
+        assert!(extract_error_msg(handle_result).contains("revealed until 1100"));
+
+        // the revealed bid must match the commitment
+        let handle_result = handle(&mut deps, at("bob", 1050), reveal(100, "wrong"));
+        assert!(extract_error_msg(handle_result).contains("do not match"));
+        let handle_result = handle(&mut deps, at("bob", 1050), reveal(100, "salt"));
+        match from_binary(&handle_result.unwrap().data.unwrap()).unwrap() {
+            HandleAnswer::RevealBid {
+                amount_bid,
+                amount_returned,
+                ..
+            } => {
+                assert_eq!(amount_bid, Some(Uint128(100)));
+                assert_eq!(amount_returned, Some(Uint128(50)));
+            }
+            _ => panic!("Unexpected HandleAnswer"),
+        }
+        let handle_result = handle(&mut deps, at("charlie", 1099), reveal(50, "pepper"));
+        // the factory learns the amount once it is revealed
+        match &handle_result.unwrap().messages[0] {
+            CosmosMsg::Wasm(WasmMsg::Execute { msg, .. }) => {
+                let register = String::from_utf8(msg.as_slice().to_vec()).unwrap();
+                assert!(register.contains("\"register_bidder\""));
+                assert!(register.contains("\"amount\":\"50\""));
+            }
+            _ => panic!("Unexpected message"),
+        }
+        let state: State = load(&deps.storage, CONFIG_KEY).unwrap();
+        assert_eq!(state.num_bidders, 2);
+        assert_eq!(state.num_committers, 1);
+
+        // the highest revealed bid wins, and the unrevealed deposit goes to the seller
+        let handle_msg = HandleMsg::Finalize {
+            new_ends_at: None,
+            new_minimum_bid: None,
+        };
+        let handle_result = handle(&mut deps, at("bob", 1100), handle_msg);
+        let (message, winning_bid, ..) = extract_finalize_fields(&handle_result);
+        assert!(message.contains("Sale has been finalized"));
+        assert!(message.contains("1 unrevealed commitments have been forfeited to the seller"));
+        assert_eq!(winning_bid, Some(Uint128(100)));
+        let state: State = load(&deps.storage, CONFIG_KEY).unwrap();
+        assert!(state.is_completed);
+        assert_eq!(state.num_committers, 0);
+    }
+
+    #[test]
+    fn test_raffle() {
+        let env = mock_env("bob", &[]);
+        // an empty or weightless raffle has no winner
+        assert_eq!(draw_raffle(b"seed", &env, &[]).unwrap(), None);
+        assert_eq!(draw_raffle(b"seed", &env, &[0, 0]).unwrap(), None);
+        // a bid with no weight can never be drawn
+        assert_eq!(draw_raffle(b"seed", &env, &[0, 10, 0]).unwrap(), Some(1));
+        // the draw is deterministic, but depends on the seed and the block
+        let mut drawn = HashSet::new();
+        for height in 0..50 {
+            let mut env = mock_env("bob", &[]);
+            env.block.height = height;
+            let first = draw_raffle(b"seed", &env, &[1, 1]).unwrap();
+            assert_eq!(first, draw_raffle(b"seed", &env, &[1, 1]).unwrap());
+            drawn.extend(first);
+        }
+        assert_eq!(drawn.len(), 2);
+
+        // close a raffle with two bids
+        let (init_result, mut deps) = init_helper();
+        assert!(
+            init_result.is_ok(),
+            "Init failed: {}",
+            init_result.err().unwrap()
+        );
+        let mut state: State = load(&deps.storage, CONFIG_KEY).unwrap();
+        state.settlement = SettlementMode::Raffle;
+        state.raffle_seed = Some(b"seed".to_vec());
+        save(&mut deps.storage, CONFIG_KEY, &state).unwrap();
         let handle_msg = HandleMsg::Receive {
             sender: HumanAddr("blah".to_string()),
-            from: HumanAddr("bob".to_string()),
-            amount: Uint128(25),
+            from: HumanAddr("alice".to_string()),
+            amount: Uint128(10),
             msg: None,
         };
-        let handle_result = handle(&mut deps, mock_env("bidaddr", &[]), handle_msg);
-        let log = extract_log(handle_result);
-        assert!(log.contains("Previously bid tokens have been returned"));
-        assert!(log.contains("\"amount_bid\":\"25\""));
-        assert!(log.contains("\"amount_returned\":\"100\""));
-        assert!(log.contains("\"bid_decimals\":8"));
+        let _handle_result = handle(&mut deps, before_close_env("selladdr"), handle_msg);
+        for (bidder, amount) in [("bob", 100), ("charlie", 50)].iter() {
+            let handle_msg = HandleMsg::Receive {
+                sender: HumanAddr("blah".to_string()),
+                from: HumanAddr(bidder.to_string()),
+                amount: Uint128(*amount),
+                msg: None,
+            };
+            let _handle_result = handle(&mut deps, before_close_env("bidaddr"), handle_msg);
+        }
+        let handle_msg = HandleMsg::Finalize {
+            new_ends_at: None,
+            new_minimum_bid: None,
+        };
+        let mut env = mock_env("bob", &[]);
+        env.block.time = 1000;
+        let handle_result = handle(&mut deps, env, handle_msg);
+        match from_binary(&handle_result.unwrap().data.unwrap()).unwrap() {
+            HandleAnswer::CloseAuction {
+                message,
+                winning_bid,
+                settlement,
+                ..
+            } => {
+                assert!(message.contains("drawn at random"));
+                assert!(winning_bid == Some(Uint128(100)) || winning_bid == Some(Uint128(50)));
+                assert_eq!(settlement, Some(SettlementMode::Raffle));
+            }
+            _ => panic!("Unexpected HandleAnswer"),
+        }
         let state: State = load(&deps.storage, CONFIG_KEY).unwrap();
-        assert_eq!(state.bidders.len(), 1);
+        assert!(state.is_completed);
+        assert_eq!(state.num_bidders, 0);
+    }
 
-        // test bid more than previous bid
+    #[test]
+    fn test_raffle_buy_now_price() {
+        let mut deps = mock_dependencies(20, &[]);
+        let mut env = mock_env("factory", &[]);
+        env.block.time = 0;
+        let init_msg = InitMsg {
+            settlement: SettlementMode::Raffle,
+            raffle_seed: Some(Binary::from(b"seed".to_vec())),
+            buy_now_price: Some(Uint128(100)),
+            ..default_init_msg()
+        };
+        let init_result = init(&mut deps, env.clone(), init_msg);
+        let error = extract_error_msg(init_result);
+        assert!(error.contains("A raffle can not take allowance bids, have a buy now price"));
+
+        // without the buy now price the raffle is created
+        let init_msg = InitMsg {
+            settlement: SettlementMode::Raffle,
+            raffle_seed: Some(Binary::from(b"seed".to_vec())),
+            ..default_init_msg()
+        };
+        let init_result = init(&mut deps, env, init_msg);
+        assert!(init_result.is_ok());
+    }
+
+    #[test]
+    fn test_crowdfund() {
+        // consign, place three bids that raise 180, and have a bidder finalize
+        let close_with = |target: u128| {
+            let (init_result, mut deps) = init_helper();
+            assert!(
+                init_result.is_ok(),
+                "Init failed: {}",
+                init_result.err().unwrap()
+            );
+            let mut state: State = load(&deps.storage, CONFIG_KEY).unwrap();
+            state.settlement = SettlementMode::Crowdfund;
+            state.funding_target = Some(target);
+            save(&mut deps.storage, CONFIG_KEY, &state).unwrap();
+            let handle_msg = HandleMsg::Receive {
+                sender: HumanAddr("blah".to_string()),
+                from: HumanAddr("alice".to_string()),
+                amount: Uint128(10),
+                msg: None,
+            };
+            let _handle_result = handle(&mut deps, before_close_env("selladdr"), handle_msg);
+            for (bidder, amount) in [("bob", 100), ("charlie", 50), ("dave", 30)].iter() {
+                let handle_msg = HandleMsg::Receive {
+                    sender: HumanAddr("blah".to_string()),
+                    from: HumanAddr(bidder.to_string()),
+                    amount: Uint128(*amount),
+                    msg: None,
+                };
+                let _handle_result = handle(&mut deps, before_close_env("bidaddr"), handle_msg);
+            }
+            let handle_msg = HandleMsg::Finalize {
+                new_ends_at: None,
+                new_minimum_bid: None,
+            };
+            let mut env = mock_env("charlie", &[]);
+            env.block.time = 1000;
+            let handle_result = handle(&mut deps, env, handle_msg);
+            (deps, handle_result)
+        };
+
+        // meeting the target sends the seller the total and each bid its slice of the lot
+        let (deps, handle_result) = close_with(150);
+        // payment to the seller, three slices, the dust to the seller, and the factory callback
+        assert_eq!(handle_result.as_ref().unwrap().messages.len(), 6);
+        let (message, winning_bid, _, sell_tokens_received, ..) =
+            extract_finalize_fields(&handle_result);
+        assert!(message.contains("every bid has been sent its share of the lot"));
+        assert_eq!(winning_bid, Some(Uint128(180)));
+        // charlie's 50 of 180 buys 2 of the 10 tokens for sale, rounded down
+        assert_eq!(sell_tokens_received, Some(Uint128(2)));
+        let state: State = load(&deps.storage, CONFIG_KEY).unwrap();
+        assert!(state.is_completed);
+        assert_eq!(state.num_bidders, 0);
+        assert_eq!(state.currently_consigned, 0);
+
+        // falling short of the target returns every bid and the consignment
+        let (deps, handle_result) = close_with(1000);
+        // three refunds, the consignment return, and the factory callback
+        assert_eq!(handle_result.as_ref().unwrap().messages.len(), 5);
+        let (message, winning_bid, ..) = extract_finalize_fields(&handle_result);
+        assert!(message.contains("raised 180 of its 1000 target"));
+        assert_eq!(winning_bid, None);
+        let state: State = load(&deps.storage, CONFIG_KEY).unwrap();
+        assert!(state.is_completed);
+        assert_eq!(state.num_bidders, 0);
+    }
+
+    #[test]
+    fn test_accept_current_best() {
+        let (init_result, mut deps) = init_helper();
+        assert!(
+            init_result.is_ok(),
+            "Init failed: {}",
+            init_result.err().unwrap()
+        );
+        let handle_msg = HandleMsg::AcceptCurrentBest {};
+        let handle_result = handle(&mut deps, before_close_env("alice"), handle_msg);
+        assert!(extract_error_msg(handle_result).contains("must be consigned"));
         let handle_msg = HandleMsg::Receive {
             sender: HumanAddr("blah".to_string()),
-            from: HumanAddr("bob".to_string()),
-            amount: Uint128(250),
+            from: HumanAddr("alice".to_string()),
+            amount: Uint128(10),
             msg: None,
         };
-        let handle_result = handle(&mut deps, mock_env("bidaddr", &[]), handle_msg);
-        let log = extract_log(handle_result);
-        assert!(log.contains("Previously bid tokens have been returned"));
-        assert!(log.contains("\"amount_bid\":\"250\""));
-        assert!(log.contains("\"amount_returned\":\"25\""));
-        assert!(log.contains("\"bid_decimals\":8"));
+        let _handle_result = handle(&mut deps, before_close_env("selladdr"), handle_msg);
+        let handle_msg = HandleMsg::AcceptCurrentBest {};
+        let handle_result = handle(&mut deps, before_close_env("alice"), handle_msg);
+        assert!(extract_error_msg(handle_result).contains("no active bids"));
+        for (bidder, amount) in [("bob", 100), ("charlie", 50)].iter() {
+            let handle_msg = HandleMsg::Receive {
+                sender: HumanAddr("blah".to_string()),
+                from: HumanAddr(bidder.to_string()),
+                amount: Uint128(*amount),
+                msg: None,
+            };
+            let _handle_result = handle(&mut deps, before_close_env("bidaddr"), handle_msg);
+        }
+
+        // only the seller may accept
+        let handle_msg = HandleMsg::AcceptCurrentBest {};
+        let handle_result = handle(&mut deps, before_close_env("bob"), handle_msg);
+        assert!(extract_error_msg(handle_result).contains("Only the auction seller"));
+
+        // accepting before the closing time sells to the highest bid
+        let handle_msg = HandleMsg::AcceptCurrentBest {};
+        let handle_result = handle(&mut deps, before_close_env("alice"), handle_msg);
+        let (message, winning_bid, _, _, _, bid_tokens_received) =
+            extract_finalize_fields(&handle_result);
+        assert!(message.contains("Sale has been finalized"));
+        assert_eq!(winning_bid, Some(Uint128(100)));
+        assert_eq!(bid_tokens_received, Some(Uint128(100)));
         let state: State = load(&deps.storage, CONFIG_KEY).unwrap();
-        assert_eq!(state.bidders.len(), 1);
+        assert!(state.is_completed);
+        assert_eq!(state.num_bidders, 0);
 
-        // try bid after close
-        let handle_msg = HandleMsg::Finalize {
-            new_ends_at: Some(2000),
-            new_minimum_bid: Some(Uint128(1000)),
-        };
-        let _used = handle(&mut deps, mock_env("alice", &[]), handle_msg);
+        let handle_msg = HandleMsg::AcceptCurrentBest {};
+        let handle_result = handle(&mut deps, before_close_env("alice"), handle_msg);
+        assert!(extract_error_msg(handle_result).contains("already closed"));
+    }
+
+    #[test]
+    fn test_accept_bid() {
+        let (init_result, mut deps) = init_helper();
+        assert!(
+            init_result.is_ok(),
+            "Init failed: {}",
+            init_result.err().unwrap()
+        );
         let handle_msg = HandleMsg::Receive {
             sender: HumanAddr("blah".to_string()),
-            from: HumanAddr("bob".to_string()),
-            amount: Uint128(2500),
+            from: HumanAddr("alice".to_string()),
+            amount: Uint128(10),
             msg: None,
         };
+        let _handle_result = handle(&mut deps, before_close_env("selladdr"), handle_msg);
+        for (bidder, amount) in [("bob", 100), ("charlie", 50)].iter() {
+            let handle_msg = HandleMsg::Receive {
+                sender: HumanAddr("blah".to_string()),
+                from: HumanAddr(bidder.to_string()),
+                amount: Uint128(*amount),
+                msg: None,
+            };
+            let _handle_result = handle(&mut deps, before_close_env("bidaddr"), handle_msg);
+        }
+        let accept = |bidder: &str| HandleMsg::AcceptBid {
+            bidder: HumanAddr(bidder.to_string()),
+        };
 
-        let handle_result = handle(&mut deps, mock_env("bidaddr", &[]), handle_msg);
-        let error = extract_error_msg(handle_result);
-        assert!(error.contains("Auction has ended"));
+        // only the seller may accept a bid, and only an active one
+        let handle_result = handle(&mut deps, before_close_env("bob"), accept("charlie"));
+        assert!(extract_error_msg(handle_result).contains("Only the auction seller"));
+        let handle_result = handle(&mut deps, before_close_env("alice"), accept("dave"));
+        assert!(extract_error_msg(handle_result).contains("No active bid for address: dave"));
+
+        // the accepted bid wins even though it is not the highest
+        let handle_result = handle(&mut deps, before_close_env("alice"), accept("charlie"));
+        // payment to the seller, the lot to charlie, bob's refund, and the factory callback
+        assert_eq!(handle_result.as_ref().unwrap().messages.len(), 4);
+        let (message, winning_bid, _, _, _, bid_tokens_received) =
+            extract_finalize_fields(&handle_result);
+        assert!(message.contains("The seller accepted the winning bid"));
+        assert_eq!(winning_bid, Some(Uint128(50)));
+        assert_eq!(bid_tokens_received, Some(Uint128(50)));
+        let state: State = load(&deps.storage, CONFIG_KEY).unwrap();
+        assert!(state.is_completed);
+        assert_eq!(state.winning_bid, 50);
+        assert_eq!(state.num_bidders, 0);
     }
 
     #[test]
-    fn test_change_min_bid() {
+    fn test_reject_bid() {
         let (init_result, mut deps) = init_helper();
         assert!(
             init_result.is_ok(),
             "Init failed: {}",
             init_result.err().unwrap()
         );
-        // try change min bid not seller
-        let handle_msg = HandleMsg::ChangeMinimumBid {
-            minimum_bid: Uint128(20),
+        for (bidder, amount) in [("bob", 100), ("charlie", 50)].iter() {
+            let handle_msg = HandleMsg::Receive {
+                sender: HumanAddr("blah".to_string()),
+                from: HumanAddr(bidder.to_string()),
+                amount: Uint128(*amount),
+                msg: None,
+            };
+            let _handle_result = handle(&mut deps, before_close_env("bidaddr"), handle_msg);
+        }
+        let reject = |bidder: &str, reason: String| HandleMsg::RejectBid {
+            bidder: HumanAddr(bidder.to_string()),
+            reason,
         };
-        let handle_result = handle(&mut deps, mock_env("bob", &[]), handle_msg);
-        let error = extract_error_msg(handle_result);
-        assert!(error.contains("Only the auction seller can change the minimum bid"));
-        let state: State = load(&deps.storage, CONFIG_KEY).unwrap();
-        assert_eq!(state.minimum_bid, 10);
 
-        // try change min bid after close
-        let handle_msg = HandleMsg::Finalize {
-            new_ends_at: None,
-            new_minimum_bid: None,
-        };
+        // only the seller may reject a bid, and only an active one
         let handle_result = handle(
             &mut deps,
-            Env {
-                block: BlockInfo {
-                    height: 12_345,
-                    time: 1000,
-                    chain_id: "cosmos-testnet-14002".to_string(),
-                },
-                message: MessageInfo {
-                    sender: HumanAddr("bob".to_string()),
-                    sent_funds: vec![],
-                },
-                contract: cosmwasm_std::ContractInfo {
-                    address: HumanAddr::from(MOCK_CONTRACT_ADDR),
-                },
-                contract_key: Some("".to_string()),
-                contract_code_hash: "".to_string(),
-            },
-            handle_msg,
+            before_close_env("charlie"),
+            reject("bob", "spite".to_string()),
         );
-        let (
-            message,
-            winning_bid,
-            bid_decimals,
-            sell_tokens_received,
-            sell_decimals,
-            bid_tokens_received,
-        ) = extract_finalize_fields(&handle_result);
-        assert_eq!(winning_bid, None);
-        assert_eq!(bid_decimals, None);
-        assert_eq!(sell_tokens_received, None);
-        assert_eq!(sell_decimals, None);
-        assert_eq!(bid_tokens_received, None);
-        assert!(message.contains("Auction has been closed"));
-        assert!(!message.contains("Auction has been closed."));
+        assert!(extract_error_msg(handle_result).contains("Only the auction seller"));
+        let handle_result = handle(
+            &mut deps,
+            before_close_env("alice"),
+            reject("dave", "unknown".to_string()),
+        );
+        assert!(extract_error_msg(handle_result).contains("No active bid for address: dave"));
+        let handle_result = handle(
+            &mut deps,
+            before_close_env("alice"),
+            reject("bob", "x".repeat(MAX_REASON_LEN + 1)),
+        );
+        assert!(extract_error_msg(handle_result).contains("rejection reason must not be longer"));
 
-        let handle_msg = HandleMsg::ChangeMinimumBid {
-            minimum_bid: Uint128(20),
-        };
-        let handle_result = handle(&mut deps, mock_env("alice", &[]), handle_msg);
-        let error = extract_error_msg(handle_result);
-        assert!(error.contains("Can not change the minimum bid of an auction that has ended"));
+        let handle_result = handle(
+            &mut deps,
+            before_close_env("alice"),
+            reject("bob", "sanctions screening".to_string()),
+        );
+        // the refund and the factory's RemoveBidder callback
+        let resp = handle_result.unwrap();
+        assert_eq!(resp.messages.len(), 2);
+        let msgs: Vec<(String, String)> = resp
+            .messages
+            .iter()
+            .filter_map(|m| match m {
+                CosmosMsg::Wasm(WasmMsg::Execute {
+                    contract_addr, msg, ..
+                }) => Some((
+                    contract_addr.to_string(),
+                    String::from_utf8(msg.as_slice().to_vec()).unwrap(),
+                )),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(msgs[0].0, "bidaddr");
+        assert!(msgs[0].1.contains("\"recipient\":\"bob\""));
+        assert!(msgs[0].1.contains("\"amount\":\"100\""));
+        assert!(msgs[1].1.contains("\"remove_bidder\""));
         let state: State = load(&deps.storage, CONFIG_KEY).unwrap();
-        assert_eq!(state.minimum_bid, 10);
+        let bob_raw = deps
+            .api
+            .canonical_address(&HumanAddr("bob".to_string()))
+            .unwrap();
+        assert!(!is_bidder(&deps.storage, bob_raw.as_slice()).unwrap());
+        assert_eq!(state.num_bidders, 1);
+        let outcomes = ReadonlyPrefixedStorage::new(PREFIX_OUTCOMES, &deps.storage);
+        let outcome: Outcome = load(&outcomes, bob_raw.as_slice()).unwrap();
+        assert_eq!(outcome.result, BidResult::Rejected);
+        assert_eq!(outcome.returned, 100);
+        assert_eq!(outcome.reason, Some("sanctions screening".to_string()));
+    }
 
-        // sanity check
+    #[test]
+    fn test_pause_bidding() {
         let (init_result, mut deps) = init_helper();
         assert!(
             init_result.is_ok(),
             "Init failed: {}",
             init_result.err().unwrap()
         );
-        let handle_msg = HandleMsg::Receive {
+        let bid_msg = || HandleMsg::Receive {
             sender: HumanAddr("blah".to_string()),
             from: HumanAddr("bob".to_string()),
-            amount: Uint128(10),
+            amount: Uint128(100),
             msg: None,
         };
-        let handle_result = handle(&mut deps, mock_env("bidaddr", &[]), handle_msg);
-        let log = extract_log(handle_result);
-        assert!(log.contains("\"amount_bid\":\"10\""));
-        assert!(log.contains("\"bid_decimals\":8"));
+
+        // only the seller may pause bidding
+        let handle_result = handle(
+            &mut deps,
+            before_close_env("bob"),
+            HandleMsg::PauseBidding {},
+        );
+        assert!(extract_error_msg(handle_result).contains("Only the auction seller"));
+
+        // bids sent while paused are returned
+        let handle_result = handle(
+            &mut deps,
+            before_close_env("alice"),
+            HandleMsg::PauseBidding {},
+        );
+        assert!(handle_result.is_ok());
         let state: State = load(&deps.storage, CONFIG_KEY).unwrap();
-        assert_eq!(state.bidders.len(), 1);
+        assert!(state.bidding_paused);
+        let handle_result = handle(&mut deps, before_close_env("bidaddr"), bid_msg());
+        assert!(extract_log(handle_result).contains("Bidding has been paused"));
 
-        let handle_msg = HandleMsg::ChangeMinimumBid {
-            minimum_bid: Uint128(20),
-        };
-        let _handle_result = handle(&mut deps, mock_env("alice", &[]), handle_msg);
+        // bids are taken again once bidding resumes
+        let handle_result = handle(
+            &mut deps,
+            before_close_env("alice"),
+            HandleMsg::ResumeBidding {},
+        );
+        assert!(handle_result.is_ok());
+        let handle_result = handle(&mut deps, before_close_env("bidaddr"), bid_msg());
+        assert!(handle_result.is_ok());
         let state: State = load(&deps.storage, CONFIG_KEY).unwrap();
-        assert_eq!(state.minimum_bid, 20);
+        assert!(!state.bidding_paused);
+        assert_eq!(state.num_bidders, 1);
 
-        let handle_msg = HandleMsg::Receive {
-            sender: HumanAddr("blah".to_string()),
-            from: HumanAddr("charlie".to_string()),
-            amount: Uint128(15),
-            msg: None,
+        // the pause is displayed in the auction info
+        let _handle_result = handle(
+            &mut deps,
+            before_close_env("alice"),
+            HandleMsg::PauseBidding {},
+        );
+        #[derive(Serialize)]
+        struct MockTokenInfo {
+            name: String,
+            symbol: String,
+            decimals: u8,
+            total_supply: Option<Uint128>,
+        }
+        #[derive(Serialize)]
+        struct MockTokenInfoWrapper {
+            token_info: MockTokenInfo,
+        }
+        struct MyMockQuerier {}
+        impl Querier for MyMockQuerier {
+            fn raw_query(&self, _request: &[u8]) -> QuerierResult {
+                Ok(to_binary(&MockTokenInfoWrapper {
+                    token_info: MockTokenInfo {
+                        name: "token".to_string(),
+                        symbol: "TKN".to_string(),
+                        decimals: 6,
+                        total_supply: None,
+                    },
+                }))
+            }
+        }
+        let deps = deps.change_querier(|_| MyMockQuerier {});
+        let query_msg = QueryMsg::AuctionInfo {
+            lang: None,
+            current_time: None,
         };
-        let handle_result = handle(&mut deps, mock_env("bidaddr", &[]), handle_msg);
-        let log = extract_log(handle_result);
-        assert!(log.contains("Bid was less than minimum allowed"));
-        assert!(log.contains("\"minimum_bid\":\"20\""));
-        assert!(log.contains("\"amount_returned\":\"15\""));
-        assert!(log.contains("\"bid_decimals\":8"));
-        let state: State = load(&deps.storage, CONFIG_KEY).unwrap();
-        assert_eq!(state.bidders.len(), 1);
+        match from_binary(&query(&deps, query_msg).unwrap()).unwrap() {
+            QueryAnswer::AuctionInfo {
+                bidding_paused,
+                status,
+                ..
+            } => {
+                assert!(bidding_paused);
+                assert!(status.starts_with("Bidding paused by the seller"));
+            }
+            _ => panic!("Unexpected QueryAnswer"),
+        }
     }
 
     #[test]
-    fn test_retract_bid() {
+    fn test_only_increasing_rebids() {
         let (init_result, mut deps) = init_helper();
         assert!(
             init_result.is_ok(),
             "Init failed: {}",
             init_result.err().unwrap()
         );
-
-        // try no bid placed
-        let handle_msg = HandleMsg::RetractBid {};
-        let handle_result = handle(&mut deps, mock_env("bob", &[]), handle_msg);
-        let message = extract_msg(&handle_result);
-        assert!(message.contains("No active bid for address"));
-        let (amount, decimals) = extract_amount_returned(&handle_result);
-        assert_eq!(amount, None);
-        assert_eq!(decimals, None);
-
-        // sanity check
-        let handle_msg = HandleMsg::Receive {
+        let bid = |amount: u128| HandleMsg::Receive {
             sender: HumanAddr("blah".to_string()),
             from: HumanAddr("bob".to_string()),
-            amount: Uint128(100),
+            amount: Uint128(amount),
             msg: None,
         };
-        let _handle_result = handle(&mut deps, mock_env("bidaddr", &[]), handle_msg);
-        let state: State = load(&deps.storage, CONFIG_KEY).unwrap();
-        assert_eq!(state.bidders.len(), 1);
-        let bid: Bid = load(
-            &deps.storage,
-            deps.api
+        let bob_bid = |deps: &Extern<MockStorage, MockApi, MockQuerier>| -> u128 {
+            let bob_raw = deps
+                .api
                 .canonical_address(&HumanAddr("bob".to_string()))
-                .unwrap()
-                .as_slice(),
-        )
-        .unwrap();
-        assert_eq!(bid.amount, 100);
+                .unwrap();
+            let bid: Bid = load(&deps.storage, bob_raw.as_slice()).unwrap();
+            bid.amount
+        };
+        // bids may be lowered by default
+        let _handle_result = handle(&mut deps, before_close_env("bidaddr"), bid(100));
+        let handle_result = handle(&mut deps, before_close_env("bidaddr"), bid(50));
+        assert!(extract_log(handle_result).contains("Bid accepted"));
+        assert_eq!(bob_bid(&deps), 50);
+
+        let mut state: State = load(&deps.storage, CONFIG_KEY).unwrap();
+        state.only_increasing_rebids = true;
+        save(&mut deps.storage, CONFIG_KEY, &state).unwrap();
+        // try lowering the bid
+        let handle_result = handle(&mut deps, before_close_env("bidaddr"), bid(40));
+        let log = extract_log(handle_result);
+        assert!(log.contains("This auction only allows a bid to be replaced with a higher bid"));
+        assert!(log.contains("\"previous_bid\":\"50\""));
+        assert!(log.contains("\"amount_returned\":\"40\""));
+        assert_eq!(bob_bid(&deps), 50);
 
-        let handle_msg = HandleMsg::RetractBid {};
-        let handle_result = handle(&mut deps, mock_env("bob", &[]), handle_msg);
-        let message = extract_msg(&handle_result);
-        assert!(message.contains("Bid retracted.  Tokens have been returned"));
-        let (amount, decimals) = extract_amount_returned(&handle_result);
-        assert_eq!(amount, Some(Uint128(100)));
-        assert_eq!(decimals, Some(8));
-        let state: State = load(&deps.storage, CONFIG_KEY).unwrap();
-        assert_eq!(state.bidders.len(), 0);
+        // sanity check
+        let handle_result = handle(&mut deps, before_close_env("bidaddr"), bid(60));
+        let log = extract_log(handle_result);
+        assert!(log.contains("Bid accepted"));
+        assert!(log.contains("\"amount_returned\":\"50\""));
+        assert_eq!(bob_bid(&deps), 60);
     }
 
     #[test]
-    fn test_finalize() {
+    fn test_transfer_seller_rights() {
         let (init_result, mut deps) = init_helper();
         assert!(
             init_result.is_ok(),
             "Init failed: {}",
             init_result.err().unwrap()
         );
+        let transfer = |new_seller: &str| HandleMsg::TransferSellerRights {
+            new_seller: HumanAddr(new_seller.to_string()),
+        };
+        // try from someone other than the seller
+        let handle_result = handle(&mut deps, mock_env("bob", &[]), transfer("bob"));
+        let error = extract_error_msg(handle_result);
+        assert!(error.contains("Only the auction seller can transfer the seller rights"));
 
-        // try return all before closing
-        let handle_msg = HandleMsg::ReturnAll {};
-        let handle_result = handle(&mut deps, mock_env("bob", &[]), handle_msg);
+        // try transferring to the current seller
+        let handle_result = handle(&mut deps, mock_env("alice", &[]), transfer("alice"));
         let error = extract_error_msg(handle_result);
-        assert!(error.contains("return_all can only be executed after the auction has ended"));
+        assert!(error.contains("alice is already the seller of this auction"));
 
-        // try non-seller closing before end time
-        let handle_msg = HandleMsg::Finalize {
-            new_ends_at: None,
-            new_minimum_bid: None,
+        // try transferring to a bidder when the seller may not bid
+        let handle_msg = HandleMsg::Receive {
+            sender: HumanAddr("blah".to_string()),
+            from: HumanAddr("bob".to_string()),
+            amount: Uint128(100),
+            msg: None,
         };
-        let handle_result = handle(
-            &mut deps,
-            Env {
-                block: BlockInfo {
-                    height: 12_345,
-                    time: 1,
-                    chain_id: "cosmos-testnet-14002".to_string(),
-                },
-                message: MessageInfo {
-                    sender: HumanAddr("bob".to_string()),
-                    sent_funds: vec![],
-                },
-                contract: cosmwasm_std::ContractInfo {
-                    address: HumanAddr::from(MOCK_CONTRACT_ADDR),
-                },
-                contract_key: Some("".to_string()),
-                contract_code_hash: "".to_string(),
-            },
-            handle_msg,
-        );
+        let _handle_result = handle(&mut deps, before_close_env("bidaddr"), handle_msg);
+        let mut state: State = load(&deps.storage, CONFIG_KEY).unwrap();
+        state.no_self_bid = true;
+        save(&mut deps.storage, CONFIG_KEY, &state).unwrap();
+        let handle_result = handle(&mut deps, mock_env("alice", &[]), transfer("bob"));
         let error = extract_error_msg(handle_result);
-        assert!(
-            error.contains("Only auction creator can finalize the sale before the closing time")
-        );
-
-        // try non-seller closing after end time
-        let (init_result, mut deps) = init_helper();
-        assert!(
-            init_result.is_ok(),
-            "Init failed: {}",
-            init_result.err().unwrap()
-        );
+        assert!(error.contains("bob has an active bid"));
 
-        let handle_msg = HandleMsg::Finalize {
-            new_ends_at: None,
-            new_minimum_bid: None,
-        };
-        let handle_result = handle(
-            &mut deps,
-            Env {
-                block: BlockInfo {
-                    height: 12_345,
-                    time: 1000,
-                    chain_id: "cosmos-testnet-14002".to_string(),
-                },
-                message: MessageInfo {
-                    sender: HumanAddr("bob".to_string()),
-                    sent_funds: vec![],
-                },
-                contract: cosmwasm_std::ContractInfo {
-                    address: HumanAddr::from(MOCK_CONTRACT_ADDR),
-                },
-                contract_key: Some("".to_string()),
-                contract_code_hash: "".to_string(),
-            },
-            handle_msg,
-        );
-        let (
-            message,
-            winning_bid,
-            bid_decimals,
-            sell_tokens_received,
-            sell_decimals,
-            bid_tokens_received,
-        ) = extract_finalize_fields(&handle_result);
-        assert_eq!(winning_bid, None);
-        assert_eq!(bid_decimals, None);
-        assert_eq!(sell_tokens_received, None);
-        assert_eq!(sell_decimals, None);
-        assert_eq!(bid_tokens_received, None);
-        assert!(message.contains("Auction has been closed"));
-        assert!(!message.contains("Auction has been closed."));
+        // sanity check
+        let handle_result = handle(&mut deps, mock_env("alice", &[]), transfer("charlie"));
+        assert!(handle_result.is_ok());
+        assert_eq!(handle_result.unwrap().messages.len(), 1);
+        let state: State = load(&deps.storage, CONFIG_KEY).unwrap();
+        assert_eq!(state.seller, HumanAddr("charlie".to_string()));
+        // the previous seller no longer has seller rights
+        let handle_msg = HandleMsg::ChangeMinimumBid {
+            minimum_bid: Uint128(20),
+            refund_lower_bids: false,
+        };
+        let handle_result = handle(&mut deps, mock_env("alice", &[]), handle_msg);
+        let error = extract_error_msg(handle_result);
+        assert!(error.contains("Only the auction seller can change the minimum bid"));
+
+        // try after the tokens for sale have been consigned
+        let handle_msg = HandleMsg::Receive {
+            sender: HumanAddr("charlie".to_string()),
+            from: HumanAddr("charlie".to_string()),
+            amount: Uint128(10),
+            msg: None,
+        };
+        let _handle_result = handle(&mut deps, before_close_env("selladdr"), handle_msg);
+        let handle_result = handle(&mut deps, mock_env("charlie", &[]), transfer("dave"));
+        let error = extract_error_msg(handle_result);
+        assert!(error.contains("Seller rights can only be transferred before the tokens"));
+    }
 
+    #[test]
+    fn test_operators() {
         let (init_result, mut deps) = init_helper();
         assert!(
             init_result.is_ok(),
             "Init failed: {}",
             init_result.err().unwrap()
         );
-        // try stranger not wanting to close without bids
-        let handle_msg = HandleMsg::Finalize {
-            new_ends_at: Some(2000),
-            new_minimum_bid: None,
-        };
-        let handle_result = handle(
-            &mut deps,
-            Env {
-                block: BlockInfo {
-                    height: 12_345,
-                    time: 1100,
-                    chain_id: "cosmos-testnet-14002".to_string(),
-                },
-                message: MessageInfo {
-                    sender: HumanAddr("bob".to_string()),
-                    sent_funds: vec![],
-                },
-                contract: cosmwasm_std::ContractInfo {
-                    address: HumanAddr::from(MOCK_CONTRACT_ADDR),
-                },
-                contract_key: Some("".to_string()),
-                contract_code_hash: "".to_string(),
-            },
-            handle_msg,
-        );
-        let error = extract_error_msg(handle_result);
-        assert!(error
-            .contains("Only the auction seller can change the closing time or the minimum bid"));
-        // try stranger not wanting to close without bids
-        let handle_msg = HandleMsg::Finalize {
-            new_ends_at: None,
-            new_minimum_bid: Some(Uint128(1000)),
+        let operators = || vec![HumanAddr("bob".to_string())];
+        // try adding an operator when not the seller
+        let handle_msg = HandleMsg::AddOperators {
+            operators: operators(),
         };
-        let handle_result = handle(
-            &mut deps,
-            Env {
-                block: BlockInfo {
-                    height: 12_345,
-                    time: 1100,
-                    chain_id: "cosmos-testnet-14002".to_string(),
-                },
-                message: MessageInfo {
-                    sender: HumanAddr("bob".to_string()),
-                    sent_funds: vec![],
-                },
-                contract: cosmwasm_std::ContractInfo {
-                    address: HumanAddr::from(MOCK_CONTRACT_ADDR),
-                },
-                contract_key: Some("".to_string()),
-                contract_code_hash: "".to_string(),
-            },
-            handle_msg,
-        );
+        let handle_result = handle(&mut deps, mock_env("bob", &[]), handle_msg);
         let error = extract_error_msg(handle_result);
-        assert!(error
-            .contains("Only the auction seller can change the closing time or the minimum bid"));
-        // try stranger not wanting to close without bids
-        let handle_msg = HandleMsg::Finalize {
-            new_ends_at: Some(2000),
-            new_minimum_bid: Some(Uint128(1000)),
+        assert!(error.contains("Only the auction seller can change the operators"));
+
+        // operators can not act before they are authorized
+        let handle_msg = HandleMsg::ChangeMinimumBid {
+            minimum_bid: Uint128(20),
+            refund_lower_bids: false,
         };
-        let handle_result = handle(
-            &mut deps,
-            Env {
-                block: BlockInfo {
-                    height: 12_345,
-                    time: 1100,
-                    chain_id: "cosmos-testnet-14002".to_string(),
-                },
-                message: MessageInfo {
-                    sender: HumanAddr("bob".to_string()),
-                    sent_funds: vec![],
-                },
-                contract: cosmwasm_std::ContractInfo {
-                    address: HumanAddr::from(MOCK_CONTRACT_ADDR),
-                },
-                contract_key: Some("".to_string()),
-                contract_code_hash: "".to_string(),
-            },
-            handle_msg,
-        );
+        let handle_result = handle(&mut deps, mock_env("bob", &[]), handle_msg);
         let error = extract_error_msg(handle_result);
-        assert!(error
-            .contains("Only the auction seller can change the closing time or the minimum bid"));
+        assert!(error.contains("Only the auction seller can change the minimum bid"));
 
-        // try seller not wanting to close without bids
+        let handle_msg = HandleMsg::AddOperators {
+            operators: operators(),
+        };
+        let handle_result = handle(&mut deps, mock_env("alice", &[]), handle_msg);
+        let handle_answer: HandleAnswer =
+            from_binary(&handle_result.unwrap().data.unwrap()).unwrap();
+        match handle_answer {
+            HandleAnswer::Operators { operators, .. } => {
+                assert_eq!(operators, vec![HumanAddr("bob".to_string())])
+            }
+            _ => panic!("Unexpected HandleAnswer"),
+        }
+        // the operator may change the minimum bid
+        let handle_msg = HandleMsg::ChangeMinimumBid {
+            minimum_bid: Uint128(20),
+            refund_lower_bids: false,
+        };
+        let handle_result = handle(&mut deps, mock_env("bob", &[]), handle_msg);
+        assert!(handle_result.is_ok());
+        let state: State = load(&deps.storage, CONFIG_KEY).unwrap();
+        assert_eq!(state.minimum_bid, 20);
+        // and extend the closing time of an auction without bids
         let handle_msg = HandleMsg::Finalize {
             new_ends_at: Some(2000),
             new_minimum_bid: None,
         };
-        let handle_result = handle(
-            &mut deps,
-            Env {
-                block: BlockInfo {
-                    height: 12_345,
-                    time: 1,
-                    chain_id: "cosmos-testnet-14002".to_string(),
-                },
-                message: MessageInfo {
-                    sender: HumanAddr("alice".to_string()),
-                    sent_funds: vec![],
-                },
-                contract: cosmwasm_std::ContractInfo {
-                    address: HumanAddr::from(MOCK_CONTRACT_ADDR),
-                },
-                contract_key: Some("".to_string()),
-                contract_code_hash: "".to_string(),
-            },
-            handle_msg,
-        );
-        let (
-            message,
-            winning_bid,
-            bid_decimals,
-            sell_tokens_received,
-            sell_decimals,
-            bid_tokens_received,
-        ) = extract_finalize_fields(&handle_result);
-        assert_eq!(winning_bid, None);
-        assert_eq!(bid_decimals, None);
-        assert_eq!(sell_tokens_received, None);
-        assert_eq!(sell_decimals, None);
-        assert_eq!(bid_tokens_received, None);
-        assert!(message.contains("There were no active bids.  The closing time has been updated"));
+        let handle_result = handle(&mut deps, before_close_env("bob"), handle_msg);
+        assert!(handle_result.is_ok());
         let state: State = load(&deps.storage, CONFIG_KEY).unwrap();
         assert_eq!(state.ends_at, 2000);
-        assert_eq!(state.minimum_bid, 10);
+        // but may not change the operators
+        let handle_msg = HandleMsg::RemoveOperators {
+            operators: operators(),
+        };
+        let handle_result = handle(&mut deps, mock_env("bob", &[]), handle_msg);
+        let error = extract_error_msg(handle_result);
+        assert!(error.contains("Only the auction seller can change the operators"));
 
-        // try seller not wanting to close without bids
+        #[derive(Debug)]
+        struct MyMockQuerier {
+            pub is_valid: bool,
+        }
+        impl Querier for MyMockQuerier {
+            fn raw_query(&self, _request: &[u8]) -> QuerierResult {
+                Ok(to_binary(&IsKeyValidWrapper {
+                    is_key_valid: IsKeyValid {
+                        is_valid: self.is_valid,
+                    },
+                }))
+            }
+        }
+        let mut deps = deps.change_querier(|_| MyMockQuerier { is_valid: true });
+        let operator_query = |address: &str| QueryMsg::Operators {
+            address: HumanAddr(address.to_string()),
+            viewing_key: "key".to_string(),
+        };
+        // try someone who is neither the seller nor an operator
+        let error = extract_error_msg(query(&deps, operator_query("charlie")));
+        assert!(error.contains("does not match the auction seller or its operators"));
+        // operators may view the list
+        match from_binary(&query(&deps, operator_query("bob")).unwrap()).unwrap() {
+            QueryAnswer::Operators { operators } => {
+                assert_eq!(operators, vec![HumanAddr("bob".to_string())])
+            }
+            _ => panic!("Unexpected QueryAnswer"),
+        }
+
+        // revoke the operator
+        let handle_msg = HandleMsg::RemoveOperators {
+            operators: operators(),
+        };
+        let _handle_result = handle(&mut deps, mock_env("alice", &[]), handle_msg);
+        match from_binary(&query(&deps, operator_query("alice")).unwrap()).unwrap() {
+            QueryAnswer::Operators { operators } => assert!(operators.is_empty()),
+            _ => panic!("Unexpected QueryAnswer"),
+        }
         let handle_msg = HandleMsg::Finalize {
             new_ends_at: None,
-            new_minimum_bid: Some(Uint128(1000)),
+            new_minimum_bid: None,
         };
-        let handle_result = handle(
-            &mut deps,
-            Env {
-                block: BlockInfo {
-                    height: 12_345,
-                    time: 1,
-                    chain_id: "cosmos-testnet-14002".to_string(),
-                },
-                message: MessageInfo {
-                    sender: HumanAddr("alice".to_string()),
-                    sent_funds: vec![],
-                },
-                contract: cosmwasm_std::ContractInfo {
-                    address: HumanAddr::from(MOCK_CONTRACT_ADDR),
-                },
-                contract_key: Some("".to_string()),
-                contract_code_hash: "".to_string(),
-            },
-            handle_msg,
+        let handle_result = handle(&mut deps, before_close_env("bob"), handle_msg);
+        let error = extract_error_msg(handle_result);
+        assert!(
+            error.contains("Only auction creator can finalize the sale before the closing time")
         );
-        let (
-            message,
-            winning_bid,
-            bid_decimals,
-            sell_tokens_received,
-            sell_decimals,
-            bid_tokens_received,
-        ) = extract_finalize_fields(&handle_result);
-        assert_eq!(winning_bid, None);
-        assert_eq!(bid_decimals, None);
-        assert_eq!(sell_tokens_received, None);
-        assert_eq!(sell_decimals, None);
-        assert_eq!(bid_tokens_received, None);
-        assert!(message.contains("There were no active bids.  The minimum bid has been updated"));
-        let state: State = load(&deps.storage, CONFIG_KEY).unwrap();
-        assert_eq!(state.ends_at, 2000);
-        assert_eq!(state.minimum_bid, 1000);
+    }
 
-        // try seller not wanting to close without bids
-        let handle_msg = HandleMsg::Finalize {
-            new_ends_at: Some(10000),
-            new_minimum_bid: Some(Uint128(100000)),
-        };
-        let handle_result = handle(
-            &mut deps,
-            Env {
-                block: BlockInfo {
-                    height: 12_345,
-                    time: 1,
-                    chain_id: "cosmos-testnet-14002".to_string(),
-                },
-                message: MessageInfo {
-                    sender: HumanAddr("alice".to_string()),
-                    sent_funds: vec![],
-                },
-                contract: cosmwasm_std::ContractInfo {
-                    address: HumanAddr::from(MOCK_CONTRACT_ADDR),
-                },
-                contract_key: Some("".to_string()),
-                contract_code_hash: "".to_string(),
-            },
-            handle_msg,
+    #[test]
+    fn test_query_view_outcome() {
+        #[derive(Debug)]
+        struct MyMockQuerier {
+            pub is_valid: bool,
+        }
+        impl Querier for MyMockQuerier {
+            fn raw_query(&self, _request: &[u8]) -> QuerierResult {
+                Ok(to_binary(&IsKeyValidWrapper {
+                    is_key_valid: IsKeyValid {
+                        is_valid: self.is_valid,
+                    },
+                }))
+            }
+        }
+        fn view_outcome<S: Storage, A: Api, Q: Querier>(
+            deps: &Extern<S, A, Q>,
+            address: &str,
+        ) -> (String, Option<BidResult>, Option<Uint128>, Option<Uint128>) {
+            let query_msg = QueryMsg::ViewOutcome {
+                address: HumanAddr(address.to_string()),
+                viewing_key: "key".to_string(),
+            };
+            match from_binary(&query(deps, query_msg).unwrap()).unwrap() {
+                QueryAnswer::Outcome {
+                    message,
+                    result,
+                    amount_bid,
+                    amount_returned,
+                    ..
+                } => (message, result, amount_bid, amount_returned),
+                _ => panic!("Unexpected"),
+            }
+        }
+        let (init_result, deps) = init_helper();
+        assert!(
+            init_result.is_ok(),
+            "Init failed: {}",
+            init_result.err().unwrap()
         );
-        let (
-            message,
-            winning_bid,
-            bid_decimals,
-            sell_tokens_received,
-            sell_decimals,
-            bid_tokens_received,
-        ) = extract_finalize_fields(&handle_result);
-        assert_eq!(winning_bid, None);
-        assert_eq!(bid_decimals, None);
-        assert_eq!(sell_tokens_received, None);
-        assert_eq!(sell_decimals, None);
-        assert_eq!(bid_tokens_received, None);
-        assert!(message.contains(
-            "There were no active bids.  The closing time and minimum bid has been updated"
-        ));
-        let state: State = load(&deps.storage, CONFIG_KEY).unwrap();
-        assert_eq!(state.ends_at, 10000);
-        assert_eq!(state.minimum_bid, 100000);
+        // try wrong key
+        let invalid_deps = deps.change_querier(|_| MyMockQuerier { is_valid: false });
+        let query_msg = QueryMsg::ViewOutcome {
+            address: HumanAddr("bob".to_string()),
+            viewing_key: "wrong_key".to_string(),
+        };
+        let query_result = query(&invalid_deps, query_msg);
+        let error = extract_error_msg(query_result);
+        assert!(error.contains("Wrong viewing key"));
 
-        // test 3 bidders, highest retracts, other two are tied with time tie-breaker
-        // winner closes after end time
-        let (init_result, mut deps) = init_helper();
+        let (init_result, deps) = init_helper();
         assert!(
             init_result.is_ok(),
             "Init failed: {}",
             init_result.err().unwrap()
         );
+        let mut deps = deps.change_querier(|_| MyMockQuerier { is_valid: true });
+        // try no outcome
+        let (message, result, _, _) = view_outcome(&deps, "bob");
+        assert!(message.contains("No bid outcome for address: bob"));
+        assert_eq!(result, None);
 
         let handle_msg = HandleMsg::Receive {
             sender: HumanAddr("blah".to_string()),
-            from: HumanAddr("bob".to_string()),
-            amount: Uint128(100),
+            from: HumanAddr("alice".to_string()),
+            amount: Uint128(10),
             msg: None,
         };
-        let _handle_result = handle(
-            &mut deps,
-            Env {
-                block: BlockInfo {
-                    height: 12_345,
-                    time: 10,
-                    chain_id: "cosmos-testnet-14002".to_string(),
-                },
-                message: MessageInfo {
-                    sender: HumanAddr("bidaddr".to_string()),
-                    sent_funds: vec![],
-                },
-                contract: cosmwasm_std::ContractInfo {
-                    address: HumanAddr::from(MOCK_CONTRACT_ADDR),
-                },
-                contract_key: Some("".to_string()),
-                contract_code_hash: "".to_string(),
-            },
-            handle_msg,
-        );
-        let handle_msg = HandleMsg::Receive {
-            sender: HumanAddr("blah".to_string()),
-            from: HumanAddr("charlie".to_string()),
-            amount: Uint128(100),
-            msg: None,
+        let _handle_result = handle(&mut deps, before_close_env("selladdr"), handle_msg);
+        for (bidder, amount) in &[("bob", 100), ("charlie", 50), ("david", 20)] {
+            let handle_msg = HandleMsg::Receive {
+                sender: HumanAddr("blah".to_string()),
+                from: HumanAddr(bidder.to_string()),
+                amount: Uint128(*amount),
+                msg: None,
+            };
+            let _handle_result = handle(&mut deps, before_close_env("bidaddr"), handle_msg);
+        }
+        // retracted bid
+        let handle_msg = HandleMsg::RetractBid {};
+        let _handle_result = handle(&mut deps, before_close_env("david"), handle_msg);
+        let (message, result, amount_bid, amount_returned) = view_outcome(&deps, "david");
+        assert!(message.contains("Bid was retracted"));
+        assert_eq!(result, Some(BidResult::Retracted));
+        assert_eq!(amount_bid, Some(Uint128(20)));
+        assert_eq!(amount_returned, Some(Uint128(20)));
+
+        let handle_msg = HandleMsg::Finalize {
+            new_ends_at: None,
+            new_minimum_bid: None,
         };
-        let _handle_result = handle(
-            &mut deps,
-            Env {
-                block: BlockInfo {
-                    height: 12_345,
-                    time: 12,
-                    chain_id: "cosmos-testnet-14002".to_string(),
-                },
-                message: MessageInfo {
-                    sender: HumanAddr("bidaddr".to_string()),
-                    sent_funds: vec![],
-                },
-                contract: cosmwasm_std::ContractInfo {
-                    address: HumanAddr::from(MOCK_CONTRACT_ADDR),
-                },
-                contract_key: Some("".to_string()),
-                contract_code_hash: "".to_string(),
-            },
-            handle_msg,
+        let _handle_result = handle(&mut deps, mock_env("alice", &[]), handle_msg);
+        // winning bid
+        let (message, result, amount_bid, amount_returned) = view_outcome(&deps, "bob");
+        assert!(message.contains("Bid won"));
+        assert_eq!(result, Some(BidResult::Won));
+        assert_eq!(amount_bid, Some(Uint128(100)));
+        assert_eq!(amount_returned, Some(Uint128(0)));
+        // losing bid
+        let (message, result, amount_bid, amount_returned) = view_outcome(&deps, "charlie");
+        assert!(message.contains("Bid did not win"));
+        assert_eq!(result, Some(BidResult::Lost));
+        assert_eq!(amount_bid, Some(Uint128(50)));
+        assert_eq!(amount_returned, Some(Uint128(50)));
+    }
+
+    #[test]
+    fn test_query_win_proof() {
+        #[derive(Debug)]
+        struct MyMockQuerier {
+            pub is_valid: bool,
+        }
+        impl Querier for MyMockQuerier {
+            fn raw_query(&self, _request: &[u8]) -> QuerierResult {
+                Ok(to_binary(&IsKeyValidWrapper {
+                    is_key_valid: IsKeyValid {
+                        is_valid: self.is_valid,
+                    },
+                }))
+            }
+        }
+        let win_proof = |address: &str| QueryMsg::WinProof {
+            address: HumanAddr(address.to_string()),
+            viewing_key: "key".to_string(),
+        };
+        let (init_result, deps) = init_helper();
+        assert!(
+            init_result.is_ok(),
+            "Init failed: {}",
+            init_result.err().unwrap()
         );
+        let mut deps = deps.change_querier(|_| MyMockQuerier { is_valid: true });
         let handle_msg = HandleMsg::Receive {
             sender: HumanAddr("blah".to_string()),
-            from: HumanAddr("david".to_string()),
-            amount: Uint128(1000),
+            from: HumanAddr("alice".to_string()),
+            amount: Uint128(10),
             msg: None,
         };
-        let _handle_result = handle(
-            &mut deps,
-            Env {
-                block: BlockInfo {
-                    height: 12_345,
-                    time: 5,
-                    chain_id: "cosmos-testnet-14002".to_string(),
-                },
-                message: MessageInfo {
-                    sender: HumanAddr("bidaddr".to_string()),
-                    sent_funds: vec![],
-                },
-                contract: cosmwasm_std::ContractInfo {
-                    address: HumanAddr::from(MOCK_CONTRACT_ADDR),
-                },
-                contract_key: Some("".to_string()),
-                contract_code_hash: "".to_string(),
-            },
-            handle_msg,
+        let _handle_result = handle(&mut deps, before_close_env("selladdr"), handle_msg);
+        for (bidder, amount) in &[("bob", 100), ("charlie", 50)] {
+            let handle_msg = HandleMsg::Receive {
+                sender: HumanAddr("blah".to_string()),
+                from: HumanAddr(bidder.to_string()),
+                amount: Uint128(*amount),
+                msg: None,
+            };
+            let _handle_result = handle(&mut deps, before_close_env("bidaddr"), handle_msg);
+        }
+        let proof = |deps: &Extern<MockStorage, MockApi, MyMockQuerier>, address: &str| {
+            let response = query(deps, win_proof(address)).unwrap();
+            // the proof reveals a bid, so it is padded like every other bid answer
+            assert_eq!(response.len(), BID_ANSWER_SIZE);
+            match from_binary(&response).unwrap() {
+                QueryAnswer::WinProof {
+                    status,
+                    message,
+                    winning_bid,
+                    bid_timestamp,
+                    closed_at,
+                    commitment,
+                    ..
+                } => (
+                    status,
+                    message,
+                    winning_bid,
+                    bid_timestamp,
+                    closed_at,
+                    commitment,
+                ),
+                _ => panic!("Unexpected"),
+            }
+        };
+        // try before the auction closed
+        let (status, message, winning_bid, _, _, commitment) = proof(&deps, "bob");
+        assert!(matches!(status, Failure));
+        assert!(message.contains("The auction has not closed yet"));
+        assert!(winning_bid.is_none());
+        assert!(commitment.is_none());
+
+        let handle_msg = HandleMsg::Finalize {
+            new_ends_at: None,
+            new_minimum_bid: None,
+        };
+        let env = mock_env("alice", &[]);
+        let closed_at = env.block.time;
+        let auction_addr = env.contract.address.clone();
+        let _handle_result = handle(&mut deps, env, handle_msg);
+        // try a losing bidder
+        let (status, message, winning_bid, _, _, commitment) = proof(&deps, "charlie");
+        assert!(matches!(status, Failure));
+        assert!(message.contains("Address charlie did not win this auction"));
+        assert!(winning_bid.is_none());
+        assert!(commitment.is_none());
+
+        // sanity check
+        let (status, _, winning_bid, bid_timestamp, closed, commitment) = proof(&deps, "bob");
+        assert!(matches!(status, Success));
+        assert_eq!(winning_bid, Some(Uint128(100)));
+        assert_eq!(bid_timestamp, Some(500));
+        assert_eq!(closed, Some(closed_at));
+        let preimage = format!("{}:bob:10:100:{}", auction_addr, closed_at);
+        let commitment = commitment.unwrap();
+        assert_eq!(commitment.as_slice(), &sha_256(preimage.as_bytes())[..]);
+    }
+
+    #[test]
+    fn test_match_swap() {
+        let (init_result, mut deps) = init_helper();
+        assert!(
+            init_result.is_ok(),
+            "Init failed: {}",
+            init_result.err().unwrap()
         );
-        let state: State = load(&deps.storage, CONFIG_KEY).unwrap();
-        assert_eq!(state.bidders.len(), 3);
-        let handle_msg = HandleMsg::RetractBid {};
-        let _handle_result = handle(&mut deps, mock_env("david", &[]), handle_msg);
-        let state: State = load(&deps.storage, CONFIG_KEY).unwrap();
-        assert_eq!(state.bidders.len(), 2);
+        let handle_msg = HandleMsg::MatchSwap {
+            recipient: HumanAddr("charlie".to_string()),
+            received: Uint128(10),
+        };
+        // try not the factory
+        let handle_result = handle(&mut deps, mock_env("alice", &[]), handle_msg);
+        let error = extract_error_msg(handle_result);
+        assert!(error.contains("Only the factory can match this auction"));
+
+        // try before consigning
+        let handle_msg = HandleMsg::MatchSwap {
+            recipient: HumanAddr("charlie".to_string()),
+            received: Uint128(10),
+        };
+        let handle_result = handle(&mut deps, mock_env("factoryaddr", &[]), handle_msg);
+        let error = extract_error_msg(handle_result);
+        assert!(error.contains("The tokens for sale have not been consigned"));
 
         let handle_msg = HandleMsg::Receive {
             sender: HumanAddr("blah".to_string()),
             from: HumanAddr("alice".to_string()),
-            amount: Uint128(20),
+            amount: Uint128(10),
             msg: None,
         };
         let _handle_result = handle(&mut deps, mock_env("selladdr", &[]), handle_msg);
+        // try less than the minimum bid
+        let handle_msg = HandleMsg::MatchSwap {
+            recipient: HumanAddr("charlie".to_string()),
+            received: Uint128(9),
+        };
+        let handle_result = handle(&mut deps, mock_env("factoryaddr", &[]), handle_msg);
+        let error = extract_error_msg(handle_result);
+        assert!(error.contains("The matched amount 9 is less than the minimum bid 10"));
+
+        // sanity check
+        let handle_msg = HandleMsg::MatchSwap {
+            recipient: HumanAddr("charlie".to_string()),
+            received: Uint128(12),
+        };
+        let handle_result = handle(&mut deps, mock_env("factoryaddr", &[]), handle_msg);
+        // transfer the lot and close with the factory
+        assert_eq!(handle_result.as_ref().unwrap().messages.len(), 2);
+        let (message, winning_bid, ..) = extract_finalize_fields(&handle_result);
+        assert!(message.contains("settled with a complementary auction"));
+        assert_eq!(winning_bid, Some(Uint128(12)));
+        let state: State = load(&deps.storage, CONFIG_KEY).unwrap();
+        assert!(state.is_completed);
+        assert_eq!(state.currently_consigned, 0);
+        assert_eq!(state.winning_bid, 12);
+
+        // try after closing
+        let handle_msg = HandleMsg::MatchSwap {
+            recipient: HumanAddr("charlie".to_string()),
+            received: Uint128(12),
+        };
+        let handle_result = handle(&mut deps, mock_env("factoryaddr", &[]), handle_msg);
+        let error = extract_error_msg(handle_result);
+        assert!(error.contains("The auction has already closed"));
+    }
+
+    #[test]
+    fn test_factory_finalize_deferred() {
+        let (init_result, mut deps) = init_helper();
+        assert!(
+            init_result.is_ok(),
+            "Init failed: {}",
+            init_result.err().unwrap()
+        );
+        let handle_msg = HandleMsg::Halt { halted: true };
+        let handle_result = handle(&mut deps, mock_env("factoryaddr", &[]), handle_msg);
+        assert!(handle_result.is_ok());
+
+        // anyone else still gets an error
+        let mut env = mock_env("bob", &[]);
+        env.block.time = 2000;
         let handle_msg = HandleMsg::Finalize {
             new_ends_at: None,
             new_minimum_bid: None,
         };
-        let handle_result = handle(
-            &mut deps,
-            Env {
-                block: BlockInfo {
-                    height: 12_345,
-                    time: 2000,
-                    chain_id: "cosmos-testnet-14002".to_string(),
-                },
-                message: MessageInfo {
-                    sender: HumanAddr("bob".to_string()),
-                    sent_funds: vec![],
-                },
-                contract: cosmwasm_std::ContractInfo {
-                    address: HumanAddr::from(MOCK_CONTRACT_ADDR),
-                },
-                contract_key: Some("".to_string()),
-                contract_code_hash: "".to_string(),
-            },
-            handle_msg,
-        );
-        let (
-            message,
-            winning_bid,
-            bid_decimals,
-            sell_tokens_received,
-            sell_decimals,
-            bid_tokens_received,
-        ) = extract_finalize_fields(&handle_result);
-        assert_eq!(winning_bid, Some(Uint128(100)));
-        assert_eq!(bid_decimals, Some(8));
-        assert_eq!(sell_tokens_received, Some(Uint128(10)));
-        assert_eq!(sell_decimals, Some(4));
-        assert_eq!(bid_tokens_received, None);
-        assert!(message.contains(
-            "Sale has been finalized.  Your bid won! You have been sent the sale token(s)"
-        ));
+        let handle_result = handle(&mut deps, env, handle_msg);
+        let error = extract_error_msg(handle_result);
+        assert!(error.contains("This auction has been halted by its factory"));
 
-        // test 3 bidders, highest is seller, seller closes
+        // the factory's batch finalize gets a failure answer instead
+        let mut env = mock_env("factoryaddr", &[]);
+        env.block.time = 2000;
+        let handle_msg = HandleMsg::Finalize {
+            new_ends_at: None,
+            new_minimum_bid: None,
+        };
+        let handle_result = handle(&mut deps, env, handle_msg);
+        assert!(handle_result.is_ok());
+        assert!(handle_result.as_ref().unwrap().messages.is_empty());
+        let handle_answer: HandleAnswer =
+            from_binary(&handle_result.unwrap().data.unwrap()).unwrap();
+        match handle_answer {
+            HandleAnswer::CloseAuction {
+                status, message, ..
+            } => {
+                assert!(matches!(status, Failure));
+                assert!(message.contains("This auction has been halted by its factory"));
+            }
+            _ => panic!("Unexpected HandleAnswer"),
+        }
+        let state: State = load(&deps.storage, CONFIG_KEY).unwrap();
+        assert!(!state.is_completed);
+    }
+
+    #[test]
+    fn test_factory_only_handles() {
         let (init_result, mut deps) = init_helper();
         assert!(
             init_result.is_ok(),
             "Init failed: {}",
             init_result.err().unwrap()
         );
+        // try halting when not the factory
+        let handle_msg = HandleMsg::Halt { halted: true };
+        let handle_result = handle(&mut deps, mock_env("alice", &[]), handle_msg);
+        let error = extract_error_msg(handle_result);
+        assert!(error.contains("Only the factory can halt this auction"));
+
+        // halt the auction
+        let handle_msg = HandleMsg::Halt { halted: true };
+        let handle_result = handle(&mut deps, mock_env("factoryaddr", &[]), handle_msg);
+        assert!(handle_result.is_ok());
+        let state: State = load(&deps.storage, CONFIG_KEY).unwrap();
+        assert!(state.is_halted);
+
+        // try consigning while halted
+        let handle_msg = HandleMsg::Receive {
+            sender: HumanAddr("blah".to_string()),
+            from: HumanAddr("alice".to_string()),
+            amount: Uint128(10),
+            msg: None,
+        };
+        let handle_result = handle(&mut deps, mock_env("selladdr", &[]), handle_msg);
+        let log = extract_log(handle_result);
+        assert!(log.contains("This auction has been halted by its factory"));
+        assert!(log.contains("\"status\":\"failure\""));
+        assert!(log.contains("\"amount_returned\":\"10\""));
 
+        // bids while halted are returned the same way
         let handle_msg = HandleMsg::Receive {
             sender: HumanAddr("blah".to_string()),
             from: HumanAddr("bob".to_string()),
             amount: Uint128(100),
             msg: None,
         };
-        let _handle_result = handle(
-            &mut deps,
-            Env {
-                block: BlockInfo {
-                    height: 12_345,
-                    time: 10,
-                    chain_id: "cosmos-testnet-14002".to_string(),
-                },
-                message: MessageInfo {
-                    sender: HumanAddr("bidaddr".to_string()),
-                    sent_funds: vec![],
-                },
-                contract: cosmwasm_std::ContractInfo {
-                    address: HumanAddr::from(MOCK_CONTRACT_ADDR),
-                },
-                contract_key: Some("".to_string()),
-                contract_code_hash: "".to_string(),
-            },
-            handle_msg,
-        );
+        let handle_result = handle(&mut deps, mock_env("bidaddr", &[]), handle_msg);
+        assert_eq!(handle_result.as_ref().unwrap().messages.len(), 1);
+        let log = extract_log(handle_result);
+        assert!(log.contains("This auction has been halted by its factory"));
+        assert!(log.contains("\"amount_returned\":\"100\""));
+
+        // try finalizing while halted
+        let handle_msg = HandleMsg::Finalize {
+            new_ends_at: None,
+            new_minimum_bid: None,
+        };
+        let handle_result = handle(&mut deps, mock_env("alice", &[]), handle_msg);
+        let error = extract_error_msg(handle_result);
+        assert!(error.contains("This auction has been halted by its factory"));
+
+        // resume and consign
+        let handle_msg = HandleMsg::Halt { halted: false };
+        let _handle_result = handle(&mut deps, mock_env("factoryaddr", &[]), handle_msg);
         let handle_msg = HandleMsg::Receive {
             sender: HumanAddr("blah".to_string()),
-            from: HumanAddr("charlie".to_string()),
-            amount: Uint128(200),
+            from: HumanAddr("alice".to_string()),
+            amount: Uint128(10),
             msg: None,
         };
-        let _handle_result = handle(
-            &mut deps,
-            Env {
-                block: BlockInfo {
-                    height: 12_345,
-                    time: 12,
-                    chain_id: "cosmos-testnet-14002".to_string(),
-                },
-                message: MessageInfo {
-                    sender: HumanAddr("bidaddr".to_string()),
-                    sent_funds: vec![],
-                },
-                contract: cosmwasm_std::ContractInfo {
-                    address: HumanAddr::from(MOCK_CONTRACT_ADDR),
-                },
-                contract_key: Some("".to_string()),
-                contract_code_hash: "".to_string(),
+        let handle_result = handle(&mut deps, mock_env("selladdr", &[]), handle_msg);
+        assert!(handle_result.is_ok());
+
+        // only the factory can deprecate the auction
+        let handle_msg = HandleMsg::Deprecate {
+            notice: "Use the new version".to_string(),
+        };
+        let handle_result = handle(&mut deps, mock_env("alice", &[]), handle_msg);
+        let error = extract_error_msg(handle_result);
+        assert!(error.contains("Only the factory can deprecate this auction"));
+        let handle_msg = HandleMsg::Deprecate {
+            notice: "Use the new version".to_string(),
+        };
+        let handle_result = handle(&mut deps, mock_env("factoryaddr", &[]), handle_msg);
+        assert!(handle_result.is_ok());
+        let state: State = load(&deps.storage, CONFIG_KEY).unwrap();
+        assert_eq!(
+            state.deprecation_notice,
+            Some("Use the new version".to_string())
+        );
+
+        // try migrating when not the factory
+        let handle_msg = HandleMsg::Migrate {
+            factory: FactoryInfo {
+                code_hash: "newhash".to_string(),
+                address: HumanAddr("newfactory".to_string()),
             },
-            handle_msg,
+            index: 7,
+            callback_key: "newkey".to_string(),
+        };
+        let handle_result = handle(&mut deps, mock_env("newfactory", &[]), handle_msg);
+        let error = extract_error_msg(handle_result);
+        assert!(error.contains("Only the factory can migrate this auction"));
+
+        // migrate to the new factory
+        let handle_msg = HandleMsg::Migrate {
+            factory: FactoryInfo {
+                code_hash: "newhash".to_string(),
+                address: HumanAddr("newfactory".to_string()),
+            },
+            index: 7,
+            callback_key: "newkey".to_string(),
+        };
+        let handle_result = handle(&mut deps, mock_env("factoryaddr", &[]), handle_msg);
+        assert!(handle_result.is_ok());
+        let state: State = load(&deps.storage, CONFIG_KEY).unwrap();
+        assert_eq!(state.factory.address, HumanAddr("newfactory".to_string()));
+        assert_eq!(state.factory.code_hash, "newhash".to_string());
+        assert_eq!(state.index, 7);
+        assert_eq!(state.callback_key, "newkey".to_string());
+
+        // old factory can no longer act on the auction
+        let handle_msg = HandleMsg::ForceFinalize {};
+        let handle_result = handle(&mut deps, mock_env("factoryaddr", &[]), handle_msg);
+        let error = extract_error_msg(handle_result);
+        assert!(error.contains("Only the factory can force this auction to close"));
+
+        // try forcing a close before the grace period ends
+        let mut env = mock_env("newfactory", &[]);
+        env.block.time = 1000 + FORCE_FINALIZE_GRACE - 1;
+        let handle_msg = HandleMsg::ForceFinalize {};
+        let handle_result = handle(&mut deps, env, handle_msg);
+        let error = extract_error_msg(handle_result);
+        assert!(error.contains("The auction can not be forced to close until"));
+
+        // force the close after the grace period
+        let mut env = mock_env("newfactory", &[]);
+        env.block.time = 1000 + FORCE_FINALIZE_GRACE;
+        let handle_msg = HandleMsg::ForceFinalize {};
+        let handle_result = handle(&mut deps, env, handle_msg);
+        assert!(handle_result.is_ok());
+        let state: State = load(&deps.storage, CONFIG_KEY).unwrap();
+        assert!(state.is_completed);
+        assert_eq!(state.currently_consigned, 0);
+    }
+
+    #[test]
+    fn test_settlement_phase() {
+        let (init_result, mut deps) = init_helper();
+        assert!(
+            init_result.is_ok(),
+            "Init failed: {}",
+            init_result.err().unwrap()
         );
         let handle_msg = HandleMsg::Receive {
             sender: HumanAddr("blah".to_string()),
             from: HumanAddr("alice".to_string()),
-            amount: Uint128(1000),
+            amount: Uint128(10),
             msg: None,
         };
-        let _handle_result = handle(
-            &mut deps,
-            Env {
-                block: BlockInfo {
-                    height: 12_345,
-                    time: 5,
-                    chain_id: "cosmos-testnet-14002".to_string(),
-                },
-                message: MessageInfo {
-                    sender: HumanAddr("bidaddr".to_string()),
-                    sent_funds: vec![],
-                },
-                contract: cosmwasm_std::ContractInfo {
-                    address: HumanAddr::from(MOCK_CONTRACT_ADDR),
-                },
-                contract_key: Some("".to_string()),
-                contract_code_hash: "".to_string(),
-            },
-            handle_msg,
-        );
-        let state: State = load(&deps.storage, CONFIG_KEY).unwrap();
-        assert_eq!(state.bidders.len(), 3);
-
+        let _handle_result = handle(&mut deps, before_close_env("selladdr"), handle_msg);
         let handle_msg = HandleMsg::Receive {
             sender: HumanAddr("blah".to_string()),
-            from: HumanAddr("alice".to_string()),
-            amount: Uint128(20),
+            from: HumanAddr("bob".to_string()),
+            amount: Uint128(100),
             msg: None,
         };
-        let _handle_result = handle(&mut deps, mock_env("selladdr", &[]), handle_msg);
+        let _handle_result = handle(&mut deps, before_close_env("bidaddr"), handle_msg);
+        let mut stale: State = load(&deps.storage, CONFIG_KEY).unwrap();
+        assert_eq!(stale.phase, SettlementPhase::Open);
+
         let handle_msg = HandleMsg::Finalize {
-            new_ends_at: Some(2000),
-            new_minimum_bid: Some(Uint128(1000)),
+            new_ends_at: None,
+            new_minimum_bid: None,
         };
-        let handle_result = handle(
-            &mut deps,
-            Env {
-                block: BlockInfo {
-                    height: 12_345,
-                    time: 1,
-                    chain_id: "cosmos-testnet-14002".to_string(),
-                },
-                message: MessageInfo {
-                    sender: HumanAddr("alice".to_string()),
-                    sent_funds: vec![],
-                },
-                contract: cosmwasm_std::ContractInfo {
-                    address: HumanAddr::from(MOCK_CONTRACT_ADDR),
-                },
-                contract_key: Some("".to_string()),
-                contract_code_hash: "".to_string(),
-            },
-            handle_msg,
-        );
-        let (
-            message,
-            winning_bid,
-            bid_decimals,
-            sell_tokens_received,
-            sell_decimals,
-            bid_tokens_received,
-        ) = extract_finalize_fields(&handle_result);
-        assert_eq!(winning_bid, Some(Uint128(1000)));
-        assert_eq!(bid_decimals, Some(8));
-        assert_eq!(sell_tokens_received, Some(Uint128(10)));
-        assert_eq!(sell_decimals, Some(4));
-        assert_eq!(bid_tokens_received, Some(Uint128(1000)));
-        assert!(message.contains("Sale has been finalized.  You have been sent the winning bid.  Your bid won! You have been sent the sale token(s)"));
+        let handle_result = handle(&mut deps, mock_env("alice", &[]), handle_msg);
+        assert!(handle_result.is_ok());
+        let query_result = query(&deps, QueryMsg::Status {});
+        match from_binary(&query_result.unwrap()).unwrap() {
+            QueryAnswer::Status {
+                settlement_phase, ..
+            } => assert_eq!(settlement_phase, SettlementPhase::Completed),
+            _ => panic!("Unexpected"),
+        }
 
-        // test 3 bidders, seller loses, seller closes
+        // a copy of the state loaded before the auction closed can not settle it again
+        let result = close_auction(&mut deps, mock_env("alice", &[]), &mut stale);
+        assert!(extract_error_msg(result).contains("already been settled"));
+
+        // finalizing the closed auction again does not pay anything out
+        let handle_msg = HandleMsg::Finalize {
+            new_ends_at: None,
+            new_minimum_bid: None,
+        };
+        let handle_result = handle(&mut deps, mock_env("alice", &[]), handle_msg);
+        assert!(handle_result.unwrap().messages.is_empty());
+    }
+
+    #[test]
+    fn test_bid_records() {
         let (init_result, mut deps) = init_helper();
         assert!(
             init_result.is_ok(),
             "Init failed: {}",
             init_result.err().unwrap()
         );
-
-        let handle_msg = HandleMsg::Receive {
-            sender: HumanAddr("blah".to_string()),
-            from: HumanAddr("bob".to_string()),
-            amount: Uint128(1000),
-            msg: None,
+        let mut state: State = load(&deps.storage, CONFIG_KEY).unwrap();
+        let bidders: Vec<Vec<u8>> = (1u8..=4).map(|i| vec![i; 20]).collect();
+        let bid = |amount: u128, timestamp: u64| Bid {
+            amount,
+            timestamp,
+            expiration: None,
+            memo: None,
+            referrer: None,
         };
-        let _handle_result = handle(
-            &mut deps,
-            Env {
-                block: BlockInfo {
-                    height: 12_345,
-                    time: 10,
-                    chain_id: "cosmos-testnet-14002".to_string(),
-                },
-                message: MessageInfo {
-                    sender: HumanAddr("bidaddr".to_string()),
-                    sent_funds: vec![],
-                },
-                contract: cosmwasm_std::ContractInfo {
-                    address: HumanAddr::from(MOCK_CONTRACT_ADDR),
-                },
-                contract_key: Some("".to_string()),
-                contract_code_hash: "".to_string(),
-            },
-            handle_msg,
+        for (i, bidder) in bidders.iter().enumerate() {
+            record_bid(&mut deps.storage, &mut state, bidder, &bid(10, i as u64)).unwrap();
+        }
+        // replacing a bid retires its old record and appends a new one
+        record_bid(&mut deps.storage, &mut state, &bidders[0], &bid(20, 9)).unwrap();
+        assert_eq!(state.num_bidders, 4);
+        assert!(remove_bidder(&mut deps.storage, &mut state, &bidders[1]).unwrap());
+        assert!(!remove_bidder(&mut deps.storage, &mut state, &bidders[1]).unwrap());
+        assert_eq!(state.num_bidders, 3);
+        assert!(!is_bidder(&deps.storage, &bidders[1]).unwrap());
+
+        // active bids are listed in the order they were placed
+        assert_eq!(
+            list_bidders(&deps.storage).unwrap(),
+            vec![bidders[2].clone(), bidders[3].clone(), bidders[0].clone()]
         );
-        let handle_msg = HandleMsg::Receive {
-            sender: HumanAddr("blah".to_string()),
-            from: HumanAddr("charlie".to_string()),
-            amount: Uint128(200),
-            msg: None,
-        };
-        let _handle_result = handle(
-            &mut deps,
-            Env {
-                block: BlockInfo {
-                    height: 12_345,
-                    time: 12,
-                    chain_id: "cosmos-testnet-14002".to_string(),
-                },
-                message: MessageInfo {
-                    sender: HumanAddr("bidaddr".to_string()),
-                    sent_funds: vec![],
-                },
-                contract: cosmwasm_std::ContractInfo {
-                    address: HumanAddr::from(MOCK_CONTRACT_ADDR),
-                },
-                contract_key: Some("".to_string()),
-                contract_code_hash: "".to_string(),
-            },
-            handle_msg,
+        let records = active_records(&deps.storage).unwrap();
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[2].amount, 20);
+        assert_eq!(records[2].timestamp, 9);
+        assert!(records.iter().all(|r| r.active));
+    }
+
+    #[test]
+    fn test_bid_record_pages() {
+        let (init_result, mut deps) = init_helper();
+        assert!(
+            init_result.is_ok(),
+            "Init failed: {}",
+            init_result.err().unwrap()
         );
-        let handle_msg = HandleMsg::Receive {
-            sender: HumanAddr("blah".to_string()),
-            from: HumanAddr("alice".to_string()),
-            amount: Uint128(25),
-            msg: None,
+        let mut state: State = load(&deps.storage, CONFIG_KEY).unwrap();
+        let bidders: Vec<Vec<u8>> = (1u8..=5).map(|i| vec![i; 20]).collect();
+        let bid = |amount: u128, timestamp: u64| Bid {
+            amount,
+            timestamp,
+            expiration: None,
+            memo: None,
+            referrer: None,
         };
-        let _handle_result = handle(
-            &mut deps,
-            Env {
-                block: BlockInfo {
-                    height: 12_345,
-                    time: 5,
-                    chain_id: "cosmos-testnet-14002".to_string(),
-                },
-                message: MessageInfo {
-                    sender: HumanAddr("bidaddr".to_string()),
-                    sent_funds: vec![],
-                },
-                contract: cosmwasm_std::ContractInfo {
-                    address: HumanAddr::from(MOCK_CONTRACT_ADDR),
-                },
-                contract_key: Some("".to_string()),
-                contract_code_hash: "".to_string(),
-            },
-            handle_msg,
+
+        // nothing to read before any bid is placed
+        assert_eq!(
+            bid_records_page(&deps.storage, None, 2).unwrap(),
+            (Vec::new(), None)
+        );
+        for (i, bidder) in bidders.iter().enumerate() {
+            record_bid(&mut deps.storage, &mut state, bidder, &bid(10, i as u64)).unwrap();
+        }
+        // positions 0 and 2 are retired
+        record_bid(&mut deps.storage, &mut state, &bidders[0], &bid(20, 9)).unwrap();
+        assert!(remove_bidder(&mut deps.storage, &mut state, &bidders[2]).unwrap());
+        let record_store = ReadonlyPrefixedStorage::new(PREFIX_BID_RECORDS, &deps.storage);
+        let records = AppendStore::<BidRecord, _>::attach(&record_store)
+            .unwrap()
+            .unwrap();
+        assert_eq!(records.len(), 6);
+        assert!(!records.get_at(0).unwrap().active);
+        assert!(!records.get_at(2).unwrap().active);
+
+        // pages only list active records, and resume after the last record read
+        let mut listed = Vec::new();
+        let mut cursor = None;
+        let mut pages = 0;
+        loop {
+            let (page, resume) = bid_records_page(&deps.storage, cursor, 2).unwrap();
+            pages += 1;
+            listed.extend(page.into_iter().map(|r| r.bidder));
+            match resume {
+                Some(position) => cursor = Some(position),
+                None => break,
+            }
+        }
+        assert_eq!(pages, 3);
+        assert_eq!(listed, list_bidders(&deps.storage).unwrap());
+        assert_eq!(
+            listed,
+            vec![
+                bidders[1].clone(),
+                bidders[3].clone(),
+                bidders[4].clone(),
+                bidders[0].clone()
+            ]
+        );
+        // a page always reads at least one record
+        let (page, resume) = bid_records_page(&deps.storage, None, 0).unwrap();
+        assert!(page.is_empty());
+        assert_eq!(resume, Some(0));
+        // starting after the last record reads nothing
+        assert_eq!(
+            bid_records_page(&deps.storage, Some(5), 2).unwrap(),
+            (Vec::new(), None)
+        );
+    }
+
+    #[test]
+    fn test_committer_storage() {
+        let (init_result, mut deps) = init_helper();
+        assert!(
+            init_result.is_ok(),
+            "Init failed: {}",
+            init_result.err().unwrap()
         );
-        let state: State = load(&deps.storage, CONFIG_KEY).unwrap();
-        assert_eq!(state.bidders.len(), 3);
+        let mut state: State = load(&deps.storage, CONFIG_KEY).unwrap();
+        let committers: Vec<Vec<u8>> = (1u8..=3).map(|i| vec![i; 20]).collect();
+        for committer in committers.iter() {
+            add_committer(&mut deps.storage, &mut state, committer).unwrap();
+        }
+        // committing again is not counted twice
+        add_committer(&mut deps.storage, &mut state, &committers[0]).unwrap();
+        assert_eq!(state.num_committers, 3);
+        assert_eq!(state.bidder_count(), 3);
+        assert!(state.has_bids());
+
+        // an address that retracts and commits again is only listed once, at its new position
+        assert!(remove_committer(&mut deps.storage, &mut state, &committers[0]).unwrap());
+        assert!(!remove_committer(&mut deps.storage, &mut state, &committers[0]).unwrap());
+        assert!(!is_committer(&deps.storage, &committers[0]).unwrap());
+        add_committer(&mut deps.storage, &mut state, &committers[0]).unwrap();
+        assert_eq!(
+            list_committers(&deps.storage).unwrap(),
+            vec![
+                committers[1].clone(),
+                committers[2].clone(),
+                committers[0].clone()
+            ]
+        );
+        for committer in committers.iter() {
+            assert!(remove_committer(&mut deps.storage, &mut state, committer).unwrap());
+        }
+        assert_eq!(state.num_committers, 0);
+        assert!(!state.has_bids());
+        assert!(list_committers(&deps.storage).unwrap().is_empty());
+    }
 
-        let handle_msg = HandleMsg::Receive {
-            sender: HumanAddr("blah".to_string()),
-            from: HumanAddr("alice".to_string()),
-            amount: Uint128(20),
-            msg: None,
-        };
-        let _handle_result = handle(&mut deps, mock_env("selladdr", &[]), handle_msg);
-        let handle_msg = HandleMsg::Finalize {
-            new_ends_at: Some(2000),
-            new_minimum_bid: None,
-        };
-        let handle_result = handle(
-            &mut deps,
-            Env {
-                block: BlockInfo {
-                    height: 12_345,
-                    time: 1,
-                    chain_id: "cosmos-testnet-14002".to_string(),
-                },
-                message: MessageInfo {
-                    sender: HumanAddr("alice".to_string()),
-                    sent_funds: vec![],
-                },
-                contract: cosmwasm_std::ContractInfo {
-                    address: HumanAddr::from(MOCK_CONTRACT_ADDR),
-                },
-                contract_key: Some("".to_string()),
-                contract_code_hash: "".to_string(),
-            },
-            handle_msg,
+    #[test]
+    fn test_return_all_pages() {
+        let (init_result, mut deps) = init_helper();
+        assert!(
+            init_result.is_ok(),
+            "Init failed: {}",
+            init_result.err().unwrap()
         );
-        let (
-            message,
-            winning_bid,
-            bid_decimals,
-            sell_tokens_received,
-            sell_decimals,
-            bid_tokens_received,
-        ) = extract_finalize_fields(&handle_result);
-        assert_eq!(winning_bid, Some(Uint128(1000)));
-        assert_eq!(bid_decimals, Some(8));
-        assert_eq!(sell_tokens_received, None);
-        assert_eq!(sell_decimals, None);
-        assert_eq!(bid_tokens_received, Some(Uint128(1025)));
-        assert!(message.contains("Sale has been finalized.  You have been sent the winning bid.  Your bid did not win and has been returned"));
+        let mut state: State = load(&deps.storage, CONFIG_KEY).unwrap();
+        for (i, name) in ["bob", "charlie", "dave"].iter().enumerate() {
+            let bidder = deps
+                .api
+                .canonical_address(&HumanAddr(name.to_string()))
+                .unwrap();
+            let stranded_bid = Bid {
+                amount: 100,
+                timestamp: i as u64,
+                expiration: None,
+                memo: None,
+                referrer: None,
+            };
+            save(&mut deps.storage, bidder.as_slice(), &stranded_bid).unwrap();
+            record_bid(
+                &mut deps.storage,
+                &mut state,
+                bidder.as_slice(),
+                &stranded_bid,
+            )
+            .unwrap();
+        }
+        state.is_completed = true;
+        save(&mut deps.storage, CONFIG_KEY, &state).unwrap();
+        let return_all = |page_size: Option<u32>| HandleMsg::ReturnAll { page_size };
+        let returned = |handle_result: &HandleResult| match from_binary(
+            &handle_result.as_ref().unwrap().data.as_ref().unwrap(),
+        )
+        .unwrap()
+        {
+            HandleAnswer::ReturnAll {
+                bids_returned,
+                bids_remaining,
+                ..
+            } => (bids_returned, bids_remaining),
+            _ => panic!("Unexpected HandleAnswer"),
+        };
 
-        // test 3 bidders, loser closes
+        // each page resumes where the last one stopped
+        let handle_result = handle(&mut deps, mock_env("bob", &[]), return_all(Some(2)));
+        assert_eq!(handle_result.as_ref().unwrap().messages.len(), 2);
+        assert_eq!(returned(&handle_result), (2, 1));
+        assert!(extract_msg(&handle_result).contains("1 bid(s) remain"));
+        let handle_result = handle(&mut deps, mock_env("bob", &[]), return_all(Some(2)));
+        assert_eq!(handle_result.as_ref().unwrap().messages.len(), 1);
+        assert_eq!(returned(&handle_result), (1, 0));
+        let state: State = load(&deps.storage, CONFIG_KEY).unwrap();
+        assert_eq!(state.num_bidders, 0);
+        let cursor: Option<u32> = may_load(&deps.storage, REFUND_CURSOR_KEY).unwrap();
+        assert!(cursor.is_none());
+        let handle_result = handle(&mut deps, mock_env("bob", &[]), return_all(None));
+        assert!(extract_msg(&handle_result).contains("There are no outstanding funds to return"));
+    }
+
+    #[test]
+    fn test_query_status() {
         let (init_result, mut deps) = init_helper();
         assert!(
             init_result.is_ok(),
             "Init failed: {}",
             init_result.err().unwrap()
         );
+        let query_result = query(&deps, QueryMsg::Status {});
+        let (is_completed, tokens_consigned, bidders, outstanding_balances, factory_index) =
+            match from_binary(&query_result.unwrap()).unwrap() {
+                QueryAnswer::Status {
+                    is_completed,
+                    tokens_consigned,
+                    bidders,
+                    outstanding_balances,
+                    factory_index,
+                    ..
+                } => (
+                    is_completed,
+                    tokens_consigned,
+                    bidders,
+                    outstanding_balances,
+                    factory_index,
+                ),
+                _ => panic!("Unexpected"),
+            };
+        assert!(!is_completed);
+        assert!(!tokens_consigned);
+        assert_eq!(bidders, 0);
+        assert!(!outstanding_balances);
+        assert_eq!(factory_index, 0);
 
+        // place a bid and consign
         let handle_msg = HandleMsg::Receive {
             sender: HumanAddr("blah".to_string()),
             from: HumanAddr("bob".to_string()),
-            amount: Uint128(500),
+            amount: Uint128(100),
             msg: None,
         };
-        let _handle_result = handle(
-            &mut deps,
-            Env {
-                block: BlockInfo {
-                    height: 12_345,
-                    time: 10,
-                    chain_id: "cosmos-testnet-14002".to_string(),
-                },
-                message: MessageInfo {
-                    sender: HumanAddr("bidaddr".to_string()),
-                    sent_funds: vec![],
-                },
-                contract: cosmwasm_std::ContractInfo {
-                    address: HumanAddr::from(MOCK_CONTRACT_ADDR),
-                },
-                contract_key: Some("".to_string()),
-                contract_code_hash: "".to_string(),
-            },
-            handle_msg,
-        );
+        let _handle_result = handle(&mut deps, before_close_env("bidaddr"), handle_msg);
         let handle_msg = HandleMsg::Receive {
             sender: HumanAddr("blah".to_string()),
-            from: HumanAddr("charlie".to_string()),
-            amount: Uint128(500),
+            from: HumanAddr("alice".to_string()),
+            amount: Uint128(10),
             msg: None,
         };
-        let _handle_result = handle(
-            &mut deps,
-            Env {
-                block: BlockInfo {
-                    height: 12_345,
-                    time: 12,
-                    chain_id: "cosmos-testnet-14002".to_string(),
-                },
-                message: MessageInfo {
-                    sender: HumanAddr("bidaddr".to_string()),
-                    sent_funds: vec![],
-                },
-                contract: cosmwasm_std::ContractInfo {
-                    address: HumanAddr::from(MOCK_CONTRACT_ADDR),
-                },
-                contract_key: Some("".to_string()),
-                contract_code_hash: "".to_string(),
-            },
-            handle_msg,
+        let _handle_result = handle(&mut deps, mock_env("selladdr", &[]), handle_msg);
+        let query_result = query(&deps, QueryMsg::Status {});
+        let (is_completed, tokens_consigned, bidders, outstanding_balances, factory_info) =
+            match from_binary(&query_result.unwrap()).unwrap() {
+                QueryAnswer::Status {
+                    is_completed,
+                    tokens_consigned,
+                    bidders,
+                    outstanding_balances,
+                    factory_info,
+                    ..
+                } => (
+                    is_completed,
+                    tokens_consigned,
+                    bidders,
+                    outstanding_balances,
+                    factory_info,
+                ),
+                _ => panic!("Unexpected"),
+            };
+        assert!(!is_completed);
+        assert!(tokens_consigned);
+        assert_eq!(bidders, 1);
+        // balances are only outstanding once the auction is closed
+        assert!(!outstanding_balances);
+        assert_eq!(factory_info.address, HumanAddr("factoryaddr".to_string()));
+        assert_eq!(factory_info.code_hash, "factoryhash".to_string());
+    }
+
+    #[test]
+    fn test_query_bid_stats() {
+        let (init_result, mut deps) = init_helper();
+        assert!(
+            init_result.is_ok(),
+            "Init failed: {}",
+            init_result.err().unwrap()
         );
-        let handle_msg = HandleMsg::Receive {
-            sender: HumanAddr("blah".to_string()),
-            from: HumanAddr("david".to_string()),
-            amount: Uint128(25),
-            msg: None,
+        for (bidder, amount) in &[("bob", 1234), ("charlie", 56789), ("dave", 300)] {
+            let handle_msg = HandleMsg::Receive {
+                sender: HumanAddr("blah".to_string()),
+                from: HumanAddr(bidder.to_string()),
+                amount: Uint128(*amount),
+                msg: None,
+            };
+            let _handle_result = handle(&mut deps, before_close_env("bidaddr"), handle_msg);
+        }
+
+        #[derive(Debug)]
+        struct MyMockQuerier {
+            pub is_valid: bool,
+        }
+        impl Querier for MyMockQuerier {
+            fn raw_query(&self, _request: &[u8]) -> QuerierResult {
+                Ok(to_binary(&IsKeyValidWrapper {
+                    is_key_valid: IsKeyValid {
+                        is_valid: self.is_valid,
+                    },
+                }))
+            }
+        }
+        let deps = deps.change_querier(|_| MyMockQuerier { is_valid: true });
+        let stats_query = |address: &str| QueryMsg::BidStats {
+            address: HumanAddr(address.to_string()),
+            viewing_key: "key".to_string(),
         };
-        let _handle_result = handle(
-            &mut deps,
-            Env {
-                block: BlockInfo {
-                    height: 12_345,
-                    time: 5,
-                    chain_id: "cosmos-testnet-14002".to_string(),
-                },
-                message: MessageInfo {
-                    sender: HumanAddr("bidaddr".to_string()),
-                    sent_funds: vec![],
-                },
-                contract: cosmwasm_std::ContractInfo {
-                    address: HumanAddr::from(MOCK_CONTRACT_ADDR),
-                },
-                contract_key: Some("".to_string()),
-                contract_code_hash: "".to_string(),
-            },
-            handle_msg,
+        // try someone other than the seller
+        let error = extract_error_msg(query(&deps, stats_query("bob")));
+        assert!(error.contains("does not match the auction seller or its operators"));
+
+        // sanity check
+        match from_binary(&query(&deps, stats_query("alice")).unwrap()).unwrap() {
+            QueryAnswer::BidStats {
+                bid_count,
+                min_bid,
+                median_bid,
+                max_bid,
+                last_bid_at,
+                ..
+            } => {
+                assert_eq!(bid_count, 3);
+                assert_eq!(min_bid, Some(Uint128(300)));
+                assert_eq!(median_bid, Some(Uint128(1200)));
+                assert_eq!(max_bid, Some(Uint128(56000)));
+                assert_eq!(last_bid_at, Some(500));
+            }
+            _ => panic!("Unexpected QueryAnswer"),
+        }
+    }
+
+    #[test]
+    fn test_export_auction() {
+        let (init_result, mut deps) = init_helper();
+        assert!(
+            init_result.is_ok(),
+            "Init failed: {}",
+            init_result.err().unwrap()
         );
-        let state: State = load(&deps.storage, CONFIG_KEY).unwrap();
-        assert_eq!(state.bidders.len(), 3);
+        for (bidder, amount) in &[("bob", 100), ("charlie", 200)] {
+            let handle_msg = HandleMsg::Receive {
+                sender: HumanAddr("blah".to_string()),
+                from: HumanAddr(bidder.to_string()),
+                amount: Uint128(*amount),
+                msg: None,
+            };
+            let _handle_result = handle(&mut deps, before_close_env("bidaddr"), handle_msg);
+        }
 
-        let handle_msg = HandleMsg::Receive {
-            sender: HumanAddr("blah".to_string()),
-            from: HumanAddr("alice".to_string()),
-            amount: Uint128(20),
-            msg: None,
-        };
-        let _handle_result = handle(&mut deps, mock_env("selladdr", &[]), handle_msg);
-        let handle_msg = HandleMsg::Finalize {
-            new_ends_at: None,
-            new_minimum_bid: None,
+        #[derive(Debug)]
+        struct MyMockQuerier {
+            pub is_valid: bool,
+        }
+        impl Querier for MyMockQuerier {
+            fn raw_query(&self, _request: &[u8]) -> QuerierResult {
+                Ok(to_binary(&IsKeyValidWrapper {
+                    is_key_valid: IsKeyValid {
+                        is_valid: self.is_valid,
+                    },
+                }))
+            }
+        }
+        let deps = deps.change_querier(|_| MyMockQuerier { is_valid: true });
+        let export_query = |address: &str| QueryMsg::ExportAuction {
+            address: HumanAddr(address.to_string()),
+            viewing_key: "key".to_string(),
         };
-        let handle_result = handle(
-            &mut deps,
-            Env {
-                block: BlockInfo {
-                    height: 12_345,
-                    time: 1000,
-                    chain_id: "cosmos-testnet-14002".to_string(),
-                },
-                message: MessageInfo {
-                    sender: HumanAddr("charlie".to_string()),
-                    sent_funds: vec![],
-                },
-                contract: cosmwasm_std::ContractInfo {
-                    address: HumanAddr::from(MOCK_CONTRACT_ADDR),
-                },
-                contract_key: Some("".to_string()),
-                contract_code_hash: "".to_string(),
-            },
-            handle_msg,
+        // try someone other than the seller
+        let error = extract_error_msg(query(&deps, export_query("bob")));
+        assert!(error
+            .contains("Address and/or viewing key does not match auction creator's information"));
+
+        // sanity check
+        match from_binary(&query(&deps, export_query("alice")).unwrap()).unwrap() {
+            QueryAnswer::ExportAuction {
+                seller,
+                sell_amount,
+                currently_consigned,
+                tokens_consigned,
+                minimum_bid,
+                bidder_count,
+                last_bid_at,
+                winning_bid,
+                is_completed,
+                allowance_bids,
+                factory_info,
+                ..
+            } => {
+                assert_eq!(seller, HumanAddr("alice".to_string()));
+                assert_eq!(sell_amount, Uint128(10));
+                assert_eq!(currently_consigned, Uint128(0));
+                assert!(!tokens_consigned);
+                assert_eq!(minimum_bid, Uint128(10));
+                assert_eq!(bidder_count, 2);
+                assert_eq!(last_bid_at, Some(500));
+                // the winning bid is not revealed while the auction is open
+                assert_eq!(winning_bid, None);
+                assert!(!is_completed);
+                assert!(!allowance_bids);
+                assert_eq!(factory_info.address, HumanAddr("factoryaddr".to_string()));
+            }
+            _ => panic!("Unexpected QueryAnswer"),
+        }
+    }
+
+    #[test]
+    fn test_query_has_bids() {
+        let (init_result, deps) = init_helper();
+        assert!(
+            init_result.is_ok(),
+            "Init failed: {}",
+            init_result.err().unwrap()
+        );
+
+        #[derive(Debug)]
+        struct MyMockQuerier {
+            pub is_valid: bool,
+        }
+        impl Querier for MyMockQuerier {
+            fn raw_query(&self, _request: &[u8]) -> QuerierResult {
+                Ok(to_binary(&IsKeyValidWrapper {
+                    is_key_valid: IsKeyValid {
+                        is_valid: self.is_valid,
+                    },
+                }))
+            }
+        }
+        let invalid_deps = deps.change_querier(|_| MyMockQuerier { is_valid: false });
+        // try wrong key
+        let query_msg = QueryMsg::HasBids {
+            address: HumanAddr("alice".to_string()),
+            viewing_key: "wrong_key".to_string(),
+        };
+        let query_result = query(&invalid_deps, query_msg);
+        let error = extract_error_msg(query_result);
+        assert!(error
+            .contains("Address and/or viewing key does not match auction creator's information"));
+
+        let (init_result, deps) = init_helper();
+        assert!(
+            init_result.is_ok(),
+            "Init failed: {}",
+            init_result.err().unwrap()
         );
-        let (
-            message,
-            winning_bid,
-            bid_decimals,
-            sell_tokens_received,
-            sell_decimals,
-            bid_tokens_received,
-        ) = extract_finalize_fields(&handle_result);
-        assert_eq!(winning_bid, Some(Uint128(500)));
-        assert_eq!(bid_decimals, Some(8));
-        assert_eq!(sell_tokens_received, None);
-        assert_eq!(sell_decimals, None);
-        assert_eq!(bid_tokens_received, Some(Uint128(500)));
-        assert!(message
-            .contains("Sale has been finalized.  Your bid did not win and has been returned"));
+        let valid_deps = deps.change_querier(|_| MyMockQuerier { is_valid: true });
+        // try not seller
+        let query_msg = QueryMsg::HasBids {
+            address: HumanAddr("bob".to_string()),
+            viewing_key: "key".to_string(),
+        };
+        let query_result = query(&valid_deps, query_msg);
+        let error = extract_error_msg(query_result);
+        assert!(error
+            .contains("Address and/or viewing key does not match auction creator's information"));
 
-        // test 3 bidders, seller closes
-        let (init_result, mut deps) = init_helper();
+        // sanity check, no bids
+        let (init_result, deps) = init_helper();
         assert!(
             init_result.is_ok(),
             "Init failed: {}",
             init_result.err().unwrap()
         );
+        let mut valid_deps = deps.change_querier(|_| MyMockQuerier { is_valid: true });
+        let query_msg = QueryMsg::HasBids {
+            address: HumanAddr("alice".to_string()),
+            viewing_key: "key".to_string(),
+        };
+        let query_result = query(&valid_deps, query_msg);
+        let has_bids = match from_binary(&query_result.unwrap()).unwrap() {
+            QueryAnswer::HasBids { has_bids, .. } => has_bids,
+            _ => panic!("Unexpected"),
+        };
+        assert!(!has_bids);
 
+        // sanity check, with bids
         let handle_msg = HandleMsg::Receive {
             sender: HumanAddr("blah".to_string()),
             from: HumanAddr("bob".to_string()),
-            amount: Uint128(1000),
+            amount: Uint128(100),
             msg: None,
         };
-        let _handle_result = handle(
-            &mut deps,
-            Env {
-                block: BlockInfo {
-                    height: 12_345,
-                    time: 10,
-                    chain_id: "cosmos-testnet-14002".to_string(),
-                },
-                message: MessageInfo {
-                    sender: HumanAddr("bidaddr".to_string()),
-                    sent_funds: vec![],
-                },
-                contract: cosmwasm_std::ContractInfo {
-                    address: HumanAddr::from(MOCK_CONTRACT_ADDR),
-                },
-                contract_key: Some("".to_string()),
-                contract_code_hash: "".to_string(),
-            },
-            handle_msg,
-        );
+        let _handle_result = handle(&mut valid_deps, before_close_env("bidaddr"), handle_msg);
+        let query_msg = QueryMsg::HasBids {
+            address: HumanAddr("alice".to_string()),
+            viewing_key: "key".to_string(),
+        };
+        let query_result = query(&valid_deps, query_msg);
+        let has_bids = match from_binary(&query_result.unwrap()).unwrap() {
+            QueryAnswer::HasBids { has_bids, .. } => has_bids,
+            _ => panic!("Unexpected"),
+        };
+        assert!(has_bids);
+
         let handle_msg = HandleMsg::Receive {
             sender: HumanAddr("blah".to_string()),
             from: HumanAddr("charlie".to_string()),
-            amount: Uint128(200),
+            amount: Uint128(100),
             msg: None,
         };
-        let _handle_result = handle(
-            &mut deps,
-            Env {
-                block: BlockInfo {
-                    height: 12_345,
-                    time: 12,
-                    chain_id: "cosmos-testnet-14002".to_string(),
-                },
-                message: MessageInfo {
-                    sender: HumanAddr("bidaddr".to_string()),
-                    sent_funds: vec![],
-                },
-                contract: cosmwasm_std::ContractInfo {
-                    address: HumanAddr::from(MOCK_CONTRACT_ADDR),
-                },
-                contract_key: Some("".to_string()),
-                contract_code_hash: "".to_string(),
-            },
-            handle_msg,
-        );
-        let handle_msg = HandleMsg::Receive {
-            sender: HumanAddr("blah".to_string()),
-            from: HumanAddr("david".to_string()),
-            amount: Uint128(25),
-            msg: None,
+        let _handle_result = handle(&mut valid_deps, before_close_env("bidaddr"), handle_msg);
+        let query_msg = QueryMsg::HasBids {
+            address: HumanAddr("alice".to_string()),
+            viewing_key: "key".to_string(),
         };
-        let _handle_result = handle(
-            &mut deps,
-            Env {
-                block: BlockInfo {
-                    height: 12_345,
-                    time: 5,
-                    chain_id: "cosmos-testnet-14002".to_string(),
-                },
-                message: MessageInfo {
-                    sender: HumanAddr("bidaddr".to_string()),
-                    sent_funds: vec![],
-                },
-                contract: cosmwasm_std::ContractInfo {
-                    address: HumanAddr::from(MOCK_CONTRACT_ADDR),
-                },
-                contract_key: Some("".to_string()),
-                contract_code_hash: "".to_string(),
-            },
-            handle_msg,
+        let query_result = query(&valid_deps, query_msg);
+        let has_bids = match from_binary(&query_result.unwrap()).unwrap() {
+            QueryAnswer::HasBids { has_bids, .. } => has_bids,
+            _ => panic!("Unexpected"),
+        };
+        assert!(has_bids);
+    }
+
+    #[test]
+    fn test_query_has_bids_count_and_time() {
+        let (init_result, deps) = init_helper();
+        assert!(
+            init_result.is_ok(),
+            "Init failed: {}",
+            init_result.err().unwrap()
         );
-        let state: State = load(&deps.storage, CONFIG_KEY).unwrap();
-        assert_eq!(state.bidders.len(), 3);
 
+        #[derive(Debug)]
+        struct MyMockQuerier {}
+        impl Querier for MyMockQuerier {
+            fn raw_query(&self, _request: &[u8]) -> QuerierResult {
+                Ok(to_binary(&IsKeyValidWrapper {
+                    is_key_valid: IsKeyValid { is_valid: true },
+                }))
+            }
+        }
+        let mut deps = deps.change_querier(|_| MyMockQuerier {});
+        let has_bids = |deps: &Extern<MockStorage, MockApi, MyMockQuerier>| {
+            let query_msg = QueryMsg::HasBids {
+                address: HumanAddr("alice".to_string()),
+                viewing_key: "key".to_string(),
+            };
+            match from_binary(&query(deps, query_msg).unwrap()).unwrap() {
+                QueryAnswer::HasBids {
+                    has_bids,
+                    bid_count,
+                    last_bid_time,
+                } => (has_bids, bid_count, last_bid_time),
+                _ => panic!("Unexpected"),
+            }
+        };
+        assert_eq!(has_bids(&deps), (false, 0, None));
+
+        // the count and time of the latest bid follow each new bid
+        for (bidder, time) in [("bob", 500), ("charlie", 600)].iter() {
+            let mut env = before_close_env("bidaddr");
+            env.block.time = *time;
+            let handle_msg = HandleMsg::Receive {
+                sender: HumanAddr("blah".to_string()),
+                from: HumanAddr(bidder.to_string()),
+                amount: Uint128(100),
+                msg: None,
+            };
+            let _handle_result = handle(&mut deps, env, handle_msg);
+        }
+        assert_eq!(has_bids(&deps), (true, 2, Some(600)));
+    }
+
+    #[test]
+    fn test_humanized_amounts() {
+        assert_eq!(
+            humanize(Some(Uint128(12345678)), 6),
+            Some("12.345678".to_string())
+        );
+        assert_eq!(humanize(Some(Uint128(1500)), 3), Some("1.5".to_string()));
+        assert_eq!(humanize(Some(Uint128(5)), 4), Some("0.0005".to_string()));
+        assert_eq!(humanize(Some(Uint128(2000)), 3), Some("2".to_string()));
+        assert_eq!(humanize(Some(Uint128(42)), 0), Some("42".to_string()));
+        assert_eq!(humanize(None, 6), None);
+
+        let (_init_result, mut deps) = init_helper();
+        // consign 10 sell tokens with 4 decimals
         let handle_msg = HandleMsg::Receive {
-            sender: HumanAddr("blah".to_string()),
+            sender: HumanAddr("alice".to_string()),
             from: HumanAddr("alice".to_string()),
-            amount: Uint128(20),
+            amount: Uint128(10),
             msg: None,
         };
-        let _handle_result = handle(&mut deps, mock_env("selladdr", &[]), handle_msg);
+        let handle_result = handle(&mut deps, before_close_env("selladdr"), handle_msg);
+        let log = extract_log(handle_result);
+        assert!(log.contains("\"amount_consigned_humanized\":\"0.001\""));
+        // bid 1.5 bid tokens with 8 decimals
+        let handle_msg = HandleMsg::Receive {
+            sender: HumanAddr("bob".to_string()),
+            from: HumanAddr("bob".to_string()),
+            amount: Uint128(150000000),
+            msg: None,
+        };
+        let handle_result = handle(&mut deps, before_close_env("bidaddr"), handle_msg);
+        let log = extract_log(handle_result);
+        assert!(log.contains("\"amount_bid_humanized\":\"1.5\""));
+        assert!(!log.contains("previous_bid_humanized"));
+
         let handle_msg = HandleMsg::Finalize {
             new_ends_at: None,
             new_minimum_bid: None,
         };
-        let handle_result = handle(
-            &mut deps,
-            Env {
-                block: BlockInfo {
-                    height: 12_345,
-                    time: 1000,
-                    chain_id: "cosmos-testnet-14002".to_string(),
-                },
-                message: MessageInfo {
-                    sender: HumanAddr("alice".to_string()),
-                    sent_funds: vec![],
-                },
-                contract: cosmwasm_std::ContractInfo {
-                    address: HumanAddr::from(MOCK_CONTRACT_ADDR),
-                },
-                contract_key: Some("".to_string()),
-                contract_code_hash: "".to_string(),
-            },
-            handle_msg,
-        );
-        let (
-            message,
-            winning_bid,
-            bid_decimals,
-            sell_tokens_received,
-            sell_decimals,
-            bid_tokens_received,
-        ) = extract_finalize_fields(&handle_result);
-        assert_eq!(winning_bid, Some(Uint128(1000)));
-        assert_eq!(bid_decimals, Some(8));
-        assert_eq!(sell_tokens_received, None);
-        assert_eq!(sell_decimals, None);
-        assert_eq!(bid_tokens_received, Some(Uint128(1000)));
-        assert!(message.contains("Sale has been finalized.  You have been sent the winning bid"));
-        assert!(!message.contains("Sale has been finalized.  You have been sent the winning bid."));
+        let handle_result = handle(&mut deps, mock_env("alice", &[]), handle_msg);
+        let handle_answer: HandleAnswer =
+            from_binary(&handle_result.unwrap().data.unwrap()).unwrap();
+        match handle_answer {
+            HandleAnswer::CloseAuction {
+                winning_bid_humanized,
+                bid_tokens_received_humanized,
+                ..
+            } => {
+                assert_eq!(winning_bid_humanized, Some("1.5".to_string()));
+                assert_eq!(bid_tokens_received_humanized, Some("1.5".to_string()));
+            }
+            _ => panic!("Unexpected HandleAnswer"),
+        }
+    }
 
-        // return all response
-        let handle_msg = HandleMsg::ReturnAll {};
-        let handle_result = handle(&mut deps, mock_env("bob", &[]), handle_msg);
-        let message = extract_msg(&handle_result);
-        assert!(message.contains("Outstanding funds have been returned"));
+    #[derive(Deserialize)]
+    #[serde(rename_all = "snake_case")]
+    enum Snip20Query {
+        Allowance { owner: HumanAddr },
+        Balance { address: HumanAddr, key: String },
+    }
+    #[derive(Serialize)]
+    struct AllowanceInfo {
+        spender: HumanAddr,
+        owner: HumanAddr,
+        allowance: Uint128,
+        expiration: Option<u64>,
+    }
+    #[derive(Serialize)]
+    struct AllowanceWrapper {
+        allowance: AllowanceInfo,
+    }
+    #[derive(Serialize)]
+    struct BalanceInfo {
+        amount: Uint128,
+    }
+    #[derive(Serialize)]
+    struct BalanceWrapper {
+        balance: BalanceInfo,
+    }
+    /// answers allowance queries from a list of (owner, allowance, expiration), and balance
+    /// queries from a list of (address, viewing key, balance)
+    struct AllowanceQuerier {
+        pub allowances: Vec<(&'static str, u128, Option<u64>)>,
+        pub balances: Vec<(&'static str, &'static str, u128)>,
+    }
+    impl Querier for AllowanceQuerier {
+        fn raw_query(&self, request: &[u8]) -> QuerierResult {
+            use cosmwasm_std::{from_slice, Empty, QueryRequest, WasmQuery};
+            let request: QueryRequest<Empty> = from_slice(request).unwrap();
+            let msg = match request {
+                QueryRequest::Wasm(WasmQuery::Smart { msg, .. }) => msg,
+                _ => panic!("Unexpected query"),
+            };
+            match from_binary(&msg).unwrap() {
+                Snip20Query::Allowance { owner } => {
+                    let (amount, expiration) = self
+                        .allowances
+                        .iter()
+                        .find(|a| a.0 == owner.as_str())
+                        .map_or((0, None), |a| (a.1, a.2));
+                    Ok(to_binary(&AllowanceWrapper {
+                        allowance: AllowanceInfo {
+                            spender: HumanAddr("cosmos2contract".to_string()),
+                            owner,
+                            allowance: Uint128(amount),
+                            expiration,
+                        },
+                    }))
+                }
+                Snip20Query::Balance { address, key } => {
+                    match self
+                        .balances
+                        .iter()
+                        .find(|b| b.0 == address.as_str() && b.1 == key)
+                    {
+                        Some(b) => Ok(to_binary(&BalanceWrapper {
+                            balance: BalanceInfo {
+                                amount: Uint128(b.2),
+                            },
+                        })),
+                        None => Ok(Err(StdError::generic_err("Wrong viewing key"))),
+                    }
+                }
+            }
+        }
+    }
 
-        // test 3 bidders, stranger closes
-        let (init_result, mut deps) = init_helper();
-        assert!(
-            init_result.is_ok(),
-            "Init failed: {}",
-            init_result.err().unwrap()
-        );
+    #[test]
+    fn test_allowance_bids() {
+        let contract = |code_hash: &str, address: &str, token_id: Option<&str>| ContractInfo {
+            code_hash: code_hash.to_string(),
+            address: HumanAddr(address.to_string()),
+            token_id: token_id.map(String::from),
+        };
+        let allowance_init = |bid_contract: ContractInfo, buy_now_price: Option<Uint128>| InitMsg {
+            bid_contract,
+            buy_now_price,
+            allowance_bids: true,
+            ..default_init_msg()
+        };
+        let mut env = mock_env("factory", &[]);
+        env.block.time = 0;
 
+        // allowances need a SNIP-20 bid token
+        let mut deps = mock_dependencies(20, &[]);
+        let init_msg = allowance_init(contract("bidhash", "bidaddr", Some("gold")), None);
+        let error = extract_error_msg(init(&mut deps, env.clone(), init_msg));
+        assert!(error.contains("bid token is a SNIP-20"));
+        // and can not buy now
+        let init_msg = allowance_init(contract("bidhash", "bidaddr", None), Some(Uint128(50)));
+        let error = extract_error_msg(init(&mut deps, env.clone(), init_msg));
+        assert!(error.contains("can not have a buy now price"));
+
+        // the auction sets a viewing key with the bid token to check allowances
+        let init_msg = allowance_init(contract("bidhash", "bidaddr", None), None);
+        let init_result = init(&mut deps, env, init_msg);
+        let sets_key = init_result.unwrap().messages.iter().any(|m| match m {
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr, msg, ..
+            }) => {
+                contract_addr.as_str() == "bidaddr"
+                    && String::from_utf8(msg.as_slice().to_vec())
+                        .unwrap()
+                        .contains("set_viewing_key")
+            }
+            _ => false,
+        });
+        assert!(sets_key);
+        let mut deps = deps.change_querier(|_| AllowanceQuerier {
+            allowances: vec![
+                ("bob", 100, None),
+                ("charlie", 200, Some(900)),
+                ("dave", 50, None),
+            ],
+            balances: vec![
+                ("bob", "key", 500),
+                ("charlie", "key", 500),
+                ("dave", "key", 500),
+            ],
+        });
         let handle_msg = HandleMsg::Receive {
             sender: HumanAddr("blah".to_string()),
-            from: HumanAddr("bob".to_string()),
-            amount: Uint128(1000),
+            from: HumanAddr("alice".to_string()),
+            amount: Uint128(10),
             msg: None,
         };
-        let _handle_result = handle(
-            &mut deps,
-            Env {
-                block: BlockInfo {
-                    height: 12_345,
-                    time: 10,
-                    chain_id: "cosmos-testnet-14002".to_string(),
-                },
-                message: MessageInfo {
-                    sender: HumanAddr("bidaddr".to_string()),
-                    sent_funds: vec![],
-                },
-                contract: cosmwasm_std::ContractInfo {
-                    address: HumanAddr::from(MOCK_CONTRACT_ADDR),
-                },
-                contract_key: Some("".to_string()),
-                contract_code_hash: "".to_string(),
-            },
-            handle_msg,
-        );
+        let handle_result = handle(&mut deps, before_close_env("selladdr"), handle_msg);
+        assert!(handle_result.is_ok());
+
+        // bid tokens sent to escrow are returned
         let handle_msg = HandleMsg::Receive {
             sender: HumanAddr("blah".to_string()),
-            from: HumanAddr("charlie".to_string()),
-            amount: Uint128(200),
+            from: HumanAddr("bob".to_string()),
+            amount: Uint128(100),
             msg: None,
         };
-        let _handle_result = handle(
-            &mut deps,
-            Env {
-                block: BlockInfo {
-                    height: 12_345,
-                    time: 12,
-                    chain_id: "cosmos-testnet-14002".to_string(),
-                },
-                message: MessageInfo {
-                    sender: HumanAddr("bidaddr".to_string()),
-                    sent_funds: vec![],
-                },
-                contract: cosmwasm_std::ContractInfo {
-                    address: HumanAddr::from(MOCK_CONTRACT_ADDR),
-                },
-                contract_key: Some("".to_string()),
-                contract_code_hash: "".to_string(),
-            },
-            handle_msg,
-        );
-        let handle_msg = HandleMsg::Receive {
-            sender: HumanAddr("blah".to_string()),
-            from: HumanAddr("david".to_string()),
-            amount: Uint128(25),
-            msg: None,
+        let handle_result = handle(&mut deps, before_close_env("bidaddr"), handle_msg);
+        let log = extract_log(handle_result);
+        assert!(log.contains("Use place_allowance_bid"));
+        assert!(log.contains("\"status\":\"failure\""));
+        assert!(log.contains("\"amount_returned\":\"100\""));
+
+        // the allowance must cover the bid
+        let handle_msg = HandleMsg::PlaceAllowanceBid {
+            amount: Uint128(150),
+            balance_key: "key".to_string(),
+            expiration: None,
+            memo: None,
+            referrer: None,
         };
-        let _handle_result = handle(
-            &mut deps,
-            Env {
-                block: BlockInfo {
-                    height: 12_345,
-                    time: 5,
-                    chain_id: "cosmos-testnet-14002".to_string(),
-                },
-                message: MessageInfo {
-                    sender: HumanAddr("bidaddr".to_string()),
-                    sent_funds: vec![],
-                },
-                contract: cosmwasm_std::ContractInfo {
-                    address: HumanAddr::from(MOCK_CONTRACT_ADDR),
-                },
-                contract_key: Some("".to_string()),
-                contract_code_hash: "".to_string(),
-            },
-            handle_msg,
-        );
+        let handle_result = handle(&mut deps, before_close_env("bob"), handle_msg);
+        let error = extract_error_msg(handle_result);
+        assert!(error.contains("does not cover your bid"));
+        // and last until the closing time
+        let handle_msg = HandleMsg::PlaceAllowanceBid {
+            amount: Uint128(150),
+            balance_key: "key".to_string(),
+            expiration: None,
+            memo: None,
+            referrer: None,
+        };
+        let handle_result = handle(&mut deps, before_close_env("charlie"), handle_msg);
+        let error = extract_error_msg(handle_result);
+        assert!(error.contains("must not expire before the closing time"));
+
+        // accepted bids do not move any tokens
+        for (bidder, amount) in [("bob", 100), ("dave", 40)].iter() {
+            let handle_msg = HandleMsg::PlaceAllowanceBid {
+                amount: Uint128(*amount),
+                balance_key: "key".to_string(),
+                expiration: None,
+                memo: None,
+                referrer: None,
+            };
+            let handle_result = handle(&mut deps, before_close_env(bidder), handle_msg);
+            let response = handle_result.unwrap();
+            assert_eq!(response.messages.len(), 1);
+            assert!(response.log[0]
+                .value
+                .contains("Your tokens will only be transferred if your bid wins"));
+        }
+        let bob_raw = deps
+            .api
+            .canonical_address(&HumanAddr("bob".to_string()))
+            .unwrap();
+        let bid: Bid = load(&deps.storage, bob_raw.as_slice()).unwrap();
+        assert_eq!(bid.amount, 100);
+
+        // bob's allowance is gone by the time the auction closes, so dave wins
+        let mut deps = deps.change_querier(|_| AllowanceQuerier {
+            allowances: vec![("bob", 20, None), ("dave", 50, None)],
+            balances: vec![("bob", "key", 500), ("dave", "key", 500)],
+        });
+        let handle_msg = HandleMsg::Finalize {
+            new_ends_at: None,
+            new_minimum_bid: None,
+        };
+        let handle_result = handle(&mut deps, mock_env("alice", &[]), handle_msg);
+        let response = handle_result.unwrap();
+        let transfers: Vec<String> = response
+            .messages
+            .iter()
+            .filter_map(|m| match m {
+                CosmosMsg::Wasm(WasmMsg::Execute {
+                    contract_addr, msg, ..
+                }) if contract_addr.as_str() == "bidaddr" => {
+                    Some(String::from_utf8(msg.as_slice().to_vec()).unwrap())
+                }
+                _ => None,
+            })
+            .collect();
+        // only the winning bid is pulled from the winner, and nothing is returned to bob
+        assert_eq!(transfers.len(), 1);
+        assert!(transfers[0].contains("\"transfer_from\""));
+        assert!(transfers[0].contains("\"owner\":\"dave\""));
+        assert!(transfers[0].contains("\"recipient\":\"alice\""));
+        assert!(transfers[0].contains("\"amount\":\"40\""));
+        let state: State = load(&deps.storage, CONFIG_KEY).unwrap();
+        assert_eq!(state.winning_bid, 40);
+        let outcomes = ReadonlyPrefixedStorage::new(PREFIX_OUTCOMES, &deps.storage);
+        let outcome: Outcome = load(&outcomes, bob_raw.as_slice()).unwrap();
+        assert_eq!(outcome.result, BidResult::Defaulted);
+        assert_eq!(outcome.returned, 0);
+    }
+
+    #[test]
+    fn test_preview_allowance_bid() {
+        let init_msg = InitMsg {
+            allowance_bids: true,
+            ..default_init_msg()
+        };
+        let mut env = mock_env("factory", &[]);
+        env.block.time = 0;
+        let mut deps = mock_dependencies(20, &[]);
+        init(&mut deps, env, init_msg).unwrap();
+        let mut deps = deps.change_querier(|_| AllowanceQuerier {
+            allowances: vec![("bob", 200, None), ("charlie", 200, Some(900))],
+            balances: vec![("bob", "key", 500)],
+        });
+        // share the bidders' key hashes so the preview does not ask the factory
         let state: State = load(&deps.storage, CONFIG_KEY).unwrap();
-        assert_eq!(state.bidders.len(), 3);
+        for bidder in ["bob", "charlie"].iter() {
+            let handle_msg = HandleMsg::ShareKeyHash {
+                address: HumanAddr(bidder.to_string()),
+                key_hash: Binary(derive_key_hash("key", &state.auction_addr)),
+                epoch: 1,
+            };
+            handle(&mut deps, mock_env("factoryaddr", &[]), handle_msg).unwrap();
+        }
+        let preview =
+            |deps: &Extern<MockStorage, MockApi, AllowanceQuerier>, bidder: &str, amount: u128| {
+                let query_msg = QueryMsg::PreviewBid {
+                    address: HumanAddr(bidder.to_string()),
+                    viewing_key: "key".to_string(),
+                    amount: Uint128(amount),
+                    current_time: Some(500),
+                };
+                match from_binary(&query(deps, query_msg).unwrap()).unwrap() {
+                    QueryAnswer::PreviewBid {
+                        accepted,
+                        message,
+                        current_bid,
+                        amount_returned,
+                        ..
+                    } => (accepted, message, current_bid, amount_returned),
+                    _ => panic!("Unexpected"),
+                }
+            };
+
+        // the allowance must cover the bid
+        let (accepted, message, _, returned) = preview(&deps, "bob", 250);
+        assert!(!accepted);
+        assert!(message.contains("allowance of 200 for this auction does not cover your bid"));
+        assert_eq!(returned, None);
+        // and last until the closing time
+        let (accepted, message, ..) = preview(&deps, "charlie", 100);
+        assert!(!accepted);
+        assert!(message.contains("must not expire before the closing time"));
+
+        // a bid the allowance covers would be accepted without sending any tokens
+        let (accepted, message, current_bid, returned) = preview(&deps, "bob", 100);
+        assert!(accepted);
+        assert_eq!(message, "Bid would be accepted");
+        assert_eq!((current_bid, returned), (None, None));
+        let handle_msg = HandleMsg::PlaceAllowanceBid {
+            amount: Uint128(100),
+            balance_key: "key".to_string(),
+            expiration: None,
+            memo: None,
+            referrer: None,
+        };
+        handle(&mut deps, before_close_env("bob"), handle_msg).unwrap();
+
+        // and would replace the current bid without returning anything
+        let (accepted, message, current_bid, returned) = preview(&deps, "bob", 150);
+        assert!(accepted);
+        assert!(message.contains("replace your current bid"));
+        assert_eq!((current_bid, returned), (Some(Uint128(100)), None));
+    }
 
+    #[test]
+    fn test_allowance_bid_balances() {
+        let mut env = mock_env("factory", &[]);
+        env.block.time = 0;
+        let mut deps = mock_dependencies(20, &[]);
+        let init_msg = InitMsg {
+            allowance_bids: true,
+            ..default_init_msg()
+        };
+        assert!(init(&mut deps, env, init_msg).is_ok());
+        let mut deps = deps.change_querier(|_| AllowanceQuerier {
+            allowances: vec![("bob", 100, None), ("charlie", 100, None)],
+            balances: vec![("bob", "bobkey", 100), ("charlie", "charliekey", 60)],
+        });
         let handle_msg = HandleMsg::Receive {
             sender: HumanAddr("blah".to_string()),
             from: HumanAddr("alice".to_string()),
-            amount: Uint128(20),
+            amount: Uint128(10),
             msg: None,
         };
-        let _handle_result = handle(&mut deps, mock_env("selladdr", &[]), handle_msg);
-        let handle_msg = HandleMsg::Finalize {
-            new_ends_at: None,
-            new_minimum_bid: None,
+        let handle_result = handle(&mut deps, before_close_env("selladdr"), handle_msg);
+        assert!(handle_result.is_ok());
+        let bid = |amount: u128, balance_key: &str| HandleMsg::PlaceAllowanceBid {
+            amount: Uint128(amount),
+            balance_key: balance_key.to_string(),
+            expiration: None,
+            memo: None,
+            referrer: None,
         };
+
+        // the viewing key must show the balance
+        let handle_result = handle(&mut deps, before_close_env("bob"), bid(90, "wrong"));
+        let error = extract_error_msg(handle_result);
+        assert!(error.contains("Unable to check your balance"));
+        // and the balance must cover the bid
         let handle_result = handle(
             &mut deps,
-            Env {
-                block: BlockInfo {
-                    height: 12_345,
-                    time: 1000,
-                    chain_id: "cosmos-testnet-14002".to_string(),
-                },
-                message: MessageInfo {
-                    sender: HumanAddr("edmund".to_string()),
-                    sent_funds: vec![],
-                },
-                contract: cosmwasm_std::ContractInfo {
-                    address: HumanAddr::from(MOCK_CONTRACT_ADDR),
-                },
-                contract_key: Some("".to_string()),
-                contract_code_hash: "".to_string(),
-            },
-            handle_msg,
+            before_close_env("charlie"),
+            bid(80, "charliekey"),
         );
-        let (
-            message,
-            winning_bid,
-            bid_decimals,
-            sell_tokens_received,
-            sell_decimals,
-            bid_tokens_received,
-        ) = extract_finalize_fields(&handle_result);
-        assert_eq!(winning_bid, Some(Uint128(1000)));
-        assert_eq!(bid_decimals, Some(8));
-        assert_eq!(sell_tokens_received, None);
-        assert_eq!(sell_decimals, None);
-        assert_eq!(bid_tokens_received, None);
-        assert!(message.contains("Sale has been finalized"));
-        assert!(!message.contains("Sale has been finalized."));
-
-        // test already closed, stranger closes
-        let handle_msg = HandleMsg::Finalize {
-            new_ends_at: None,
-            new_minimum_bid: None,
-        };
+        let error = extract_error_msg(handle_result);
+        assert!(error.contains("Your balance of 60 does not cover your bid"));
+        let handle_result = handle(&mut deps, before_close_env("bob"), bid(90, "bobkey"));
+        assert!(handle_result.is_ok());
         let handle_result = handle(
             &mut deps,
-            Env {
-                block: BlockInfo {
-                    height: 12_345,
-                    time: 1000,
-                    chain_id: "cosmos-testnet-14002".to_string(),
-                },
-                message: MessageInfo {
-                    sender: HumanAddr("bob".to_string()),
-                    sent_funds: vec![],
-                },
-                contract: cosmwasm_std::ContractInfo {
-                    address: HumanAddr::from(MOCK_CONTRACT_ADDR),
-                },
-                contract_key: Some("".to_string()),
-                contract_code_hash: "".to_string(),
-            },
-            handle_msg,
-        );
-        let (
-            message,
-            winning_bid,
-            bid_decimals,
-            sell_tokens_received,
-            sell_decimals,
-            bid_tokens_received,
-        ) = extract_finalize_fields(&handle_result);
-        assert_eq!(winning_bid, None);
-        assert_eq!(bid_decimals, None);
-        assert_eq!(sell_tokens_received, None);
-        assert_eq!(sell_decimals, None);
-        assert_eq!(bid_tokens_received, None);
-        assert!(message.contains("Auction has been closed"));
-        assert!(!message.contains("Auction has been closed."));
-
-        // test no bidders, seller closes
-        let (init_result, mut deps) = init_helper();
-        assert!(
-            init_result.is_ok(),
-            "Init failed: {}",
-            init_result.err().unwrap()
+            before_close_env("charlie"),
+            bid(50, "charliekey"),
         );
+        assert!(handle_result.is_ok());
 
-        let handle_msg = HandleMsg::Receive {
-            sender: HumanAddr("blah".to_string()),
-            from: HumanAddr("alice".to_string()),
-            amount: Uint128(20),
-            msg: None,
+        // bob spent the balance behind the allowance, so the seller can not accept his bid
+        let mut deps = deps.change_querier(|_| AllowanceQuerier {
+            allowances: vec![("bob", 100, None), ("charlie", 100, None)],
+            balances: vec![("bob", "bobkey", 10), ("charlie", "charliekey", 60)],
+        });
+        let handle_msg = HandleMsg::AcceptBid {
+            bidder: HumanAddr("bob".to_string()),
         };
-        let _handle_result = handle(&mut deps, mock_env("selladdr", &[]), handle_msg);
+        let handle_result = handle(&mut deps, before_close_env("alice"), handle_msg);
+        let error = extract_error_msg(handle_result);
+        assert!(error.contains("The allowance or balance of bob no longer covers their bid"));
+
+        // and the next best bid wins when the auction closes
         let handle_msg = HandleMsg::Finalize {
             new_ends_at: None,
             new_minimum_bid: None,
         };
-        let handle_result = handle(
-            &mut deps,
-            Env {
-                block: BlockInfo {
-                    height: 12_345,
-                    time: 10,
-                    chain_id: "cosmos-testnet-14002".to_string(),
-                },
-                message: MessageInfo {
-                    sender: HumanAddr("alice".to_string()),
-                    sent_funds: vec![],
-                },
-                contract: cosmwasm_std::ContractInfo {
-                    address: HumanAddr::from(MOCK_CONTRACT_ADDR),
-                },
-                contract_key: Some("".to_string()),
-                contract_code_hash: "".to_string(),
-            },
-            handle_msg,
-        );
-        let (
-            message,
-            winning_bid,
-            bid_decimals,
-            sell_tokens_received,
-            sell_decimals,
-            bid_tokens_received,
-        ) = extract_finalize_fields(&handle_result);
-        assert_eq!(winning_bid, None);
-        assert_eq!(bid_decimals, None);
-        assert_eq!(sell_tokens_received, Some(Uint128(10)));
-        assert_eq!(sell_decimals, Some(4));
-        assert_eq!(bid_tokens_received, None);
-        assert!(message.contains("Auction has been closed.  Consigned tokens have been returned because there were no active bids"));
+        let handle_result = handle(&mut deps, mock_env("alice", &[]), handle_msg);
+        let response = handle_result.unwrap();
+        let pulled: Vec<String> = response
+            .messages
+            .iter()
+            .filter_map(|m| match m {
+                CosmosMsg::Wasm(WasmMsg::Execute {
+                    contract_addr, msg, ..
+                }) if contract_addr.as_str() == "bidaddr" => {
+                    Some(String::from_utf8(msg.as_slice().to_vec()).unwrap())
+                }
+                _ => None,
+            })
+            .collect();
+        assert_eq!(pulled.len(), 1);
+        assert!(pulled[0].contains("\"owner\":\"charlie\""));
+        assert!(pulled[0].contains("\"amount\":\"50\""));
+        let bob_raw = deps
+            .api
+            .canonical_address(&HumanAddr("bob".to_string()))
+            .unwrap();
+        let outcomes = ReadonlyPrefixedStorage::new(PREFIX_OUTCOMES, &deps.storage);
+        let outcome: Outcome = load(&outcomes, bob_raw.as_slice()).unwrap();
+        assert_eq!(outcome.result, BidResult::Defaulted);
+        // the bidders' viewing keys are forgotten
+        let key_store = ReadonlyPrefixedStorage::new(PREFIX_BALANCE_KEYS, &deps.storage);
+        let key: Option<String> = may_load(&key_store, bob_raw.as_slice()).unwrap();
+        assert!(key.is_none());
+    }
 
-        // test no bidders, stranger closes
+    #[test]
+    fn test_retry_settlement() {
         let (init_result, mut deps) = init_helper();
         assert!(
             init_result.is_ok(),
             "Init failed: {}",
             init_result.err().unwrap()
         );
+        let mut after_close = mock_env("alice", &[]);
+        after_close.block.time = 1001;
+
+        // escrowed bids can always be settled
+        let handle_result = handle(
+            &mut deps,
+            after_close.clone(),
+            HandleMsg::RetrySettlement {},
+        );
+        let error = extract_error_msg(handle_result);
+        assert!(error.contains("Only an auction with allowance bids can retry"));
 
+        let mut env = mock_env("factory", &[]);
+        env.block.time = 0;
+        let mut deps = mock_dependencies(20, &[]);
+        let init_msg = InitMsg {
+            allowance_bids: true,
+            ..default_init_msg()
+        };
+        assert!(init(&mut deps, env, init_msg).is_ok());
+        let allowances = vec![
+            ("bob", 100, None),
+            ("charlie", 50, None),
+            ("dave", 30, None),
+        ];
+        let mut deps = deps.change_querier(|_| AllowanceQuerier {
+            allowances: allowances.clone(),
+            balances: vec![
+                ("bob", "key", 100),
+                ("charlie", "key", 50),
+                ("dave", "key", 30),
+            ],
+        });
         let handle_msg = HandleMsg::Receive {
             sender: HumanAddr("blah".to_string()),
             from: HumanAddr("alice".to_string()),
-            amount: Uint128(20),
+            amount: Uint128(10),
             msg: None,
         };
-        let _handle_result = handle(&mut deps, mock_env("selladdr", &[]), handle_msg);
-        let handle_msg = HandleMsg::Finalize {
-            new_ends_at: None,
-            new_minimum_bid: None,
+        let handle_result = handle(&mut deps, before_close_env("selladdr"), handle_msg);
+        assert!(handle_result.is_ok());
+        for (bidder, amount) in [("bob", 100), ("charlie", 50), ("dave", 30)].iter() {
+            let handle_msg = HandleMsg::PlaceAllowanceBid {
+                amount: Uint128(*amount),
+                balance_key: "key".to_string(),
+                expiration: None,
+                memo: None,
+                referrer: None,
+            };
+            let handle_result = handle(&mut deps, before_close_env(bidder), handle_msg);
+            assert!(handle_result.is_ok());
+        }
+
+        // only the seller may retry
+        let mut env = after_close.clone();
+        env.message.sender = HumanAddr("charlie".to_string());
+        let handle_result = handle(&mut deps, env, HandleMsg::RetrySettlement {});
+        let error = extract_error_msg(handle_result);
+        assert!(error.contains("Only the auction seller can retry"));
+        // and not before the closing time
+        let handle_result = handle(
+            &mut deps,
+            before_close_env("alice"),
+            HandleMsg::RetrySettlement {},
+        );
+        let error = extract_error_msg(handle_result);
+        assert!(error.contains("can not be retried before the closing time"));
+        // and not while the winner can still pay
+        let handle_result = handle(
+            &mut deps,
+            after_close.clone(),
+            HandleMsg::RetrySettlement {},
+        );
+        let error = extract_error_msg(handle_result);
+        assert!(error.contains("The allowance and balance of bob still cover their bid"));
+
+        // the winner who spent their balance is disqualified, and the next best bid is promoted
+        let mut deps = deps.change_querier(|_| AllowanceQuerier {
+            allowances: allowances.clone(),
+            balances: vec![
+                ("bob", "key", 10),
+                ("charlie", "key", 50),
+                ("dave", "key", 0),
+            ],
+        });
+        let retry = |deps: &mut Extern<MockStorage, MockApi, AllowanceQuerier>| {
+            let handle_result = handle(deps, after_close.clone(), HandleMsg::RetrySettlement {});
+            let response = handle_result.unwrap();
+            let answer: HandleAnswer = from_binary(&response.data.unwrap()).unwrap();
+            match answer {
+                HandleAnswer::RetrySettlement {
+                    defaulted,
+                    promoted,
+                    standby,
+                    ..
+                } => (response.messages, defaulted, promoted, standby),
+                _ => panic!("Unexpected HandleAnswer"),
+            }
         };
+        let (messages, defaulted, promoted, standby) = retry(&mut deps);
+        assert_eq!(defaulted, HumanAddr("bob".to_string()));
+        assert_eq!(promoted, Some(HumanAddr("charlie".to_string())));
+        assert_eq!(standby, 2);
+        // nothing was escrowed, so only the factory is told
+        assert_eq!(messages.len(), 1);
+        match &messages[0] {
+            CosmosMsg::Wasm(WasmMsg::Execute { contract_addr, .. }) => {
+                assert_eq!(contract_addr, &HumanAddr("factoryaddr".to_string()));
+            }
+            _ => panic!("Unexpected message"),
+        }
+        let bob_raw = deps
+            .api
+            .canonical_address(&HumanAddr("bob".to_string()))
+            .unwrap();
+        let outcomes = ReadonlyPrefixedStorage::new(PREFIX_OUTCOMES, &deps.storage);
+        let outcome: Outcome = load(&outcomes, bob_raw.as_slice()).unwrap();
+        assert_eq!(outcome.result, BidResult::Defaulted);
+        assert_eq!(outcome.returned, 0);
+
+        // a bid removed after the ranking is skipped
+        let charlie_raw = deps
+            .api
+            .canonical_address(&HumanAddr("charlie".to_string()))
+            .unwrap();
+        let mut state: State = load(&deps.storage, CONFIG_KEY).unwrap();
+        remove_bidder(&mut deps.storage, &mut state, charlie_raw.as_slice()).unwrap();
+        save(&mut deps.storage, CONFIG_KEY, &state).unwrap();
+        let (_, defaulted, promoted, standby) = retry(&mut deps);
+        assert_eq!(defaulted, HumanAddr("dave".to_string()));
+        assert_eq!(promoted, None);
+        assert_eq!(standby, 0);
         let handle_result = handle(
             &mut deps,
-            Env {
-                block: BlockInfo {
-                    height: 12_345,
-                    time: 1000,
-                    chain_id: "cosmos-testnet-14002".to_string(),
-                },
-                message: MessageInfo {
-                    sender: HumanAddr("bob".to_string()),
-                    sent_funds: vec![],
-                },
-                contract: cosmwasm_std::ContractInfo {
-                    address: HumanAddr::from(MOCK_CONTRACT_ADDR),
-                },
-                contract_key: Some("".to_string()),
-                contract_code_hash: "".to_string(),
-            },
-            handle_msg,
+            after_close.clone(),
+            HandleMsg::RetrySettlement {},
         );
-        let (
-            message,
-            winning_bid,
-            bid_decimals,
-            sell_tokens_received,
-            sell_decimals,
-            bid_tokens_received,
-        ) = extract_finalize_fields(&handle_result);
-        assert_eq!(winning_bid, None);
-        assert_eq!(bid_decimals, None);
-        assert_eq!(sell_tokens_received, None);
-        assert_eq!(sell_decimals, None);
-        assert_eq!(bid_tokens_received, None);
-        assert!(message.contains("Auction has been closed"));
-        assert!(!message.contains("Auction has been closed."));
+        let error = extract_error_msg(handle_result);
+        assert!(error.contains("There are no bids left"));
+
+        // closing the auction clears the standby list
+        let handle_msg = HandleMsg::Finalize {
+            new_ends_at: None,
+            new_minimum_bid: None,
+        };
+        let handle_result = handle(&mut deps, after_close, handle_msg);
+        assert!(handle_result.is_ok());
+        let standby: Option<Vec<Vec<u8>>> = may_load(&deps.storage, STANDBY_KEY).unwrap();
+        assert!(standby.is_none());
     }
 
     #[test]
-    fn test_query_view_bid() {
-        let (init_result, deps) = init_helper();
+    fn test_bid_memos() {
+        let (init_result, mut deps) = init_helper();
         assert!(
             init_result.is_ok(),
             "Init failed: {}",
             init_result.err().unwrap()
         );
+        let handle_msg = HandleMsg::Receive {
+            sender: HumanAddr("blah".to_string()),
+            from: HumanAddr("alice".to_string()),
+            amount: Uint128(10),
+            msg: None,
+        };
+        let handle_result = handle(&mut deps, before_close_env("selladdr"), handle_msg);
+        assert!(handle_result.is_ok());
+        let bid_msg = |bidder: &str, amount: u128, memo: Option<String>| HandleMsg::Receive {
+            sender: HumanAddr("blah".to_string()),
+            from: HumanAddr(bidder.to_string()),
+            amount: Uint128(amount),
+            msg: Some(
+                to_binary(&ReceiveMsg::PlaceBid {
+                    expiration: None,
+                    max_bid: None,
+                    unit_price: None,
+                    memo,
+                    referrer: None,
+                })
+                .unwrap(),
+            ),
+        };
+        for (bidder, amount, memo) in [
+            ("bob", 100, Some("send to my cold wallet")),
+            ("charlie", 50, Some("pay in two parts")),
+            ("dave", 30, None),
+        ]
+        .iter()
+        {
+            let handle_msg = bid_msg(bidder, *amount, memo.map(|m| m.to_string()));
+            let handle_result = handle(&mut deps, before_close_env("bidaddr"), handle_msg);
+            assert!(handle_result.is_ok());
+        }
+
+        // memos are limited by their escaped length
+        let handle_msg = bid_msg("eve", 20, Some("x".repeat(MAX_MEMO_LEN + 1)));
+        let handle_result = handle(&mut deps, before_close_env("bidaddr"), handle_msg);
+        let log = extract_log(handle_result);
+        assert!(log.contains("must not be longer than"));
+        assert!(log.contains("\"amount_returned\":\"20\""));
+        let handle_msg = bid_msg("eve", 20, Some("\u{1}".repeat(MAX_MEMO_LEN / 2)));
+        let handle_result = handle(&mut deps, before_close_env("bidaddr"), handle_msg);
+        let log = extract_log(handle_result);
+        assert!(log.contains("must not be longer than"));
+        assert!(log.contains("\"amount_returned\":\"20\""));
 
         #[derive(Debug)]
         struct MyMockQuerier {
@@ -2905,179 +12637,203 @@ mod tests {
                 }))
             }
         }
-        let invalid_deps = deps.change_querier(|_| MyMockQuerier { is_valid: false });
-        // try wrong key
-        let query_msg = QueryMsg::ViewBid {
-            address: HumanAddr("bob".to_string()),
-            viewing_key: "wrong_key".to_string(),
+        let mut deps = deps.change_querier(|_| MyMockQuerier { is_valid: true });
+        let memos_query = |address: &str| QueryMsg::BidMemos {
+            address: HumanAddr(address.to_string()),
+            viewing_key: "key".to_string(),
         };
-        let query_result = query(&invalid_deps, query_msg);
-        let error = extract_error_msg(query_result);
-        assert!(error.contains("Wrong viewing key"));
-
-        let (init_result, deps) = init_helper();
-        assert!(
-            init_result.is_ok(),
-            "Init failed: {}",
-            init_result.err().unwrap()
-        );
-        let valid_deps = deps.change_querier(|_| MyMockQuerier { is_valid: true });
-        // try no bid
+        // bidders can not see each other's memos
+        let error = extract_error_msg(query(&deps, memos_query("bob")));
+        assert!(error.contains("does not match the auction seller or its operators"));
+        match from_binary(&query(&deps, memos_query("alice")).unwrap()).unwrap() {
+            QueryAnswer::BidMemos {
+                memos,
+                winner_memos,
+            } => {
+                assert_eq!(
+                    memos,
+                    vec![
+                        "pay in two parts".to_string(),
+                        "send to my cold wallet".to_string()
+                    ]
+                );
+                assert!(winner_memos.is_empty());
+            }
+            _ => panic!("Unexpected QueryAnswer"),
+        }
+        // but can see their own
         let query_msg = QueryMsg::ViewBid {
             address: HumanAddr("bob".to_string()),
             viewing_key: "key".to_string(),
         };
-        let query_result = query(&valid_deps, query_msg);
-        let (message, bid_decimals) = match from_binary(&query_result.unwrap()).unwrap() {
-            QueryAnswer::Bid {
-                message,
-                bid_decimals,
-                ..
-            } => (message, bid_decimals),
-            _ => panic!("Unexpected"),
+        match from_binary(&query(&deps, query_msg).unwrap()).unwrap() {
+            QueryAnswer::Bid { memo, .. } => {
+                assert_eq!(memo, Some("send to my cold wallet".to_string()))
+            }
+            _ => panic!("Unexpected QueryAnswer"),
+        }
+
+        // the winner's memo is kept after the bids are removed
+        let mut after_close = mock_env("alice", &[]);
+        after_close.block.time = 1001;
+        let handle_msg = HandleMsg::Finalize {
+            new_ends_at: None,
+            new_minimum_bid: None,
         };
-        assert!(message.contains("No active bid for address: bob"));
-        assert_eq!(bid_decimals, None);
+        let handle_result = handle(&mut deps, after_close, handle_msg);
+        assert!(handle_result.is_ok());
+        match from_binary(&query(&deps, memos_query("alice")).unwrap()).unwrap() {
+            QueryAnswer::BidMemos {
+                memos,
+                winner_memos,
+            } => {
+                assert!(memos.is_empty());
+                assert_eq!(winner_memos, vec!["send to my cold wallet".to_string()]);
+            }
+            _ => panic!("Unexpected QueryAnswer"),
+        }
+    }
 
-        // sanity check
-        let (init_result, deps) = init_helper();
+    #[test]
+    fn test_bid_referrer() {
+        let (init_result, mut deps) = init_helper();
         assert!(
             init_result.is_ok(),
             "Init failed: {}",
             init_result.err().unwrap()
         );
-        let mut valid_deps = deps.change_querier(|_| MyMockQuerier { is_valid: true });
-        let handle_msg = HandleMsg::Receive {
+        let bid_msg = |referrer: &str| HandleMsg::Receive {
             sender: HumanAddr("blah".to_string()),
             from: HumanAddr("bob".to_string()),
             amount: Uint128(100),
-            msg: None,
-        };
-        let _handle_result = handle(&mut valid_deps, mock_env("bidaddr", &[]), handle_msg);
-        let query_msg = QueryMsg::ViewBid {
-            address: HumanAddr("bob".to_string()),
-            viewing_key: "key".to_string(),
-        };
-        let query_result = query(&valid_deps, query_msg);
-        let (message, amount_bid, bid_decimals) = match from_binary(&query_result.unwrap()).unwrap()
-        {
-            QueryAnswer::Bid {
-                message,
-                amount_bid,
-                bid_decimals,
-                ..
-            } => (message, amount_bid, bid_decimals),
-            _ => panic!("Unexpected"),
+            msg: Some(
+                to_binary(&ReceiveMsg::PlaceBid {
+                    expiration: None,
+                    max_bid: None,
+                    unit_price: None,
+                    memo: None,
+                    referrer: Some(HumanAddr(referrer.to_string())),
+                })
+                .unwrap(),
+            ),
         };
-        assert!(message.contains("Bid placed"));
-        assert_eq!(amount_bid, Some(Uint128(100)));
-        assert_eq!(bid_decimals, Some(8));
+        // a bidder can not refer themselves
+        let handle_result = handle(&mut deps, before_close_env("bidaddr"), bid_msg("bob"));
+        let log = extract_log(handle_result);
+        assert!(log.contains("can not refer themselves"));
+        assert!(log.contains("\"amount_returned\":\"100\""));
+        let handle_result = handle(&mut deps, before_close_env("bidaddr"), bid_msg("carol"));
+        assert!(handle_result.is_ok());
+        let bidder_raw = deps
+            .api
+            .canonical_address(&HumanAddr("bob".to_string()))
+            .unwrap();
+        let bid: Bid = load(&deps.storage, bidder_raw.as_slice()).unwrap();
+        assert_eq!(
+            bid.referrer,
+            Some(
+                deps.api
+                    .canonical_address(&HumanAddr("carol".to_string()))
+                    .unwrap()
+            )
+        );
     }
 
     #[test]
-    fn test_query_has_bids() {
-        let (init_result, deps) = init_helper();
+    fn test_localized_descriptions() {
+        let (init_result, mut deps) = init_helper();
         assert!(
             init_result.is_ok(),
             "Init failed: {}",
             init_result.err().unwrap()
         );
+        let set_msg = |description: &str, lang: Option<&str>| HandleMsg::SetDescription {
+            description: description.to_string(),
+            lang: lang.map(String::from),
+        };
 
-        #[derive(Debug)]
-        struct MyMockQuerier {
-            pub is_valid: bool,
+        // only the seller may change the description
+        let handle_result = handle(
+            &mut deps,
+            before_close_env("bob"),
+            set_msg("mine now", None),
+        );
+        let error = extract_error_msg(handle_result);
+        assert!(error.contains("Only the auction seller can change the description"));
+        let handle_result = handle(
+            &mut deps,
+            before_close_env("alice"),
+            set_msg("bonjour", Some(&"x".repeat(MAX_LANG_LEN + 1))),
+        );
+        let error = extract_error_msg(handle_result);
+        assert!(error.contains("A language code must be"));
+        for (text, lang) in [
+            ("An auction", None),
+            ("Une enchère", Some("fr")),
+            ("Una subasta", Some("es")),
+            ("Eine Auktion", Some("de")),
+            ("", Some("de")),
+        ]
+        .iter()
+        {
+            let handle_result = handle(&mut deps, before_close_env("alice"), set_msg(text, *lang));
+            assert!(handle_result.is_ok());
+        }
+        let state: State = load(&deps.storage, CONFIG_KEY).unwrap();
+        assert_eq!(state.description, Some("An auction".to_string()));
+        let languages: Vec<&String> = state.descriptions.keys().collect();
+        assert_eq!(languages, vec!["es", "fr"]);
+
+        #[derive(Serialize)]
+        struct MockTokenInfo {
+            name: String,
+            symbol: String,
+            decimals: u8,
+            total_supply: Option<Uint128>,
+        }
+        #[derive(Serialize)]
+        struct MockTokenInfoWrapper {
+            token_info: MockTokenInfo,
         }
+        struct MyMockQuerier {}
         impl Querier for MyMockQuerier {
             fn raw_query(&self, _request: &[u8]) -> QuerierResult {
-                Ok(to_binary(&IsKeyValidWrapper {
-                    is_key_valid: IsKeyValid {
-                        is_valid: self.is_valid,
+                Ok(to_binary(&MockTokenInfoWrapper {
+                    token_info: MockTokenInfo {
+                        name: "token".to_string(),
+                        symbol: "TKN".to_string(),
+                        decimals: 6,
+                        total_supply: None,
                     },
                 }))
             }
         }
-        let invalid_deps = deps.change_querier(|_| MyMockQuerier { is_valid: false });
-        // try wrong key
-        let query_msg = QueryMsg::HasBids {
-            address: HumanAddr("alice".to_string()),
-            viewing_key: "wrong_key".to_string(),
-        };
-        let query_result = query(&invalid_deps, query_msg);
-        let error = extract_error_msg(query_result);
-        assert!(error
-            .contains("Address and/or viewing key does not match auction creator's information"));
-
-        let (init_result, deps) = init_helper();
-        assert!(
-            init_result.is_ok(),
-            "Init failed: {}",
-            init_result.err().unwrap()
-        );
-        let valid_deps = deps.change_querier(|_| MyMockQuerier { is_valid: true });
-        // try not seller
-        let query_msg = QueryMsg::HasBids {
-            address: HumanAddr("bob".to_string()),
-            viewing_key: "key".to_string(),
-        };
-        let query_result = query(&valid_deps, query_msg);
-        let error = extract_error_msg(query_result);
-        assert!(error
-            .contains("Address and/or viewing key does not match auction creator's information"));
-
-        // sanity check, no bids
-        let (init_result, deps) = init_helper();
-        assert!(
-            init_result.is_ok(),
-            "Init failed: {}",
-            init_result.err().unwrap()
-        );
-        let mut valid_deps = deps.change_querier(|_| MyMockQuerier { is_valid: true });
-        let query_msg = QueryMsg::HasBids {
-            address: HumanAddr("alice".to_string()),
-            viewing_key: "key".to_string(),
-        };
-        let query_result = query(&valid_deps, query_msg);
-        let has_bids = match from_binary(&query_result.unwrap()).unwrap() {
-            QueryAnswer::HasBids { has_bids, .. } => has_bids,
-            _ => panic!("Unexpected"),
-        };
-        assert!(!has_bids);
-
-        // sanity check, with bids
-        let handle_msg = HandleMsg::Receive {
-            sender: HumanAddr("blah".to_string()),
-            from: HumanAddr("bob".to_string()),
-            amount: Uint128(100),
-            msg: None,
-        };
-        let _handle_result = handle(&mut valid_deps, mock_env("bidaddr", &[]), handle_msg);
-        let query_msg = QueryMsg::HasBids {
-            address: HumanAddr("alice".to_string()),
-            viewing_key: "key".to_string(),
-        };
-        let query_result = query(&valid_deps, query_msg);
-        let has_bids = match from_binary(&query_result.unwrap()).unwrap() {
-            QueryAnswer::HasBids { has_bids, .. } => has_bids,
-            _ => panic!("Unexpected"),
-        };
-        assert!(has_bids);
-
-        let handle_msg = HandleMsg::Receive {
-            sender: HumanAddr("blah".to_string()),
-            from: HumanAddr("charlie".to_string()),
-            amount: Uint128(100),
-            msg: None,
-        };
-        let _handle_result = handle(&mut valid_deps, mock_env("bidaddr", &[]), handle_msg);
-        let query_msg = QueryMsg::HasBids {
-            address: HumanAddr("alice".to_string()),
-            viewing_key: "key".to_string(),
-        };
-        let query_result = query(&valid_deps, query_msg);
-        let has_bids = match from_binary(&query_result.unwrap()).unwrap() {
-            QueryAnswer::HasBids { has_bids, .. } => has_bids,
-            _ => panic!("Unexpected"),
+        let deps = deps.change_querier(|_| MyMockQuerier {});
+        let info = |lang: Option<&str>| {
+            let query_msg = QueryMsg::AuctionInfo {
+                lang: lang.map(String::from),
+                current_time: None,
+            };
+            match from_binary(&query(&deps, query_msg).unwrap()).unwrap() {
+                QueryAnswer::AuctionInfo {
+                    description,
+                    description_lang,
+                    languages,
+                    ..
+                } => (description, description_lang, languages),
+                _ => panic!("Unexpected QueryAnswer"),
+            }
         };
-        assert!(has_bids);
+        // a translation is shown if there is one
+        let (description, description_lang, languages) = info(Some("fr"));
+        assert_eq!(description, Some("Une enchère".to_string()));
+        assert_eq!(description_lang, Some("fr".to_string()));
+        assert_eq!(languages, vec!["es".to_string(), "fr".to_string()]);
+        // otherwise the default is shown
+        for lang in [None, Some("de")].iter() {
+            let (description, description_lang, _) = info(*lang);
+            assert_eq!(description, Some("An auction".to_string()));
+            assert_eq!(description_lang, None);
+        }
     }
 }
@@ -0,0 +1,26 @@
+use cosmwasm_std::{Env, StdError, StdResult};
+
+use crate::state::State;
+
+/// number of seconds after the closing time before the factory, or anyone using ForceClose, may
+/// force an auction to close without a sale
+pub const FORCE_FINALIZE_GRACE: u64 = 90 * 24 * 60 * 60;
+
+/// Returns StdResult<()>
+///
+/// verifies the message was sent by the factory that created this auction
+///
+/// # Arguments
+///
+/// * `env` - a reference to the Env of contract's environment
+/// * `state` - a reference to the auction state
+/// * `action` - description of the action being attempted, used in the error message
+pub fn factory_only(env: &Env, state: &State, action: &str) -> StdResult<()> {
+    if env.message.sender != state.factory.address {
+        return Err(StdError::generic_err(format!(
+            "Only the factory can {}",
+            action
+        )));
+    }
+    Ok(())
+}
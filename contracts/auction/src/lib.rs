@@ -1,5 +1,13 @@
+mod bidders;
+mod board;
 pub mod contract;
+mod factory_only;
 pub mod msg;
+mod notify;
+#[cfg(test)]
+mod padding_audit;
+#[cfg(test)]
+mod proptests;
 pub mod state;
 
 #[cfg(target_arch = "wasm32")]
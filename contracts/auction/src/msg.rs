@@ -3,7 +3,13 @@ use serde::{Deserialize, Serialize};
 
 use cosmwasm_std::{Binary, CosmosMsg, HumanAddr, Querier, StdResult, Uint128};
 
-use secret_toolkit::snip20::{register_receive_msg, token_info_query, transfer_msg, TokenInfo};
+use secret_toolkit::{
+    snip20::{
+        allowance_query, balance_query, register_receive_msg, set_viewing_key_msg,
+        token_info_query, transfer_from_msg, transfer_msg, Allowance, Balance, TokenInfo,
+    },
+    utils::{HandleCallback, Query},
+};
 
 use crate::contract::BLOCK_SIZE;
 
@@ -20,14 +26,10 @@ pub struct InitMsg {
     pub seller: HumanAddr,
     /// sell contract code hash and address
     pub sell_contract: ContractInfo,
-    /// sell symbol index
-    pub sell_symbol: u16,
     /// sell token decimal places
     pub sell_decimals: u8,
     /// bid contract code hash and address
     pub bid_contract: ContractInfo,
-    /// bid symbol index
-    pub bid_symbol: u16,
     /// bid token decimal places,
     pub bid_decimals: u8,
     /// amount of tokens being sold
@@ -42,6 +44,173 @@ pub struct InitMsg {
     /// auctions for the same token, etc...
     #[serde(default)]
     pub description: Option<String>,
+    /// Optional translations of the description.  The description above is the default shown
+    /// when a translation is not available
+    #[serde(default)]
+    pub descriptions: Vec<LocalizedDescription>,
+    /// Optional additional tokens sold together with the sell tokens as one lot
+    #[serde(default)]
+    pub bundle: Vec<BundleItem>,
+    /// Optional deposit in bid tokens the seller posts.  If the auction closes with bids but
+    /// without the sell tokens ever being consigned, the deposit is split among the bidders
+    #[serde(default)]
+    pub deposit: Option<Uint128>,
+    /// Optional timestamp by which the seller must have consigned the full lot.  If it has not
+    /// been consigned by then, anyone may cancel the auction.  Timestamp is in seconds since
+    /// epoch 01/01/1970
+    #[serde(default)]
+    pub consign_by: Option<u64>,
+    /// Optional price at which a bidder may buy the lot immediately and close the auction
+    #[serde(default)]
+    pub buy_now_price: Option<Uint128>,
+    /// true if the seller is not allowed to bid in the auction
+    #[serde(default)]
+    pub no_self_bid: bool,
+    /// true if a bidder may only replace their bid with a higher bid
+    #[serde(default)]
+    pub only_increasing_rebids: bool,
+    /// policy used to pick the winner when the highest bids are tied.  Defaults to earliest_wins
+    #[serde(default)]
+    pub tie_break: TieBreak,
+    /// true if bids are backed by an allowance given to the auction instead of being sent to
+    /// escrow.  Only the winning bid is transferred when the auction closes.  Requires a SNIP-20
+    /// bid token, and can not be combined with a buy now price or pro-rata tie breaks
+    #[serde(default)]
+    pub allowance_bids: bool,
+    /// true if the auction has a message board where the seller can post updates and bidders can
+    /// post questions
+    #[serde(default)]
+    pub message_board: bool,
+    /// key the factory generated for this auction to authenticate its callbacks
+    pub callback_key: String,
+    /// Optional block size, in bytes, that responses are padded to.  Defaults to, and can not be
+    /// smaller than, 256
+    #[serde(default)]
+    pub block_size: Option<u16>,
+    /// Optional schedule by which the minimum bid declines, starting from when the auction is
+    /// created
+    #[serde(default)]
+    pub min_bid_decay: Option<MinBidDecay>,
+    /// Optional number of seconds the seller has to accept or reject the winning bid when
+    /// someone other than the seller finalizes the auction.  If the seller does not respond in
+    /// time, the sale goes through
+    #[serde(default)]
+    pub seller_confirmation: Option<u64>,
+    /// true if bidders may specify a price per whole sell token instead of a total bid.  The
+    /// total cost of a unit price is computed against the sell amount
+    #[serde(default)]
+    pub unit_price_bids: bool,
+    /// Optional limit on the number of distinct bidders.  Once it is reached, only addresses
+    /// that already have an active bid may bid
+    #[serde(default)]
+    pub max_bidders: Option<u32>,
+    /// Optional number of distinct bidders the auction needs to sell.  If fewer have active
+    /// bids when it closes, every bid is returned instead
+    #[serde(default)]
+    pub min_bidders: Option<u32>,
+    /// Optional number of seconds after the closing time during which sealed bids are revealed.
+    /// If given, bids are placed as hash commitments backed by a deposit, and only bids that
+    /// are revealed in time compete.  Deposits of unrevealed commitments go to the seller
+    #[serde(default)]
+    pub reveal_window: Option<u64>,
+    /// how the winner is picked from the active bids.  Defaults to highest_bid
+    #[serde(default)]
+    pub settlement: SettlementMode,
+    /// Optional secret seed from the factory's prng, required to draw a raffle winner
+    #[serde(default)]
+    pub raffle_seed: Option<Binary>,
+    /// Optional amount a crowdfund must raise to sell the lot.  Required for a crowdfund
+    #[serde(default)]
+    pub funding_target: Option<Uint128>,
+}
+
+/// schedule by which the minimum bid declines while the auction is open.  A step of 1 second
+/// gives a linear decline
+#[derive(Serialize, Deserialize, JsonSchema, Clone, PartialEq, Debug)]
+pub struct MinBidDecay {
+    /// lowest value the minimum bid declines to
+    pub floor: Uint128,
+    /// amount the minimum bid declines every interval
+    pub step: Uint128,
+    /// number of seconds between declines
+    pub interval: u64,
+}
+
+/// minimum bid decay schedule and the time it started
+#[derive(Serialize, Deserialize, JsonSchema, Clone, PartialEq, Debug)]
+pub struct MinBidDecayInfo {
+    /// lowest value the minimum bid declines to
+    pub floor: Uint128,
+    /// amount the minimum bid declines every interval
+    pub step: Uint128,
+    /// number of seconds between declines
+    pub interval: u64,
+    /// time the decline started in seconds since epoch 01/01/1970.  Changing the minimum bid
+    /// restarts the schedule
+    pub start: u64,
+}
+
+/// policy used to pick the winner when the highest bids are tied
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum TieBreak {
+    /// the bid placed first wins
+    EarliestWins,
+    /// the bid placed last wins
+    LatestWins,
+    /// the lot is split evenly among all tied bids if it divides evenly, otherwise the bid placed
+    /// first wins
+    ProRata,
+}
+
+impl Default for TieBreak {
+    fn default() -> Self {
+        TieBreak::EarliestWins
+    }
+}
+
+/// how the winner is picked from the active bids when the auction closes
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum SettlementMode {
+    /// the highest bid wins
+    HighestBid,
+    /// the winner is drawn at random with odds proportional to the size of each bid, and pays
+    /// their bid
+    Raffle,
+    /// every bid wins a slice of the lot proportional to its size if the bids add up to the
+    /// funding target, otherwise every bid is returned
+    Crowdfund,
+}
+
+impl Default for SettlementMode {
+    fn default() -> Self {
+        SettlementMode::HighestBid
+    }
+}
+
+/// how far the auction has progressed toward paying out its bids and lot
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum SettlementPhase {
+    /// the auction has not started settling
+    Open,
+    /// the settlement has started, but the auction has not closed.  This lasts while the
+    /// winning bid awaits the seller's acceptance or a failed settlement is being retried
+    Settling,
+    /// the auction has closed and its settlement has been paid out
+    Completed,
+}
+
+/// an additional token included in an auction lot
+#[derive(Serialize, Deserialize, JsonSchema, Clone, PartialEq, Debug)]
+pub struct BundleItem {
+    /// token contract code hash and address
+    pub contract: ContractInfo,
+    /// amount of tokens being sold
+    pub amount: Uint128,
+    /// token decimal places
+    pub decimals: u8,
 }
 
 /// Handle messages
@@ -59,17 +228,73 @@ pub enum HandleMsg {
         from: HumanAddr,
         /// amount of tokens sent
         amount: Uint128,
-        /// Optional base64 encoded message sent with the Send call -- not needed or used by this
-        /// contract
+        /// Optional base64 encoded ReceiveMsg sent with the Send call
+        #[serde(default)]
+        msg: Option<Binary>,
+    },
+
+    /// Snip1155Receive gets called by SNIP-1155 token contracts of the auction.  It behaves the
+    /// same as Receive, but will only accept the token_id used by the auction
+    Snip1155Receive {
+        /// address of person or contract that sent the tokens that triggered this receive
+        sender: HumanAddr,
+        /// id of the tokens sent
+        token_id: String,
+        /// address of the owner of the tokens sent to the auction
+        #[serde(default)]
+        from: Option<HumanAddr>,
+        /// amount of tokens sent
+        amount: Uint128,
+        /// Optional memo sent with the tokens -- not used by this contract
+        #[serde(default)]
+        memo: Option<String>,
+        /// Optional base64 encoded ReceiveMsg sent with the Send call
         #[serde(default)]
         msg: Option<Binary>,
     },
 
+    /// PlaceAllowanceBid places a bid in an auction that takes bids backed by allowances.  The
+    /// bidder must have given the auction an allowance of at least the bid amount that does not
+    /// expire before the closing time, and a viewing key that shows a balance covering the bid.
+    /// No tokens are transferred unless the bid wins
+    PlaceAllowanceBid {
+        /// amount to bid
+        amount: Uint128,
+        /// the bidder's viewing key with the bid token, used to check that their balance covers
+        /// the bid when it is placed and when the auction closes
+        balance_key: String,
+        /// optional time after which the bid is no longer valid.  Timestamp is in seconds since
+        /// epoch 01/01/1970
+        #[serde(default)]
+        expiration: Option<u64>,
+        /// optional private memo for the seller, such as settlement instructions
+        #[serde(default)]
+        memo: Option<String>,
+        /// optional address that referred the bidder.  If the bid wins, the referrer is paid a
+        /// share of the factory's creation fee
+        #[serde(default)]
+        referrer: Option<HumanAddr>,
+    },
+
+    /// RevealBid reveals the bid behind the calling address' sealed commitment.  It may only be
+    /// called during the reveal window that follows the closing time
+    RevealBid {
+        /// amount that was bid
+        amount: Uint128,
+        /// salt that was hashed with the bid to create the commitment
+        salt: String,
+    },
+
     /// RetractBid will retract any active bid the calling address has made and return the tokens
     /// that are held in escrow
     RetractBid {},
 
-    /// Finalize will close the auction
+    /// ReclaimExpiredBid returns the calling address' bid if it has expired.  It may be called at
+    /// any time, even after the closing time has passed
+    ReclaimExpiredBid {},
+
+    /// Finalize will close the auction.  If the factory sends it while the auction can not close
+    /// yet, the auction answers with a Failure instead of an error
     Finalize {
         /// optional timestamp to extend the closing time to if there are no bids. Timestamp is in
         /// seconds since epoch 01/01/1970
@@ -80,17 +305,198 @@ pub enum HandleMsg {
         new_minimum_bid: Option<Uint128>,
     },
 
+    /// AcceptCurrentBest lets the seller close the auction before the closing time by selling to
+    /// the current highest bid and returning every other bid.  Only the seller or its operators
+    /// may call it, and only if the lot has been consigned and there is an active bid
+    AcceptCurrentBest {},
+
+    /// AcceptBid lets the seller close the auction by selling to a chosen bid, even if it is not
+    /// the highest, and returning every other bid.  Only the seller or its operators may call
+    /// it, and only if the lot has been consigned
+    AcceptBid {
+        /// address of the bidder whose bid is accepted
+        bidder: HumanAddr,
+    },
+
+    /// RejectBid lets the seller return a particular bid and remove it from the auction.  Only
+    /// the seller or its operators may call it, and only before the auction closes.  The reason
+    /// is saved with the bid's outcome, so the bidder can see why their bid was returned
+    RejectBid {
+        /// address of the bidder whose bid is rejected
+        bidder: HumanAddr,
+        /// reason for the rejection
+        reason: String,
+    },
+
+    /// PauseBidding lets the seller stop taking new bids while they adjust the auction's terms.
+    /// Existing bids remain active and may still be retracted.  Only the seller or its
+    /// operators may call it
+    PauseBidding {},
+
+    /// ResumeBidding lets the seller take new bids again after pausing them
+    ResumeBidding {},
+
     /// If the auction holds any funds after it has closed (should never happen), this will return
     /// those funds to their owners.  Should never be needed, but included in case of unforeseen
-    /// error
-    ReturnAll {},
+    /// error.  If you specify page size, it only reads that many bid records, and the next
+    /// return_all resumes where it left off
+    ReturnAll {
+        /// optional number of bid records to read
+        #[serde(default)]
+        page_size: Option<u32>,
+    },
+
+    /// CancelUnconsigned cancels an auction whose seller did not consign the full lot by the
+    /// consignment deadline.  All bids are returned, and anyone may call it once the deadline
+    /// has passed
+    CancelUnconsigned {},
+
+    /// ForceClose closes an auction that is still open long after its closing time without a
+    /// sale.  All bids, consigned tokens, and any deposit are returned, and anyone may call it
+    ForceClose {},
 
     /// ChangeMinimumBid allows the seller to change the minimum bid.  The new minimum bid only
     /// applies to new bids placed.  Any bid that were already accepted, will still be considered
-    /// valid bids
+    /// valid bids unless refund_lower_bids is true
     ChangeMinimumBid {
         /// new minimum bid
         minimum_bid: Uint128,
+        /// true if existing bids below the new minimum bid should be returned and removed
+        #[serde(default)]
+        refund_lower_bids: bool,
+    },
+
+    /// ChangeSellAmount allows the seller to change the sell amount while nothing has been
+    /// consigned and no bids have been placed
+    ChangeSellAmount {
+        /// new sell amount
+        new_amount: Uint128,
+    },
+
+    /// SetDescription changes the default description of the auction, or its translation for a
+    /// language.  An empty description removes it.  Only the seller or its operators may call
+    /// it, and only while the auction is open
+    SetDescription {
+        /// new description
+        description: String,
+        /// optional language code of the translation to set.  If not specified, the default
+        /// description is changed
+        #[serde(default)]
+        lang: Option<String>,
+    },
+
+    /// PostMessage posts to the auction's message board.  The seller and its operators may post
+    /// updates, and bidders with an active bid may post questions.  The board is read only once
+    /// the auction closes
+    PostMessage {
+        /// text of the message
+        text: String,
+    },
+
+    /// RegisterNotification lets a bidder with an active bid opt in to an encrypted notification
+    /// of how their bid ended, which is included in the logs of the transaction that closes the
+    /// auction.  The key is a 32-byte secret the bidder's client keeps to detect and decrypt the
+    /// notification.  Omitting the key cancels the registration
+    RegisterNotification {
+        /// optional 32-byte notification key
+        #[serde(default)]
+        key: Option<Binary>,
+    },
+
+    /// RetrySettlement disqualifies the winning bid of an auction with allowance bids whose winner's
+    /// allowance or balance no longer covers the bid, and promotes the next best bid to winner.
+    /// Only the seller may call it, and only after the closing time.  Finalize must be called
+    /// afterwards to settle with the promoted bidder
+    RetrySettlement {},
+
+    /// RespondToWinner lets the seller accept or reject the winning bid of an auction that is
+    /// awaiting the seller's confirmation.  Accepting completes the sale, and rejecting closes
+    /// the auction without a sale, returning all bids and the consigned tokens.  Only the seller
+    /// or its operators may call it, and only before the confirmation window ends
+    RespondToWinner {
+        /// true to accept the winning bid, false to reject it
+        accept: bool,
+    },
+
+    /// TransferSellerRights hands the auction over to a new seller.  Only the seller may call it,
+    /// and only before the tokens for sale have been fully consigned.  Any consigned tokens,
+    /// deposit, and proceeds of the sale will be sent to the new seller
+    TransferSellerRights {
+        /// address of the new seller
+        new_seller: HumanAddr,
+    },
+
+    /// AddOperators allows the seller to authorize addresses to change the minimum bid, change
+    /// the closing time, and finalize the auction on the seller's behalf
+    AddOperators {
+        /// addresses to authorize
+        operators: Vec<HumanAddr>,
+    },
+
+    /// RemoveOperators allows the seller to revoke the authorization of operators
+    RemoveOperators {
+        /// addresses whose authorization should be revoked
+        operators: Vec<HumanAddr>,
+    },
+
+    /// Halt stops (or resumes) accepting bids, consignments, and finalization.  Bidders may still
+    /// retract their bids while the auction is halted
+    ///
+    /// Only the factory will use this function
+    Halt {
+        /// true if the auction should be halted, false if it should resume
+        halted: bool,
+    },
+
+    /// ForceFinalize closes an auction that is still open long after its closing time
+    ///
+    /// Only the factory will use this function
+    ForceFinalize {},
+
+    /// Deprecate marks the auction as created with an outdated auction contract version, and
+    /// displays the notice in its AuctionInfo
+    ///
+    /// Only the factory will use this function
+    Deprecate {
+        /// deprecation warning to display
+        notice: String,
+    },
+
+    /// Migrate links the auction to a new factory
+    ///
+    /// Only the factory will use this function
+    Migrate {
+        /// code hash and address of the new factory
+        factory: FactoryInfo,
+        /// index of the auction with the new factory
+        index: u32,
+        /// key the new factory gave the auction to authenticate its callbacks
+        callback_key: String,
+    },
+
+    /// MatchSwap settles the auction with the seller of a complementary auction.  The lot is sent
+    /// to the other seller, while the factory has the other auction send its lot to this seller
+    ///
+    /// Only the factory will use this function
+    MatchSwap {
+        /// seller of the complementary auction who receives this auction's lot
+        recipient: HumanAddr,
+        /// amount of bid tokens this auction's seller receives from the complementary auction
+        received: Uint128,
+    },
+
+    /// ShareKeyHash gives the auction a hash derived from an address' viewing key, so the auction
+    /// can validate the address' key without querying the factory.  A hash from a newer key epoch
+    /// invalidates the ones cached for the address before it
+    ///
+    /// Only the factory will use this function
+    ShareKeyHash {
+        /// address whose viewing key the hash was derived from
+        address: HumanAddr,
+        /// hash derived from the address' viewing key
+        key_hash: Binary,
+        /// number of times the address had set a viewing key when the hash was derived
+        epoch: u32,
     },
 }
 
@@ -99,7 +505,17 @@ pub enum HandleMsg {
 #[serde(rename_all = "snake_case")]
 pub enum QueryMsg {
     /// Displays the auction information
-    AuctionInfo {},
+    AuctionInfo {
+        /// optional language code of the description to display.  The default description is
+        /// displayed if there is no translation for the language
+        #[serde(default)]
+        lang: Option<String>,
+        /// optional current time in seconds since epoch 01/01/1970, used to display the current
+        /// minimum bid of an auction whose minimum bid declines.  Queries can not read the block
+        /// time, so the starting minimum bid is displayed if it is not given
+        #[serde(default)]
+        current_time: Option<u64>,
+    },
     /// View active bid for input address
     ViewBid {
         /// address whose bid should be displayed
@@ -107,6 +523,37 @@ pub enum QueryMsg {
         /// bidder's viewing key
         viewing_key: String,
     },
+    /// reports whether a bid of the given amount from the input address would be accepted, and
+    /// what would happen to the address' current bid, without placing it
+    PreviewBid {
+        /// address that would place the bid
+        address: HumanAddr,
+        /// bidder's viewing key
+        viewing_key: String,
+        /// amount that would be bid
+        amount: Uint128,
+        /// optional current time in seconds since epoch 01/01/1970.  Queries can not read the
+        /// block time, so the closing time is only checked, and a declining minimum bid is only
+        /// applied, if it is given
+        #[serde(default)]
+        current_time: Option<u64>,
+    },
+    /// displays the internal state flags of the auction to help debug stuck auctions
+    Status {},
+    /// View how the input address' last bid ended, even after it has been removed
+    ViewOutcome {
+        /// address whose bid outcome should be displayed
+        address: HumanAddr,
+        /// bidder's viewing key
+        viewing_key: String,
+    },
+    /// displays proof that the input address won the closed auction
+    WinProof {
+        /// address of the winner
+        address: HumanAddr,
+        /// winner's viewing key
+        viewing_key: String,
+    },
     /// returns boolean indicating whether there are any active bids
     HasBids {
         /// address to authenticate as the auction seller
@@ -114,6 +561,51 @@ pub enum QueryMsg {
         /// seller's viewing key
         viewing_key: String,
     },
+    /// lists the addresses authorized to act on the seller's behalf
+    Operators {
+        /// address to authenticate as the auction seller or one of its operators
+        address: HumanAddr,
+        /// viewing key of the address
+        viewing_key: String,
+    },
+    /// displays aggregate statistics of the active bids without revealing the bidders
+    BidStats {
+        /// address to authenticate as the auction seller or one of its operators
+        address: HumanAddr,
+        /// viewing key of the address
+        viewing_key: String,
+    },
+    /// lists the memos attached to the active bids without revealing who sent them, and the
+    /// memos of the winning bids once the auction has closed
+    BidMemos {
+        /// address to authenticate as the auction seller or one of its operators
+        address: HumanAddr,
+        /// viewing key of the address
+        viewing_key: String,
+    },
+    /// displays the auction's complete internal state for debugging a stuck auction
+    ExportAuction {
+        /// address to authenticate as the auction seller
+        address: HumanAddr,
+        /// seller's viewing key
+        viewing_key: String,
+    },
+    /// lists the message board in reverse chronological order.  Only the seller, its operators,
+    /// and addresses that have bid in the auction may read it.  If you specify page size, it
+    /// returns only that number of messages (default is 20).  If you specify the before
+    /// parameter, it will start listing from the first message whose id is less than "before"
+    Messages {
+        /// address to authenticate
+        address: HumanAddr,
+        /// viewing key of the address
+        viewing_key: String,
+        /// optionally only show messages with id less than specified value
+        #[serde(default)]
+        before: Option<u32>,
+        /// optional number of messages to return
+        #[serde(default)]
+        page_size: Option<u32>,
+    },
 }
 
 /// responses to queries
@@ -130,20 +622,102 @@ pub enum QueryAnswer {
         sell_amount: Uint128,
         /// minimum bid that will be accepted
         minimum_bid: Uint128,
+        /// Optional schedule by which the minimum bid declines
+        #[serde(skip_serializing_if = "Option::is_none")]
+        min_bid_decay: Option<MinBidDecayInfo>,
         /// Optional String description of auction
         #[serde(skip_serializing_if = "Option::is_none")]
         description: Option<String>,
+        /// language code of the description if it is a translation
+        #[serde(skip_serializing_if = "Option::is_none")]
+        description_lang: Option<String>,
+        /// language codes of the available translations of the description
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        languages: Vec<String>,
         /// address of auction contract
         auction_address: HumanAddr,
         /// time at which anyone can close the auction
         ends_at: String,
+        /// time at which anyone can close the auction in seconds since epoch 01/01/1970
+        ends_at_timestamp: u64,
+        /// Optional time by which the seller must consign the lot in seconds since epoch
+        /// 01/01/1970
+        #[serde(skip_serializing_if = "Option::is_none")]
+        consign_by: Option<u64>,
+        /// Optional price at which a bidder may buy the lot immediately
+        #[serde(skip_serializing_if = "Option::is_none")]
+        buy_now_price: Option<Uint128>,
+        /// Optional number of seconds the seller has to accept or reject the winning bid when
+        /// someone else finalizes the auction
+        #[serde(skip_serializing_if = "Option::is_none")]
+        seller_confirmation: Option<u64>,
+        /// Optional time by which the seller must accept or reject the winning bid in seconds
+        /// since epoch 01/01/1970, if the auction is awaiting the seller's confirmation
+        #[serde(skip_serializing_if = "Option::is_none")]
+        accept_by: Option<u64>,
+        /// true if the seller has paused new bids
+        bidding_paused: bool,
+        /// how far the auction has progressed toward paying out its bids and lot
+        settlement_phase: SettlementPhase,
+        /// true if the seller is not allowed to bid in the auction
+        no_self_bid: bool,
+        /// true if a bidder may only replace their bid with a higher bid
+        only_increasing_rebids: bool,
+        /// true if bids are backed by allowances instead of being sent to escrow
+        allowance_bids: bool,
+        /// true if the auction has a message board
+        message_board: bool,
+        /// true if bidders may specify a price per whole sell token instead of a total bid
+        unit_price_bids: bool,
+        /// Optional price per whole sell token of the minimum bid, if the auction takes unit
+        /// price bids
+        #[serde(skip_serializing_if = "Option::is_none")]
+        minimum_unit_price: Option<Uint128>,
+        /// Optional limit on the number of distinct bidders
+        #[serde(skip_serializing_if = "Option::is_none")]
+        max_bidders: Option<u32>,
+        /// Optional number of new bidders the auction can still accept, if it limits the number
+        /// of bidders
+        #[serde(skip_serializing_if = "Option::is_none")]
+        bidder_slots_remaining: Option<u32>,
+        /// Optional number of distinct bidders the auction needs to sell
+        #[serde(skip_serializing_if = "Option::is_none")]
+        min_bidders: Option<u32>,
+        /// Optional number of seconds after the closing time during which sealed bids are
+        /// revealed
+        #[serde(skip_serializing_if = "Option::is_none")]
+        reveal_window: Option<u64>,
         /// status of the auction can be "Accepting bids: Tokens to be sold have(not) been
-        /// consigned" or "Closed" (will also state if there are outstanding funds after auction
-        /// closure
+        /// consigned", "Bidding paused by the seller: Tokens to be sold have(not) been consigned",
+        /// "Awaiting the seller's acceptance of the winning bid", "Cancelled", "Rejected by the
+        /// seller", "Closed without enough bidders", or "Closed" (will also state if there are
+        /// outstanding funds after auction closure
         status: String,
         /// If the auction resulted in a swap, this will state the winning bid
         #[serde(skip_serializing_if = "Option::is_none")]
         winning_bid: Option<Uint128>,
+        /// Optional list of additional tokens sold in the same lot
+        #[serde(skip_serializing_if = "Option::is_none")]
+        bundle: Option<Vec<BundleToken>>,
+        /// Optional amount of bid tokens the seller has posted as a deposit
+        #[serde(skip_serializing_if = "Option::is_none")]
+        seller_deposit: Option<Uint128>,
+        /// policy used to pick the winner when the highest bids are tied
+        tie_break: TieBreak,
+        /// how the winner is picked from the active bids
+        settlement: SettlementMode,
+        /// Optional amount a crowdfund must raise to sell the lot
+        #[serde(skip_serializing_if = "Option::is_none")]
+        funding_target: Option<Uint128>,
+        /// index of the auction with the factory
+        factory_index: u32,
+        /// address of the factory that created the auction
+        factory_address: HumanAddr,
+        /// code hash and address of the factory that created the auction
+        factory_info: FactoryInfo,
+        /// warning from the factory if the auction was created with an outdated contract version
+        #[serde(skip_serializing_if = "Option::is_none")]
+        deprecation_notice: Option<String>,
     },
     /// response from view bid attempt
     Bid {
@@ -154,16 +728,352 @@ pub enum QueryAnswer {
         /// Optional amount bid
         #[serde(skip_serializing_if = "Option::is_none")]
         amount_bid: Option<Uint128>,
+        /// Optional price per whole sell token of the amount bid, if the auction takes unit
+        /// price bids
+        #[serde(skip_serializing_if = "Option::is_none")]
+        unit_price: Option<Uint128>,
         /// Optional number of decimals in bid amount
         #[serde(skip_serializing_if = "Option::is_none")]
         bid_decimals: Option<u8>,
+        /// Optional time the bid was placed in seconds since epoch 01/01/1970
+        #[serde(skip_serializing_if = "Option::is_none")]
+        bid_timestamp: Option<u64>,
+        /// Optional time the bid expires in seconds since epoch 01/01/1970
+        #[serde(skip_serializing_if = "Option::is_none")]
+        bid_expiration: Option<u64>,
+        /// Optional memo attached to the bid
+        #[serde(skip_serializing_if = "Option::is_none")]
+        memo: Option<String>,
+    },
+    /// PreviewBid query response
+    PreviewBid {
+        /// true if the bid would become the address' active bid
+        accepted: bool,
+        /// why the bid would be rejected, or what would happen to the current bid
+        message: String,
+        /// minimum bid that would be accepted
+        minimum_bid: Uint128,
+        /// Optional amount of the address' current bid
+        #[serde(skip_serializing_if = "Option::is_none")]
+        current_bid: Option<Uint128>,
+        /// Optional amount of bid tokens that would be returned from escrow
+        #[serde(skip_serializing_if = "Option::is_none")]
+        amount_returned: Option<Uint128>,
+        /// decimal places for bid amounts
+        bid_decimals: u8,
     },
     /// response indicating whether there any active bids
-    HasBids { has_bids: bool },
+    HasBids {
+        /// true if there are any active bids
+        has_bids: bool,
+        /// number of active bids
+        bid_count: u32,
+        /// Optional time of the most recently placed bid in seconds since epoch 01/01/1970
+        #[serde(skip_serializing_if = "Option::is_none")]
+        last_bid_time: Option<u64>,
+    },
+    /// addresses authorized to act on the seller's behalf
+    Operators { operators: Vec<HumanAddr> },
+    /// aggregate statistics of the active bids.  Amounts are rounded down to their two most
+    /// significant digits
+    BidStats {
+        /// number of active bids
+        bid_count: u32,
+        /// Optional lowest active bid
+        #[serde(skip_serializing_if = "Option::is_none")]
+        min_bid: Option<Uint128>,
+        /// Optional median active bid.  The lower of the two middle bids if there is an even
+        /// number of bids
+        #[serde(skip_serializing_if = "Option::is_none")]
+        median_bid: Option<Uint128>,
+        /// Optional highest active bid
+        #[serde(skip_serializing_if = "Option::is_none")]
+        max_bid: Option<Uint128>,
+        /// decimal places for bid amounts
+        bid_decimals: u8,
+        /// Optional time of the most recently placed bid in seconds since epoch 01/01/1970
+        #[serde(skip_serializing_if = "Option::is_none")]
+        last_bid_at: Option<u64>,
+    },
+    /// response from view outcome attempt
+    Outcome {
+        /// success or failure
+        status: ResponseStatus,
+        /// execution description
+        message: String,
+        /// Optional result of the bid
+        #[serde(skip_serializing_if = "Option::is_none")]
+        result: Option<BidResult>,
+        /// Optional amount bid
+        #[serde(skip_serializing_if = "Option::is_none")]
+        amount_bid: Option<Uint128>,
+        /// Optional amount of bid tokens returned to the bidder
+        #[serde(skip_serializing_if = "Option::is_none")]
+        amount_returned: Option<Uint128>,
+        /// Optional number of decimals in bid amounts
+        #[serde(skip_serializing_if = "Option::is_none")]
+        bid_decimals: Option<u8>,
+        /// Optional height of the block in which the bid ended
+        #[serde(skip_serializing_if = "Option::is_none")]
+        block_height: Option<u64>,
+        /// Optional time the bid ended in seconds since epoch 01/01/1970
+        #[serde(skip_serializing_if = "Option::is_none")]
+        block_time: Option<u64>,
+        /// Optional reason the seller gave for rejecting the bid
+        #[serde(skip_serializing_if = "Option::is_none")]
+        reason: Option<String>,
+    },
+    /// Status query response
+    Status {
+        /// true if the auction is closed
+        is_completed: bool,
+        /// true if the auction was cancelled because the lot was not consigned in time
+        is_cancelled: bool,
+        /// true if the auction was force closed because it was left open long after its closing
+        /// time
+        is_force_closed: bool,
+        /// true if the seller rejected the winning bid
+        is_rejected: bool,
+        /// true if the auction is waiting for the seller to accept or reject the winning bid
+        awaiting_acceptance: bool,
+        /// true if the factory has halted the auction
+        is_halted: bool,
+        /// how far the auction has progressed toward paying out its bids and lot
+        settlement_phase: SettlementPhase,
+        /// true if all tokens for sale have been consigned to escrow
+        tokens_consigned: bool,
+        /// true if the seller has posted the full deposit
+        deposit_posted: bool,
+        /// number of addresses with an active bid
+        bidders: u32,
+        /// true if the auction is closed but still holds tokens that should have been returned
+        outstanding_balances: bool,
+        /// version of the auction contract
+        contract_version: String,
+        /// index of the auction with the factory
+        factory_index: u32,
+        /// code hash and address of the factory that created the auction
+        factory_info: FactoryInfo,
+    },
+    /// WinProof query response
+    WinProof {
+        /// success or failure
+        status: ResponseStatus,
+        /// execution description
+        message: String,
+        /// Optional address of the auction
+        #[serde(skip_serializing_if = "Option::is_none")]
+        auction_address: Option<HumanAddr>,
+        /// Optional address of the winner
+        #[serde(skip_serializing_if = "Option::is_none")]
+        winner: Option<HumanAddr>,
+        /// Optional amount of tokens sold
+        #[serde(skip_serializing_if = "Option::is_none")]
+        sell_amount: Option<Uint128>,
+        /// Optional winning bid
+        #[serde(skip_serializing_if = "Option::is_none")]
+        winning_bid: Option<Uint128>,
+        /// Optional number of decimals in winning_bid
+        #[serde(skip_serializing_if = "Option::is_none")]
+        bid_decimals: Option<u8>,
+        /// Optional time the winning bid was placed in seconds since epoch 01/01/1970
+        #[serde(skip_serializing_if = "Option::is_none")]
+        bid_timestamp: Option<u64>,
+        /// Optional time the auction closed in seconds since epoch 01/01/1970.  This matches the
+        /// timestamp of the factory's closed auction record
+        #[serde(skip_serializing_if = "Option::is_none")]
+        closed_at: Option<u64>,
+        /// Optional sha256 hash of "auction_address:winner:sell_amount:winning_bid:closed_at"
+        #[serde(skip_serializing_if = "Option::is_none")]
+        commitment: Option<Binary>,
+    },
+    /// response listing the memos attached to bids
+    BidMemos {
+        /// memos of the active bids in alphabetical order
+        memos: Vec<String>,
+        /// memos of the winning bids, once the auction has closed
+        winner_memos: Vec<String>,
+    },
+    /// complete internal state of the auction.  The callback and allowance keys are omitted
+    ExportAuction {
+        /// address of the auction
+        auction_address: HumanAddr,
+        /// index of the auction with the factory
+        factory_index: u32,
+        /// code hash and address of the factory that created the auction
+        factory_info: FactoryInfo,
+        /// address of the seller
+        seller: HumanAddr,
+        /// addresses authorized to act on the seller's behalf
+        operators: Vec<HumanAddr>,
+        /// code hash and address of the sell token contract
+        sell_contract: ContractInfo,
+        /// sell token decimal places
+        sell_decimals: u8,
+        /// code hash and address of the bid token contract
+        bid_contract: ContractInfo,
+        /// bid token decimal places
+        bid_decimals: u8,
+        /// amount of tokens for sale
+        sell_amount: Uint128,
+        /// amount of tokens for sale currently consigned to auction escrow
+        currently_consigned: Uint128,
+        /// true if all tokens for sale have been consigned to escrow
+        tokens_consigned: bool,
+        /// consignment of each additional token of a bundled lot
+        bundle: Vec<ConsignmentInfo>,
+        /// stored minimum bid, before any decay is applied
+        minimum_bid: Uint128,
+        /// Optional minimum bid decay schedule
+        #[serde(skip_serializing_if = "Option::is_none")]
+        min_bid_decay: Option<MinBidDecayInfo>,
+        /// deposit the seller must post
+        deposit: Uint128,
+        /// amount of the deposit the seller has posted
+        deposit_posted: Uint128,
+        /// Optional price at which a bid immediately wins
+        #[serde(skip_serializing_if = "Option::is_none")]
+        buy_now_price: Option<Uint128>,
+        /// timestamp after which anyone may close the auction
+        ends_at: u64,
+        /// Optional time by which the lot must be consigned
+        #[serde(skip_serializing_if = "Option::is_none")]
+        consign_by: Option<u64>,
+        /// Optional number of seconds the seller has to accept or reject the winning bid
+        #[serde(skip_serializing_if = "Option::is_none")]
+        seller_confirmation: Option<u64>,
+        /// Optional time by which the seller must accept or reject the winning bid
+        #[serde(skip_serializing_if = "Option::is_none")]
+        accept_by: Option<u64>,
+        /// number of addresses with an active bid
+        bidder_count: u32,
+        /// Optional time of the most recently placed bid
+        #[serde(skip_serializing_if = "Option::is_none")]
+        last_bid_at: Option<u64>,
+        /// Optional winning bid, once the auction has closed with a sale
+        #[serde(skip_serializing_if = "Option::is_none")]
+        winning_bid: Option<Uint128>,
+        /// policy used to pick the winner when the highest bids are tied
+        tie_break: TieBreak,
+        /// how the winner is picked from the active bids
+        settlement: SettlementMode,
+        /// Optional amount a crowdfund must raise to sell the lot
+        #[serde(skip_serializing_if = "Option::is_none")]
+        funding_target: Option<Uint128>,
+        /// true if the seller and its operators may not bid
+        no_self_bid: bool,
+        /// true if a bid may only be replaced with a higher bid
+        only_increasing_rebids: bool,
+        /// true if bids may be priced per whole sell token
+        unit_price_bids: bool,
+        /// Optional limit on the number of distinct bidders
+        #[serde(skip_serializing_if = "Option::is_none")]
+        max_bidders: Option<u32>,
+        /// Optional number of distinct bidders the auction needs to sell
+        #[serde(skip_serializing_if = "Option::is_none")]
+        min_bidders: Option<u32>,
+        /// true if the auction closed without a sale because it had too few bidders
+        too_few_bidders: bool,
+        /// Optional number of seconds after the closing time during which sealed bids are
+        /// revealed
+        #[serde(skip_serializing_if = "Option::is_none")]
+        reveal_window: Option<u64>,
+        /// number of sealed commitments that have not been revealed
+        unrevealed_commitments: u32,
+        /// true if the auction has a message board
+        message_board: bool,
+        /// true if bids are backed by allowances instead of escrow
+        allowance_bids: bool,
+        /// true if the auction is closed
+        is_completed: bool,
+        /// true if the auction was cancelled because the lot was not consigned in time
+        is_cancelled: bool,
+        /// true if the auction was force closed
+        is_force_closed: bool,
+        /// true if the factory has halted the auction
+        is_halted: bool,
+        /// true if the seller rejected the winning bid
+        is_rejected: bool,
+        /// Optional notice that the auction contract has been deprecated
+        #[serde(skip_serializing_if = "Option::is_none")]
+        deprecation_notice: Option<String>,
+    },
+    /// response listing the message board
+    Messages {
+        /// messages in reverse chronological order
+        messages: Vec<BoardMessage>,
+        /// total number of messages posted
+        count: u32,
+    },
     /// Viewing Key Error
     ViewingKeyError { error: String },
 }
 
+/// action requested by including it as the msg of the Send that sends tokens to the auction.  If
+/// no msg is sent, tokens for sale are consigned and bid tokens place a bid (or post the deposit
+/// if sent by the seller)
+#[derive(Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ReceiveMsg {
+    /// consign the tokens for sale, or post the deposit if bid tokens are sent by the seller
+    Consign {},
+    /// place a bid
+    PlaceBid {
+        /// optional time after which the bid is no longer valid and will not be considered when
+        /// the auction closes.  Timestamp is in seconds since epoch 01/01/1970
+        #[serde(default)]
+        expiration: Option<u64>,
+        /// optional maximum amount to bid.  Any tokens sent above it are returned
+        #[serde(default)]
+        max_bid: Option<Uint128>,
+        /// optional price per whole sell token, if the auction takes unit price bids.  The bid
+        /// is the total cost of the lot at this price, and any tokens sent above it are returned
+        #[serde(default)]
+        unit_price: Option<Uint128>,
+        /// optional private memo for the seller, such as settlement instructions.  It is only
+        /// visible to the bidder and the seller
+        #[serde(default)]
+        memo: Option<String>,
+        /// optional address that referred the bidder.  If the bid wins, the referrer is paid a
+        /// share of the factory's creation fee
+        #[serde(default)]
+        referrer: Option<HumanAddr>,
+    },
+    /// buy the lot at the auction's buy now price and close the auction.  Any tokens sent above
+    /// the buy now price are returned
+    BuyNow {},
+    /// place a sealed bid in an auction with a reveal window.  The tokens sent are the deposit,
+    /// which must cover the bid when it is revealed
+    CommitBid {
+        /// sha256 hash of the string "*bidder_address*:*amount*:*salt*"
+        commitment: Binary,
+    },
+    /// add the tokens for sale to the lot, increasing the sell amount.  Only the seller may
+    /// increase the lot, and only after the sell amount has been fully consigned and before any
+    /// bids have been placed
+    IncreaseLot {},
+}
+
+/// how a bid that is no longer active ended
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum BidResult {
+    /// the bid won the auction
+    Won,
+    /// the bid did not win and was returned
+    Lost,
+    /// the bidder retracted the bid
+    Retracted,
+    /// the bid expired before the auction closed and was returned
+    Expired,
+    /// the bid was returned because the seller raised the minimum bid above it
+    Refunded,
+    /// the bid would have won, but its allowance no longer covered it when the auction closed
+    Defaulted,
+    /// the seller rejected the bid and returned it
+    Rejected,
+}
+
 /// token's contract address and TokenInfo response
 #[derive(Serialize, Deserialize, Debug, JsonSchema)]
 pub struct Token {
@@ -173,6 +1083,89 @@ pub struct Token {
     pub token_info: TokenInfo,
 }
 
+/// code hash and address of the factory an auction is linked to
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
+pub struct FactoryInfo {
+    /// factory's code hash string
+    pub code_hash: String,
+    /// factory's address
+    pub address: HumanAddr,
+}
+
+/// a translation of the auction description
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug, PartialEq)]
+pub struct LocalizedDescription {
+    /// language code, such as "en" or "zh-Hant"
+    pub lang: String,
+    /// description in that language
+    pub text: String,
+}
+
+/// a message posted to the auction's message board.  Authors other than the seller are not
+/// revealed, so reading the board does not reveal who is bidding
+#[derive(Serialize, Deserialize, Debug, JsonSchema, PartialEq)]
+pub struct BoardMessage {
+    /// id of the message
+    pub id: u32,
+    /// true if the seller or one of its operators posted the message
+    pub from_seller: bool,
+    /// true if the address reading the board posted the message
+    pub is_yours: bool,
+    /// text of the message
+    pub text: String,
+    /// time the message was posted in seconds since epoch 01/01/1970
+    pub timestamp: u64,
+}
+
+/// the status of a bid sent to a bidder who registered for a notification when the auction
+/// closed.  It is serialized as JSON, padded with spaces, and encrypted with the bidder's
+/// notification key
+#[derive(Serialize, Deserialize, Debug, JsonSchema, PartialEq)]
+pub struct Notification {
+    /// how the bid ended
+    pub result: BidResult,
+    /// amount of the bid
+    pub amount: Uint128,
+    /// amount of bid tokens returned to the bidder
+    pub returned: Uint128,
+    /// winning bid, if the auction sold
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub winning_bid: Option<Uint128>,
+}
+
+/// an additional token of a bundled lot and the amount being sold
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
+pub struct BundleToken {
+    /// token address and TokenInfo query response
+    pub token: Token,
+    /// amount of tokens being sold
+    pub amount: Uint128,
+}
+
+/// an additional token of a bundled lot and its consignment progress
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
+pub struct ConsignmentInfo {
+    /// code hash and address of the token contract
+    pub contract: ContractInfo,
+    /// amount of tokens being sold
+    pub amount: Uint128,
+    /// token decimal places
+    pub decimals: u8,
+    /// amount of tokens currently consigned to auction escrow
+    pub currently_consigned: Uint128,
+}
+
+/// the total amount of a token returned by return_all
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
+pub struct ReturnedAmount {
+    /// contract address of token
+    pub contract_address: HumanAddr,
+    /// amount of tokens returned
+    pub amount: Uint128,
+    /// token decimal places
+    pub decimals: u8,
+}
+
 /// success or failure response
 #[derive(Serialize, Deserialize, Debug, JsonSchema)]
 pub enum ResponseStatus {
@@ -180,11 +1173,26 @@ pub enum ResponseStatus {
     Failure,
 }
 
-/// Responses from handle functions
+/// Responses from handle functions.
+///
+/// Tokens sent with a Receive that the auction can not use are transferred back with a Failure
+/// status and `amount_returned` in one of these answers:
+///
+/// * sell token sent with an increase_lot msg: IncreaseLot
+/// * any other sell token or bundled token, including one sent with a bid msg: Consign
+/// * bid token sent with a consign msg: Deposit
+/// * bid token sent with a commit_bid msg, or with any msg to a sealed auction: CommitBid
+/// * any other bid token, including a seller's send without a msg, an increase_lot msg, invalid
+///   bid options, or a send to an allowance auction: Bid
+///
+/// A halted auction returns every send with the same answers.  Only a Receive whose msg can not
+/// be parsed, or that comes from a token the auction does not hold, is an error, and the token
+/// transfer is reverted
 #[derive(Serialize, Deserialize, Debug, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum HandleAnswer {
-    /// response from consign attempt
+    /// response from consign attempt.  Failure if more tokens are needed, or if the
+    /// consignment was returned
     Consign {
         /// success or failure
         status: ResponseStatus,
@@ -200,8 +1208,35 @@ pub enum HandleAnswer {
         amount_returned: Option<Uint128>,
         /// decimal places for amounts
         sell_decimals: u8,
+        /// Optional amount consigned as a decimal string
+        #[serde(skip_serializing_if = "Option::is_none")]
+        amount_consigned_humanized: Option<String>,
+        /// Optional amount that still needs to be consigned as a decimal string
+        #[serde(skip_serializing_if = "Option::is_none")]
+        amount_needed_humanized: Option<String>,
+        /// Optional amount of tokens returned from escrow as a decimal string
+        #[serde(skip_serializing_if = "Option::is_none")]
+        amount_returned_humanized: Option<String>,
+    },
+    /// response from the seller posting a deposit.  Failure if more tokens are needed, or if the
+    /// tokens were returned
+    Deposit {
+        /// success or failure
+        status: ResponseStatus,
+        /// execution description
+        message: String,
+        /// amount of the deposit posted
+        amount_posted: Uint128,
+        /// Optional amount that still needs to be posted
+        #[serde(skip_serializing_if = "Option::is_none")]
+        amount_needed: Option<Uint128>,
+        /// Optional amount of tokens returned from escrow
+        #[serde(skip_serializing_if = "Option::is_none")]
+        amount_returned: Option<Uint128>,
+        /// decimal places for amounts
+        bid_decimals: u8,
     },
-    /// response from bid attempt
+    /// response from bid or buy now attempt.  Failure if the bid did not become active
     Bid {
         /// success or failure
         status: ResponseStatus,
@@ -216,11 +1251,27 @@ pub enum HandleAnswer {
         /// Optional amount bid
         #[serde(skip_serializing_if = "Option::is_none")]
         amount_bid: Option<Uint128>,
+        /// Optional price per whole sell token of the amount bid, if the auction takes unit
+        /// price bids
+        #[serde(skip_serializing_if = "Option::is_none")]
+        unit_price: Option<Uint128>,
         /// Optional amount of tokens returned from escrow
         #[serde(skip_serializing_if = "Option::is_none")]
         amount_returned: Option<Uint128>,
         /// decimal places for bid amounts
         bid_decimals: u8,
+        /// Optional amount of previous bid returned from escrow as a decimal string
+        #[serde(skip_serializing_if = "Option::is_none")]
+        previous_bid_humanized: Option<String>,
+        /// Optional minimum bid amount as a decimal string
+        #[serde(skip_serializing_if = "Option::is_none")]
+        minimum_bid_humanized: Option<String>,
+        /// Optional amount bid as a decimal string
+        #[serde(skip_serializing_if = "Option::is_none")]
+        amount_bid_humanized: Option<String>,
+        /// Optional amount of tokens returned from escrow as a decimal string
+        #[serde(skip_serializing_if = "Option::is_none")]
+        amount_returned_humanized: Option<String>,
     },
     /// response from closing the auction
     CloseAuction {
@@ -231,6 +1282,17 @@ pub enum HandleAnswer {
         /// Optional amount of winning bid
         #[serde(skip_serializing_if = "Option::is_none")]
         winning_bid: Option<Uint128>,
+        /// Optional price per whole sell token of the winning bid, if the auction takes unit
+        /// price bids
+        #[serde(skip_serializing_if = "Option::is_none")]
+        winning_unit_price: Option<Uint128>,
+        /// Optional mechanism that picked the winning bid, if the auction closed with a sale
+        #[serde(skip_serializing_if = "Option::is_none")]
+        settlement: Option<SettlementMode>,
+        /// Optional number of distinct bidders the auction needed but did not reach, if it
+        /// closed without a sale because of it
+        #[serde(skip_serializing_if = "Option::is_none")]
+        unmet_min_bidders: Option<u32>,
         /// Optional number of bid token decimals if there was a winning bid
         #[serde(skip_serializing_if = "Option::is_none")]
         bid_decimals: Option<u8>,
@@ -243,6 +1305,60 @@ pub enum HandleAnswer {
         /// Optional amount of bid tokens transferred to auction closer
         #[serde(skip_serializing_if = "Option::is_none")]
         bid_tokens_received: Option<Uint128>,
+        /// Optional amount of winning bid as a decimal string
+        #[serde(skip_serializing_if = "Option::is_none")]
+        winning_bid_humanized: Option<String>,
+        /// Optional amount of sell tokens transferred to auction closer as a decimal string
+        #[serde(skip_serializing_if = "Option::is_none")]
+        sell_tokens_received_humanized: Option<String>,
+        /// Optional amount of bid tokens transferred to auction closer as a decimal string
+        #[serde(skip_serializing_if = "Option::is_none")]
+        bid_tokens_received_humanized: Option<String>,
+    },
+    /// response from returning the funds held by a closed auction
+    ReturnAll {
+        /// success or failure
+        status: ResponseStatus,
+        /// execution description
+        message: String,
+        /// number of bids refunded
+        bids_returned: u32,
+        /// number of bids still held, which another return_all will return
+        bids_remaining: u32,
+        /// true if consigned tokens were returned to the seller
+        consignment_returned: bool,
+        /// total amount returned of each token
+        amounts: Vec<ReturnedAmount>,
+    },
+    /// response from committing to a sealed bid.  Failure if the deposit was returned
+    CommitBid {
+        /// success or failure
+        status: ResponseStatus,
+        /// execution description
+        message: String,
+        /// Optional amount deposited with the commitment
+        #[serde(skip_serializing_if = "Option::is_none")]
+        deposit: Option<Uint128>,
+        /// Optional amount of tokens returned from escrow
+        #[serde(skip_serializing_if = "Option::is_none")]
+        amount_returned: Option<Uint128>,
+        /// decimal places for bid amounts
+        bid_decimals: u8,
+    },
+    /// response from revealing a sealed bid
+    RevealBid {
+        /// success or failure
+        status: ResponseStatus,
+        /// execution description
+        message: String,
+        /// Optional amount of the revealed bid, if it is now active
+        #[serde(skip_serializing_if = "Option::is_none")]
+        amount_bid: Option<Uint128>,
+        /// Optional amount of the deposit returned from escrow
+        #[serde(skip_serializing_if = "Option::is_none")]
+        amount_returned: Option<Uint128>,
+        /// decimal places for bid amounts
+        bid_decimals: u8,
     },
     /// response from attempt to retract bid
     RetractBid {
@@ -257,6 +1373,55 @@ pub enum HandleAnswer {
         #[serde(skip_serializing_if = "Option::is_none")]
         bid_decimals: Option<u8>,
     },
+    /// response from rejecting a bid
+    RejectBid {
+        /// success or failure
+        status: ResponseStatus,
+        /// execution description
+        message: String,
+    },
+    /// response from pausing or resuming bidding
+    PauseBidding {
+        /// success or failure
+        status: ResponseStatus,
+        /// true if new bids are paused
+        bidding_paused: bool,
+    },
+    /// response from transferring the seller rights
+    TransferSellerRights {
+        /// success or failure
+        status: ResponseStatus,
+        /// address of the new seller
+        seller: HumanAddr,
+    },
+    /// response from adding or removing operators
+    Operators {
+        /// success or failure
+        status: ResponseStatus,
+        /// addresses currently authorized to act on the seller's behalf
+        operators: Vec<HumanAddr>,
+    },
+    /// response from a factory lifecycle action
+    FactoryAction {
+        /// success or failure
+        status: ResponseStatus,
+        /// execution description
+        message: String,
+    },
+    /// response from increasing the lot.  Failure if the tokens were returned
+    IncreaseLot {
+        /// success or failure
+        status: ResponseStatus,
+        /// execution description
+        message: String,
+        /// current sell amount
+        sell_amount: Uint128,
+        /// Optional amount of tokens returned from escrow
+        #[serde(skip_serializing_if = "Option::is_none")]
+        amount_returned: Option<Uint128>,
+        /// decimal places for the sell amount
+        sell_decimals: u8,
+    },
     /// response from attempt to change minimum bid
     ChangeMinimumBid {
         /// success or failure
@@ -265,6 +1430,53 @@ pub enum HandleAnswer {
         minimum_bid: Uint128,
         /// decimal places for minimum bid
         bid_decimals: u8,
+        /// Optional number of bids below the new minimum that were returned
+        #[serde(skip_serializing_if = "Option::is_none")]
+        bids_refunded: Option<u32>,
+    },
+    /// response from changing the sell amount
+    ChangeSellAmount {
+        /// success or failure
+        status: ResponseStatus,
+        /// new sell amount
+        sell_amount: Uint128,
+        /// decimal places for the sell amount
+        sell_decimals: u8,
+    },
+    /// response from changing the description
+    SetDescription {
+        /// success or failure
+        status: ResponseStatus,
+        /// language codes of the available translations of the description
+        languages: Vec<String>,
+    },
+    /// response from posting to the message board
+    PostMessage {
+        /// success or failure
+        status: ResponseStatus,
+        /// id of the posted message
+        id: u32,
+    },
+    /// response from registering for a close notification
+    RegisterNotification {
+        /// success or failure
+        status: ResponseStatus,
+        /// true if the bidder is now registered for a notification
+        registered: bool,
+    },
+    /// response from retrying the settlement with the next best bid
+    RetrySettlement {
+        /// success or failure
+        status: ResponseStatus,
+        /// execution description
+        message: String,
+        /// address whose winning bid was disqualified
+        defaulted: HumanAddr,
+        /// address of the bidder who is now winning, if any bids remain
+        #[serde(skip_serializing_if = "Option::is_none")]
+        promoted: Option<HumanAddr>,
+        /// number of bids remaining, including the promoted bid
+        standby: u32,
     },
 }
 
@@ -275,16 +1487,56 @@ pub struct ContractInfo {
     pub code_hash: String,
     /// contract's address
     pub address: HumanAddr,
+    /// id of the token if this is a SNIP-1155 contract.  If not specified, the contract is treated
+    /// as a SNIP-20
+    #[serde(default)]
+    pub token_id: Option<String>,
 }
 
 impl ContractInfo {
+    /// Returns true if this is the token sent from the specified contract
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - a reference to the address of the contract that sent the tokens
+    /// * `token_id` - optional id of the tokens if they were sent by a SNIP-1155 contract
+    pub fn is_token(&self, address: &HumanAddr, token_id: Option<&str>) -> bool {
+        self.address == *address && self.token_id.as_deref() == token_id
+    }
+
+    /// Returns true if both contracts represent the same token
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - a reference to the other token's ContractInfo
+    pub fn is_same_token(&self, other: &ContractInfo) -> bool {
+        self.is_token(&other.address, other.token_id.as_deref())
+    }
+
     /// Returns a StdResult<CosmosMsg> used to execute Transfer
     ///
     /// # Arguments
     ///
+    /// * `escrow` - a reference to the address holding the tokens (used by SNIP-1155 transfers)
     /// * `recipient` - address tokens are to be sent to
     /// * `amount` - Uint128 amount of tokens to send
-    pub fn transfer_msg(&self, recipient: HumanAddr, amount: Uint128) -> StdResult<CosmosMsg> {
+    pub fn transfer_msg(
+        &self,
+        escrow: &HumanAddr,
+        recipient: HumanAddr,
+        amount: Uint128,
+    ) -> StdResult<CosmosMsg> {
+        if let Some(token_id) = self.token_id.as_ref() {
+            return Snip1155HandleMsg::Transfer {
+                token_id: token_id.clone(),
+                from: escrow.clone(),
+                recipient,
+                amount,
+                memo: None,
+                padding: None,
+            }
+            .to_cosmos_msg(self.code_hash.clone(), self.address.clone(), None);
+        }
         transfer_msg(
             recipient,
             amount,
@@ -295,12 +1547,108 @@ impl ContractInfo {
         )
     }
 
+    /// Returns a StdResult<CosmosMsg> used to execute TransferFrom.  Only SNIP-20 tokens support
+    /// allowances
+    ///
+    /// # Arguments
+    ///
+    /// * `owner` - address tokens are to be sent from
+    /// * `recipient` - address tokens are to be sent to
+    /// * `amount` - Uint128 amount of tokens to send
+    pub fn transfer_from_msg(
+        &self,
+        owner: HumanAddr,
+        recipient: HumanAddr,
+        amount: Uint128,
+    ) -> StdResult<CosmosMsg> {
+        transfer_from_msg(
+            owner,
+            recipient,
+            amount,
+            None,
+            BLOCK_SIZE,
+            self.code_hash.clone(),
+            self.address.clone(),
+        )
+    }
+
+    /// Returns a StdResult<CosmosMsg> used to execute SetViewingKey
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - String holding the viewing key to set
+    pub fn set_viewing_key_msg(&self, key: String) -> StdResult<CosmosMsg> {
+        set_viewing_key_msg(
+            key,
+            None,
+            BLOCK_SIZE,
+            self.code_hash.clone(),
+            self.address.clone(),
+        )
+    }
+
+    /// Returns a StdResult<Allowance> from performing Allowance query
+    ///
+    /// # Arguments
+    ///
+    /// * `querier` - a reference to the Querier dependency of the querying contract
+    /// * `owner` - address that gave the allowance
+    /// * `spender` - address allowed to spend the owner's tokens
+    /// * `key` - viewing key of either the owner or the spender
+    pub fn allowance_query<Q: Querier>(
+        &self,
+        querier: &Q,
+        owner: HumanAddr,
+        spender: HumanAddr,
+        key: String,
+    ) -> StdResult<Allowance> {
+        allowance_query(
+            querier,
+            owner,
+            spender,
+            key,
+            BLOCK_SIZE,
+            self.code_hash.clone(),
+            self.address.clone(),
+        )
+    }
+
+    /// Returns a StdResult<Balance> from performing Balance query
+    ///
+    /// # Arguments
+    ///
+    /// * `querier` - a reference to the Querier dependency of the querying contract
+    /// * `address` - address whose balance is queried
+    /// * `key` - viewing key of the address
+    pub fn balance_query<Q: Querier>(
+        &self,
+        querier: &Q,
+        address: HumanAddr,
+        key: String,
+    ) -> StdResult<Balance> {
+        balance_query(
+            querier,
+            address,
+            key,
+            BLOCK_SIZE,
+            self.code_hash.clone(),
+            self.address.clone(),
+        )
+    }
+
     /// Returns a StdResult<CosmosMsg> used to execute RegisterReceive
     ///
     /// # Arguments
     ///
     /// * `code_hash` - String holding code hash contract to be called when sent tokens
     pub fn register_receive_msg(&self, code_hash: String) -> StdResult<CosmosMsg> {
+        if self.token_id.is_some() {
+            return Snip1155HandleMsg::RegisterReceive {
+                code_hash,
+                padding: None,
+            }
+            .to_cosmos_msg(self.code_hash.clone(), self.address.clone(), None);
+        }
         register_receive_msg(
             code_hash,
             None,
@@ -316,6 +1664,13 @@ impl ContractInfo {
     ///
     /// * `querier` - a reference to the Querier dependency of the querying contract
     pub fn token_info_query<Q: Querier>(&self, querier: &Q) -> StdResult<TokenInfo> {
+        if let Some(token_id) = self.token_id.as_ref() {
+            let resp: Snip1155PublicInfoWrapper = Snip1155QueryMsg::TokenIdPublicInfo {
+                token_id: token_id.clone(),
+            }
+            .query(querier, self.code_hash.clone(), self.address.clone())?;
+            return Ok(resp.token_id_public_info.into_token_info());
+        }
         token_info_query(
             querier,
             BLOCK_SIZE,
@@ -324,3 +1679,85 @@ impl ContractInfo {
         )
     }
 }
+
+/// the SNIP-1155 handle messages the auction will call
+#[derive(Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Snip1155HandleMsg {
+    /// transfer tokens the auction is holding
+    Transfer {
+        token_id: String,
+        from: HumanAddr,
+        recipient: HumanAddr,
+        amount: Uint128,
+        memo: Option<String>,
+        padding: Option<String>,
+    },
+    /// register the auction's code hash so it will be called when sent tokens
+    RegisterReceive {
+        code_hash: String,
+        padding: Option<String>,
+    },
+}
+
+impl HandleCallback for Snip1155HandleMsg {
+    const BLOCK_SIZE: usize = BLOCK_SIZE;
+}
+
+/// the SNIP-1155 queries the auction will call
+#[derive(Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Snip1155QueryMsg {
+    /// public info of a token id
+    TokenIdPublicInfo { token_id: String },
+}
+
+impl Query for Snip1155QueryMsg {
+    const BLOCK_SIZE: usize = BLOCK_SIZE;
+}
+
+/// SNIP-1155 token configuration (only the fields used by the auction)
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Snip1155TokenConfig {
+    Fungible { decimals: u8 },
+    Nft {},
+}
+
+/// SNIP-1155 token id info (only the fields used by the auction)
+#[derive(Deserialize)]
+pub struct Snip1155TokenIdInfo {
+    pub name: String,
+    pub symbol: String,
+    pub token_config: Snip1155TokenConfig,
+}
+
+/// SNIP-1155 public info of a token id
+#[derive(Deserialize)]
+pub struct Snip1155PublicInfo {
+    pub token_id_info: Snip1155TokenIdInfo,
+    #[serde(default)]
+    pub total_supply: Option<Uint128>,
+}
+
+impl Snip1155PublicInfo {
+    /// Returns TokenInfo in the same format used for SNIP-20 tokens
+    pub fn into_token_info(self) -> TokenInfo {
+        let decimals = match self.token_id_info.token_config {
+            Snip1155TokenConfig::Fungible { decimals } => decimals,
+            Snip1155TokenConfig::Nft {} => 0,
+        };
+        TokenInfo {
+            name: self.token_id_info.name,
+            symbol: self.token_id_info.symbol,
+            decimals,
+            total_supply: self.total_supply,
+        }
+    }
+}
+
+/// TokenIdPublicInfo wrapper struct
+#[derive(Deserialize)]
+pub struct Snip1155PublicInfoWrapper {
+    pub token_id_public_info: Snip1155PublicInfo,
+}
@@ -0,0 +1,331 @@
+use cosmwasm_std::{
+    log, to_binary, Api, Binary, Env, Extern, HandleResponse, HandleResult, HumanAddr,
+    LogAttribute, Querier, ReadonlyStorage, StdError, StdResult, Storage, Uint128,
+};
+
+use cosmwasm_storage::{PrefixedStorage, ReadonlyPrefixedStorage};
+
+use serde_json_wasm as serde_json;
+
+use auction_common::crypto::sha_256;
+
+use crate::bidders::is_bidder;
+use crate::contract::{CONFIG_KEY, PREFIX_OUTCOMES};
+use crate::msg::{HandleAnswer, Notification, ResponseStatus::Success};
+use crate::state::{load, may_load, Outcome, State};
+
+/// prefix for storage of the bidders' notification keys
+pub const PREFIX_NOTIFY_KEYS: &[u8] = b"notifykeys";
+
+/// length of a notification key in bytes
+pub const NOTIFY_KEY_LEN: usize = 32;
+
+/// length in bytes of the tag that lets a bidder's client find its notification
+pub const NOTIFY_TAG_LEN: usize = 8;
+
+/// size in bytes that a notification is padded to before it is encrypted, so every notification
+/// in the close logs has the same length
+pub const NOTIFICATION_SIZE: usize = 192;
+
+/// Returns HandleResult
+///
+/// registers or cancels a bidder's notification key
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `key` - optional notification key
+pub fn try_register_notification<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    key: Option<Binary>,
+) -> HandleResult {
+    let state: State = load(&deps.storage, CONFIG_KEY)?;
+    if state.is_completed {
+        return Err(StdError::generic_err("The auction has already closed"));
+    }
+    let bidder = deps.api.canonical_address(&env.message.sender)?;
+    if !is_bidder(&deps.storage, bidder.as_slice())? {
+        return Err(StdError::generic_err(
+            "Only bidders with an active bid may register for a notification",
+        ));
+    }
+    let mut store = PrefixedStorage::new(PREFIX_NOTIFY_KEYS, &mut deps.storage);
+    let registered = if let Some(notify_key) = key.as_ref() {
+        if notify_key.len() != NOTIFY_KEY_LEN {
+            return Err(StdError::generic_err(format!(
+                "A notification key must be {} bytes",
+                NOTIFY_KEY_LEN
+            )));
+        }
+        store.set(bidder.as_slice(), notify_key.as_slice());
+        true
+    } else {
+        store.remove(bidder.as_slice());
+        false
+    };
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: Some(to_binary(&HandleAnswer::RegisterNotification {
+            status: Success,
+            registered,
+        })?),
+    })
+}
+
+/// Returns StdResult<Vec<LogAttribute>>
+///
+/// builds the encrypted notifications for the registered bidders whose bids were settled when
+/// the auction closed, and removes their registrations.  The notifications are sorted by their
+/// ciphertext, so their order does not reveal how the bids ranked
+///
+/// # Arguments
+///
+/// * `storage` - mutable reference to the contract's storage
+/// * `auction` - a reference to the auction's address
+/// * `winning_bid` - winning bid, if the auction sold
+/// * `closed` - canonical addresses of the bidders whose outcomes were just saved
+pub fn notification_logs<S: Storage>(
+    storage: &mut S,
+    auction: &HumanAddr,
+    winning_bid: Option<Uint128>,
+    closed: &[Vec<u8>],
+) -> StdResult<Vec<LogAttribute>> {
+    let mut sealed = Vec::new();
+    for bidder in closed {
+        let key = match ReadonlyPrefixedStorage::new(PREFIX_NOTIFY_KEYS, &*storage).get(bidder) {
+            Some(k) => k,
+            None => continue,
+        };
+        let outcome: Option<Outcome> = may_load(
+            &ReadonlyPrefixedStorage::new(PREFIX_OUTCOMES, &*storage),
+            bidder,
+        )?;
+        if let Some(outcome) = outcome {
+            let mut json = serde_json::to_string(&Notification {
+                result: outcome.result,
+                amount: Uint128(outcome.amount),
+                returned: Uint128(outcome.returned),
+                winning_bid,
+            })
+            .map_err(|e| StdError::generic_err(e.to_string()))?;
+            if json.len() < NOTIFICATION_SIZE {
+                json.push_str(&" ".repeat(NOTIFICATION_SIZE - json.len()));
+            }
+            sealed.push(seal(&key, auction, json.as_bytes()));
+        }
+        PrefixedStorage::new(PREFIX_NOTIFY_KEYS, storage).remove(bidder);
+    }
+    sealed.sort();
+
+    Ok(sealed
+        .into_iter()
+        .map(|s| log("notification", Binary(s).to_base64()))
+        .collect())
+}
+
+/// Returns [u8; NOTIFY_TAG_LEN]
+///
+/// the tag a bidder's client looks for at the start of a notification
+///
+/// # Arguments
+///
+/// * `key` - the bidder's notification key
+/// * `auction` - a reference to the auction's address
+pub fn notification_tag(key: &[u8], auction: &HumanAddr) -> [u8; NOTIFY_TAG_LEN] {
+    let hash = sha_256(&[b"tag".as_ref(), key, auction.0.as_bytes()].concat());
+    let mut tag = [0u8; NOTIFY_TAG_LEN];
+    tag.copy_from_slice(&hash[..NOTIFY_TAG_LEN]);
+    tag
+}
+
+/// Returns Vec<u8>
+///
+/// prefixes the tag to the text XORed with a keystream derived from the notification key and
+/// the auction address.  An auction only closes once, so the keystream is never reused even if
+/// a bidder uses the same key in several auctions.  Applying the keystream again decrypts it
+///
+/// # Arguments
+///
+/// * `key` - the bidder's notification key
+/// * `auction` - a reference to the auction's address
+/// * `text` - the padded notification
+fn seal(key: &[u8], auction: &HumanAddr, text: &[u8]) -> Vec<u8> {
+    let mut sealed = notification_tag(key, auction).to_vec();
+    sealed.extend_from_slice(&apply_keystream(key, auction, text));
+    sealed
+}
+
+/// Returns Vec<u8>
+///
+/// XORs the bytes with a keystream of SHA-256 hashes of the key, auction, and a block counter
+///
+/// # Arguments
+///
+/// * `key` - the bidder's notification key
+/// * `auction` - a reference to the auction's address
+/// * `bytes` - the bytes to encrypt or decrypt
+fn apply_keystream(key: &[u8], auction: &HumanAddr, bytes: &[u8]) -> Vec<u8> {
+    bytes
+        .chunks(32)
+        .enumerate()
+        .flat_map(|(i, chunk)| {
+            let counter = (i as u32).to_be_bytes();
+            let pad = sha_256(&[b"pad".as_ref(), key, auction.0.as_bytes(), &counter].concat());
+            chunk
+                .iter()
+                .zip(pad.iter())
+                .map(|(b, p)| b ^ p)
+                .collect::<Vec<u8>>()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::contract::tests::default_init_msg;
+    use crate::contract::{handle, init};
+    use crate::msg::{BidResult, HandleMsg};
+    use cosmwasm_std::from_binary;
+    use cosmwasm_std::testing::{mock_dependencies, mock_env, MockApi, MockQuerier, MockStorage};
+
+    fn init_helper() -> Extern<MockStorage, MockApi, MockQuerier> {
+        let mut deps = mock_dependencies(20, &[]);
+        let mut env = mock_env("factory", &[]);
+        env.block.time = 0;
+        init(&mut deps, env, default_init_msg()).unwrap();
+        deps
+    }
+
+    fn env_at(sender: &str, time: u64) -> Env {
+        let mut env = mock_env(sender, &[]);
+        env.block.time = time;
+        env
+    }
+
+    fn send(
+        deps: &mut Extern<MockStorage, MockApi, MockQuerier>,
+        token: &str,
+        from: &str,
+        amount: u128,
+    ) {
+        let handle_msg = HandleMsg::Receive {
+            sender: HumanAddr(from.to_string()),
+            from: HumanAddr(from.to_string()),
+            amount: Uint128(amount),
+            msg: None,
+        };
+        handle(deps, env_at(token, 500), handle_msg).unwrap();
+    }
+
+    fn register(
+        deps: &mut Extern<MockStorage, MockApi, MockQuerier>,
+        sender: &str,
+        key: Option<Vec<u8>>,
+    ) -> StdResult<HandleResponse> {
+        let handle_msg = HandleMsg::RegisterNotification {
+            key: key.map(Binary),
+        };
+        handle(deps, env_at(sender, 500), handle_msg)
+    }
+
+    /// decrypts the notification tagged for the key, the way a bidder's client would
+    fn open(logs: &[LogAttribute], key: &[u8]) -> Option<Notification> {
+        let auction = HumanAddr("cosmos2contract".to_string());
+        let tag = notification_tag(key, &auction);
+        logs.iter()
+            .filter(|l| l.key.trim_end() == "notification")
+            .map(|l| Binary::from_base64(l.value.trim_end()).unwrap().0)
+            .find(|s| s[..NOTIFY_TAG_LEN] == tag)
+            .map(|s| {
+                let text = apply_keystream(key, &auction, &s[NOTIFY_TAG_LEN..]);
+                serde_json::from_str(String::from_utf8(text).unwrap().trim_end()).unwrap()
+            })
+    }
+
+    #[test]
+    fn test_notifications() {
+        let mut deps = init_helper();
+        send(&mut deps, "selladdr", "alice", 10);
+
+        // only bidders may register
+        let error = register(&mut deps, "bob", Some(vec![1; NOTIFY_KEY_LEN])).unwrap_err();
+        assert!(error.to_string().contains("Only bidders"));
+        send(&mut deps, "bidaddr", "bob", 100);
+        send(&mut deps, "bidaddr", "carol", 200);
+        send(&mut deps, "bidaddr", "dave", 50);
+        let error = register(&mut deps, "bob", Some(vec![1; 16])).unwrap_err();
+        assert!(error.to_string().contains("must be 32 bytes"));
+        let resp = register(&mut deps, "bob", Some(vec![1; NOTIFY_KEY_LEN])).unwrap();
+        let answer: HandleAnswer = from_binary(&resp.data.unwrap()).unwrap();
+        assert!(matches!(
+            answer,
+            HandleAnswer::RegisterNotification {
+                registered: true,
+                ..
+            }
+        ));
+        register(&mut deps, "carol", Some(vec![2; NOTIFY_KEY_LEN])).unwrap();
+        // a registration can be cancelled
+        register(&mut deps, "dave", Some(vec![3; NOTIFY_KEY_LEN])).unwrap();
+        register(&mut deps, "dave", None).unwrap();
+
+        let finalize = HandleMsg::Finalize {
+            new_ends_at: None,
+            new_minimum_bid: None,
+        };
+        let resp = handle(&mut deps, env_at("alice", 1001), finalize).unwrap();
+        // every notification has the same padded length
+        let sealed: Vec<&LogAttribute> = resp
+            .log
+            .iter()
+            .filter(|l| l.key.trim_end() == "notification")
+            .collect();
+        assert_eq!(sealed.len(), 2);
+        assert_eq!(sealed[0].value.len(), sealed[1].value.len());
+        assert_eq!(
+            open(&resp.log, &[1; NOTIFY_KEY_LEN]),
+            Some(Notification {
+                result: BidResult::Lost,
+                amount: Uint128(100),
+                returned: Uint128(100),
+                winning_bid: Some(Uint128(200)),
+            })
+        );
+        assert_eq!(
+            open(&resp.log, &[2; NOTIFY_KEY_LEN]),
+            Some(Notification {
+                result: BidResult::Won,
+                amount: Uint128(200),
+                returned: Uint128(0),
+                winning_bid: Some(Uint128(200)),
+            })
+        );
+        assert!(open(&resp.log, &[3; NOTIFY_KEY_LEN]).is_none());
+
+        // registrations are removed once the notification is sent
+        let error = register(&mut deps, "bob", None).unwrap_err();
+        assert!(error.to_string().contains("already closed"));
+        let bob = deps
+            .api
+            .canonical_address(&HumanAddr("bob".to_string()))
+            .unwrap();
+        let keys = ReadonlyPrefixedStorage::new(PREFIX_NOTIFY_KEYS, &deps.storage);
+        assert!(keys.get(bob.as_slice()).is_none());
+    }
+
+    #[test]
+    fn test_largest_notification_fits() {
+        let largest = Notification {
+            result: BidResult::Defaulted,
+            amount: Uint128(u128::MAX),
+            returned: Uint128(u128::MAX),
+            winning_bid: Some(Uint128(u128::MAX)),
+        };
+        assert!(serde_json::to_string(&largest).unwrap().len() <= NOTIFICATION_SIZE);
+    }
+}
@@ -0,0 +1,242 @@
+use serde::Serialize;
+
+use cosmwasm_std::{Binary, HumanAddr, Uint128};
+
+use crate::contract::{
+    humanize, to_bid_answer, BID_ANSWER_SIZE, BLOCK_SIZE, MAX_MEMO_LEN, MAX_REASON_LEN,
+};
+use crate::msg::{BidResult, HandleAnswer, QueryAnswer, ResponseStatus};
+
+/// decimal places of the tokens the audit checks.  Humanized amounts grow with the number of
+/// decimals, so tokens with more than 38 decimals could push a bid answer past BID_ANSWER_SIZE
+const AUDITED_DECIMALS: [u8; 5] = [0, 6, 8, 18, 38];
+
+/// smallest and largest amounts a bid answer can hold
+const AUDITED_AMOUNTS: [u128; 2] = [1, u128::MAX];
+
+/// a secret network address, which always has the same length
+const ADDRESS: &str = "secret1qqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqq";
+
+/// Returns usize
+///
+/// length of a serialized answer once it has been padded to a multiple of BLOCK_SIZE, the same
+/// way pad_handle_result and pad_query_result pad responses
+///
+/// # Arguments
+///
+/// * `answer` - a reference to the answer to measure
+fn padded_len<T: Serialize>(answer: &T) -> usize {
+    let len = serde_json_wasm::to_string(answer).unwrap().len();
+    let surplus = len % BLOCK_SIZE;
+    if surplus == 0 {
+        len
+    } else {
+        len + BLOCK_SIZE - surplus
+    }
+}
+
+/// Returns bool
+///
+/// true if the handle answer can reveal the amount of a bid, and must be padded to
+/// BID_ANSWER_SIZE.  This match is deliberately exhaustive, so a new answer will not compile
+/// until it has been classified
+fn handle_reveals_bid(answer: &HandleAnswer) -> bool {
+    match answer {
+        HandleAnswer::Bid { .. }
+        | HandleAnswer::CommitBid { .. }
+        | HandleAnswer::RevealBid { .. }
+        | HandleAnswer::RetractBid { .. } => true,
+        HandleAnswer::Consign { .. }
+        | HandleAnswer::IncreaseLot { .. }
+        | HandleAnswer::Deposit { .. }
+        | HandleAnswer::CloseAuction { .. }
+        | HandleAnswer::ReturnAll { .. }
+        | HandleAnswer::TransferSellerRights { .. }
+        | HandleAnswer::Operators { .. }
+        | HandleAnswer::FactoryAction { .. }
+        | HandleAnswer::ChangeMinimumBid { .. }
+        | HandleAnswer::ChangeSellAmount { .. }
+        | HandleAnswer::RetrySettlement { .. }
+        | HandleAnswer::RejectBid { .. }
+        | HandleAnswer::PauseBidding { .. }
+        | HandleAnswer::SetDescription { .. }
+        | HandleAnswer::PostMessage { .. }
+        | HandleAnswer::RegisterNotification { .. } => false,
+    }
+}
+
+/// Returns bool
+///
+/// true if the query answer can reveal the amount of a bid, and must be padded to
+/// BID_ANSWER_SIZE.  This match is deliberately exhaustive, so a new answer will not compile
+/// until it has been classified
+fn query_reveals_bid(answer: &QueryAnswer) -> bool {
+    match answer {
+        QueryAnswer::Bid { .. }
+        | QueryAnswer::PreviewBid { .. }
+        | QueryAnswer::Outcome { .. }
+        | QueryAnswer::WinProof { .. } => true,
+        // the winning bid is public once the auction closes, and bid stats are rounded
+        QueryAnswer::AuctionInfo { .. }
+        | QueryAnswer::HasBids { .. }
+        | QueryAnswer::Operators { .. }
+        | QueryAnswer::BidStats { .. }
+        | QueryAnswer::BidMemos { .. }
+        | QueryAnswer::ExportAuction { .. }
+        | QueryAnswer::Status { .. }
+        | QueryAnswer::Messages { .. }
+        | QueryAnswer::ViewingKeyError { .. } => false,
+    }
+}
+
+/// the largest form of every handle answer that can reveal a bid
+fn handle_answers(amount: u128, decimals: u8) -> Vec<HandleAnswer> {
+    let some = Some(Uint128(amount));
+    let human = humanize(some, decimals);
+    vec![
+        HandleAnswer::Bid {
+            status: ResponseStatus::Failure,
+            message: format!(
+                "This auction only allows a bid to be replaced with a higher bid.  Your \
+                 current bid of {} remains active and the new bid tokens have been returned",
+                amount
+            ),
+            previous_bid: some,
+            minimum_bid: some,
+            amount_bid: some,
+            unit_price: some,
+            amount_returned: some,
+            bid_decimals: decimals,
+            previous_bid_humanized: human.clone(),
+            minimum_bid_humanized: human.clone(),
+            amount_bid_humanized: human.clone(),
+            amount_returned_humanized: human,
+        },
+        HandleAnswer::CommitBid {
+            status: ResponseStatus::Success,
+            message: format!(
+                "Commitment accepted.  Reveal your bid after the closing time, and before {}.  \
+                 The deposit of your previous commitment has been returned",
+                u64::MAX
+            ),
+            deposit: some,
+            amount_returned: some,
+            bid_decimals: decimals,
+        },
+        HandleAnswer::RevealBid {
+            status: ResponseStatus::Success,
+            message: "Bid revealed.  Tokens deposited above your bid have been returned"
+                .to_string(),
+            amount_bid: some,
+            amount_returned: some,
+            bid_decimals: decimals,
+        },
+        HandleAnswer::RetractBid {
+            status: ResponseStatus::Success,
+            message: "Bid retracted.  Tokens have been returned".to_string(),
+            amount_returned: some,
+            bid_decimals: Some(decimals),
+        },
+    ]
+}
+
+/// the largest form of every query answer that can reveal a bid
+fn query_answers(amount: u128, decimals: u8) -> Vec<QueryAnswer> {
+    let some = Some(Uint128(amount));
+    vec![
+        QueryAnswer::Bid {
+            status: ResponseStatus::Success,
+            message: format!("Bid placed 2021-01-01 00:00:00 UTC by {}", ADDRESS),
+            amount_bid: some,
+            unit_price: some,
+            bid_decimals: Some(decimals),
+            bid_timestamp: Some(u64::MAX),
+            bid_expiration: Some(u64::MAX),
+            memo: Some("m".repeat(MAX_MEMO_LEN)),
+        },
+        QueryAnswer::PreviewBid {
+            accepted: false,
+            message: "Bid is the same as your current bid.  The bid tokens would be returned and \
+                      your current bid would keep its place in line"
+                .to_string(),
+            minimum_bid: Uint128(amount),
+            current_bid: some,
+            amount_returned: some,
+            bid_decimals: decimals,
+        },
+        QueryAnswer::Outcome {
+            status: ResponseStatus::Success,
+            message: "Bid was below the new minimum bid and was returned 2021-01-01 00:00:00 UTC"
+                .to_string(),
+            result: Some(BidResult::Refunded),
+            amount_bid: some,
+            amount_returned: some,
+            bid_decimals: Some(decimals),
+            block_height: Some(u64::MAX),
+            block_time: Some(u64::MAX),
+            reason: None,
+        },
+        QueryAnswer::Outcome {
+            status: ResponseStatus::Success,
+            message: "Bid was rejected by the seller 2021-01-01 00:00:00 UTC".to_string(),
+            result: Some(BidResult::Rejected),
+            amount_bid: some,
+            amount_returned: some,
+            bid_decimals: Some(decimals),
+            block_height: Some(u64::MAX),
+            block_time: Some(u64::MAX),
+            reason: Some("r".repeat(MAX_REASON_LEN)),
+        },
+        QueryAnswer::WinProof {
+            status: ResponseStatus::Success,
+            message: format!("Address {} won this auction", ADDRESS),
+            auction_address: Some(HumanAddr(ADDRESS.to_string())),
+            winner: Some(HumanAddr(ADDRESS.to_string())),
+            sell_amount: some,
+            winning_bid: some,
+            bid_decimals: Some(decimals),
+            bid_timestamp: Some(u64::MAX),
+            closed_at: Some(u64::MAX),
+            commitment: Some(Binary::from(&[u8::MAX; 32][..])),
+        },
+    ]
+}
+
+#[test]
+fn test_bid_answers_have_fixed_size() {
+    assert_eq!(BID_ANSWER_SIZE % BLOCK_SIZE, 0);
+    for decimals in AUDITED_DECIMALS.iter() {
+        for amount in AUDITED_AMOUNTS.iter() {
+            for answer in handle_answers(*amount, *decimals).iter() {
+                assert!(handle_reveals_bid(answer));
+                assert!(
+                    padded_len(answer) <= BID_ANSWER_SIZE,
+                    "{:?} does not fit in BID_ANSWER_SIZE",
+                    answer
+                );
+                assert_eq!(to_bid_answer(answer).unwrap().len(), BID_ANSWER_SIZE);
+            }
+            for answer in query_answers(*amount, *decimals).iter() {
+                assert!(query_reveals_bid(answer));
+                assert!(
+                    padded_len(answer) <= BID_ANSWER_SIZE,
+                    "{:?} does not fit in BID_ANSWER_SIZE",
+                    answer
+                );
+                assert_eq!(to_bid_answer(answer).unwrap().len(), BID_ANSWER_SIZE);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_block_padding_alone_leaks_bid_size() {
+    // without the fixed size, the number of digits in a bid changes the number of blocks
+    let small = &handle_answers(1, 18)[0];
+    let large = &handle_answers(u128::MAX, 18)[0];
+    assert!(padded_len(small) < padded_len(large));
+    assert_eq!(
+        to_bid_answer(small).unwrap().len(),
+        to_bid_answer(large).unwrap().len()
+    );
+}
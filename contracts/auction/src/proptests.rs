@@ -0,0 +1,301 @@
+use std::collections::HashMap;
+
+use serde::Deserialize;
+
+use cosmwasm_std::testing::{mock_dependencies, mock_env, MockApi, MockQuerier, MockStorage};
+use cosmwasm_std::{
+    from_binary, to_binary, CosmosMsg, Env, Extern, HandleResponse, HumanAddr, StdResult, Uint128,
+    WasmMsg,
+};
+
+use proptest::prelude::*;
+
+use crate::bidders::{list_bidders, list_committers};
+use crate::contract::tests::default_init_msg;
+use crate::contract::{handle, init, CONFIG_KEY};
+use crate::msg::{ContractInfo, HandleMsg, InitMsg, ReceiveMsg};
+use crate::state::{load, save, State};
+
+const SELLER: &str = "alice";
+const SELL_TOKEN: &str = "selladdr";
+const BID_TOKEN: &str = "bidaddr";
+const SELL_AMOUNT: u128 = 10;
+const ENDS_AT: u64 = 1000;
+
+/// the only message the auction sends to its token contracts after init
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum TokenMsg {
+    Transfer {
+        recipient: HumanAddr,
+        amount: Uint128,
+    },
+}
+
+/// an action taken against the auction before it is finalized
+#[derive(Debug, Clone)]
+enum Op {
+    Consign(u128),
+    Deposit(u128),
+    Bid(usize, u128),
+    Retract(usize),
+}
+
+fn bidder(i: usize) -> String {
+    format!("bidder{}", i)
+}
+
+fn op_strategy() -> impl Strategy<Value = Op> {
+    prop_oneof![
+        (1u128..=12).prop_map(Op::Consign),
+        (1u128..=60).prop_map(Op::Deposit),
+        (0usize..4, 1u128..=200).prop_map(|(b, a)| Op::Bid(b, a)),
+        (0usize..4).prop_map(Op::Retract),
+    ]
+}
+
+/// tokens that moved in and out of the auction, keyed by (token, address)
+#[derive(Default)]
+struct Ledger {
+    sent: HashMap<(String, String), u128>,
+    received: HashMap<(String, String), u128>,
+}
+
+impl Ledger {
+    fn send(&mut self, token: &str, from: &str, amount: u128) {
+        *self
+            .sent
+            .entry((token.to_string(), from.to_string()))
+            .or_insert(0) += amount;
+    }
+
+    /// record every token transfer made by a successful handle
+    fn record(&mut self, resp: &HandleResponse) {
+        for msg in resp.messages.iter() {
+            if let CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr, msg, ..
+            }) = msg
+            {
+                if contract_addr.as_str() != SELL_TOKEN && contract_addr.as_str() != BID_TOKEN {
+                    continue;
+                }
+                let TokenMsg::Transfer { recipient, amount } = from_binary(msg).unwrap();
+                *self
+                    .received
+                    .entry((contract_addr.to_string(), recipient.to_string()))
+                    .or_insert(0) += amount.u128();
+            }
+        }
+    }
+
+    fn sent_by(&self, token: &str, addr: &str) -> u128 {
+        self.sent
+            .get(&(token.to_string(), addr.to_string()))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    fn received_by(&self, token: &str, addr: &str) -> u128 {
+        self.received
+            .get(&(token.to_string(), addr.to_string()))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    fn total_sent(&self, token: &str) -> u128 {
+        self.sent
+            .iter()
+            .filter(|((t, _), _)| t == token)
+            .map(|(_, a)| a)
+            .sum()
+    }
+
+    fn total_received(&self, token: &str) -> u128 {
+        self.received
+            .iter()
+            .filter(|((t, _), _)| t == token)
+            .map(|(_, a)| a)
+            .sum()
+    }
+
+    /// addresses other than the seller that received sell tokens
+    fn sell_recipients(&self) -> Vec<String> {
+        self.received
+            .iter()
+            .filter(|((t, a), amount)| t == SELL_TOKEN && a != SELLER && **amount > 0)
+            .map(|((_, a), _)| a.clone())
+            .collect()
+    }
+}
+
+/// asserts the settlement of a finalized auction conserved every token and paid out correctly
+fn check_invariants(ledger: &Ledger, state: &State, storage: &MockStorage) {
+    // the auction holds nothing once it has closed
+    assert!(state.is_completed);
+    assert_eq!(state.num_bidders, 0);
+    assert_eq!(state.num_committers, 0);
+    // and the keyed bidder storage agrees with the counters
+    assert!(list_bidders(storage).unwrap().is_empty());
+    assert!(list_committers(storage).unwrap().is_empty());
+    assert_eq!(state.currently_consigned, 0);
+    assert_eq!(state.deposit_posted, 0);
+    // total tokens in == total tokens out
+    for token in [SELL_TOKEN, BID_TOKEN].iter() {
+        assert_eq!(
+            ledger.total_sent(token),
+            ledger.total_received(token),
+            "{} was not conserved",
+            token
+        );
+    }
+    // no bidder gets back more than they sent, beyond a share of a forfeited deposit
+    let deposit = ledger.sent_by(BID_TOKEN, SELLER);
+    for i in 0..4 {
+        let addr = bidder(i);
+        assert!(
+            ledger.received_by(BID_TOKEN, &addr) <= ledger.sent_by(BID_TOKEN, &addr) + deposit,
+            "{} was refunded more than they sent",
+            addr
+        );
+    }
+    // at most one winner, who receives exactly the lot and paid the winning bid
+    let winners = ledger.sell_recipients();
+    assert!(winners.len() <= 1, "multiple winners: {:?}", winners);
+    if let Some(winner) = winners.first() {
+        assert_eq!(ledger.received_by(SELL_TOKEN, winner), SELL_AMOUNT);
+        assert!(state.winning_bid >= 10);
+        assert_eq!(
+            ledger.sent_by(BID_TOKEN, winner) - ledger.received_by(BID_TOKEN, winner),
+            state.winning_bid
+        );
+        assert_eq!(
+            ledger.received_by(BID_TOKEN, SELLER),
+            state.winning_bid + deposit
+        );
+    } else {
+        assert_eq!(state.winning_bid, 0);
+        assert!(ledger.received_by(BID_TOKEN, SELLER) <= deposit);
+    }
+    // the seller never gets back more of the lot than they consigned
+    assert!(ledger.received_by(SELL_TOKEN, SELLER) <= ledger.sent_by(SELL_TOKEN, SELLER));
+}
+
+fn init_helper(deposit: u128) -> Extern<MockStorage, MockApi, MockQuerier> {
+    let mut deps = mock_dependencies(20, &[]);
+    let mut env = mock_env("factory", &[]);
+    env.block.time = 0;
+    let contract = |code_hash: &str, address: &str| ContractInfo {
+        code_hash: code_hash.to_string(),
+        address: HumanAddr(address.to_string()),
+        token_id: None,
+    };
+    let init_msg = InitMsg {
+        seller: HumanAddr(SELLER.to_string()),
+        sell_contract: contract("sellhash", SELL_TOKEN),
+        bid_contract: contract("bidhash", BID_TOKEN),
+        sell_amount: Uint128(SELL_AMOUNT),
+        ends_at: ENDS_AT,
+        ..default_init_msg()
+    };
+    init(&mut deps, env, init_msg).unwrap();
+    let mut state: State = load(&deps.storage, CONFIG_KEY).unwrap();
+    state.deposit = deposit;
+    save(&mut deps.storage, CONFIG_KEY, &state).unwrap();
+    deps
+}
+
+fn env_at(sender: &str, time: u64) -> Env {
+    let mut env = mock_env(sender, &[]);
+    env.block.time = time;
+    env
+}
+
+/// applies a handle, recording its transfers and any tokens it accepted
+fn apply(
+    deps: &mut Extern<MockStorage, MockApi, MockQuerier>,
+    ledger: &mut Ledger,
+    env: Env,
+    msg: HandleMsg,
+    sent: Option<(&str, &str, u128)>,
+) -> StdResult<()> {
+    let resp = handle(deps, env, msg)?;
+    // a failed handle reverts the token transfer that triggered it
+    if let Some((token, from, amount)) = sent {
+        ledger.send(token, from, amount);
+    }
+    ledger.record(&resp);
+    Ok(())
+}
+
+fn receive(from: &str, amount: u128, msg: Option<ReceiveMsg>) -> HandleMsg {
+    HandleMsg::Receive {
+        sender: HumanAddr("blah".to_string()),
+        from: HumanAddr(from.to_string()),
+        amount: Uint128(amount),
+        msg: msg.map(|m| to_binary(&m).unwrap()),
+    }
+}
+
+fn run(ops: &[Op], deposit: u128, early: bool) -> (Ledger, State, MockStorage) {
+    let mut deps = init_helper(deposit);
+    let mut ledger = Ledger::default();
+    let before_close = 500;
+    for op in ops.iter() {
+        // rejected actions are expected and leave nothing behind
+        let _ = match op {
+            Op::Consign(amount) => apply(
+                &mut deps,
+                &mut ledger,
+                env_at(SELL_TOKEN, before_close),
+                receive(SELLER, *amount, None),
+                Some((SELL_TOKEN, SELLER, *amount)),
+            ),
+            Op::Deposit(amount) => apply(
+                &mut deps,
+                &mut ledger,
+                env_at(BID_TOKEN, before_close),
+                receive(SELLER, *amount, Some(ReceiveMsg::Consign {})),
+                Some((BID_TOKEN, SELLER, *amount)),
+            ),
+            Op::Bid(i, amount) => apply(
+                &mut deps,
+                &mut ledger,
+                env_at(BID_TOKEN, before_close),
+                receive(&bidder(*i), *amount, None),
+                Some((BID_TOKEN, &bidder(*i), *amount)),
+            ),
+            Op::Retract(i) => apply(
+                &mut deps,
+                &mut ledger,
+                env_at(&bidder(*i), before_close),
+                HandleMsg::RetractBid {},
+                None,
+            ),
+        };
+    }
+    // the seller may close early, otherwise anyone may close after the closing time
+    let env = if early {
+        env_at(SELLER, before_close)
+    } else {
+        env_at("charlie", ENDS_AT + 1)
+    };
+    let finalize = HandleMsg::Finalize {
+        new_ends_at: None,
+        new_minimum_bid: None,
+    };
+    apply(&mut deps, &mut ledger, env, finalize, None).unwrap();
+    let state: State = load(&deps.storage, CONFIG_KEY).unwrap();
+    (ledger, state, deps.storage)
+}
+
+proptest! {
+    #[test]
+    fn finalize_conserves_tokens(
+        ops in prop::collection::vec(op_strategy(), 0..24),
+        deposit in 0u128..=50,
+        early in any::<bool>(),
+    ) {
+        let (ledger, state, storage) = run(&ops, deposit, early);
+        check_invariants(&ledger, &state, &storage);
+    }
+}
@@ -1,12 +1,15 @@
-use std::{any::type_name, collections::HashSet};
+use std::{
+    any::type_name,
+    collections::{BTreeMap, HashSet},
+};
 
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 
-use cosmwasm_std::{HumanAddr, ReadonlyStorage, StdError, StdResult, Storage};
+use cosmwasm_std::{CanonicalAddr, HumanAddr, ReadonlyStorage, StdError, StdResult, Storage};
 
 use secret_toolkit::serialization::{Bincode2, Serde};
 
-use crate::msg::ContractInfo;
+use crate::msg::{BidResult, BundleItem, ContractInfo, SettlementMode, SettlementPhase, TieBreak};
 
 /// state of the auction
 #[derive(Serialize, Deserialize)]
@@ -33,19 +36,187 @@ pub struct State {
     pub minimum_bid: u128,
     /// amount of tokens currently consigned to auction escrow
     pub currently_consigned: u128,
-    /// list of addresses of bidders
-    pub bidders: HashSet<Vec<u8>>,
+    /// number of addresses with an active bid.  The bids are listed by their records, so the
+    /// state does not grow with the number of bidders
+    pub num_bidders: u32,
     /// timestamp after which anyone may close the auction.
     /// Timestamp is in seconds since epoch 01/01/1970
     pub ends_at: u64,
     /// true if the auction is closed
     pub is_completed: bool,
+    /// true if the auction was cancelled because the lot was not consigned in time
+    pub is_cancelled: bool,
+    /// true if the auction was force closed because it was left open long after its closing time
+    pub is_force_closed: bool,
+    /// true if the factory has halted the auction
+    pub is_halted: bool,
+    /// how far the auction has progressed toward paying out its bids and lot.  It is saved
+    /// before any settlement transfer is built, so a stale copy of the state can not settle again
+    pub phase: SettlementPhase,
+    /// true if the seller has paused new bids
+    pub bidding_paused: bool,
     /// true if all tokens for sale have been consigned to escrow
     pub tokens_consigned: bool,
     /// Optional text description of auction
     pub description: Option<String>,
+    /// translations of the description keyed by language code
+    pub descriptions: BTreeMap<String, String>,
     /// winning bid
     pub winning_bid: u128,
+    /// additional tokens sold in the same lot
+    pub bundle: Vec<ConsignItem>,
+    /// amount of bid tokens the seller must post as a deposit
+    pub deposit: u128,
+    /// amount of the deposit currently held in escrow
+    pub deposit_posted: u128,
+    /// optional timestamp by which the seller must have consigned the full lot
+    pub consign_by: Option<u64>,
+    /// optional price at which a bidder may buy the lot immediately
+    pub buy_now_price: Option<u128>,
+    /// true if the seller is not allowed to bid
+    pub no_self_bid: bool,
+    /// true if a bidder may only replace their bid with a higher bid
+    pub only_increasing_rebids: bool,
+    /// canonical addresses the seller has authorized to change the minimum bid and closing
+    /// time, and to finalize the auction on the seller's behalf
+    pub operators: HashSet<Vec<u8>>,
+    /// time of the most recently placed bid in seconds since epoch 01/01/1970
+    pub last_bid_at: Option<u64>,
+    /// policy used to pick the winner when the highest bids are tied
+    pub tie_break: TieBreak,
+    /// key the factory gave this auction to authenticate its callbacks
+    pub callback_key: String,
+    /// viewing key the auction set with the bid token to check bidders' allowances.  None if
+    /// bids are sent to escrow instead of being backed by allowances
+    pub allowance_key: Option<String>,
+    /// true if the auction has a message board
+    pub message_board: bool,
+    /// memos attached to the winning bids, kept for the seller after the bids are removed
+    pub winner_memos: Vec<String>,
+    /// warning from the factory if the auction was created with an outdated contract version
+    pub deprecation_notice: Option<String>,
+    /// optional schedule by which the minimum bid declines
+    pub min_bid_decay: Option<StoredDecay>,
+    /// optional number of seconds the seller has to accept or reject the winning bid when
+    /// someone else finalizes the auction
+    pub seller_confirmation: Option<u64>,
+    /// time by which the seller must accept or reject the winning bid, once someone else has
+    /// finalized the auction
+    pub accept_by: Option<u64>,
+    /// true if the seller rejected the winning bid
+    pub is_rejected: bool,
+    /// true if bidders may specify a price per whole sell token instead of a total bid
+    pub unit_price_bids: bool,
+    /// optional limit on the number of distinct bidders
+    pub max_bidders: Option<u32>,
+    /// optional number of distinct bidders the auction needs to sell
+    pub min_bidders: Option<u32>,
+    /// true if the auction closed without a sale because it had too few bidders
+    pub too_few_bidders: bool,
+    /// optional number of seconds after the closing time during which sealed bids are revealed
+    pub reveal_window: Option<u64>,
+    /// number of addresses with sealed commitments that have not been revealed.  The committers
+    /// are kept in their own storage, so the state does not grow with the number of committers
+    pub num_committers: u32,
+    /// how the winner is picked from the active bids
+    pub settlement: SettlementMode,
+    /// secret seed from the factory's prng used to draw a raffle winner
+    pub raffle_seed: Option<Vec<u8>>,
+    /// amount a crowdfund must raise to sell the lot
+    pub funding_target: Option<u128>,
+    /// bidder whose bid the seller explicitly accepted
+    pub accepted_bidder: Option<CanonicalAddr>,
+}
+
+impl State {
+    /// Returns u128
+    ///
+    /// the minimum bid in effect at the given time after applying any decay schedule
+    ///
+    /// # Arguments
+    ///
+    /// * `time` - time in seconds since epoch 01/01/1970
+    pub fn effective_minimum_bid(&self, time: u64) -> u128 {
+        match self.min_bid_decay.as_ref() {
+            Some(decay) => {
+                let steps = (time.saturating_sub(decay.start) / decay.interval) as u128;
+                // a minimum bid set below the floor is not raised to it
+                let floor = decay.floor.min(self.minimum_bid);
+                self.minimum_bid
+                    .saturating_sub(decay.step.saturating_mul(steps))
+                    .max(floor)
+            }
+            None => self.minimum_bid,
+        }
+    }
+
+    /// Returns bool
+    ///
+    /// true if the auction has fewer distinct bidders than it needs to sell
+    pub fn lacks_min_bidders(&self) -> bool {
+        self.min_bidders.map_or(false, |m| self.num_bidders < m)
+    }
+
+    /// Returns u32
+    ///
+    /// number of distinct addresses with either an active bid or an unrevealed commitment
+    pub fn bidder_count(&self) -> u32 {
+        self.num_bidders + self.num_committers
+    }
+
+    /// Returns bool
+    ///
+    /// true if the auction has any active bids or unrevealed commitments
+    pub fn has_bids(&self) -> bool {
+        self.num_bidders > 0 || self.num_committers > 0
+    }
+
+    /// Returns Option<u64>
+    ///
+    /// time the reveal window ends, if the auction takes sealed bids
+    pub fn reveal_ends_at(&self) -> Option<u64> {
+        self.reveal_window.map(|w| self.ends_at.saturating_add(w))
+    }
+}
+
+/// schedule by which the minimum bid declines
+#[derive(Serialize, Deserialize)]
+pub struct StoredDecay {
+    /// lowest value the minimum bid declines to
+    pub floor: u128,
+    /// amount the minimum bid declines every interval
+    pub step: u128,
+    /// number of seconds between declines
+    pub interval: u64,
+    /// time the decline started
+    pub start: u64,
+}
+
+/// an additional token of a bundled lot and its consignment progress
+#[derive(Serialize, Deserialize)]
+pub struct ConsignItem {
+    /// code hash and address of the token contract
+    pub contract: ContractInfo,
+    /// amount of tokens for sale
+    pub amount: u128,
+    /// token decimal places
+    pub decimals: u8,
+    /// amount of tokens currently consigned to auction escrow
+    pub currently_consigned: u128,
+    /// true if all of this item has been consigned to escrow
+    pub consigned: bool,
+}
+
+impl From<BundleItem> for ConsignItem {
+    fn from(item: BundleItem) -> Self {
+        ConsignItem {
+            contract: item.contract,
+            amount: item.amount.u128(),
+            decimals: item.decimals,
+            currently_consigned: 0,
+            consigned: false,
+        }
+    }
 }
 
 /// bid data
@@ -55,6 +226,68 @@ pub struct Bid {
     pub amount: u128,
     /// time bid was placed
     pub timestamp: u64,
+    /// optional time after which the bid is no longer valid
+    pub expiration: Option<u64>,
+    /// optional private memo for the seller
+    pub memo: Option<String>,
+    /// optional address that referred the bidder
+    pub referrer: Option<CanonicalAddr>,
+}
+
+/// record of a bid, kept in the order the bids were placed
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct BidRecord {
+    /// canonical address of the bidder
+    pub bidder: Vec<u8>,
+    /// amount of the bid
+    pub amount: u128,
+    /// time the bid was placed
+    pub timestamp: u64,
+    /// true until the bid is replaced by a newer one from the same bidder or removed
+    pub active: bool,
+}
+
+/// sealed bid that has not been revealed
+#[derive(Serialize, Deserialize)]
+pub struct Commitment {
+    /// sha256 hash of the bidder's address, the bid amount, and a salt
+    pub hash: Vec<u8>,
+    /// amount deposited with the commitment
+    pub deposit: u128,
+    /// time the commitment was placed
+    pub timestamp: u64,
+}
+
+/// a message posted to the auction's message board
+#[derive(Serialize, Deserialize)]
+pub struct StoredMessage {
+    /// canonical address of the author
+    pub author: Vec<u8>,
+    /// true if the seller or one of its operators posted the message
+    pub from_seller: bool,
+    /// text of the message
+    pub text: String,
+    /// time the message was posted in seconds since epoch 01/01/1970
+    pub timestamp: u64,
+}
+
+/// outcome of a bid that is no longer active
+#[derive(Serialize, Deserialize)]
+pub struct Outcome {
+    /// how the bid ended
+    pub result: BidResult,
+    /// amount that was bid
+    pub amount: u128,
+    /// time the bid was placed
+    pub bid_timestamp: u64,
+    /// amount of bid tokens returned to the bidder
+    pub returned: u128,
+    /// height of the block in which the bid ended
+    pub block_height: u64,
+    /// time the bid ended in seconds since epoch 01/01/1970
+    pub block_time: u64,
+    /// reason the seller gave for rejecting the bid
+    pub reason: Option<String>,
 }
 
 /// Returns StdResult<()> resulting from saving an item to storage
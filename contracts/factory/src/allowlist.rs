@@ -0,0 +1,126 @@
+use cosmwasm_std::{
+    to_binary, Api, Env, Extern, HandleResponse, HandleResult, HumanAddr, Querier, QueryResult,
+    ReadonlyStorage, StdError, StdResult, Storage,
+};
+
+use cosmwasm_storage::{PrefixedStorage, ReadonlyPrefixedStorage};
+
+use crate::contract::check_admin;
+use crate::msg::{HandleAnswer, QueryAnswer, ResponseStatus::Success};
+use crate::state::{may_load, remove, save};
+
+/// prefix for storage of the addresses allowed to create auctions while the allowlist is enforced
+pub const PREFIX_ALLOWLIST: &[u8] = b"allowlist";
+/// storage key for whether only allowlisted addresses may create auctions
+pub const ALLOWLIST_ENABLED_KEY: &[u8] = b"allowlistenabled";
+/// storage key for the number of allowlisted addresses
+pub const ALLOWLIST_COUNT_KEY: &[u8] = b"allowlistcount";
+
+/// Returns StdResult<()>
+///
+/// returns an error if the allowlist is enforced and the address is not on it
+///
+/// # Arguments
+///
+/// * `storage` - a reference to the contract's storage
+/// * `api` - a reference to the Api used to convert human and canonical addresses
+/// * `seller` - a reference to the address creating an auction
+pub fn check_allowlisted<S: ReadonlyStorage, A: Api>(
+    storage: &S,
+    api: &A,
+    seller: &HumanAddr,
+) -> StdResult<()> {
+    let enabled: bool = may_load(storage, ALLOWLIST_ENABLED_KEY)?.unwrap_or(false);
+    if enabled {
+        let list_store = ReadonlyPrefixedStorage::new(PREFIX_ALLOWLIST, storage);
+        let seller_raw = api.canonical_address(seller)?;
+        if may_load::<bool, _>(&list_store, seller_raw.as_slice())?.is_none() {
+            return Err(StdError::generic_err(
+                "Only allowlisted addresses may create auctions with this factory",
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Returns HandleResult
+///
+/// allows the admin to start or stop only letting allowlisted addresses create auctions
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `enabled` - true if only allowlisted addresses may create auctions
+pub fn try_set_allowlist_mode<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    enabled: bool,
+) -> HandleResult {
+    check_admin(deps, &env)?;
+    save(&mut deps.storage, ALLOWLIST_ENABLED_KEY, &enabled)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: Some(to_binary(&HandleAnswer::Status {
+            status: Success,
+            message: None,
+        })?),
+    })
+}
+
+/// Returns HandleResult
+///
+/// allows the admin to add addresses to, or remove them from, the allowlist
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `addresses` - the addresses to add or remove
+/// * `add` - true if the addresses should be added
+pub fn try_update_allowlist<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    addresses: &[HumanAddr],
+    add: bool,
+) -> HandleResult {
+    check_admin(deps, &env)?;
+    let mut count: u32 = may_load(&deps.storage, ALLOWLIST_COUNT_KEY)?.unwrap_or(0);
+    let mut list_store = PrefixedStorage::new(PREFIX_ALLOWLIST, &mut deps.storage);
+    for address in addresses.iter() {
+        let raw = deps.api.canonical_address(address)?;
+        let listed = may_load::<bool, _>(&list_store, raw.as_slice())?.is_some();
+        if add && !listed {
+            save(&mut list_store, raw.as_slice(), &true)?;
+            count += 1;
+        } else if !add && listed {
+            remove(&mut list_store, raw.as_slice());
+            count -= 1;
+        }
+    }
+    save(&mut deps.storage, ALLOWLIST_COUNT_KEY, &count)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: Some(to_binary(&HandleAnswer::Status {
+            status: Success,
+            message: None,
+        })?),
+    })
+}
+
+/// Returns QueryResult displaying whether the allowlist is enforced and how many addresses are
+/// on it.  Membership of individual addresses is not displayed
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+pub fn try_allowlist_info<S: Storage, A: Api, Q: Querier>(deps: &Extern<S, A, Q>) -> QueryResult {
+    to_binary(&QueryAnswer::Allowlist {
+        enabled: may_load(&deps.storage, ALLOWLIST_ENABLED_KEY)?.unwrap_or(false),
+        count: may_load(&deps.storage, ALLOWLIST_COUNT_KEY)?.unwrap_or(0),
+    })
+}
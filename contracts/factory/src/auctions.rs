@@ -0,0 +1,1903 @@
+use serde::Serialize;
+
+use cosmwasm_std::{
+    log, to_binary, Api, BankMsg, Binary, CanonicalAddr, Coin, CosmosMsg, Env, Extern,
+    HandleResponse, HandleResult, HumanAddr, Querier, ReadonlyStorage, StdError, StdResult,
+    Storage, Uint128,
+};
+
+use cosmwasm_storage::{PrefixedStorage, ReadonlyPrefixedStorage};
+
+use std::collections::HashSet;
+
+use secret_toolkit::{storage::AppendStoreMut, utils::InitCallback};
+
+use auction_common::crypto::{load_seed, sha_256, Prng};
+use auction_common::math::{mul_div, Rounding};
+use auction_common::MAX_BUNDLE_ITEMS;
+
+use crate::allowlist::check_allowlisted;
+use crate::contract::{
+    check_nickname, check_tags, next_series_round, this_contract, AuctionReceiveMsg, ACTIVE_KEY,
+    BLOCK_SIZE, CONFIG_KEY, LEGACY_CONFIG_KEY, LEGACY_SYMDEC_KEY, PENDING_KEY, PREFIX_ACTIVE_INFO,
+    PREFIX_AUCTION_SELLERS, PREFIX_BID_AMOUNTS, PREFIX_BUNDLED, PREFIX_CALLBACK_KEYS,
+    PREFIX_CLOSED_INFO, PREFIX_CLOSED_NICKNAMES, PREFIX_CLOSED_ORDER, PREFIX_CLOSED_POSITIONS,
+    PREFIX_CLOSE_RETRY, PREFIX_CODE_HASHES, PREFIX_LABELS, PREFIX_NICKNAMES, PREFIX_PAIR_SALES,
+    PREFIX_SERIES, PREFIX_SERIES_ROUNDS, PREFIX_SETTLEMENTS, PREFIX_SYMDECS, PRUNED_KEY,
+    TOKEN_COUNT_KEY,
+};
+use crate::lists::{
+    add_to_ending_index, add_to_persons_closed, add_to_tag_index, filter_only_active,
+    remove_from_ending_index, remove_from_persons_active, remove_from_tag_index, AddressList,
+};
+use crate::msg::{
+    Asset, BundleItem, ContractInfo, FactoryStatus, HandleAnswer, LocalizedDescription,
+    MinBidDecay, RegisterAuctionInfo, ResponseStatus::Success, SettlementMode, StoreAuctionInfo,
+    StoreSettlementRecord, TieBreak, TreasuryAction,
+};
+use crate::state::{
+    load, may_load, remove, save, AuctionFee, Config, LegacyConfig, Series, StorePairClose,
+    StoredTreasuryRecord, TokenSymDec,
+};
+use crate::subscribers::record_pair_close;
+use crate::treasury::{
+    pay_referrals, record_treasury_change, reserve_auction_fee, FEE_DENOM, MAX_BASIS_POINTS,
+};
+use crate::viewing_key::{ViewingKey, VIEWING_KEY_SIZE};
+use crate::viewing_keys::share_key_msg;
+
+/// Returns HandleResult
+///
+/// create a new auction
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `label` - optional String containing the label to give the auction.  The factory generates
+///             one if it is not given
+/// * `sell_contract` - ContractInfo containing the code hash and address of the sale token
+/// * `bid_contract` - ContractInfo containing the code hash and address of the bid token
+/// * `sell_amount` - Uint128 amount to sell in smallest denomination
+/// * `minimum_bid` - Uint128 minimum bid owner will accept
+/// * `ends_at` - time in seconds since epoch 01/01/1970 after which anyone may close the auction
+/// * `description` - optional free-form text string owner may have used to describe the auction
+/// * `descriptions` - optional translations of the description
+/// * `nickname` - optional short name only displayed in the seller's lists
+/// * `tags` - tags the auction is listed under
+/// * `bundle` - additional tokens sold together with the sell tokens
+/// * `deposit` - optional deposit the seller posts in bid tokens
+/// * `consign_by` - optional time by which the lot must be consigned
+/// * `buy_now_price` - optional price at which a bidder may buy the lot immediately
+/// * `no_self_bid` - true if the seller is not allowed to bid
+/// * `only_increasing_rebids` - true if a bidder may only replace their bid with a higher bid
+/// * `tie_break` - policy used to pick the winner when the highest bids are tied
+/// * `allowance_bids` - true if bids are backed by allowances instead of being sent to escrow
+/// * `message_board` - true if the auction has a message board
+/// * `min_bid_decay` - optional schedule by which the minimum bid declines
+/// * `seller_confirmation` - optional number of seconds the seller has to accept or reject the
+///                           winning bid when someone else finalizes the auction
+/// * `unit_price_bids` - true if bidders may specify a price per whole sell token
+/// * `max_bidders` - optional limit on the number of distinct bidders
+/// * `min_bidders` - optional number of distinct bidders the auction needs to sell
+/// * `reveal_window` - optional number of seconds after the closing time to reveal sealed bids
+/// * `settlement` - how the winner is picked from the active bids
+/// * `funding_target` - optional amount a crowdfund must raise to sell the lot
+/// * `referrer` - optional address that referred the seller
+#[allow(clippy::too_many_arguments)]
+pub fn try_create_auction<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    label: Option<String>,
+    sell_contract: ContractInfo,
+    bid_contract: ContractInfo,
+    sell_amount: Uint128,
+    minimum_bid: Uint128,
+    ends_at: u64,
+    description: Option<String>,
+    descriptions: Vec<LocalizedDescription>,
+    nickname: Option<String>,
+    tags: Vec<String>,
+    bundle: Vec<BundleItem>,
+    deposit: Option<Uint128>,
+    consign_by: Option<u64>,
+    buy_now_price: Option<Uint128>,
+    no_self_bid: bool,
+    only_increasing_rebids: bool,
+    tie_break: TieBreak,
+    allowance_bids: bool,
+    message_board: bool,
+    min_bid_decay: Option<MinBidDecay>,
+    seller_confirmation: Option<u64>,
+    unit_price_bids: bool,
+    max_bidders: Option<u32>,
+    min_bidders: Option<u32>,
+    reveal_window: Option<u64>,
+    settlement: SettlementMode,
+    funding_target: Option<Uint128>,
+    referrer: Option<HumanAddr>,
+) -> HandleResult {
+    check_new_auction(
+        &deps.storage,
+        env.block.time,
+        &sell_contract,
+        &bid_contract,
+        sell_amount,
+        ends_at,
+        bundle.len(),
+    )?;
+    check_seller(
+        &deps.storage,
+        &deps.api,
+        &env.message.sender,
+        nickname.as_ref(),
+        referrer.as_ref(),
+    )?;
+    let tags = check_tags(tags)?;
+    let auction = NewAuction {
+        seller: env.message.sender.clone(),
+        label,
+        sell_contract,
+        bid_contract,
+        sell_amount,
+        minimum_bid,
+        ends_at,
+        description,
+        descriptions,
+        bundle,
+        deposit,
+        consign_by,
+        buy_now_price,
+        no_self_bid,
+        only_increasing_rebids,
+        tie_break,
+        allowance_bids,
+        message_board,
+        min_bid_decay,
+        seller_confirmation,
+        unit_price_bids,
+        max_bidders,
+        min_bidders,
+        reveal_window,
+        settlement,
+        funding_target,
+    };
+    let (cosmosmsg, index, label) = new_auction_msg(deps, &env, auction, true)?;
+    // save the seller's nickname for the auction
+    if let Some(name) = nickname {
+        let mut nick_store = PrefixedStorage::new(PREFIX_NICKNAMES, &mut deps.storage);
+        save(&mut nick_store, &index.to_le_bytes(), &name)?;
+    }
+    add_to_tag_index(&mut deps.storage, &tags, index)?;
+    let mut messages = vec![cosmosmsg];
+    if let Some(fee_msg) = collect_creation_fee(deps, &env, index, referrer.as_ref())? {
+        messages.push(fee_msg);
+    }
+
+    Ok(HandleResponse {
+        messages,
+        log: vec![],
+        data: Some(to_binary(&HandleAnswer::Status {
+            status: Success,
+            message: Some(label),
+        })?),
+    })
+}
+
+/// Returns StdResult<Option<CosmosMsg>>
+///
+/// verifies the creation fee, if the factory charges one, was sent with the message.  The
+/// referral share of the fee is always kept in the treasury and reserved until the auction closes.
+/// The rest is either credited to the treasury or forwarded to the configured address, in which
+/// case the message sending it is returned
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - a reference to the Env of contract's environment
+/// * `index` - factory index of the auction being created
+/// * `referrer` - optional address that referred the seller
+fn collect_creation_fee<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    index: u32,
+    referrer: Option<&HumanAddr>,
+) -> StdResult<Option<CosmosMsg>> {
+    let config: Config = load_config(&deps.storage)?;
+    let fee = match config.creation_fee {
+        Some(fee) => fee,
+        None => return Ok(None),
+    };
+    let sent = &env.message.sent_funds;
+    if sent.len() != 1 || sent[0].denom != FEE_DENOM || sent[0].amount.u128() != fee.amount {
+        return Err(StdError::generic_err(format!(
+            "Creating an auction requires a fee of exactly {}{}",
+            fee.amount, FEE_DENOM
+        )));
+    }
+    let reserved = mul_div(
+        fee.amount,
+        u128::from(config.referral_share),
+        u128::from(MAX_BASIS_POINTS),
+        Rounding::Down,
+    )?;
+    let (kept, message) = match fee.treasury {
+        Some(treasury) => {
+            let forwarded = fee.amount - reserved;
+            let message = if forwarded > 0 {
+                Some(CosmosMsg::Bank(BankMsg::Send {
+                    from_address: env.contract.address.clone(),
+                    to_address: deps.api.human_address(&treasury)?,
+                    amount: vec![Coin {
+                        denom: FEE_DENOM.to_string(),
+                        amount: Uint128(forwarded),
+                    }],
+                }))
+            } else {
+                None
+            };
+            (reserved, message)
+        }
+        None => (fee.amount, None),
+    };
+    if kept > 0 {
+        record_treasury_change(
+            &mut deps.storage,
+            &Asset::Native {
+                denom: FEE_DENOM.to_string(),
+            },
+            StoredTreasuryRecord {
+                action: TreasuryAction::CreationFee { index },
+                amount: kept,
+                address: deps.api.canonical_address(&env.message.sender)?,
+                time: env.block.time,
+            },
+        )?;
+    }
+    // reserve the referral share so it can be paid to referrers if the auction sells
+    if reserved > 0 {
+        let auction_fee = AuctionFee {
+            reserved,
+            referrer: referrer
+                .map(|r| deps.api.canonical_address(r))
+                .transpose()?,
+        };
+        reserve_auction_fee(&mut deps.storage, index, &auction_fee)?;
+    }
+    Ok(message)
+}
+
+/// Returns StdResult<()>
+///
+/// verifies the seller may create auctions, and the nickname and referrer they gave are valid
+///
+/// # Arguments
+///
+/// * `storage` - a reference to the contract's storage
+/// * `api` - a reference to the Api used to convert human and canonical addresses
+/// * `seller` - a reference to the address creating the auction
+/// * `nickname` - optional short name only displayed in the seller's lists
+/// * `referrer` - optional address that referred the seller
+pub fn check_seller<S: ReadonlyStorage, A: Api>(
+    storage: &S,
+    api: &A,
+    seller: &HumanAddr,
+    nickname: Option<&String>,
+    referrer: Option<&HumanAddr>,
+) -> StdResult<()> {
+    check_allowlisted(storage, api, seller)?;
+    if let Some(name) = nickname {
+        check_nickname(name)?;
+    }
+    if referrer == Some(seller) {
+        return Err(StdError::generic_err("A seller can not refer themselves"));
+    }
+    Ok(())
+}
+
+/// Returns StdResult<()>
+///
+/// verifies the factory is accepting new auctions and the auction's parameters are sane
+///
+/// # Arguments
+///
+/// * `storage` - a reference to the contract's storage
+/// * `now` - current time in seconds since epoch 01/01/1970
+/// * `sell_contract` - a reference to the ContractInfo of the sale token
+/// * `bid_contract` - a reference to the ContractInfo of the bid token
+/// * `sell_amount` - Uint128 amount to sell in smallest denomination
+/// * `ends_at` - time in seconds since epoch 01/01/1970 after which anyone may close the auction
+/// * `bundle_len` - number of additional tokens sold in the same lot
+pub fn check_new_auction<S: ReadonlyStorage>(
+    storage: &S,
+    now: u64,
+    sell_contract: &ContractInfo,
+    bid_contract: &ContractInfo,
+    sell_amount: Uint128,
+    ends_at: u64,
+    bundle_len: usize,
+) -> StdResult<()> {
+    let config: Config = load_config(storage)?;
+    if config.status.creation_stopped {
+        return Err(StdError::generic_err(
+            "The factory has been stopped.  No new auctions can be created",
+        ));
+    }
+
+    if sell_amount.u128() == 0 {
+        return Err(StdError::generic_err("Sell amount must be greater than 0"));
+    }
+    if sell_contract.is_same_token(bid_contract) {
+        return Err(StdError::generic_err(
+            "Sell contract and bid contract must be different",
+        ));
+    }
+    if ends_at <= now {
+        return Err(StdError::generic_err(format!(
+            "Closing time {} must be later than the current block time {}",
+            ends_at, now
+        )));
+    }
+    if sell_amount.u128() < config.min_sell_amount {
+        return Err(StdError::generic_err(format!(
+            "Sell amount must be at least {}",
+            config.min_sell_amount
+        )));
+    }
+    if bundle_len > MAX_BUNDLE_ITEMS {
+        return Err(StdError::generic_err(format!(
+            "An auction can not bundle more than {} additional tokens",
+            MAX_BUNDLE_ITEMS
+        )));
+    }
+    Ok(())
+}
+
+/// parameters of an auction the factory is about to instantiate
+pub struct NewAuction {
+    /// auction seller
+    pub seller: HumanAddr,
+    /// optional String label for the auction, generated by the factory if not given
+    pub label: Option<String>,
+    /// sell contract code hash and address
+    pub sell_contract: ContractInfo,
+    /// bid contract code hash and address
+    pub bid_contract: ContractInfo,
+    /// amount of tokens being sold
+    pub sell_amount: Uint128,
+    /// minimum bid that will be accepted
+    pub minimum_bid: Uint128,
+    /// timestamp after which anyone may close the auction
+    pub ends_at: u64,
+    /// Optional free-form description of the auction
+    pub description: Option<String>,
+    /// translations of the description
+    pub descriptions: Vec<LocalizedDescription>,
+    /// additional tokens sold in the same lot
+    pub bundle: Vec<BundleItem>,
+    /// optional deposit the seller posts in bid tokens
+    pub deposit: Option<Uint128>,
+    /// optional timestamp by which the lot must be consigned
+    pub consign_by: Option<u64>,
+    /// optional price at which a bidder may buy the lot immediately
+    pub buy_now_price: Option<Uint128>,
+    /// true if the seller is not allowed to bid
+    pub no_self_bid: bool,
+    /// true if a bidder may only replace their bid with a higher bid
+    pub only_increasing_rebids: bool,
+    /// policy used to pick the winner when the highest bids are tied
+    pub tie_break: TieBreak,
+    /// true if bids are backed by allowances instead of being sent to escrow
+    pub allowance_bids: bool,
+    /// true if the auction has a message board
+    pub message_board: bool,
+    /// optional schedule by which the minimum bid declines
+    pub min_bid_decay: Option<MinBidDecay>,
+    /// optional number of seconds the seller has to accept or reject the winning bid
+    pub seller_confirmation: Option<u64>,
+    /// true if bidders may specify a price per whole sell token
+    pub unit_price_bids: bool,
+    /// optional limit on the number of distinct bidders
+    pub max_bidders: Option<u32>,
+    /// optional number of distinct bidders the auction needs to sell
+    pub min_bidders: Option<u32>,
+    /// optional number of seconds after the closing time during which sealed bids are revealed
+    pub reveal_window: Option<u64>,
+    /// how the winner is picked from the active bids
+    pub settlement: SettlementMode,
+    /// optional amount a crowdfund must raise to sell the lot
+    pub funding_target: Option<Uint128>,
+}
+
+/// an additional token included in an auction lot
+#[derive(Serialize)]
+pub struct AuctionBundleItem {
+    /// token contract code hash and address
+    pub contract: ContractInfo,
+    /// amount of tokens being sold
+    pub amount: Uint128,
+    /// token decimal places
+    pub decimals: u8,
+}
+
+/// Instantiation message
+#[derive(Serialize)]
+pub struct AuctionInitMsg {
+    /// factory contract code hash and address
+    pub factory: ContractInfo,
+    /// auction index with the factory
+    pub index: u32,
+    /// String label for the auction
+    pub label: String,
+    /// auction seller
+    pub seller: HumanAddr,
+    /// sell contract code hash and address
+    pub sell_contract: ContractInfo,
+    /// sell token decimal places
+    pub sell_decimals: u8,
+    /// bid contract code hash and address
+    pub bid_contract: ContractInfo,
+    /// bid token decimal places,
+    pub bid_decimals: u8,
+    /// amount of tokens being sold
+    pub sell_amount: Uint128,
+    /// minimum bid that will be accepted
+    pub minimum_bid: Uint128,
+    /// timestamp after which anyone may close the auction.
+    /// Timestamp is in seconds since epoch 01/01/1970
+    pub ends_at: u64,
+    /// Optional free-form description of the auction (best to avoid double quotes). As an example
+    /// it could be the date the owner will likely finalize the auction, or a list of other
+    /// auctions for the same token, etc...
+    #[serde(default)]
+    pub description: Option<String>,
+    /// translations of the description
+    pub descriptions: Vec<LocalizedDescription>,
+    /// additional tokens sold in the same lot
+    pub bundle: Vec<AuctionBundleItem>,
+    /// optional deposit the seller posts in bid tokens
+    pub deposit: Option<Uint128>,
+    /// optional timestamp by which the lot must be consigned
+    pub consign_by: Option<u64>,
+    /// optional price at which a bidder may buy the lot immediately
+    pub buy_now_price: Option<Uint128>,
+    /// true if the seller is not allowed to bid
+    pub no_self_bid: bool,
+    /// true if a bidder may only replace their bid with a higher bid
+    pub only_increasing_rebids: bool,
+    /// policy used to pick the winner when the highest bids are tied
+    pub tie_break: TieBreak,
+    /// true if bids are backed by allowances instead of being sent to escrow
+    pub allowance_bids: bool,
+    /// true if the auction has a message board
+    pub message_board: bool,
+    /// key the auction must present with its callbacks to the factory
+    pub callback_key: String,
+    /// block size the auction pads its responses to
+    pub block_size: u16,
+    /// optional schedule by which the minimum bid declines
+    pub min_bid_decay: Option<MinBidDecay>,
+    /// optional number of seconds the seller has to accept or reject the winning bid
+    pub seller_confirmation: Option<u64>,
+    /// true if bidders may specify a price per whole sell token
+    pub unit_price_bids: bool,
+    /// optional limit on the number of distinct bidders
+    pub max_bidders: Option<u32>,
+    /// optional number of distinct bidders the auction needs to sell
+    pub min_bidders: Option<u32>,
+    /// optional number of seconds after the closing time during which sealed bids are
+    /// revealed
+    pub reveal_window: Option<u64>,
+    /// how the winner is picked from the active bids
+    pub settlement: SettlementMode,
+    /// optional secret seed the auction uses to draw a raffle winner
+    pub raffle_seed: Option<Binary>,
+    /// optional amount a crowdfund must raise to sell the lot
+    pub funding_target: Option<Uint128>,
+}
+
+impl InitCallback for AuctionInitMsg {
+    const BLOCK_SIZE: usize = BLOCK_SIZE;
+}
+
+/// an auction that passed every check the factory makes before instantiating it
+pub struct PreparedAuction {
+    /// instantiation message without its callback key or raffle seed
+    pub init: AuctionInitMsg,
+    /// keys and symbols/decimals of the tokens the factory has not seen before
+    pub new_tokens: Vec<(Vec<u8>, TokenSymDec)>,
+    /// the factory config the message was created with
+    pub config: Config,
+}
+
+/// Returns StdResult<PreparedAuction>
+///
+/// queries the auction's tokens and verifies its label and limits without saving anything, so
+/// it can be used to preview an auction's creation as well as to create it
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `factory` - ContractInfo of the factory
+/// * `auction` - NewAuction parameters of the auction
+/// * `check_limits` - true if the decimals and minimum bid limits should be enforced
+pub fn prepare_auction<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    factory: ContractInfo,
+    auction: NewAuction,
+    check_limits: bool,
+) -> StdResult<PreparedAuction> {
+    let NewAuction {
+        seller,
+        label,
+        sell_contract,
+        bid_contract,
+        sell_amount,
+        minimum_bid,
+        ends_at,
+        description,
+        descriptions,
+        bundle,
+        deposit,
+        consign_by,
+        buy_now_price,
+        no_self_bid,
+        only_increasing_rebids,
+        tie_break,
+        allowance_bids,
+        message_board,
+        min_bid_decay,
+        seller_confirmation,
+        unit_price_bids,
+        max_bidders,
+        min_bidders,
+        reveal_window,
+        settlement,
+        funding_target,
+    } = auction;
+    // a raffle gives the lot to a random bid, so a buyer could pay the buy now price and lose
+    if settlement == SettlementMode::Raffle && buy_now_price.is_some() {
+        return Err(StdError::generic_err(
+            "A raffle can not have a buy now price",
+        ));
+    }
+    let config: Config = load_config(&deps.storage)?;
+    // get sell token info
+    let sell_token_info = sell_contract.token_info_query(&deps.querier)?;
+    let sell_decimals = sell_token_info.decimals;
+    let sell_key = token_key(
+        &deps.api.canonical_address(&sell_contract.address)?,
+        sell_contract.token_id.as_ref(),
+    );
+    let may_sell_symdec = load_symdec(&deps.storage, &sell_key)?;
+    // get bid token info
+    let bid_token_info = bid_contract.token_info_query(&deps.querier)?;
+    let bid_decimals = bid_token_info.decimals;
+    let bid_key = token_key(
+        &deps.api.canonical_address(&bid_contract.address)?,
+        bid_contract.token_id.as_ref(),
+    );
+    let may_bid_symdec = load_symdec(&deps.storage, &bid_key)?;
+    let label = label.unwrap_or_else(|| {
+        format!(
+            "auction-{}-{}-{}",
+            config.index, sell_token_info.symbol, bid_token_info.symbol
+        )
+    });
+    // labels must be unique, so catch duplicates before the instantiation fails
+    let label_store = ReadonlyPrefixedStorage::new(PREFIX_LABELS, &deps.storage);
+    if may_load::<u32, _>(&label_store, label.as_bytes())?.is_some() {
+        return Err(StdError::generic_err(format!(
+            "An auction with the label {} already exists",
+            label
+        )));
+    }
+    // make sure tokens we already know still report the same number of decimals, otherwise the
+    // auction lists would display the wrong amounts
+    if check_limits {
+        for (may_symdec, decimals, address) in &[
+            (&may_sell_symdec, sell_decimals, &sell_contract.address),
+            (&may_bid_symdec, bid_decimals, &bid_contract.address),
+        ] {
+            if let Some(symdec) = may_symdec {
+                if symdec.decimals != *decimals {
+                    return Err(StdError::generic_err(format!(
+                        "Token {} now reports {} decimals, but the factory has it recorded as {}",
+                        address, decimals, symdec.decimals
+                    )));
+                }
+            }
+        }
+    }
+    // reject a minimum bid that is more than the allowed multiple of the sell amount
+    if let Some(multiple) = config.max_bid_multiple.filter(|_| check_limits) {
+        if exceeds_bid_multiple(
+            sell_amount.u128(),
+            sell_decimals,
+            minimum_bid.u128(),
+            bid_decimals,
+            multiple,
+        ) {
+            return Err(StdError::generic_err(format!(
+                "Minimum bid can not be more than {} times the sell amount",
+                multiple
+            )));
+        }
+    }
+    // get the decimals of any bundled tokens
+    let mut auction_bundle = Vec::new();
+    for item in bundle {
+        let decimals = item.contract.token_info_query(&deps.querier)?.decimals;
+        auction_bundle.push(AuctionBundleItem {
+            contract: item.contract,
+            amount: item.amount,
+            decimals,
+        });
+    }
+    let mut new_tokens = Vec::new();
+    if may_sell_symdec.is_none() {
+        let symdec = TokenSymDec {
+            symbol: sell_token_info.symbol,
+            decimals: sell_decimals,
+        };
+        new_tokens.push((sell_key.clone(), symdec));
+    }
+    if may_bid_symdec.is_none() && bid_key != sell_key {
+        let symdec = TokenSymDec {
+            symbol: bid_token_info.symbol,
+            decimals: bid_decimals,
+        };
+        new_tokens.push((bid_key, symdec));
+    }
+
+    let init = AuctionInitMsg {
+        factory,
+        index: config.index,
+        label,
+        seller,
+        sell_contract,
+        sell_decimals,
+        bid_contract,
+        bid_decimals,
+        sell_amount,
+        minimum_bid,
+        ends_at,
+        description,
+        descriptions,
+        bundle: auction_bundle,
+        deposit,
+        consign_by,
+        buy_now_price,
+        no_self_bid,
+        only_increasing_rebids,
+        tie_break,
+        allowance_bids,
+        message_board,
+        callback_key: String::new(),
+        block_size: config.block_size,
+        min_bid_decay,
+        seller_confirmation,
+        unit_price_bids,
+        max_bidders,
+        min_bidders,
+        reveal_window,
+        settlement,
+        raffle_seed: None,
+        funding_target,
+    };
+
+    Ok(PreparedAuction {
+        init,
+        new_tokens,
+        config,
+    })
+}
+
+/// Returns StdResult<(CosmosMsg, u32, String)>
+///
+/// creates the message that instantiates a new auction and returns it along with the index
+/// and label given to the auction.  All token queries are performed before anything is saved,
+/// so an error will not leave a partial update behind
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - a reference to the Env of contract's environment
+/// * `auction` - NewAuction parameters of the auction
+/// * `check_limits` - true if the decimals and minimum bid limits should be enforced
+pub fn new_auction_msg<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    auction: NewAuction,
+    check_limits: bool,
+) -> StdResult<(CosmosMsg, u32, String)> {
+    let PreparedAuction {
+        mut init,
+        new_tokens,
+        mut config,
+    } = prepare_auction(deps, this_contract(env), auction, check_limits)?;
+    // record any token the factory has not seen before
+    for (key, symdec) in new_tokens.iter() {
+        add_token(&mut deps.storage, key, symdec)?;
+    }
+    let label = init.label.clone();
+    let index = config.index;
+
+    // save label and only register an auction giving the matching label
+    save(&mut deps.storage, PENDING_KEY, &label)?;
+    let mut label_store = PrefixedStorage::new(PREFIX_LABELS, &mut deps.storage);
+    save(&mut label_store, label.as_bytes(), &index)?;
+    // create the key the auction will use to authenticate its callbacks
+    let prng_seed = load_seed(&deps.storage)?;
+    let callback_key = ViewingKey::new(env, &prng_seed, &index.to_le_bytes());
+    // a raffle draws its winner from a seed only the auction knows
+    if init.settlement == SettlementMode::Raffle {
+        let entropy = [b"raffle".as_ref(), &index.to_le_bytes()].concat();
+        init.raffle_seed = Some(Binary::from(
+            &Prng::new(&prng_seed, &entropy).rand_bytes()[..],
+        ));
+    }
+    let mut key_store = PrefixedStorage::new(PREFIX_CALLBACK_KEYS, &mut deps.storage);
+    save(
+        &mut key_store,
+        &index.to_le_bytes(),
+        &callback_key.to_hashed(),
+    )?;
+    init.callback_key = callback_key.0;
+
+    // remember which code the auction runs, so the factory knows which messages it understands
+    let mut hash_store = PrefixedStorage::new(PREFIX_CODE_HASHES, &mut deps.storage);
+    save(
+        &mut hash_store,
+        &index.to_le_bytes(),
+        &config.version.code_hash,
+    )?;
+    // increment the index for the next auction
+    config.index += 1;
+    save(&mut deps.storage, CONFIG_KEY, &config)?;
+
+    let cosmosmsg = init.to_cosmos_msg(
+        label.clone(),
+        config.version.code_id,
+        config.version.code_hash,
+        None,
+    )?;
+
+    Ok((cosmosmsg, index, label))
+}
+
+/// Returns Vec<u8>
+///
+/// creates the key used to map a token to its symbol and decimals
+///
+/// # Arguments
+///
+/// * `address` - a reference to the canonical address of the token contract
+/// * `token_id` - optional id of the token if it is a SNIP-1155 token
+pub fn token_key(address: &CanonicalAddr, token_id: Option<&String>) -> Vec<u8> {
+    let mut key = address.as_slice().to_vec();
+    if let Some(id) = token_id {
+        key.extend_from_slice(id.as_bytes());
+    }
+    key
+}
+
+/// Returns StdResult<Option<TokenSymDec>>
+///
+/// loads the symbol and decimals the factory recorded for a token.  None if no auction has used
+/// the token yet
+///
+/// # Arguments
+///
+/// * `storage` - a reference to the contract's storage
+/// * `key` - the token's key created by token_key
+pub fn load_symdec<S: ReadonlyStorage>(storage: &S, key: &[u8]) -> StdResult<Option<TokenSymDec>> {
+    let symdec = may_load(&ReadonlyPrefixedStorage::new(PREFIX_SYMDECS, storage), key)?;
+    if symdec.is_some() || storage.get(CONFIG_KEY).is_some() {
+        return Ok(symdec);
+    }
+    // a factory that has not upgraded its config yet still has its tokens in the original list
+    let may_index = may_load::<LegacyConfig, _>(storage, LEGACY_CONFIG_KEY)?
+        .and_then(|legacy| legacy.symdecmap.get(key).copied());
+    if let Some(index) = may_index {
+        let mut symdecs: Vec<TokenSymDec> =
+            may_load(storage, LEGACY_SYMDEC_KEY)?.unwrap_or_default();
+        if (index as usize) < symdecs.len() {
+            return Ok(Some(symdecs.swap_remove(index as usize)));
+        }
+    }
+    Ok(None)
+}
+
+/// Returns StdResult<Option<(TokenSymDec, TokenSymDec)>>
+///
+/// loads the symbols and decimals of an auction's sell and bid tokens.  None if either token is
+/// unknown
+///
+/// # Arguments
+///
+/// * `storage` - a reference to the contract's storage
+/// * `sell_key` - the sell token's key
+/// * `bid_key` - the bid token's key
+pub fn load_pair<S: ReadonlyStorage>(
+    storage: &S,
+    sell_key: &[u8],
+    bid_key: &[u8],
+) -> StdResult<Option<(TokenSymDec, TokenSymDec)>> {
+    Ok(load_symdec(storage, sell_key)?.zip(load_symdec(storage, bid_key)?))
+}
+
+/// Returns StdResult<()>
+///
+/// records the symbol and decimals of a token the factory has not seen before
+///
+/// # Arguments
+///
+/// * `storage` - a mutable reference to the contract's storage
+/// * `key` - the token's key created by token_key
+/// * `symdec` - a reference to the token's symbol and decimals
+fn add_token<S: Storage>(storage: &mut S, key: &[u8], symdec: &TokenSymDec) -> StdResult<()> {
+    let mut symdec_store = PrefixedStorage::new(PREFIX_SYMDECS, storage);
+    save(&mut symdec_store, key, symdec)?;
+    let count: u32 = may_load(storage, TOKEN_COUNT_KEY)?.unwrap_or(0);
+    save(storage, TOKEN_COUNT_KEY, &(count + 1))
+}
+
+/// Returns Config
+///
+/// converts the original factory's config to the current format.  Settings the original
+/// factory did not have get the same defaults init gives them
+///
+/// # Arguments
+///
+/// * `legacy` - the original factory's config
+fn upgraded_config(legacy: LegacyConfig) -> Config {
+    Config {
+        version: legacy.version,
+        index: legacy.index,
+        status: FactoryStatus {
+            creation_stopped: legacy.stopped,
+            ..FactoryStatus::default()
+        },
+        deprecated: Vec::new(),
+        revoked: Vec::new(),
+        admin: legacy.admin,
+        min_sell_amount: 1,
+        max_bid_multiple: None,
+        block_size: BLOCK_SIZE as u16,
+        creation_fee: None,
+        referral_share: 0,
+    }
+}
+
+/// Returns StdResult<Config>
+///
+/// loads the factory config.  If the factory has not handled a message since being upgraded from
+/// the original config format, its config is converted without saving it, so queries work
+/// before the first handle upgrades the storage
+///
+/// # Arguments
+///
+/// * `storage` - a reference to the contract's storage
+pub fn load_config<S: ReadonlyStorage>(storage: &S) -> StdResult<Config> {
+    if let Some(config) = may_load(storage, CONFIG_KEY)? {
+        return Ok(config);
+    }
+    match may_load::<LegacyConfig, _>(storage, LEGACY_CONFIG_KEY)? {
+        Some(legacy) => Ok(upgraded_config(legacy)),
+        None => load(storage, CONFIG_KEY),
+    }
+}
+
+/// Returns StdResult<u32>
+///
+/// the number of tokens the factory has recorded, including those still held in the original
+/// factory's token index map
+///
+/// # Arguments
+///
+/// * `storage` - a reference to the contract's storage
+pub fn token_count<S: ReadonlyStorage>(storage: &S) -> StdResult<u32> {
+    if let Some(count) = may_load(storage, TOKEN_COUNT_KEY)? {
+        return Ok(count);
+    }
+    Ok(may_load::<LegacyConfig, _>(storage, LEGACY_CONFIG_KEY)?
+        .map_or(0, |legacy| legacy.symdecmap.len() as u32))
+}
+
+/// Returns StdResult<()>
+///
+/// moves the token data out of the original factory's config and symdec list into storage keyed
+/// by token, so adding a token no longer rewrites the config.  Does nothing once the config has
+/// been upgraded
+///
+/// # Arguments
+///
+/// * `storage` - a mutable reference to the contract's storage
+pub fn upgrade_config<S: Storage>(storage: &mut S) -> StdResult<()> {
+    if storage.get(CONFIG_KEY).is_some() {
+        return Ok(());
+    }
+    if let Some(legacy) = may_load::<LegacyConfig, _>(storage, LEGACY_CONFIG_KEY)? {
+        let symdecs: Vec<TokenSymDec> = may_load(storage, LEGACY_SYMDEC_KEY)?.unwrap_or_default();
+        for (key, index) in legacy.symdecmap.iter() {
+            if let Some(symdec) = symdecs.get(*index as usize) {
+                let mut symdec_store = PrefixedStorage::new(PREFIX_SYMDECS, storage);
+                save(&mut symdec_store, key, symdec)?;
+            }
+        }
+        save(storage, TOKEN_COUNT_KEY, &(legacy.symdecmap.len() as u32))?;
+        remove(storage, LEGACY_SYMDEC_KEY);
+        save(storage, CONFIG_KEY, &upgraded_config(legacy))?;
+        remove(storage, LEGACY_CONFIG_KEY);
+    }
+    Ok(())
+}
+
+/// Returns HandleResult
+///
+/// Registers the calling auction by saving its info and adding it to the appropriate lists
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `seller` - reference to the address of the auction's seller
+/// * `reg_auction` - reference to RegisterAuctionInfo of the auction that is trying to register
+/// * `sell_contract` - ContractInfo of the sale token
+/// * `bundle` - additional tokens sold in the same lot
+/// * `bid_contract` - ContractInfo of the bid token
+/// * `deposit` - optional amount of the seller's deposit
+/// * `callback_key` - optional key the factory gave the auction to authenticate its callbacks
+#[allow(clippy::too_many_arguments)]
+pub fn try_register_auction<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    seller: HumanAddr,
+    reg_auction: &RegisterAuctionInfo,
+    sell_contract: ContractInfo,
+    bundle: Vec<BundleItem>,
+    bid_contract: Option<ContractInfo>,
+    deposit: Option<Uint128>,
+    callback_key: Option<&str>,
+) -> HandleResult {
+    // verify this is the auction we are waiting for
+    let load_label: Option<String> = may_load(&deps.storage, PENDING_KEY)?;
+    let auth_label =
+        load_label.ok_or_else(|| StdError::generic_err("Unable to authenticate registration."))?;
+    if auth_label != reg_auction.label {
+        return Err(StdError::generic_err(
+            "Label does not match the auction we are creating",
+        ));
+    }
+    if !is_callback_key_valid(&deps.storage, reg_auction.index, callback_key)? {
+        return Err(StdError::generic_err(
+            "Unable to authenticate registration.",
+        ));
+    }
+    let bid_contract = bid_contract
+        .ok_or_else(|| StdError::generic_err("Registration is missing the bid token contract"))?;
+    remove(&mut deps.storage, PENDING_KEY);
+
+    // convert register auction info to storage format
+    let auction_addr = deps.api.canonical_address(&env.message.sender)?;
+    let config: Config = load_config(&deps.storage)?;
+    let sell_key = token_key(
+        &deps.api.canonical_address(&sell_contract.address)?,
+        sell_contract.token_id.as_ref(),
+    );
+    let bid_key = token_key(
+        &deps.api.canonical_address(&bid_contract.address)?,
+        bid_contract.token_id.as_ref(),
+    );
+    let auction =
+        reg_auction.to_store_auction_info(auction_addr, config.version.code_id, sell_key, bid_key);
+
+    // save the auction info keyed by its index
+    let mut info_store = PrefixedStorage::new(PREFIX_ACTIVE_INFO, &mut deps.storage);
+    save(&mut info_store, &reg_auction.index.to_le_bytes(), &auction)?;
+    if !bundle.is_empty() {
+        let mut bundled_store = PrefixedStorage::new(PREFIX_BUNDLED, &mut deps.storage);
+        save(
+            &mut bundled_store,
+            &reg_auction.index.to_le_bytes(),
+            &(bundle.len() as u8),
+        )?;
+    }
+    add_to_ending_index(&mut deps.storage, auction.ends_at, reg_auction.index)?;
+
+    // add the auction address to list of active auctions
+    let mut active: HashSet<u32> = load(&deps.storage, ACTIVE_KEY)?;
+    active.insert(reg_auction.index);
+    save(&mut deps.storage, ACTIVE_KEY, &active)?;
+
+    // get list of seller's active auctions
+    let seller_raw = &deps.api.canonical_address(&seller)?;
+    // remember the seller in case the auction is matched with a complementary auction
+    let mut auction_seller_store = PrefixedStorage::new(PREFIX_AUCTION_SELLERS, &mut deps.storage);
+    save(
+        &mut auction_seller_store,
+        &reg_auction.index.to_le_bytes(),
+        seller_raw,
+    )?;
+    let mut seller_store =
+        PrefixedStorage::new(AddressList::SellerActive.prefix(), &mut deps.storage);
+    let load_auctions: Option<HashSet<u32>> = may_load(&seller_store, seller_raw.as_slice())?;
+    let mut my_active = load_auctions.unwrap_or_default();
+    // add this auction to seller's list
+    my_active.insert(reg_auction.index);
+    save(&mut seller_store, seller_raw.as_slice(), &my_active)?;
+
+    // tell the auction explicitly that the tokens are a consignment (or the seller's deposit)
+    let consign = to_binary(&AuctionReceiveMsg::Consign {})?;
+    // series rounds are consigned from the factory's escrow
+    let round_store = ReadonlyPrefixedStorage::new(PREFIX_SERIES_ROUNDS, &deps.storage);
+    let may_series: Option<u32> = may_load(&round_store, &reg_auction.index.to_le_bytes())?;
+    let consign_msg = if let Some(id) = may_series {
+        let mut series_store = PrefixedStorage::new(PREFIX_SERIES, &mut deps.storage);
+        let mut series: Series = load(&series_store, &id.to_le_bytes())?;
+        if let Some(round) = series
+            .history
+            .iter_mut()
+            .find(|r| r.index == reg_auction.index)
+        {
+            round.address = Some(deps.api.canonical_address(&env.message.sender)?);
+        }
+        save(&mut series_store, &id.to_le_bytes(), &series)?;
+        sell_contract.send_msg(
+            &env.contract.address,
+            env.message.sender.clone(),
+            reg_auction.sell_amount,
+            Some(consign.clone()),
+        )?
+    } else {
+        sell_contract.send_from_msg(
+            seller.clone(),
+            env.message.sender.clone(),
+            reg_auction.sell_amount,
+            Some(consign.clone()),
+        )?
+    };
+    // consign the sell tokens and any bundled tokens
+    let mut messages = vec![consign_msg];
+    for item in bundle {
+        messages.push(item.contract.send_from_msg(
+            seller.clone(),
+            env.message.sender.clone(),
+            item.amount,
+            Some(consign.clone()),
+        )?);
+    }
+    // and post the seller's deposit
+    if let Some(amount) = deposit {
+        messages.push(bid_contract.send_from_msg(
+            seller.clone(),
+            env.message.sender.clone(),
+            amount,
+            Some(consign),
+        )?);
+    }
+    // let the auction validate the seller's viewing key without asking the factory
+    if let Some(msg) = share_key_msg(
+        &deps.storage,
+        reg_auction.index,
+        &env.message.sender,
+        &seller,
+        seller_raw,
+    )? {
+        messages.push(msg);
+    }
+
+    Ok(HandleResponse {
+        messages,
+        log: vec![log("auction_address", env.message.sender)],
+        data: None,
+    })
+}
+
+/// Returns HandleResult
+///
+/// closes the calling auction by saving its info and adding/removing it to/from the
+/// appropriate lists
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `index` - auction index
+/// * `seller` - reference to the address of the auction's seller
+/// * `bidder` - reference to the auction's winner if it had one
+/// * `winning_bid` - auction's winning bid if it had one
+/// * `co_winners` - other bidders that split the lot with the winner because of a tie
+/// * `referrer` - reference to the address that referred the winning bidder if there was one
+/// * `unmet_min_bidders` - number of distinct bidders the auction needed but did not reach
+/// * `callback_key` - optional key the factory gave the auction to authenticate its callbacks
+#[allow(clippy::too_many_arguments)]
+pub fn try_close_auction<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    index: u32,
+    seller: &HumanAddr,
+    bidder: Option<&HumanAddr>,
+    winning_bid: Option<Uint128>,
+    co_winners: &[HumanAddr],
+    referrer: Option<&HumanAddr>,
+    unmet_min_bidders: Option<u32>,
+    callback_key: Option<&str>,
+) -> HandleResult {
+    let auction_addr = &deps.api.canonical_address(&env.message.sender)?;
+
+    // verify auction is in active list of auctions and not a spam attempt
+    let (may_active, may_info, may_error) =
+        authenticate_auction(&deps.storage, auction_addr, index, callback_key)?;
+    if let Some(error) = may_error {
+        return error;
+    }
+    // the auction will not make any more callbacks
+    let mut key_store = PrefixedStorage::new(PREFIX_CALLBACK_KEYS, &mut deps.storage);
+    key_store.remove(&index.to_le_bytes());
+    let mut hash_store = PrefixedStorage::new(PREFIX_CODE_HASHES, &mut deps.storage);
+    hash_store.remove(&index.to_le_bytes());
+    let mut retry_store = PrefixedStorage::new(PREFIX_CLOSE_RETRY, &mut deps.storage);
+    retry_store.remove(&index.to_le_bytes());
+    let mut bundled_store = PrefixedStorage::new(PREFIX_BUNDLED, &mut deps.storage);
+    bundled_store.remove(&index.to_le_bytes());
+    let mut auction_seller_store = PrefixedStorage::new(PREFIX_AUCTION_SELLERS, &mut deps.storage);
+    auction_seller_store.remove(&index.to_le_bytes());
+    // delete the active auction info
+    let mut info_store = PrefixedStorage::new(PREFIX_ACTIVE_INFO, &mut deps.storage);
+    info_store.remove(&index.to_le_bytes());
+    // remove the auction from the active list
+    let mut active = may_active.unwrap();
+    active.remove(&index);
+    save(&mut deps.storage, ACTIVE_KEY, &active)?;
+
+    // set the closed auction info
+    let timestamp = env.block.time;
+    let auction_info = may_info.unwrap();
+    remove_from_ending_index(&mut deps.storage, auction_info.ends_at, index)?;
+    remove_from_tag_index(&mut deps.storage, index)?;
+    let winners_raw = bidder
+        .into_iter()
+        .chain(co_winners.iter())
+        .map(|w| deps.api.canonical_address(w))
+        .collect::<StdResult<Vec<CanonicalAddr>>>()?;
+    let closed_info = auction_info.to_store_closed_auction_info(
+        winning_bid.map(|n| n.u128()),
+        winners_raw,
+        timestamp,
+    );
+    let mut closed_info_store = PrefixedStorage::new(PREFIX_CLOSED_INFO, &mut deps.storage);
+    save(&mut closed_info_store, &index.to_le_bytes(), &closed_info)?;
+    // add the auction to the closing order, and remember its position for paging
+    let mut order_store = PrefixedStorage::new(PREFIX_CLOSED_ORDER, &mut deps.storage);
+    let mut order = AppendStoreMut::attach_or_create(&mut order_store)?;
+    let position = order.len();
+    order.push(&index)?;
+    let mut pos_store = PrefixedStorage::new(PREFIX_CLOSED_POSITIONS, &mut deps.storage);
+    save(&mut pos_store, &index.to_le_bytes(), &position)?;
+    // list a sale with its pair's other sales
+    if winning_bid.is_some() {
+        let mut sales_store = PrefixedStorage::multilevel(
+            &[
+                PREFIX_PAIR_SALES,
+                &auction_info.sell_token,
+                &auction_info.bid_token,
+            ],
+            &mut deps.storage,
+        );
+        let mut sales = AppendStoreMut::attach_or_create(&mut sales_store)?;
+        sales.push(&index)?;
+    }
+    // move the nickname to the closed auctions' nicknames
+    let mut nick_store = PrefixedStorage::new(PREFIX_NICKNAMES, &mut deps.storage);
+    let may_nickname: Option<String> = may_load(&nick_store, &index.to_le_bytes())?;
+    if let Some(nickname) = may_nickname {
+        nick_store.remove(&index.to_le_bytes());
+        let mut closed_nick_store =
+            PrefixedStorage::new(PREFIX_CLOSED_NICKNAMES, &mut deps.storage);
+        save(&mut closed_nick_store, &index.to_le_bytes(), &nickname)?;
+    }
+
+    // record the terms of the settlement
+    let (sell_symbol, sell_decimals) = load_symdec(&deps.storage, &auction_info.sell_token)?
+        .map_or((String::new(), 0), |s| (s.symbol, s.decimals));
+    let (bid_symbol, bid_decimals) = load_symdec(&deps.storage, &auction_info.bid_token)?
+        .map_or((String::new(), 0), |s| (s.symbol, s.decimals));
+    let pair = format!("{}-{}", sell_symbol, bid_symbol);
+    let winners: Vec<&str> = bidder
+        .into_iter()
+        .chain(co_winners.iter())
+        .map(|w| w.as_str())
+        .collect();
+    let terms = format!(
+        "{}:{}:{}:{}:{}:{}:{}:{}",
+        index,
+        env.message.sender,
+        pair,
+        auction_info.sell_amount,
+        winning_bid.map_or_else(|| "none".to_string(), |w| w.to_string()),
+        seller,
+        winners.join(","),
+        timestamp
+    );
+    let settlement = StoreSettlementRecord {
+        address: auction_addr.clone(),
+        pair,
+        sell_amount: auction_info.sell_amount,
+        sell_decimals,
+        winning_bid: winning_bid.map(|n| n.u128()),
+        bid_decimals,
+        winners: winners.len() as u32,
+        unmet_min_bidders,
+        timestamp,
+        digest: sha_256(terms.as_bytes()).to_vec(),
+    };
+    let mut settle_store = PrefixedStorage::new(PREFIX_SETTLEMENTS, &mut deps.storage);
+    save(&mut settle_store, &index.to_le_bytes(), &settlement)?;
+
+    // remove auction from seller's active list
+    let seller_raw = &deps.api.canonical_address(seller)?;
+    remove_from_persons_active(
+        &mut deps.storage,
+        AddressList::SellerActive,
+        seller_raw,
+        index,
+    )?;
+    // add to seller's closed list
+    let pruned: u32 = may_load(&deps.storage, PRUNED_KEY)?.unwrap_or(0);
+    add_to_persons_closed(
+        &mut deps.storage,
+        AddressList::SellerClosed,
+        seller_raw,
+        index,
+        pruned,
+    )?;
+
+    // if auction had a winner (or several that split the lot)
+    for winner in bidder.into_iter().chain(co_winners.iter()) {
+        let winner_raw = &deps.api.canonical_address(winner)?;
+        // clean up the bidders list of active auctions
+        let mut bidder_store =
+            PrefixedStorage::new(AddressList::Bidder.prefix(), &mut deps.storage);
+        let (win_active, _) = filter_only_active(&bidder_store, winner_raw, &mut active)?;
+        save(&mut bidder_store, winner_raw.as_slice(), &win_active)?;
+        // add to winner's closed list
+        add_to_persons_closed(
+            &mut deps.storage,
+            AddressList::Winner,
+            winner_raw,
+            index,
+            pruned,
+        )?;
+    }
+
+    // pay the referrers their share of the creation fee if the lot sold
+    let mut messages = pay_referrals(deps, &env, index, winning_bid.is_some(), referrer)?;
+    // if this was a series round, create the next round
+    let round_store = ReadonlyPrefixedStorage::new(PREFIX_SERIES_ROUNDS, &deps.storage);
+    let may_series: Option<u32> = may_load(&round_store, &index.to_le_bytes())?;
+    if let Some(id) = may_series {
+        if let Some(msg) = next_series_round(deps, &env, id, index, winning_bid)? {
+            messages.push(msg);
+        }
+    }
+    // log the close for any contracts subscribed to the pair
+    record_pair_close(
+        &mut deps.storage,
+        &auction_info.sell_token,
+        &auction_info.bid_token,
+        &StorePairClose {
+            index,
+            auction: auction_addr.clone(),
+            sell_amount: auction_info.sell_amount,
+            winning_bid: winning_bid.map(|n| n.u128()),
+            timestamp,
+        },
+    )?;
+
+    Ok(HandleResponse {
+        messages,
+        log: vec![],
+        data: None,
+    })
+}
+
+/// Returns HandleResult
+///
+/// changes the closing time, minimum bid, and/or sell amount of an auction
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `index` - auction index
+/// * `ends_at` - optional new closing time
+/// * `minimum_bid` - optional new minimum bid
+/// * `sell_amount` - optional new sell amount
+/// * `callback_key` - optional key the factory gave the auction to authenticate its callbacks
+pub fn try_change_auction_info<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    index: u32,
+    ends_at: Option<u64>,
+    minimum_bid: Option<Uint128>,
+    sell_amount: Option<Uint128>,
+    callback_key: Option<&str>,
+) -> HandleResult {
+    let auction_addr = &deps.api.canonical_address(&env.message.sender)?;
+
+    // verify auction is in active list of auctions and not a spam attempt
+    let (_may_active, may_info, may_error) =
+        authenticate_auction(&deps.storage, auction_addr, index, callback_key)?;
+    if let Some(error) = may_error {
+        return error;
+    }
+
+    let mut auction_info = may_info.unwrap();
+    if let Some(min_bid) = minimum_bid {
+        auction_info.minimum_bid = min_bid.u128();
+    }
+    if let Some(amount) = sell_amount {
+        // a seller can not get around the creation limits by lowering the sell amount later
+        let config: Config = load_config(&deps.storage)?;
+        if amount.u128() < config.min_sell_amount {
+            return Err(StdError::generic_err(format!(
+                "Sell amount must be at least {}",
+                config.min_sell_amount
+            )));
+        }
+        auction_info.sell_amount = amount.u128();
+    }
+    if let Some(ends) = ends_at {
+        // move the auction to its new bucket of the closing time index
+        remove_from_ending_index(&mut deps.storage, auction_info.ends_at, index)?;
+        add_to_ending_index(&mut deps.storage, ends, index)?;
+        auction_info.ends_at = ends;
+    }
+    let mut info_store = PrefixedStorage::new(PREFIX_ACTIVE_INFO, &mut deps.storage);
+    save(&mut info_store, &index.to_le_bytes(), &auction_info)?;
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: None,
+    })
+}
+
+/// Returns HandleResult
+///
+/// moves the calling auction from its previous seller's active list to the new seller's
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `index` - auction index
+/// * `seller` - reference to the address of the auction's previous seller
+/// * `new_seller` - reference to the address of the auction's new seller
+/// * `callback_key` - optional key the factory gave the auction to authenticate its callbacks
+pub fn try_change_seller<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    index: u32,
+    seller: &HumanAddr,
+    new_seller: &HumanAddr,
+    callback_key: Option<&str>,
+) -> HandleResult {
+    let auction_addr = &deps.api.canonical_address(&env.message.sender)?;
+
+    // verify auction is in active list of auctions and not a spam attempt
+    let (_may_active, _may_info, may_error) =
+        authenticate_auction(&deps.storage, auction_addr, index, callback_key)?;
+    if let Some(error) = may_error {
+        return error;
+    }
+
+    let seller_raw = &deps.api.canonical_address(seller)?;
+    let new_raw = &deps.api.canonical_address(new_seller)?;
+    remove_from_persons_active(
+        &mut deps.storage,
+        AddressList::SellerActive,
+        seller_raw,
+        index,
+    )?;
+    let mut seller_store =
+        PrefixedStorage::new(AddressList::SellerActive.prefix(), &mut deps.storage);
+    let mut new_active: HashSet<u32> =
+        may_load(&seller_store, new_raw.as_slice())?.unwrap_or_default();
+    new_active.insert(index);
+    save(&mut seller_store, new_raw.as_slice(), &new_active)?;
+    let mut auction_seller_store = PrefixedStorage::new(PREFIX_AUCTION_SELLERS, &mut deps.storage);
+    save(&mut auction_seller_store, &index.to_le_bytes(), new_raw)?;
+    // the nickname was the previous seller's
+    let mut nick_store = PrefixedStorage::new(PREFIX_NICKNAMES, &mut deps.storage);
+    nick_store.remove(&index.to_le_bytes());
+
+    // the auction forgets the previous seller's key hash, so share the new seller's
+    let messages = share_key_msg(
+        &deps.storage,
+        index,
+        &env.message.sender,
+        new_seller,
+        new_raw,
+    )?
+    .into_iter()
+    .collect();
+
+    Ok(HandleResponse {
+        messages,
+        log: vec![],
+        data: None,
+    })
+}
+
+/// Returns HandleResult
+///
+/// registers a new bidder of the calling auction, or records the new amount of a bidder's
+/// replaced bid
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `index` - auction index
+/// * `bidder` - address of the bidder
+/// * `amount` - optional amount of the bidder's active bid
+/// * `callback_key` - optional key the factory gave the auction to authenticate its callbacks
+pub fn try_reg_bidder<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    index: u32,
+    bidder: HumanAddr,
+    amount: Option<Uint128>,
+    callback_key: Option<&str>,
+) -> HandleResult {
+    let auction_addr = &deps.api.canonical_address(&env.message.sender)?;
+
+    // verify auction is in active list of auctions and not a spam attempt
+    let (may_active, may_info, may_error) =
+        authenticate_auction(&deps.storage, auction_addr, index, callback_key)?;
+    if let Some(error) = may_error {
+        return error;
+    }
+
+    let mut active = may_active.unwrap();
+    let config: Config = load_config(&deps.storage)?;
+    // a revoked version may still close and return its bids, but can not take new ones
+    if may_info
+        .as_ref()
+        .map_or(false, |i| config.revoked.contains(&i.code_id))
+    {
+        return Err(StdError::generic_err(
+            "This auction's contract version has been revoked, so it can not accept bids",
+        ));
+    }
+    let bidder_raw = &deps.api.canonical_address(&bidder)?;
+    let listed: HashSet<u32> = may_load(
+        &ReadonlyPrefixedStorage::new(AddressList::Bidder.prefix(), &deps.storage),
+        bidder_raw.as_slice(),
+    )?
+    .unwrap_or_default();
+    // the admin may stop new bids while shutting the factory down, but a bidder may still change
+    // a bid they already placed
+    if !listed.contains(&index) {
+        let from_old_version = may_info.map_or(false, |i| i.code_id != config.version.code_id);
+        if config.status.bids_stopped || (config.status.old_versions_stopped && from_old_version) {
+            return Err(StdError::generic_err(
+                "The factory has stopped registering new bids for this auction",
+            ));
+        }
+    }
+
+    // clean up the bidders list of active auctions
+    let mut bidder_store = PrefixedStorage::new(AddressList::Bidder.prefix(), &mut deps.storage);
+    let (mut my_active, _) = filter_only_active(&bidder_store, bidder_raw, &mut active)?;
+    // add this auction to the list
+    my_active.insert(index);
+    save(&mut bidder_store, bidder_raw.as_slice(), &my_active)?;
+    // drop the amounts of bids in auctions that have since closed, and record this one
+    let mut amount_store = PrefixedStorage::multilevel(
+        &[PREFIX_BID_AMOUNTS, bidder_raw.as_slice()],
+        &mut deps.storage,
+    );
+    for closed in listed.difference(&my_active) {
+        remove(&mut amount_store, &closed.to_le_bytes());
+    }
+    match amount {
+        Some(amt) => save(&mut amount_store, &index.to_le_bytes(), &amt.u128())?,
+        None => remove(&mut amount_store, &index.to_le_bytes()),
+    }
+
+    // let the auction validate the bidder's viewing key without asking the factory
+    let messages = share_key_msg(
+        &deps.storage,
+        index,
+        &env.message.sender,
+        &bidder,
+        bidder_raw,
+    )?
+    .into_iter()
+    .collect();
+
+    Ok(HandleResponse {
+        messages,
+        log: vec![],
+        data: None,
+    })
+}
+
+/// Returns HandleResult
+///
+/// removes registration of the retracting bidder of the calling auction
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `index` - auction index
+/// * `bidder` - reference to the address of the retracting bidder
+/// * `callback_key` - optional key the factory gave the auction to authenticate its callbacks
+pub fn try_remove_bidder<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    index: u32,
+    bidder: &HumanAddr,
+    callback_key: Option<&str>,
+) -> HandleResult {
+    let auction_addr = &deps.api.canonical_address(&env.message.sender)?;
+
+    // verify auction is in active list of auctions and not a spam attempt
+    let (may_active, _may_info, may_error) =
+        authenticate_auction(&deps.storage, auction_addr, index, callback_key)?;
+    if let Some(error) = may_error {
+        return error;
+    }
+
+    let mut active = may_active.unwrap();
+
+    // clean up the bidders list of active auctions
+    let bidder_raw = &deps.api.canonical_address(&bidder)?;
+    let mut bidder_store = PrefixedStorage::new(AddressList::Bidder.prefix(), &mut deps.storage);
+    let (mut my_active, _) = filter_only_active(&bidder_store, bidder_raw, &mut active)?;
+    // remove this auction from the list
+    my_active.remove(&index);
+    save(&mut bidder_store, bidder_raw.as_slice(), &my_active)?;
+    let mut amount_store = PrefixedStorage::multilevel(
+        &[PREFIX_BID_AMOUNTS, bidder_raw.as_slice()],
+        &mut deps.storage,
+    );
+    remove(&mut amount_store, &index.to_le_bytes());
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: None,
+    })
+}
+
+/// Returns StdResult<(Option<HashSet<u32>>, Option<StoreAuctionInfo>, Option<HandleResult>)>
+///
+/// verifies that the auction is in the list of active auctions and presented the key it was
+/// given, and returns the active auction list, the auction information, or a possible error.
+/// It does not check whether the auction's contract version was revoked, so that a revoked
+/// auction can still close and return its bids
+///
+/// # Arguments
+///
+/// * `storage` - a reference to contract's storage
+/// * `auction` - a reference to the auction's address
+/// * `index` - index/key of the auction
+/// * `callback_key` - optional key the factory gave the auction to authenticate its callbacks
+#[allow(clippy::type_complexity)]
+pub fn authenticate_auction<S: ReadonlyStorage>(
+    storage: &S,
+    auction: &CanonicalAddr,
+    index: u32,
+    callback_key: Option<&str>,
+) -> StdResult<(
+    Option<HashSet<u32>>,
+    Option<StoreAuctionInfo>,
+    Option<HandleResult>,
+)> {
+    let mut error: Option<HandleResult> = None;
+    let mut info: Option<StoreAuctionInfo> = None;
+    let active: Option<HashSet<u32>> = may_load(storage, ACTIVE_KEY)?;
+    if let Some(active_set) = active.as_ref() {
+        // get the auction information
+        let info_store = ReadonlyPrefixedStorage::new(PREFIX_ACTIVE_INFO, storage);
+        info = may_load(&info_store, &index.to_le_bytes())?;
+        if let Some(auction_info) = info.as_ref() {
+            if auction_info.address != *auction
+                || !active_set.contains(&index)
+                || !is_callback_key_valid(storage, index, callback_key)?
+            {
+                error = Some(Ok(HandleResponse {
+                    messages: vec![],
+                    log: vec![log(
+                        "Unauthorized",
+                        "You are not an active auction this factory created",
+                    )],
+                    data: None,
+                }));
+            }
+        } else {
+            error = Some(Ok(HandleResponse {
+                messages: vec![],
+                log: vec![
+                    log(
+                        "Error",
+                        "Unable to register action with the factory contract",
+                    ),
+                    log("Reason", "Missing auction information"),
+                ],
+                data: None,
+            }));
+        }
+    // if you can't load the active auction list, it is an error but still let auction process
+    } else {
+        error = Some(Ok(HandleResponse {
+            messages: vec![],
+            log: vec![
+                log(
+                    "Error",
+                    "Unable to register action with the factory contract",
+                ),
+                log("Reason", "Missing active auction list"),
+            ],
+            data: None,
+        }));
+    }
+    Ok((active, info, error))
+}
+
+/// Returns StdResult<bool>
+///
+/// verifies the key an auction presented with its callback.  Auctions created before callback
+/// keys were introduced do not have a stored key, and are only authenticated by their address
+///
+/// # Arguments
+///
+/// * `storage` - a reference to contract's storage
+/// * `index` - index/key of the auction
+/// * `callback_key` - optional key the auction presented
+pub fn is_callback_key_valid<S: ReadonlyStorage>(
+    storage: &S,
+    index: u32,
+    callback_key: Option<&str>,
+) -> StdResult<bool> {
+    let key_store = ReadonlyPrefixedStorage::new(PREFIX_CALLBACK_KEYS, storage);
+    let may_hashed: Option<[u8; VIEWING_KEY_SIZE]> = may_load(&key_store, &index.to_le_bytes())?;
+    Ok(match may_hashed {
+        Some(hashed) => callback_key.map_or(false, |key| {
+            ViewingKey(key.to_string()).check_viewing_key(&hashed)
+        }),
+        None => true,
+    })
+}
+
+/// Returns bool
+///
+/// returns true if the minimum bid is worth more than the allowed multiple of the sell amount
+/// after adjusting for the decimal places of each token
+///
+/// # Arguments
+///
+/// * `sell_amount` - amount being sold in the sell token's smallest denomination
+/// * `sell_decimals` - number of decimal places of the sell token
+/// * `minimum_bid` - minimum bid in the bid token's smallest denomination
+/// * `bid_decimals` - number of decimal places of the bid token
+/// * `multiple` - the allowed multiple
+pub fn exceeds_bid_multiple(
+    sell_amount: u128,
+    sell_decimals: u8,
+    minimum_bid: u128,
+    bid_decimals: u8,
+    multiple: u128,
+) -> bool {
+    // compare minimum_bid / 10^bid_decimals against multiple * sell_amount / 10^sell_decimals
+    let limit = 10u128
+        .checked_pow(bid_decimals as u32)
+        .and_then(|p| p.checked_mul(sell_amount))
+        .and_then(|p| p.checked_mul(multiple));
+    let bid = 10u128
+        .checked_pow(sell_decimals as u32)
+        .and_then(|p| p.checked_mul(minimum_bid));
+    match (bid, limit) {
+        // if the limit is too big to represent, nothing can exceed it
+        (_, None) => false,
+        (None, Some(_)) => true,
+        (Some(b), Some(l)) => b > l,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::msg::AuctionContractInfo;
+    use cosmwasm_std::testing::MockStorage;
+    use cosmwasm_std::Binary;
+    use std::collections::HashMap;
+
+    fn addr(name: &str) -> CanonicalAddr {
+        CanonicalAddr(Binary(name.as_bytes().to_vec()))
+    }
+
+    #[test]
+    fn test_token_key() {
+        let token = addr("token");
+        let id = "1".to_string();
+        assert_eq!(token_key(&token, None), b"token".to_vec());
+        assert_eq!(token_key(&token, Some(&id)), b"token1".to_vec());
+        assert_ne!(token_key(&token, None), token_key(&token, Some(&id)));
+    }
+
+    #[test]
+    fn test_exceeds_bid_multiple() {
+        // 1 sell token for 100 bid tokens with a limit of 100
+        assert!(!exceeds_bid_multiple(1_000_000, 6, 100_000_000, 6, 100));
+        assert!(exceeds_bid_multiple(1_000_000, 6, 100_000_001, 6, 100));
+        // decimals are taken into account
+        assert!(!exceeds_bid_multiple(1, 0, 100_000_000, 6, 100));
+        assert!(exceeds_bid_multiple(1, 0, 100_000_001, 6, 100));
+        // a limit too big to represent can not be exceeded
+        assert!(!exceeds_bid_multiple(u128::MAX, 0, u128::MAX, 18, 100));
+    }
+
+    #[test]
+    fn test_authenticate_auction() {
+        let mut storage = MockStorage::new();
+        // fails without an active list
+        let (_, _, error) = authenticate_auction(&storage, &addr("auction"), 1, None).unwrap();
+        assert!(error.is_some());
+
+        let active: HashSet<u32> = vec![1].into_iter().collect();
+        save(&mut storage, ACTIVE_KEY, &active).unwrap();
+        let info = StoreAuctionInfo {
+            address: addr("auction"),
+            code_id: 1,
+            label: "auction".to_string(),
+            sell_token: b"sell".to_vec(),
+            bid_token: b"bid".to_vec(),
+            sell_amount: 10,
+            minimum_bid: 10,
+            ends_at: 1000,
+        };
+        let mut info_store = PrefixedStorage::new(PREFIX_ACTIVE_INFO, &mut storage);
+        save(&mut info_store, &1u32.to_le_bytes(), &info).unwrap();
+
+        // auctions without a callback key are authenticated by address
+        let (may_active, may_info, error) =
+            authenticate_auction(&storage, &addr("auction"), 1, None).unwrap();
+        assert!(error.is_none());
+        assert!(may_active.unwrap().contains(&1));
+        assert_eq!(may_info.unwrap().ends_at, 1000);
+        let (_, _, error) = authenticate_auction(&storage, &addr("imposter"), 1, None).unwrap();
+        assert!(error.is_some());
+        let (_, _, error) = authenticate_auction(&storage, &addr("auction"), 2, None).unwrap();
+        assert!(error.is_some());
+
+        // once a key is stored, it must be presented
+        let mut key_store = PrefixedStorage::new(PREFIX_CALLBACK_KEYS, &mut storage);
+        let hashed = ViewingKey("callbackkey".to_string()).to_hashed();
+        save(&mut key_store, &1u32.to_le_bytes(), &hashed).unwrap();
+        assert!(is_callback_key_valid(&storage, 1, Some("callbackkey")).unwrap());
+        assert!(!is_callback_key_valid(&storage, 1, Some("wrongkey")).unwrap());
+        assert!(!is_callback_key_valid(&storage, 1, None).unwrap());
+        let (_, _, error) =
+            authenticate_auction(&storage, &addr("auction"), 1, Some("wrongkey")).unwrap();
+        assert!(error.is_some());
+        let (_, _, error) =
+            authenticate_auction(&storage, &addr("auction"), 1, Some("callbackkey")).unwrap();
+        assert!(error.is_none());
+    }
+
+    /// config exactly as the original factory saved it
+    #[derive(Serialize)]
+    struct BaselineConfig {
+        version: AuctionContractInfo,
+        symdecmap: HashMap<Vec<u8>, u16>,
+        index: u32,
+        stopped: bool,
+        admin: CanonicalAddr,
+    }
+
+    /// Returns MockStorage holding the config and token list of an original factory
+    fn baseline_storage(stopped: bool) -> MockStorage {
+        let mut storage = MockStorage::new();
+        let symdecmap: HashMap<Vec<u8>, u16> = vec![(b"sell".to_vec(), 0), (b"bid".to_vec(), 1)]
+            .into_iter()
+            .collect();
+        let baseline = BaselineConfig {
+            version: AuctionContractInfo {
+                code_id: 7,
+                code_hash: "hash".to_string(),
+            },
+            symdecmap,
+            index: 3,
+            stopped,
+            admin: addr("admin"),
+        };
+        save(&mut storage, b"config", &baseline).unwrap();
+        let symdecs = vec![
+            TokenSymDec {
+                symbol: "SELL".to_string(),
+                decimals: 6,
+            },
+            TokenSymDec {
+                symbol: "BID".to_string(),
+                decimals: 18,
+            },
+        ];
+        save(&mut storage, b"symdec", &symdecs).unwrap();
+        storage
+    }
+
+    #[test]
+    fn test_upgrade_config() {
+        let mut storage = baseline_storage(true);
+
+        upgrade_config(&mut storage).unwrap();
+        assert!(storage.get(LEGACY_CONFIG_KEY).is_none());
+        assert!(storage.get(LEGACY_SYMDEC_KEY).is_none());
+        let config: Config = load(&storage, CONFIG_KEY).unwrap();
+        assert_eq!(config.version.code_id, 7);
+        assert_eq!(config.version.code_hash, "hash");
+        assert_eq!(config.index, 3);
+        assert_eq!(config.admin, addr("admin"));
+        // the original stop flag only stopped auction creation
+        assert_eq!(
+            config.status,
+            FactoryStatus {
+                creation_stopped: true,
+                bids_stopped: false,
+                old_versions_stopped: false,
+            }
+        );
+        assert!(config.deprecated.is_empty());
+        assert!(config.revoked.is_empty());
+        assert_eq!(config.min_sell_amount, 1);
+        assert_eq!(config.max_bid_multiple, None);
+        assert_eq!(config.block_size, BLOCK_SIZE as u16);
+        assert!(config.creation_fee.is_none());
+        assert_eq!(config.referral_share, 0);
+        let (sell, bid) = load_pair(&storage, b"sell", b"bid").unwrap().unwrap();
+        assert_eq!(sell.symbol, "SELL");
+        assert_eq!(bid.decimals, 18);
+        assert!(load_symdec(&storage, b"other").unwrap().is_none());
+        assert_eq!(token_count(&storage).unwrap(), 2);
+
+        // an upgraded config is left alone
+        let symdec = TokenSymDec {
+            symbol: "NEW".to_string(),
+            decimals: 0,
+        };
+        add_token(&mut storage, b"new", &symdec).unwrap();
+        upgrade_config(&mut storage).unwrap();
+        assert_eq!(
+            load_symdec(&storage, b"new").unwrap().unwrap().symbol,
+            "NEW"
+        );
+        assert_eq!(token_count(&storage).unwrap(), 3);
+        let config: Config = load(&storage, CONFIG_KEY).unwrap();
+        assert_eq!(config.index, 3);
+    }
+
+    #[test]
+    fn test_read_config_before_upgrade() {
+        // queries can not upgrade the storage, so they read the original format directly
+        let storage = baseline_storage(false);
+        let config = load_config(&storage).unwrap();
+        assert_eq!(config.version.code_id, 7);
+        assert_eq!(config.index, 3);
+        assert_eq!(config.status, FactoryStatus::default());
+        let (sell, bid) = load_pair(&storage, b"sell", b"bid").unwrap().unwrap();
+        assert_eq!(sell.decimals, 6);
+        assert_eq!(bid.symbol, "BID");
+        assert!(load_symdec(&storage, b"other").unwrap().is_none());
+        assert_eq!(token_count(&storage).unwrap(), 2);
+        assert!(storage.get(CONFIG_KEY).is_none());
+
+        // a factory with no config at all is still an error
+        assert!(load_config(&MockStorage::new()).is_err());
+    }
+}
@@ -1,29 +1,59 @@
 use serde::Serialize;
 
 use cosmwasm_std::{
-    log, to_binary, Api, Binary, CanonicalAddr, Env, Extern, HandleResponse, HandleResult,
+    to_binary, Api, Binary, CanonicalAddr, CosmosMsg, Env, Extern, HandleResponse, HandleResult,
     HumanAddr, InitResponse, InitResult, Querier, QueryResult, ReadonlyStorage, StdError,
     StdResult, Storage, Uint128,
 };
 
 use cosmwasm_storage::{PrefixedStorage, ReadonlyPrefixedStorage};
 
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeSet, HashSet};
 
 use secret_toolkit::{
-    snip20::{send_from_msg, token_info_query},
-    storage::{AppendStore, AppendStoreMut},
-    utils::{pad_handle_result, pad_query_result, InitCallback},
+    storage::AppendStore,
+    utils::{pad_handle_result, pad_query_result, HandleCallback},
 };
 
+use auction_common::crypto::{fold_entropy, init_seed};
+use auction_common::math::checked_mul;
+
+use crate::allowlist::{
+    check_allowlisted, try_allowlist_info, try_set_allowlist_mode, try_update_allowlist,
+};
+use crate::auctions::{
+    check_new_auction, load_config, load_symdec, new_auction_msg, token_key,
+    try_change_auction_info, try_change_seller, try_close_auction, try_create_auction,
+    try_reg_bidder, try_register_auction, try_remove_bidder, upgrade_config, NewAuction,
+};
+use crate::lists::{
+    filter_only_active, remove_from_ending_index, remove_from_persons_active,
+    remove_from_tag_index, AddressList,
+};
 use crate::msg::{
-    AuctionContractInfo, AuctionInfo, ClosedAuctionInfo, ContractInfo, FilterTypes, HandleAnswer,
-    HandleMsg, InitMsg, MyActiveLists, MyClosedLists, QueryAnswer, QueryMsg, RegisterAuctionInfo,
-    ResponseStatus::Success, StoreAuctionInfo, StoreClosedAuctionInfo,
+    AuctionContractInfo, ContractInfo, CreationFee, FactoryStatus, HandleAnswer, HandleMsg,
+    IbcOrigin, InitMsg, QueryMsg, ResponseStatus::Success, SettlementMode, StoreAuctionInfo,
+    StoreClosedAuctionInfo, TieBreak,
+};
+use crate::queries::{
+    try_admin_auction_info, try_auction_by_label, try_bid_totals, try_get_auctions,
+    try_list_active, try_list_by_tag, try_list_closed, try_list_ending_soon, try_list_my,
+    try_list_series, try_list_watched, try_preview_create_auction, try_query_config,
+    try_query_status, try_recent_prices, try_settlement_record, try_storage_info,
+};
+use crate::state::{
+    load, may_load, remove, save, Config, PendingDeprecation, Series, SeriesRound,
+    StoredCreationFee, TokenSymDec,
+};
+use crate::subscribers::{
+    try_add_close_subscriber, try_list_close_subscribers, try_list_pair_closes,
+    try_remove_close_subscriber, try_set_close_subscriber_status,
+};
+use crate::treasury::{
+    release_auction_fee, try_referral_earnings, try_set_referral_share, try_treasury_balances,
+    try_treasury_records, try_withdraw,
 };
-use crate::rand::sha_256;
-use crate::state::{load, may_load, remove, save, Config, TokenSymDec};
-use crate::viewing_key::{ViewingKey, VIEWING_KEY_SIZE};
+use crate::viewing_keys::{try_create_key, try_set_key, try_validate_key, try_validate_keys};
 
 /// prefix for storage of sellers' closed auctions
 pub const PREFIX_SELLERS_CLOSED: &[u8] = b"sellersclosed";
@@ -31,27 +61,175 @@ pub const PREFIX_SELLERS_CLOSED: &[u8] = b"sellersclosed";
 pub const PREFIX_SELLERS_ACTIVE: &[u8] = b"sellersactive";
 /// prefix for storage of bidders' active auctions
 pub const PREFIX_BIDDERS: &[u8] = b"bidders";
+/// prefix for storage of the amounts of bidders' active bids
+pub const PREFIX_BID_AMOUNTS: &[u8] = b"bidamounts";
 /// prefix for storage of bidders' won auctions
 pub const PREFIX_WINNERS: &[u8] = b"winners";
+/// prefix for storage of addresses' watchlists of active auctions
+pub const PREFIX_WATCHERS: &[u8] = b"watchers";
 /// prefix for storage of an active auction info
 pub const PREFIX_ACTIVE_INFO: &[u8] = b"activeinfo";
-/// prefix for storage of a closed auction info
+/// prefix for storage of a closed auction info keyed by the auction's index
 pub const PREFIX_CLOSED_INFO: &[u8] = b"closedinfo";
+/// prefix for storage of the indices of closed auctions in the order they closed
+pub const PREFIX_CLOSED_ORDER: &[u8] = b"closedorder";
+/// prefix for storage of the indices of each token pair's sold auctions in the order they closed
+pub const PREFIX_PAIR_SALES: &[u8] = b"pairsales";
 /// prefix for viewing keys
 pub const PREFIX_VIEW_KEY: &[u8] = b"viewingkey";
-/// storage key for prng seed
-pub const PRNG_SEED_KEY: &[u8] = b"prngseed";
+/// prefix for storage of each address' key epoch, the number of times it has set a viewing key
+pub const PREFIX_KEY_EPOCHS: &[u8] = b"keyepochs";
+/// prefix for storage of active auctions' nicknames
+pub const PREFIX_NICKNAMES: &[u8] = b"nicknames";
+/// prefix for storage of closed auctions' nicknames
+pub const PREFIX_CLOSED_NICKNAMES: &[u8] = b"closednicknames";
+/// prefix for storage of auction series
+pub const PREFIX_SERIES: &[u8] = b"series";
+/// prefix for storage mapping a series round's auction index to its series
+pub const PREFIX_SERIES_ROUNDS: &[u8] = b"seriesrounds";
+/// prefix for storage mapping an auction's index to its position in the closing order
+pub const PREFIX_CLOSED_POSITIONS: &[u8] = b"closedpositions";
+/// prefix for storage mapping a token's key to its symbol and decimals
+pub const PREFIX_SYMDECS: &[u8] = b"symdecs";
+/// prefix for storage of the hashed keys auctions use to authenticate their callbacks
+pub const PREFIX_CALLBACK_KEYS: &[u8] = b"callbackkeys";
+/// prefix for storage mapping an active auction's index to its seller
+pub const PREFIX_AUCTION_SELLERS: &[u8] = b"auctionsellers";
+/// prefix for storage mapping a token's key to its IBC origin
+pub const PREFIX_TOKEN_ORIGINS: &[u8] = b"tokenorigins";
+/// prefix for storage of closed auctions' settlement records
+pub const PREFIX_SETTLEMENTS: &[u8] = b"settlements";
+/// prefix for storage of the active auctions whose closing times fall in each time bucket
+pub const PREFIX_ENDING: &[u8] = b"ending";
+/// prefix for storage mapping an active auction's index to the code hash it was instantiated with
+pub const PREFIX_CODE_HASHES: &[u8] = b"codehashes";
+/// prefix for storage of the number of additional tokens bundled in an active auction's lot
+pub const PREFIX_BUNDLED: &[u8] = b"bundled";
+/// prefix for storage of the time after which CloseExpired may finalize an active auction again
+pub const PREFIX_CLOSE_RETRY: &[u8] = b"closeretry";
+/// prefix for storage of the active auctions listed under each tag
+pub const PREFIX_TAGS: &[u8] = b"tags";
+/// prefix for storage of each active auction's tags
+pub const PREFIX_AUCTION_TAGS: &[u8] = b"auctiontags";
+/// prefix for storage of the index of the auction created with each label
+pub const PREFIX_LABELS: &[u8] = b"labels";
 /// storage key for the factory config
-pub const CONFIG_KEY: &[u8] = b"config";
+pub const CONFIG_KEY: &[u8] = b"factoryconfig";
+/// storage key for the config of older factories, which also held the token index map
+pub const LEGACY_CONFIG_KEY: &[u8] = b"config";
 /// storage key for the active auction list
 pub const ACTIVE_KEY: &[u8] = b"active";
-/// storage key for token symbols and decimals
-pub const SYMDEC_KEY: &[u8] = b"symdec";
+/// storage key for the token symbols and decimals list of older factories
+pub const LEGACY_SYMDEC_KEY: &[u8] = b"symdec";
+/// storage key for the number of tokens the factory has recorded
+pub const TOKEN_COUNT_KEY: &[u8] = b"tokencount";
 /// storage key for the label of the auction we just instantiated
 pub const PENDING_KEY: &[u8] = b"pending";
+/// storage key for the factory's own code hash and address
+pub const FACTORY_INFO_KEY: &[u8] = b"factoryinfo";
+/// storage key for the number of closed auction records that have been pruned
+pub const PRUNED_KEY: &[u8] = b"pruned";
+/// storage key for the progress of deprecating the active auctions of earlier versions
+pub const DEPRECATION_KEY: &[u8] = b"deprecation";
+/// storage key for the number of auction series
+pub const SERIES_COUNT_KEY: &[u8] = b"seriescount";
+/// storage key for the set of time buckets that hold active auctions' closing times
+pub const ENDING_BUCKETS_KEY: &[u8] = b"endingbuckets";
 /// pad handle responses and log attributes to blocks of 256 bytes to prevent leaking info based on
 /// response size
 pub const BLOCK_SIZE: usize = 256;
+/// largest response padding block size a deployment may configure
+pub const MAX_BLOCK_SIZE: u16 = 4096;
+/// maximum number of characters in an auction nickname
+pub const MAX_NICKNAME_LEN: usize = 32;
+/// maximum number of auctions that can be requested in one GetAuctions query
+pub const MAX_GET_AUCTIONS: usize = 30;
+/// maximum number of series that can be requested in one ListSeries query
+pub const MAX_LIST_SERIES: u32 = 30;
+/// maximum number of sales that can be requested in one RecentPrices query
+pub const MAX_RECENT_PRICES: u32 = 100;
+/// maximum number of address/key pairs that can be authenticated in one AreKeysValid query
+pub const MAX_KEY_PAIRS: usize = 30;
+/// number of seconds covered by each bucket of the closing time index
+pub const ENDING_BUCKET_SECONDS: u64 = 3600;
+/// maximum number of auctions that can be finalized in one CloseExpired call
+pub const MAX_CLOSE_EXPIRED: u32 = 20;
+/// number of seconds CloseExpired waits before finalizing an auction that did not close again
+pub const CLOSE_RETRY_SECONDS: u64 = 3600;
+/// maximum number of tags an auction can be listed under
+pub const MAX_TAGS: usize = 5;
+/// maximum number of characters in a tag
+pub const MAX_TAG_LEN: usize = 32;
+/// maximum number of active auctions an address can watch
+pub const MAX_WATCHED: usize = 100;
+/// maximum length of a deprecation notice in bytes
+pub const MAX_NOTICE_LEN: usize = 256;
+/// default number of active auctions checked for deprecation in one message
+pub const DEFAULT_DEPRECATION_PAGE: u32 = 50;
+/// default number of closed auction records pruned in one message
+pub const DEFAULT_PRUNE_PAGE: u32 = 200;
+
+/// the auction handle messages the factory will call
+#[derive(Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuctionHandleMsg {
+    /// settles the auction by sending its lot to the seller of a complementary auction
+    MatchSwap {
+        /// seller of the complementary auction
+        recipient: HumanAddr,
+        /// amount the auction's seller receives from the complementary auction
+        received: Uint128,
+    },
+    /// halts or resumes the auction
+    Halt {
+        /// true if the auction should be halted
+        halted: bool,
+    },
+    /// closes an auction that is still open long after its closing time
+    ForceFinalize {},
+    /// warns that the auction was created with an outdated contract version
+    Deprecate {
+        /// deprecation warning to display
+        notice: String,
+    },
+    /// links the auction to a new factory
+    Migrate {
+        /// code hash and address of the new factory
+        factory: ContractInfo,
+        /// index of the auction with the new factory
+        index: u32,
+        /// key the new factory gave the auction to authenticate its callbacks
+        callback_key: String,
+    },
+    /// closes the auction
+    Finalize {
+        /// optional new closing time if there are no bids
+        new_ends_at: Option<u64>,
+        /// optional new minimum bid if there are no bids
+        new_minimum_bid: Option<Uint128>,
+    },
+    /// gives the auction a hash derived from an address' viewing key
+    ShareKeyHash {
+        /// address whose viewing key the hash was derived from
+        address: HumanAddr,
+        /// hash derived from the address' viewing key
+        key_hash: Binary,
+        /// the address' key epoch when the hash was derived
+        epoch: u32,
+    },
+}
+
+impl HandleCallback for AuctionHandleMsg {
+    const BLOCK_SIZE: usize = BLOCK_SIZE;
+}
+
+/// the action the factory requests when it sends tokens to an auction
+#[derive(Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuctionReceiveMsg {
+    /// consign the tokens for sale, or post the deposit when sending bid tokens for the seller
+    Consign {},
+}
 
 ////////////////////////////////////// Init ///////////////////////////////////////
 /// Returns InitResult
@@ -68,22 +246,35 @@ pub fn init<S: Storage, A: Api, Q: Querier>(
     env: Env,
     msg: InitMsg,
 ) -> InitResult {
-    let prng_seed: Vec<u8> = sha_256(base64::encode(msg.entropy).as_bytes()).to_vec();
+    // auctions refuse to pad to less than BLOCK_SIZE, so the factory must not configure it either
+    let block_size = msg.block_size.unwrap_or(BLOCK_SIZE as u16);
+    if usize::from(block_size) < BLOCK_SIZE || block_size > MAX_BLOCK_SIZE {
+        return Err(StdError::generic_err(format!(
+            "Block size must be between {} and {}",
+            BLOCK_SIZE, MAX_BLOCK_SIZE
+        )));
+    }
     let active: HashSet<u32> = HashSet::new();
-    let symdec: Vec<TokenSymDec> = Vec::new();
 
     let config = Config {
         version: msg.auction_contract,
-        symdecmap: HashMap::new(),
         index: 0,
-        stopped: false,
+        status: FactoryStatus::default(),
+        deprecated: Vec::new(),
+        revoked: Vec::new(),
         admin: deps.api.canonical_address(&env.message.sender)?,
+        min_sell_amount: 1,
+        max_bid_multiple: None,
+        block_size,
+        creation_fee: None,
+        referral_share: 0,
     };
 
     save(&mut deps.storage, CONFIG_KEY, &config)?;
-    save(&mut deps.storage, PRNG_SEED_KEY, &prng_seed)?;
+    init_seed(&mut deps.storage, &msg.entropy)?;
     save(&mut deps.storage, ACTIVE_KEY, &active)?;
-    save(&mut deps.storage, SYMDEC_KEY, &symdec)?;
+    save(&mut deps.storage, TOKEN_COUNT_KEY, &0u32)?;
+    save(&mut deps.storage, FACTORY_INFO_KEY, &this_contract(&env))?;
 
     Ok(InitResponse::default())
 }
@@ -101,6 +292,13 @@ pub fn handle<S: Storage, A: Api, Q: Querier>(
     env: Env,
     msg: HandleMsg,
 ) -> HandleResult {
+    // an older factory's config is split up by the first message it handles after an upgrade
+    upgrade_config(&mut deps.storage)?;
+    // queries can not read the factory's address, so a factory that predates storing it saves it
+    // the first time it handles a message
+    if deps.storage.get(FACTORY_INFO_KEY).is_none() {
+        save(&mut deps.storage, FACTORY_INFO_KEY, &this_contract(&env))?;
+    }
     let response = match msg {
         HandleMsg::CreateAuction {
             label,
@@ -110,6 +308,27 @@ pub fn handle<S: Storage, A: Api, Q: Querier>(
             minimum_bid,
             ends_at,
             description,
+            descriptions,
+            nickname,
+            tags,
+            bundle,
+            deposit,
+            consign_by,
+            buy_now_price,
+            no_self_bid,
+            only_increasing_rebids,
+            tie_break,
+            allowance_bids,
+            message_board,
+            min_bid_decay,
+            seller_confirmation,
+            unit_price_bids,
+            max_bidders,
+            min_bidders,
+            reveal_window,
+            settlement,
+            funding_target,
+            referrer,
         } => try_create_auction(
             deps,
             env,
@@ -120,586 +339,583 @@ pub fn handle<S: Storage, A: Api, Q: Querier>(
             minimum_bid,
             ends_at,
             description,
+            descriptions,
+            nickname,
+            tags,
+            bundle,
+            deposit,
+            consign_by,
+            buy_now_price,
+            no_self_bid,
+            only_increasing_rebids,
+            tie_break,
+            allowance_bids,
+            message_board,
+            min_bid_decay,
+            seller_confirmation,
+            unit_price_bids,
+            max_bidders,
+            min_bidders,
+            reveal_window,
+            settlement,
+            funding_target,
+            referrer,
         ),
         HandleMsg::RegisterAuction {
             seller,
             auction,
             sell_contract,
-        } => try_register_auction(deps, env, seller, &auction, sell_contract),
-        HandleMsg::RegisterBidder { index, bidder } => try_reg_bidder(deps, env, index, bidder),
-        HandleMsg::RemoveBidder { index, bidder } => try_remove_bidder(deps, env, index, &bidder),
+            bundle,
+            bid_contract,
+            deposit,
+            callback_key,
+        } => try_register_auction(
+            deps,
+            env,
+            seller,
+            &auction,
+            sell_contract,
+            bundle,
+            bid_contract,
+            deposit,
+            callback_key.as_deref(),
+        ),
+        HandleMsg::RegisterBidder {
+            index,
+            bidder,
+            amount,
+            callback_key,
+        } => try_reg_bidder(deps, env, index, bidder, amount, callback_key.as_deref()),
+        HandleMsg::RemoveBidder {
+            index,
+            bidder,
+            callback_key,
+        } => try_remove_bidder(deps, env, index, &bidder, callback_key.as_deref()),
         HandleMsg::CloseAuction {
             index,
             seller,
             bidder,
             winning_bid,
-        } => try_close_auction(deps, env, index, &seller, bidder.as_ref(), winning_bid),
+            co_winners,
+            referrer,
+            unmet_min_bidders,
+            callback_key,
+        } => try_close_auction(
+            deps,
+            env,
+            index,
+            &seller,
+            bidder.as_ref(),
+            winning_bid,
+            &co_winners,
+            referrer.as_ref(),
+            unmet_min_bidders,
+            callback_key.as_deref(),
+        ),
         HandleMsg::CreateViewingKey { entropy } => try_create_key(deps, env, &entropy),
         HandleMsg::SetViewingKey { key, .. } => try_set_key(deps, env, &key),
-        HandleMsg::NewAuctionContract { auction_contract } => {
-            try_new_contract(deps, env, auction_contract)
+        HandleMsg::NewAuctionContract {
+            auction_contract,
+            deprecate_old,
+            deprecation_notice,
+            page_size,
+        } => try_new_contract(
+            deps,
+            env,
+            auction_contract,
+            deprecate_old,
+            deprecation_notice,
+            page_size,
+        ),
+        HandleMsg::ContinueDeprecation { page_size } => {
+            try_continue_deprecation(deps, env, page_size)
+        }
+        HandleMsg::SetStatus {
+            stop_creation,
+            stop_bids,
+            stop_old_versions,
+        } => try_set_status(deps, env, stop_creation, stop_bids, stop_old_versions),
+        HandleMsg::RevokeAuctionVersion { code_id, revoked } => {
+            try_revoke_version(deps, env, code_id, revoked)
+        }
+        HandleMsg::Reseed { entropy } => try_reseed(deps, env, &entropy),
+        HandleMsg::SetCreationLimits {
+            min_sell_amount,
+            max_bid_multiple,
+        } => try_set_limits(deps, env, min_sell_amount, max_bid_multiple),
+        HandleMsg::SetCreationFee { fee } => try_set_creation_fee(deps, env, fee),
+        HandleMsg::SetReferralShare { basis_points } => {
+            try_set_referral_share(deps, env, basis_points)
+        }
+        HandleMsg::Withdraw { asset, amount, to } => try_withdraw(deps, env, asset, amount, to),
+        HandleMsg::CloseExpired { limit } => try_close_expired(deps, env, limit),
+        HandleMsg::HaltAuction { index, halted } => try_halt_auction(deps, env, index, halted),
+        HandleMsg::ForceFinalizeAuction { index } => try_force_finalize_auction(deps, env, index),
+        HandleMsg::MigrateAuction {
+            index,
+            factory_address,
+            factory_code_hash,
+            new_index,
+            callback_key,
+        } => try_migrate_auction(
+            deps,
+            env,
+            index,
+            ContractInfo {
+                code_hash: factory_code_hash,
+                address: factory_address,
+                token_id: None,
+            },
+            new_index,
+            callback_key,
+        ),
+        HandleMsg::RefreshTokenInfo {
+            token_address,
+            code_hash,
+            token_id,
+        } => try_refresh_token_info(
+            deps,
+            env,
+            ContractInfo {
+                code_hash,
+                address: token_address,
+                token_id,
+            },
+        ),
+        HandleMsg::SetTokenOrigin {
+            token_address,
+            token_id,
+            origin,
+        } => try_set_token_origin(deps, env, token_address, token_id, origin),
+        HandleMsg::SetAllowlistMode { enabled } => try_set_allowlist_mode(deps, env, enabled),
+        HandleMsg::AddToAllowlist { addresses } => {
+            try_update_allowlist(deps, env, &addresses, true)
+        }
+        HandleMsg::RemoveFromAllowlist { addresses } => {
+            try_update_allowlist(deps, env, &addresses, false)
         }
-        HandleMsg::SetStatus { stop } => try_set_status(deps, env, stop),
+        HandleMsg::AddCloseSubscriber {
+            subscriber,
+            sell_token,
+            bid_token,
+        } => try_add_close_subscriber(deps, env, &subscriber, sell_token, bid_token),
+        HandleMsg::RemoveCloseSubscriber {
+            subscriber,
+            sell_token,
+            bid_token,
+        } => try_remove_close_subscriber(deps, env, &subscriber, &sell_token, &bid_token),
+        HandleMsg::SetCloseSubscriberStatus {
+            subscriber,
+            sell_token,
+            bid_token,
+            enabled,
+        } => try_set_close_subscriber_status(
+            deps,
+            env,
+            &subscriber,
+            &sell_token,
+            &bid_token,
+            enabled,
+        ),
         HandleMsg::ChangeAuctionInfo {
             index,
             ends_at,
             minimum_bid,
-        } => try_change_auction_info(deps, env, index, ends_at, minimum_bid),
+            sell_amount,
+            callback_key,
+        } => try_change_auction_info(
+            deps,
+            env,
+            index,
+            ends_at,
+            minimum_bid,
+            sell_amount,
+            callback_key.as_deref(),
+        ),
+        HandleMsg::ChangeSeller {
+            index,
+            seller,
+            new_seller,
+            callback_key,
+        } => try_change_seller(
+            deps,
+            env,
+            index,
+            &seller,
+            &new_seller,
+            callback_key.as_deref(),
+        ),
+        HandleMsg::PruneClosed {
+            keep_after_timestamp,
+            page_size,
+        } => try_prune_closed(deps, env, keep_after_timestamp, page_size),
+        HandleMsg::SetNickname { auction, nickname } => {
+            try_set_nickname(deps, env, &auction, nickname)
+        }
+        HandleMsg::Watch { index } => try_watch(deps, env, index, true),
+        HandleMsg::Unwatch { index } => try_watch(deps, env, index, false),
+        HandleMsg::CreateSeries {
+            label,
+            sell_contract,
+            bid_contract,
+            sell_amount,
+            minimum_bid,
+            interval,
+            rounds,
+            description,
+        } => try_create_series(
+            deps,
+            env,
+            label,
+            sell_contract,
+            bid_contract,
+            sell_amount,
+            minimum_bid,
+            interval,
+            rounds,
+            description,
+        ),
+        HandleMsg::CancelSeries { series } => try_cancel_series(deps, env, series),
+        HandleMsg::MatchAuctions { a, b } => try_match_auctions(deps, env, a, b),
     };
-    pad_handle_result(response, BLOCK_SIZE)
+    pad_handle_result(response, padding_block_size(&deps.storage))
 }
 
 /// Returns HandleResult
 ///
-/// create a new auction
+/// allows admin to add a new auction version to the list of compatible auctions, and optionally
+/// start deprecating the auctions of earlier versions, checking the first page of active auctions
 ///
 /// # Arguments
 ///
 /// * `deps` - mutable reference to Extern containing all the contract's external dependencies
 /// * `env` - Env of contract's environment
-/// * `label` - String containing the label to give the auction
-/// * `sell_contract` - ContractInfo containing the code hash and address of the sale token
-/// * `bid_contract` - ContractInfo containing the code hash and address of the bid token
-/// * `sell_amount` - Uint128 amount to sell in smallest denomination
-/// * `minimum_bid` - Uint128 minimum bid owner will accept
-/// * `ends_at` - time in seconds since epoch 01/01/1970 after which anyone may close the auction
-/// * `description` - optional free-form text string owner may have used to describe the auction
-#[allow(clippy::too_many_arguments)]
-fn try_create_auction<S: Storage, A: Api, Q: Querier>(
+/// * `auction_contract` - AuctionContractInfo of the new auction version
+/// * `deprecate_old` - true if the auctions of earlier versions should be marked as deprecated
+/// * `deprecation_notice` - optional warning sent to the active auctions of earlier versions
+/// * `page_size` - optional number of active auctions to check for deprecation
+fn try_new_contract<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
     env: Env,
-    label: String,
-    sell_contract: ContractInfo,
-    bid_contract: ContractInfo,
-    sell_amount: Uint128,
-    minimum_bid: Uint128,
-    ends_at: u64,
-    description: Option<String>,
+    auction_contract: AuctionContractInfo,
+    deprecate_old: bool,
+    deprecation_notice: Option<String>,
+    page_size: Option<u32>,
 ) -> HandleResult {
-    /// Instantiation message
-    #[derive(Serialize)]
-    pub struct AuctionInitMsg {
-        /// factory contract code hash and address
-        pub factory: ContractInfo,
-        /// auction index with the factory
-        pub index: u32,
-        /// String label for the auction
-        pub label: String,
-        /// auction seller
-        pub seller: HumanAddr,
-        /// sell contract code hash and address
-        pub sell_contract: ContractInfo,
-        /// sell symbol index
-        pub sell_symbol: u16,
-        /// sell token decimal places
-        pub sell_decimals: u8,
-        /// bid contract code hash and address
-        pub bid_contract: ContractInfo,
-        /// bid symbol index
-        pub bid_symbol: u16,
-        /// bid token decimal places,
-        pub bid_decimals: u8,
-        /// amount of tokens being sold
-        pub sell_amount: Uint128,
-        /// minimum bid that will be accepted
-        pub minimum_bid: Uint128,
-        /// timestamp after which anyone may close the auction.
-        /// Timestamp is in seconds since epoch 01/01/1970
-        pub ends_at: u64,
-        /// Optional free-form description of the auction (best to avoid double quotes). As an example
-        /// it could be the date the owner will likely finalize the auction, or a list of other
-        /// auctions for the same token, etc...
-        #[serde(default)]
-        pub description: Option<String>,
-    }
-
-    impl InitCallback for AuctionInitMsg {
-        const BLOCK_SIZE: usize = BLOCK_SIZE;
-    }
-
-    let mut config: Config = load(&deps.storage, CONFIG_KEY)?;
-    if config.stopped {
+    // only allow admin to do this
+    let mut config: Config = load_config(&deps.storage)?;
+    let sender = deps.api.canonical_address(&env.message.sender)?;
+    if config.admin != sender {
         return Err(StdError::generic_err(
-            "The factory has been stopped.  No new auctions can be created",
+            "This is an admin command. Admin commands can only be run from admin address",
         ));
     }
-
-    let factory = ContractInfo {
-        code_hash: env.contract_code_hash,
-        address: env.contract.address,
-    };
-    // get sell token info
-    let sell_token_info = token_info_query(
-        &deps.querier,
-        BLOCK_SIZE,
-        sell_contract.code_hash.clone(),
-        sell_contract.address.clone(),
-    )?;
-    let sell_decimals = sell_token_info.decimals;
-    let sell_addr_raw = &deps.api.canonical_address(&sell_contract.address)?;
-    let may_sell_index = config
-        .symdecmap
-        .get(&sell_addr_raw.as_slice().to_vec())
-        .copied();
-    // get bid token info
-    let bid_token_info = token_info_query(
-        &deps.querier,
-        BLOCK_SIZE,
-        bid_contract.code_hash.clone(),
-        bid_contract.address.clone(),
-    )?;
-    let bid_decimals = bid_token_info.decimals;
-    let bid_addr_raw = &deps.api.canonical_address(&bid_contract.address)?;
-    let may_bid_index = config
-        .symdecmap
-        .get(&bid_addr_raw.as_slice().to_vec())
-        .copied();
-    let add_symbol = may_sell_index.is_none() || may_bid_index.is_none();
-    let sell_index: u16;
-    let bid_index: u16;
-    // if there is a new symbol add it to the list and get its index
-    if add_symbol {
-        let mut symdecs: Vec<TokenSymDec> = load(&deps.storage, SYMDEC_KEY)?;
-        match may_sell_index {
-            Some(unwrap) => sell_index = unwrap,
-            None => {
-                let symdec = TokenSymDec {
-                    symbol: sell_token_info.symbol,
-                    decimals: sell_token_info.decimals,
-                };
-                sell_index = symdecs.len() as u16;
-                config
-                    .symdecmap
-                    .insert(sell_addr_raw.as_slice().to_vec(), sell_index);
-                symdecs.push(symdec)
-            }
+    if let Some(notice) = deprecation_notice.as_ref() {
+        if notice.trim().is_empty() || notice.len() > MAX_NOTICE_LEN {
+            return Err(StdError::generic_err(format!(
+                "A deprecation notice must not be empty or longer than {} bytes",
+                MAX_NOTICE_LEN
+            )));
         }
-        match may_bid_index {
-            Some(unwrap) => bid_index = unwrap,
-            None => {
-                let symdec = TokenSymDec {
-                    symbol: bid_token_info.symbol,
-                    decimals: bid_token_info.decimals,
-                };
-                bid_index = symdecs.len() as u16;
-                config
-                    .symdecmap
-                    .insert(bid_addr_raw.as_slice().to_vec(), bid_index);
-                symdecs.push(symdec)
-            }
+    }
+    let old_code_id = config.version.code_id;
+    config.version = auction_contract;
+    let new_code_id = config.version.code_id;
+    // returning to an earlier version undeprecates it
+    config.deprecated.retain(|id| *id != new_code_id);
+    // a deprecation still in progress was for an earlier version, so it is abandoned
+    remove(&mut deps.storage, DEPRECATION_KEY);
+    let mut messages = Vec::new();
+    let mut message = None;
+    if deprecate_old || deprecation_notice.is_some() {
+        if old_code_id != new_code_id && !config.deprecated.contains(&old_code_id) {
+            config.deprecated.push(old_code_id);
         }
-        save(&mut deps.storage, SYMDEC_KEY, &symdecs)?;
-    // not a new symbol so just get its index from the map
-    } else {
-        sell_index = may_sell_index.unwrap();
-        bid_index = may_bid_index.unwrap();
+        let mut pending = PendingDeprecation {
+            code_id: new_code_id,
+            notice: deprecation_notice,
+            after: None,
+        };
+        let (page_msgs, status) = deprecate_page(deps, &mut config, &mut pending, page_size)?;
+        messages = page_msgs;
+        message = Some(status);
     }
-
-    // save label and only register an auction giving the matching label
-    save(&mut deps.storage, PENDING_KEY, &label)?;
-
-    let initmsg = AuctionInitMsg {
-        factory,
-        index: config.index,
-        label: label.clone(),
-        seller: env.message.sender,
-        sell_contract,
-        sell_symbol: sell_index,
-        sell_decimals,
-        bid_contract,
-        bid_symbol: bid_index,
-        bid_decimals,
-        sell_amount,
-        minimum_bid,
-        ends_at,
-        description,
-    };
-    // increment the index for the next auction
-    config.index += 1;
     save(&mut deps.storage, CONFIG_KEY, &config)?;
 
-    let cosmosmsg = initmsg.to_cosmos_msg(
-        label,
-        config.version.code_id,
-        config.version.code_hash,
-        None,
-    )?;
-
     Ok(HandleResponse {
-        messages: vec![cosmosmsg],
+        messages,
         log: vec![],
         data: Some(to_binary(&HandleAnswer::Status {
             status: Success,
-            message: None,
+            message,
         })?),
     })
 }
 
 /// Returns HandleResult
 ///
-/// Registers the calling auction by saving its info and adding it to the appropriate lists
+/// allows admin to check the next page of active auctions of a deprecation in progress
 ///
 /// # Arguments
 ///
 /// * `deps` - mutable reference to Extern containing all the contract's external dependencies
 /// * `env` - Env of contract's environment
-/// * `seller` - reference to the address of the auction's seller
-/// * `reg_auction` - reference to RegisterAuctionInfo of the auction that is trying to register
-fn try_register_auction<S: Storage, A: Api, Q: Querier>(
+/// * `page_size` - optional number of active auctions to check for deprecation
+fn try_continue_deprecation<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
     env: Env,
-    seller: HumanAddr,
-    reg_auction: &RegisterAuctionInfo,
-    sell_contract: ContractInfo,
+    page_size: Option<u32>,
 ) -> HandleResult {
-    // verify this is the auction we are waiting for
-    let load_label: Option<String> = may_load(&deps.storage, PENDING_KEY)?;
-    let auth_label =
-        load_label.ok_or_else(|| StdError::generic_err("Unable to authenticate registration."))?;
-    if auth_label != reg_auction.label {
+    // only allow admin to do this
+    let mut config: Config = load_config(&deps.storage)?;
+    let sender = deps.api.canonical_address(&env.message.sender)?;
+    if config.admin != sender {
         return Err(StdError::generic_err(
-            "Label does not match the auction we are creating",
+            "This is an admin command. Admin commands can only be run from admin address",
         ));
     }
-    remove(&mut deps.storage, PENDING_KEY);
-
-    // convert register auction info to storage format
-    let auction_addr = deps.api.canonical_address(&env.message.sender)?;
-    let auction = reg_auction.to_store_auction_info(auction_addr);
-
-    // save the auction info keyed by its index
-    let mut info_store = PrefixedStorage::new(PREFIX_ACTIVE_INFO, &mut deps.storage);
-    save(&mut info_store, &reg_auction.index.to_le_bytes(), &auction)?;
-
-    // add the auction address to list of active auctions
-    let mut active: HashSet<u32> = load(&deps.storage, ACTIVE_KEY)?;
-    active.insert(reg_auction.index);
-    save(&mut deps.storage, ACTIVE_KEY, &active)?;
-
-    // get list of seller's active auctions
-    let seller_raw = &deps.api.canonical_address(&seller)?;
-    let mut seller_store = PrefixedStorage::new(PREFIX_SELLERS_ACTIVE, &mut deps.storage);
-    let load_auctions: Option<HashSet<u32>> = may_load(&seller_store, seller_raw.as_slice())?;
-    let mut my_active = load_auctions.unwrap_or_default();
-    // add this auction to seller's list
-    my_active.insert(reg_auction.index);
-    save(&mut seller_store, seller_raw.as_slice(), &my_active)?;
+    let mut pending: PendingDeprecation = may_load(&deps.storage, DEPRECATION_KEY)?
+        .ok_or_else(|| StdError::generic_err("There is no deprecation in progress"))?;
+    let (messages, status) = deprecate_page(deps, &mut config, &mut pending, page_size)?;
+    save(&mut deps.storage, CONFIG_KEY, &config)?;
 
     Ok(HandleResponse {
-        messages: vec![send_from_msg(
-            seller,
-            env.message.sender.clone(),
-            reg_auction.sell_amount,
-            None,
-            None,
-            BLOCK_SIZE,
-            sell_contract.code_hash,
-            sell_contract.address,
-        )?],
-        log: vec![log("auction_address", env.message.sender)],
-        data: None,
+        messages,
+        log: vec![],
+        data: Some(to_binary(&HandleAnswer::Status {
+            status: Success,
+            message: Some(status),
+        })?),
     })
 }
 
-/// Returns HandleResult
+/// Returns StdResult<(Vec<CosmosMsg>, String)>
 ///
-/// closes the calling auction by saving its info and adding/removing it to/from the
-/// appropriate lists
+/// checks the next page of active auctions for ones created with an earlier version, marks
+/// their versions as deprecated, and returns the messages sending them the deprecation notice if
+/// there is one, along with a description of the progress.  The progress is saved if any
+/// active auctions remain to be checked, and forgotten otherwise
 ///
 /// # Arguments
 ///
 /// * `deps` - mutable reference to Extern containing all the contract's external dependencies
-/// * `env` - Env of contract's environment
-/// * `index` - auction index
-/// * `seller` - reference to the address of the auction's seller
-/// * `bidder` - reference to the auction's winner if it had one
-/// * `winning_bid` - auction's winning bid if it had one
-fn try_close_auction<S: Storage, A: Api, Q: Querier>(
+/// * `config` - a mutable reference to the factory config, which the caller saves
+/// * `pending` - a mutable reference to the progress of the deprecation
+/// * `page_size` - optional number of active auctions to check
+fn deprecate_page<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
-    env: Env,
-    index: u32,
-    seller: &HumanAddr,
-    bidder: Option<&HumanAddr>,
-    winning_bid: Option<Uint128>,
-) -> HandleResult {
-    let auction_addr = &deps.api.canonical_address(&env.message.sender)?;
-
-    // verify auction is in active list of auctions and not a spam attempt
-    let (may_active, may_info, may_error) =
-        authenticate_auction(&deps.storage, auction_addr, index)?;
-    if let Some(error) = may_error {
-        return error;
+    config: &mut Config,
+    pending: &mut PendingDeprecation,
+    page_size: Option<u32>,
+) -> StdResult<(Vec<CosmosMsg>, String)> {
+    let active: HashSet<u32> = load(&deps.storage, ACTIVE_KEY)?;
+    let mut indices: Vec<u32> = active
+        .into_iter()
+        .filter(|i| pending.after.map_or(true, |a| *i > a))
+        .collect();
+    indices.sort_unstable();
+    let take = page_size.unwrap_or(DEFAULT_DEPRECATION_PAGE).max(1) as usize;
+    let remaining = indices.len().saturating_sub(take);
+    indices.truncate(take);
+    let read_info = ReadonlyPrefixedStorage::new(PREFIX_ACTIVE_INFO, &deps.storage);
+    let read_hash = ReadonlyPrefixedStorage::new(PREFIX_CODE_HASHES, &deps.storage);
+    let mut messages = Vec::new();
+    for index in indices.iter() {
+        let may_info: Option<StoreAuctionInfo> = may_load(&read_info, &index.to_le_bytes())?;
+        if let Some(info) = may_info.filter(|i| i.code_id != pending.code_id) {
+            if !config.deprecated.contains(&info.code_id) {
+                config.deprecated.push(info.code_id);
+            }
+            // warn the auction if the factory knows which code it runs
+            let may_hash: Option<String> = may_load(&read_hash, &index.to_le_bytes())?;
+            if let (Some(notice), Some(code_hash)) = (pending.notice.as_ref(), may_hash) {
+                messages.push(
+                    AuctionHandleMsg::Deprecate {
+                        notice: notice.clone(),
+                    }
+                    .to_cosmos_msg(
+                        code_hash,
+                        deps.api.human_address(&info.address)?,
+                        None,
+                    )?,
+                );
+            }
+        }
     }
-    // delete the active auction info
-    let mut info_store = PrefixedStorage::new(PREFIX_ACTIVE_INFO, &mut deps.storage);
-    info_store.remove(&index.to_le_bytes());
-    // remove the auction from the active list
-    let mut active = may_active.unwrap();
-    active.remove(&index);
-    save(&mut deps.storage, ACTIVE_KEY, &active)?;
-
-    // set the closed auction info
-    let timestamp = env.block.time;
-    let auction_info = may_info.unwrap();
-    let closed_info =
-        auction_info.to_store_closed_auction_info(winning_bid.map(|n| n.u128()), timestamp);
-    let mut closed_info_store = PrefixedStorage::new(PREFIX_CLOSED_INFO, &mut deps.storage);
-    let mut closed_store = AppendStoreMut::attach_or_create(&mut closed_info_store)?;
-    let closed_index = closed_store.len();
-    closed_store.push(&closed_info)?;
-
-    // remove auction from seller's active list
-    let seller_raw = &deps.api.canonical_address(seller)?;
-    remove_from_persons_active(&mut deps.storage, PREFIX_SELLERS_ACTIVE, seller_raw, index)?;
-    // add to seller's closed list
-    let mut sell_store = PrefixedStorage::multilevel(
-        &[PREFIX_SELLERS_CLOSED, seller_raw.as_slice()],
-        &mut deps.storage,
-    );
-    let mut seller_closed = AppendStoreMut::attach_or_create(&mut sell_store)?;
-    seller_closed.push(&closed_index)?;
-
-    // if auction had a winner
-    if let Some(winner) = bidder {
-        let winner_raw = &deps.api.canonical_address(winner)?;
-        // clean up the bidders list of active auctions
-        let mut bidder_store = PrefixedStorage::new(PREFIX_BIDDERS, &mut deps.storage);
-        let (win_active, _) = filter_only_active(&bidder_store, winner_raw, &mut active)?;
-        save(&mut bidder_store, winner_raw.as_slice(), &win_active)?;
-        // add to winner's closed
-        let mut win_store = PrefixedStorage::multilevel(
-            &[PREFIX_WINNERS, winner_raw.as_slice()],
-            &mut deps.storage,
-        );
-        let mut winner_list = AppendStoreMut::attach_or_create(&mut win_store)?;
-        winner_list.push(&closed_index)?;
+    if remaining == 0 {
+        remove(&mut deps.storage, DEPRECATION_KEY);
+        return Ok((messages, "Deprecation is complete".to_string()));
     }
-
-    Ok(HandleResponse {
-        messages: vec![],
-        log: vec![],
-        data: None,
-    })
+    pending.after = indices.last().copied();
+    save(&mut deps.storage, DEPRECATION_KEY, pending)?;
+    Ok((
+        messages,
+        format!(
+            "{} active auctions remain to be checked for deprecation",
+            remaining
+        ),
+    ))
 }
 
 /// Returns HandleResult
 ///
-/// changes the closing time and/or minimum bid of an auction
+/// allows admin to stop or restart auction creation, bid registration, and bid registration
+/// from auctions of earlier versions
 ///
 /// # Arguments
 ///
 /// * `deps` - mutable reference to Extern containing all the contract's external dependencies
 /// * `env` - Env of contract's environment
-/// * `index` - auction index
-/// * `ends_at` - optional new closing time
-/// * `minimum_bid` - optional new minimum bid
-fn try_change_auction_info<S: Storage, A: Api, Q: Querier>(
+/// * `stop_creation` - optionally true if the factory should disallow auction creation
+/// * `stop_bids` - optionally true if the factory should disallow registering new bids
+/// * `stop_old_versions` - optionally true if the factory should disallow registering new bids
+///                         from auctions of earlier versions
+fn try_set_status<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
     env: Env,
-    index: u32,
-    ends_at: Option<u64>,
-    minimum_bid: Option<Uint128>,
+    stop_creation: Option<bool>,
+    stop_bids: Option<bool>,
+    stop_old_versions: Option<bool>,
 ) -> HandleResult {
-    let auction_addr = &deps.api.canonical_address(&env.message.sender)?;
-
-    // verify auction is in active list of auctions and not a spam attempt
-    let (_may_active, may_info, may_error) =
-        authenticate_auction(&deps.storage, auction_addr, index)?;
-    if let Some(error) = may_error {
-        return error;
+    // only allow admin to do this
+    let mut config: Config = load_config(&deps.storage)?;
+    let sender = deps.api.canonical_address(&env.message.sender)?;
+    if config.admin != sender {
+        return Err(StdError::generic_err(
+            "This is an admin command. Admin commands can only be run from admin address",
+        ));
     }
-
-    let mut auction_info = may_info.unwrap();
-    if let Some(min_bid) = minimum_bid {
-        auction_info.minimum_bid = min_bid.u128();
+    if let Some(stop) = stop_creation {
+        config.status.creation_stopped = stop;
     }
-    if let Some(ends) = ends_at {
-        auction_info.ends_at = ends;
+    if let Some(stop) = stop_bids {
+        config.status.bids_stopped = stop;
     }
-    let mut info_store = PrefixedStorage::new(PREFIX_ACTIVE_INFO, &mut deps.storage);
-    save(&mut info_store, &index.to_le_bytes(), &auction_info)?;
+    if let Some(stop) = stop_old_versions {
+        config.status.old_versions_stopped = stop;
+    }
+    save(&mut deps.storage, CONFIG_KEY, &config)?;
+
     Ok(HandleResponse {
         messages: vec![],
         log: vec![],
-        data: None,
+        data: Some(to_binary(&HandleAnswer::Status {
+            status: Success,
+            message: None,
+        })?),
     })
 }
 
 /// Returns HandleResult
 ///
-/// registers a new bidder of the calling auction
+/// allows admin to revoke or restore an earlier auction contract version
 ///
 /// # Arguments
 ///
 /// * `deps` - mutable reference to Extern containing all the contract's external dependencies
 /// * `env` - Env of contract's environment
-/// * `index` - auction index
-/// * `bidder` - address of the new bidder
-fn try_reg_bidder<S: Storage, A: Api, Q: Querier>(
+/// * `code_id` - code id of the auction contract version
+/// * `revoked` - true if the version should be revoked
+fn try_revoke_version<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
     env: Env,
-    index: u32,
-    bidder: HumanAddr,
+    code_id: u64,
+    revoked: bool,
 ) -> HandleResult {
-    let auction_addr = &deps.api.canonical_address(&env.message.sender)?;
-
-    // verify auction is in active list of auctions and not a spam attempt
-    let (may_active, _may_info, may_error) =
-        authenticate_auction(&deps.storage, auction_addr, index)?;
-    if let Some(error) = may_error {
-        return error;
+    // only allow admin to do this
+    let mut config: Config = load_config(&deps.storage)?;
+    let sender = deps.api.canonical_address(&env.message.sender)?;
+    if config.admin != sender {
+        return Err(StdError::generic_err(
+            "This is an admin command. Admin commands can only be run from admin address",
+        ));
     }
-
-    let mut active = may_active.unwrap();
-
-    // clean up the bidders list of active auctions
-    let bidder_raw = &deps.api.canonical_address(&bidder)?;
-    let mut bidder_store = PrefixedStorage::new(PREFIX_BIDDERS, &mut deps.storage);
-    let (mut my_active, _) = filter_only_active(&bidder_store, bidder_raw, &mut active)?;
-    // add this auction to the list
-    my_active.insert(index);
-    save(&mut bidder_store, bidder_raw.as_slice(), &my_active)?;
+    if revoked && code_id == config.version.code_id {
+        return Err(StdError::generic_err(
+            "The current auction contract version can not be revoked",
+        ));
+    }
+    config.revoked.retain(|id| *id != code_id);
+    if revoked {
+        config.revoked.push(code_id);
+    }
+    save(&mut deps.storage, CONFIG_KEY, &config)?;
 
     Ok(HandleResponse {
         messages: vec![],
         log: vec![],
-        data: None,
+        data: Some(to_binary(&HandleAnswer::Status {
+            status: Success,
+            message: None,
+        })?),
     })
 }
 
 /// Returns HandleResult
 ///
-/// removes registration of the retracting bidder of the calling auction
+/// allows admin to fold new entropy into the prng seed
 ///
 /// # Arguments
 ///
 /// * `deps` - mutable reference to Extern containing all the contract's external dependencies
 /// * `env` - Env of contract's environment
-/// * `index` - auction index
-/// * `bidder` - reference to the address of the retracting bidder
-fn try_remove_bidder<S: Storage, A: Api, Q: Querier>(
+/// * `entropy` - string slice to be folded into the seed
+fn try_reseed<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
     env: Env,
-    index: u32,
-    bidder: &HumanAddr,
+    entropy: &str,
 ) -> HandleResult {
-    let auction_addr = &deps.api.canonical_address(&env.message.sender)?;
-
-    // verify auction is in active list of auctions and not a spam attempt
-    let (may_active, _may_info, may_error) =
-        authenticate_auction(&deps.storage, auction_addr, index)?;
-    if let Some(error) = may_error {
-        return error;
+    // only allow admin to do this
+    let config: Config = load_config(&deps.storage)?;
+    let sender = deps.api.canonical_address(&env.message.sender)?;
+    if config.admin != sender {
+        return Err(StdError::generic_err(
+            "This is an admin command. Admin commands can only be run from admin address",
+        ));
     }
-
-    let mut active = may_active.unwrap();
-
-    // clean up the bidders list of active auctions
-    let bidder_raw = &deps.api.canonical_address(&bidder)?;
-    let mut bidder_store = PrefixedStorage::new(PREFIX_BIDDERS, &mut deps.storage);
-    let (mut my_active, _) = filter_only_active(&bidder_store, bidder_raw, &mut active)?;
-    // remove this auction from the list
-    my_active.remove(&index);
-    save(&mut bidder_store, bidder_raw.as_slice(), &my_active)?;
+    fold_entropy(&mut deps.storage, &env, entropy.as_bytes())?;
 
     Ok(HandleResponse {
         messages: vec![],
         log: vec![],
-        data: None,
+        data: Some(to_binary(&HandleAnswer::Status {
+            status: Success,
+            message: None,
+        })?),
     })
 }
 
-/// Returns StdResult<(Option<HashSet<u32>>, Option<StoreAuctionInfo>, Option<HandleResult>)>
-///
-/// verifies that the auction is in the list of active auctions, and returns the active auction
-/// list, the auction information, or a possible error
-///
-/// # Arguments
-///
-/// * `storage` - a reference to contract's storage
-/// * `auction` - a reference to the auction's address
-/// * `index` - index/key of the auction
-#[allow(clippy::type_complexity)]
-fn authenticate_auction<S: ReadonlyStorage>(
-    storage: &S,
-    auction: &CanonicalAddr,
-    index: u32,
-) -> StdResult<(
-    Option<HashSet<u32>>,
-    Option<StoreAuctionInfo>,
-    Option<HandleResult>,
-)> {
-    let mut error: Option<HandleResult> = None;
-    let mut info: Option<StoreAuctionInfo> = None;
-    let active: Option<HashSet<u32>> = may_load(storage, ACTIVE_KEY)?;
-    if let Some(active_set) = active.as_ref() {
-        // get the auction information
-        let info_store = ReadonlyPrefixedStorage::new(PREFIX_ACTIVE_INFO, storage);
-        info = may_load(&info_store, &index.to_le_bytes())?;
-        if let Some(auction_info) = info.as_ref() {
-            if auction_info.address != *auction || !active_set.contains(&index) {
-                error = Some(Ok(HandleResponse {
-                    messages: vec![],
-                    log: vec![log(
-                        "Unauthorized",
-                        "You are not an active auction this factory created",
-                    )],
-                    data: None,
-                }));
-            }
-        } else {
-            error = Some(Ok(HandleResponse {
-                messages: vec![],
-                log: vec![
-                    log(
-                        "Error",
-                        "Unable to register action with the factory contract",
-                    ),
-                    log("Reason", "Missing auction information"),
-                ],
-                data: None,
-            }));
-        }
-    // if you can't load the active auction list, it is an error but still let auction process
-    } else {
-        error = Some(Ok(HandleResponse {
-            messages: vec![],
-            log: vec![
-                log(
-                    "Error",
-                    "Unable to register action with the factory contract",
-                ),
-                log("Reason", "Missing active auction list"),
-            ],
-            data: None,
-        }));
-    }
-    Ok((active, info, error))
-}
-
 /// Returns HandleResult
 ///
-/// allows admin to add a new auction version to the list of compatible auctions
+/// allows admin to change the limits used to reject obviously broken auctions
 ///
 /// # Arguments
 ///
 /// * `deps` - mutable reference to Extern containing all the contract's external dependencies
 /// * `env` - Env of contract's environment
-/// * `auction_contract` - AuctionContractInfo of the new auction version
-fn try_new_contract<S: Storage, A: Api, Q: Querier>(
+/// * `min_sell_amount` - smallest sell amount an auction may have
+/// * `max_bid_multiple` - optional limit on the minimum bid as a multiple of the sell amount
+fn try_set_limits<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
     env: Env,
-    auction_contract: AuctionContractInfo,
+    min_sell_amount: Uint128,
+    max_bid_multiple: Option<Uint128>,
 ) -> HandleResult {
     // only allow admin to do this
-    let mut config: Config = load(&deps.storage, CONFIG_KEY)?;
+    let mut config: Config = load_config(&deps.storage)?;
     let sender = deps.api.canonical_address(&env.message.sender)?;
     if config.admin != sender {
         return Err(StdError::generic_err(
             "This is an admin command. Admin commands can only be run from admin address",
         ));
     }
-    config.version = auction_contract;
+    if min_sell_amount.u128() == 0 {
+        return Err(StdError::generic_err(
+            "Minimum sell amount must be greater than 0",
+        ));
+    }
+    config.min_sell_amount = min_sell_amount.u128();
+    config.max_bid_multiple = max_bid_multiple.map(|m| m.u128());
     save(&mut deps.storage, CONFIG_KEY, &config)?;
 
     Ok(HandleResponse {
@@ -714,27 +930,42 @@ fn try_new_contract<S: Storage, A: Api, Q: Querier>(
 
 /// Returns HandleResult
 ///
-/// allows admin to change the factory status to (dis)allow the creation of new auctions
+/// allows admin to set or remove the fee charged to create an auction
 ///
 /// # Arguments
 ///
 /// * `deps` - mutable reference to Extern containing all the contract's external dependencies
 /// * `env` - Env of contract's environment
-/// * `stop` - true if the factory should disallow auction creation
-fn try_set_status<S: Storage, A: Api, Q: Querier>(
+/// * `fee` - optional uscrt amount and treasury address of the creation fee
+fn try_set_creation_fee<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
     env: Env,
-    stop: bool,
+    fee: Option<CreationFee>,
 ) -> HandleResult {
     // only allow admin to do this
-    let mut config: Config = load(&deps.storage, CONFIG_KEY)?;
+    let mut config: Config = load_config(&deps.storage)?;
     let sender = deps.api.canonical_address(&env.message.sender)?;
     if config.admin != sender {
         return Err(StdError::generic_err(
             "This is an admin command. Admin commands can only be run from admin address",
         ));
     }
-    config.stopped = stop;
+    config.creation_fee = fee
+        .map(|f| {
+            if f.amount.u128() == 0 {
+                return Err(StdError::generic_err(
+                    "Creation fee must be greater than 0.  Omit the fee to remove it",
+                ));
+            }
+            Ok(StoredCreationFee {
+                amount: f.amount.u128(),
+                treasury: f
+                    .treasury
+                    .map(|t| deps.api.canonical_address(&t))
+                    .transpose()?,
+            })
+        })
+        .transpose()?;
     save(&mut deps.storage, CONFIG_KEY, &config)?;
 
     Ok(HandleResponse {
@@ -749,496 +980,1575 @@ fn try_set_status<S: Storage, A: Api, Q: Querier>(
 
 /// Returns HandleResult
 ///
-/// create a viewing key and set it with any active auctions the sender is the bidder
+/// sends a finalize message to the active auctions whose closing time has passed, earliest first.
+/// Each auction is finalized with the code hash it was instantiated with.  An auction that is still
+/// active after it was sent a finalize is skipped for CLOSE_RETRY_SECONDS, so an auction that can
+/// not close yet does not hold up the ones behind it
 ///
 /// # Arguments
 ///
 /// * `deps` - mutable reference to Extern containing all the contract's external dependencies
 /// * `env` - Env of contract's environment
-/// * `entropy` - string slice to be used as an entropy source for randomization
-fn try_create_key<S: Storage, A: Api, Q: Querier>(
+/// * `limit` - maximum number of auctions to finalize
+fn try_close_expired<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
     env: Env,
-    entropy: &str,
+    limit: u32,
 ) -> HandleResult {
-    // create and store the key
-    let prng_seed: Vec<u8> = load(&deps.storage, PRNG_SEED_KEY)?;
-    let key = ViewingKey::new(&env, &prng_seed, entropy.as_ref());
-    let message_sender = &deps.api.canonical_address(&env.message.sender)?;
-    let mut key_store = PrefixedStorage::new(PREFIX_VIEW_KEY, &mut deps.storage);
-    save(&mut key_store, message_sender.as_slice(), &key.to_hashed())?;
-
-    // clean up the bidder's list of active auctions
-    let load_active: Option<HashSet<u32>> = may_load(&deps.storage, ACTIVE_KEY)?;
-    if let Some(mut active) = load_active {
-        let mut bidder_store = PrefixedStorage::new(PREFIX_BIDDERS, &mut deps.storage);
-        let (my_active, update) = filter_only_active(&bidder_store, message_sender, &mut active)?;
-        // if list was updated, save it
-        if update {
-            save(&mut bidder_store, message_sender.as_slice(), &my_active)?;
+    if limit > MAX_CLOSE_EXPIRED {
+        return Err(StdError::generic_err(format!(
+            "Can not finalize more than {} auctions at a time",
+            MAX_CLOSE_EXPIRED
+        )));
+    }
+    let now = env.block.time;
+    let buckets: BTreeSet<u64> = may_load(&deps.storage, ENDING_BUCKETS_KEY)?.unwrap_or_default();
+    let read_ending = ReadonlyPrefixedStorage::new(PREFIX_ENDING, &deps.storage);
+    let read_info = ReadonlyPrefixedStorage::new(PREFIX_ACTIVE_INFO, &deps.storage);
+    let read_hash = ReadonlyPrefixedStorage::new(PREFIX_CODE_HASHES, &deps.storage);
+    let read_retry = ReadonlyPrefixedStorage::new(PREFIX_CLOSE_RETRY, &deps.storage);
+    let mut expired = Vec::new();
+    // buckets are visited earliest first, so stop once enough expired auctions have been found
+    for bucket in buckets.range(..=now / ENDING_BUCKET_SECONDS) {
+        if expired.len() >= limit as usize {
+            break;
+        }
+        let indices: HashSet<u32> =
+            may_load(&read_ending, &bucket.to_le_bytes())?.unwrap_or_default();
+        for index in indices.into_iter() {
+            let key = index.to_le_bytes();
+            let may_info: Option<StoreAuctionInfo> = may_load(&read_info, &key)?;
+            if let Some(info) = may_info.filter(|i| i.ends_at <= now) {
+                let retry_after: Option<u64> = may_load(&read_retry, &key)?;
+                if retry_after.map_or(false, |r| now < r) {
+                    continue;
+                }
+                // auctions created before the factory recorded code hashes must be finalized
+                // directly
+                if let Some(code_hash) = may_load::<String, _>(&read_hash, &key)? {
+                    expired.push((info.ends_at, index, info.address, code_hash));
+                }
+            }
         }
     }
+    expired.sort_by(|a, b| a.0.cmp(&b.0).then(a.1.cmp(&b.1)));
+    expired.truncate(limit as usize);
+
+    let mut messages = Vec::new();
+    let mut auctions = Vec::new();
+    let retry_after = now.saturating_add(CLOSE_RETRY_SECONDS);
+    for (_, index, address, code_hash) in expired.into_iter() {
+        messages.push(
+            AuctionHandleMsg::Finalize {
+                new_ends_at: None,
+                new_minimum_bid: None,
+            }
+            .to_cosmos_msg(code_hash, deps.api.human_address(&address)?, None)?,
+        );
+        // an auction that closes removes this when it calls back
+        let mut retry_store = PrefixedStorage::new(PREFIX_CLOSE_RETRY, &mut deps.storage);
+        save(&mut retry_store, &index.to_le_bytes(), &retry_after)?;
+        auctions.push(index);
+    }
 
     Ok(HandleResponse {
-        messages: vec![],
+        messages,
         log: vec![],
-        data: Some(to_binary(&HandleAnswer::ViewingKey {
-            key: format!("{}", key),
+        data: Some(to_binary(&HandleAnswer::CloseExpired {
+            status: Success,
+            auctions,
         })?),
     })
 }
 
-/// Returns HandleResult
+/// Returns StdResult<HumanAddr>
 ///
-/// sets the viewing key and set it with any active auctions the sender is the bidder
+/// verifies the sender is the admin and returns the address of the specified active auction
 ///
 /// # Arguments
 ///
-/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
-/// * `env` - Env of contract's environment
-/// * `key` - string slice to be used as the viewing key
-fn try_set_key<S: Storage, A: Api, Q: Querier>(
-    deps: &mut Extern<S, A, Q>,
-    env: Env,
-    key: &str,
-) -> HandleResult {
-    // store the viewing key
-    let vk = ViewingKey(key.to_string());
-    let message_sender = &deps.api.canonical_address(&env.message.sender)?;
-    let mut key_store = PrefixedStorage::new(PREFIX_VIEW_KEY, &mut deps.storage);
-    save(&mut key_store, message_sender.as_slice(), &vk.to_hashed())?;
-
-    // clean up the bidder's list of active auctions
-    let load_active: Option<HashSet<u32>> = may_load(&deps.storage, ACTIVE_KEY)?;
-    if let Some(mut active) = load_active {
-        let mut bidder_store = PrefixedStorage::new(PREFIX_BIDDERS, &mut deps.storage);
-        let (my_active, update) = filter_only_active(&bidder_store, message_sender, &mut active)?;
-        // if list was updated, save it
-        if update {
-            save(&mut bidder_store, message_sender.as_slice(), &my_active)?;
-        }
+/// * `deps` - a reference to Extern containing all the contract's external dependencies
+/// * `env` - a reference to the Env of contract's environment
+/// * `config` - a reference to the factory Config
+/// * `index` - index of the auction
+fn admin_active_auction<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    env: &Env,
+    config: &Config,
+    index: u32,
+) -> StdResult<HumanAddr> {
+    let sender = deps.api.canonical_address(&env.message.sender)?;
+    if config.admin != sender {
+        return Err(StdError::generic_err(
+            "This is an admin command. Admin commands can only be run from admin address",
+        ));
     }
+    let read_info = ReadonlyPrefixedStorage::new(PREFIX_ACTIVE_INFO, &deps.storage);
+    let info: StoreAuctionInfo = may_load(&read_info, &index.to_le_bytes())?
+        .ok_or_else(|| StdError::generic_err(format!("Auction {} is not active", index)))?;
+    deps.api.human_address(&info.address)
+}
+
+/// Returns HandleResult
+///
+/// allows admin to halt or resume an active auction
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `index` - index of the auction
+/// * `halted` - true if the auction should be halted
+fn try_halt_auction<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    index: u32,
+    halted: bool,
+) -> HandleResult {
+    let config: Config = load_config(&deps.storage)?;
+    let address = admin_active_auction(deps, &env, &config, index)?;
 
     Ok(HandleResponse {
-        messages: vec![],
+        messages: vec![AuctionHandleMsg::Halt { halted }.to_cosmos_msg(
+            config.version.code_hash,
+            address,
+            None,
+        )?],
         log: vec![],
-        data: Some(to_binary(&HandleAnswer::ViewingKey {
-            key: key.to_string(),
+        data: Some(to_binary(&HandleAnswer::Status {
+            status: Success,
+            message: None,
         })?),
     })
 }
 
-/// Returns StdResult<()>
+/// Returns HandleResult
 ///
-/// remove an auction from a seller's or bidder's list of active auctions
+/// allows admin to close an active auction that is still open long after its closing time
 ///
 /// # Arguments
 ///
-/// * `storage` - mutable reference to contract's storage
-/// * `prefix` - prefix to storage of either seller's or bidder's active auction lists
-/// * `person` - a reference to the canonical address of the person the list belongs to
-/// * `index` - index of the auction to remove
-fn remove_from_persons_active<S: Storage>(
-    storage: &mut S,
-    prefix: &[u8],
-    person: &CanonicalAddr,
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `index` - index of the auction
+fn try_force_finalize_auction<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
     index: u32,
-) -> StdResult<()> {
-    let mut store = PrefixedStorage::new(prefix, storage);
-    let load_active: Option<HashSet<u32>> = may_load(&store, person.as_slice())?;
-    if let Some(mut active) = load_active {
-        active.remove(&index);
-        save(&mut store, person.as_slice(), &active)?;
-    }
-    Ok(())
+) -> HandleResult {
+    let config: Config = load_config(&deps.storage)?;
+    let address = admin_active_auction(deps, &env, &config, index)?;
+
+    Ok(HandleResponse {
+        messages: vec![AuctionHandleMsg::ForceFinalize {}.to_cosmos_msg(
+            config.version.code_hash,
+            address,
+            None,
+        )?],
+        log: vec![],
+        data: Some(to_binary(&HandleAnswer::Status {
+            status: Success,
+            message: None,
+        })?),
+    })
 }
 
-/// Returns StdResult<(HashSet<u32>, bool)> which is the address' updated active list
-/// and a bool that is true if the list has been changed from what was in storage
+/// Returns HandleResult
 ///
-/// remove any closed auctions from the list
+/// allows admin to hand an active auction over to a new factory
 ///
 /// # Arguments
 ///
-/// * `storage` - a reference to bidder's active list storage subspace
-/// * `address` - a reference to the canonical address of the person the list belongs to
-/// * `active` - a mutable reference to the HashSet list of active auctions
-fn filter_only_active<S: ReadonlyStorage>(
-    storage: &S,
-    address: &CanonicalAddr,
-    active: &mut HashSet<u32>,
-) -> StdResult<(HashSet<u32>, bool)> {
-    // get person's current list
-    let load_auctions: Option<HashSet<u32>> = may_load(storage, address.as_slice())?;
-
-    // if there are active auctions in the list
-    if let Some(my_auctions) = load_auctions {
-        let start_len = my_auctions.len();
-        // only keep the intersection of the person's list and the active auctions list
-        let my_active: HashSet<u32> = my_auctions.iter().filter_map(|v| active.take(v)).collect();
-        let updated = start_len != my_active.len();
-        return Ok((my_active, updated));
-        // if not just return an empty list
-    }
-    Ok((HashSet::new(), false))
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `index` - index of the auction with this factory
+/// * `factory` - code hash and address of the new factory
+/// * `new_index` - index of the auction with the new factory
+/// * `callback_key` - key the new factory gave the auction to authenticate its callbacks
+fn try_migrate_auction<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    index: u32,
+    factory: ContractInfo,
+    new_index: u32,
+    callback_key: String,
+) -> HandleResult {
+    let config: Config = load_config(&deps.storage)?;
+    let address = admin_active_auction(deps, &env, &config, index)?;
+
+    // stop listing the auction and accepting its callbacks
+    let mut active: HashSet<u32> = load(&deps.storage, ACTIVE_KEY)?;
+    active.remove(&index);
+    save(&mut deps.storage, ACTIVE_KEY, &active)?;
+    let mut info_store = PrefixedStorage::new(PREFIX_ACTIVE_INFO, &mut deps.storage);
+    let info: StoreAuctionInfo = load(&info_store, &index.to_le_bytes())?;
+    info_store.remove(&index.to_le_bytes());
+    remove_from_ending_index(&mut deps.storage, info.ends_at, index)?;
+    remove_from_tag_index(&mut deps.storage, index)?;
+    let mut key_store = PrefixedStorage::new(PREFIX_CALLBACK_KEYS, &mut deps.storage);
+    key_store.remove(&index.to_le_bytes());
+    let mut hash_store = PrefixedStorage::new(PREFIX_CODE_HASHES, &mut deps.storage);
+    hash_store.remove(&index.to_le_bytes());
+    let mut nick_store = PrefixedStorage::new(PREFIX_NICKNAMES, &mut deps.storage);
+    nick_store.remove(&index.to_le_bytes());
+    // the new factory will not know about any creation fee to share with referrers, so the
+    // reserve becomes withdrawable
+    release_auction_fee(&mut deps.storage, index)?;
+    let mut auction_seller_store = PrefixedStorage::new(PREFIX_AUCTION_SELLERS, &mut deps.storage);
+    let may_seller: Option<CanonicalAddr> = may_load(&auction_seller_store, &index.to_le_bytes())?;
+    auction_seller_store.remove(&index.to_le_bytes());
+    if let Some(seller_raw) = may_seller {
+        remove_from_persons_active(
+            &mut deps.storage,
+            AddressList::SellerActive,
+            &seller_raw,
+            index,
+        )?;
+    }
+
+    Ok(HandleResponse {
+        messages: vec![AuctionHandleMsg::Migrate {
+            factory,
+            index: new_index,
+            callback_key,
+        }
+        .to_cosmos_msg(config.version.code_hash, address, None)?],
+        log: vec![],
+        data: Some(to_binary(&HandleAnswer::Status {
+            status: Success,
+            message: None,
+        })?),
+    })
 }
 
-/////////////////////////////////////// Query /////////////////////////////////////
-/// Returns QueryResult
+/// Returns HandleResult
+///
+/// allows admin to requery a token's symbol and decimals and update the cached values
 ///
 /// # Arguments
 ///
-/// * `deps` - reference to Extern containing all the contract's external dependencies
-/// * `msg` - QueryMsg passed in with the query call
-pub fn query<S: Storage, A: Api, Q: Querier>(deps: &Extern<S, A, Q>, msg: QueryMsg) -> QueryResult {
-    let response = match msg {
-        QueryMsg::ListMyAuctions {
-            address,
-            viewing_key,
-            filter,
-        } => try_list_my(deps, &address, viewing_key, filter),
-        QueryMsg::ListActiveAuctions {} => try_list_active(deps),
-        QueryMsg::ListClosedAuctions { before, page_size } => {
-            try_list_closed(deps, before, page_size)
-        }
-        QueryMsg::IsKeyValid {
-            address,
-            viewing_key,
-        } => try_validate_key(deps, &address, viewing_key),
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `token` - ContractInfo of the token to refresh
+fn try_refresh_token_info<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    token: ContractInfo,
+) -> HandleResult {
+    // only allow admin to do this
+    let config: Config = load_config(&deps.storage)?;
+    let sender = deps.api.canonical_address(&env.message.sender)?;
+    if config.admin != sender {
+        return Err(StdError::generic_err(
+            "This is an admin command. Admin commands can only be run from admin address",
+        ));
+    }
+    let key = token_key(
+        &deps.api.canonical_address(&token.address)?,
+        token.token_id.as_ref(),
+    );
+    if load_symdec(&deps.storage, &key)?.is_none() {
+        return Err(StdError::generic_err(format!(
+            "Token {} has not been used by any auction",
+            token.address
+        )));
+    }
+    let token_info = token.token_info_query(&deps.querier)?;
+    let symdec = TokenSymDec {
+        symbol: token_info.symbol,
+        decimals: token_info.decimals,
     };
-    pad_query_result(response, BLOCK_SIZE)
+    let message = format!(
+        "Token {} now displays as {} with {} decimals",
+        token.address, symdec.symbol, symdec.decimals
+    );
+    let mut symdec_store = PrefixedStorage::new(PREFIX_SYMDECS, &mut deps.storage);
+    save(&mut symdec_store, &key, &symdec)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: Some(to_binary(&HandleAnswer::Status {
+            status: Success,
+            message: Some(message),
+        })?),
+    })
 }
 
-/// Returns QueryResult indicating whether the address/key pair is valid
+/// Returns HandleResult
+///
+/// allows admin to record or clear the origin chain and denom of an IBC-wrapped token
 ///
 /// # Arguments
 ///
-/// * `deps` - reference to Extern containing all the contract's external dependencies
-/// * `address` - a reference to the address whose key should be validated
-/// * `viewing_key` - String key used for authentication
-fn try_validate_key<S: Storage, A: Api, Q: Querier>(
-    deps: &Extern<S, A, Q>,
-    address: &HumanAddr,
-    viewing_key: String,
-) -> QueryResult {
-    let addr_raw = &deps.api.canonical_address(address)?;
-    to_binary(&QueryAnswer::IsKeyValid {
-        is_valid: is_key_valid(&deps.storage, addr_raw, viewing_key)?,
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `token_address` - address of the token contract
+/// * `token_id` - id of the token if this is a SNIP-1155 contract
+/// * `origin` - optional origin of the token.  None removes any existing origin
+fn try_set_token_origin<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    token_address: HumanAddr,
+    token_id: Option<String>,
+    origin: Option<IbcOrigin>,
+) -> HandleResult {
+    // only allow admin to do this
+    let config: Config = load_config(&deps.storage)?;
+    let sender = deps.api.canonical_address(&env.message.sender)?;
+    if config.admin != sender {
+        return Err(StdError::generic_err(
+            "This is an admin command. Admin commands can only be run from admin address",
+        ));
+    }
+    let key = token_key(
+        &deps.api.canonical_address(&token_address)?,
+        token_id.as_ref(),
+    );
+    if load_symdec(&deps.storage, &key)?.is_none() {
+        return Err(StdError::generic_err(format!(
+            "Token {} has not been used by any auction",
+            token_address
+        )));
+    }
+    let mut origin_store = PrefixedStorage::new(PREFIX_TOKEN_ORIGINS, &mut deps.storage);
+    let message = if let Some(orig) = origin {
+        if orig.chain_id.is_empty() || orig.denom.is_empty() {
+            return Err(StdError::generic_err(
+                "The origin chain id and denom can not be blank",
+            ));
+        }
+        let message = format!(
+            "Token {} is now displayed as {} from chain {}",
+            token_address, orig.denom, orig.chain_id
+        );
+        save(&mut origin_store, &key, &orig)?;
+        message
+    } else {
+        remove(&mut origin_store, &key);
+        format!("Token {} no longer displays an origin", token_address)
+    };
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: Some(to_binary(&HandleAnswer::Status {
+            status: Success,
+            message: Some(message),
+        })?),
     })
 }
 
-/// Returns QueryResult listing the active auctions
+/// Returns HandleResult
+///
+/// adds an active auction to, or removes an auction from, the caller's watchlist.  Closed
+/// auctions are pruned from the watchlist whenever it is updated
 ///
 /// # Arguments
 ///
-/// * `deps` - reference to Extern containing all the contract's external dependencies
-fn try_list_active<S: Storage, A: Api, Q: Querier>(deps: &Extern<S, A, Q>) -> QueryResult {
-    to_binary(&QueryAnswer::ListActiveAuctions {
-        active: display_active_list(&deps.api, &deps.storage, None, ACTIVE_KEY)?,
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `index` - factory index of the auction
+/// * `watch` - true if the auction should be added to the watchlist
+fn try_watch<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    index: u32,
+    watch: bool,
+) -> HandleResult {
+    let mut active: HashSet<u32> = load(&deps.storage, ACTIVE_KEY)?;
+    if watch && !active.contains(&index) {
+        return Err(StdError::generic_err(format!(
+            "Auction {} is not an active auction",
+            index
+        )));
+    }
+    let watcher_raw = &deps.api.canonical_address(&env.message.sender)?;
+    let mut watch_store = PrefixedStorage::new(AddressList::Watcher.prefix(), &mut deps.storage);
+    // remove any auctions that have closed
+    let (mut watched, _) = filter_only_active(&watch_store, watcher_raw, &mut active)?;
+    if watch {
+        watched.insert(index);
+        if watched.len() > MAX_WATCHED {
+            return Err(StdError::generic_err(format!(
+                "You can not watch more than {} active auctions",
+                MAX_WATCHED
+            )));
+        }
+    } else {
+        watched.remove(&index);
+    }
+    if watched.is_empty() {
+        watch_store.remove(watcher_raw.as_slice());
+    } else {
+        save(&mut watch_store, watcher_raw.as_slice(), &watched)?;
+    }
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: Some(to_binary(&HandleAnswer::Status {
+            status: Success,
+            message: None,
+        })?),
     })
 }
 
-/// Returns StdResult<bool> result of validating an address' viewing key
+/// Returns HandleResult
+///
+/// allows a seller to set or remove the nickname of one of their active auctions
 ///
 /// # Arguments
 ///
-/// * `storage` - a reference to the contract's storage
-/// * `address` - a reference to the address whose key should be validated
-/// * `viewing_key` - String key used for authentication
-fn is_key_valid<S: ReadonlyStorage>(
-    storage: &S,
-    address: &CanonicalAddr,
-    viewing_key: String,
-) -> StdResult<bool> {
-    // load the address' key
-    let read_key = ReadonlyPrefixedStorage::new(PREFIX_VIEW_KEY, storage);
-    let load_key: Option<[u8; VIEWING_KEY_SIZE]> = may_load(&read_key, address.as_slice())?;
-    let input_key = ViewingKey(viewing_key);
-    // if a key was set
-    if let Some(expected_key) = load_key {
-        // and it matches
-        if input_key.check_viewing_key(&expected_key) {
-            return Ok(true);
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `auction` - a reference to the address of the auction to nickname
+/// * `nickname` - optional new nickname
+fn try_set_nickname<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    auction: &HumanAddr,
+    nickname: Option<String>,
+) -> HandleResult {
+    if let Some(name) = nickname.as_ref() {
+        check_nickname(name)?;
+    }
+    let seller_raw = &deps.api.canonical_address(&env.message.sender)?;
+    let auction_raw = &deps.api.canonical_address(auction)?;
+    // find the auction in the seller's list of active auctions
+    let seller_store =
+        ReadonlyPrefixedStorage::new(AddressList::SellerActive.prefix(), &deps.storage);
+    let my_active: HashSet<u32> =
+        may_load(&seller_store, seller_raw.as_slice())?.unwrap_or_default();
+    let info_store = ReadonlyPrefixedStorage::new(PREFIX_ACTIVE_INFO, &deps.storage);
+    let mut found: Option<u32> = None;
+    for index in my_active.iter() {
+        let may_info: Option<StoreAuctionInfo> = may_load(&info_store, &index.to_le_bytes())?;
+        if may_info.map_or(false, |i| i.address == *auction_raw) {
+            found = Some(*index);
+            break;
         }
+    }
+    let index = found.ok_or_else(|| {
+        StdError::generic_err(format!(
+            "{} is not an active auction you are selling",
+            auction
+        ))
+    })?;
+    let mut nick_store = PrefixedStorage::new(PREFIX_NICKNAMES, &mut deps.storage);
+    if let Some(name) = nickname {
+        save(&mut nick_store, &index.to_le_bytes(), &name)?;
     } else {
-        // Checking the key will take significant time. We don't want to exit immediately if it isn't set
-        // in a way which will allow to time the command and determine if a viewing key doesn't exist
-        input_key.check_viewing_key(&[0u8; VIEWING_KEY_SIZE]);
+        nick_store.remove(&index.to_le_bytes());
     }
-    Ok(false)
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: Some(to_binary(&HandleAnswer::Status {
+            status: Success,
+            message: None,
+        })?),
+    })
 }
 
-/// Returns QueryResult listing the auctions the address interacted with
+/// Returns ContractInfo
+///
+/// the factory's own code hash and address
+///
+/// # Arguments
+///
+/// * `env` - a reference to the Env of contract's environment
+pub(crate) fn this_contract(env: &Env) -> ContractInfo {
+    ContractInfo {
+        code_hash: env.contract_code_hash.clone(),
+        address: env.contract.address.clone(),
+        token_id: None,
+    }
+}
+
+/// Returns StdResult<()>
+///
+/// returns an error if the sender is not the admin
 ///
 /// # Arguments
 ///
 /// * `deps` - reference to Extern containing all the contract's external dependencies
-/// * `address` - a reference to the address whose auctions should be listed
-/// * `viewing_key` - String key used to authenticate the query
-/// * `filter` - optional choice of display filters
-fn try_list_my<S: Storage, A: Api, Q: Querier>(
+/// * `env` - a reference to the Env of contract's environment
+pub(crate) fn check_admin<S: Storage, A: Api, Q: Querier>(
     deps: &Extern<S, A, Q>,
-    address: &HumanAddr,
-    viewing_key: String,
-    filter: Option<FilterTypes>,
-) -> QueryResult {
-    let addr_raw = &deps.api.canonical_address(address)?;
-    // if key matches
-    if is_key_valid(&deps.storage, addr_raw, viewing_key)? {
-        let mut active_lists: Option<MyActiveLists> = None;
-        let mut closed_lists: Option<MyClosedLists> = None;
-        // if no filter default to ALL
-        let types = filter.unwrap_or(FilterTypes::All);
-
-        // list the active auctions
-        if types == FilterTypes::Active || types == FilterTypes::All {
-            let seller_active = display_active_list(
-                &deps.api,
-                &deps.storage,
-                Some(PREFIX_SELLERS_ACTIVE),
-                addr_raw.as_slice(),
-            )?;
-            let bidder_active = display_active_list(
-                &deps.api,
-                &deps.storage,
-                Some(PREFIX_BIDDERS),
-                addr_raw.as_slice(),
-            )?;
-            if seller_active.is_some() || bidder_active.is_some() {
-                active_lists = Some(MyActiveLists {
-                    as_seller: seller_active,
-                    as_bidder: bidder_active,
-                });
-            }
+    env: &Env,
+) -> StdResult<()> {
+    let config: Config = load_config(&deps.storage)?;
+    if config.admin != deps.api.canonical_address(&env.message.sender)? {
+        return Err(StdError::generic_err(
+            "This is an admin command. Admin commands can only be run from admin address",
+        ));
+    }
+    Ok(())
+}
+
+/// Returns StdResult<()>
+///
+/// verifies that a nickname is not too long
+///
+/// # Arguments
+///
+/// * `nickname` - a reference to the nickname to check
+pub(crate) fn check_nickname(nickname: &str) -> StdResult<()> {
+    if nickname.chars().count() > MAX_NICKNAME_LEN {
+        return Err(StdError::generic_err(format!(
+            "Nicknames can not be longer than {} characters",
+            MAX_NICKNAME_LEN
+        )));
+    }
+    Ok(())
+}
+
+/// Returns StdResult<Vec<String>>
+///
+/// verifies that an auction does not have too many tags and that none are empty or too long,
+/// and returns them lowercased without duplicates so tags match regardless of case
+///
+/// # Arguments
+///
+/// * `tags` - the tags to check
+pub(crate) fn check_tags(tags: Vec<String>) -> StdResult<Vec<String>> {
+    let mut checked: Vec<String> = Vec::new();
+    for tag in tags.into_iter() {
+        let tag = tag.trim().to_lowercase();
+        if tag.is_empty() || tag.chars().count() > MAX_TAG_LEN {
+            return Err(StdError::generic_err(format!(
+                "Tags must be 1 to {} characters long",
+                MAX_TAG_LEN
+            )));
         }
-        // list the closed auctions
-        if types == FilterTypes::Closed || types == FilterTypes::All {
-            let seller_closed = display_addr_closed(
-                &deps.api,
-                &deps.storage,
-                PREFIX_SELLERS_CLOSED,
-                addr_raw.as_slice(),
-            )?;
-            let won = display_addr_closed(
-                &deps.api,
-                &deps.storage,
-                PREFIX_WINNERS,
-                addr_raw.as_slice(),
-            )?;
-            if seller_closed.is_some() || won.is_some() {
-                closed_lists = Some(MyClosedLists {
-                    as_seller: seller_closed,
-                    won,
-                });
+        if !checked.contains(&tag) {
+            checked.push(tag);
+        }
+    }
+    if checked.len() > MAX_TAGS {
+        return Err(StdError::generic_err(format!(
+            "An auction can not have more than {} tags",
+            MAX_TAGS
+        )));
+    }
+    Ok(checked)
+}
+
+/// Returns HandleResult
+///
+/// allows admin to prune the records of auctions that closed before the specified time, at most
+/// a page at a time.  The closing order itself is never rewritten.  It is only read through the
+/// AppendStore, and PRUNED_KEY marks how much of its beginning has been pruned
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `keep_after_timestamp` - closed auctions with an earlier closing time will be pruned
+/// * `page_size` - optional maximum number of records to prune
+fn try_prune_closed<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    keep_after_timestamp: u64,
+    page_size: Option<u32>,
+) -> HandleResult {
+    // only allow admin to do this
+    let config: Config = load_config(&deps.storage)?;
+    let sender = deps.api.canonical_address(&env.message.sender)?;
+    if config.admin != sender {
+        return Err(StdError::generic_err(
+            "This is an admin command. Admin commands can only be run from admin address",
+        ));
+    }
+    let start: u32 = may_load(&deps.storage, PRUNED_KEY)?.unwrap_or(0);
+    let mut pruned = start;
+    let order_store = ReadonlyPrefixedStorage::new(PREFIX_CLOSED_ORDER, &deps.storage);
+    let mut prune_indices = Vec::new();
+    let mut more = false;
+    if let Some(order) = AppendStore::<u32, _>::attach(&order_store).transpose()? {
+        // auctions are added to the closing order as they close, so stop at the first auction
+        // that should be kept
+        let info_store = ReadonlyPrefixedStorage::new(PREFIX_CLOSED_INFO, &deps.storage);
+        let end = order
+            .len()
+            .min(start.saturating_add(page_size.unwrap_or(DEFAULT_PRUNE_PAGE).max(1)));
+        more = end < order.len();
+        while pruned < end {
+            let index = order.get_at(pruned)?;
+            let may_info: Option<StoreClosedAuctionInfo> =
+                may_load(&info_store, &index.to_le_bytes())?;
+            if may_info.map_or(false, |i| i.timestamp >= keep_after_timestamp) {
+                more = false;
+                break;
             }
+            prune_indices.push(index);
+            pruned += 1;
         }
+    }
+    let mut closed_info_store = PrefixedStorage::new(PREFIX_CLOSED_INFO, &mut deps.storage);
+    for index in prune_indices.iter() {
+        closed_info_store.remove(&index.to_le_bytes());
+    }
+    let mut closed_nick_store = PrefixedStorage::new(PREFIX_CLOSED_NICKNAMES, &mut deps.storage);
+    for index in prune_indices.iter() {
+        closed_nick_store.remove(&index.to_le_bytes());
+    }
+    save(&mut deps.storage, PRUNED_KEY, &pruned)?;
 
-        return to_binary(&QueryAnswer::ListMyAuctions {
-            active: active_lists,
-            closed: closed_lists,
-        });
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: Some(to_binary(&HandleAnswer::Status {
+            status: Success,
+            message: Some(format!(
+                "Pruned {} closed auction records{}",
+                pruned - start,
+                if more {
+                    ".  More records may be pruned with another prune_closed message"
+                } else {
+                    ""
+                }
+            )),
+        })?),
+    })
+}
+
+/// Returns HandleResult
+///
+/// create a series of auctions with the same parameters
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `label` - String containing the label of the series
+/// * `sell_contract` - ContractInfo containing the code hash and address of the sale token
+/// * `bid_contract` - ContractInfo containing the code hash and address of the bid token
+/// * `sell_amount` - Uint128 amount to sell in each round
+/// * `minimum_bid` - Uint128 minimum bid of each round
+/// * `interval` - number of seconds each round lasts
+/// * `rounds` - number of rounds in the series
+/// * `description` - optional free-form text string describing the auctions
+#[allow(clippy::too_many_arguments)]
+fn try_create_series<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    label: String,
+    sell_contract: ContractInfo,
+    bid_contract: ContractInfo,
+    sell_amount: Uint128,
+    minimum_bid: Uint128,
+    interval: u64,
+    rounds: u32,
+    description: Option<String>,
+) -> HandleResult {
+    if rounds == 0 {
+        return Err(StdError::generic_err(
+            "A series must have at least one round",
+        ));
     }
-    to_binary(&QueryAnswer::ViewingKeyError {
-        error: "Wrong viewing key for this address or viewing key not set".to_string(),
+    if interval == 0 {
+        return Err(StdError::generic_err(
+            "The interval between rounds must be greater than 0",
+        ));
+    }
+    check_allowlisted(&deps.storage, &deps.api, &env.message.sender)?;
+    let ends_at = env.block.time.saturating_add(interval);
+    check_new_auction(
+        &deps.storage,
+        env.block.time,
+        &sell_contract,
+        &bid_contract,
+        sell_amount,
+        ends_at,
+        0,
+    )?;
+    let escrow = sell_amount
+        .u128()
+        .checked_mul(rounds as u128)
+        .ok_or_else(|| StdError::generic_err("The total amount sold by the series is too large"))?;
+
+    let config: Config = load_config(&deps.storage)?;
+    let auction = NewAuction {
+        seller: env.message.sender.clone(),
+        label: Some(format!("{}-{}", label, config.index)),
+        sell_contract: sell_contract.clone(),
+        bid_contract: bid_contract.clone(),
+        sell_amount,
+        minimum_bid,
+        ends_at,
+        description: description.clone(),
+        descriptions: Vec::new(),
+        bundle: Vec::new(),
+        deposit: None,
+        consign_by: None,
+        buy_now_price: None,
+        no_self_bid: false,
+        only_increasing_rebids: false,
+        tie_break: TieBreak::default(),
+        allowance_bids: false,
+        message_board: false,
+        min_bid_decay: None,
+        seller_confirmation: None,
+        unit_price_bids: false,
+        max_bidders: None,
+        min_bidders: None,
+        reveal_window: None,
+        settlement: SettlementMode::default(),
+        funding_target: None,
+    };
+    let (auction_msg, index, _) = new_auction_msg(deps, &env, auction, true)?;
+
+    let id: u32 = may_load(&deps.storage, SERIES_COUNT_KEY)?.unwrap_or(0);
+    let series = Series {
+        seller: deps.api.canonical_address(&env.message.sender)?,
+        label,
+        sell_contract,
+        bid_contract,
+        sell_amount: sell_amount.u128(),
+        minimum_bid: minimum_bid.u128(),
+        description,
+        interval,
+        rounds,
+        history: vec![SeriesRound {
+            index,
+            address: None,
+            ends_at,
+            closed: false,
+            winning_bid: None,
+        }],
+        cancelled: false,
+    };
+    let mut series_store = PrefixedStorage::new(PREFIX_SERIES, &mut deps.storage);
+    save(&mut series_store, &id.to_le_bytes(), &series)?;
+    let mut round_store = PrefixedStorage::new(PREFIX_SERIES_ROUNDS, &mut deps.storage);
+    save(&mut round_store, &index.to_le_bytes(), &id)?;
+    save(&mut deps.storage, SERIES_COUNT_KEY, &(id + 1))?;
+
+    // place the sell tokens of every round in escrow before creating the first round
+    let escrow_msg = series.sell_contract.transfer_from_msg(
+        env.message.sender,
+        env.contract.address,
+        Uint128(escrow),
+    )?;
+
+    Ok(HandleResponse {
+        messages: vec![escrow_msg, auction_msg],
+        log: vec![],
+        data: Some(to_binary(&HandleAnswer::Status {
+            status: Success,
+            message: Some(format!("Created series {}", id)),
+        })?),
     })
 }
 
-/// Returns StdResult<Option<Vec<AuctionInfo>>>
+/// Returns StdResult<Option<CosmosMsg>>
 ///
-/// provide the appropriate list of active auctions
+/// records the result of a closed series round and creates the next round if there is one
 ///
 /// # Arguments
 ///
-/// * `api` - reference to the Api used to convert canonical and human addresses
-/// * `storage` - a reference to the contract's storage
-/// * `prefix` - optional storage prefix to load from
-/// * `key` - storage key to read
-fn display_active_list<S: ReadonlyStorage, A: Api>(
-    api: &A,
-    storage: &S,
-    prefix: Option<&[u8]>,
-    key: &[u8],
-) -> StdResult<Option<Vec<AuctionInfo>>> {
-    let load_list: Option<HashSet<u32>> = if let Some(pref) = prefix {
-        // reading a person's list
-        let read = &ReadonlyPrefixedStorage::new(pref, storage);
-        // if reading a bidder's list
-        if pref == PREFIX_BIDDERS {
-            // read the factory's active list
-            let load_active: Option<HashSet<u32>> = may_load(storage, ACTIVE_KEY)?;
-            if let Some(mut active) = load_active {
-                let canonical = CanonicalAddr(Binary(key.to_vec()));
-                // remove any auctions that closed from the list
-                let (my_active, _) = filter_only_active(read, &canonical, &mut active)?;
-                Some(my_active)
-            } else {
-                None
-            }
-        // read a seller's list
-        } else {
-            may_load(read, key)?
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - a reference to the Env of contract's environment
+/// * `id` - id of the series
+/// * `index` - index of the round that closed
+/// * `winning_bid` - the round's winning bid if it had one
+pub(crate) fn next_series_round<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    id: u32,
+    index: u32,
+    winning_bid: Option<Uint128>,
+) -> StdResult<Option<CosmosMsg>> {
+    let series_store = ReadonlyPrefixedStorage::new(PREFIX_SERIES, &deps.storage);
+    let mut series: Series = load(&series_store, &id.to_le_bytes())?;
+    let mut prev_ends_at = env.block.time;
+    if let Some(round) = series.history.iter_mut().find(|r| r.index == index) {
+        round.closed = true;
+        round.winning_bid = winning_bid.map(|n| n.u128());
+        prev_ends_at = round.ends_at;
+    }
+    let mut cosmos_msg = None;
+    let config: Config = load_config(&deps.storage)?;
+    if !series.cancelled
+        && !config.status.creation_stopped
+        && (series.history.len() as u32) < series.rounds
+    {
+        // keep to the schedule unless the previous round closed late
+        let mut ends_at = prev_ends_at.saturating_add(series.interval);
+        if ends_at <= env.block.time {
+            ends_at = env.block.time.saturating_add(series.interval);
         }
-    // read the factory's active list
-    } else {
-        may_load(storage, key)?
-    };
-    // turn list of active auctions to a vec of displayable auction infos
-    let mut actives = match load_list {
-        Some(list) => {
-            let mut display_list = Vec::new();
-            let read_info = &ReadonlyPrefixedStorage::new(PREFIX_ACTIVE_INFO, storage);
-            // get the token symbol strings
-            let symdecs: Vec<TokenSymDec> = load(storage, SYMDEC_KEY)?;
-            for index in list.iter() {
-                // get this auction's info
-                let load_info: Option<StoreAuctionInfo> =
-                    may_load(read_info, &index.to_le_bytes())?;
-                if let Some(info) = load_info {
-                    let may_sell_symdec = symdecs.get(info.sell_symbol as usize);
-                    if let Some(sell_symdec) = may_sell_symdec {
-                        let may_bid_symdec = symdecs.get(info.bid_symbol as usize);
-                        if let Some(bid_symdec) = may_bid_symdec {
-                            let pair = format!("{}-{}", sell_symdec.symbol, bid_symdec.symbol);
-                            display_list.push(AuctionInfo {
-                                address: api.human_address(&info.address)?,
-                                label: info.label,
-                                pair,
-                                sell_amount: Uint128(info.sell_amount),
-                                sell_decimals: sell_symdec.decimals,
-                                minimum_bid: Uint128(info.minimum_bid),
-                                bid_decimals: bid_symdec.decimals,
-                                ends_at: info.ends_at,
-                            });
-                        }
-                    }
-                }
-            }
-            display_list
+        let auction = NewAuction {
+            seller: deps.api.human_address(&series.seller)?,
+            label: Some(format!("{}-{}", series.label, config.index)),
+            sell_contract: series.sell_contract.clone(),
+            bid_contract: series.bid_contract.clone(),
+            sell_amount: Uint128(series.sell_amount),
+            minimum_bid: Uint128(series.minimum_bid),
+            ends_at,
+            description: series.description.clone(),
+            descriptions: Vec::new(),
+            bundle: Vec::new(),
+            deposit: None,
+            consign_by: None,
+            buy_now_price: None,
+            no_self_bid: false,
+            only_increasing_rebids: false,
+            tie_break: TieBreak::default(),
+            allowance_bids: false,
+            message_board: false,
+            min_bid_decay: None,
+            seller_confirmation: None,
+            unit_price_bids: false,
+            max_bidders: None,
+            min_bidders: None,
+            reveal_window: None,
+            settlement: SettlementMode::default(),
+            funding_target: None,
+        };
+        // failing to create the next round must not prevent the previous round from closing, so
+        // the series just stalls, and the seller can cancel it to retrieve the remaining escrow
+        if let Ok((msg, new_index, _)) = new_auction_msg(deps, env, auction, false) {
+            series.history.push(SeriesRound {
+                index: new_index,
+                address: None,
+                ends_at,
+                closed: false,
+                winning_bid: None,
+            });
+            let mut round_store = PrefixedStorage::new(PREFIX_SERIES_ROUNDS, &mut deps.storage);
+            save(&mut round_store, &new_index.to_le_bytes(), &id)?;
+            cosmos_msg = Some(msg);
         }
-        None => Vec::new(),
-    };
-    if actives.is_empty() {
-        return Ok(None);
     }
-    // sort it by pair
-    actives.sort_by(|a, b| a.pair.cmp(&b.pair));
-    Ok(Some(actives))
+    let mut series_store = PrefixedStorage::new(PREFIX_SERIES, &mut deps.storage);
+    save(&mut series_store, &id.to_le_bytes(), &series)?;
+    Ok(cosmos_msg)
 }
 
-/// Returns StdResult<Option<Vec<ClosedAuctionInfo>>>
+/// Returns HandleResult
 ///
-/// provide the appropriate list of closed auctions
+/// allows a seller to cancel a series and retrieve the sell tokens of the rounds not created
 ///
 /// # Arguments
 ///
-/// * `api` - reference to the Api used to convert canonical and human addresses
-/// * `storage` - a reference to the contract's storage
-/// * `prefix` - storage prefix to load from
-/// * `key` - storage key to read
-fn display_addr_closed<S: ReadonlyStorage, A: Api>(
-    api: &A,
-    storage: &S,
-    prefix: &[u8],
-    key: &[u8],
-) -> StdResult<Option<Vec<ClosedAuctionInfo>>> {
-    let list_store = ReadonlyPrefixedStorage::multilevel(&[prefix, key], storage);
-    let may_read_list = AppendStore::<u32, _>::attach(&list_store);
-    let mut closed_vec = Vec::new();
-    if let Some(closed_list) = may_read_list.and_then(|r| r.ok()) {
-        let info_store = ReadonlyPrefixedStorage::new(PREFIX_CLOSED_INFO, storage);
-        let may_read_info = AppendStore::<StoreClosedAuctionInfo, _>::attach(&info_store);
-        if let Some(closed_info) = may_read_info.and_then(|r| r.ok()) {
-            // get the token symbol strings
-            let symdecs: Vec<TokenSymDec> = load(storage, SYMDEC_KEY)?;
-            // grab backwards from the starting point
-            for index_res in closed_list.iter().rev() {
-                if let Ok(index) = index_res {
-                    // get this auction's info
-                    let load_info = closed_info.get_at(index);
-                    if let Ok(info) = load_info {
-                        let may_sell_symdec = symdecs.get(info.sell_symbol as usize);
-                        if let Some(sell_symdec) = may_sell_symdec {
-                            let may_bid_symdec = symdecs.get(info.bid_symbol as usize);
-                            if let Some(bid_symdec) = may_bid_symdec {
-                                let pair = format!("{}-{}", sell_symdec.symbol, bid_symdec.symbol);
-                                closed_vec.push(ClosedAuctionInfo {
-                                    index: None,
-                                    address: api.human_address(&info.address)?,
-                                    label: info.label,
-                                    pair,
-                                    sell_amount: Uint128(info.sell_amount),
-                                    sell_decimals: sell_symdec.decimals,
-                                    winning_bid: info.winning_bid.map(Uint128),
-                                    bid_decimals: info.winning_bid.map(|_a| bid_symdec.decimals),
-                                    timestamp: info.timestamp,
-                                });
-                            }
-                        }
-                    }
-                }
-            }
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `id` - id of the series
+fn try_cancel_series<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    id: u32,
+) -> HandleResult {
+    let mut series_store = PrefixedStorage::new(PREFIX_SERIES, &mut deps.storage);
+    let may_series: Option<Series> = may_load(&series_store, &id.to_le_bytes())?;
+    let mut series =
+        may_series.ok_or_else(|| StdError::generic_err(format!("Series {} does not exist", id)))?;
+    if series.seller != deps.api.canonical_address(&env.message.sender)? {
+        return Err(StdError::generic_err("Only the seller can cancel a series"));
+    }
+    if series.cancelled {
+        return Err(StdError::generic_err(format!(
+            "Series {} has already been cancelled",
+            id
+        )));
+    }
+    series.cancelled = true;
+    save(&mut series_store, &id.to_le_bytes(), &series)?;
+
+    let unstarted = series.rounds - series.history.len() as u32;
+    let mut messages = Vec::new();
+    if unstarted > 0 {
+        messages.push(series.sell_contract.transfer_msg(
+            &env.contract.address,
+            env.message.sender,
+            Uint128(checked_mul(series.sell_amount, unstarted as u128)?),
+        )?);
+    }
+
+    Ok(HandleResponse {
+        messages,
+        log: vec![],
+        data: Some(to_binary(&HandleAnswer::Status {
+            status: Success,
+            message: Some(format!(
+                "Series cancelled.  Returned the sell tokens of {} round(s) that were not created",
+                unstarted
+            )),
+        })?),
+    })
+}
+
+/// Returns HandleResult
+///
+/// allows admin to settle two complementary auctions with crossing prices by swapping their lots
+/// between the two sellers
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `a` - index of the first auction
+/// * `b` - index of the second auction
+fn try_match_auctions<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    a: u32,
+    b: u32,
+) -> HandleResult {
+    // only allow admin to do this
+    let config: Config = load_config(&deps.storage)?;
+    let sender = deps.api.canonical_address(&env.message.sender)?;
+    if config.admin != sender {
+        return Err(StdError::generic_err(
+            "This is an admin command. Admin commands can only be run from admin address",
+        ));
+    }
+    if a == b {
+        return Err(StdError::generic_err(
+            "An auction can not be matched with itself",
+        ));
+    }
+    let read_info = ReadonlyPrefixedStorage::new(PREFIX_ACTIVE_INFO, &deps.storage);
+    let read_sellers = ReadonlyPrefixedStorage::new(PREFIX_AUCTION_SELLERS, &deps.storage);
+    let read_hash = ReadonlyPrefixedStorage::new(PREFIX_CODE_HASHES, &deps.storage);
+    let read_bundled = ReadonlyPrefixedStorage::new(PREFIX_BUNDLED, &deps.storage);
+    let mut matched = Vec::new();
+    for index in &[a, b] {
+        let key = index.to_le_bytes();
+        let may_info: Option<StoreAuctionInfo> = may_load(&read_info, &key)?;
+        let info = may_info
+            .ok_or_else(|| StdError::generic_err(format!("Auction {} is not active", index)))?;
+        let may_seller: Option<CanonicalAddr> = may_load(&read_sellers, &key)?;
+        let may_code_hash: Option<String> = may_load(&read_hash, &key)?;
+        let (seller, code_hash) = may_seller.zip(may_code_hash).ok_or_else(|| {
+            StdError::generic_err(format!("Auction {} can not be matched", index))
+        })?;
+        // the minimum bid prices the whole lot, so it can not be compared with the other
+        // auction's sell amount if other tokens are sold with it
+        if may_load::<u8, _>(&read_bundled, &key)?.is_some() {
+            return Err(StdError::generic_err(format!(
+                "Auction {} sells a bundled lot and can not be matched",
+                index
+            )));
         }
+        matched.push((info, deps.api.human_address(&seller)?, code_hash));
+    }
+    let (info_b, seller_b, code_hash_b) = matched.pop().unwrap();
+    let (info_a, seller_a, code_hash_a) = matched.pop().unwrap();
+    // each auction must sell the token the other accepts as bids
+    if info_a.sell_token != info_b.bid_token || info_a.bid_token != info_b.sell_token {
+        return Err(StdError::generic_err(format!(
+            "Auctions {} and {} do not trade the same tokens in opposite directions",
+            a, b
+        )));
     }
-    if closed_vec.is_empty() {
-        return Ok(None);
+    // each seller must receive at least their minimum bid
+    if info_b.sell_amount < info_a.minimum_bid || info_a.sell_amount < info_b.minimum_bid {
+        return Err(StdError::generic_err(format!(
+            "The prices of auctions {} and {} do not cross",
+            a, b
+        )));
     }
-    Ok(Some(closed_vec))
+    // both auctions settle in the same transaction, so if either fails, neither swap happens
+    let messages = vec![
+        AuctionHandleMsg::MatchSwap {
+            recipient: seller_b,
+            received: Uint128(info_b.sell_amount),
+        }
+        .to_cosmos_msg(code_hash_a, deps.api.human_address(&info_a.address)?, None)?,
+        AuctionHandleMsg::MatchSwap {
+            recipient: seller_a,
+            received: Uint128(info_a.sell_amount),
+        }
+        .to_cosmos_msg(code_hash_b, deps.api.human_address(&info_b.address)?, None)?,
+    ];
+
+    Ok(HandleResponse {
+        messages,
+        log: vec![],
+        data: Some(to_binary(&HandleAnswer::Status {
+            status: Success,
+            message: None,
+        })?),
+    })
 }
 
-/// Returns QueryResult listing the closed auctions
+/////////////////////////////////////// Query /////////////////////////////////////
+/// Returns QueryResult
 ///
 /// # Arguments
 ///
 /// * `deps` - reference to Extern containing all the contract's external dependencies
-/// * `before` - optional u32 index of the earliest auction you do not want to display
-/// * `page_size` - optional number of auctions to display
-fn try_list_closed<S: Storage, A: Api, Q: Querier>(
-    deps: &Extern<S, A, Q>,
-    before: Option<u32>,
-    page_size: Option<u32>,
-) -> QueryResult {
-    let read_store = ReadonlyPrefixedStorage::new(PREFIX_CLOSED_INFO, &deps.storage);
-    let may_read_store = AppendStore::<StoreClosedAuctionInfo, _>::attach(&read_store);
-    let mut closed_vec = Vec::new();
-    if let Some(closed_store) = may_read_store.and_then(|r| r.ok()) {
-        // get the token symbol strings
-        let symdecs: Vec<TokenSymDec> = load(&deps.storage, SYMDEC_KEY)?;
-        // start iterating from the last close or before given index
-        let len = closed_store.len();
-        let mut pos = before.unwrap_or(len);
-        if pos > len {
-            pos = len;
+/// * `msg` - QueryMsg passed in with the query call
+pub fn query<S: Storage, A: Api, Q: Querier>(deps: &Extern<S, A, Q>, msg: QueryMsg) -> QueryResult {
+    let response = match msg {
+        QueryMsg::ListMyAuctions {
+            address,
+            viewing_key,
+            filter,
+        } => try_list_my(deps, &address, viewing_key, filter),
+        QueryMsg::ListWatched {
+            address,
+            viewing_key,
+        } => try_list_watched(deps, &address, viewing_key),
+        QueryMsg::BidTotals {
+            address,
+            viewing_key,
+        } => try_bid_totals(deps, &address, viewing_key),
+        QueryMsg::ListActiveAuctions { code_id } => try_list_active(deps, code_id),
+        QueryMsg::ListClosedAuctions { before, page_size } => {
+            try_list_closed(deps, before, page_size)
         }
-        let skip = (len - pos) as usize;
-        let quant = page_size.unwrap_or(200) as usize;
-        // grab backwards from the starting point
-        for (i, res) in closed_store.iter().enumerate().rev().skip(skip).take(quant) {
-            if let Ok(info) = res {
-                let may_sell_symdec = symdecs.get(info.sell_symbol as usize);
-                if let Some(sell_symdec) = may_sell_symdec {
-                    let may_bid_symdec = symdecs.get(info.bid_symbol as usize);
-                    if let Some(bid_symdec) = may_bid_symdec {
-                        let pair = format!("{}-{}", sell_symdec.symbol, bid_symdec.symbol);
-                        closed_vec.push(ClosedAuctionInfo {
-                            index: Some(i as u32),
-                            address: deps.api.human_address(&info.address)?,
-                            label: info.label,
-                            pair,
-                            sell_amount: Uint128(info.sell_amount),
-                            sell_decimals: sell_symdec.decimals,
-                            winning_bid: info.winning_bid.map(Uint128),
-                            bid_decimals: info.winning_bid.map(|_a| bid_symdec.decimals),
-                            timestamp: info.timestamp,
-                        });
-                    }
-                }
+        QueryMsg::ListEndingSoon {
+            current_time,
+            within_seconds,
+            page_size,
+        } => try_list_ending_soon(deps, current_time, within_seconds, page_size),
+        QueryMsg::ListAuctionsByTag {
+            tag,
+            page,
+            page_size,
+        } => try_list_by_tag(deps, &tag, page, page_size),
+        QueryMsg::IsKeyValid {
+            address,
+            viewing_key,
+        } => try_validate_key(deps, &address, viewing_key),
+        QueryMsg::AreKeysValid { pairs } => try_validate_keys(deps, pairs),
+        QueryMsg::StorageInfo {} => try_storage_info(deps),
+        QueryMsg::ListSeries { start, limit } => try_list_series(deps, start, limit),
+        QueryMsg::GetAuctions { indices } => try_get_auctions(deps, &indices),
+        QueryMsg::AuctionByLabel { label } => try_auction_by_label(deps, &label),
+        QueryMsg::SettlementRecord { index } => try_settlement_record(deps, index),
+        QueryMsg::RecentPrices {
+            sell_token,
+            bid_token,
+            limit,
+        } => try_recent_prices(deps, &sell_token, &bid_token, limit),
+        QueryMsg::Allowlist {} => try_allowlist_info(deps),
+        QueryMsg::CloseSubscribers {
+            sell_token,
+            bid_token,
+        } => try_list_close_subscribers(deps, &sell_token, &bid_token),
+        QueryMsg::PairCloses {
+            sell_token,
+            bid_token,
+            since,
+            limit,
+        } => try_list_pair_closes(deps, &sell_token, &bid_token, since, limit),
+        QueryMsg::PreviewCreateAuction {
+            seller,
+            current_time,
+            create_auction,
+        } => try_preview_create_auction(deps, &seller, current_time, create_auction),
+        QueryMsg::Config {} => try_query_config(deps),
+        QueryMsg::Status {} => try_query_status(deps),
+        QueryMsg::TreasuryBalances {} => try_treasury_balances(deps),
+        QueryMsg::ReferralEarnings {
+            address,
+            viewing_key,
+        } => try_referral_earnings(deps, &address, viewing_key),
+        QueryMsg::TreasuryRecords {
+            address,
+            viewing_key,
+            asset,
+            before,
+            page_size,
+        } => try_treasury_records(deps, &address, viewing_key, &asset, before, page_size),
+        QueryMsg::AdminAuctionInfo {
+            address,
+            viewing_key,
+            index,
+        } => try_admin_auction_info(deps, &address, viewing_key, index),
+    };
+    pad_query_result(response, padding_block_size(&deps.storage))
+}
+
+/// Returns usize
+///
+/// the block size responses are padded to, falling back to BLOCK_SIZE if the config can not be
+/// read
+///
+/// # Arguments
+///
+/// * `storage` - a reference to the contract's storage
+fn padding_block_size<S: ReadonlyStorage>(storage: &S) -> usize {
+    load_config(storage)
+        .ok()
+        .map_or(BLOCK_SIZE, |config| usize::from(config.block_size))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lists::add_to_ending_index;
+    use cosmwasm_std::testing::{mock_dependencies, mock_env, MockApi, MockQuerier, MockStorage};
+    use cosmwasm_std::{from_binary, WasmMsg};
+    use secret_toolkit::storage::AppendStoreMut;
+
+    fn init_helper() -> (
+        StdResult<InitResponse>,
+        Extern<MockStorage, MockApi, MockQuerier>,
+    ) {
+        let mut deps = mock_dependencies(20, &[]);
+        let env = mock_env("admin", &[]);
+        let init_msg = InitMsg {
+            entropy: "entropy".to_string(),
+            auction_contract: AuctionContractInfo {
+                code_id: 1,
+                code_hash: "auctionhash".to_string(),
+            },
+            block_size: None,
+        };
+        (init(&mut deps, env, init_msg), deps)
+    }
+
+    fn env_at(sender: &str, time: u64) -> Env {
+        let mut env = mock_env(sender, &[]);
+        env.block.time = time;
+        env
+    }
+
+    fn extract_error_msg(error: HandleResult) -> String {
+        match error {
+            Err(StdError::GenericErr { msg, .. }) => msg,
+            _ => panic!("Expected an error"),
+        }
+    }
+
+    fn extract_status_msg(result: &HandleResult) -> Option<String> {
+        let response = result.as_ref().unwrap();
+        match from_binary(response.data.as_ref().unwrap()).unwrap() {
+            HandleAnswer::Status { message, .. } => message,
+            _ => panic!("Unexpected"),
+        }
+    }
+
+    fn auction_info(
+        deps: &Extern<MockStorage, MockApi, MockQuerier>,
+        index: u32,
+        ends_at: u64,
+    ) -> StoreAuctionInfo {
+        StoreAuctionInfo {
+            address: deps
+                .api
+                .canonical_address(&HumanAddr(format!("auction{}", index)))
+                .unwrap(),
+            code_id: 1,
+            label: format!("auction{}", index),
+            sell_token: b"sell".to_vec(),
+            bid_token: b"bid".to_vec(),
+            sell_amount: 10,
+            minimum_bid: 10,
+            ends_at,
+        }
+    }
+
+    // adds an active auction the factory knows the seller and code hash of
+    fn add_auction(
+        deps: &mut Extern<MockStorage, MockApi, MockQuerier>,
+        index: u32,
+        info: &StoreAuctionInfo,
+    ) {
+        let key = index.to_le_bytes();
+        let seller = deps
+            .api
+            .canonical_address(&HumanAddr(format!("seller{}", index)))
+            .unwrap();
+        save(
+            &mut PrefixedStorage::new(PREFIX_ACTIVE_INFO, &mut deps.storage),
+            &key,
+            info,
+        )
+        .unwrap();
+        save(
+            &mut PrefixedStorage::new(PREFIX_AUCTION_SELLERS, &mut deps.storage),
+            &key,
+            &seller,
+        )
+        .unwrap();
+        save(
+            &mut PrefixedStorage::new(PREFIX_CODE_HASHES, &mut deps.storage),
+            &key,
+            &"auctionhash".to_string(),
+        )
+        .unwrap();
+        add_to_ending_index(&mut deps.storage, info.ends_at, index).unwrap();
+        let mut active: HashSet<u32> = load(&deps.storage, ACTIVE_KEY).unwrap();
+        active.insert(index);
+        save(&mut deps.storage, ACTIVE_KEY, &active).unwrap();
+    }
+
+    fn message_contracts(result: &HandleResult) -> Vec<HumanAddr> {
+        result
+            .as_ref()
+            .unwrap()
+            .messages
+            .iter()
+            .map(|m| match m {
+                CosmosMsg::Wasm(WasmMsg::Execute { contract_addr, .. }) => contract_addr.clone(),
+                _ => panic!("Unexpected message"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_prune_closed() {
+        let (init_result, mut deps) = init_helper();
+        assert!(init_result.is_ok());
+        // auctions closed at 100, 200, 300, 400, and 500
+        for index in 0u32..5 {
+            let info = StoreClosedAuctionInfo {
+                address: deps
+                    .api
+                    .canonical_address(&HumanAddr(format!("auction{}", index)))
+                    .unwrap(),
+                code_id: 1,
+                label: format!("auction{}", index),
+                sell_token: b"sell".to_vec(),
+                bid_token: b"bid".to_vec(),
+                sell_amount: 10,
+                winning_bid: None,
+                winners: Vec::new(),
+                timestamp: (index as u64 + 1) * 100,
+            };
+            let mut info_store = PrefixedStorage::new(PREFIX_CLOSED_INFO, &mut deps.storage);
+            save(&mut info_store, &index.to_le_bytes(), &info).unwrap();
+            let mut order_store = PrefixedStorage::new(PREFIX_CLOSED_ORDER, &mut deps.storage);
+            let mut order = AppendStoreMut::<u32, _>::attach_or_create(&mut order_store).unwrap();
+            order.push(&index).unwrap();
+        }
+        let prune_msg = || HandleMsg::PruneClosed {
+            keep_after_timestamp: 350,
+            page_size: Some(2),
+        };
+        let closed_exists = |deps: &Extern<MockStorage, MockApi, MockQuerier>, index: u32| {
+            let info_store = ReadonlyPrefixedStorage::new(PREFIX_CLOSED_INFO, &deps.storage);
+            may_load::<StoreClosedAuctionInfo, _>(&info_store, &index.to_le_bytes())
+                .unwrap()
+                .is_some()
+        };
+
+        // only the admin may prune
+        let handle_result = handle(&mut deps, mock_env("alice", &[]), prune_msg());
+        assert!(extract_error_msg(handle_result).contains("admin command"));
+        assert!(closed_exists(&deps, 0));
+
+        // a full page leaves more to prune
+        let handle_result = handle(&mut deps, mock_env("admin", &[]), prune_msg());
+        assert_eq!(
+            extract_status_msg(&handle_result),
+            Some(
+                "Pruned 2 closed auction records.  More records may be pruned with another \
+                 prune_closed message"
+                    .to_string()
+            )
+        );
+        assert!(!closed_exists(&deps, 0));
+        assert!(!closed_exists(&deps, 1));
+        assert!(closed_exists(&deps, 2));
+
+        // pruning stops at the first auction to keep
+        let handle_result = handle(&mut deps, mock_env("admin", &[]), prune_msg());
+        assert_eq!(
+            extract_status_msg(&handle_result),
+            Some("Pruned 1 closed auction records".to_string())
+        );
+        assert!(!closed_exists(&deps, 2));
+        assert!(closed_exists(&deps, 3));
+        assert!(closed_exists(&deps, 4));
+        let handle_result = handle(&mut deps, mock_env("admin", &[]), prune_msg());
+        assert_eq!(
+            extract_status_msg(&handle_result),
+            Some("Pruned 0 closed auction records".to_string())
+        );
+        assert_eq!(load::<u32, _>(&deps.storage, PRUNED_KEY).unwrap(), 3);
+        assert!(closed_exists(&deps, 3));
+    }
+
+    #[test]
+    fn test_create_series_checks() {
+        let (init_result, mut deps) = init_helper();
+        assert!(init_result.is_ok());
+        let token = |address: &str| ContractInfo {
+            code_hash: "tokenhash".to_string(),
+            address: HumanAddr(address.to_string()),
+            token_id: None,
+        };
+        let series_msg =
+            |bid: &str, sell_amount: u128, interval: u64, rounds: u32| HandleMsg::CreateSeries {
+                label: "series".to_string(),
+                sell_contract: token("sell"),
+                bid_contract: token(bid),
+                sell_amount: Uint128(sell_amount),
+                minimum_bid: Uint128(10),
+                interval,
+                rounds,
+                description: None,
+            };
+
+        // a series needs rounds
+        let handle_result = handle(
+            &mut deps,
+            mock_env("alice", &[]),
+            series_msg("bid", 10, 100, 0),
+        );
+        assert!(extract_error_msg(handle_result).contains("A series must have at least one round"));
+
+        // rounds need to last
+        let handle_result = handle(
+            &mut deps,
+            mock_env("alice", &[]),
+            series_msg("bid", 10, 0, 3),
+        );
+        assert!(extract_error_msg(handle_result)
+            .contains("The interval between rounds must be greater than 0"));
+
+        // each round is checked like a new auction
+        let handle_result = handle(
+            &mut deps,
+            mock_env("alice", &[]),
+            series_msg("sell", 10, 100, 3),
+        );
+        assert!(extract_error_msg(handle_result)
+            .contains("Sell contract and bid contract must be different"));
+        let handle_result = handle(
+            &mut deps,
+            mock_env("alice", &[]),
+            series_msg("bid", 0, 100, 3),
+        );
+        assert!(extract_error_msg(handle_result).contains("Sell amount must be greater than 0"));
+
+        // the escrow of every round must fit
+        let handle_result = handle(
+            &mut deps,
+            mock_env("alice", &[]),
+            series_msg("bid", u128::MAX, 100, 2),
+        );
+        assert!(extract_error_msg(handle_result)
+            .contains("The total amount sold by the series is too large"));
+        assert!(may_load::<u32, _>(&deps.storage, SERIES_COUNT_KEY)
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn test_match_auctions() {
+        let (init_result, mut deps) = init_helper();
+        assert!(init_result.is_ok());
+        // sells 10 SELL for at least 8 BID
+        let mut info = auction_info(&deps, 0, 1000);
+        info.minimum_bid = 8;
+        add_auction(&mut deps, 0, &info);
+        // sells 9 BID for at least 10 SELL
+        let mut info = auction_info(&deps, 1, 1000);
+        info.sell_token = b"bid".to_vec();
+        info.bid_token = b"sell".to_vec();
+        info.sell_amount = 9;
+        add_auction(&mut deps, 1, &info);
+        // sells SELL for BID like auction 0
+        let info = auction_info(&deps, 2, 1000);
+        add_auction(&mut deps, 2, &info);
+        // sells 7 BID, which is below auction 0's minimum bid
+        let mut info = auction_info(&deps, 3, 1000);
+        info.sell_token = b"bid".to_vec();
+        info.bid_token = b"sell".to_vec();
+        info.sell_amount = 7;
+        add_auction(&mut deps, 3, &info);
+        // would match auction 0, but bundles other tokens with its lot
+        let mut info = auction_info(&deps, 4, 1000);
+        info.sell_token = b"bid".to_vec();
+        info.bid_token = b"sell".to_vec();
+        add_auction(&mut deps, 4, &info);
+        save(
+            &mut PrefixedStorage::new(PREFIX_BUNDLED, &mut deps.storage),
+            &4u32.to_le_bytes(),
+            &1u8,
+        )
+        .unwrap();
+        let match_msg = |a: u32, b: u32| HandleMsg::MatchAuctions { a, b };
+
+        // only the admin may match auctions
+        let handle_result = handle(&mut deps, mock_env("alice", &[]), match_msg(0, 1));
+        assert!(extract_error_msg(handle_result).contains("admin command"));
+
+        let handle_result = handle(&mut deps, mock_env("admin", &[]), match_msg(0, 0));
+        assert!(
+            extract_error_msg(handle_result).contains("An auction can not be matched with itself")
+        );
+        let handle_result = handle(&mut deps, mock_env("admin", &[]), match_msg(0, 5));
+        assert!(extract_error_msg(handle_result).contains("Auction 5 is not active"));
+        let handle_result = handle(&mut deps, mock_env("admin", &[]), match_msg(0, 2));
+        assert!(extract_error_msg(handle_result)
+            .contains("Auctions 0 and 2 do not trade the same tokens in opposite directions"));
+        let handle_result = handle(&mut deps, mock_env("admin", &[]), match_msg(0, 3));
+        assert!(extract_error_msg(handle_result)
+            .contains("The prices of auctions 0 and 3 do not cross"));
+        let handle_result = handle(&mut deps, mock_env("admin", &[]), match_msg(0, 4));
+        assert!(extract_error_msg(handle_result)
+            .contains("Auction 4 sells a bundled lot and can not be matched"));
+
+        // an auction the factory does not know the code hash of can not be called
+        remove(
+            &mut PrefixedStorage::new(PREFIX_CODE_HASHES, &mut deps.storage),
+            &1u32.to_le_bytes(),
+        );
+        let handle_result = handle(&mut deps, mock_env("admin", &[]), match_msg(0, 1));
+        assert!(extract_error_msg(handle_result).contains("Auction 1 can not be matched"));
+        save(
+            &mut PrefixedStorage::new(PREFIX_CODE_HASHES, &mut deps.storage),
+            &1u32.to_le_bytes(),
+            &"auctionhash".to_string(),
+        )
+        .unwrap();
+
+        // both auctions are told to swap
+        let handle_result = handle(&mut deps, mock_env("admin", &[]), match_msg(1, 0));
+        assert_eq!(
+            message_contracts(&handle_result),
+            vec![
+                HumanAddr("auction1".to_string()),
+                HumanAddr("auction0".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_close_expired() {
+        let (init_result, mut deps) = init_helper();
+        assert!(init_result.is_ok());
+        for (index, ends_at) in [(0u32, 2000u64), (1, 1000), (2, 5000)].iter() {
+            let info = auction_info(&deps, *index, *ends_at);
+            add_auction(&mut deps, *index, &info);
+        }
+        // an auction created before the factory recorded code hashes is skipped
+        let info = auction_info(&deps, 3, 1500);
+        add_auction(&mut deps, 3, &info);
+        remove(
+            &mut PrefixedStorage::new(PREFIX_CODE_HASHES, &mut deps.storage),
+            &3u32.to_le_bytes(),
+        );
+        let close_expired = |deps: &mut Extern<MockStorage, MockApi, MockQuerier>,
+                             time: u64,
+                             limit: u32| {
+            let handle_result = handle(
+                deps,
+                env_at("alice", time),
+                HandleMsg::CloseExpired { limit },
+            );
+            let auctions = match from_binary(handle_result.as_ref().unwrap().data.as_ref().unwrap())
+                .unwrap()
+            {
+                HandleAnswer::CloseExpired { auctions, .. } => auctions,
+                _ => panic!("Unexpected"),
+            };
+            (auctions, message_contracts(&handle_result))
+        };
+
+        // the number of auctions finalized at once is capped
+        let handle_result = handle(
+            &mut deps,
+            env_at("alice", 2500),
+            HandleMsg::CloseExpired {
+                limit: MAX_CLOSE_EXPIRED + 1,
+            },
+        );
+        assert!(extract_error_msg(handle_result)
+            .contains("Can not finalize more than 20 auctions at a time"));
+
+        // the earliest expired auction is finalized first
+        let (auctions, contracts) = close_expired(&mut deps, 2500, 1);
+        assert_eq!(auctions, vec![1]);
+        assert_eq!(contracts, vec![HumanAddr("auction1".to_string())]);
+
+        // an auction that did not close is skipped until its retry time
+        let (auctions, _) = close_expired(&mut deps, 2500, 20);
+        assert_eq!(auctions, vec![0]);
+        let (auctions, _) = close_expired(&mut deps, 2500, 20);
+        assert!(auctions.is_empty());
+
+        // once the retry time passes, they are finalized again along with newly expired auctions
+        let (auctions, contracts) = close_expired(&mut deps, 2500 + CLOSE_RETRY_SECONDS, 20);
+        assert_eq!(auctions, vec![1, 0, 2]);
+        assert_eq!(
+            contracts,
+            vec![
+                HumanAddr("auction1".to_string()),
+                HumanAddr("auction0".to_string()),
+                HumanAddr("auction2".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_deprecation_pages() {
+        let (init_result, mut deps) = init_helper();
+        assert!(init_result.is_ok());
+        // auctions 0 through 2 run version 1, and auction 3 already runs version 2
+        for index in 0u32..4 {
+            let mut info = auction_info(&deps, index, 1000);
+            if index == 3 {
+                info.code_id = 2;
             }
+            add_auction(&mut deps, index, &info);
         }
+        // auction 2 was created before the factory recorded code hashes, so it can not be warned
+        remove(
+            &mut PrefixedStorage::new(PREFIX_CODE_HASHES, &mut deps.storage),
+            &2u32.to_le_bytes(),
+        );
+        let continue_msg = || HandleMsg::ContinueDeprecation { page_size: Some(2) };
+
+        // there is nothing to continue yet
+        let handle_result = handle(&mut deps, mock_env("admin", &[]), continue_msg());
+        assert!(extract_error_msg(handle_result).contains("There is no deprecation in progress"));
+
+        // only the admin may add a version
+        let new_msg = || HandleMsg::NewAuctionContract {
+            auction_contract: AuctionContractInfo {
+                code_id: 2,
+                code_hash: "newhash".to_string(),
+            },
+            deprecate_old: false,
+            deprecation_notice: Some("Please use version 2".to_string()),
+            page_size: Some(2),
+        };
+        let handle_result = handle(&mut deps, mock_env("alice", &[]), new_msg());
+        assert!(extract_error_msg(handle_result).contains("admin command"));
+
+        // the first page is checked by the new version message
+        let handle_result = handle(&mut deps, mock_env("admin", &[]), new_msg());
+        assert_eq!(
+            extract_status_msg(&handle_result),
+            Some("2 active auctions remain to be checked for deprecation".to_string())
+        );
+        assert_eq!(
+            message_contracts(&handle_result),
+            vec![
+                HumanAddr("auction0".to_string()),
+                HumanAddr("auction1".to_string())
+            ]
+        );
+        let config = load_config(&deps.storage).unwrap();
+        assert_eq!(config.version.code_id, 2);
+        assert_eq!(config.deprecated, vec![1]);
+
+        // auctions of the current version and ones without a code hash are not warned
+        let handle_result = handle(&mut deps, mock_env("admin", &[]), continue_msg());
+        assert_eq!(
+            extract_status_msg(&handle_result),
+            Some("Deprecation is complete".to_string())
+        );
+        assert!(message_contracts(&handle_result).is_empty());
+        assert!(
+            may_load::<PendingDeprecation, _>(&deps.storage, DEPRECATION_KEY)
+                .unwrap()
+                .is_none()
+        );
+        let handle_result = handle(&mut deps, mock_env("admin", &[]), continue_msg());
+        assert!(extract_error_msg(handle_result).contains("There is no deprecation in progress"));
     }
-    let closed = if closed_vec.is_empty() {
-        None
-    } else {
-        Some(closed_vec)
-    };
-    to_binary(&QueryAnswer::ListClosedAuctions { closed })
 }
@@ -1,9 +1,15 @@
+mod allowlist;
+mod auctions;
 pub mod contract;
+mod lists;
 pub mod msg;
-mod rand;
+mod queries;
 pub mod state;
+mod subscribers;
+mod treasury;
 mod utils;
 mod viewing_key;
+mod viewing_keys;
 
 #[cfg(target_arch = "wasm32")]
 mod wasm {
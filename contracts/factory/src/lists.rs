@@ -0,0 +1,652 @@
+use cosmwasm_std::{Api, Binary, CanonicalAddr, ReadonlyStorage, StdResult, Storage, Uint128};
+
+use cosmwasm_storage::{PrefixedStorage, ReadonlyPrefixedStorage};
+
+use std::collections::{BTreeSet, HashSet};
+
+use secret_toolkit::storage::{AppendStore, AppendStoreMut};
+
+use auction_common::math::split;
+
+use crate::auctions::{load_config, load_pair};
+use crate::contract::{
+    ACTIVE_KEY, ENDING_BUCKETS_KEY, ENDING_BUCKET_SECONDS, PREFIX_ACTIVE_INFO, PREFIX_AUCTION_TAGS,
+    PREFIX_BIDDERS, PREFIX_BID_AMOUNTS, PREFIX_CLOSED_INFO, PREFIX_CLOSED_NICKNAMES, PREFIX_ENDING,
+    PREFIX_NICKNAMES, PREFIX_SELLERS_ACTIVE, PREFIX_SELLERS_CLOSED, PREFIX_TAGS,
+    PREFIX_TOKEN_ORIGINS, PREFIX_WATCHERS, PREFIX_WINNERS,
+};
+use crate::msg::{AuctionInfo, ClosedAuctionInfo, StoreAuctionInfo, StoreClosedAuctionInfo};
+use crate::state::{may_load, save, Config};
+
+/// the lists of auctions kept for each address
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum AddressList {
+    /// active auctions the address is selling
+    SellerActive,
+    /// active auctions the address has bid on
+    Bidder,
+    /// closed auctions the address sold
+    SellerClosed,
+    /// closed auctions the address won
+    Winner,
+    /// active auctions the address is watching
+    Watcher,
+}
+
+impl AddressList {
+    /// Returns &[u8] storage prefix of the list
+    pub fn prefix(self) -> &'static [u8] {
+        match self {
+            AddressList::SellerActive => PREFIX_SELLERS_ACTIVE,
+            AddressList::Bidder => PREFIX_BIDDERS,
+            AddressList::SellerClosed => PREFIX_SELLERS_CLOSED,
+            AddressList::Winner => PREFIX_WINNERS,
+            AddressList::Watcher => PREFIX_WATCHERS,
+        }
+    }
+}
+
+/// Returns StdResult<()>
+///
+/// remove any pruned auctions from a seller's or winner's list of closed auctions
+///
+/// # Arguments
+///
+/// * `storage` - mutable reference to contract's storage
+/// * `list` - either the seller's or winner's closed auction list
+/// * `person` - a reference to the canonical address of the person the list belongs to
+/// * `pruned` - number of closed auction records that have been pruned
+pub fn compact_closed_list<S: Storage>(
+    storage: &mut S,
+    list: AddressList,
+    person: &CanonicalAddr,
+    pruned: u32,
+) -> StdResult<()> {
+    if pruned == 0 {
+        return Ok(());
+    }
+    // lists are in chronological order, so any pruned auctions are at the front
+    let mut first_kept = 0;
+    let mut len = 0;
+    let read_store =
+        ReadonlyPrefixedStorage::multilevel(&[list.prefix(), person.as_slice()], storage);
+    if let Some(closed) = AppendStore::<u32, _>::attach(&read_store).and_then(|r| r.ok()) {
+        let info_store = ReadonlyPrefixedStorage::new(PREFIX_CLOSED_INFO, storage);
+        len = closed.len();
+        first_kept = len;
+        for i in 0..len {
+            if info_store.get(&closed.get_at(i)?.to_le_bytes()).is_some() {
+                first_kept = i;
+                break;
+            }
+        }
+    }
+    if first_kept > 0 {
+        let mut store = PrefixedStorage::multilevel(&[list.prefix(), person.as_slice()], storage);
+        let mut closed = AppendStoreMut::<u32, _>::attach_or_create(&mut store)?;
+        // shift the remaining auctions to the front and drop the leftover tail
+        for i in 0..(len - first_kept) {
+            let index = closed.get_at(i + first_kept)?;
+            closed.set_at(i, &index)?;
+        }
+        for _ in 0..first_kept {
+            closed.pop()?;
+        }
+    }
+    Ok(())
+}
+
+/// Returns StdResult<()>
+///
+/// add a closed auction to a seller's or winner's list of closed auctions, after removing any
+/// pruned auctions from the list
+///
+/// # Arguments
+///
+/// * `storage` - mutable reference to contract's storage
+/// * `list` - either the seller's or winner's closed auction list
+/// * `person` - a reference to the canonical address of the person the list belongs to
+/// * `index` - index of the closed auction
+/// * `pruned` - number of closed auction records that have been pruned
+pub fn add_to_persons_closed<S: Storage>(
+    storage: &mut S,
+    list: AddressList,
+    person: &CanonicalAddr,
+    index: u32,
+    pruned: u32,
+) -> StdResult<()> {
+    compact_closed_list(storage, list, person, pruned)?;
+    let mut store = PrefixedStorage::multilevel(&[list.prefix(), person.as_slice()], storage);
+    let mut closed = AppendStoreMut::attach_or_create(&mut store)?;
+    closed.push(&index)
+}
+
+/// Returns StdResult<()>
+///
+/// add an active auction to the bucket of the closing time index that holds its closing time
+///
+/// # Arguments
+///
+/// * `storage` - mutable reference to contract's storage
+/// * `ends_at` - closing time of the auction
+/// * `index` - index of the auction
+pub fn add_to_ending_index<S: Storage>(storage: &mut S, ends_at: u64, index: u32) -> StdResult<()> {
+    let bucket = ends_at / ENDING_BUCKET_SECONDS;
+    let mut buckets: BTreeSet<u64> = may_load(storage, ENDING_BUCKETS_KEY)?.unwrap_or_default();
+    if buckets.insert(bucket) {
+        save(storage, ENDING_BUCKETS_KEY, &buckets)?;
+    }
+    let mut store = PrefixedStorage::new(PREFIX_ENDING, storage);
+    let mut ending: HashSet<u32> = may_load(&store, &bucket.to_le_bytes())?.unwrap_or_default();
+    ending.insert(index);
+    save(&mut store, &bucket.to_le_bytes(), &ending)
+}
+
+/// Returns StdResult<()>
+///
+/// remove an auction from the bucket of the closing time index that holds its closing time
+///
+/// # Arguments
+///
+/// * `storage` - mutable reference to contract's storage
+/// * `ends_at` - closing time of the auction
+/// * `index` - index of the auction
+pub fn remove_from_ending_index<S: Storage>(
+    storage: &mut S,
+    ends_at: u64,
+    index: u32,
+) -> StdResult<()> {
+    let bucket = ends_at / ENDING_BUCKET_SECONDS;
+    let mut store = PrefixedStorage::new(PREFIX_ENDING, storage);
+    let may_ending: Option<HashSet<u32>> = may_load(&store, &bucket.to_le_bytes())?;
+    if let Some(mut ending) = may_ending {
+        ending.remove(&index);
+        if ending.is_empty() {
+            store.remove(&bucket.to_le_bytes());
+            let mut buckets: BTreeSet<u64> =
+                may_load(storage, ENDING_BUCKETS_KEY)?.unwrap_or_default();
+            buckets.remove(&bucket);
+            save(storage, ENDING_BUCKETS_KEY, &buckets)?;
+        } else {
+            save(&mut store, &bucket.to_le_bytes(), &ending)?;
+        }
+    }
+    Ok(())
+}
+
+/// Returns StdResult<()>
+///
+/// list an active auction under each of its tags
+///
+/// # Arguments
+///
+/// * `storage` - mutable reference to contract's storage
+/// * `tags` - the auction's tags
+/// * `index` - index of the auction
+pub fn add_to_tag_index<S: Storage>(storage: &mut S, tags: &[String], index: u32) -> StdResult<()> {
+    if tags.is_empty() {
+        return Ok(());
+    }
+    let mut tag_store = PrefixedStorage::new(PREFIX_TAGS, storage);
+    for tag in tags.iter() {
+        let mut tagged: BTreeSet<u32> = may_load(&tag_store, tag.as_bytes())?.unwrap_or_default();
+        tagged.insert(index);
+        save(&mut tag_store, tag.as_bytes(), &tagged)?;
+    }
+    // remember the tags so the auction can be unlisted when it closes
+    let mut auction_tag_store = PrefixedStorage::new(PREFIX_AUCTION_TAGS, storage);
+    save(&mut auction_tag_store, &index.to_le_bytes(), &tags.to_vec())
+}
+
+/// Returns StdResult<()>
+///
+/// remove an auction from the lists of all its tags
+///
+/// # Arguments
+///
+/// * `storage` - mutable reference to contract's storage
+/// * `index` - index of the auction
+pub fn remove_from_tag_index<S: Storage>(storage: &mut S, index: u32) -> StdResult<()> {
+    let mut auction_tag_store = PrefixedStorage::new(PREFIX_AUCTION_TAGS, storage);
+    let may_tags: Option<Vec<String>> = may_load(&auction_tag_store, &index.to_le_bytes())?;
+    if let Some(tags) = may_tags {
+        auction_tag_store.remove(&index.to_le_bytes());
+        let mut tag_store = PrefixedStorage::new(PREFIX_TAGS, storage);
+        for tag in tags.iter() {
+            let may_tagged: Option<BTreeSet<u32>> = may_load(&tag_store, tag.as_bytes())?;
+            if let Some(mut tagged) = may_tagged {
+                tagged.remove(&index);
+                if tagged.is_empty() {
+                    tag_store.remove(tag.as_bytes());
+                } else {
+                    save(&mut tag_store, tag.as_bytes(), &tagged)?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Returns StdResult<()>
+///
+/// remove an auction from a seller's or bidder's list of active auctions
+///
+/// # Arguments
+///
+/// * `storage` - mutable reference to contract's storage
+/// * `list` - either the seller's or bidder's active auction list
+/// * `person` - a reference to the canonical address of the person the list belongs to
+/// * `index` - index of the auction to remove
+pub fn remove_from_persons_active<S: Storage>(
+    storage: &mut S,
+    list: AddressList,
+    person: &CanonicalAddr,
+    index: u32,
+) -> StdResult<()> {
+    let mut store = PrefixedStorage::new(list.prefix(), storage);
+    let load_active: Option<HashSet<u32>> = may_load(&store, person.as_slice())?;
+    if let Some(mut active) = load_active {
+        active.remove(&index);
+        save(&mut store, person.as_slice(), &active)?;
+    }
+    Ok(())
+}
+
+/// Returns StdResult<(HashSet<u32>, bool)> which is the address' updated active list
+/// and a bool that is true if the list has been changed from what was in storage
+///
+/// remove any closed auctions from the list
+///
+/// # Arguments
+///
+/// * `storage` - a reference to bidder's active list storage subspace
+/// * `address` - a reference to the canonical address of the person the list belongs to
+/// * `active` - a mutable reference to the HashSet list of active auctions
+pub fn filter_only_active<S: ReadonlyStorage>(
+    storage: &S,
+    address: &CanonicalAddr,
+    active: &mut HashSet<u32>,
+) -> StdResult<(HashSet<u32>, bool)> {
+    // get person's current list
+    let load_auctions: Option<HashSet<u32>> = may_load(storage, address.as_slice())?;
+
+    // if there are active auctions in the list
+    if let Some(my_auctions) = load_auctions {
+        let start_len = my_auctions.len();
+        // only keep the intersection of the person's list and the active auctions list
+        let my_active: HashSet<u32> = my_auctions.iter().filter_map(|v| active.take(v)).collect();
+        let updated = start_len != my_active.len();
+        return Ok((my_active, updated));
+        // if not just return an empty list
+    }
+    Ok((HashSet::new(), false))
+}
+
+/// Returns StdResult<Option<Vec<AuctionInfo>>>
+///
+/// provide the appropriate list of active auctions
+///
+/// # Arguments
+///
+/// * `api` - reference to the Api used to convert canonical and human addresses
+/// * `storage` - a reference to the contract's storage
+/// * `list` - optional address list to load from.  If not specified, the factory's active list
+///            is read
+/// * `key` - storage key to read
+pub fn display_active_list<S: ReadonlyStorage, A: Api>(
+    api: &A,
+    storage: &S,
+    list: Option<AddressList>,
+    key: &[u8],
+) -> StdResult<Option<Vec<AuctionInfo>>> {
+    let load_list: Option<HashSet<u32>> = if let Some(person_list) = list {
+        // reading a person's list
+        let read = &ReadonlyPrefixedStorage::new(person_list.prefix(), storage);
+        // if reading a bidder's list or watchlist
+        if person_list == AddressList::Bidder || person_list == AddressList::Watcher {
+            // read the factory's active list
+            let load_active: Option<HashSet<u32>> = may_load(storage, ACTIVE_KEY)?;
+            if let Some(mut active) = load_active {
+                let canonical = CanonicalAddr(Binary(key.to_vec()));
+                // remove any auctions that closed from the list
+                let (my_active, _) = filter_only_active(read, &canonical, &mut active)?;
+                Some(my_active)
+            } else {
+                None
+            }
+        // read a seller's list
+        } else {
+            may_load(read, key)?
+        }
+    // read the factory's active list
+    } else {
+        may_load(storage, key)?
+    };
+    // only show nicknames to the seller
+    let show_nicknames = list == Some(AddressList::SellerActive);
+    // and only show bid amounts to the bidder
+    let show_bids = list == Some(AddressList::Bidder);
+    // turn list of active auctions to a vec of displayable auction infos
+    let mut actives = match load_list {
+        Some(list) => {
+            let mut display_list = Vec::new();
+            let read_info = &ReadonlyPrefixedStorage::new(PREFIX_ACTIVE_INFO, storage);
+            let read_nick = &ReadonlyPrefixedStorage::new(PREFIX_NICKNAMES, storage);
+            let read_origin = &ReadonlyPrefixedStorage::new(PREFIX_TOKEN_ORIGINS, storage);
+            let read_bids =
+                &ReadonlyPrefixedStorage::multilevel(&[PREFIX_BID_AMOUNTS, key], storage);
+            let config: Config = load_config(storage)?;
+            for index in list.iter() {
+                // get this auction's info
+                let load_info: Option<StoreAuctionInfo> =
+                    may_load(read_info, &index.to_le_bytes())?;
+                if let Some(info) = load_info {
+                    if let Some((sell_symdec, bid_symdec)) =
+                        load_pair(storage, &info.sell_token, &info.bid_token)?
+                    {
+                        let pair = format!("{}-{}", sell_symdec.symbol, bid_symdec.symbol);
+                        let nickname = if show_nicknames {
+                            may_load(read_nick, &index.to_le_bytes())?
+                        } else {
+                            None
+                        };
+                        let my_bid = if show_bids {
+                            may_load::<u128, _>(read_bids, &index.to_le_bytes())?.map(Uint128)
+                        } else {
+                            None
+                        };
+                        display_list.push(AuctionInfo {
+                            index: *index,
+                            address: api.human_address(&info.address)?,
+                            label: info.label,
+                            pair,
+                            sell_amount: Uint128(info.sell_amount),
+                            sell_decimals: sell_symdec.decimals,
+                            minimum_bid: Uint128(info.minimum_bid),
+                            bid_decimals: bid_symdec.decimals,
+                            ends_at: info.ends_at,
+                            nickname,
+                            my_bid,
+                            sell_origin: may_load(read_origin, &info.sell_token)?,
+                            bid_origin: may_load(read_origin, &info.bid_token)?,
+                            code_id: info.code_id,
+                            deprecated: config.deprecated.contains(&info.code_id),
+                        });
+                    }
+                }
+            }
+            display_list
+        }
+        None => Vec::new(),
+    };
+    if actives.is_empty() {
+        return Ok(None);
+    }
+    // sort it by pair
+    actives.sort_by(|a, b| a.pair.cmp(&b.pair));
+    Ok(Some(actives))
+}
+
+/// Returns StdResult<Option<Vec<ClosedAuctionInfo>>>
+///
+/// provide the appropriate list of closed auctions
+///
+/// # Arguments
+///
+/// * `api` - reference to the Api used to convert canonical and human addresses
+/// * `storage` - a reference to the contract's storage
+/// * `list` - either the seller's or winner's closed auction list
+/// * `key` - storage key to read
+pub fn display_addr_closed<S: ReadonlyStorage, A: Api>(
+    api: &A,
+    storage: &S,
+    list: AddressList,
+    key: &[u8],
+) -> StdResult<Option<Vec<ClosedAuctionInfo>>> {
+    let list_store = ReadonlyPrefixedStorage::multilevel(&[list.prefix(), key], storage);
+    let may_read_list = AppendStore::<u32, _>::attach(&list_store);
+    let mut closed_vec = Vec::new();
+    // only show nicknames to the seller
+    let show_nicknames = list == AddressList::SellerClosed;
+    // and only show what was paid and won to the winner
+    let show_winnings = list == AddressList::Winner;
+    if let Some(closed_list) = may_read_list.and_then(|r| r.ok()) {
+        let info_store = ReadonlyPrefixedStorage::new(PREFIX_CLOSED_INFO, storage);
+        let nick_store = ReadonlyPrefixedStorage::new(PREFIX_CLOSED_NICKNAMES, storage);
+        // grab backwards from the starting point
+        for index_res in closed_list.iter().rev() {
+            if let Ok(index) = index_res {
+                // get this auction's info
+                let load_info: Option<StoreClosedAuctionInfo> =
+                    may_load(&info_store, &index.to_le_bytes())?;
+                if let Some(info) = load_info {
+                    if let Some((sell_symdec, bid_symdec)) =
+                        load_pair(storage, &info.sell_token, &info.bid_token)?
+                    {
+                        let pair = format!("{}-{}", sell_symdec.symbol, bid_symdec.symbol);
+                        let nickname = if show_nicknames {
+                            may_load(&nick_store, &index.to_le_bytes())?
+                        } else {
+                            None
+                        };
+                        // every tied winner paid the winning bid for an equal share
+                        let is_winner =
+                            show_winnings && info.winners.iter().any(|w| w.as_slice() == key);
+                        let (amount_paid, amount_won) = if is_winner {
+                            (
+                                info.winning_bid.map(Uint128),
+                                Some(Uint128(split(info.sell_amount, info.winners.len())?.0)),
+                            )
+                        } else {
+                            (None, None)
+                        };
+                        let winning_unit_price = info.winning_unit_price(sell_symdec.decimals);
+                        closed_vec.push(ClosedAuctionInfo {
+                            index: Some(index),
+                            address: api.human_address(&info.address)?,
+                            label: info.label,
+                            pair,
+                            sell_amount: Uint128(info.sell_amount),
+                            sell_decimals: sell_symdec.decimals,
+                            winning_bid: info.winning_bid.map(Uint128),
+                            winning_unit_price,
+                            bid_decimals: info.winning_bid.map(|_a| bid_symdec.decimals),
+                            timestamp: info.timestamp,
+                            code_id: info.code_id,
+                            nickname,
+                            amount_paid,
+                            amount_won,
+                        });
+                    }
+                }
+            }
+        }
+    }
+    if closed_vec.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(closed_vec))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::contract::PREFIX_SYMDECS;
+    use crate::state::{load, TokenSymDec};
+    use cosmwasm_std::testing::{MockApi, MockStorage};
+    use cosmwasm_std::HumanAddr;
+
+    fn addr(name: &str) -> CanonicalAddr {
+        CanonicalAddr(Binary(name.as_bytes().to_vec()))
+    }
+
+    #[test]
+    fn test_ending_index() {
+        let mut storage = MockStorage::new();
+        add_to_ending_index(&mut storage, 100, 1).unwrap();
+        add_to_ending_index(&mut storage, 200, 2).unwrap();
+        add_to_ending_index(&mut storage, ENDING_BUCKET_SECONDS + 5, 3).unwrap();
+        let buckets: BTreeSet<u64> = load(&storage, ENDING_BUCKETS_KEY).unwrap();
+        assert_eq!(buckets.into_iter().collect::<Vec<u64>>(), vec![0, 1]);
+        let store = ReadonlyPrefixedStorage::new(PREFIX_ENDING, &storage);
+        let ending: HashSet<u32> = load(&store, &0u64.to_le_bytes()).unwrap();
+        assert_eq!(ending.len(), 2);
+
+        // emptying a bucket removes it from the index
+        remove_from_ending_index(&mut storage, ENDING_BUCKET_SECONDS + 5, 3).unwrap();
+        remove_from_ending_index(&mut storage, 100, 1).unwrap();
+        let buckets: BTreeSet<u64> = load(&storage, ENDING_BUCKETS_KEY).unwrap();
+        assert_eq!(buckets.into_iter().collect::<Vec<u64>>(), vec![0]);
+        let store = ReadonlyPrefixedStorage::new(PREFIX_ENDING, &storage);
+        let ending: HashSet<u32> = load(&store, &0u64.to_le_bytes()).unwrap();
+        assert!(ending.contains(&2));
+        assert!(!ending.contains(&1));
+        let removed: Option<HashSet<u32>> = may_load(&store, &1u64.to_le_bytes()).unwrap();
+        assert!(removed.is_none());
+    }
+
+    #[test]
+    fn test_tag_index() {
+        let mut storage = MockStorage::new();
+        let tags = vec!["otc".to_string(), "nft".to_string()];
+        add_to_tag_index(&mut storage, &tags, 2).unwrap();
+        add_to_tag_index(&mut storage, &tags[..1], 1).unwrap();
+        let store = ReadonlyPrefixedStorage::new(PREFIX_TAGS, &storage);
+        let otc: BTreeSet<u32> = load(&store, b"otc").unwrap();
+        assert_eq!(otc.into_iter().collect::<Vec<u32>>(), vec![1, 2]);
+
+        // emptying a tag removes it
+        remove_from_tag_index(&mut storage, 2).unwrap();
+        let store = ReadonlyPrefixedStorage::new(PREFIX_TAGS, &storage);
+        let otc: BTreeSet<u32> = load(&store, b"otc").unwrap();
+        assert_eq!(otc.into_iter().collect::<Vec<u32>>(), vec![1]);
+        let nft: Option<BTreeSet<u32>> = may_load(&store, b"nft").unwrap();
+        assert!(nft.is_none());
+        let store = ReadonlyPrefixedStorage::new(PREFIX_AUCTION_TAGS, &storage);
+        let removed: Option<Vec<String>> = may_load(&store, &2u32.to_le_bytes()).unwrap();
+        assert!(removed.is_none());
+    }
+
+    #[test]
+    fn test_remove_from_persons_active() {
+        let mut storage = MockStorage::new();
+        let alice = addr("alice");
+        let mut store = PrefixedStorage::new(AddressList::SellerActive.prefix(), &mut storage);
+        let active: HashSet<u32> = vec![1, 2].into_iter().collect();
+        save(&mut store, alice.as_slice(), &active).unwrap();
+
+        remove_from_persons_active(&mut storage, AddressList::SellerActive, &alice, 1).unwrap();
+        // removing from a different list leaves the seller's list alone
+        remove_from_persons_active(&mut storage, AddressList::Bidder, &alice, 2).unwrap();
+        let store = ReadonlyPrefixedStorage::new(AddressList::SellerActive.prefix(), &storage);
+        let active: HashSet<u32> = load(&store, alice.as_slice()).unwrap();
+        assert_eq!(active.into_iter().collect::<Vec<u32>>(), vec![2]);
+    }
+
+    #[test]
+    fn test_filter_only_active() {
+        let mut storage = MockStorage::new();
+        let alice = addr("alice");
+        let mine: HashSet<u32> = vec![1, 2, 3].into_iter().collect();
+        save(&mut storage, alice.as_slice(), &mine).unwrap();
+        let mut active: HashSet<u32> = vec![2, 3, 4].into_iter().collect();
+
+        let (my_active, updated) = filter_only_active(&storage, &alice, &mut active).unwrap();
+        assert!(updated);
+        let mut my_active: Vec<u32> = my_active.into_iter().collect();
+        my_active.sort_unstable();
+        assert_eq!(my_active, vec![2, 3]);
+
+        // an address without a list gets an empty one
+        let (empty, updated) = filter_only_active(&storage, &addr("bob"), &mut active).unwrap();
+        assert!(empty.is_empty());
+        assert!(!updated);
+    }
+
+    #[test]
+    fn test_compact_closed_list() {
+        let mut storage = MockStorage::new();
+        let alice = addr("alice");
+        let mut store = PrefixedStorage::multilevel(
+            &[AddressList::Winner.prefix(), alice.as_slice()],
+            &mut storage,
+        );
+        let mut list = AppendStoreMut::<u32, _>::attach_or_create(&mut store).unwrap();
+        for i in 10..15u32 {
+            list.push(&i).unwrap();
+        }
+        // the three earliest auctions have been pruned, so only the last two have records
+        let mut info_store = PrefixedStorage::new(PREFIX_CLOSED_INFO, &mut storage);
+        for i in 13..15u32 {
+            save(&mut info_store, &i.to_le_bytes(), &i).unwrap();
+        }
+
+        compact_closed_list(&mut storage, AddressList::Winner, &alice, 3).unwrap();
+        let store = ReadonlyPrefixedStorage::multilevel(
+            &[AddressList::Winner.prefix(), alice.as_slice()],
+            &storage,
+        );
+        let list = AppendStore::<u32, _>::attach(&store).unwrap().unwrap();
+        let kept: StdResult<Vec<u32>> = list.iter().collect();
+        assert_eq!(kept.unwrap(), vec![13, 14]);
+    }
+
+    #[test]
+    fn test_display_winnings() {
+        let mut storage = MockStorage::new();
+        let api = MockApi::new(20);
+        let canonical = |name: &str| api.canonical_address(&HumanAddr(name.to_string())).unwrap();
+        let (alice, bob, carol) = (canonical("alice"), canonical("bob"), canonical("carol"));
+        let mut symdec_store = PrefixedStorage::new(PREFIX_SYMDECS, &mut storage);
+        let sell = TokenSymDec {
+            symbol: "SELL".to_string(),
+            decimals: 6,
+        };
+        save(&mut symdec_store, b"sell", &sell).unwrap();
+        let bid = TokenSymDec {
+            symbol: "BID".to_string(),
+            decimals: 8,
+        };
+        save(&mut symdec_store, b"bid", &bid).unwrap();
+        // bob and carol split the lot
+        let info = StoreClosedAuctionInfo {
+            address: canonical("auction"),
+            code_id: 1,
+            label: "auction".to_string(),
+            sell_token: b"sell".to_vec(),
+            bid_token: b"bid".to_vec(),
+            sell_amount: 1000,
+            winning_bid: Some(300),
+            winners: vec![bob.clone(), carol],
+            timestamp: 1000,
+        };
+        let mut info_store = PrefixedStorage::new(PREFIX_CLOSED_INFO, &mut storage);
+        save(&mut info_store, &7u32.to_le_bytes(), &info).unwrap();
+        for (list, person) in [
+            (AddressList::SellerClosed, &alice),
+            (AddressList::Winner, &bob),
+        ]
+        .iter()
+        {
+            let mut store =
+                PrefixedStorage::multilevel(&[list.prefix(), person.as_slice()], &mut storage);
+            let mut list = AppendStoreMut::<u32, _>::attach_or_create(&mut store).unwrap();
+            list.push(&7).unwrap();
+        }
+
+        // the winner sees what they paid and the share of the lot they won
+        let won = display_addr_closed(&api, &storage, AddressList::Winner, bob.as_slice())
+            .unwrap()
+            .unwrap();
+        assert_eq!(won[0].index, Some(7));
+        assert_eq!(won[0].amount_paid, Some(Uint128(300)));
+        assert_eq!(won[0].amount_won, Some(Uint128(500)));
+        // the seller does not
+        let sold = display_addr_closed(&api, &storage, AddressList::SellerClosed, alice.as_slice())
+            .unwrap()
+            .unwrap();
+        assert_eq!(sold[0].winning_bid, Some(Uint128(300)));
+        assert!(sold[0].amount_paid.is_none());
+        assert!(sold[0].amount_won.is_none());
+    }
+}
@@ -1,7 +1,18 @@
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-use cosmwasm_std::{CanonicalAddr, HumanAddr, Uint128};
+use cosmwasm_std::{Binary, CanonicalAddr, CosmosMsg, HumanAddr, Querier, StdResult, Uint128};
+
+use secret_toolkit::{
+    snip20::{
+        send_from_msg, send_msg, token_info_query, transfer_from_msg, transfer_msg, TokenInfo,
+    },
+    utils::{HandleCallback, Query},
+};
+
+use auction_common::math::unit_price_for_total;
+
+use crate::contract::BLOCK_SIZE;
 
 /// Instantiation message
 #[derive(Serialize, Deserialize, JsonSchema)]
@@ -10,16 +21,22 @@ pub struct InitMsg {
     pub entropy: String,
     /// auction contract info
     pub auction_contract: AuctionContractInfo,
+    /// Optional block size, in bytes, that the factory and its auctions pad responses to.
+    /// Defaults to 256, and must be between 256 and 4096
+    #[serde(default)]
+    pub block_size: Option<u16>,
 }
 
 /// Handle messages
 #[derive(Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum HandleMsg {
-    /// CreateAuction will instantiate a new auction
+    /// CreateAuction will instantiate a new auction.  If the factory charges a creation fee, it
+    /// must be sent with this message
     CreateAuction {
-        /// String label for the auction
-        label: String,
+        /// optional String label for the auction.  If omitted, the factory generates one
+        #[serde(default)]
+        label: Option<String>,
         /// sell contract code hash and address
         sell_contract: ContractInfo,
         /// bid contract code hash and address
@@ -36,6 +53,85 @@ pub enum HandleMsg {
         /// auctions for the same token, etc...
         #[serde(default)]
         description: Option<String>,
+        /// Optional translations of the description.  The description above is the default shown
+        /// when a translation is not available
+        #[serde(default)]
+        descriptions: Vec<LocalizedDescription>,
+        /// Optional short name only displayed to the seller when listing their auctions
+        #[serde(default)]
+        nickname: Option<String>,
+        /// Optional tags the auction is listed under, such as "stablecoin" or "otc"
+        #[serde(default)]
+        tags: Vec<String>,
+        /// Optional additional tokens sold together with the sell tokens as one lot
+        #[serde(default)]
+        bundle: Vec<BundleItem>,
+        /// Optional deposit in bid tokens the seller posts.  If the auction closes with bids but
+        /// without the sell tokens ever being consigned, the deposit is split among the bidders
+        #[serde(default)]
+        deposit: Option<Uint128>,
+        /// Optional timestamp by which the full lot must be consigned.  If it has not been
+        /// consigned by then, anyone may cancel the auction and have all bids returned.
+        /// Timestamp is in seconds since epoch 01/01/1970
+        #[serde(default)]
+        consign_by: Option<u64>,
+        /// Optional price at which a bidder may buy the lot immediately and close the auction
+        #[serde(default)]
+        buy_now_price: Option<Uint128>,
+        /// true if the seller should not be allowed to bid in the auction.  Defaults to false
+        #[serde(default)]
+        no_self_bid: bool,
+        /// true if a bidder may only replace their bid with a higher bid.  Defaults to false
+        #[serde(default)]
+        only_increasing_rebids: bool,
+        /// policy used to pick the winner when the highest bids are tied.  Defaults to
+        /// earliest_wins
+        #[serde(default)]
+        tie_break: TieBreak,
+        /// true if bids should be backed by an allowance given to the auction instead of being
+        /// sent to escrow.  Requires a SNIP-20 bid token.  Defaults to false
+        #[serde(default)]
+        allowance_bids: bool,
+        /// true if the auction should have a message board where the seller can post updates and
+        /// bidders can post questions.  Defaults to false
+        #[serde(default)]
+        message_board: bool,
+        /// Optional schedule by which the minimum bid declines from the time the auction is
+        /// created
+        #[serde(default)]
+        min_bid_decay: Option<MinBidDecay>,
+        /// Optional number of seconds the seller has to accept or reject the winning bid when
+        /// someone other than the seller finalizes the auction.  If the seller does not respond
+        /// in time, the sale goes through
+        #[serde(default)]
+        seller_confirmation: Option<u64>,
+        /// true if bidders may specify a price per whole sell token instead of a total bid
+        #[serde(default)]
+        unit_price_bids: bool,
+        /// Optional limit on the number of distinct bidders.  Once it is reached, only addresses
+        /// that already have an active bid may bid
+        #[serde(default)]
+        max_bidders: Option<u32>,
+        /// Optional number of distinct bidders the auction needs to sell.  If fewer have active
+        /// bids when it closes, every bid is returned instead
+        #[serde(default)]
+        min_bidders: Option<u32>,
+        /// Optional number of seconds after the closing time during which sealed bids are
+        /// revealed.  If given, bids are placed as hash commitments backed by a deposit, and
+        /// only bids that are revealed in time compete
+        #[serde(default)]
+        reveal_window: Option<u64>,
+        /// how the winner is picked from the active bids.  A raffle draws the winner at random
+        /// with odds weighted by bid size.  Defaults to highest_bid
+        #[serde(default)]
+        settlement: SettlementMode,
+        /// Optional amount a crowdfund must raise to sell the lot.  Required for a crowdfund
+        #[serde(default)]
+        funding_target: Option<Uint128>,
+        /// Optional address that referred the seller.  If the auction closes with a sale, the
+        /// referrer is paid a share of the creation fee
+        #[serde(default)]
+        referrer: Option<HumanAddr>,
     },
 
     /// RegisterAuction saves the auction info of a newly instantiated auction and adds it to the list
@@ -49,6 +145,18 @@ pub enum HandleMsg {
         auction: RegisterAuctionInfo,
         /// sell token contract info
         sell_contract: ContractInfo,
+        /// additional tokens sold in the same lot
+        #[serde(default)]
+        bundle: Vec<BundleItem>,
+        /// bid token contract info
+        #[serde(default)]
+        bid_contract: Option<ContractInfo>,
+        /// optional deposit the seller posts in bid tokens
+        #[serde(default)]
+        deposit: Option<Uint128>,
+        /// key the factory gave the auction to authenticate its callbacks
+        #[serde(default)]
+        callback_key: Option<String>,
     },
 
     /// CloseAuction tells the factory that the auction closed and provides the winning bid if appropriate
@@ -65,10 +173,24 @@ pub enum HandleMsg {
         /// winning bid if the auction ended in a swap
         #[serde(default)]
         winning_bid: Option<Uint128>,
+        /// other bidders that split the lot with the winner because of a tie
+        #[serde(default)]
+        co_winners: Vec<HumanAddr>,
+        /// address that referred the winning bidder
+        #[serde(default)]
+        referrer: Option<HumanAddr>,
+        /// number of distinct bidders the auction needed but did not reach, if it closed without
+        /// a sale because of it
+        #[serde(default)]
+        unmet_min_bidders: Option<u32>,
+        /// key the factory gave the auction to authenticate its callbacks
+        #[serde(default)]
+        callback_key: Option<String>,
     },
 
-    /// RegisterBidder allows the factory to know an auction has a new bidder so it can update their
-    /// list of auctions, as well a create a viewing key for the auction if one was set
+    /// RegisterBidder allows the factory to know an auction has a new bidder, or that a bidder
+    /// changed their bid, so it can update their list of auctions, as well a create a viewing key
+    /// for the auction if one was set
     ///
     /// Only auctions will use this function    
     RegisterBidder {
@@ -76,6 +198,12 @@ pub enum HandleMsg {
         index: u32,
         /// bidder's address        
         bidder: HumanAddr,
+        /// amount of the bidder's active bid, if the auction knows it
+        #[serde(default)]
+        amount: Option<Uint128>,
+        /// key the factory gave the auction to authenticate its callbacks
+        #[serde(default)]
+        callback_key: Option<String>,
     },
 
     /// RemoveBidder allows the factory to know a bidder retracted his bid from an auction
@@ -86,11 +214,34 @@ pub enum HandleMsg {
         index: u32,
         /// bidder's address        
         bidder: HumanAddr,
+        /// key the factory gave the auction to authenticate its callbacks
+        #[serde(default)]
+        callback_key: Option<String>,
     },
 
-    /// Allows the admin to add a new auction contract version
+    /// Allows the admin to add a new auction contract version, optionally deprecating the
+    /// auctions created with earlier versions
     NewAuctionContract {
         auction_contract: AuctionContractInfo,
+        /// true if the auctions of earlier versions should be marked as deprecated in listings
+        #[serde(default)]
+        deprecate_old: bool,
+        /// optional warning sent to every active auction of an earlier version.  Sending a notice
+        /// also marks the earlier versions as deprecated
+        #[serde(default)]
+        deprecation_notice: Option<String>,
+        /// optional number of active auctions to check for deprecation in this message.  Any
+        /// remaining ones are checked with ContinueDeprecation.  Defaults to 50
+        #[serde(default)]
+        page_size: Option<u32>,
+    },
+
+    /// Allows the admin to check the next page of active auctions of a deprecation started by
+    /// NewAuctionContract
+    ContinueDeprecation {
+        /// optional number of active auctions to check.  Defaults to 50
+        #[serde(default)]
+        page_size: Option<u32>,
     },
 
     /// Create a viewing key to be used with all factory and auction authenticated queries
@@ -103,10 +254,188 @@ pub enum HandleMsg {
         padding: Option<String>,
     },
 
-    /// Allows an admin to start/stop all auction creation
-    SetStatus { stop: bool },
+    /// Allows an admin to stop or restart parts of the factory, so it can be shut down in phases.
+    /// Any switch that is not specified is left unchanged
+    SetStatus {
+        /// optionally stop (or restart) the creation of new auctions
+        #[serde(default)]
+        stop_creation: Option<bool>,
+        /// optionally stop (or restart) the registration of new bids from all auctions
+        #[serde(default)]
+        stop_bids: Option<bool>,
+        /// optionally stop (or restart) the registration of new bids from auctions created with
+        /// an earlier auction contract version
+        #[serde(default)]
+        stop_old_versions: Option<bool>,
+    },
+
+    /// Allows an admin to revoke (or restore) an earlier auction contract version.  The factory
+    /// refuses to register bids with auctions created with a revoked version, but still records
+    /// their closes and the bids they return
+    RevokeAuctionVersion {
+        /// code id of the auction contract version
+        code_id: u64,
+        /// true if the version should be revoked, false if it should be restored
+        revoked: bool,
+    },
+
+    /// Allows an admin to fold new entropy into the seed used to generate keys
+    Reseed {
+        /// string used as an entropy source
+        entropy: String,
+    },
+
+    /// Allows an admin to set the limits used to reject obviously broken auctions
+    SetCreationLimits {
+        /// smallest sell amount (in the sell token's smallest denomination) an auction may have
+        min_sell_amount: Uint128,
+        /// optional limit on the minimum bid expressed as a multiple of the sell amount after
+        /// adjusting for each token's decimal places.  If not specified, there will be no limit
+        #[serde(default)]
+        max_bid_multiple: Option<Uint128>,
+    },
+
+    /// Allows an admin to set (or remove) the uscrt fee charged to create an auction
+    SetCreationFee {
+        /// fee to charge.  If not specified, auctions can be created for free
+        #[serde(default)]
+        fee: Option<CreationFee>,
+    },
+
+    /// Allows an admin to set the share of an auction's creation fee paid to the referrers of its
+    /// seller and winning bidder when it closes with a sale
+    SetReferralShare {
+        /// share of the creation fee in basis points (1/100th of a percent)
+        basis_points: u16,
+    },
+
+    /// Allows an admin to withdraw fees held in the factory's treasury
+    Withdraw {
+        /// asset to withdraw
+        asset: Asset,
+        /// amount to withdraw
+        amount: Uint128,
+        /// address to send the withdrawal to
+        to: HumanAddr,
+    },
+
+    /// Finalizes up to limit active auctions whose closing time has passed, earliest first.
+    /// Anyone may call this.  An auction that does not close is skipped for an hour
+    CloseExpired {
+        /// maximum number of auctions to finalize
+        limit: u32,
+    },
+
+    /// Allows an admin to halt or resume an active auction.  A halted auction does not accept
+    /// bids, consignments, or finalization, but bidders may still retract their bids
+    HaltAuction {
+        /// index of the auction
+        index: u32,
+        /// true if the auction should be halted, false if it should resume
+        halted: bool,
+    },
+
+    /// Allows an admin to close an active auction that is still open long after its closing time
+    ForceFinalizeAuction {
+        /// index of the auction
+        index: u32,
+    },
+
+    /// Allows an admin to hand an active auction over to a new factory.  This factory will no
+    /// longer list the auction or accept its callbacks
+    MigrateAuction {
+        /// index of the auction with this factory
+        index: u32,
+        /// address of the new factory
+        factory_address: HumanAddr,
+        /// code hash of the new factory
+        factory_code_hash: String,
+        /// index of the auction with the new factory
+        new_index: u32,
+        /// key the new factory gave the auction to authenticate its callbacks
+        callback_key: String,
+    },
+
+    /// Allows an admin to requery a token's symbol and decimals and update the cached values
+    /// displayed in the auction lists
+    RefreshTokenInfo {
+        /// address of the token contract
+        token_address: HumanAddr,
+        /// code hash of the token contract
+        code_hash: String,
+        /// id of the token if this is a SNIP-1155 contract
+        #[serde(default)]
+        token_id: Option<String>,
+    },
+
+    /// Allows an admin to record (or clear) the chain and denom an IBC-wrapped token originated
+    /// from, so the auction lists can display where the token came from
+    SetTokenOrigin {
+        /// address of the token contract
+        token_address: HumanAddr,
+        /// id of the token if this is a SNIP-1155 contract
+        #[serde(default)]
+        token_id: Option<String>,
+        /// origin of the token.  If not specified, any existing origin will be removed
+        #[serde(default)]
+        origin: Option<IbcOrigin>,
+    },
+
+    /// Allows an admin to start or stop only letting allowlisted addresses create auctions
+    SetAllowlistMode {
+        /// true if only allowlisted addresses may create auctions
+        enabled: bool,
+    },
+
+    /// Allows an admin to add addresses to the list of those allowed to create auctions while
+    /// the allowlist is enforced
+    AddToAllowlist {
+        /// addresses to add
+        addresses: Vec<HumanAddr>,
+    },
+
+    /// Allows an admin to remove addresses from the allowlist
+    RemoveFromAllowlist {
+        /// addresses to remove
+        addresses: Vec<HumanAddr>,
+    },
+
+    /// Allows an admin to subscribe a contract to the closes of a token pair's auctions.  While
+    /// the pair has an enabled subscriber, the factory logs its closes, and the subscribers read
+    /// them with the PairCloses query
+    AddCloseSubscriber {
+        /// address of the subscribing contract
+        subscriber: HumanAddr,
+        /// token being sold
+        sell_token: TokenAddress,
+        /// token being bid
+        bid_token: TokenAddress,
+    },
 
-    /// Change the closing time and/or minimum bid of an auction
+    /// Allows an admin to unsubscribe a contract from the closes of a token pair's auctions
+    RemoveCloseSubscriber {
+        /// address of the subscribed contract
+        subscriber: HumanAddr,
+        /// token being sold
+        sell_token: TokenAddress,
+        /// token being bid
+        bid_token: TokenAddress,
+    },
+
+    /// Allows an admin to disable or enable a subscribed contract without removing its
+    /// subscription.  A pair's closes are only logged while it has an enabled subscriber
+    SetCloseSubscriberStatus {
+        /// address of the subscribed contract
+        subscriber: HumanAddr,
+        /// token being sold
+        sell_token: TokenAddress,
+        /// token being bid
+        bid_token: TokenAddress,
+        /// true if the contract should be enabled
+        enabled: bool,
+    },
+
+    /// Change the closing time, minimum bid, and/or sell amount of an auction
     ///
     /// Only auctions will call this function
     ChangeAuctionInfo {
@@ -118,6 +447,102 @@ pub enum HandleMsg {
         /// optional new minimum bid
         #[serde(default)]
         minimum_bid: Option<Uint128>,
+        /// optional new sell amount
+        #[serde(default)]
+        sell_amount: Option<Uint128>,
+        /// key the factory gave the auction to authenticate its callbacks
+        #[serde(default)]
+        callback_key: Option<String>,
+    },
+
+    /// Moves an auction from its previous seller's lists to the new seller's lists
+    ///
+    /// Only auctions will call this function
+    ChangeSeller {
+        /// auction index
+        index: u32,
+        /// previous seller's address
+        seller: HumanAddr,
+        /// new seller's address
+        new_seller: HumanAddr,
+        /// key the factory gave the auction to authenticate its callbacks
+        #[serde(default)]
+        callback_key: Option<String>,
+    },
+
+    /// Allows the admin to prune the records of auctions that closed before the specified time.
+    /// Pruned auctions will no longer be displayed in any closed auction list, and they will be
+    /// removed from a seller's/winner's closed lists the next time that list is updated
+    PruneClosed {
+        /// closed auctions with a closing time earlier than this will be pruned.
+        /// Timestamp is in seconds since epoch 01/01/1970
+        keep_after_timestamp: u64,
+        /// optional maximum number of records to prune in this message.  Defaults to 200
+        #[serde(default)]
+        page_size: Option<u32>,
+    },
+
+    /// Allows a seller to set or remove the nickname of one of their active auctions
+    SetNickname {
+        /// address of the auction
+        auction: HumanAddr,
+        /// new nickname.  If not specified, the current nickname will be removed
+        #[serde(default)]
+        nickname: Option<String>,
+    },
+
+    /// Adds an active auction to the caller's private watchlist
+    Watch {
+        /// factory index of the auction
+        index: u32,
+    },
+
+    /// Removes an auction from the caller's private watchlist
+    Unwatch {
+        /// factory index of the auction
+        index: u32,
+    },
+
+    /// CreateSeries will create a series of auctions with the same parameters.  The sell tokens
+    /// for every round are placed in the factory's escrow, the first round is created
+    /// immediately, and each following round is created when the previous one closes
+    CreateSeries {
+        /// String label for the series.  Each round's label will be this label followed by the
+        /// round's auction index
+        label: String,
+        /// sell contract code hash and address
+        sell_contract: ContractInfo,
+        /// bid contract code hash and address
+        bid_contract: ContractInfo,
+        /// amount of tokens being sold in each round
+        sell_amount: Uint128,
+        /// minimum bid that will be accepted in each round
+        minimum_bid: Uint128,
+        /// number of seconds each round will last
+        interval: u64,
+        /// number of rounds in the series
+        rounds: u32,
+        /// Optional free-form description of the auctions (best to avoid double quotes)
+        #[serde(default)]
+        description: Option<String>,
+    },
+
+    /// Allows a seller to cancel a series.  No further rounds will be created, and the sell
+    /// tokens of the rounds that were not created will be returned.  A round that is already
+    /// running will continue until it is closed
+    CancelSeries {
+        /// id of the series
+        series: u32,
+    },
+
+    /// Allows an admin to settle two complementary auctions (one sells X for Y while the other
+    /// sells Y for X) whose prices cross by swapping their lots between the two sellers.  Both
+    /// auctions must be fully consigned, have no active bids, and not sell a bundled lot
+    MatchAuctions {
+        /// index of the first auction
+        a: u32,
+        /// index of the second auction
+        b: u32,
     },
 }
 
@@ -135,15 +560,60 @@ pub enum QueryMsg {
         #[serde(default)]
         filter: Option<FilterTypes>,
     },
-    /// lists all active auctions sorted by pair
-    ListActiveAuctions {},
+    /// lists the active auctions on the given address' watchlist
+    ListWatched {
+        /// address whose watchlist to display
+        address: HumanAddr,
+        /// viewing key
+        viewing_key: String,
+    },
+    /// totals the amounts the given address has bid across its active auctions, per bid token
+    BidTotals {
+        /// address whose bids to total
+        address: HumanAddr,
+        /// viewing key
+        viewing_key: String,
+    },
+    /// lists all active auctions sorted by pair, optionally only those created with one auction
+    /// contract version
+    ListActiveAuctions {
+        /// optional code id of the auction contract version to list
+        #[serde(default)]
+        code_id: Option<u64>,
+    },
+    /// lists the active auctions closing between current_time and current_time + within_seconds,
+    /// sorted by closing time.  Queries can not read the block time, so the caller supplies the
+    /// current time.  If you specify page size, it returns only that number of auctions (default
+    /// is 200)
+    ListEndingSoon {
+        /// current time in seconds since epoch 01/01/1970
+        current_time: u64,
+        /// length of the window in seconds
+        within_seconds: u64,
+        /// optional number of auctions to return
+        #[serde(default)]
+        page_size: Option<u32>,
+    },
+    /// lists the active auctions with the given tag in the order they were created.  If you
+    /// specify page size, it returns only that number of auctions (default is 200).  If you
+    /// specify page, it skips page * page_size auctions
+    ListAuctionsByTag {
+        /// tag to list
+        tag: String,
+        /// optional page number starting at 0
+        #[serde(default)]
+        page: Option<u32>,
+        /// optional number of auctions to return
+        #[serde(default)]
+        page_size: Option<u32>,
+    },
     /// lists closed auctions in reverse chronological order.  If you specify page size, it returns
     /// only that number of auctions (default is 200).  If you specify the before parameter, it will
     /// start listing from the first auction whose index is less than "before".  If you are
     /// paginating, you would take the index of the last auction you receive, and specify that as the
     /// before parameter on your next query so it will continue where it left off
     ListClosedAuctions {
-        /// optionally only show auctions with index less than specified value
+        /// optionally only show auctions that closed before the auction with this index
         #[serde(default)]
         before: Option<u32>,
         /// optional number of auctions to return
@@ -157,6 +627,122 @@ pub enum QueryMsg {
         /// viewing key
         viewing_key: String,
     },
+    /// authenticates several address/viewing key pairs at once
+    AreKeysValid {
+        /// addresses and the viewing keys to authenticate them with
+        pairs: Vec<(HumanAddr, String)>,
+    },
+    /// displays the number of auctions and tokens the factory is storing
+    StorageInfo {},
+    /// lists auction series with the schedule and results of each round
+    ListSeries {
+        /// optional id of the first series to list.  Defaults to 0
+        #[serde(default)]
+        start: Option<u32>,
+        /// optional number of series to list.  Defaults to 10, and can be at most 30
+        #[serde(default)]
+        limit: Option<u32>,
+    },
+    /// displays the current info of a batch of auctions, whether they are active or closed
+    GetAuctions {
+        /// factory indices of the auctions to display
+        indices: Vec<u32>,
+    },
+    /// displays the current info of the auction created with a label
+    AuctionByLabel {
+        /// label the auction was created with
+        label: String,
+    },
+    /// displays the settlement record the factory stored when an auction closed
+    SettlementRecord {
+        /// factory index of the closed auction
+        index: u32,
+    },
+    /// lists the most recent winning bids of the auctions that sold one token for another, newest
+    /// first
+    RecentPrices {
+        /// token that was sold
+        sell_token: TokenAddress,
+        /// token that was bid
+        bid_token: TokenAddress,
+        /// optional number of sales to list.  Defaults to 10, and can be at most 100
+        #[serde(default)]
+        limit: Option<u32>,
+    },
+    /// lists the contracts subscribed to the closes of a token pair's auctions
+    CloseSubscribers {
+        /// token being sold
+        sell_token: TokenAddress,
+        /// token being bid
+        bid_token: TokenAddress,
+    },
+    /// lists the logged closes of a subscribed token pair's auctions in the order they happened
+    PairCloses {
+        /// token being sold
+        sell_token: TokenAddress,
+        /// token being bid
+        bid_token: TokenAddress,
+        /// optional number of logged closes to skip.  Pass the `next` of the previous answer to
+        /// only list the closes that happened since.  Defaults to 0
+        #[serde(default)]
+        since: Option<u32>,
+        /// optional number of closes to list.  Defaults to 10, and can be at most 100
+        #[serde(default)]
+        limit: Option<u32>,
+    },
+    /// displays whether only allowlisted addresses may create auctions, and how many are
+    /// allowlisted
+    Allowlist {},
+    /// makes every check the factory makes when creating an auction, and displays the
+    /// instantiation message it would send, without creating anything.  Queries can not read the
+    /// sender or the block time, so the caller supplies the seller and the current time
+    PreviewCreateAuction {
+        /// address that would create the auction
+        seller: HumanAddr,
+        /// current time in seconds since epoch 01/01/1970
+        current_time: u64,
+        /// the create_auction message the seller would send
+        create_auction: HandleMsg,
+    },
+    /// displays the factory's auction creation settings
+    Config {},
+    /// displays which parts of the factory have been stopped
+    Status {},
+    /// displays the balance of every asset the factory's treasury has received
+    TreasuryBalances {},
+    /// displays the referral fees an address has earned
+    ReferralEarnings {
+        /// address whose earnings to display
+        address: HumanAddr,
+        /// viewing key
+        viewing_key: String,
+    },
+    /// lists the changes to the treasury balance of an asset in reverse chronological order.
+    /// Only the admin may view them
+    TreasuryRecords {
+        /// admin address
+        address: HumanAddr,
+        /// admin's viewing key
+        viewing_key: String,
+        /// asset whose records to display
+        asset: Asset,
+        /// optionally only show records with id less than specified value
+        #[serde(default)]
+        before: Option<u32>,
+        /// optional number of records to return
+        #[serde(default)]
+        page_size: Option<u32>,
+    },
+    /// displays the factory's stored registration record of an auction and the state of its
+    /// registration.  Only the admin may view it
+    AdminAuctionInfo {
+        /// admin address
+        address: HumanAddr,
+        /// admin's viewing key
+        viewing_key: String,
+        /// factory index of the auction
+        index: u32,
+    },
 }
 
 /// the filter types when viewing an address' auctions
@@ -181,12 +767,34 @@ pub enum QueryAnswer {
         #[serde(skip_serializing_if = "Option::is_none")]
         closed: Option<MyClosedLists>,
     },
+    /// List the active auctions on an address' watchlist
+    ListWatched {
+        /// watched auctions sorted by pair
+        watched: Vec<AuctionInfo>,
+    },
+    /// Totals of an address' active bids
+    BidTotals {
+        /// one total per bid token, sorted by symbol
+        totals: Vec<BidTokenTotal>,
+    },
     /// List active auctions sorted by pair
     ListActiveAuctions {
         /// active auctions sorted by pair
         #[serde(skip_serializing_if = "Option::is_none")]
         active: Option<Vec<AuctionInfo>>,
     },
+    /// List active auctions closing soon, sorted by closing time
+    ListEndingSoon {
+        /// active auctions closing within the requested window
+        auctions: Vec<AuctionInfo>,
+    },
+    /// List active auctions with a tag
+    ListAuctionsByTag {
+        /// active auctions with the tag in the order they were created
+        auctions: Vec<AuctionInfo>,
+        /// total number of active auctions with the tag
+        count: u32,
+    },
     /// List closed auctions in reverse chronological order
     ListClosedAuctions {
         /// closed auctions in reverse chronological order
@@ -197,6 +805,293 @@ pub enum QueryAnswer {
     ViewingKeyError { error: String },
     /// result of authenticating address/key pair
     IsKeyValid { is_valid: bool },
+    /// results of authenticating address/key pairs, in the order the pairs were given
+    AreKeysValid { is_valid: Vec<bool> },
+    /// counts of what the factory is storing
+    StorageInfo {
+        /// number of active auctions
+        active_auctions: u32,
+        /// number of closed auction records still stored
+        closed_auctions: u32,
+        /// number of closed auction records that have been pruned
+        pruned_auctions: u32,
+        /// number of token symbols/decimals stored
+        tokens: u32,
+    },
+    /// info of the requested batch of auctions
+    GetAuctions {
+        /// info of each requested auction in the order requested
+        auctions: Vec<IndexedAuctionInfo>,
+    },
+    /// info of the auction created with the requested label
+    AuctionByLabel {
+        /// info of the auction if the factory created one with the label
+        #[serde(skip_serializing_if = "Option::is_none")]
+        auction: Option<IndexedAuctionInfo>,
+    },
+    /// List of auction series
+    ListSeries {
+        /// auction series in the order they were created
+        series: Vec<SeriesInfo>,
+        /// id to pass as `start` to list the following series, if there are any
+        #[serde(skip_serializing_if = "Option::is_none")]
+        next: Option<u32>,
+    },
+    /// the auction a create_auction message would instantiate
+    PreviewCreateAuction {
+        /// factory index the auction would be given if no other auction is created first
+        index: u32,
+        /// label the auction would be given
+        label: String,
+        /// code id of the auction contract that would be instantiated
+        code_id: u64,
+        /// code hash of the auction contract that would be instantiated
+        code_hash: String,
+        /// JSON instantiation message.  The callback key is left empty and the raffle seed is
+        /// null, because they are only generated when the auction is created
+        init_msg: Binary,
+        /// uscrt fee that must be sent with the create_auction message
+        #[serde(skip_serializing_if = "Option::is_none")]
+        creation_fee: Option<Uint128>,
+    },
+    /// the factory's auction creation settings
+    Config {
+        /// smallest sell amount (in the sell token's smallest denomination) an auction may have
+        min_sell_amount: Uint128,
+        /// optional limit on the minimum bid expressed as a multiple of the sell amount
+        #[serde(skip_serializing_if = "Option::is_none")]
+        max_bid_multiple: Option<Uint128>,
+        /// fee that must be sent to create an auction
+        #[serde(skip_serializing_if = "Option::is_none")]
+        creation_fee: Option<CreationFee>,
+        /// share of the creation fee paid to referrers in basis points
+        referral_share: u16,
+    },
+    /// which parts of the factory have been stopped
+    Status {
+        /// the factory's stop switches
+        status: FactoryStatus,
+        /// code id of the current auction contract version
+        code_id: u64,
+        /// code ids of the deprecated auction contract versions
+        deprecated_code_ids: Vec<u64>,
+        /// code ids of the revoked auction contract versions
+        revoked_code_ids: Vec<u64>,
+    },
+    /// referral fees an address has earned
+    ReferralEarnings {
+        /// total uscrt earned
+        earned: Uint128,
+        /// number of referral payouts received
+        payouts: u32,
+    },
+    /// allowlist mode and size
+    Allowlist {
+        /// true if only allowlisted addresses may create auctions
+        enabled: bool,
+        /// number of allowlisted addresses
+        count: u32,
+    },
+    /// contracts subscribed to the closes of a token pair's auctions
+    CloseSubscribers {
+        /// subscribers in the order they were added
+        subscribers: Vec<CloseSubscriberInfo>,
+    },
+    /// logged closes of a token pair's auctions
+    PairCloses {
+        /// closes in the order they happened
+        closes: Vec<PairClose>,
+        /// cursor to pass as `since` to list the closes logged after these
+        next: u32,
+    },
+    /// treasury balances in the order each asset was first received
+    TreasuryBalances { balances: Vec<TreasuryBalance> },
+    /// changes to the treasury balance of an asset
+    TreasuryRecords {
+        /// records in reverse chronological order
+        records: Vec<TreasuryRecord>,
+        /// total number of records of the asset
+        count: u32,
+    },
+    /// recent winning bids of a token pair
+    RecentPrices {
+        /// number of decimal places of the sell token
+        sell_decimals: u8,
+        /// number of decimal places of the bid token
+        bid_decimals: u8,
+        /// winning bids, newest first
+        prices: Vec<SalePrice>,
+    },
+    /// settlement record of a closed auction
+    SettlementRecord {
+        /// factory index of the auction
+        index: u32,
+        /// auction address
+        auction_address: HumanAddr,
+        /// symbols of tokens sold and bid in form of SELL-BID at the time the auction closed
+        pair: String,
+        /// sell amount
+        sell_amount: Uint128,
+        /// number of decimal places in sell_amount
+        sell_decimals: u8,
+        /// winning bid if the lot was sold
+        #[serde(skip_serializing_if = "Option::is_none")]
+        winning_bid: Option<Uint128>,
+        /// number of decimal places in winning_bid
+        bid_decimals: u8,
+        /// number of bidders that won a share of the lot
+        winners: u32,
+        /// number of distinct bidders the auction needed but did not reach, if it closed without
+        /// a sale because of it
+        #[serde(skip_serializing_if = "Option::is_none")]
+        unmet_min_bidders: Option<u32>,
+        /// time the auction closed in seconds since epoch 01/01/1970
+        timestamp: u64,
+        /// whether the lot was sold
+        outcome: SettlementOutcome,
+        /// sha256 hash of the settlement terms including the seller and winners' addresses
+        digest: Binary,
+    },
+    /// registration record of an auction
+    AdminAuctionInfo {
+        /// factory index of the auction
+        index: u32,
+        /// stored info if the auction is registered as active
+        #[serde(skip_serializing_if = "Option::is_none")]
+        active: Option<ActiveRegistration>,
+        /// stored info if the auction is registered as closed
+        #[serde(skip_serializing_if = "Option::is_none")]
+        closed: Option<ClosedRegistration>,
+        /// position of the auction in the closed auction list
+        #[serde(skip_serializing_if = "Option::is_none")]
+        closed_position: Option<u32>,
+        /// seller recorded when the auction registered
+        #[serde(skip_serializing_if = "Option::is_none")]
+        seller: Option<HumanAddr>,
+        /// code hash of the auction contract version the auction was created with
+        #[serde(skip_serializing_if = "Option::is_none")]
+        code_hash: Option<String>,
+        /// true if the auction's contract version is deprecated
+        deprecated: bool,
+        /// true if the auction's contract version is revoked
+        revoked: bool,
+        /// true if the auction was created but has not registered yet
+        pending: bool,
+        /// true if the auction still has a callback key
+        has_callback_key: bool,
+        /// true if the auction's closed record has been pruned
+        pruned: bool,
+        /// id of the series the auction is a round of
+        #[serde(skip_serializing_if = "Option::is_none")]
+        series_id: Option<u32>,
+        /// true if the factory stored a settlement record when the auction closed
+        has_settlement: bool,
+    },
+}
+
+/// stored registration info of an active auction
+#[derive(Serialize, Deserialize, JsonSchema, Debug)]
+pub struct ActiveRegistration {
+    /// address that registered the auction
+    pub address: HumanAddr,
+    /// code id of the auction contract version the auction was created with
+    pub code_id: u64,
+    /// auction label
+    pub label: String,
+    /// key of the sell token: its canonical address followed by any token id
+    pub sell_token: Binary,
+    /// key of the bid token: its canonical address followed by any token id
+    pub bid_token: Binary,
+    /// sell amount
+    pub sell_amount: Uint128,
+    /// minimum bid
+    pub minimum_bid: Uint128,
+    /// timestamp after which anyone may close the auction
+    pub ends_at: u64,
+}
+
+/// stored registration info of a closed auction
+#[derive(Serialize, Deserialize, JsonSchema, Debug)]
+pub struct ClosedRegistration {
+    /// address that registered the auction
+    pub address: HumanAddr,
+    /// code id of the auction contract version the auction was created with
+    pub code_id: u64,
+    /// auction label
+    pub label: String,
+    /// key of the sell token: its canonical address followed by any token id
+    pub sell_token: Binary,
+    /// key of the bid token: its canonical address followed by any token id
+    pub bid_token: Binary,
+    /// sell amount
+    pub sell_amount: Uint128,
+    /// winning bid
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub winning_bid: Option<Uint128>,
+    /// the bidders that won the lot or a share of it
+    pub winners: Vec<HumanAddr>,
+    /// time the auction closed in seconds since epoch 01/01/1970
+    pub timestamp: u64,
+}
+
+/// the info of an auction requested by its factory index.  If neither active nor closed info is
+/// present, the auction does not exist or its closed record has been pruned
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct IndexedAuctionInfo {
+    /// factory index of the auction
+    pub index: u32,
+    /// auction info if the auction is active
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub active: Option<AuctionInfo>,
+    /// auction info if the auction has closed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub closed: Option<ClosedAuctionInfo>,
+}
+
+/// display format of an auction series
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct SeriesInfo {
+    /// id of the series
+    pub id: u32,
+    /// label of the series
+    pub label: String,
+    /// seller of the series
+    pub seller: HumanAddr,
+    /// sell contract code hash and address
+    pub sell_contract: ContractInfo,
+    /// bid contract code hash and address
+    pub bid_contract: ContractInfo,
+    /// amount of tokens sold in each round
+    pub sell_amount: Uint128,
+    /// minimum bid of each round
+    pub minimum_bid: Uint128,
+    /// number of seconds each round lasts
+    pub interval: u64,
+    /// true if the seller cancelled the series
+    pub cancelled: bool,
+    /// schedule and results of every round
+    pub rounds: Vec<SeriesRoundInfo>,
+}
+
+/// display format of a round of an auction series
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct SeriesRoundInfo {
+    /// round number starting at 1
+    pub round: u32,
+    /// index of the round's auction if it has been created
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub index: Option<u32>,
+    /// address of the round's auction if it has been created
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub address: Option<HumanAddr>,
+    /// closing time of the round.  For rounds that have not been created yet, this is the
+    /// earliest expected closing time
+    pub ends_at: u64,
+    /// status of the round can be "Scheduled", "Active", "Closed", or "Cancelled"
+    pub status: String,
+    /// winning bid if the round closed with a sale
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub winning_bid: Option<Uint128>,
 }
 
 /// Lists of active auctions sorted by pair where the address is a seller or bidder
@@ -245,15 +1140,405 @@ pub enum HandleAnswer {
         #[serde(skip_serializing_if = "Option::is_none")]
         message: Option<String>,
     },
+    /// response from finalizing expired auctions
+    CloseExpired {
+        /// success or failure
+        status: ResponseStatus,
+        /// factory indices of the auctions that were sent a finalize message
+        auctions: Vec<u32>,
+    },
 }
 
 /// code hash and address of a contract
-#[derive(Serialize, Deserialize, JsonSchema)]
+#[derive(Serialize, Deserialize, JsonSchema, Clone)]
 pub struct ContractInfo {
     /// contract's code hash string
     pub code_hash: String,
     /// contract's address
     pub address: HumanAddr,
+    /// id of the token if this is a SNIP-1155 contract.  If not specified, the contract is treated
+    /// as a SNIP-20
+    #[serde(default)]
+    pub token_id: Option<String>,
+}
+
+/// address of a token contract, and the id of the token if it is a SNIP-1155 contract
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug)]
+pub struct TokenAddress {
+    /// token contract address
+    pub address: HumanAddr,
+    /// id of the token if this is a SNIP-1155 contract
+    #[serde(default)]
+    pub token_id: Option<String>,
+}
+
+/// a contract subscribed to the closes of a token pair's auctions
+#[derive(Serialize, Deserialize, JsonSchema, Debug)]
+pub struct CloseSubscriberInfo {
+    /// subscriber's address
+    pub address: HumanAddr,
+    /// true if the subscriber is enabled
+    pub enabled: bool,
+}
+
+/// a logged close of a token pair's auction
+#[derive(Serialize, Deserialize, JsonSchema, Debug)]
+pub struct PairClose {
+    /// factory index of the auction
+    pub index: u32,
+    /// auction address
+    pub auction_address: HumanAddr,
+    /// amount of the sell token in the lot
+    pub sell_amount: Uint128,
+    /// winning bid if the lot sold
+    pub winning_bid: Option<Uint128>,
+    /// time the auction closed in seconds since epoch 01/01/1970
+    pub timestamp: u64,
+}
+
+/// winning bid of a closed auction
+#[derive(Serialize, Deserialize, JsonSchema, Debug)]
+pub struct SalePrice {
+    /// factory index of the auction
+    pub index: u32,
+    /// amount of the sell token that was sold
+    pub sell_amount: Uint128,
+    /// winning bid
+    pub winning_bid: Uint128,
+    /// winning bid's price per whole sell token, rounded down
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unit_price: Option<Uint128>,
+    /// time the auction closed in seconds since epoch 01/01/1970
+    pub timestamp: u64,
+}
+
+/// a translation of the auction description
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug, PartialEq)]
+pub struct LocalizedDescription {
+    /// language code, such as "en" or "zh-Hant"
+    pub lang: String,
+    /// description in that language
+    pub text: String,
+}
+
+/// policy used to pick the winner when the highest bids are tied
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum TieBreak {
+    /// the bid placed first wins
+    EarliestWins,
+    /// the bid placed last wins
+    LatestWins,
+    /// the lot is split evenly among all tied bids if it divides evenly, otherwise the bid placed
+    /// first wins
+    ProRata,
+}
+
+impl Default for TieBreak {
+    fn default() -> Self {
+        TieBreak::EarliestWins
+    }
+}
+
+/// how an auction picks the winner from its active bids
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum SettlementMode {
+    /// the highest bid wins
+    HighestBid,
+    /// the winner is drawn at random with odds proportional to the size of each bid, and pays
+    /// their bid
+    Raffle,
+    /// every bid wins a slice of the lot proportional to its size if the bids add up to the
+    /// funding target, otherwise every bid is returned
+    Crowdfund,
+}
+
+impl Default for SettlementMode {
+    fn default() -> Self {
+        SettlementMode::HighestBid
+    }
+}
+
+/// schedule by which an auction's minimum bid declines while it is open.  A step of 1 second
+/// gives a linear decline
+#[derive(Serialize, Deserialize, JsonSchema, Clone, PartialEq, Debug)]
+pub struct MinBidDecay {
+    /// lowest value the minimum bid declines to
+    pub floor: Uint128,
+    /// amount the minimum bid declines every interval
+    pub step: Uint128,
+    /// number of seconds between declines
+    pub interval: u64,
+}
+
+/// an additional token included in an auction lot
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct BundleItem {
+    /// token contract code hash and address
+    pub contract: ContractInfo,
+    /// amount of tokens being sold
+    pub amount: Uint128,
+}
+
+impl ContractInfo {
+    /// Returns true if both contracts represent the same token
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - a reference to the other token's ContractInfo
+    pub fn is_same_token(&self, other: &ContractInfo) -> bool {
+        self.address == other.address && self.token_id == other.token_id
+    }
+
+    /// Returns a StdResult<CosmosMsg> used to send tokens the factory has permission to send
+    ///
+    /// # Arguments
+    ///
+    /// * `owner` - address of the owner of the tokens
+    /// * `recipient` - address the tokens are to be sent to
+    /// * `amount` - Uint128 amount of tokens to send
+    /// * `msg` - optional base64 encoded message to pass to the recipient
+    pub fn send_from_msg(
+        &self,
+        owner: HumanAddr,
+        recipient: HumanAddr,
+        amount: Uint128,
+        msg: Option<Binary>,
+    ) -> StdResult<CosmosMsg> {
+        if let Some(token_id) = self.token_id.as_ref() {
+            return Snip1155HandleMsg::Send {
+                token_id: token_id.clone(),
+                from: owner,
+                recipient,
+                recipient_code_hash: None,
+                amount,
+                msg,
+                memo: None,
+                padding: None,
+            }
+            .to_cosmos_msg(self.code_hash.clone(), self.address.clone(), None);
+        }
+        send_from_msg(
+            owner,
+            recipient,
+            amount,
+            msg,
+            None,
+            BLOCK_SIZE,
+            self.code_hash.clone(),
+            self.address.clone(),
+        )
+    }
+
+    /// Returns a StdResult<CosmosMsg> used to pull tokens the factory has permission to send into
+    /// the factory's escrow
+    ///
+    /// # Arguments
+    ///
+    /// * `owner` - address of the owner of the tokens
+    /// * `factory` - address of the factory
+    /// * `amount` - Uint128 amount of tokens to transfer
+    pub fn transfer_from_msg(
+        &self,
+        owner: HumanAddr,
+        factory: HumanAddr,
+        amount: Uint128,
+    ) -> StdResult<CosmosMsg> {
+        if let Some(token_id) = self.token_id.as_ref() {
+            return Snip1155HandleMsg::Transfer {
+                token_id: token_id.clone(),
+                from: owner,
+                recipient: factory,
+                amount,
+                memo: None,
+                padding: None,
+            }
+            .to_cosmos_msg(self.code_hash.clone(), self.address.clone(), None);
+        }
+        transfer_from_msg(
+            owner,
+            factory,
+            amount,
+            None,
+            BLOCK_SIZE,
+            self.code_hash.clone(),
+            self.address.clone(),
+        )
+    }
+
+    /// Returns a StdResult<CosmosMsg> used to transfer tokens out of the factory's escrow
+    ///
+    /// # Arguments
+    ///
+    /// * `factory` - a reference to the address of the factory
+    /// * `recipient` - address the tokens are to be sent to
+    /// * `amount` - Uint128 amount of tokens to transfer
+    pub fn transfer_msg(
+        &self,
+        factory: &HumanAddr,
+        recipient: HumanAddr,
+        amount: Uint128,
+    ) -> StdResult<CosmosMsg> {
+        if let Some(token_id) = self.token_id.as_ref() {
+            return Snip1155HandleMsg::Transfer {
+                token_id: token_id.clone(),
+                from: factory.clone(),
+                recipient,
+                amount,
+                memo: None,
+                padding: None,
+            }
+            .to_cosmos_msg(self.code_hash.clone(), self.address.clone(), None);
+        }
+        transfer_msg(
+            recipient,
+            amount,
+            None,
+            BLOCK_SIZE,
+            self.code_hash.clone(),
+            self.address.clone(),
+        )
+    }
+
+    /// Returns a StdResult<CosmosMsg> used to send tokens out of the factory's escrow to a
+    /// contract
+    ///
+    /// # Arguments
+    ///
+    /// * `factory` - a reference to the address of the factory
+    /// * `recipient` - address the tokens are to be sent to
+    /// * `amount` - Uint128 amount of tokens to send
+    /// * `msg` - optional base64 encoded message to pass to the recipient
+    pub fn send_msg(
+        &self,
+        factory: &HumanAddr,
+        recipient: HumanAddr,
+        amount: Uint128,
+        msg: Option<Binary>,
+    ) -> StdResult<CosmosMsg> {
+        if self.token_id.is_some() {
+            return self.send_from_msg(factory.clone(), recipient, amount, msg);
+        }
+        send_msg(
+            recipient,
+            amount,
+            msg,
+            None,
+            BLOCK_SIZE,
+            self.code_hash.clone(),
+            self.address.clone(),
+        )
+    }
+
+    /// Returns a StdResult<TokenInfo> from performing TokenInfo query
+    ///
+    /// # Arguments
+    ///
+    /// * `querier` - a reference to the Querier dependency of the querying contract
+    pub fn token_info_query<Q: Querier>(&self, querier: &Q) -> StdResult<TokenInfo> {
+        if let Some(token_id) = self.token_id.as_ref() {
+            let resp: Snip1155PublicInfoWrapper = Snip1155QueryMsg::TokenIdPublicInfo {
+                token_id: token_id.clone(),
+            }
+            .query(querier, self.code_hash.clone(), self.address.clone())?;
+            return Ok(resp.token_id_public_info.into_token_info());
+        }
+        token_info_query(
+            querier,
+            BLOCK_SIZE,
+            self.code_hash.clone(),
+            self.address.clone(),
+        )
+    }
+}
+
+/// the SNIP-1155 handle messages the factory will call
+#[derive(Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Snip1155HandleMsg {
+    /// transfer tokens the factory owns or has been given permission to transfer
+    Transfer {
+        token_id: String,
+        from: HumanAddr,
+        recipient: HumanAddr,
+        amount: Uint128,
+        memo: Option<String>,
+        padding: Option<String>,
+    },
+    /// send tokens the factory has been given permission to send
+    Send {
+        token_id: String,
+        from: HumanAddr,
+        recipient: HumanAddr,
+        recipient_code_hash: Option<String>,
+        amount: Uint128,
+        msg: Option<Binary>,
+        memo: Option<String>,
+        padding: Option<String>,
+    },
+}
+
+impl HandleCallback for Snip1155HandleMsg {
+    const BLOCK_SIZE: usize = BLOCK_SIZE;
+}
+
+/// the SNIP-1155 queries the factory will call
+#[derive(Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Snip1155QueryMsg {
+    /// public info of a token id
+    TokenIdPublicInfo { token_id: String },
+}
+
+impl Query for Snip1155QueryMsg {
+    const BLOCK_SIZE: usize = BLOCK_SIZE;
+}
+
+/// SNIP-1155 token configuration (only the fields used by the factory)
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Snip1155TokenConfig {
+    Fungible { decimals: u8 },
+    Nft {},
+}
+
+/// SNIP-1155 token id info (only the fields used by the factory)
+#[derive(Deserialize)]
+pub struct Snip1155TokenIdInfo {
+    pub name: String,
+    pub symbol: String,
+    pub token_config: Snip1155TokenConfig,
+}
+
+/// SNIP-1155 public info of a token id
+#[derive(Deserialize)]
+pub struct Snip1155PublicInfo {
+    pub token_id_info: Snip1155TokenIdInfo,
+    #[serde(default)]
+    pub total_supply: Option<Uint128>,
+}
+
+impl Snip1155PublicInfo {
+    /// Returns TokenInfo in the same format used for SNIP-20 tokens
+    pub fn into_token_info(self) -> TokenInfo {
+        let decimals = match self.token_id_info.token_config {
+            Snip1155TokenConfig::Fungible { decimals } => decimals,
+            Snip1155TokenConfig::Nft {} => 0,
+        };
+        TokenInfo {
+            name: self.token_id_info.name,
+            symbol: self.token_id_info.symbol,
+            decimals,
+            total_supply: self.total_supply,
+        }
+    }
+}
+
+/// TokenIdPublicInfo wrapper struct
+#[derive(Deserialize)]
+pub struct Snip1155PublicInfoWrapper {
+    pub token_id_public_info: Snip1155PublicInfo,
 }
 
 /// Info needed to instantiate an auction
@@ -265,9 +1550,104 @@ pub struct AuctionContractInfo {
     pub code_hash: String,
 }
 
+/// the parts of the factory the admin has stopped
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Copy, Default, Debug, PartialEq)]
+pub struct FactoryStatus {
+    /// true if new auctions can not be created
+    pub creation_stopped: bool,
+    /// true if auctions can not register new bids
+    pub bids_stopped: bool,
+    /// true if auctions created with an earlier auction contract version can not register new
+    /// bids
+    pub old_versions_stopped: bool,
+}
+
+/// chain and denom an IBC-wrapped token originated from
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug, PartialEq)]
+pub struct IbcOrigin {
+    /// id of the chain the token was bridged from
+    pub chain_id: String,
+    /// denom of the token on its origin chain
+    pub denom: String,
+}
+
+/// fee charged to create an auction
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug, PartialEq)]
+pub struct CreationFee {
+    /// amount of uscrt that must be sent with CreateAuction
+    pub amount: Uint128,
+    /// optional address the fee is forwarded to.  If not specified, the fee accumulates in the
+    /// factory's treasury
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub treasury: Option<HumanAddr>,
+}
+
+/// an asset the factory's treasury can hold.  Fees are only ever paid in native coin, so that
+/// is the only kind of asset the treasury can receive
+#[derive(Serialize, Deserialize, JsonSchema, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum Asset {
+    /// native coin
+    Native {
+        /// denomination of the coin
+        denom: String,
+    },
+}
+
+/// reason the treasury balance of an asset changed
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum TreasuryAction {
+    /// fee paid to create an auction
+    CreationFee {
+        /// factory index of the created auction
+        index: u32,
+    },
+    /// withdrawal by the admin
+    Withdrawal {},
+    /// share of a creation fee paid to a referrer
+    ReferralPayout {
+        /// factory index of the auction that paid the creation fee
+        index: u32,
+    },
+}
+
+/// treasury balance of an asset
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct TreasuryBalance {
+    /// the asset
+    pub asset: Asset,
+    /// amount currently held
+    pub balance: Uint128,
+    /// amount of the balance reserved for referral payouts of active auctions, which can not be
+    /// withdrawn
+    pub reserved: Uint128,
+    /// total amount ever received
+    pub received: Uint128,
+    /// total amount ever withdrawn
+    pub withdrawn: Uint128,
+}
+
+/// a change to the treasury balance of an asset
+#[derive(Serialize, Deserialize, JsonSchema, Debug, PartialEq)]
+pub struct TreasuryRecord {
+    /// position of the record in the asset's history
+    pub id: u32,
+    /// reason for the change
+    pub action: TreasuryAction,
+    /// amount received or withdrawn
+    pub amount: Uint128,
+    /// address that paid the fee or received the withdrawal
+    pub address: HumanAddr,
+    /// time of the change in seconds since epoch 01/01/1970
+    pub time: u64,
+}
+
 /// active auction display info
 #[derive(Serialize, Deserialize, JsonSchema)]
 pub struct AuctionInfo {
+    /// auction index with the factory
+    pub index: u32,
     /// auction address
     pub address: HumanAddr,
     /// auction label
@@ -285,6 +1665,38 @@ pub struct AuctionInfo {
     /// timestamp after which anyone may close the auction.
     /// Timestamp is in seconds since epoch 01/01/1970
     pub ends_at: u64,
+    /// seller's nickname for the auction.  Only displayed in the seller's own lists
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nickname: Option<String>,
+    /// amount of the viewer's active bid.  Only displayed in the bidder's own lists, and only
+    /// once a sealed bid has been revealed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub my_bid: Option<Uint128>,
+    /// origin of the sell token if it is an IBC-wrapped token
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sell_origin: Option<IbcOrigin>,
+    /// origin of the bid token if it is an IBC-wrapped token
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bid_origin: Option<IbcOrigin>,
+    /// code id of the auction contract version the auction was created with
+    pub code_id: u64,
+    /// true if the auction was created with a deprecated auction contract version
+    pub deprecated: bool,
+}
+
+/// total amount an address has bid in one token across its active auctions
+#[derive(Serialize, Deserialize, JsonSchema, Debug)]
+pub struct BidTokenTotal {
+    /// key the factory uses to identify the bid token
+    pub token: Binary,
+    /// bid token symbol
+    pub symbol: String,
+    /// number of decimal places in amount
+    pub decimals: u8,
+    /// total amount of the active bids in this token
+    pub amount: Uint128,
+    /// number of active auctions with a bid in this token
+    pub auctions: u32,
 }
 
 /// active auction info for storage
@@ -294,10 +1706,6 @@ pub struct RegisterAuctionInfo {
     pub index: u32,
     /// auction label
     pub label: String,
-    /// sell symbol index
-    pub sell_symbol: u16,
-    /// bid symbol index
-    pub bid_symbol: u16,
     /// sell amount
     pub sell_amount: Uint128,
     /// minimum bid
@@ -309,12 +1717,19 @@ pub struct RegisterAuctionInfo {
 
 impl RegisterAuctionInfo {
     /// takes the register auction information and creates a store auction info struct
-    pub fn to_store_auction_info(&self, address: CanonicalAddr) -> StoreAuctionInfo {
+    pub fn to_store_auction_info(
+        &self,
+        address: CanonicalAddr,
+        code_id: u64,
+        sell_token: Vec<u8>,
+        bid_token: Vec<u8>,
+    ) -> StoreAuctionInfo {
         StoreAuctionInfo {
             address,
+            code_id,
             label: self.label.clone(),
-            sell_symbol: self.sell_symbol,
-            bid_symbol: self.bid_symbol,
+            sell_token,
+            bid_token,
             sell_amount: self.sell_amount.u128(),
             minimum_bid: self.minimum_bid.u128(),
             ends_at: self.ends_at,
@@ -327,12 +1742,14 @@ impl RegisterAuctionInfo {
 pub struct StoreAuctionInfo {
     /// auction address
     pub address: CanonicalAddr,
+    /// code id of the auction contract version the auction was created with
+    pub code_id: u64,
     /// auction label
     pub label: String,
-    /// sell symbol index
-    pub sell_symbol: u16,
-    /// bid symbol index
-    pub bid_symbol: u16,
+    /// key of the sell token: its canonical address followed by any token id
+    pub sell_token: Vec<u8>,
+    /// key of the bid token: its canonical address followed by any token id
+    pub bid_token: Vec<u8>,
     /// sell amount
     pub sell_amount: u128,
     /// minimum bid
@@ -347,15 +1764,18 @@ impl StoreAuctionInfo {
     pub fn to_store_closed_auction_info(
         &self,
         winning_bid: Option<u128>,
+        winners: Vec<CanonicalAddr>,
         timestamp: u64,
     ) -> StoreClosedAuctionInfo {
         StoreClosedAuctionInfo {
             address: self.address.clone(),
+            code_id: self.code_id,
             label: self.label.clone(),
-            sell_symbol: self.sell_symbol,
-            bid_symbol: self.bid_symbol,
+            sell_token: self.sell_token.clone(),
+            bid_token: self.bid_token.clone(),
             sell_amount: self.sell_amount,
             winning_bid,
+            winners,
             timestamp,
         }
     }
@@ -364,7 +1784,7 @@ impl StoreAuctionInfo {
 /// closed auction display info
 #[derive(Serialize, Deserialize, JsonSchema)]
 pub struct ClosedAuctionInfo {
-    /// index in closed auction list
+    /// factory index of the auction
     #[serde(skip_serializing_if = "Option::is_none")]
     pub index: Option<u32>,
     /// auction address
@@ -380,11 +1800,26 @@ pub struct ClosedAuctionInfo {
     /// winning bid
     #[serde(skip_serializing_if = "Option::is_none")]
     pub winning_bid: Option<Uint128>,
+    /// price per whole sell token of the winning bid, rounded down
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub winning_unit_price: Option<Uint128>,
     /// number of decimal places in winning_bid
     #[serde(skip_serializing_if = "Option::is_none")]
     pub bid_decimals: Option<u8>,
     /// time the auction closed in seconds since epoch 01/01/1970
     pub timestamp: u64,
+    /// code id of the auction contract version the auction was created with
+    pub code_id: u64,
+    /// seller's nickname for the auction.  Only displayed in the seller's own lists
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nickname: Option<String>,
+    /// amount of bid tokens the winner paid.  Only displayed in the winner's own lists
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub amount_paid: Option<Uint128>,
+    /// amount of the sell token the winner received, which is less than sell_amount if the lot
+    /// was split between tied bids.  Only displayed in the winner's own lists
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub amount_won: Option<Uint128>,
 }
 
 /// closed auction storage format
@@ -392,16 +1827,70 @@ pub struct ClosedAuctionInfo {
 pub struct StoreClosedAuctionInfo {
     /// auction address
     pub address: CanonicalAddr,
+    /// code id of the auction contract version the auction was created with
+    pub code_id: u64,
     /// auction label
     pub label: String,
-    /// sell symbol index
-    pub sell_symbol: u16,
-    /// bid symbol index
-    pub bid_symbol: u16,
+    /// key of the sell token: its canonical address followed by any token id
+    pub sell_token: Vec<u8>,
+    /// key of the bid token: its canonical address followed by any token id
+    pub bid_token: Vec<u8>,
     /// sell amount
     pub sell_amount: u128,
     /// winning bid
     pub winning_bid: Option<u128>,
+    /// the bidders that won the lot or a share of it
+    pub winners: Vec<CanonicalAddr>,
+    /// time the auction closed in seconds since epoch 01/01/1970
+    pub timestamp: u64,
+}
+
+impl StoreClosedAuctionInfo {
+    /// Returns Option<Uint128> of the winning bid's price per whole sell token, rounded down
+    ///
+    /// # Arguments
+    ///
+    /// * `sell_decimals` - number of decimal places the sell token uses
+    pub fn winning_unit_price(&self, sell_decimals: u8) -> Option<Uint128> {
+        self.winning_bid
+            .and_then(|w| unit_price_for_total(w, self.sell_amount, sell_decimals).ok())
+            .map(Uint128)
+    }
+}
+
+/// how an auction was settled
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SettlementOutcome {
+    /// the lot was swapped for the winning bid
+    Sold,
+    /// the auction closed without a sale
+    NoSale,
+    /// the auction closed without a sale because it had too few bidders
+    TooFewBidders,
+}
+
+/// settlement record storage format
+#[derive(Serialize, Deserialize)]
+pub struct StoreSettlementRecord {
+    /// auction address
+    pub address: CanonicalAddr,
+    /// symbols of tokens sold and bid in form of SELL-BID at the time the auction closed
+    pub pair: String,
+    /// sell amount
+    pub sell_amount: u128,
+    /// number of decimal places in sell_amount
+    pub sell_decimals: u8,
+    /// winning bid if the lot was sold
+    pub winning_bid: Option<u128>,
+    /// number of decimal places in winning_bid
+    pub bid_decimals: u8,
+    /// number of bidders that won a share of the lot
+    pub winners: u32,
+    /// number of distinct bidders the auction needed but did not reach
+    pub unmet_min_bidders: Option<u32>,
     /// time the auction closed in seconds since epoch 01/01/1970
     pub timestamp: u64,
+    /// sha256 hash of the settlement terms
+    pub digest: Vec<u8>,
 }
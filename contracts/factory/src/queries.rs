@@ -0,0 +1,1235 @@
+use cosmwasm_std::{
+    to_binary, Api, Binary, CanonicalAddr, Extern, HumanAddr, Querier, QueryResult,
+    ReadonlyStorage, StdError, StdResult, Storage, Uint128,
+};
+
+use cosmwasm_storage::ReadonlyPrefixedStorage;
+
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+
+use secret_toolkit::storage::AppendStore;
+
+use auction_common::math::checked_add;
+
+use crate::auctions::{
+    check_new_auction, check_seller, load_config, load_pair, load_symdec, prepare_auction,
+    token_count, token_key, NewAuction,
+};
+use crate::contract::{
+    check_tags, ACTIVE_KEY, ENDING_BUCKETS_KEY, ENDING_BUCKET_SECONDS, FACTORY_INFO_KEY,
+    MAX_GET_AUCTIONS, MAX_LIST_SERIES, MAX_RECENT_PRICES, PENDING_KEY, PREFIX_ACTIVE_INFO,
+    PREFIX_AUCTION_SELLERS, PREFIX_BID_AMOUNTS, PREFIX_CALLBACK_KEYS, PREFIX_CLOSED_INFO,
+    PREFIX_CLOSED_ORDER, PREFIX_CLOSED_POSITIONS, PREFIX_CODE_HASHES, PREFIX_ENDING, PREFIX_LABELS,
+    PREFIX_PAIR_SALES, PREFIX_SERIES, PREFIX_SERIES_ROUNDS, PREFIX_SETTLEMENTS, PREFIX_TAGS,
+    PREFIX_TOKEN_ORIGINS, PRUNED_KEY, SERIES_COUNT_KEY,
+};
+use crate::lists::{display_active_list, display_addr_closed, filter_only_active, AddressList};
+use crate::msg::{
+    ActiveRegistration, AuctionInfo, BidTokenTotal, ClosedAuctionInfo, ClosedRegistration,
+    ContractInfo, CreationFee, FilterTypes, HandleMsg, IndexedAuctionInfo, MyActiveLists,
+    MyClosedLists, QueryAnswer, SeriesInfo, SeriesRoundInfo, SettlementOutcome, StoreAuctionInfo,
+    StoreClosedAuctionInfo, StoreSettlementRecord,
+};
+use crate::state::{load, may_load, Config, Series};
+use crate::viewing_keys::is_key_valid;
+
+/// Returns QueryResult displaying the number of auctions and tokens the factory is storing
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+pub fn try_storage_info<S: Storage, A: Api, Q: Querier>(deps: &Extern<S, A, Q>) -> QueryResult {
+    let active: HashSet<u32> = load(&deps.storage, ACTIVE_KEY)?;
+    let tokens = token_count(&deps.storage)?;
+    let pruned: u32 = may_load(&deps.storage, PRUNED_KEY)?.unwrap_or(0);
+    let read_store = ReadonlyPrefixedStorage::new(PREFIX_CLOSED_ORDER, &deps.storage);
+    let closed_len = AppendStore::<u32, _>::attach(&read_store)
+        .and_then(|r| r.ok())
+        .map_or(0, |c| c.len());
+
+    to_binary(&QueryAnswer::StorageInfo {
+        active_auctions: active.len() as u32,
+        closed_auctions: closed_len - pruned,
+        pruned_auctions: pruned,
+        tokens,
+    })
+}
+
+/// Returns QueryResult displaying the current info of a batch of auctions
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `indices` - factory indices of the auctions to display
+pub fn try_get_auctions<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    indices: &[u32],
+) -> QueryResult {
+    if indices.len() > MAX_GET_AUCTIONS {
+        return Err(StdError::generic_err(format!(
+            "Can not request more than {} auctions at a time",
+            MAX_GET_AUCTIONS
+        )));
+    }
+    let auctions = indexed_auctions(deps, indices)?;
+
+    to_binary(&QueryAnswer::GetAuctions { auctions })
+}
+
+/// Returns QueryResult displaying the current info of the auction created with a label
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `label` - label the auction was created with
+pub fn try_auction_by_label<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    label: &str,
+) -> QueryResult {
+    let read_labels = ReadonlyPrefixedStorage::new(PREFIX_LABELS, &deps.storage);
+    let may_index: Option<u32> = may_load(&read_labels, label.as_bytes())?;
+    let auction = match may_index {
+        Some(index) => indexed_auctions(deps, &[index])?.pop(),
+        None => None,
+    };
+
+    to_binary(&QueryAnswer::AuctionByLabel { auction })
+}
+
+/// Returns StdResult<Vec<IndexedAuctionInfo>>
+///
+/// gets the current info of a batch of auctions, whether they are active or closed
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `indices` - factory indices of the auctions to display
+fn indexed_auctions<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    indices: &[u32],
+) -> StdResult<Vec<IndexedAuctionInfo>> {
+    let config: Config = load_config(&deps.storage)?;
+    let read_info = ReadonlyPrefixedStorage::new(PREFIX_ACTIVE_INFO, &deps.storage);
+    let read_origin = ReadonlyPrefixedStorage::new(PREFIX_TOKEN_ORIGINS, &deps.storage);
+    let read_closed = ReadonlyPrefixedStorage::new(PREFIX_CLOSED_INFO, &deps.storage);
+    let mut auctions = Vec::new();
+    for index in indices {
+        let mut active = None;
+        let mut closed = None;
+        let load_info: Option<StoreAuctionInfo> = may_load(&read_info, &index.to_le_bytes())?;
+        if let Some(info) = load_info {
+            if let Some((sell_symdec, bid_symdec)) =
+                load_pair(&deps.storage, &info.sell_token, &info.bid_token)?
+            {
+                active = Some(AuctionInfo {
+                    index: *index,
+                    address: deps.api.human_address(&info.address)?,
+                    label: info.label,
+                    pair: format!("{}-{}", sell_symdec.symbol, bid_symdec.symbol),
+                    sell_amount: Uint128(info.sell_amount),
+                    sell_decimals: sell_symdec.decimals,
+                    minimum_bid: Uint128(info.minimum_bid),
+                    bid_decimals: bid_symdec.decimals,
+                    ends_at: info.ends_at,
+                    nickname: None,
+                    my_bid: None,
+                    sell_origin: may_load(&read_origin, &info.sell_token)?,
+                    bid_origin: may_load(&read_origin, &info.bid_token)?,
+                    code_id: info.code_id,
+                    deprecated: config.deprecated.contains(&info.code_id),
+                });
+            }
+        } else {
+            // pruned closed auctions no longer have a record
+            let load_closed: Option<StoreClosedAuctionInfo> =
+                may_load(&read_closed, &index.to_le_bytes())?;
+            if let Some(info) = load_closed {
+                if let Some((sell_symdec, bid_symdec)) =
+                    load_pair(&deps.storage, &info.sell_token, &info.bid_token)?
+                {
+                    let winning_unit_price = info.winning_unit_price(sell_symdec.decimals);
+                    closed = Some(ClosedAuctionInfo {
+                        index: Some(*index),
+                        address: deps.api.human_address(&info.address)?,
+                        label: info.label,
+                        pair: format!("{}-{}", sell_symdec.symbol, bid_symdec.symbol),
+                        sell_amount: Uint128(info.sell_amount),
+                        sell_decimals: sell_symdec.decimals,
+                        winning_bid: info.winning_bid.map(Uint128),
+                        winning_unit_price,
+                        bid_decimals: info.winning_bid.map(|_a| bid_symdec.decimals),
+                        timestamp: info.timestamp,
+                        code_id: info.code_id,
+                        nickname: None,
+                        amount_paid: None,
+                        amount_won: None,
+                    });
+                }
+            }
+        }
+        auctions.push(IndexedAuctionInfo {
+            index: *index,
+            active,
+            closed,
+        });
+    }
+
+    Ok(auctions)
+}
+
+/// Returns QueryResult listing the active auctions that close within the specified window,
+/// sorted by closing time
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `current_time` - start of the window in seconds since epoch 01/01/1970
+/// * `within_seconds` - length of the window in seconds
+/// * `page_size` - optional number of auctions to display
+pub fn try_list_ending_soon<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    current_time: u64,
+    within_seconds: u64,
+    page_size: Option<u32>,
+) -> QueryResult {
+    let window_end = current_time.saturating_add(within_seconds);
+    let buckets: BTreeSet<u64> = may_load(&deps.storage, ENDING_BUCKETS_KEY)?.unwrap_or_default();
+    let read_ending = ReadonlyPrefixedStorage::new(PREFIX_ENDING, &deps.storage);
+    let read_info = ReadonlyPrefixedStorage::new(PREFIX_ACTIVE_INFO, &deps.storage);
+    let mut ending = Vec::new();
+    for bucket in
+        buckets.range(current_time / ENDING_BUCKET_SECONDS..=window_end / ENDING_BUCKET_SECONDS)
+    {
+        let indices: HashSet<u32> =
+            may_load(&read_ending, &bucket.to_le_bytes())?.unwrap_or_default();
+        for index in indices.into_iter() {
+            let may_info: Option<StoreAuctionInfo> = may_load(&read_info, &index.to_le_bytes())?;
+            if let Some(info) =
+                may_info.filter(|i| i.ends_at >= current_time && i.ends_at <= window_end)
+            {
+                ending.push((index, info));
+            }
+        }
+    }
+    ending.sort_by(|a, b| a.1.ends_at.cmp(&b.1.ends_at).then(a.0.cmp(&b.0)));
+    ending.truncate(page_size.unwrap_or(200) as usize);
+    let config: Config = load_config(&deps.storage)?;
+    let read_origin = ReadonlyPrefixedStorage::new(PREFIX_TOKEN_ORIGINS, &deps.storage);
+    let mut auctions = Vec::new();
+    for (index, info) in ending.into_iter() {
+        if let Some((sell_symdec, bid_symdec)) =
+            load_pair(&deps.storage, &info.sell_token, &info.bid_token)?
+        {
+            auctions.push(AuctionInfo {
+                index,
+                address: deps.api.human_address(&info.address)?,
+                label: info.label,
+                pair: format!("{}-{}", sell_symdec.symbol, bid_symdec.symbol),
+                sell_amount: Uint128(info.sell_amount),
+                sell_decimals: sell_symdec.decimals,
+                minimum_bid: Uint128(info.minimum_bid),
+                bid_decimals: bid_symdec.decimals,
+                ends_at: info.ends_at,
+                nickname: None,
+                my_bid: None,
+                sell_origin: may_load(&read_origin, &info.sell_token)?,
+                bid_origin: may_load(&read_origin, &info.bid_token)?,
+                code_id: info.code_id,
+                deprecated: config.deprecated.contains(&info.code_id),
+            });
+        }
+    }
+
+    to_binary(&QueryAnswer::ListEndingSoon { auctions })
+}
+
+/// Returns QueryResult listing a page of the active auctions with a tag in the order they were
+/// created
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `tag` - tag to list
+/// * `page` - optional page number starting at 0
+/// * `page_size` - optional number of auctions to display
+pub fn try_list_by_tag<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    tag: &str,
+    page: Option<u32>,
+    page_size: Option<u32>,
+) -> QueryResult {
+    // tags are stored lowercased
+    let tag = tag.trim().to_lowercase();
+    let read_tags = ReadonlyPrefixedStorage::new(PREFIX_TAGS, &deps.storage);
+    let tagged: BTreeSet<u32> = may_load(&read_tags, tag.as_bytes())?.unwrap_or_default();
+    let quant = page_size.unwrap_or(200) as usize;
+    let skip = (page.unwrap_or(0) as usize).saturating_mul(quant);
+    let config: Config = load_config(&deps.storage)?;
+    let read_info = ReadonlyPrefixedStorage::new(PREFIX_ACTIVE_INFO, &deps.storage);
+    let read_origin = ReadonlyPrefixedStorage::new(PREFIX_TOKEN_ORIGINS, &deps.storage);
+    let mut auctions = Vec::new();
+    for index in tagged.iter().skip(skip).take(quant) {
+        let may_info: Option<StoreAuctionInfo> = may_load(&read_info, &index.to_le_bytes())?;
+        if let Some(info) = may_info {
+            if let Some((sell_symdec, bid_symdec)) =
+                load_pair(&deps.storage, &info.sell_token, &info.bid_token)?
+            {
+                auctions.push(AuctionInfo {
+                    index: *index,
+                    address: deps.api.human_address(&info.address)?,
+                    label: info.label,
+                    pair: format!("{}-{}", sell_symdec.symbol, bid_symdec.symbol),
+                    sell_amount: Uint128(info.sell_amount),
+                    sell_decimals: sell_symdec.decimals,
+                    minimum_bid: Uint128(info.minimum_bid),
+                    bid_decimals: bid_symdec.decimals,
+                    ends_at: info.ends_at,
+                    nickname: None,
+                    my_bid: None,
+                    sell_origin: may_load(&read_origin, &info.sell_token)?,
+                    bid_origin: may_load(&read_origin, &info.bid_token)?,
+                    code_id: info.code_id,
+                    deprecated: config.deprecated.contains(&info.code_id),
+                });
+            }
+        }
+    }
+
+    to_binary(&QueryAnswer::ListAuctionsByTag {
+        auctions,
+        count: tagged.len() as u32,
+    })
+}
+
+/// Returns QueryResult displaying the factory's auction creation settings
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+pub fn try_query_config<S: Storage, A: Api, Q: Querier>(deps: &Extern<S, A, Q>) -> QueryResult {
+    let config: Config = load_config(&deps.storage)?;
+    let creation_fee = config
+        .creation_fee
+        .map(|f| -> StdResult<CreationFee> {
+            Ok(CreationFee {
+                amount: Uint128(f.amount),
+                treasury: f.treasury.map(|t| deps.api.human_address(&t)).transpose()?,
+            })
+        })
+        .transpose()?;
+
+    to_binary(&QueryAnswer::Config {
+        min_sell_amount: Uint128(config.min_sell_amount),
+        max_bid_multiple: config.max_bid_multiple.map(Uint128),
+        creation_fee,
+        referral_share: config.referral_share,
+    })
+}
+
+/// Returns QueryResult displaying which parts of the factory have been stopped
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+pub fn try_query_status<S: Storage, A: Api, Q: Querier>(deps: &Extern<S, A, Q>) -> QueryResult {
+    let config: Config = load_config(&deps.storage)?;
+
+    to_binary(&QueryAnswer::Status {
+        status: config.status,
+        code_id: config.version.code_id,
+        deprecated_code_ids: config.deprecated,
+        revoked_code_ids: config.revoked,
+    })
+}
+
+/// Returns QueryResult displaying the settlement record of a closed auction
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `index` - factory index of the closed auction
+pub fn try_settlement_record<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    index: u32,
+) -> QueryResult {
+    let settle_store = ReadonlyPrefixedStorage::new(PREFIX_SETTLEMENTS, &deps.storage);
+    let record: StoreSettlementRecord =
+        may_load(&settle_store, &index.to_le_bytes())?.ok_or_else(|| {
+            StdError::generic_err(format!(
+                "There is no settlement record for auction {}",
+                index
+            ))
+        })?;
+    let outcome = if record.winning_bid.is_some() {
+        SettlementOutcome::Sold
+    } else if record.unmet_min_bidders.is_some() {
+        SettlementOutcome::TooFewBidders
+    } else {
+        SettlementOutcome::NoSale
+    };
+
+    to_binary(&QueryAnswer::SettlementRecord {
+        index,
+        auction_address: deps.api.human_address(&record.address)?,
+        pair: record.pair,
+        sell_amount: Uint128(record.sell_amount),
+        sell_decimals: record.sell_decimals,
+        winning_bid: record.winning_bid.map(Uint128),
+        bid_decimals: record.bid_decimals,
+        winners: record.winners,
+        unmet_min_bidders: record.unmet_min_bidders,
+        timestamp: record.timestamp,
+        outcome,
+        digest: Binary(record.digest),
+    })
+}
+
+/// Returns QueryResult listing the most recent winning bids of a token pair
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `sell_token` - a reference to the address of the token that was sold
+/// * `bid_token` - a reference to the address of the token that was bid
+/// * `limit` - optional number of sales to list
+pub fn try_recent_prices<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    sell_token: &TokenAddress,
+    bid_token: &TokenAddress,
+    limit: Option<u32>,
+) -> QueryResult {
+    let sell_key = token_key(
+        &deps.api.canonical_address(&sell_token.address)?,
+        sell_token.token_id.as_ref(),
+    );
+    let bid_key = token_key(
+        &deps.api.canonical_address(&bid_token.address)?,
+        bid_token.token_id.as_ref(),
+    );
+    let (sell_symdec, bid_symdec) = load_pair(&deps.storage, &sell_key, &bid_key)?
+        .ok_or_else(|| StdError::generic_err("No auction has used this token pair"))?;
+    let quant = limit.unwrap_or(10).min(MAX_RECENT_PRICES);
+    let mut prices = Vec::new();
+    let sales_store = ReadonlyPrefixedStorage::multilevel(
+        &[PREFIX_PAIR_SALES, &sell_key, &bid_key],
+        &deps.storage,
+    );
+    if let Some(sales) = AppendStore::<u32, _>::attach(&sales_store) {
+        let sales = sales?;
+        let read_closed = ReadonlyPrefixedStorage::new(PREFIX_CLOSED_INFO, &deps.storage);
+        for index in sales.iter().rev().take(quant as usize) {
+            let index = index?;
+            // records are pruned oldest first, so every earlier sale is gone too
+            let info: StoreClosedAuctionInfo = match may_load(&read_closed, &index.to_le_bytes())? {
+                Some(i) => i,
+                None => break,
+            };
+            if let Some(winning_bid) = info.winning_bid {
+                prices.push(SalePrice {
+                    index,
+                    sell_amount: Uint128(info.sell_amount),
+                    winning_bid: Uint128(winning_bid),
+                    unit_price: info.winning_unit_price(sell_symdec.decimals),
+                    timestamp: info.timestamp,
+                });
+            }
+        }
+    }
+
+    to_binary(&QueryAnswer::RecentPrices {
+        sell_decimals: sell_symdec.decimals,
+        bid_decimals: bid_symdec.decimals,
+        prices,
+    })
+}
+
+/// Returns QueryResult displaying the factory's stored registration record of an auction and
+/// the state of its registration
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `address` - a reference to the admin's address
+/// * `viewing_key` - String holding the admin's viewing key
+/// * `index` - factory index of the auction
+pub fn try_admin_auction_info<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    address: &HumanAddr,
+    viewing_key: String,
+    index: u32,
+) -> QueryResult {
+    let config: Config = load_config(&deps.storage)?;
+    let addr_raw = deps.api.canonical_address(address)?;
+    if !is_key_valid(&deps.storage, &addr_raw, viewing_key)? || addr_raw != config.admin {
+        return to_binary(&QueryAnswer::ViewingKeyError {
+            error: "Only the admin may view auction registration records".to_string(),
+        });
+    }
+    if index >= config.index {
+        return Err(StdError::generic_err(format!(
+            "Auction {} has not been created",
+            index
+        )));
+    }
+    let key = index.to_le_bytes();
+    let pruned: u32 = may_load(&deps.storage, PRUNED_KEY)?.unwrap_or(0);
+    let read_info = ReadonlyPrefixedStorage::new(PREFIX_ACTIVE_INFO, &deps.storage);
+    let active = may_load::<StoreAuctionInfo, _>(&read_info, &key)?
+        .map(|info| -> StdResult<ActiveRegistration> {
+            Ok(ActiveRegistration {
+                address: deps.api.human_address(&info.address)?,
+                code_id: info.code_id,
+                label: info.label,
+                sell_token: Binary::from(info.sell_token.as_slice()),
+                bid_token: Binary::from(info.bid_token.as_slice()),
+                sell_amount: Uint128(info.sell_amount),
+                minimum_bid: Uint128(info.minimum_bid),
+                ends_at: info.ends_at,
+            })
+        })
+        .transpose()?;
+    let read_pos = ReadonlyPrefixedStorage::new(PREFIX_CLOSED_POSITIONS, &deps.storage);
+    let closed_position: Option<u32> = may_load(&read_pos, &key)?;
+    let read_closed = ReadonlyPrefixedStorage::new(PREFIX_CLOSED_INFO, &deps.storage);
+    let closed = may_load::<StoreClosedAuctionInfo, _>(&read_closed, &key)?
+        .map(|info| -> StdResult<ClosedRegistration> {
+            Ok(ClosedRegistration {
+                address: deps.api.human_address(&info.address)?,
+                code_id: info.code_id,
+                label: info.label,
+                sell_token: Binary::from(info.sell_token.as_slice()),
+                bid_token: Binary::from(info.bid_token.as_slice()),
+                sell_amount: Uint128(info.sell_amount),
+                winning_bid: info.winning_bid.map(Uint128),
+                winners: info
+                    .winners
+                    .iter()
+                    .map(|w| deps.api.human_address(w))
+                    .collect::<StdResult<Vec<HumanAddr>>>()?,
+                timestamp: info.timestamp,
+            })
+        })
+        .transpose()?;
+    let code_id = active
+        .as_ref()
+        .map(|a| a.code_id)
+        .or_else(|| closed.as_ref().map(|c| c.code_id));
+    let read_sellers = ReadonlyPrefixedStorage::new(PREFIX_AUCTION_SELLERS, &deps.storage);
+    let seller = may_load::<CanonicalAddr, _>(&read_sellers, &key)?
+        .map(|s| deps.api.human_address(&s))
+        .transpose()?;
+    let read_hash = ReadonlyPrefixedStorage::new(PREFIX_CODE_HASHES, &deps.storage);
+    let code_hash: Option<String> = may_load(&read_hash, &key)?;
+    // the auction is pending if the label the factory is waiting on belongs to it
+    let pending = match may_load::<String, _>(&deps.storage, PENDING_KEY)? {
+        Some(label) => {
+            let read_labels = ReadonlyPrefixedStorage::new(PREFIX_LABELS, &deps.storage);
+            may_load::<u32, _>(&read_labels, label.as_bytes())? == Some(index)
+        }
+        None => false,
+    };
+    let read_keys = ReadonlyPrefixedStorage::new(PREFIX_CALLBACK_KEYS, &deps.storage);
+    let read_rounds = ReadonlyPrefixedStorage::new(PREFIX_SERIES_ROUNDS, &deps.storage);
+    let read_settle = ReadonlyPrefixedStorage::new(PREFIX_SETTLEMENTS, &deps.storage);
+
+    to_binary(&QueryAnswer::AdminAuctionInfo {
+        index,
+        active,
+        closed,
+        closed_position,
+        seller,
+        code_hash,
+        deprecated: code_id.map_or(false, |c| config.deprecated.contains(&c)),
+        revoked: code_id.map_or(false, |c| config.revoked.contains(&c)),
+        pending,
+        has_callback_key: read_keys.get(&key).is_some(),
+        pruned: closed_position.map_or(false, |p| p < pruned),
+        series_id: may_load(&read_rounds, &key)?,
+        has_settlement: read_settle.get(&key).is_some(),
+    })
+}
+
+/// Returns QueryResult listing a page of auction series with the schedule and results of their
+/// rounds
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `start` - optional id of the first series to list
+/// * `limit` - optional number of series to list
+pub fn try_list_series<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    start: Option<u32>,
+    limit: Option<u32>,
+) -> QueryResult {
+    let count: u32 = may_load(&deps.storage, SERIES_COUNT_KEY)?.unwrap_or(0);
+    let series_store = ReadonlyPrefixedStorage::new(PREFIX_SERIES, &deps.storage);
+    let info_store = ReadonlyPrefixedStorage::new(PREFIX_ACTIVE_INFO, &deps.storage);
+    let first = start.unwrap_or(0).min(count);
+    let end = first
+        .saturating_add(limit.unwrap_or(10).min(MAX_LIST_SERIES))
+        .min(count);
+    let mut list = Vec::new();
+    for id in first..end {
+        let series: Series = load(&series_store, &id.to_le_bytes())?;
+        let mut rounds = Vec::new();
+        let mut ends_at = 0;
+        for (i, round) in series.history.iter().enumerate() {
+            // a running round may have had its closing time changed
+            let active: Option<StoreAuctionInfo> = if round.closed {
+                None
+            } else {
+                may_load(&info_store, &round.index.to_le_bytes())?
+            };
+            ends_at = active.map_or(round.ends_at, |a| a.ends_at);
+            let address = match round.address.as_ref() {
+                Some(addr) => Some(deps.api.human_address(addr)?),
+                None => None,
+            };
+            let status = if round.closed { "Closed" } else { "Active" };
+            rounds.push(SeriesRoundInfo {
+                round: i as u32 + 1,
+                index: Some(round.index),
+                address,
+                ends_at,
+                status: status.to_string(),
+                winning_bid: round.winning_bid.map(Uint128),
+            });
+        }
+        let status = if series.cancelled {
+            "Cancelled"
+        } else {
+            "Scheduled"
+        };
+        for round in series.history.len() as u32..series.rounds {
+            ends_at = ends_at.saturating_add(series.interval);
+            rounds.push(SeriesRoundInfo {
+                round: round + 1,
+                index: None,
+                address: None,
+                ends_at,
+                status: status.to_string(),
+                winning_bid: None,
+            });
+        }
+        list.push(SeriesInfo {
+            id,
+            label: series.label,
+            seller: deps.api.human_address(&series.seller)?,
+            sell_contract: series.sell_contract,
+            bid_contract: series.bid_contract,
+            sell_amount: Uint128(series.sell_amount),
+            minimum_bid: Uint128(series.minimum_bid),
+            interval: series.interval,
+            cancelled: series.cancelled,
+            rounds,
+        });
+    }
+
+    to_binary(&QueryAnswer::ListSeries {
+        series: list,
+        next: Some(end).filter(|e| *e < count),
+    })
+}
+
+/// Returns QueryResult listing the active auctions
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `code_id` - optional code id of the auction contract version to list
+pub fn try_list_active<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    code_id: Option<u64>,
+) -> QueryResult {
+    let mut active = display_active_list(&deps.api, &deps.storage, None, ACTIVE_KEY)?;
+    if let Some(id) = code_id {
+        active = active
+            .map(|list| {
+                list.into_iter()
+                    .filter(|a| a.code_id == id)
+                    .collect::<Vec<AuctionInfo>>()
+            })
+            .filter(|list| !list.is_empty());
+    }
+
+    to_binary(&QueryAnswer::ListActiveAuctions { active })
+}
+
+/// Returns QueryResult listing the auctions the address interacted with
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `address` - a reference to the address whose auctions should be listed
+/// * `viewing_key` - String key used to authenticate the query
+/// * `filter` - optional choice of display filters
+pub fn try_list_my<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    address: &HumanAddr,
+    viewing_key: String,
+    filter: Option<FilterTypes>,
+) -> QueryResult {
+    let addr_raw = &deps.api.canonical_address(address)?;
+    // if key matches
+    if is_key_valid(&deps.storage, addr_raw, viewing_key)? {
+        let mut active_lists: Option<MyActiveLists> = None;
+        let mut closed_lists: Option<MyClosedLists> = None;
+        // if no filter default to ALL
+        let types = filter.unwrap_or(FilterTypes::All);
+
+        // list the active auctions
+        if types == FilterTypes::Active || types == FilterTypes::All {
+            let seller_active = display_active_list(
+                &deps.api,
+                &deps.storage,
+                Some(AddressList::SellerActive),
+                addr_raw.as_slice(),
+            )?;
+            let bidder_active = display_active_list(
+                &deps.api,
+                &deps.storage,
+                Some(AddressList::Bidder),
+                addr_raw.as_slice(),
+            )?;
+            if seller_active.is_some() || bidder_active.is_some() {
+                active_lists = Some(MyActiveLists {
+                    as_seller: seller_active,
+                    as_bidder: bidder_active,
+                });
+            }
+        }
+        // list the closed auctions
+        if types == FilterTypes::Closed || types == FilterTypes::All {
+            let seller_closed = display_addr_closed(
+                &deps.api,
+                &deps.storage,
+                AddressList::SellerClosed,
+                addr_raw.as_slice(),
+            )?;
+            let won = display_addr_closed(
+                &deps.api,
+                &deps.storage,
+                AddressList::Winner,
+                addr_raw.as_slice(),
+            )?;
+            if seller_closed.is_some() || won.is_some() {
+                closed_lists = Some(MyClosedLists {
+                    as_seller: seller_closed,
+                    won,
+                });
+            }
+        }
+
+        return to_binary(&QueryAnswer::ListMyAuctions {
+            active: active_lists,
+            closed: closed_lists,
+        });
+    }
+    to_binary(&QueryAnswer::ViewingKeyError {
+        error: "Wrong viewing key for this address or viewing key not set".to_string(),
+    })
+}
+
+/// Returns QueryResult listing the active auctions on an address' watchlist
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `address` - a reference to the address whose watchlist should be displayed
+/// * `viewing_key` - String holding the address' viewing key
+pub fn try_list_watched<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    address: &HumanAddr,
+    viewing_key: String,
+) -> QueryResult {
+    let addr_raw = &deps.api.canonical_address(address)?;
+    if !is_key_valid(&deps.storage, addr_raw, viewing_key)? {
+        return to_binary(&QueryAnswer::ViewingKeyError {
+            error: "Wrong viewing key for this address or viewing key not set".to_string(),
+        });
+    }
+    // closed auctions are filtered out when the list is displayed
+    let watched = display_active_list(
+        &deps.api,
+        &deps.storage,
+        Some(AddressList::Watcher),
+        addr_raw.as_slice(),
+    )?
+    .unwrap_or_default();
+
+    to_binary(&QueryAnswer::ListWatched { watched })
+}
+
+/// Returns QueryResult totaling an address' active bids per bid token
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `address` - a reference to the address whose bids should be totaled
+/// * `viewing_key` - String holding the address' viewing key
+pub fn try_bid_totals<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    address: &HumanAddr,
+    viewing_key: String,
+) -> QueryResult {
+    let addr_raw = &deps.api.canonical_address(address)?;
+    if !is_key_valid(&deps.storage, addr_raw, viewing_key)? {
+        return to_binary(&QueryAnswer::ViewingKeyError {
+            error: "Wrong viewing key for this address or viewing key not set".to_string(),
+        });
+    }
+    // bids in auctions that have closed were either paid or returned, so only count active ones
+    let mut active: HashSet<u32> = may_load(&deps.storage, ACTIVE_KEY)?.unwrap_or_default();
+    let read_bidder = ReadonlyPrefixedStorage::new(AddressList::Bidder.prefix(), &deps.storage);
+    let (my_active, _) = filter_only_active(&read_bidder, addr_raw, &mut active)?;
+    let read_info = ReadonlyPrefixedStorage::new(PREFIX_ACTIVE_INFO, &deps.storage);
+    let read_bids = ReadonlyPrefixedStorage::multilevel(
+        &[PREFIX_BID_AMOUNTS, addr_raw.as_slice()],
+        &deps.storage,
+    );
+    let mut sums: BTreeMap<Vec<u8>, (u128, u32)> = BTreeMap::new();
+    for index in my_active.iter() {
+        // sealed bids are not counted until they are revealed
+        let amount: u128 = match may_load(&read_bids, &index.to_le_bytes())? {
+            Some(amt) => amt,
+            None => continue,
+        };
+        if let Some(info) = may_load::<StoreAuctionInfo, _>(&read_info, &index.to_le_bytes())? {
+            let sum = sums.entry(info.bid_token).or_insert((0, 0));
+            sum.0 = checked_add(sum.0, amount)?;
+            sum.1 += 1;
+        }
+    }
+    let mut totals = Vec::new();
+    for (token, (amount, auctions)) in sums.into_iter() {
+        if let Some(symdec) = load_symdec(&deps.storage, &token)? {
+            totals.push(BidTokenTotal {
+                token: Binary(token),
+                symbol: symdec.symbol,
+                decimals: symdec.decimals,
+                amount: Uint128(amount),
+                auctions,
+            });
+        }
+    }
+    totals.sort_by(|a, b| a.symbol.cmp(&b.symbol));
+
+    to_binary(&QueryAnswer::BidTotals { totals })
+}
+
+/// Returns QueryResult listing the closed auctions
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `before` - optional index of the most recently closed auction you do not want to display
+/// * `page_size` - optional number of auctions to display
+pub fn try_list_closed<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    before: Option<u32>,
+    page_size: Option<u32>,
+) -> QueryResult {
+    let read_order = ReadonlyPrefixedStorage::new(PREFIX_CLOSED_ORDER, &deps.storage);
+    let may_read_order = AppendStore::<u32, _>::attach(&read_order);
+    let mut closed_vec = Vec::new();
+    if let Some(order) = may_read_order.and_then(|r| r.ok()) {
+        // start iterating from the last close or before the given auction
+        let len = order.len();
+        let pos = match before {
+            Some(index) => {
+                let read_pos = ReadonlyPrefixedStorage::new(PREFIX_CLOSED_POSITIONS, &deps.storage);
+                may_load::<u32, _>(&read_pos, &index.to_le_bytes())?.ok_or_else(|| {
+                    StdError::generic_err(format!("Auction {} has not closed", index))
+                })?
+            }
+            None => len,
+        };
+        let quant = page_size.unwrap_or(200) as usize;
+        let pruned: u32 = may_load(&deps.storage, PRUNED_KEY)?.unwrap_or(0);
+        let read_closed = ReadonlyPrefixedStorage::new(PREFIX_CLOSED_INFO, &deps.storage);
+        // grab backwards from the starting point, stopping at the pruned records
+        for index in (pruned..pos).rev().map(|p| order.get_at(p)).take(quant) {
+            let index = index?;
+            let load_info: Option<StoreClosedAuctionInfo> =
+                may_load(&read_closed, &index.to_le_bytes())?;
+            if let Some(info) = load_info {
+                if let Some((sell_symdec, bid_symdec)) =
+                    load_pair(&deps.storage, &info.sell_token, &info.bid_token)?
+                {
+                    let pair = format!("{}-{}", sell_symdec.symbol, bid_symdec.symbol);
+                    let winning_unit_price = info.winning_unit_price(sell_symdec.decimals);
+                    closed_vec.push(ClosedAuctionInfo {
+                        index: Some(index),
+                        address: deps.api.human_address(&info.address)?,
+                        label: info.label,
+                        pair,
+                        sell_amount: Uint128(info.sell_amount),
+                        sell_decimals: sell_symdec.decimals,
+                        winning_bid: info.winning_bid.map(Uint128),
+                        winning_unit_price,
+                        bid_decimals: info.winning_bid.map(|_a| bid_symdec.decimals),
+                        timestamp: info.timestamp,
+                        code_id: info.code_id,
+                        nickname: None,
+                        amount_paid: None,
+                        amount_won: None,
+                    });
+                }
+            }
+        }
+    }
+    let closed = if closed_vec.is_empty() {
+        None
+    } else {
+        Some(closed_vec)
+    };
+    to_binary(&QueryAnswer::ListClosedAuctions { closed })
+}
+
+/// Returns QueryResult displaying the instantiation message a CreateAuction message would send,
+/// after making every check the factory would make when creating the auction
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `seller` - a reference to the address that would create the auction
+/// * `current_time` - current time in seconds since epoch 01/01/1970
+/// * `create_auction` - the CreateAuction message to preview
+pub fn try_preview_create_auction<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    seller: &HumanAddr,
+    current_time: u64,
+    create_auction: HandleMsg,
+) -> QueryResult {
+    let (auction, nickname, tags, referrer) = match create_auction {
+        HandleMsg::CreateAuction {
+            label,
+            sell_contract,
+            bid_contract,
+            sell_amount,
+            minimum_bid,
+            ends_at,
+            description,
+            descriptions,
+            nickname,
+            tags,
+            bundle,
+            deposit,
+            consign_by,
+            buy_now_price,
+            no_self_bid,
+            only_increasing_rebids,
+            tie_break,
+            allowance_bids,
+            message_board,
+            min_bid_decay,
+            seller_confirmation,
+            unit_price_bids,
+            max_bidders,
+            min_bidders,
+            reveal_window,
+            settlement,
+            funding_target,
+            referrer,
+        } => (
+            NewAuction {
+                seller: seller.clone(),
+                label,
+                sell_contract,
+                bid_contract,
+                sell_amount,
+                minimum_bid,
+                ends_at,
+                description,
+                descriptions,
+                bundle,
+                deposit,
+                consign_by,
+                buy_now_price,
+                no_self_bid,
+                only_increasing_rebids,
+                tie_break,
+                allowance_bids,
+                message_board,
+                min_bid_decay,
+                seller_confirmation,
+                unit_price_bids,
+                max_bidders,
+                min_bidders,
+                reveal_window,
+                settlement,
+                funding_target,
+            },
+            nickname,
+            tags,
+            referrer,
+        ),
+        _ => {
+            return Err(StdError::generic_err(
+                "Only a create_auction message can be previewed",
+            ))
+        }
+    };
+    check_new_auction(
+        &deps.storage,
+        current_time,
+        &auction.sell_contract,
+        &auction.bid_contract,
+        auction.sell_amount,
+        auction.ends_at,
+        auction.bundle.len(),
+    )?;
+    check_seller(
+        &deps.storage,
+        &deps.api,
+        seller,
+        nickname.as_ref(),
+        referrer.as_ref(),
+    )?;
+    check_tags(tags)?;
+    let factory: ContractInfo = load(&deps.storage, FACTORY_INFO_KEY)?;
+    let prepared = prepare_auction(deps, factory, auction, true)?;
+
+    to_binary(&QueryAnswer::PreviewCreateAuction {
+        index: prepared.init.index,
+        label: prepared.init.label.clone(),
+        code_id: prepared.config.version.code_id,
+        code_hash: prepared.config.version.code_hash,
+        init_msg: to_binary(&prepared.init)?,
+        creation_fee: prepared.config.creation_fee.map(|f| Uint128(f.amount)),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::contract::{init, query, PREFIX_SYMDECS};
+    use crate::lists::add_to_ending_index;
+    use crate::msg::{AuctionContractInfo, InitMsg, QueryMsg};
+    use crate::state::{save, TokenSymDec};
+    use cosmwasm_std::testing::{mock_dependencies, mock_env, MockApi, MockQuerier, MockStorage};
+    use cosmwasm_std::{from_binary, InitResponse, StdResult};
+    use cosmwasm_storage::PrefixedStorage;
+    use secret_toolkit::storage::AppendStoreMut;
+
+    fn init_helper() -> (
+        StdResult<InitResponse>,
+        Extern<MockStorage, MockApi, MockQuerier>,
+    ) {
+        let mut deps = mock_dependencies(20, &[]);
+        let env = mock_env("admin", &[]);
+        let init_msg = InitMsg {
+            entropy: "entropy".to_string(),
+            auction_contract: AuctionContractInfo {
+                code_id: 1,
+                code_hash: "auctionhash".to_string(),
+            },
+            block_size: None,
+        };
+        (init(&mut deps, env, init_msg), deps)
+    }
+
+    // adds active auctions closing at the given times
+    fn add_auctions(deps: &mut Extern<MockStorage, MockApi, MockQuerier>, ends: &[(u32, u64)]) {
+        let mut symdec_store = PrefixedStorage::new(PREFIX_SYMDECS, &mut deps.storage);
+        let sell = TokenSymDec {
+            symbol: "SELL".to_string(),
+            decimals: 6,
+        };
+        save(&mut symdec_store, b"sell", &sell).unwrap();
+        let bid = TokenSymDec {
+            symbol: "BID".to_string(),
+            decimals: 6,
+        };
+        save(&mut symdec_store, b"bid", &bid).unwrap();
+        let mut active: HashSet<u32> = load(&deps.storage, ACTIVE_KEY).unwrap();
+        for (index, ends_at) in ends.iter() {
+            let info = StoreAuctionInfo {
+                address: deps
+                    .api
+                    .canonical_address(&HumanAddr(format!("auction{}", index)))
+                    .unwrap(),
+                code_id: 1,
+                label: format!("auction{}", index),
+                sell_token: b"sell".to_vec(),
+                bid_token: b"bid".to_vec(),
+                sell_amount: 10,
+                minimum_bid: 10,
+                ends_at: *ends_at,
+            };
+            let mut info_store = PrefixedStorage::new(PREFIX_ACTIVE_INFO, &mut deps.storage);
+            save(&mut info_store, &index.to_le_bytes(), &info).unwrap();
+            add_to_ending_index(&mut deps.storage, *ends_at, *index).unwrap();
+            active.insert(*index);
+        }
+        save(&mut deps.storage, ACTIVE_KEY, &active).unwrap();
+    }
+
+    fn ending_soon(
+        deps: &Extern<MockStorage, MockApi, MockQuerier>,
+        current_time: u64,
+        within_seconds: u64,
+        page_size: Option<u32>,
+    ) -> Vec<u32> {
+        let query_msg = QueryMsg::ListEndingSoon {
+            current_time,
+            within_seconds,
+            page_size,
+        };
+        match from_binary(&query(deps, query_msg).unwrap()).unwrap() {
+            QueryAnswer::ListEndingSoon { auctions } => auctions.iter().map(|a| a.index).collect(),
+            _ => panic!("Unexpected"),
+        }
+    }
+
+    #[test]
+    fn test_list_active() {
+        let (init_result, mut deps) = init_helper();
+        assert!(
+            init_result.is_ok(),
+            "Init failed: {}",
+            init_result.err().unwrap()
+        );
+        let active = match from_binary(&try_list_active(&deps).unwrap()).unwrap() {
+            QueryAnswer::ListActiveAuctions { active } => active,
+            _ => panic!("Unexpected"),
+        };
+        assert!(active.is_none());
+
+        add_auctions(&mut deps, &[(1, 500)]);
+        let active = match from_binary(&try_list_active(&deps).unwrap()).unwrap() {
+            QueryAnswer::ListActiveAuctions { active } => active.unwrap(),
+            _ => panic!("Unexpected"),
+        };
+        assert_eq!(active.len(), 1);
+        assert_eq!(active[0].index, 1);
+        assert_eq!(active[0].pair, "SELL-BID".to_string());
+        assert_eq!(active[0].address, HumanAddr("auction1".to_string()));
+    }
+
+    #[test]
+    fn test_list_ending_soon() {
+        let (init_result, mut deps) = init_helper();
+        assert!(
+            init_result.is_ok(),
+            "Init failed: {}",
+            init_result.err().unwrap()
+        );
+        add_auctions(&mut deps, &[(1, 5000), (2, 500), (3, 100_000)]);
+
+        // sorted by closing time
+        assert_eq!(ending_soon(&deps, 0, 6000, None), vec![2, 1]);
+        assert_eq!(ending_soon(&deps, 0, 6000, Some(1)), vec![2]);
+        // auctions that already closed are not listed
+        assert_eq!(ending_soon(&deps, 1000, 6000, None), vec![1]);
+        assert!(ending_soon(&deps, 200_000, 6000, None).is_empty());
+    }
+
+    #[test]
+    fn test_list_closed() {
+        let (init_result, mut deps) = init_helper();
+        assert!(
+            init_result.is_ok(),
+            "Init failed: {}",
+            init_result.err().unwrap()
+        );
+        add_auctions(&mut deps, &[]);
+        // auctions close in a different order than they were created
+        for (position, index) in [5u32, 2, 9].iter().enumerate() {
+            let info = StoreClosedAuctionInfo {
+                address: deps
+                    .api
+                    .canonical_address(&HumanAddr(format!("auction{}", index)))
+                    .unwrap(),
+                code_id: 1,
+                label: format!("auction{}", index),
+                sell_token: b"sell".to_vec(),
+                bid_token: b"bid".to_vec(),
+                sell_amount: 10,
+                winning_bid: None,
+                winners: Vec::new(),
+                timestamp: 1000 + position as u64,
+            };
+            let mut info_store = PrefixedStorage::new(PREFIX_CLOSED_INFO, &mut deps.storage);
+            save(&mut info_store, &index.to_le_bytes(), &info).unwrap();
+            let mut order_store = PrefixedStorage::new(PREFIX_CLOSED_ORDER, &mut deps.storage);
+            let mut order = AppendStoreMut::attach_or_create(&mut order_store).unwrap();
+            order.push(index).unwrap();
+            let mut pos_store = PrefixedStorage::new(PREFIX_CLOSED_POSITIONS, &mut deps.storage);
+            save(&mut pos_store, &index.to_le_bytes(), &(position as u32)).unwrap();
+        }
+        let list_closed = |before: Option<u32>| -> Vec<u32> {
+            let query_msg = QueryMsg::ListClosedAuctions {
+                before,
+                page_size: None,
+            };
+            match from_binary(&query(&deps, query_msg).unwrap()).unwrap() {
+                QueryAnswer::ListClosedAuctions { closed } => closed
+                    .unwrap_or_default()
+                    .iter()
+                    .map(|c| c.index.unwrap())
+                    .collect(),
+                _ => panic!("Unexpected"),
+            }
+        };
+
+        // listed most recent first by their factory indices
+        assert_eq!(list_closed(None), vec![9, 2, 5]);
+        assert_eq!(list_closed(Some(2)), vec![5]);
+        assert!(list_closed(Some(5)).is_empty());
+        // paging from an auction that has not closed is an error
+        let query_msg = QueryMsg::ListClosedAuctions {
+            before: Some(3),
+            page_size: None,
+        };
+        assert!(query(&deps, query_msg).is_err());
+    }
+
+    #[test]
+    fn test_list_series() {
+        let (_init_result, mut deps) = init_helper();
+        let contract = |address: &str| ContractInfo {
+            code_hash: "hash".to_string(),
+            address: HumanAddr(address.to_string()),
+            token_id: None,
+        };
+        for id in 0u32..3 {
+            let series = Series {
+                seller: deps
+                    .api
+                    .canonical_address(&HumanAddr("alice".to_string()))
+                    .unwrap(),
+                label: format!("series{}", id),
+                sell_contract: contract("selladdr"),
+                bid_contract: contract("bidaddr"),
+                sell_amount: 10,
+                minimum_bid: 5,
+                description: None,
+                interval: 100,
+                rounds: 2,
+                history: Vec::new(),
+                cancelled: false,
+            };
+            let mut series_store = PrefixedStorage::new(PREFIX_SERIES, &mut deps.storage);
+            save(&mut series_store, &id.to_le_bytes(), &series).unwrap();
+        }
+        save(&mut deps.storage, SERIES_COUNT_KEY, &3u32).unwrap();
+        let list_series = |start: Option<u32>, limit: Option<u32>| -> (Vec<u32>, Option<u32>) {
+            match from_binary(&query(&deps, QueryMsg::ListSeries { start, limit }).unwrap())
+                .unwrap()
+            {
+                QueryAnswer::ListSeries { series, next } => {
+                    (series.iter().map(|s| s.id).collect(), next)
+                }
+                _ => panic!("Unexpected"),
+            }
+        };
+
+        // series are listed a page at a time
+        assert_eq!(list_series(None, Some(2)), (vec![0, 1], Some(2)));
+        assert_eq!(list_series(Some(2), Some(2)), (vec![2], None));
+        assert_eq!(list_series(None, None), (vec![0, 1, 2], None));
+        assert_eq!(list_series(Some(5), None), (vec![], None));
+    }
+}
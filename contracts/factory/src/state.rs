@@ -7,7 +7,20 @@ use cosmwasm_std::{CanonicalAddr, ReadonlyStorage, StdError, StdResult, Storage}
 
 use secret_toolkit::serialization::{Bincode2, Serde};
 
-use crate::msg::AuctionContractInfo;
+use crate::msg::{
+    Asset, AuctionContractInfo, ContractInfo, FactoryStatus, TokenAddress, TreasuryAction,
+};
+
+/// progress of deprecating the active auctions of earlier auction contract versions
+#[derive(Serialize, Deserialize)]
+pub struct PendingDeprecation {
+    /// code id of the current version, whose auctions are not deprecated
+    pub code_id: u64,
+    /// optional warning sent to each active auction of an earlier version
+    pub notice: Option<String>,
+    /// index of the last active auction checked
+    pub after: Option<u32>,
+}
 
 /// symbol and number of decimal places of a token
 #[derive(Serialize, Deserialize)]
@@ -21,6 +34,43 @@ pub struct TokenSymDec {
 /// grouping the data primarily used when creating a new auction
 #[derive(Serialize, Deserialize)]
 pub struct Config {
+    /// code hash and address of the auction contract
+    pub version: AuctionContractInfo,
+    /// unique id to give created auction
+    pub index: u32,
+    /// parts of the factory the admin has stopped
+    pub status: FactoryStatus,
+    /// code ids of the deprecated auction contract versions
+    pub deprecated: Vec<u64>,
+    /// code ids of the revoked auction contract versions, whose callbacks are ignored
+    pub revoked: Vec<u64>,
+    /// address of the factory admin
+    pub admin: CanonicalAddr,
+    /// smallest sell amount (in the sell token's smallest denomination) an auction may have
+    pub min_sell_amount: u128,
+    /// optional limit on how many whole sell tokens' worth of bid tokens the minimum bid may be
+    pub max_bid_multiple: Option<u128>,
+    /// block size, in bytes, that the factory and its auctions pad responses to
+    pub block_size: u16,
+    /// optional fee charged to create an auction
+    pub creation_fee: Option<StoredCreationFee>,
+    /// share of the creation fee paid to referrers in basis points
+    pub referral_share: u16,
+}
+
+/// fee charged to create an auction
+#[derive(Serialize, Deserialize)]
+pub struct StoredCreationFee {
+    /// amount of uscrt that must be sent with CreateAuction
+    pub amount: u128,
+    /// optional address the fee is forwarded to instead of the factory's treasury
+    pub treasury: Option<CanonicalAddr>,
+}
+
+/// config format of the original factory, which also held the token index map.  It is kept so
+/// an older factory's config can be upgraded.  Field order must match the format it was saved with
+#[derive(Serialize, Deserialize)]
+pub struct LegacyConfig {
     /// code hash and address of the auction contract
     pub version: AuctionContractInfo,
     /// map token contract address to symdec list index
@@ -33,6 +83,127 @@ pub struct Config {
     pub admin: CanonicalAddr,
 }
 
+/// treasury holdings of an asset
+#[derive(Serialize, Deserialize)]
+pub struct TreasuryAccount {
+    /// the asset
+    pub asset: Asset,
+    /// amount currently held
+    pub balance: u128,
+    /// total amount ever received
+    pub received: u128,
+    /// total amount ever withdrawn
+    pub withdrawn: u128,
+}
+
+/// share of its creation fee an active auction reserved in the treasury for referrals
+#[derive(Serialize, Deserialize)]
+pub struct AuctionFee {
+    /// uscrt amount of the fee reserved in the treasury for referral payouts
+    pub reserved: u128,
+    /// optional address that referred the seller
+    pub referrer: Option<CanonicalAddr>,
+}
+
+/// referral fees an address has earned
+#[derive(Serialize, Deserialize, Default)]
+pub struct ReferralEarnings {
+    /// total uscrt earned
+    pub earned: u128,
+    /// number of referral payouts received
+    pub payouts: u32,
+}
+
+/// a change to the treasury balance of an asset
+#[derive(Serialize, Deserialize)]
+pub struct StoredTreasuryRecord {
+    /// reason for the change
+    pub action: TreasuryAction,
+    /// amount received or withdrawn
+    pub amount: u128,
+    /// address that paid the fee or received the withdrawal
+    pub address: CanonicalAddr,
+    /// time of the change in seconds since epoch 01/01/1970
+    pub time: u64,
+}
+
+/// an auction series
+#[derive(Serialize, Deserialize)]
+pub struct Series {
+    /// seller of the series
+    pub seller: CanonicalAddr,
+    /// label of the series
+    pub label: String,
+    /// sell contract code hash and address
+    pub sell_contract: ContractInfo,
+    /// bid contract code hash and address
+    pub bid_contract: ContractInfo,
+    /// amount of tokens sold in each round
+    pub sell_amount: u128,
+    /// minimum bid of each round
+    pub minimum_bid: u128,
+    /// Optional free-form description of the auctions
+    pub description: Option<String>,
+    /// number of seconds each round lasts
+    pub interval: u64,
+    /// number of rounds in the series
+    pub rounds: u32,
+    /// rounds that have been created so far
+    pub history: Vec<SeriesRound>,
+    /// true if the seller cancelled the series
+    pub cancelled: bool,
+}
+
+/// a round of an auction series that has been created
+#[derive(Serialize, Deserialize)]
+pub struct SeriesRound {
+    /// index of the round's auction
+    pub index: u32,
+    /// address of the round's auction once it has registered
+    pub address: Option<CanonicalAddr>,
+    /// closing time of the round
+    pub ends_at: u64,
+    /// true if the round has closed
+    pub closed: bool,
+    /// winning bid if the round closed with a sale
+    pub winning_bid: Option<u128>,
+}
+
+/// a contract subscribed to the closes of a token pair's auctions
+#[derive(Serialize, Deserialize)]
+pub struct CloseSubscriber {
+    /// subscriber's address
+    pub address: CanonicalAddr,
+    /// true if the subscriber is enabled
+    pub enabled: bool,
+}
+
+/// a logged close of a subscribed token pair's auction
+#[derive(Serialize, Deserialize)]
+pub struct StorePairClose {
+    /// factory index of the auction
+    pub index: u32,
+    /// auction address
+    pub auction: CanonicalAddr,
+    /// amount of the sell token in the lot
+    pub sell_amount: u128,
+    /// winning bid if the lot sold
+    pub winning_bid: Option<u128>,
+    /// time the auction closed in seconds since epoch 01/01/1970
+    pub timestamp: u64,
+}
+
+/// the contracts subscribed to the closes of a token pair's auctions
+#[derive(Serialize, Deserialize)]
+pub struct PairSubscribers {
+    /// token being sold
+    pub sell_token: TokenAddress,
+    /// token being bid
+    pub bid_token: TokenAddress,
+    /// subscribers in the order they were added
+    pub subscribers: Vec<CloseSubscriber>,
+}
+
 /// Returns StdResult<()> resulting from saving an item to storage
 ///
 /// # Arguments
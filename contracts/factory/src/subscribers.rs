@@ -0,0 +1,345 @@
+use cosmwasm_std::{
+    to_binary, Api, CanonicalAddr, Env, Extern, HandleResponse, HandleResult, HumanAddr, Querier,
+    QueryResult, ReadonlyStorage, StdError, StdResult, Storage, Uint128,
+};
+
+use cosmwasm_storage::{PrefixedStorage, ReadonlyPrefixedStorage};
+
+use secret_toolkit::storage::{AppendStore, AppendStoreMut};
+
+use crate::auctions::token_key;
+use crate::contract::check_admin;
+use crate::msg::{
+    CloseSubscriberInfo, HandleAnswer, PairClose, QueryAnswer, ResponseStatus::Success,
+    TokenAddress,
+};
+use crate::state::{may_load, remove, save, CloseSubscriber, PairSubscribers, StorePairClose};
+
+/// prefix for storage of the contracts subscribed to each token pair's closes
+pub const PREFIX_PAIR_SUBSCRIBERS: &[u8] = b"pairsubscribers";
+/// prefix for storage of the log of each subscribed token pair's closes
+pub const PREFIX_PAIR_CLOSES: &[u8] = b"paircloses";
+/// maximum number of contracts that can subscribe to one token pair
+pub const MAX_PAIR_SUBSCRIBERS: usize = 5;
+/// maximum number of closes the pair closes query can list at once
+pub const MAX_PAIR_CLOSES: u32 = 100;
+
+/// Returns StdResult<Vec<u8>>
+///
+/// creates the key a token pair's subscribers are stored under
+///
+/// # Arguments
+///
+/// * `api` - a reference to the Api used to convert human and canonical addresses
+/// * `sell_token` - a reference to the address of the token being sold
+/// * `bid_token` - a reference to the address of the token being bid
+fn pair_key<A: Api>(
+    api: &A,
+    sell_token: &TokenAddress,
+    bid_token: &TokenAddress,
+) -> StdResult<Vec<u8>> {
+    let sell_key = token_key(
+        &api.canonical_address(&sell_token.address)?,
+        sell_token.token_id.as_ref(),
+    );
+    let bid_key = token_key(
+        &api.canonical_address(&bid_token.address)?,
+        bid_token.token_id.as_ref(),
+    );
+    Ok(stored_pair_key(&sell_key, &bid_key))
+}
+
+/// Returns Vec<u8>
+///
+/// creates the key a token pair's subscribers are stored under from the pair's token keys.  The
+/// sell key is length-prefixed so different pairs can never produce the same key
+///
+/// # Arguments
+///
+/// * `sell_key` - the sell token's key
+/// * `bid_key` - the bid token's key
+fn stored_pair_key(sell_key: &[u8], bid_key: &[u8]) -> Vec<u8> {
+    let mut key = (sell_key.len() as u16).to_be_bytes().to_vec();
+    key.extend_from_slice(sell_key);
+    key.extend_from_slice(bid_key);
+    key
+}
+
+/// Returns HandleResult
+///
+/// allows the admin to subscribe a contract to the closes of a token pair's auctions.  The
+/// factory logs the closes of a pair while it has an enabled subscriber, and the subscribers read
+/// the log with the pair closes query
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `subscriber` - a reference to the address of the subscribing contract
+/// * `sell_token` - address of the token being sold
+/// * `bid_token` - address of the token being bid
+pub fn try_add_close_subscriber<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    subscriber: &HumanAddr,
+    sell_token: TokenAddress,
+    bid_token: TokenAddress,
+) -> HandleResult {
+    check_admin(deps, &env)?;
+    let key = pair_key(&deps.api, &sell_token, &bid_token)?;
+    let subscriber_raw = deps.api.canonical_address(subscriber)?;
+    let mut pair_store = PrefixedStorage::new(PREFIX_PAIR_SUBSCRIBERS, &mut deps.storage);
+    let mut pair: PairSubscribers =
+        may_load(&pair_store, &key)?.unwrap_or_else(|| PairSubscribers {
+            sell_token,
+            bid_token,
+            subscribers: Vec::new(),
+        });
+    if pair.subscribers.iter().any(|s| s.address == subscriber_raw) {
+        return Err(StdError::generic_err(format!(
+            "{} is already subscribed to this token pair",
+            subscriber
+        )));
+    }
+    if pair.subscribers.len() >= MAX_PAIR_SUBSCRIBERS {
+        return Err(StdError::generic_err(format!(
+            "A token pair can not have more than {} subscribers",
+            MAX_PAIR_SUBSCRIBERS
+        )));
+    }
+    pair.subscribers.push(CloseSubscriber {
+        address: subscriber_raw,
+        enabled: true,
+    });
+    save(&mut pair_store, &key, &pair)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: Some(to_binary(&HandleAnswer::Status {
+            status: Success,
+            message: None,
+        })?),
+    })
+}
+
+/// Returns HandleResult
+///
+/// allows the admin to unsubscribe a contract from the closes of a token pair's auctions
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `subscriber` - a reference to the address of the subscribed contract
+/// * `sell_token` - a reference to the address of the token being sold
+/// * `bid_token` - a reference to the address of the token being bid
+pub fn try_remove_close_subscriber<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    subscriber: &HumanAddr,
+    sell_token: &TokenAddress,
+    bid_token: &TokenAddress,
+) -> HandleResult {
+    check_admin(deps, &env)?;
+    let key = pair_key(&deps.api, sell_token, bid_token)?;
+    let subscriber_raw = deps.api.canonical_address(subscriber)?;
+    let mut pair_store = PrefixedStorage::new(PREFIX_PAIR_SUBSCRIBERS, &mut deps.storage);
+    let mut pair = load_subscribed_pair(&pair_store, &key, subscriber, &subscriber_raw)?;
+    pair.subscribers.retain(|s| s.address != subscriber_raw);
+    if pair.subscribers.is_empty() {
+        remove(&mut pair_store, &key);
+    } else {
+        save(&mut pair_store, &key, &pair)?;
+    }
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: Some(to_binary(&HandleAnswer::Status {
+            status: Success,
+            message: None,
+        })?),
+    })
+}
+
+/// Returns HandleResult
+///
+/// allows the admin to disable or enable a subscribed contract.  The pair's closes are only
+/// logged while it has an enabled subscriber
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `subscriber` - a reference to the address of the subscribed contract
+/// * `sell_token` - a reference to the address of the token being sold
+/// * `bid_token` - a reference to the address of the token being bid
+/// * `enabled` - true if the subscriber should be enabled
+pub fn try_set_close_subscriber_status<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    subscriber: &HumanAddr,
+    sell_token: &TokenAddress,
+    bid_token: &TokenAddress,
+    enabled: bool,
+) -> HandleResult {
+    check_admin(deps, &env)?;
+    let key = pair_key(&deps.api, sell_token, bid_token)?;
+    let subscriber_raw = deps.api.canonical_address(subscriber)?;
+    let mut pair_store = PrefixedStorage::new(PREFIX_PAIR_SUBSCRIBERS, &mut deps.storage);
+    let mut pair = load_subscribed_pair(&pair_store, &key, subscriber, &subscriber_raw)?;
+    for sub in pair
+        .subscribers
+        .iter_mut()
+        .filter(|s| s.address == subscriber_raw)
+    {
+        sub.enabled = enabled;
+    }
+    save(&mut pair_store, &key, &pair)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: Some(to_binary(&HandleAnswer::Status {
+            status: Success,
+            message: None,
+        })?),
+    })
+}
+
+/// Returns StdResult<PairSubscribers>
+///
+/// loads a token pair's subscribers, and returns an error if the contract is not one of them
+///
+/// # Arguments
+///
+/// * `storage` - a reference to the pair subscribers' storage subspace
+/// * `key` - the pair's storage key
+/// * `subscriber` - a reference to the address of the subscribed contract
+/// * `subscriber_raw` - a reference to the canonical address of the subscribed contract
+fn load_subscribed_pair<S: ReadonlyStorage>(
+    storage: &S,
+    key: &[u8],
+    subscriber: &HumanAddr,
+    subscriber_raw: &CanonicalAddr,
+) -> StdResult<PairSubscribers> {
+    let not_subscribed = || {
+        StdError::generic_err(format!(
+            "{} is not subscribed to this token pair",
+            subscriber
+        ))
+    };
+    let pair: PairSubscribers = may_load(storage, key)?.ok_or_else(not_subscribed)?;
+    if pair
+        .subscribers
+        .iter()
+        .all(|s| s.address != *subscriber_raw)
+    {
+        return Err(not_subscribed());
+    }
+    Ok(pair)
+}
+
+/// Returns StdResult<()>
+///
+/// adds a closed auction to its token pair's log of closes if the pair has an enabled
+/// subscriber.  Nothing is sent to the subscribers, so a close costs the same no matter how many
+/// contracts subscribed
+///
+/// # Arguments
+///
+/// * `storage` - a mutable reference to the contract's storage
+/// * `sell_key` - the key of the auction's sell token
+/// * `bid_key` - the key of the auction's bid token
+/// * `close` - a reference to the record of the close
+pub fn record_pair_close<S: Storage>(
+    storage: &mut S,
+    sell_key: &[u8],
+    bid_key: &[u8],
+    close: &StorePairClose,
+) -> StdResult<()> {
+    let key = stored_pair_key(sell_key, bid_key);
+    let pair_store = ReadonlyPrefixedStorage::new(PREFIX_PAIR_SUBSCRIBERS, storage);
+    let may_pair: Option<PairSubscribers> = may_load(&pair_store, &key)?;
+    if may_pair.map_or(false, |p| p.subscribers.iter().any(|s| s.enabled)) {
+        let mut close_store = PrefixedStorage::multilevel(&[PREFIX_PAIR_CLOSES, &key], storage);
+        let mut closes = AppendStoreMut::attach_or_create(&mut close_store)?;
+        closes.push(close)?;
+    }
+    Ok(())
+}
+
+/// Returns QueryResult listing a token pair's logged closes in the order they happened,
+/// starting from a cursor, and the cursor to resume from
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `sell_token` - a reference to the address of the token being sold
+/// * `bid_token` - a reference to the address of the token being bid
+/// * `since` - optional number of logged closes to skip, usually the cursor of the last call
+/// * `limit` - optional number of closes to list
+pub fn try_list_pair_closes<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    sell_token: &TokenAddress,
+    bid_token: &TokenAddress,
+    since: Option<u32>,
+    limit: Option<u32>,
+) -> QueryResult {
+    let key = pair_key(&deps.api, sell_token, bid_token)?;
+    let quant = limit.unwrap_or(10).min(MAX_PAIR_CLOSES);
+    let mut next = since.unwrap_or(0);
+    let mut closes = Vec::new();
+    let close_store =
+        ReadonlyPrefixedStorage::multilevel(&[PREFIX_PAIR_CLOSES, &key], &deps.storage);
+    if let Some(log) = AppendStore::<StorePairClose, _>::attach(&close_store) {
+        let log = log?;
+        let end = next.saturating_add(quant).min(log.len());
+        for position in next..end {
+            let close = log.get_at(position)?;
+            closes.push(PairClose {
+                index: close.index,
+                auction_address: deps.api.human_address(&close.auction)?,
+                sell_amount: Uint128(close.sell_amount),
+                winning_bid: close.winning_bid.map(Uint128),
+                timestamp: close.timestamp,
+            });
+        }
+        next = next.max(end);
+    }
+
+    to_binary(&QueryAnswer::PairCloses { closes, next })
+}
+
+/// Returns QueryResult listing the contracts subscribed to the closes of a token pair's
+/// auctions
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `sell_token` - a reference to the address of the token being sold
+/// * `bid_token` - a reference to the address of the token being bid
+pub fn try_list_close_subscribers<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    sell_token: &TokenAddress,
+    bid_token: &TokenAddress,
+) -> QueryResult {
+    let key = pair_key(&deps.api, sell_token, bid_token)?;
+    let pair_store = ReadonlyPrefixedStorage::new(PREFIX_PAIR_SUBSCRIBERS, &deps.storage);
+    let subscribers = match may_load::<PairSubscribers, _>(&pair_store, &key)? {
+        Some(pair) => pair
+            .subscribers
+            .into_iter()
+            .map(|s| {
+                Ok(CloseSubscriberInfo {
+                    address: deps.api.human_address(&s.address)?,
+                    enabled: s.enabled,
+                })
+            })
+            .collect::<StdResult<Vec<CloseSubscriberInfo>>>()?,
+        None => Vec::new(),
+    };
+
+    to_binary(&QueryAnswer::CloseSubscribers { subscribers })
+}
@@ -0,0 +1,520 @@
+use cosmwasm_std::{
+    to_binary, Api, BankMsg, CanonicalAddr, Coin, CosmosMsg, Env, Extern, HandleResponse,
+    HandleResult, HumanAddr, Querier, QueryResult, ReadonlyStorage, StdError, StdResult, Storage,
+    Uint128,
+};
+
+use cosmwasm_storage::{PrefixedStorage, ReadonlyPrefixedStorage};
+
+use secret_toolkit::storage::{AppendStore, AppendStoreMut};
+
+use auction_common::math::split;
+
+use crate::auctions::load_config;
+use crate::contract::CONFIG_KEY;
+use crate::msg::{
+    Asset, HandleAnswer, QueryAnswer, ResponseStatus::Success, TreasuryAction, TreasuryBalance,
+    TreasuryRecord,
+};
+use crate::state::{
+    load, may_load, save, AuctionFee, Config, ReferralEarnings, StoredTreasuryRecord,
+    TreasuryAccount,
+};
+use crate::viewing_keys::is_key_valid;
+
+/// prefix for storage of the treasury's holdings of each asset
+pub const PREFIX_TREASURY: &[u8] = b"treasury";
+/// prefix for storage of the changes to the treasury balance of each asset
+pub const PREFIX_TREASURY_RECORDS: &[u8] = b"treasuryrecords";
+/// storage key for the list of assets the treasury has received
+pub const TREASURY_ASSETS_KEY: &[u8] = b"treasuryassets";
+/// prefix for storage of the share of its creation fee each active auction reserved for referrals
+pub const PREFIX_AUCTION_FEES: &[u8] = b"auctionfees";
+/// storage key for the total uscrt the treasury holds in reserve for active auctions' referral
+/// payouts, which can not be withdrawn
+pub const REFERRAL_RESERVE_KEY: &[u8] = b"referralreserve";
+/// prefix for storage of the referral fees each address has earned
+pub const PREFIX_REFERRAL_EARNINGS: &[u8] = b"referralearnings";
+/// number of records listed if no page size is given
+pub const DEFAULT_PAGE_SIZE: u32 = 200;
+/// denomination of the creation fee
+pub const FEE_DENOM: &str = "uscrt";
+/// number of basis points in a whole
+pub const MAX_BASIS_POINTS: u16 = 10000;
+
+/// Returns Vec<u8>
+///
+/// creates the key used to store an asset's treasury account and records
+///
+/// # Arguments
+///
+/// * `asset` - a reference to the asset
+pub fn asset_key(asset: &Asset) -> Vec<u8> {
+    match asset {
+        Asset::Native { denom } => [b"n".to_vec(), denom.as_bytes().to_vec()].concat(),
+    }
+}
+
+/// Returns StdResult<()>
+///
+/// records a change to the treasury balance of an asset
+///
+/// # Arguments
+///
+/// * `storage` - a mutable reference to the contract's storage
+/// * `asset` - a reference to the asset
+/// * `record` - the change to the asset's balance
+pub fn record_treasury_change<S: Storage>(
+    storage: &mut S,
+    asset: &Asset,
+    record: StoredTreasuryRecord,
+) -> StdResult<()> {
+    let key = asset_key(asset);
+    let mut acct_store = PrefixedStorage::new(PREFIX_TREASURY, storage);
+    let may_account: Option<TreasuryAccount> = may_load(&acct_store, &key)?;
+    let is_new = may_account.is_none();
+    let mut account = may_account.unwrap_or(TreasuryAccount {
+        asset: asset.clone(),
+        balance: 0,
+        received: 0,
+        withdrawn: 0,
+    });
+    if matches!(
+        record.action,
+        TreasuryAction::Withdrawal {} | TreasuryAction::ReferralPayout { .. }
+    ) {
+        if record.amount > account.balance {
+            return Err(StdError::generic_err(format!(
+                "The treasury only holds {} of that asset",
+                account.balance
+            )));
+        }
+        account.balance -= record.amount;
+        account.withdrawn += record.amount;
+    } else {
+        account.balance = account
+            .balance
+            .checked_add(record.amount)
+            .ok_or_else(|| StdError::generic_err("Treasury balance would overflow"))?;
+        account.received = account.received.saturating_add(record.amount);
+    }
+    save(&mut acct_store, &key, &account)?;
+    // remember the order assets were first received in
+    if is_new {
+        let mut assets: Vec<Vec<u8>> = may_load(storage, TREASURY_ASSETS_KEY)?.unwrap_or_default();
+        assets.push(key.clone());
+        save(storage, TREASURY_ASSETS_KEY, &assets)?;
+    }
+    let mut rec_store = PrefixedStorage::multilevel(&[PREFIX_TREASURY_RECORDS, &key], storage);
+    let mut records = AppendStoreMut::<StoredTreasuryRecord, _>::attach_or_create(&mut rec_store)?;
+    records.push(&record)
+}
+
+/// Returns StdResult<()>
+///
+/// reserves the referral share of an auction's creation fee, which must already be credited to
+/// the treasury, so it is held until the auction closes
+///
+/// # Arguments
+///
+/// * `storage` - a mutable reference to the contract's storage
+/// * `index` - factory index of the auction
+/// * `fee` - a reference to the reserved share and the seller's referrer
+pub fn reserve_auction_fee<S: Storage>(
+    storage: &mut S,
+    index: u32,
+    fee: &AuctionFee,
+) -> StdResult<()> {
+    let reserve: u128 = may_load(storage, REFERRAL_RESERVE_KEY)?.unwrap_or(0);
+    save(
+        storage,
+        REFERRAL_RESERVE_KEY,
+        &reserve.saturating_add(fee.reserved),
+    )?;
+    let mut fee_store = PrefixedStorage::new(PREFIX_AUCTION_FEES, storage);
+    save(&mut fee_store, &index.to_le_bytes(), fee)
+}
+
+/// Returns StdResult<Option<AuctionFee>>
+///
+/// forgets the referral share an auction reserved and returns it, so it can either be paid out
+/// or become part of the treasury's withdrawable balance
+///
+/// # Arguments
+///
+/// * `storage` - a mutable reference to the contract's storage
+/// * `index` - factory index of the auction
+pub fn release_auction_fee<S: Storage>(
+    storage: &mut S,
+    index: u32,
+) -> StdResult<Option<AuctionFee>> {
+    let mut fee_store = PrefixedStorage::new(PREFIX_AUCTION_FEES, storage);
+    let may_fee: Option<AuctionFee> = may_load(&fee_store, &index.to_le_bytes())?;
+    fee_store.remove(&index.to_le_bytes());
+    if let Some(fee) = may_fee.as_ref() {
+        let reserve: u128 = may_load(storage, REFERRAL_RESERVE_KEY)?.unwrap_or(0);
+        save(
+            storage,
+            REFERRAL_RESERVE_KEY,
+            &reserve.saturating_sub(fee.reserved),
+        )?;
+    }
+    Ok(may_fee)
+}
+
+/// Returns StdResult<Vec<CosmosMsg>>
+///
+/// pays the referrers of a closed auction's seller and winning bidder the share of its creation
+/// fee it reserved if it closed with a sale, and releases the reserve otherwise
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - a reference to the Env of contract's environment
+/// * `index` - factory index of the closed auction
+/// * `sold` - true if the auction closed with a sale
+/// * `bidder_referrer` - optional address that referred the winning bidder
+pub fn pay_referrals<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    index: u32,
+    sold: bool,
+    bidder_referrer: Option<&HumanAddr>,
+) -> StdResult<Vec<CosmosMsg>> {
+    let fee = match release_auction_fee(&mut deps.storage, index)? {
+        Some(fee) if sold => fee,
+        _ => return Ok(vec![]),
+    };
+    let mut referrers: Vec<CanonicalAddr> = fee.referrer.into_iter().collect();
+    if let Some(referrer) = bidder_referrer {
+        referrers.push(deps.api.canonical_address(referrer)?);
+    }
+    let total = fee.reserved;
+    if referrers.is_empty() || total == 0 {
+        return Ok(vec![]);
+    }
+    let scrt = Asset::Native {
+        denom: FEE_DENOM.to_string(),
+    };
+    // the reserve can not be withdrawn, so recording the payout only fails if the treasury's
+    // records are inconsistent, and then the close is rejected instead of dropping the payout
+    // any remainder of the split goes to the first referrer
+    let (share, mut remainder) = split(total, referrers.len())?;
+    let mut messages = Vec::new();
+    for referrer in referrers.iter() {
+        let amount = share + remainder;
+        remainder = 0;
+        if amount == 0 {
+            continue;
+        }
+        record_treasury_change(
+            &mut deps.storage,
+            &scrt,
+            StoredTreasuryRecord {
+                action: TreasuryAction::ReferralPayout { index },
+                amount,
+                address: referrer.clone(),
+                time: env.block.time,
+            },
+        )?;
+        let mut earn_store = PrefixedStorage::new(PREFIX_REFERRAL_EARNINGS, &mut deps.storage);
+        let mut earnings: ReferralEarnings =
+            may_load(&earn_store, referrer.as_slice())?.unwrap_or_default();
+        earnings.earned = earnings.earned.saturating_add(amount);
+        earnings.payouts += 1;
+        save(&mut earn_store, referrer.as_slice(), &earnings)?;
+        messages.push(CosmosMsg::Bank(BankMsg::Send {
+            from_address: env.contract.address.clone(),
+            to_address: deps.api.human_address(referrer)?,
+            amount: vec![Coin {
+                denom: FEE_DENOM.to_string(),
+                amount: Uint128(amount),
+            }],
+        }));
+    }
+    Ok(messages)
+}
+
+/// Returns HandleResult
+///
+/// allows admin to set the share of creation fees paid to referrers
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `basis_points` - share of the creation fee in basis points
+pub fn try_set_referral_share<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    basis_points: u16,
+) -> HandleResult {
+    // only allow admin to do this
+    let mut config: Config = load_config(&deps.storage)?;
+    let sender = deps.api.canonical_address(&env.message.sender)?;
+    if config.admin != sender {
+        return Err(StdError::generic_err(
+            "This is an admin command. Admin commands can only be run from admin address",
+        ));
+    }
+    if basis_points > MAX_BASIS_POINTS {
+        return Err(StdError::generic_err(format!(
+            "Referral share can not be more than {} basis points",
+            MAX_BASIS_POINTS
+        )));
+    }
+    config.referral_share = basis_points;
+    save(&mut deps.storage, CONFIG_KEY, &config)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: Some(to_binary(&HandleAnswer::Status {
+            status: Success,
+            message: None,
+        })?),
+    })
+}
+
+/// Returns HandleResult
+///
+/// allows the admin to withdraw fees held in the treasury
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `asset` - asset to withdraw
+/// * `amount` - amount to withdraw
+/// * `to` - address to send the withdrawal to
+pub fn try_withdraw<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    asset: Asset,
+    amount: Uint128,
+    to: HumanAddr,
+) -> HandleResult {
+    // only allow admin to do this
+    let config: Config = load_config(&deps.storage)?;
+    let sender = deps.api.canonical_address(&env.message.sender)?;
+    if config.admin != sender {
+        return Err(StdError::generic_err(
+            "This is an admin command. Admin commands can only be run from admin address",
+        ));
+    }
+    if amount.u128() == 0 {
+        return Err(StdError::generic_err(
+            "Withdrawal amount must be greater than 0",
+        ));
+    }
+    // fees reserved for referral payouts can not be withdrawn
+    let available = held(&deps.storage, &asset)?.saturating_sub(reserved(&deps.storage, &asset)?);
+    if amount.u128() > available {
+        return Err(StdError::generic_err(format!(
+            "The treasury only holds {} of that asset that is not reserved for referral payouts",
+            available
+        )));
+    }
+    record_treasury_change(
+        &mut deps.storage,
+        &asset,
+        StoredTreasuryRecord {
+            action: TreasuryAction::Withdrawal {},
+            amount: amount.u128(),
+            address: deps.api.canonical_address(&to)?,
+            time: env.block.time,
+        },
+    )?;
+    let message = match asset {
+        Asset::Native { denom } => CosmosMsg::Bank(BankMsg::Send {
+            from_address: env.contract.address,
+            to_address: to,
+            amount: vec![Coin { denom, amount }],
+        }),
+    };
+
+    Ok(HandleResponse {
+        messages: vec![message],
+        log: vec![],
+        data: Some(to_binary(&HandleAnswer::Status {
+            status: Success,
+            message: None,
+        })?),
+    })
+}
+
+/// Returns StdResult<u128>
+///
+/// amount of an asset the treasury holds
+///
+/// # Arguments
+///
+/// * `storage` - a reference to the contract's storage
+/// * `asset` - a reference to the asset
+fn held<S: ReadonlyStorage>(storage: &S, asset: &Asset) -> StdResult<u128> {
+    let acct_store = ReadonlyPrefixedStorage::new(PREFIX_TREASURY, storage);
+    Ok(may_load::<TreasuryAccount, _>(&acct_store, &asset_key(asset))?.map_or(0, |a| a.balance))
+}
+
+/// Returns StdResult<u128>
+///
+/// amount of an asset the treasury holds in reserve for referral payouts.  Referrals are only
+/// paid from creation fees, so no other asset is ever reserved
+///
+/// # Arguments
+///
+/// * `storage` - a reference to the contract's storage
+/// * `asset` - a reference to the asset
+fn reserved<S: ReadonlyStorage>(storage: &S, asset: &Asset) -> StdResult<u128> {
+    let Asset::Native { denom } = asset;
+    if denom != FEE_DENOM {
+        return Ok(0);
+    }
+    Ok(may_load(storage, REFERRAL_RESERVE_KEY)?.unwrap_or(0))
+}
+
+/// Returns QueryResult displaying the treasury balance of every asset it has received
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+pub fn try_treasury_balances<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+) -> QueryResult {
+    let assets: Vec<Vec<u8>> = may_load(&deps.storage, TREASURY_ASSETS_KEY)?.unwrap_or_default();
+    let acct_store = ReadonlyPrefixedStorage::new(PREFIX_TREASURY, &deps.storage);
+    let balances = assets
+        .iter()
+        .map(|key| {
+            let account: TreasuryAccount = load(&acct_store, key)?;
+            Ok(TreasuryBalance {
+                reserved: Uint128(reserved(&deps.storage, &account.asset)?),
+                asset: account.asset,
+                balance: Uint128(account.balance),
+                received: Uint128(account.received),
+                withdrawn: Uint128(account.withdrawn),
+            })
+        })
+        .collect::<StdResult<Vec<TreasuryBalance>>>()?;
+
+    to_binary(&QueryAnswer::TreasuryBalances { balances })
+}
+
+/// Returns QueryResult displaying the referral fees an address has earned
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `address` - a reference to the address whose earnings to display
+/// * `viewing_key` - String holding the address' viewing key
+pub fn try_referral_earnings<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    address: &HumanAddr,
+    viewing_key: String,
+) -> QueryResult {
+    let addr_raw = deps.api.canonical_address(address)?;
+    if !is_key_valid(&deps.storage, &addr_raw, viewing_key)? {
+        return to_binary(&QueryAnswer::ViewingKeyError {
+            error: "Wrong viewing key for this address or viewing key not set".to_string(),
+        });
+    }
+    let earn_store = ReadonlyPrefixedStorage::new(PREFIX_REFERRAL_EARNINGS, &deps.storage);
+    let earnings: ReferralEarnings =
+        may_load(&earn_store, addr_raw.as_slice())?.unwrap_or_default();
+
+    to_binary(&QueryAnswer::ReferralEarnings {
+        earned: Uint128(earnings.earned),
+        payouts: earnings.payouts,
+    })
+}
+
+/// Returns QueryResult listing the changes to the treasury balance of an asset in reverse
+/// chronological order
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `address` - a reference to the admin's address
+/// * `viewing_key` - String holding the admin's viewing key
+/// * `asset` - a reference to the asset whose records to display
+/// * `before` - optionally only show records with id less than specified value
+/// * `page_size` - optional number of records to return
+pub fn try_treasury_records<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    address: &HumanAddr,
+    viewing_key: String,
+    asset: &Asset,
+    before: Option<u32>,
+    page_size: Option<u32>,
+) -> QueryResult {
+    let config: Config = load_config(&deps.storage)?;
+    let addr_raw = deps.api.canonical_address(address)?;
+    if !is_key_valid(&deps.storage, &addr_raw, viewing_key)? || addr_raw != config.admin {
+        return to_binary(&QueryAnswer::ViewingKeyError {
+            error: "Only the admin may view treasury records".to_string(),
+        });
+    }
+    let key = asset_key(asset);
+    let rec_store =
+        ReadonlyPrefixedStorage::multilevel(&[PREFIX_TREASURY_RECORDS, &key], &deps.storage);
+    let mut records = Vec::new();
+    let mut count = 0u32;
+    if let Some(history) =
+        AppendStore::<StoredTreasuryRecord, _>::attach(&rec_store).and_then(|r| r.ok())
+    {
+        count = history.len();
+        let end = before.map_or(count, |b| b.min(count));
+        let take = page_size.unwrap_or(DEFAULT_PAGE_SIZE);
+        for id in (end.saturating_sub(take)..end).rev() {
+            let stored = history.get_at(id)?;
+            records.push(TreasuryRecord {
+                id,
+                action: stored.action,
+                amount: Uint128(stored.amount),
+                address: deps.api.human_address(&stored.address)?,
+                time: stored.time,
+            });
+        }
+    }
+
+    to_binary(&QueryAnswer::TreasuryRecords { records, count })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmwasm_std::testing::MockStorage;
+
+    #[test]
+    fn test_record_treasury_change() {
+        let mut storage = MockStorage::new();
+        let scrt = Asset::Native {
+            denom: "uscrt".to_string(),
+        };
+        let record = |action: TreasuryAction, amount: u128| StoredTreasuryRecord {
+            action,
+            amount,
+            address: CanonicalAddr::default(),
+            time: 0,
+        };
+        let fee = |index: u32| TreasuryAction::CreationFee { index };
+        record_treasury_change(&mut storage, &scrt, record(fee(0), 100)).unwrap();
+        record_treasury_change(&mut storage, &scrt, record(fee(1), 50)).unwrap();
+
+        // can not withdraw more than the treasury holds
+        let withdrawal = TreasuryAction::Withdrawal {};
+        let error = record_treasury_change(&mut storage, &scrt, record(withdrawal.clone(), 151))
+            .unwrap_err();
+        assert!(error.to_string().contains("only holds 150"));
+        record_treasury_change(&mut storage, &scrt, record(withdrawal, 120)).unwrap();
+        let acct_store = ReadonlyPrefixedStorage::new(PREFIX_TREASURY, &storage);
+        let account: TreasuryAccount = load(&acct_store, &asset_key(&scrt)).unwrap();
+        assert_eq!(
+            (account.balance, account.received, account.withdrawn),
+            (30, 150, 120)
+        );
+        // the asset is only listed once
+        let assets: Vec<Vec<u8>> = load(&storage, TREASURY_ASSETS_KEY).unwrap();
+        assert_eq!(assets.len(), 1);
+    }
+}
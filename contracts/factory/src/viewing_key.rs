@@ -5,7 +5,8 @@ use serde::{Deserialize, Serialize};
 
 use cosmwasm_std::Env;
 
-use crate::rand::{sha_256, Prng};
+use auction_common::crypto::{sha_256, Prng};
+
 use crate::utils::{create_hashed_password, ct_slice_compare};
 
 pub const VIEWING_KEY_SIZE: usize = 32;
@@ -0,0 +1,471 @@
+use cosmwasm_std::{
+    to_binary, Api, Binary, CanonicalAddr, CosmosMsg, Env, Extern, HandleResponse, HandleResult,
+    HumanAddr, Querier, QueryResult, ReadonlyStorage, StdError, StdResult, Storage,
+};
+
+use cosmwasm_storage::{PrefixedStorage, ReadonlyPrefixedStorage};
+
+use std::collections::HashSet;
+
+use secret_toolkit::utils::HandleCallback;
+
+use auction_common::crypto::{fold_entropy, load_seed, sha_256};
+
+use crate::contract::{
+    AuctionHandleMsg, ACTIVE_KEY, MAX_KEY_PAIRS, PREFIX_CODE_HASHES, PREFIX_KEY_EPOCHS,
+    PREFIX_VIEW_KEY, PRUNED_KEY,
+};
+use crate::lists::{compact_closed_list, filter_only_active, AddressList};
+use crate::msg::{HandleAnswer, QueryAnswer};
+use crate::state::{may_load, save};
+use crate::viewing_key::{ViewingKey, VIEWING_KEY_SIZE};
+
+/// Returns HandleResult
+///
+/// create a viewing key for the sender
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `entropy` - string slice to be used as an entropy source for randomization
+pub fn try_create_key<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    entropy: &str,
+) -> HandleResult {
+    // create and store the key
+    let prng_seed = load_seed(&deps.storage)?;
+    let key = ViewingKey::new(&env, &prng_seed, entropy.as_ref());
+    // keep the seed fresh with every caller's entropy
+    fold_entropy(&mut deps.storage, &env, entropy.as_bytes())?;
+    save_key(deps, &env.message.sender, &key)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: Some(to_binary(&HandleAnswer::ViewingKey {
+            key: format!("{}", key),
+        })?),
+    })
+}
+
+/// Returns HandleResult
+///
+/// sets the viewing key of the sender
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `key` - string slice to be used as the viewing key
+pub fn try_set_key<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    key: &str,
+) -> HandleResult {
+    // store the viewing key
+    let vk = ViewingKey(key.to_string());
+    save_key(deps, &env.message.sender, &vk)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: Some(to_binary(&HandleAnswer::ViewingKey {
+            key: key.to_string(),
+        })?),
+    })
+}
+
+/// Returns StdResult<()>
+///
+/// saves an address' viewing key, starts a new key epoch, and cleans up the address' lists.  The new key's hash is not
+/// sent to the address' active auctions.  Each auction is given the hash the next time the
+/// address bids in it, and until then checks the new key with the factory
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `address` - a reference to the address whose key is being saved
+/// * `key` - a reference to the viewing key
+fn save_key<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    address: &HumanAddr,
+    key: &ViewingKey,
+) -> StdResult<()> {
+    let address_raw = &deps.api.canonical_address(address)?;
+    let mut key_store = PrefixedStorage::new(PREFIX_VIEW_KEY, &mut deps.storage);
+    save(&mut key_store, address_raw.as_slice(), &key.to_hashed())?;
+    // start a new key epoch so auctions stop trusting the hashes of the old key once they see it
+    let mut epoch_store = PrefixedStorage::new(PREFIX_KEY_EPOCHS, &mut deps.storage);
+    let epoch: u32 = may_load(&epoch_store, address_raw.as_slice())?.unwrap_or(0);
+    save(
+        &mut epoch_store,
+        address_raw.as_slice(),
+        &epoch.wrapping_add(1),
+    )?;
+
+    // clean up the bidder's list of active auctions
+    let load_active: Option<HashSet<u32>> = may_load(&deps.storage, ACTIVE_KEY)?;
+    if let Some(mut active) = load_active {
+        let mut bidder_store =
+            PrefixedStorage::new(AddressList::Bidder.prefix(), &mut deps.storage);
+        let (my_active, update) = filter_only_active(&bidder_store, address_raw, &mut active)?;
+        // if list was updated, save it
+        if update {
+            save(&mut bidder_store, address_raw.as_slice(), &my_active)?;
+        }
+    }
+    // remove any pruned auctions from the address' closed lists
+    let pruned: u32 = may_load(&deps.storage, PRUNED_KEY)?.unwrap_or(0);
+    compact_closed_list(
+        &mut deps.storage,
+        AddressList::SellerClosed,
+        address_raw,
+        pruned,
+    )?;
+    compact_closed_list(&mut deps.storage, AddressList::Winner, address_raw, pruned)
+}
+
+/// Returns StdResult<Option<CosmosMsg>>
+///
+/// creates the message that shares the hash derived from an address' viewing key with an auction,
+/// if the address has a key and the auction was created with code that understands the message
+///
+/// # Arguments
+///
+/// * `storage` - a reference to the contract's storage
+/// * `index` - index of the auction
+/// * `auction` - a reference to the auction's address
+/// * `address` - a reference to the address whose key is being shared
+/// * `address_raw` - a reference to the canonical form of the address
+pub fn share_key_msg<S: ReadonlyStorage>(
+    storage: &S,
+    index: u32,
+    auction: &HumanAddr,
+    address: &HumanAddr,
+    address_raw: &CanonicalAddr,
+) -> StdResult<Option<CosmosMsg>> {
+    // auctions created before the factory recorded code hashes will keep asking the factory
+    let hash_store = ReadonlyPrefixedStorage::new(PREFIX_CODE_HASHES, storage);
+    let may_code_hash: Option<String> = may_load(&hash_store, &index.to_le_bytes())?;
+    let key_store = ReadonlyPrefixedStorage::new(PREFIX_VIEW_KEY, storage);
+    let may_key: Option<[u8; VIEWING_KEY_SIZE]> = may_load(&key_store, address_raw.as_slice())?;
+    if let (Some(code_hash), Some(hashed_key)) = (may_code_hash, may_key) {
+        let key_hash = Binary(auction_key_hash(&hashed_key, auction).to_vec());
+        let epoch_store = ReadonlyPrefixedStorage::new(PREFIX_KEY_EPOCHS, storage);
+        let epoch: u32 = may_load(&epoch_store, address_raw.as_slice())?.unwrap_or(0);
+        let msg = AuctionHandleMsg::ShareKeyHash {
+            address: address.clone(),
+            key_hash,
+            epoch,
+        }
+        .to_cosmos_msg(code_hash, auction.clone(), None)?;
+        return Ok(Some(msg));
+    }
+    Ok(None)
+}
+
+/// Returns [u8; 32]
+///
+/// derives the hash an auction uses to validate an address' viewing key.  Including the auction's
+/// address means the hash shared with one auction is of no use with any other
+///
+/// # Arguments
+///
+/// * `hashed_key` - the sha256 hash of the viewing key, as the factory stores it
+/// * `auction` - a reference to the auction's address
+pub fn auction_key_hash(hashed_key: &[u8], auction: &HumanAddr) -> [u8; 32] {
+    sha_256(&[hashed_key, auction.0.as_bytes()].concat())
+}
+
+/// Returns QueryResult indicating whether the address/key pair is valid
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `address` - a reference to the address whose key should be validated
+/// * `viewing_key` - String key used for authentication
+pub fn try_validate_key<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    address: &HumanAddr,
+    viewing_key: String,
+) -> QueryResult {
+    let addr_raw = &deps.api.canonical_address(address)?;
+    to_binary(&QueryAnswer::IsKeyValid {
+        is_valid: is_key_valid(&deps.storage, addr_raw, viewing_key)?,
+    })
+}
+
+/// Returns QueryResult indicating whether each address/key pair is valid
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `pairs` - addresses and the keys used to authenticate them
+pub fn try_validate_keys<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    pairs: Vec<(HumanAddr, String)>,
+) -> QueryResult {
+    if pairs.len() > MAX_KEY_PAIRS {
+        return Err(StdError::generic_err(format!(
+            "Can not authenticate more than {} keys at a time",
+            MAX_KEY_PAIRS
+        )));
+    }
+    let is_valid = pairs
+        .into_iter()
+        .map(|(address, viewing_key)| {
+            let addr_raw = deps.api.canonical_address(&address)?;
+            is_key_valid(&deps.storage, &addr_raw, viewing_key)
+        })
+        .collect::<StdResult<Vec<bool>>>()?;
+    to_binary(&QueryAnswer::AreKeysValid { is_valid })
+}
+
+/// Returns StdResult<bool> result of validating an address' viewing key
+///
+/// # Arguments
+///
+/// * `storage` - a reference to the contract's storage
+/// * `address` - a reference to the address whose key should be validated
+/// * `viewing_key` - String key used for authentication
+pub fn is_key_valid<S: ReadonlyStorage>(
+    storage: &S,
+    address: &CanonicalAddr,
+    viewing_key: String,
+) -> StdResult<bool> {
+    // load the address' key
+    let read_key = ReadonlyPrefixedStorage::new(PREFIX_VIEW_KEY, storage);
+    let load_key: Option<[u8; VIEWING_KEY_SIZE]> = may_load(&read_key, address.as_slice())?;
+    let input_key = ViewingKey(viewing_key);
+    // if a key was set
+    if let Some(expected_key) = load_key {
+        // and it matches
+        if input_key.check_viewing_key(&expected_key) {
+            return Ok(true);
+        }
+    } else {
+        // Checking the key will take significant time. We don't want to exit immediately if it isn't set
+        // in a way which will allow to time the command and determine if a viewing key doesn't exist
+        input_key.check_viewing_key(&[0u8; VIEWING_KEY_SIZE]);
+    }
+    Ok(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::contract::{handle, init, query};
+    use crate::msg::{AuctionContractInfo, HandleMsg, InitMsg, QueryMsg};
+    use cosmwasm_std::testing::{mock_dependencies, mock_env, MockApi, MockQuerier, MockStorage};
+    use cosmwasm_std::{from_binary, InitResponse};
+
+    fn init_helper() -> (
+        StdResult<InitResponse>,
+        Extern<MockStorage, MockApi, MockQuerier>,
+    ) {
+        let mut deps = mock_dependencies(20, &[]);
+        let env = mock_env("admin", &[]);
+        let init_msg = InitMsg {
+            entropy: "entropy".to_string(),
+            auction_contract: AuctionContractInfo {
+                code_id: 1,
+                code_hash: "auctionhash".to_string(),
+            },
+            block_size: None,
+        };
+        (init(&mut deps, env, init_msg), deps)
+    }
+
+    #[test]
+    fn test_create_and_set_key() {
+        let (init_result, mut deps) = init_helper();
+        assert!(
+            init_result.is_ok(),
+            "Init failed: {}",
+            init_result.err().unwrap()
+        );
+        let alice = deps
+            .api
+            .canonical_address(&HumanAddr("alice".to_string()))
+            .unwrap();
+        assert!(!is_key_valid(&deps.storage, &alice, "anything".to_string()).unwrap());
+
+        let handle_msg = HandleMsg::CreateViewingKey {
+            entropy: "blah".to_string(),
+        };
+        let handle_result = handle(&mut deps, mock_env("alice", &[]), handle_msg);
+        let key = match from_binary(&handle_result.unwrap().data.unwrap()).unwrap() {
+            HandleAnswer::ViewingKey { key } => key,
+            _ => panic!("Unexpected"),
+        };
+        assert!(is_key_valid(&deps.storage, &alice, key.clone()).unwrap());
+        assert!(!is_key_valid(&deps.storage, &alice, "wrong".to_string()).unwrap());
+
+        // a set key replaces the created one
+        let handle_msg = HandleMsg::SetViewingKey {
+            key: "chosen".to_string(),
+            padding: None,
+        };
+        let handle_result = handle(&mut deps, mock_env("alice", &[]), handle_msg);
+        assert!(handle_result.is_ok());
+        assert!(!is_key_valid(&deps.storage, &alice, key).unwrap());
+
+        let query_msg = QueryMsg::IsKeyValid {
+            address: HumanAddr("alice".to_string()),
+            viewing_key: "chosen".to_string(),
+        };
+        let is_valid = match from_binary(&query(&deps, query_msg).unwrap()).unwrap() {
+            QueryAnswer::IsKeyValid { is_valid } => is_valid,
+            _ => panic!("Unexpected"),
+        };
+        assert!(is_valid);
+    }
+
+    #[test]
+    fn test_share_key_msg() {
+        let (init_result, mut deps) = init_helper();
+        assert!(
+            init_result.is_ok(),
+            "Init failed: {}",
+            init_result.err().unwrap()
+        );
+        let auction = HumanAddr("auction".to_string());
+        let alice = HumanAddr("alice".to_string());
+        let alice_raw = deps.api.canonical_address(&alice).unwrap();
+
+        // nothing to share without a key
+        let mut hash_store = PrefixedStorage::new(PREFIX_CODE_HASHES, &mut deps.storage);
+        save(
+            &mut hash_store,
+            &0u32.to_le_bytes(),
+            &"auctionhash".to_string(),
+        )
+        .unwrap();
+        assert!(
+            share_key_msg(&deps.storage, 0, &auction, &alice, &alice_raw)
+                .unwrap()
+                .is_none()
+        );
+
+        let handle_msg = HandleMsg::SetViewingKey {
+            key: "chosen".to_string(),
+            padding: None,
+        };
+        let handle_result = handle(&mut deps, mock_env("alice", &[]), handle_msg);
+        assert!(handle_result.is_ok());
+        // auctions without a recorded code hash may not understand the message
+        assert!(
+            share_key_msg(&deps.storage, 1, &auction, &alice, &alice_raw)
+                .unwrap()
+                .is_none()
+        );
+        let msg = share_key_msg(&deps.storage, 0, &auction, &alice, &alice_raw)
+            .unwrap()
+            .unwrap();
+        let expected = AuctionHandleMsg::ShareKeyHash {
+            address: alice.clone(),
+            key_hash: Binary(sha_256(&[&sha_256(b"chosen")[..], b"auction"].concat()).to_vec()),
+            epoch: 1,
+        }
+        .to_cosmos_msg("auctionhash".to_string(), auction.clone(), None)
+        .unwrap();
+        assert_eq!(msg, expected);
+        // every key change starts a new epoch
+        let handle_msg = HandleMsg::CreateViewingKey {
+            entropy: "blah".to_string(),
+            padding: None,
+        };
+        let handle_result = handle(&mut deps, mock_env("alice", &[]), handle_msg);
+        assert!(handle_result.unwrap().messages.is_empty());
+        let epoch_store = ReadonlyPrefixedStorage::new(PREFIX_KEY_EPOCHS, &deps.storage);
+        let epoch: Option<u32> = may_load(&epoch_store, alice_raw.as_slice()).unwrap();
+        assert_eq!(epoch, Some(2));
+        // the hash is bound to the auction
+        assert_ne!(
+            auction_key_hash(&sha_256(b"chosen"), &auction),
+            auction_key_hash(&sha_256(b"chosen"), &HumanAddr("other".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_are_keys_valid() {
+        let (init_result, mut deps) = init_helper();
+        assert!(
+            init_result.is_ok(),
+            "Init failed: {}",
+            init_result.err().unwrap()
+        );
+        for (address, key) in [("alice", "alicekey"), ("bob", "bobkey")].iter() {
+            let handle_msg = HandleMsg::SetViewingKey {
+                key: key.to_string(),
+                padding: None,
+            };
+            let handle_result = handle(&mut deps, mock_env(*address, &[]), handle_msg);
+            assert!(handle_result.is_ok());
+        }
+        let pair = |address: &str, key: &str| (HumanAddr(address.to_string()), key.to_string());
+
+        // results are in the order the pairs were given
+        let query_msg = QueryMsg::AreKeysValid {
+            pairs: vec![
+                pair("alice", "alicekey"),
+                pair("bob", "alicekey"),
+                pair("carol", "carolkey"),
+                pair("bob", "bobkey"),
+            ],
+        };
+        let is_valid = match from_binary(&query(&deps, query_msg).unwrap()).unwrap() {
+            QueryAnswer::AreKeysValid { is_valid } => is_valid,
+            _ => panic!("Unexpected"),
+        };
+        assert_eq!(is_valid, vec![true, false, false, true]);
+
+        // try too many pairs
+        let query_msg = QueryMsg::AreKeysValid {
+            pairs: vec![pair("alice", "alicekey"); MAX_KEY_PAIRS + 1],
+        };
+        let error = match query(&deps, query_msg) {
+            Err(StdError::GenericErr { msg, .. }) => msg,
+            _ => panic!("Expected an error"),
+        };
+        assert!(error.contains("Can not authenticate more than 30 keys at a time"));
+    }
+
+    #[test]
+    fn test_reseed() {
+        let (_, mut deps) = init_helper();
+        let initial = load_seed(&deps.storage).unwrap();
+        assert_eq!(
+            initial,
+            sha_256(base64::encode("entropy").as_bytes()).to_vec()
+        );
+
+        // only the admin may reseed
+        let handle_msg = HandleMsg::Reseed {
+            entropy: "more".to_string(),
+        };
+        let handle_result = handle(&mut deps, mock_env("alice", &[]), handle_msg);
+        match handle_result {
+            Err(StdError::GenericErr { msg, .. }) => assert!(msg.contains("admin command")),
+            _ => panic!("Expected an error"),
+        }
+        assert_eq!(load_seed(&deps.storage).unwrap(), initial);
+        let handle_msg = HandleMsg::Reseed {
+            entropy: "more".to_string(),
+        };
+        let handle_result = handle(&mut deps, mock_env("admin", &[]), handle_msg);
+        assert!(handle_result.is_ok());
+        let reseeded = load_seed(&deps.storage).unwrap();
+        assert_ne!(reseeded, initial);
+
+        // creating a viewing key folds its entropy into the seed
+        let handle_msg = HandleMsg::CreateViewingKey {
+            entropy: "blah".to_string(),
+        };
+        let handle_result = handle(&mut deps, mock_env("alice", &[]), handle_msg);
+        assert!(handle_result.is_ok());
+        assert_ne!(load_seed(&deps.storage).unwrap(), reseeded);
+    }
+}
@@ -1,3 +1,3132 @@
+//! Runs the factory and its auctions together against mock SNIP-20 tokens.  Every message a
+//! contract returns is dispatched to the contract it targets, depth first, so a single call
+//! exercises the whole create -> register -> bid -> close -> list flow the way it would run
+//! on chain.  Apart from a failed SNIP-20 Send returning its tokens, state is not rolled back
+//! when a dispatched message fails, so tests should only expect errors from the first contract
+//! called
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use cosmwasm_std::testing::{mock_dependencies, mock_env, MockApi, MockStorage};
+use cosmwasm_std::{
+    coins, from_binary, from_slice, to_binary, Api, BankMsg, Binary, Coin, CosmosMsg, Empty, Env,
+    Extern, HumanAddr, Querier, QuerierResult, QueryRequest, StdError, StdResult, Uint128, WasmMsg,
+    WasmQuery,
+};
+
+use auction_factory::contract::{handle, init, query};
+use auction_factory::msg::{
+    Asset, AuctionContractInfo, BundleItem, ClosedAuctionInfo, ContractInfo, CreationFee,
+    FactoryStatus, FilterTypes, HandleAnswer, HandleMsg, IbcOrigin, IndexedAuctionInfo, InitMsg,
+    MinBidDecay, QueryAnswer, QueryMsg, SettlementMode, SettlementOutcome, TieBreak, TokenAddress,
+    TreasuryAction,
+};
+use sealed_bid_auction::contract as auction;
+use sealed_bid_auction::contract::CONFIG_KEY as AUCTION_CONFIG_KEY;
+use sealed_bid_auction::msg as auction_msg;
+use sealed_bid_auction::state::{load as auction_load, State as AuctionState};
+
+const FACTORY: &str = "factoryaddr";
+const FACTORY_HASH: &str = "factoryhash";
+const AUCTION_HASH: &str = "auctionhash";
+const SELL_TOKEN: &str = "selladdr";
+const BID_TOKEN: &str = "bidaddr";
+const EXTRA_TOKEN: &str = "extraaddr";
+const ADMIN: &str = "admin";
+const SELLER: &str = "alice";
+const SUBSCRIBER: &str = "subscriberaddr";
+
+/// the handle messages the mock SNIP-20 understands
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum TokenMsg {
+    Transfer {
+        recipient: HumanAddr,
+        amount: Uint128,
+    },
+    Send {
+        recipient: HumanAddr,
+        amount: Uint128,
+        #[serde(default)]
+        msg: Option<Binary>,
+    },
+    SendFrom {
+        owner: HumanAddr,
+        recipient: HumanAddr,
+        amount: Uint128,
+        #[serde(default)]
+        msg: Option<Binary>,
+    },
+    RegisterReceive {
+        code_hash: String,
+    },
+}
+
+/// token info returned by the mock SNIP-20s
+#[derive(Serialize)]
+struct TokenInfo {
+    name: String,
+    symbol: String,
+    decimals: u8,
+    total_supply: Option<Uint128>,
+}
+
+#[derive(Serialize)]
+struct TokenInfoResponse {
+    token_info: TokenInfo,
+}
+
+/// answers the token info queries the factory and auctions make of the mock SNIP-20s
+#[derive(Default)]
+struct TokenQuerier {
+    /// true once the sell token has rebranded as SOLD with 4 decimals
+    rebranded: bool,
+}
+
+impl Querier for TokenQuerier {
+    fn raw_query(&self, bin_request: &[u8]) -> QuerierResult {
+        let request: QueryRequest<Empty> = from_slice(bin_request).unwrap();
+        let contract_addr = match request {
+            QueryRequest::Wasm(WasmQuery::Smart { contract_addr, .. }) => contract_addr,
+            _ => panic!("Unexpected query"),
+        };
+        let (symbol, decimals) = match contract_addr.as_str() {
+            SELL_TOKEN if self.rebranded => ("SOLD", 4),
+            SELL_TOKEN => ("SELL", 6),
+            BID_TOKEN => ("BID", 8),
+            EXTRA_TOKEN => ("EXTRA", 0),
+            other => panic!("Query of unknown token {}", other),
+        };
+        Ok(to_binary(&TokenInfoResponse {
+            token_info: TokenInfo {
+                name: symbol.to_lowercase(),
+                symbol: symbol.to_string(),
+                decimals,
+                total_supply: None,
+            },
+        }))
+    }
+}
+
+type Deps = Extern<MockStorage, MockApi, TokenQuerier>;
+
+fn new_deps() -> Deps {
+    mock_dependencies(20, &[]).change_querier(|_| TokenQuerier::default())
+}
+
+/// the factory, its auctions, and the token balances of everyone involved
+struct Chain {
+    time: u64,
+    factory: Deps,
+    auctions: HashMap<String, Deps>,
+    /// code hashes the auctions were instantiated with, keyed by address
+    code_hashes: HashMap<String, String>,
+    /// balances keyed by (token, owner)
+    balances: HashMap<(String, String), u128>,
+    /// code hashes the tokens will call Receive with, keyed by (token, contract)
+    receivers: HashMap<(String, String), String>,
+}
+
+impl Chain {
+    fn new() -> Self {
+        Self::with_block_size(None)
+    }
+
+    fn with_block_size(block_size: Option<u16>) -> Self {
+        let mut factory = new_deps();
+        let init_msg = InitMsg {
+            entropy: "entropy".to_string(),
+            auction_contract: AuctionContractInfo {
+                code_id: 1,
+                code_hash: AUCTION_HASH.to_string(),
+            },
+            block_size,
+        };
+        let mut env = mock_env(ADMIN, &[]);
+        env.contract.address = HumanAddr(FACTORY.to_string());
+        env.contract_code_hash = FACTORY_HASH.to_string();
+        init(&mut factory, env, init_msg).unwrap();
+        Chain {
+            time: 100,
+            factory,
+            auctions: HashMap::new(),
+            code_hashes: HashMap::new(),
+            balances: HashMap::new(),
+            receivers: HashMap::new(),
+        }
+    }
+
+    fn env(&self, sender: &str, contract: &str, code_hash: &str) -> Env {
+        let mut env = mock_env(sender, &[]);
+        env.block.time = self.time;
+        env.contract.address = HumanAddr(contract.to_string());
+        env.contract_code_hash = code_hash.to_string();
+        env
+    }
+
+    fn balance(&self, token: &str, owner: &str) -> u128 {
+        self.balances
+            .get(&(token.to_string(), owner.to_string()))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    fn mint(&mut self, token: &str, owner: &str, amount: u128) {
+        *self
+            .balances
+            .entry((token.to_string(), owner.to_string()))
+            .or_insert(0) += amount;
+    }
+
+    fn move_tokens(&mut self, token: &str, from: &str, to: &str, amount: u128) -> StdResult<()> {
+        let balance = self.balance(token, from);
+        if balance < amount {
+            return Err(StdError::generic_err(format!(
+                "{} has insufficient {} balance",
+                from, token
+            )));
+        }
+        self.balances
+            .insert((token.to_string(), from.to_string()), balance - amount);
+        self.mint(token, to, amount);
+        Ok(())
+    }
+
+    /// executes a factory handle sent by `sender` and everything it triggers
+    fn factory_handle(&mut self, sender: &str, msg: HandleMsg) -> StdResult<Option<Binary>> {
+        self.factory_handle_with_funds(sender, msg, &[])
+    }
+
+    /// executes a factory handle sent by `sender` along with native coins
+    fn factory_handle_with_funds(
+        &mut self,
+        sender: &str,
+        msg: HandleMsg,
+        funds: &[Coin],
+    ) -> StdResult<Option<Binary>> {
+        let mut env = self.env(sender, FACTORY, FACTORY_HASH);
+        env.message.sent_funds = funds.to_vec();
+        let resp = handle(&mut self.factory, env, msg)?;
+        self.dispatch(FACTORY, resp.messages)?;
+        Ok(resp.data)
+    }
+
+    /// executes an auction handle sent by `sender` and everything it triggers
+    fn auction_handle(
+        &mut self,
+        sender: &str,
+        address: &str,
+        msg: auction_msg::HandleMsg,
+    ) -> StdResult<Option<Binary>> {
+        let code_hash = self
+            .code_hashes
+            .get(address)
+            .map_or(AUCTION_HASH, |h| h.as_str());
+        let env = self.env(sender, address, code_hash);
+        let deps = self
+            .auctions
+            .get_mut(address)
+            .ok_or_else(|| StdError::generic_err(format!("No auction at {}", address)))?;
+        let resp = auction::handle(deps, env, msg)?;
+        self.dispatch(address, resp.messages)?;
+        Ok(resp.data)
+    }
+
+    /// executes a SNIP-20 handle sent by `sender` and everything it triggers.  Allowances are
+    /// not tracked, so any address may SendFrom any owner
+    fn token_handle(&mut self, sender: &str, token: &str, msg: TokenMsg) -> StdResult<()> {
+        match msg {
+            TokenMsg::Transfer { recipient, amount } => {
+                self.move_tokens(token, sender, recipient.as_str(), amount.u128())
+            }
+            TokenMsg::Send {
+                recipient,
+                amount,
+                msg,
+            } => self.send_tokens(token, sender, sender, recipient.as_str(), amount, msg),
+            TokenMsg::SendFrom {
+                owner,
+                recipient,
+                amount,
+                msg,
+            } => self.send_tokens(
+                token,
+                sender,
+                owner.as_str(),
+                recipient.as_str(),
+                amount,
+                msg,
+            ),
+            TokenMsg::RegisterReceive { code_hash } => {
+                self.receivers
+                    .insert((token.to_string(), sender.to_string()), code_hash);
+                Ok(())
+            }
+        }
+    }
+
+    /// moves `owner`'s tokens to `recipient`, calling its Receive if it registered with the token
+    fn send_tokens(
+        &mut self,
+        token: &str,
+        sender: &str,
+        owner: &str,
+        recipient: &str,
+        amount: Uint128,
+        msg: Option<Binary>,
+    ) -> StdResult<()> {
+        self.move_tokens(token, owner, recipient, amount.u128())?;
+        let registered = self
+            .receivers
+            .contains_key(&(token.to_string(), recipient.to_string()));
+        if !registered {
+            return Ok(());
+        }
+        let receive = auction_msg::HandleMsg::Receive {
+            sender: HumanAddr(sender.to_string()),
+            from: HumanAddr(owner.to_string()),
+            amount,
+            msg,
+        };
+        // a failed Receive reverts the send
+        self.auction_handle(token, recipient, receive)
+            .map(|_| ())
+            .or_else(|e| {
+                self.move_tokens(token, recipient, owner, amount.u128())?;
+                Err(e)
+            })
+    }
+
+    /// a user sends tokens to a contract, calling its Receive
+    fn send(
+        &mut self,
+        from: &str,
+        token: &str,
+        recipient: &str,
+        amount: u128,
+        msg: Option<auction_msg::ReceiveMsg>,
+    ) -> StdResult<()> {
+        let send = TokenMsg::Send {
+            recipient: HumanAddr(recipient.to_string()),
+            amount: Uint128(amount),
+            msg: msg.map(|m| to_binary(&m)).transpose()?,
+        };
+        self.token_handle(from, token, send)
+    }
+
+    /// routes the messages a contract returned, depth first
+    fn dispatch(&mut self, sender: &str, messages: Vec<CosmosMsg>) -> StdResult<()> {
+        for message in messages {
+            match message {
+                CosmosMsg::Wasm(WasmMsg::Instantiate {
+                    msg,
+                    callback_code_hash,
+                    ..
+                }) => {
+                    let address = format!("auction{}", self.auctions.len());
+                    let env = self.env(sender, &address, &callback_code_hash);
+                    let init_msg: auction_msg::InitMsg = from_binary(&msg)?;
+                    let mut deps = new_deps();
+                    let resp = auction::init(&mut deps, env, init_msg)?;
+                    self.auctions.insert(address.clone(), deps);
+                    self.code_hashes.insert(address.clone(), callback_code_hash);
+                    self.dispatch(&address, resp.messages)?;
+                }
+                CosmosMsg::Wasm(WasmMsg::Execute {
+                    contract_addr,
+                    callback_code_hash,
+                    msg,
+                    ..
+                }) => match contract_addr.as_str() {
+                    FACTORY => {
+                        self.factory_handle(sender, from_binary(&msg)?)?;
+                    }
+                    SELL_TOKEN | BID_TOKEN | EXTRA_TOKEN => {
+                        self.token_handle(sender, contract_addr.as_str(), from_binary(&msg)?)?;
+                    }
+                    addr => {
+                        // an auction rejects messages sent with the wrong code hash
+                        if self.code_hashes.get(addr) != Some(&callback_code_hash) {
+                            return Err(StdError::generic_err(format!(
+                                "{} does not run code {}",
+                                addr, callback_code_hash
+                            )));
+                        }
+                        self.auction_handle(sender, addr, from_binary(&msg)?)?;
+                    }
+                },
+                CosmosMsg::Bank(BankMsg::Send {
+                    to_address, amount, ..
+                }) => {
+                    for coin in amount {
+                        self.mint(&coin.denom, to_address.as_str(), coin.amount.u128());
+                    }
+                }
+                other => panic!("Unexpected message {:?}", other),
+            }
+        }
+        Ok(())
+    }
+
+    fn factory_query(&self, msg: QueryMsg) -> QueryAnswer {
+        from_binary(&query(&self.factory, msg).unwrap()).unwrap()
+    }
+
+    fn auction_status(&self, address: &str) -> auction_msg::QueryAnswer {
+        let deps = &self.auctions[address];
+        from_binary(&auction::query(deps, auction_msg::QueryMsg::Status {}).unwrap()).unwrap()
+    }
+
+    /// creates a 1000 SELL for a minimum 100 BID auction closing at `ends_at`, and returns its
+    /// address
+    fn create_auction(&mut self, label: &str, ends_at: u64) -> String {
+        self.create_tagged_auction(label, ends_at, &[])
+    }
+
+    /// creates the same auction as create_auction, listed under the given tags
+    fn create_tagged_auction(&mut self, label: &str, ends_at: u64, tags: &[&str]) -> String {
+        self.factory_handle(SELLER, create_msg(label, ends_at, tags))
+            .unwrap();
+        format!("auction{}", self.auctions.len() - 1)
+    }
+
+    fn viewing_key(&mut self, address: &str) -> String {
+        let data = self
+            .factory_handle(
+                address,
+                HandleMsg::CreateViewingKey {
+                    entropy: "entropy".to_string(),
+                },
+            )
+            .unwrap()
+            .unwrap();
+        match from_binary(&data).unwrap() {
+            HandleAnswer::ViewingKey { key } => key,
+            _ => panic!("Unexpected viewing key answer"),
+        }
+    }
+
+    fn active_labels(&self) -> Vec<String> {
+        match self.factory_query(QueryMsg::ListActiveAuctions { code_id: None }) {
+            QueryAnswer::ListActiveAuctions { active } => active
+                .unwrap_or_default()
+                .into_iter()
+                .map(|a| a.label)
+                .collect(),
+            _ => panic!("Unexpected ListActiveAuctions answer"),
+        }
+    }
+
+    fn closed(&self) -> Vec<ClosedAuctionInfo> {
+        let list = QueryMsg::ListClosedAuctions {
+            before: None,
+            page_size: None,
+        };
+        match self.factory_query(list) {
+            QueryAnswer::ListClosedAuctions { closed } => closed.unwrap_or_default(),
+            _ => panic!("Unexpected ListClosedAuctions answer"),
+        }
+    }
+}
+
+/// the message creating a 1000 SELL for a minimum 100 BID auction closing at `ends_at`
+fn create_msg(label: &str, ends_at: u64, tags: &[&str]) -> HandleMsg {
+    let contract = |address: &str, code_hash: &str| ContractInfo {
+        code_hash: code_hash.to_string(),
+        address: HumanAddr(address.to_string()),
+        token_id: None,
+    };
+    HandleMsg::CreateAuction {
+        label: Some(label.to_string()),
+        sell_contract: contract(SELL_TOKEN, "sellhash"),
+        bid_contract: contract(BID_TOKEN, "bidhash"),
+        sell_amount: Uint128(1000),
+        minimum_bid: Uint128(100),
+        ends_at,
+        description: None,
+        descriptions: vec![],
+        nickname: Some(format!("{} nick", label)),
+        tags: tags.iter().map(|t| t.to_string()).collect(),
+        bundle: vec![],
+        deposit: None,
+        consign_by: None,
+        buy_now_price: None,
+        no_self_bid: false,
+        only_increasing_rebids: false,
+        tie_break: TieBreak::EarliestWins,
+        allowance_bids: false,
+        message_board: false,
+        min_bid_decay: None,
+        seller_confirmation: None,
+        unit_price_bids: false,
+        max_bidders: None,
+        min_bidders: None,
+        reveal_window: None,
+        settlement: SettlementMode::HighestBid,
+        funding_target: None,
+        referrer: None,
+    }
+}
+
+fn place_bid() -> Option<auction_msg::ReceiveMsg> {
+    Some(auction_msg::ReceiveMsg::PlaceBid {
+        expiration: None,
+        max_bid: None,
+        unit_price: None,
+        memo: None,
+        referrer: None,
+    })
+}
+
 #[test]
-#[ignore]
-fn empty_test() {}
+fn test_create_register_bid_close_list() {
+    let mut chain = Chain::new();
+    chain.mint(SELL_TOKEN, SELLER, 1000);
+    chain.mint(BID_TOKEN, "bob", 500);
+    chain.mint(BID_TOKEN, "carol", 500);
+
+    // creating the auction instantiates it, it registers with the factory and its tokens, and
+    // the factory consigns the seller's tokens
+    let auction = chain.create_auction("first", 1000);
+    assert_eq!(chain.active_labels(), vec!["first".to_string()]);
+    for token in [SELL_TOKEN, BID_TOKEN].iter() {
+        assert_eq!(
+            chain
+                .receivers
+                .get(&(token.to_string(), auction.clone()))
+                .map(String::as_str),
+            Some(AUCTION_HASH)
+        );
+    }
+
+    assert_eq!(chain.balance(SELL_TOKEN, SELLER), 0);
+    match chain.auction_status(&auction) {
+        auction_msg::QueryAnswer::Status {
+            tokens_consigned, ..
+        } => assert!(tokens_consigned),
+        _ => panic!("Unexpected Status answer"),
+    }
+
+    // bid through the token
+    chain
+        .send("bob", BID_TOKEN, &auction, 200, place_bid())
+        .unwrap();
+    chain
+        .send("carol", BID_TOKEN, &auction, 300, place_bid())
+        .unwrap();
+    assert_eq!(chain.balance(SELL_TOKEN, &auction), 1000);
+    assert_eq!(chain.balance(BID_TOKEN, &auction), 500);
+
+    // the bidders now see the auction in their active lists
+    let key = chain.viewing_key("carol");
+    let mine = QueryMsg::ListMyAuctions {
+        address: HumanAddr("carol".to_string()),
+        viewing_key: key.clone(),
+        filter: Some(FilterTypes::Active),
+    };
+    match chain.factory_query(mine) {
+        QueryAnswer::ListMyAuctions { active, .. } => {
+            let as_bidder = active.unwrap().as_bidder.unwrap();
+            assert_eq!(as_bidder.len(), 1);
+            assert_eq!(as_bidder[0].address, HumanAddr(auction.clone()));
+            assert_eq!(as_bidder[0].my_bid, Some(Uint128(300)));
+        }
+        _ => panic!("Unexpected ListMyAuctions answer"),
+    }
+    // the seller's list does not show any bid amount
+    let seller_key = chain.viewing_key(SELLER);
+    let theirs = QueryMsg::ListMyAuctions {
+        address: HumanAddr(SELLER.to_string()),
+        viewing_key: seller_key,
+        filter: Some(FilterTypes::Active),
+    };
+    match chain.factory_query(theirs) {
+        QueryAnswer::ListMyAuctions { active, .. } => {
+            let as_seller = active.unwrap().as_seller.unwrap();
+            assert!(as_seller[0].my_bid.is_none());
+        }
+        _ => panic!("Unexpected ListMyAuctions answer"),
+    }
+
+    // anyone may close the auction after its closing time
+    chain.time = 1001;
+    let finalize = auction_msg::HandleMsg::Finalize {
+        new_ends_at: None,
+        new_minimum_bid: None,
+    };
+    chain.auction_handle("dave", &auction, finalize).unwrap();
+
+    // the tokens were settled
+    assert_eq!(chain.balance(SELL_TOKEN, "carol"), 1000);
+    assert_eq!(chain.balance(BID_TOKEN, SELLER), 300);
+    assert_eq!(chain.balance(BID_TOKEN, "bob"), 500);
+    assert_eq!(chain.balance(BID_TOKEN, "carol"), 200);
+    assert_eq!(chain.balance(SELL_TOKEN, &auction), 0);
+    assert_eq!(chain.balance(BID_TOKEN, &auction), 0);
+    match chain.auction_status(&auction) {
+        auction_msg::QueryAnswer::Status { is_completed, .. } => assert!(is_completed),
+        _ => panic!("Unexpected Status answer"),
+    }
+
+    // and the factory moved the auction to its closed list
+    assert!(chain.active_labels().is_empty());
+    let closed = chain.closed();
+    assert_eq!(closed.len(), 1);
+    assert_eq!(closed[0].address, HumanAddr(auction.clone()));
+    assert_eq!(closed[0].winning_bid, Some(Uint128(300)));
+    // a thousandth of a sell token sold for 300, so a whole token was priced at 300000
+    assert_eq!(closed[0].winning_unit_price, Some(Uint128(300_000)));
+    assert!(closed[0].amount_paid.is_none());
+    let mine = QueryMsg::ListMyAuctions {
+        address: HumanAddr("carol".to_string()),
+        viewing_key: key,
+        filter: Some(FilterTypes::Closed),
+    };
+    match chain.factory_query(mine) {
+        QueryAnswer::ListMyAuctions { closed, .. } => {
+            let won = closed.unwrap().won.unwrap();
+            assert_eq!(won.len(), 1);
+            assert_eq!(won[0].address, HumanAddr(auction));
+            assert_eq!(won[0].amount_paid, Some(Uint128(300)));
+            assert_eq!(won[0].amount_won, Some(Uint128(1000)));
+        }
+        _ => panic!("Unexpected ListMyAuctions answer"),
+    }
+}
+
+#[test]
+fn test_list_auctions_by_tag() {
+    let mut chain = Chain::new();
+    chain.mint(SELL_TOKEN, SELLER, 3000);
+    let by_tag = |chain: &Chain, tag: &str, page: Option<u32>, page_size: Option<u32>| {
+        let list = QueryMsg::ListAuctionsByTag {
+            tag: tag.to_string(),
+            page,
+            page_size,
+        };
+        match chain.factory_query(list) {
+            QueryAnswer::ListAuctionsByTag { auctions, count } => (
+                auctions
+                    .into_iter()
+                    .map(|a| a.label)
+                    .collect::<Vec<String>>(),
+                count,
+            ),
+            _ => panic!("Unexpected ListAuctionsByTag answer"),
+        }
+    };
+
+    // tags are matched regardless of case, and duplicates are ignored
+    let first = chain.create_tagged_auction("first", 1000, &["OTC", "stablecoin", "otc"]);
+    chain.create_tagged_auction("second", 2000, &["otc"]);
+    chain.create_auction("untagged", 2000);
+    let create = HandleMsg::CreateAuction {
+        label: Some("too many".to_string()),
+        sell_contract: ContractInfo {
+            code_hash: "sellhash".to_string(),
+            address: HumanAddr(SELL_TOKEN.to_string()),
+            token_id: None,
+        },
+        bid_contract: ContractInfo {
+            code_hash: "bidhash".to_string(),
+            address: HumanAddr(BID_TOKEN.to_string()),
+            token_id: None,
+        },
+        sell_amount: Uint128(1000),
+        minimum_bid: Uint128(100),
+        ends_at: 1000,
+        description: None,
+        descriptions: vec![],
+        nickname: None,
+        tags: (0..6).map(|i| format!("tag{}", i)).collect(),
+        bundle: vec![],
+        deposit: None,
+        consign_by: None,
+        buy_now_price: None,
+        no_self_bid: false,
+        only_increasing_rebids: false,
+        tie_break: TieBreak::EarliestWins,
+        allowance_bids: false,
+        message_board: false,
+        min_bid_decay: None,
+        seller_confirmation: None,
+        unit_price_bids: false,
+        max_bidders: None,
+        min_bidders: None,
+        reveal_window: None,
+        settlement: SettlementMode::HighestBid,
+        funding_target: None,
+        referrer: None,
+    };
+    let error = chain.factory_handle(SELLER, create).unwrap_err();
+    assert!(error.to_string().contains("can not have more than 5 tags"));
+
+    assert_eq!(
+        by_tag(&chain, "Otc", None, None),
+        (vec!["first".to_string(), "second".to_string()], 2)
+    );
+    assert_eq!(
+        by_tag(&chain, "otc", Some(1), Some(1)),
+        (vec!["second".to_string()], 2)
+    );
+    assert_eq!(
+        by_tag(&chain, "stablecoin", None, None),
+        (vec!["first".to_string()], 1)
+    );
+    assert_eq!(by_tag(&chain, "nft", None, None), (Vec::<String>::new(), 0));
+
+    // closed auctions are no longer listed under their tags
+    chain.time = 1001;
+    let finalize = auction_msg::HandleMsg::Finalize {
+        new_ends_at: None,
+        new_minimum_bid: None,
+    };
+    chain.auction_handle("dave", &first, finalize).unwrap();
+    assert_eq!(
+        by_tag(&chain, "otc", None, None),
+        (vec!["second".to_string()], 1)
+    );
+    assert_eq!(
+        by_tag(&chain, "stablecoin", None, None),
+        (Vec::<String>::new(), 0)
+    );
+}
+
+#[test]
+fn test_creation_fee() {
+    let mut chain = Chain::new();
+    let set_fee = |fee: Option<u128>| HandleMsg::SetCreationFee {
+        fee: fee.map(|amount| CreationFee {
+            amount: Uint128(amount),
+            treasury: Some(HumanAddr("treasury".to_string())),
+        }),
+    };
+    let error = chain
+        .factory_handle(SELLER, set_fee(Some(500)))
+        .unwrap_err();
+    assert!(error.to_string().contains("admin command"));
+    chain.factory_handle(ADMIN, set_fee(Some(500))).unwrap();
+    match chain.factory_query(QueryMsg::Config {}) {
+        QueryAnswer::Config { creation_fee, .. } => assert_eq!(
+            creation_fee,
+            Some(CreationFee {
+                amount: Uint128(500),
+                treasury: Some(HumanAddr("treasury".to_string())),
+            })
+        ),
+        _ => panic!("Unexpected QueryAnswer"),
+    }
+
+    // creations without the exact fee are rejected
+    for funds in [vec![], coins(499, "uscrt"), coins(500, "uatom")].iter() {
+        let error = chain
+            .factory_handle_with_funds(SELLER, create_msg("nofee", 1000, &[]), funds)
+            .unwrap_err();
+        assert!(error.to_string().contains("fee of exactly 500uscrt"));
+    }
+    assert!(chain.auctions.is_empty());
+
+    // the fee is forwarded to the treasury
+    chain
+        .factory_handle_with_funds(SELLER, create_msg("paid", 1000, &[]), &coins(500, "uscrt"))
+        .unwrap();
+    assert_eq!(chain.active_labels(), vec!["paid".to_string()]);
+    assert_eq!(chain.balance("uscrt", "treasury"), 500);
+
+    // removing the fee makes creation free again
+    chain.factory_handle(ADMIN, set_fee(None)).unwrap();
+    match chain.factory_query(QueryMsg::Config {}) {
+        QueryAnswer::Config { creation_fee, .. } => assert!(creation_fee.is_none()),
+        _ => panic!("Unexpected QueryAnswer"),
+    }
+    chain.create_auction("free", 1000);
+    assert_eq!(chain.active_labels().len(), 2);
+}
+
+#[test]
+fn test_treasury() {
+    let mut chain = Chain::new();
+    let scrt = Asset::Native {
+        denom: "uscrt".to_string(),
+    };
+    // without a treasury address, creation fees accumulate in the factory
+    let set_fee = HandleMsg::SetCreationFee {
+        fee: Some(CreationFee {
+            amount: Uint128(300),
+            treasury: None,
+        }),
+    };
+    chain.factory_handle(ADMIN, set_fee).unwrap();
+    for label in ["first", "second"].iter() {
+        chain
+            .factory_handle_with_funds(SELLER, create_msg(label, 1000, &[]), &coins(300, "uscrt"))
+            .unwrap();
+    }
+    assert_eq!(chain.balance("uscrt", "treasury"), 0);
+
+    // only the admin may withdraw, and only what the treasury holds
+    let withdraw = |amount: u128| HandleMsg::Withdraw {
+        asset: scrt.clone(),
+        amount: Uint128(amount),
+        to: HumanAddr("treasury".to_string()),
+    };
+    let error = chain.factory_handle(SELLER, withdraw(100)).unwrap_err();
+    assert!(error.to_string().contains("admin command"));
+    let error = chain.factory_handle(ADMIN, withdraw(601)).unwrap_err();
+    assert!(error.to_string().contains("only holds 600"));
+    chain.factory_handle(ADMIN, withdraw(400)).unwrap();
+    assert_eq!(chain.balance("uscrt", "treasury"), 400);
+    match chain.factory_query(QueryMsg::TreasuryBalances {}) {
+        QueryAnswer::TreasuryBalances { balances } => {
+            assert_eq!(balances.len(), 1);
+            assert_eq!(
+                (
+                    balances[0].balance,
+                    balances[0].received,
+                    balances[0].withdrawn
+                ),
+                (Uint128(200), Uint128(600), Uint128(400))
+            );
+        }
+        _ => panic!("Unexpected QueryAnswer"),
+    }
+
+    // only the admin may view the records
+    let records = |chain: &Chain, address: &str, key: &str| {
+        chain.factory_query(QueryMsg::TreasuryRecords {
+            address: HumanAddr(address.to_string()),
+            viewing_key: key.to_string(),
+            asset: scrt.clone(),
+            before: None,
+            page_size: Some(2),
+        })
+    };
+    let seller_key = chain.viewing_key(SELLER);
+    assert!(matches!(
+        records(&chain, SELLER, &seller_key),
+        QueryAnswer::ViewingKeyError { .. }
+    ));
+    let admin_key = chain.viewing_key(ADMIN);
+    match records(&chain, ADMIN, &admin_key) {
+        QueryAnswer::TreasuryRecords { records, count } => {
+            assert_eq!(count, 3);
+            let actions: Vec<TreasuryAction> = records.into_iter().map(|r| r.action).collect();
+            assert_eq!(
+                actions,
+                vec![
+                    TreasuryAction::Withdrawal {},
+                    TreasuryAction::CreationFee { index: 1 }
+                ]
+            );
+        }
+        _ => panic!("Unexpected QueryAnswer"),
+    }
+}
+
+#[test]
+fn test_admin_auction_info() {
+    let mut chain = Chain::new();
+    chain.mint(SELL_TOKEN, SELLER, 1000);
+    let address = chain.create_auction("first", 1000);
+    let admin_key = chain.viewing_key(ADMIN);
+    let seller_key = chain.viewing_key(SELLER);
+    let info = |address: &str, key: &str, index: u32| QueryMsg::AdminAuctionInfo {
+        address: HumanAddr(address.to_string()),
+        viewing_key: key.to_string(),
+        index,
+    };
+
+    // only the admin may view registration records
+    assert!(matches!(
+        chain.factory_query(info(SELLER, &seller_key, 0)),
+        QueryAnswer::ViewingKeyError { .. }
+    ));
+    let error = query(&chain.factory, info(ADMIN, &admin_key, 1)).unwrap_err();
+    assert!(error.to_string().contains("Auction 1 has not been created"));
+
+    match chain.factory_query(info(ADMIN, &admin_key, 0)) {
+        QueryAnswer::AdminAuctionInfo {
+            active,
+            closed,
+            seller,
+            code_hash,
+            pending,
+            has_callback_key,
+            has_settlement,
+            ..
+        } => {
+            let active = active.unwrap();
+            assert_eq!(active.address, HumanAddr(address.clone()));
+            assert_eq!(active.label, "first");
+            assert!(closed.is_none());
+            assert_eq!(seller, Some(HumanAddr(SELLER.to_string())));
+            assert_eq!(code_hash, Some(AUCTION_HASH.to_string()));
+            assert!(!pending);
+            assert!(has_callback_key);
+            assert!(!has_settlement);
+        }
+        _ => panic!("Unexpected QueryAnswer"),
+    }
+
+    // once closed, the active record and the callback key are gone
+    chain.time = 1001;
+    let finalize = auction_msg::HandleMsg::Finalize {
+        new_ends_at: None,
+        new_minimum_bid: None,
+    };
+    chain.auction_handle("dave", &address, finalize).unwrap();
+    match chain.factory_query(info(ADMIN, &admin_key, 0)) {
+        QueryAnswer::AdminAuctionInfo {
+            active,
+            closed,
+            closed_position,
+            has_callback_key,
+            has_settlement,
+            pruned,
+            ..
+        } => {
+            assert!(active.is_none());
+            let closed = closed.unwrap();
+            assert_eq!(closed.label, "first");
+            assert_eq!(closed.winning_bid, None);
+            assert_eq!(closed_position, Some(0));
+            assert!(!has_callback_key);
+            assert!(has_settlement);
+            assert!(!pruned);
+        }
+        _ => panic!("Unexpected QueryAnswer"),
+    }
+}
+
+#[test]
+fn test_referral_fees() {
+    let mut chain = Chain::new();
+    chain.mint(SELL_TOKEN, SELLER, 2000);
+    chain.mint(BID_TOKEN, "bob", 500);
+    let set_fee = HandleMsg::SetCreationFee {
+        fee: Some(CreationFee {
+            amount: Uint128(1000),
+            treasury: None,
+        }),
+    };
+    chain.factory_handle(ADMIN, set_fee).unwrap();
+    let set_share = |basis_points: u16| HandleMsg::SetReferralShare { basis_points };
+    let error = chain.factory_handle(ADMIN, set_share(10001)).unwrap_err();
+    assert!(error.to_string().contains("can not be more than 10000"));
+    chain.factory_handle(ADMIN, set_share(2010)).unwrap();
+
+    // the seller was referred by erin
+    let referred = |label: &str, referrer: &str| {
+        let mut msg = create_msg(label, 1000, &[]);
+        if let HandleMsg::CreateAuction { referrer: r, .. } = &mut msg {
+            *r = Some(HumanAddr(referrer.to_string()));
+        }
+        msg
+    };
+    let fee = coins(1000, "uscrt");
+    let error = chain
+        .factory_handle_with_funds(SELLER, referred("self", SELLER), &fee)
+        .unwrap_err();
+    assert!(error.to_string().contains("can not refer themselves"));
+    chain
+        .factory_handle_with_funds(SELLER, referred("sold", "erin"), &fee)
+        .unwrap();
+    let sold = "auction0".to_string();
+    chain
+        .factory_handle_with_funds(SELLER, referred("unsold", "erin"), &fee)
+        .unwrap();
+    let unsold = "auction1".to_string();
+
+    // the referral shares are reserved until the auctions close
+    match chain.factory_query(QueryMsg::TreasuryBalances {}) {
+        QueryAnswer::TreasuryBalances { balances } => {
+            assert_eq!(
+                (balances[0].balance, balances[0].reserved),
+                (Uint128(2000), Uint128(402))
+            );
+        }
+        _ => panic!("Unexpected QueryAnswer"),
+    }
+    let withdraw = |amount: u128| HandleMsg::Withdraw {
+        asset: Asset::Native {
+            denom: "uscrt".to_string(),
+        },
+        amount: Uint128(amount),
+        to: HumanAddr(ADMIN.to_string()),
+    };
+    let error = chain.factory_handle(ADMIN, withdraw(1599)).unwrap_err();
+    assert!(error.to_string().contains("only holds 1598"));
+    // changing the share does not change what was reserved
+    chain.factory_handle(ADMIN, set_share(0)).unwrap();
+
+    // and the winning bidder by dave
+    let bid = Some(auction_msg::ReceiveMsg::PlaceBid {
+        expiration: None,
+        max_bid: None,
+        unit_price: None,
+        memo: None,
+        referrer: Some(HumanAddr("dave".to_string())),
+    });
+    chain.send("bob", BID_TOKEN, &sold, 200, bid).unwrap();
+    chain.time = 1001;
+    for auction in [&sold, &unsold].iter() {
+        let finalize = auction_msg::HandleMsg::Finalize {
+            new_ends_at: None,
+            new_minimum_bid: None,
+        };
+        chain.auction_handle(SELLER, auction, finalize).unwrap();
+    }
+
+    // only the sale paid referrals, splitting 20.1% of its fee with the remainder to erin
+    assert_eq!(chain.balance("uscrt", "erin"), 101);
+    assert_eq!(chain.balance("uscrt", "dave"), 100);
+    match chain.factory_query(QueryMsg::TreasuryBalances {}) {
+        QueryAnswer::TreasuryBalances { balances } => {
+            assert_eq!(
+                (balances[0].balance, balances[0].reserved),
+                (Uint128(1799), Uint128(0))
+            );
+        }
+        _ => panic!("Unexpected QueryAnswer"),
+    }
+    chain.factory_handle(ADMIN, withdraw(1799)).unwrap();
+    let key = chain.viewing_key("erin");
+    let earnings = |address: &str, key: &str| QueryMsg::ReferralEarnings {
+        address: HumanAddr(address.to_string()),
+        viewing_key: key.to_string(),
+    };
+    match chain.factory_query(earnings("erin", &key)) {
+        QueryAnswer::ReferralEarnings { earned, payouts } => {
+            assert_eq!(earned, Uint128(101));
+            assert_eq!(payouts, 1);
+        }
+        _ => panic!("Unexpected QueryAnswer"),
+    }
+    assert!(matches!(
+        chain.factory_query(earnings("dave", &key)),
+        QueryAnswer::ViewingKeyError { .. }
+    ));
+}
+
+#[test]
+fn test_forwarded_fee_referrals() {
+    let mut chain = Chain::new();
+    chain.mint(SELL_TOKEN, SELLER, 1000);
+    chain.mint(BID_TOKEN, "bob", 500);
+    let set_fee = HandleMsg::SetCreationFee {
+        fee: Some(CreationFee {
+            amount: Uint128(1000),
+            treasury: Some(HumanAddr("treasury".to_string())),
+        }),
+    };
+    chain.factory_handle(ADMIN, set_fee).unwrap();
+    chain
+        .factory_handle(ADMIN, HandleMsg::SetReferralShare { basis_points: 2000 })
+        .unwrap();
+
+    // the referral share stays in the factory and only the rest is forwarded
+    let mut msg = create_msg("forwarded", 1000, &[]);
+    if let HandleMsg::CreateAuction { referrer, .. } = &mut msg {
+        *referrer = Some(HumanAddr("erin".to_string()));
+    }
+    chain
+        .factory_handle_with_funds(SELLER, msg, &coins(1000, "uscrt"))
+        .unwrap();
+    let auction = "auction0".to_string();
+    assert_eq!(chain.balance("uscrt", "treasury"), 800);
+    match chain.factory_query(QueryMsg::TreasuryBalances {}) {
+        QueryAnswer::TreasuryBalances { balances } => {
+            assert_eq!(
+                (balances[0].balance, balances[0].reserved),
+                (Uint128(200), Uint128(200))
+            );
+        }
+        _ => panic!("Unexpected QueryAnswer"),
+    }
+
+    // so the referrer is paid when the auction sells
+    let bid = Some(auction_msg::ReceiveMsg::PlaceBid {
+        expiration: None,
+        max_bid: None,
+        unit_price: None,
+        memo: None,
+        referrer: None,
+    });
+    chain.send("bob", BID_TOKEN, &auction, 200, bid).unwrap();
+    chain.time = 1001;
+    let finalize = auction_msg::HandleMsg::Finalize {
+        new_ends_at: None,
+        new_minimum_bid: None,
+    };
+    chain.auction_handle(SELLER, &auction, finalize).unwrap();
+    assert_eq!(chain.balance("uscrt", "erin"), 200);
+    match chain.factory_query(QueryMsg::TreasuryBalances {}) {
+        QueryAnswer::TreasuryBalances { balances } => {
+            assert_eq!(
+                (balances[0].balance, balances[0].reserved),
+                (Uint128(0), Uint128(0))
+            );
+        }
+        _ => panic!("Unexpected QueryAnswer"),
+    }
+}
+
+#[test]
+fn test_retract_and_close_without_sale() {
+    let mut chain = Chain::new();
+    chain.mint(SELL_TOKEN, SELLER, 2000);
+    chain.mint(BID_TOKEN, "bob", 500);
+
+    let first = chain.create_auction("first", 1000);
+    let second = chain.create_auction("second", 2000);
+    assert_ne!(first, second);
+    let mut labels = chain.active_labels();
+    labels.sort();
+    assert_eq!(labels, vec!["first".to_string(), "second".to_string()]);
+
+    // a bid that does not meet the minimum is returned to the bidder
+    chain
+        .send("bob", BID_TOKEN, &first, 50, place_bid())
+        .unwrap();
+    assert_eq!(chain.balance(BID_TOKEN, "bob"), 500);
+    chain
+        .send("bob", BID_TOKEN, &first, 150, place_bid())
+        .unwrap();
+    chain
+        .auction_handle("bob", &first, auction_msg::HandleMsg::RetractBid {})
+        .unwrap();
+    assert_eq!(chain.balance(BID_TOKEN, "bob"), 500);
+
+    // closing an auction without bids returns the consignment and lists it with no sale
+    chain.time = 1001;
+    let finalize = auction_msg::HandleMsg::Finalize {
+        new_ends_at: None,
+        new_minimum_bid: None,
+    };
+    chain.auction_handle("dave", &first, finalize).unwrap();
+    assert_eq!(chain.active_labels(), vec!["second".to_string()]);
+    let closed = chain.closed();
+    assert_eq!(closed.len(), 1);
+    assert_eq!(closed[0].label, "first");
+    assert_eq!(closed[0].winning_bid, None);
+    assert_eq!(chain.balance(SELL_TOKEN, SELLER), 1000);
+}
+
+#[test]
+fn test_min_bidders() {
+    let mut chain = Chain::new();
+    chain.mint(SELL_TOKEN, SELLER, 1000);
+    chain.mint(BID_TOKEN, "bob", 500);
+    let mut msg = create_msg("fair launch", 1000, &[]);
+    if let HandleMsg::CreateAuction { min_bidders, .. } = &mut msg {
+        *min_bidders = Some(2);
+    }
+    chain.factory_handle(SELLER, msg).unwrap();
+    let address = format!("auction{}", chain.auctions.len() - 1);
+    chain
+        .send("bob", BID_TOKEN, &address, 150, place_bid())
+        .unwrap();
+
+    // with only one bidder, closing returns the bid and the consignment
+    chain.time = 1001;
+    let finalize = auction_msg::HandleMsg::Finalize {
+        new_ends_at: None,
+        new_minimum_bid: None,
+    };
+    chain.auction_handle("bob", &address, finalize).unwrap();
+    assert_eq!(chain.balance(BID_TOKEN, "bob"), 500);
+    assert_eq!(chain.balance(SELL_TOKEN, SELLER), 1000);
+    match chain.factory_query(QueryMsg::SettlementRecord { index: 0 }) {
+        QueryAnswer::SettlementRecord {
+            winning_bid,
+            unmet_min_bidders,
+            outcome,
+            ..
+        } => {
+            assert_eq!(winning_bid, None);
+            assert_eq!(unmet_min_bidders, Some(2));
+            assert_eq!(outcome, SettlementOutcome::TooFewBidders);
+        }
+        _ => panic!("Unexpected QueryAnswer"),
+    }
+}
+
+#[test]
+fn test_phased_shutdown() {
+    let mut chain = Chain::new();
+    chain.mint(SELL_TOKEN, SELLER, 2000);
+    for bidder in ["bob", "carol", "dave", "erin"].iter() {
+        chain.mint(BID_TOKEN, bidder, 500);
+    }
+    let old = chain.create_auction("old", 1000);
+    let new_contract = HandleMsg::NewAuctionContract {
+        auction_contract: AuctionContractInfo {
+            code_id: 2,
+            code_hash: AUCTION_HASH.to_string(),
+        },
+        deprecate_old: false,
+        deprecation_notice: None,
+        page_size: None,
+    };
+    chain.factory_handle(ADMIN, new_contract).unwrap();
+    let new = chain.create_auction("new", 1000);
+    let status = |chain: &Chain| match chain.factory_query(QueryMsg::Status {}) {
+        QueryAnswer::Status {
+            status, code_id, ..
+        } => {
+            assert_eq!(code_id, 2);
+            status
+        }
+        _ => panic!("Unexpected Status answer"),
+    };
+    assert_eq!(status(&chain), FactoryStatus::default());
+    let set_status = |creation: Option<bool>, bids: Option<bool>, old_versions: Option<bool>| {
+        HandleMsg::SetStatus {
+            stop_creation: creation,
+            stop_bids: bids,
+            stop_old_versions: old_versions,
+        }
+    };
+    let error = chain
+        .factory_handle(SELLER, set_status(Some(true), None, None))
+        .unwrap_err();
+    assert!(error.to_string().contains("admin command"));
+
+    // auctions of the earlier version can no longer register bids
+    chain
+        .factory_handle(ADMIN, set_status(None, None, Some(true)))
+        .unwrap();
+    let error = chain
+        .send("bob", BID_TOKEN, &old, 200, place_bid())
+        .unwrap_err();
+    assert!(error.to_string().contains("stopped registering new bids"));
+    chain
+        .send("carol", BID_TOKEN, &new, 200, place_bid())
+        .unwrap();
+
+    // stopping creation and all bids leaves the other switch alone
+    chain
+        .factory_handle(ADMIN, set_status(Some(true), Some(true), None))
+        .unwrap();
+    assert_eq!(
+        status(&chain),
+        FactoryStatus {
+            creation_stopped: true,
+            bids_stopped: true,
+            old_versions_stopped: true,
+        }
+    );
+    let error = chain
+        .factory_handle(SELLER, create_msg("late", 1000, &[]))
+        .unwrap_err();
+    assert!(error.to_string().contains("No new auctions can be created"));
+    let error = chain
+        .send("dave", BID_TOKEN, &new, 300, place_bid())
+        .unwrap_err();
+    assert!(error.to_string().contains("stopped registering new bids"));
+
+    // restarting bids still leaves the earlier version stopped
+    chain
+        .factory_handle(ADMIN, set_status(None, Some(false), None))
+        .unwrap();
+    chain
+        .send("erin", BID_TOKEN, &new, 300, place_bid())
+        .unwrap();
+    assert!(chain
+        .send("erin", BID_TOKEN, &old, 200, place_bid())
+        .is_err());
+
+    // a bidder may still raise a bid they placed before bids were stopped
+    chain
+        .factory_handle(ADMIN, set_status(None, Some(true), None))
+        .unwrap();
+    chain
+        .send("carol", BID_TOKEN, &new, 250, place_bid())
+        .unwrap();
+    let key = chain.viewing_key("carol");
+    let mine = QueryMsg::ListMyAuctions {
+        address: HumanAddr("carol".to_string()),
+        viewing_key: key,
+        filter: Some(FilterTypes::Active),
+    };
+    match chain.factory_query(mine) {
+        QueryAnswer::ListMyAuctions { active, .. } => {
+            let as_bidder = active.unwrap().as_bidder.unwrap();
+            assert_eq!(as_bidder[0].my_bid, Some(Uint128(250)));
+        }
+        _ => panic!("Unexpected ListMyAuctions answer"),
+    }
+}
+
+#[test]
+fn test_deprecation_notices() {
+    let mut chain = Chain::new();
+    chain.mint(SELL_TOKEN, SELLER, 2000);
+    let old = chain.create_auction("old", 1000);
+    let new_contract = |code_id: u64, notice: Option<String>| HandleMsg::NewAuctionContract {
+        auction_contract: AuctionContractInfo {
+            code_id,
+            code_hash: AUCTION_HASH.to_string(),
+        },
+        deprecate_old: false,
+        deprecation_notice: notice,
+        page_size: None,
+    };
+    let error = chain
+        .factory_handle(ADMIN, new_contract(2, Some("a".repeat(257))))
+        .unwrap_err();
+    assert!(error.to_string().contains("longer than 256 bytes"));
+
+    // the notice is sent to the old auction, which displays it
+    let notice = "Please bid on the new version".to_string();
+    chain
+        .factory_handle(ADMIN, new_contract(2, Some(notice.clone())))
+        .unwrap();
+    let state: AuctionState =
+        auction_load(&chain.auctions[&old].storage, AUCTION_CONFIG_KEY).unwrap();
+    assert_eq!(state.deprecation_notice, Some(notice));
+
+    // and the factory lists it as deprecated
+    let new = chain.create_auction("new", 1000);
+    let deprecated = |chain: &Chain| -> Vec<(String, bool)> {
+        match chain.factory_query(QueryMsg::ListActiveAuctions { code_id: None }) {
+            QueryAnswer::ListActiveAuctions { active } => {
+                let mut flags: Vec<(String, bool)> = active
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|a| (a.address.0, a.deprecated))
+                    .collect();
+                flags.sort();
+                flags
+            }
+            _ => panic!("Unexpected ListActiveAuctions answer"),
+        }
+    };
+    assert_eq!(
+        deprecated(&chain),
+        vec![(new.clone(), false), (old.clone(), true)]
+    );
+    match chain.factory_query(QueryMsg::Status {}) {
+        QueryAnswer::Status {
+            deprecated_code_ids,
+            ..
+        } => assert_eq!(deprecated_code_ids, vec![1]),
+        _ => panic!("Unexpected Status answer"),
+    }
+
+    // returning to the earlier version undeprecates it
+    chain.factory_handle(ADMIN, new_contract(1, None)).unwrap();
+    assert_eq!(deprecated(&chain), vec![(new, false), (old, false)]);
+}
+
+#[test]
+fn test_prune_closed() {
+    let mut chain = Chain::new();
+    chain.mint(SELL_TOKEN, SELLER, 3000);
+    let auctions: Vec<String> = ["first", "second", "third"]
+        .iter()
+        .map(|label| chain.create_auction(label, 1000))
+        .collect();
+    // the auctions close one second apart
+    for (i, auction) in auctions.iter().enumerate() {
+        chain.time = 1001 + i as u64;
+        let finalize = auction_msg::HandleMsg::Finalize {
+            new_ends_at: None,
+            new_minimum_bid: None,
+        };
+        chain.auction_handle(SELLER, auction, finalize).unwrap();
+    }
+    let prune = |page_size: Option<u32>| HandleMsg::PruneClosed {
+        keep_after_timestamp: 1003,
+        page_size,
+    };
+    let status_message = |data: Option<Binary>| match from_binary(&data.unwrap()).unwrap() {
+        HandleAnswer::Status { message, .. } => message.unwrap(),
+        _ => panic!("Unexpected Status answer"),
+    };
+    let labels =
+        |chain: &Chain| -> Vec<String> { chain.closed().into_iter().map(|c| c.label).collect() };
+    let error = chain.factory_handle(SELLER, prune(None)).unwrap_err();
+    assert!(error.to_string().contains("admin command"));
+
+    // only a page of records is pruned at a time
+    let data = chain.factory_handle(ADMIN, prune(Some(1))).unwrap();
+    assert_eq!(
+        status_message(data),
+        "Pruned 1 closed auction records.  More records may be pruned with another prune_closed \
+         message"
+    );
+    assert_eq!(labels(&chain), vec!["third", "second"]);
+
+    // and pruning stops at the first record to keep
+    let data = chain.factory_handle(ADMIN, prune(None)).unwrap();
+    assert_eq!(status_message(data), "Pruned 1 closed auction records");
+    assert_eq!(labels(&chain), vec!["third"]);
+    let data = chain.factory_handle(ADMIN, prune(Some(5))).unwrap();
+    assert_eq!(status_message(data), "Pruned 0 closed auction records");
+    match chain.factory_query(QueryMsg::StorageInfo {}) {
+        QueryAnswer::StorageInfo {
+            closed_auctions,
+            pruned_auctions,
+            ..
+        } => assert_eq!((closed_auctions, pruned_auctions), (1, 2)),
+        _ => panic!("Unexpected StorageInfo answer"),
+    }
+}
+
+#[test]
+fn test_paginated_deprecation() {
+    let mut chain = Chain::new();
+    chain.mint(SELL_TOKEN, SELLER, 3000);
+    let auctions: Vec<String> = ["first", "second", "third"]
+        .iter()
+        .map(|label| chain.create_auction(label, 1000))
+        .collect();
+    let notice = "Please bid on the new version".to_string();
+    let noticed = |chain: &Chain| -> Vec<bool> {
+        auctions
+            .iter()
+            .map(|auction| {
+                let state: AuctionState =
+                    auction_load(&chain.auctions[auction].storage, AUCTION_CONFIG_KEY).unwrap();
+                state.deprecation_notice.is_some()
+            })
+            .collect()
+    };
+    let status_message = |data: Option<Binary>| match from_binary(&data.unwrap()).unwrap() {
+        HandleAnswer::Status { message, .. } => message,
+        _ => panic!("Unexpected Status answer"),
+    };
+    let error = chain
+        .factory_handle(ADMIN, HandleMsg::ContinueDeprecation { page_size: None })
+        .unwrap_err();
+    assert!(error.to_string().contains("no deprecation in progress"));
+
+    // only the first page of old auctions is warned
+    let new_contract = HandleMsg::NewAuctionContract {
+        auction_contract: AuctionContractInfo {
+            code_id: 2,
+            code_hash: AUCTION_HASH.to_string(),
+        },
+        deprecate_old: false,
+        deprecation_notice: Some(notice),
+        page_size: Some(2),
+    };
+    let data = chain.factory_handle(ADMIN, new_contract).unwrap();
+    assert_eq!(
+        status_message(data),
+        Some("1 active auctions remain to be checked for deprecation".to_string())
+    );
+    assert_eq!(noticed(&chain), vec![true, true, false]);
+
+    // and only the admin may continue with the rest
+    let error = chain
+        .factory_handle(SELLER, HandleMsg::ContinueDeprecation { page_size: None })
+        .unwrap_err();
+    assert!(error.to_string().contains("admin command"));
+    let data = chain
+        .factory_handle(ADMIN, HandleMsg::ContinueDeprecation { page_size: None })
+        .unwrap();
+    assert_eq!(
+        status_message(data),
+        Some("Deprecation is complete".to_string())
+    );
+    assert_eq!(noticed(&chain), vec![true, true, true]);
+    let error = chain
+        .factory_handle(ADMIN, HandleMsg::ContinueDeprecation { page_size: None })
+        .unwrap_err();
+    assert!(error.to_string().contains("no deprecation in progress"));
+}
+
+#[test]
+fn test_auction_versions() {
+    let mut chain = Chain::new();
+    chain.mint(SELL_TOKEN, SELLER, 2000);
+    chain.mint(BID_TOKEN, "bob", 500);
+    let old = chain.create_auction("old", 1000);
+    let new_contract = HandleMsg::NewAuctionContract {
+        auction_contract: AuctionContractInfo {
+            code_id: 2,
+            code_hash: AUCTION_HASH.to_string(),
+        },
+        deprecate_old: false,
+        deprecation_notice: None,
+        page_size: None,
+    };
+    chain.factory_handle(ADMIN, new_contract).unwrap();
+    let new = chain.create_auction("new", 1000);
+    let labels = |chain: &Chain, code_id: Option<u64>| -> Vec<(String, u64)> {
+        match chain.factory_query(QueryMsg::ListActiveAuctions { code_id }) {
+            QueryAnswer::ListActiveAuctions { active } => {
+                let mut labels: Vec<(String, u64)> = active
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|a| (a.label, a.code_id))
+                    .collect();
+                labels.sort();
+                labels
+            }
+            _ => panic!("Unexpected ListActiveAuctions answer"),
+        }
+    };
+    assert_eq!(
+        labels(&chain, None),
+        vec![("new".to_string(), 2), ("old".to_string(), 1)]
+    );
+    assert_eq!(labels(&chain, Some(1)), vec![("old".to_string(), 1)]);
+    assert_eq!(labels(&chain, Some(2)), vec![("new".to_string(), 2)]);
+    assert!(labels(&chain, Some(3)).is_empty());
+
+    // only the admin can revoke, and never the current version
+    let revoke = |code_id: u64| HandleMsg::RevokeAuctionVersion {
+        code_id,
+        revoked: true,
+    };
+    let error = chain.factory_handle(SELLER, revoke(1)).unwrap_err();
+    assert!(error.to_string().contains("admin command"));
+    let error = chain.factory_handle(ADMIN, revoke(2)).unwrap_err();
+    assert!(error.to_string().contains("can not be revoked"));
+    chain.factory_handle(ADMIN, revoke(1)).unwrap();
+    match chain.factory_query(QueryMsg::Status {}) {
+        QueryAnswer::Status {
+            revoked_code_ids, ..
+        } => assert_eq!(revoked_code_ids, vec![1]),
+        _ => panic!("Unexpected Status answer"),
+    }
+
+    // a revoked version can not take bids
+    let error = chain
+        .send("bob", BID_TOKEN, &old, 100, place_bid())
+        .unwrap_err();
+    assert!(error.to_string().contains("has been revoked"));
+    chain
+        .send("bob", BID_TOKEN, &new, 100, place_bid())
+        .unwrap();
+
+    // but the factory still records its close, like the current version's
+    chain.time = 1001;
+    let finalize = || auction_msg::HandleMsg::Finalize {
+        new_ends_at: None,
+        new_minimum_bid: None,
+    };
+    chain.auction_handle("dave", &old, finalize()).unwrap();
+    chain.auction_handle("dave", &new, finalize()).unwrap();
+    assert!(labels(&chain, None).is_empty());
+    let mut closed: Vec<(String, u64)> = chain
+        .closed()
+        .into_iter()
+        .map(|c| (c.label, c.code_id))
+        .collect();
+    closed.sort();
+    assert_eq!(closed, vec![("new".to_string(), 2), ("old".to_string(), 1)]);
+}
+
+#[test]
+fn test_duplicate_labels() {
+    let mut chain = Chain::new();
+    chain.mint(SELL_TOKEN, SELLER, 2000);
+    let first = chain.create_auction("first", 1000);
+    let error = chain
+        .factory_handle(SELLER, create_msg("first", 2000, &[]))
+        .unwrap_err();
+    assert!(error
+        .to_string()
+        .contains("An auction with the label first already exists"));
+    assert_eq!(chain.active_labels(), vec!["first".to_string()]);
+
+    let by_label = |chain: &Chain, label: &str| -> Option<IndexedAuctionInfo> {
+        let query = QueryMsg::AuctionByLabel {
+            label: label.to_string(),
+        };
+        match chain.factory_query(query) {
+            QueryAnswer::AuctionByLabel { auction } => auction,
+            _ => panic!("Unexpected AuctionByLabel answer"),
+        }
+    };
+    assert!(by_label(&chain, "second").is_none());
+    let found = by_label(&chain, "first").unwrap();
+    assert_eq!(found.index, 0);
+    assert_eq!(found.active.unwrap().address.0, first);
+
+    // the label stays taken after the auction closes
+    chain.time = 1001;
+    let finalize = auction_msg::HandleMsg::Finalize {
+        new_ends_at: None,
+        new_minimum_bid: None,
+    };
+    chain.auction_handle("dave", &first, finalize).unwrap();
+    let found = by_label(&chain, "first").unwrap();
+    assert!(found.active.is_none());
+    assert_eq!(found.closed.unwrap().label, "first");
+    assert!(chain
+        .factory_handle(SELLER, create_msg("first", 2000, &[]))
+        .is_err());
+}
+
+#[test]
+fn test_generated_labels() {
+    let mut chain = Chain::new();
+    chain.mint(SELL_TOKEN, SELLER, 3000);
+    chain.create_auction("first", 1000);
+    let mut unlabeled = create_msg("", 1000, &[]);
+    if let HandleMsg::CreateAuction { label, .. } = &mut unlabeled {
+        *label = None;
+    }
+    let data = chain.factory_handle(SELLER, unlabeled).unwrap().unwrap();
+    match from_binary(&data).unwrap() {
+        HandleAnswer::Status { message, .. } => {
+            assert_eq!(message, Some("auction-1-SELL-BID".to_string()))
+        }
+        _ => panic!("Unexpected CreateAuction answer"),
+    }
+    let mut labels = chain.active_labels();
+    labels.sort();
+    assert_eq!(
+        labels,
+        vec!["auction-1-SELL-BID".to_string(), "first".to_string()]
+    );
+
+    // a given label is echoed back as well
+    let data = chain
+        .factory_handle(SELLER, create_msg("third", 1000, &[]))
+        .unwrap()
+        .unwrap();
+    match from_binary(&data).unwrap() {
+        HandleAnswer::Status { message, .. } => assert_eq!(message, Some("third".to_string())),
+        _ => panic!("Unexpected CreateAuction answer"),
+    }
+}
+
+#[test]
+fn test_increase_lot() {
+    let mut chain = Chain::new();
+    chain.mint(SELL_TOKEN, SELLER, 2000);
+    chain.mint(BID_TOKEN, "bob", 500);
+    let auction = chain.create_auction("first", 1000);
+    let increase = || Some(auction_msg::ReceiveMsg::IncreaseLot {});
+    chain
+        .send(SELLER, SELL_TOKEN, &auction, 500, increase())
+        .unwrap();
+    assert_eq!(chain.balance(SELL_TOKEN, &auction), 1500);
+    let sell_amount =
+        |chain: &Chain| match chain.factory_query(QueryMsg::ListActiveAuctions { code_id: None }) {
+            QueryAnswer::ListActiveAuctions { active } => active.unwrap()[0].sell_amount,
+            _ => panic!("Unexpected ListActiveAuctions answer"),
+        };
+    assert_eq!(sell_amount(&chain), Uint128(1500));
+
+    // the lot is fixed once there is a bid, so the tokens are returned
+    chain
+        .send("bob", BID_TOKEN, &auction, 200, place_bid())
+        .unwrap();
+    chain
+        .send(SELLER, SELL_TOKEN, &auction, 500, increase())
+        .unwrap();
+    assert_eq!(sell_amount(&chain), Uint128(1500));
+    assert_eq!(chain.balance(SELL_TOKEN, &auction), 1500);
+    assert_eq!(chain.balance(SELL_TOKEN, SELLER), 500);
+}
+
+#[test]
+fn test_min_bid_decay() {
+    let mut chain = Chain::new();
+    chain.mint(SELL_TOKEN, SELLER, 2000);
+    chain.mint(BID_TOKEN, "bob", 500);
+    chain.mint(BID_TOKEN, "carol", 500);
+    let decaying = |label: &str, floor: u128| {
+        let mut msg = create_msg(label, 1000, &[]);
+        if let HandleMsg::CreateAuction { min_bid_decay, .. } = &mut msg {
+            *min_bid_decay = Some(MinBidDecay {
+                floor: Uint128(floor),
+                step: Uint128(10),
+                interval: 50,
+            });
+        }
+        msg
+    };
+    // the floor can not be above the minimum bid of 100
+    let error = chain
+        .factory_handle(SELLER, decaying("too high", 101))
+        .unwrap_err();
+    assert!(error
+        .to_string()
+        .contains("floor can not be more than the minimum bid"));
+    chain
+        .factory_handle(SELLER, decaying("decaying", 60))
+        .unwrap();
+    let auction = format!("auction{}", chain.auctions.len() - 1);
+    let minimum_bid = |chain: &Chain, current_time: u64| {
+        let query = auction_msg::QueryMsg::AuctionInfo {
+            lang: None,
+            current_time: Some(current_time),
+        };
+        match from_binary(&auction::query(&chain.auctions[&auction], query).unwrap()).unwrap() {
+            auction_msg::QueryAnswer::AuctionInfo { minimum_bid, .. } => minimum_bid,
+            _ => panic!("Unexpected AuctionInfo answer"),
+        }
+    };
+    // created at time 100, the minimum bid declines by 10 every 50 seconds down to 60
+    assert_eq!(minimum_bid(&chain, 100), Uint128(100));
+    assert_eq!(minimum_bid(&chain, 250), Uint128(70));
+    assert_eq!(minimum_bid(&chain, 900), Uint128(60));
+
+    // a bid below the starting minimum is returned at first, but accepted once it has declined
+    chain
+        .send("bob", BID_TOKEN, &auction, 80, place_bid())
+        .unwrap();
+    assert_eq!(chain.balance(BID_TOKEN, "bob"), 500);
+    chain.time = 200;
+    chain
+        .send("carol", BID_TOKEN, &auction, 80, place_bid())
+        .unwrap();
+    assert_eq!(chain.balance(BID_TOKEN, "carol"), 420);
+}
+
+#[test]
+fn test_watchlist() {
+    let mut chain = Chain::new();
+    chain.mint(SELL_TOKEN, SELLER, 2000);
+    let first = chain.create_auction("first", 1000);
+    let second = chain.create_auction("second", 2000);
+    let indices: HashMap<String, u32> =
+        match chain.factory_query(QueryMsg::ListActiveAuctions { code_id: None }) {
+            QueryAnswer::ListActiveAuctions { active } => active
+                .unwrap()
+                .into_iter()
+                .map(|a| (a.address.0, a.index))
+                .collect(),
+            _ => panic!("Unexpected ListActiveAuctions answer"),
+        };
+    let key = chain.viewing_key("dave");
+    let watched = |chain: &Chain, viewing_key: &str| -> Option<Vec<HumanAddr>> {
+        let list = QueryMsg::ListWatched {
+            address: HumanAddr("dave".to_string()),
+            viewing_key: viewing_key.to_string(),
+        };
+        match chain.factory_query(list) {
+            QueryAnswer::ListWatched { watched } => {
+                Some(watched.into_iter().map(|a| a.address).collect())
+            }
+            QueryAnswer::ViewingKeyError { .. } => None,
+            _ => panic!("Unexpected ListWatched answer"),
+        }
+    };
+    assert_eq!(watched(&chain, &key), Some(vec![]));
+
+    // only active auctions can be watched
+    let error = chain
+        .factory_handle("dave", HandleMsg::Watch { index: 99 })
+        .unwrap_err();
+    assert!(error.to_string().contains("not an active auction"));
+    for auction in [&first, &second].iter() {
+        let index = indices[auction.as_str()];
+        chain
+            .factory_handle("dave", HandleMsg::Watch { index })
+            .unwrap();
+    }
+    let mut both = watched(&chain, &key).unwrap();
+    both.sort();
+    assert_eq!(
+        both,
+        vec![HumanAddr(first.clone()), HumanAddr(second.clone())]
+    );
+    assert!(watched(&chain, "wrong key").is_none());
+
+    // unwatching removes the auction
+    let index = indices[second.as_str()];
+    chain
+        .factory_handle("dave", HandleMsg::Unwatch { index })
+        .unwrap();
+    assert_eq!(watched(&chain, &key), Some(vec![HumanAddr(first.clone())]));
+
+    // closed auctions drop off the watchlist
+    chain.time = 1001;
+    let finalize = auction_msg::HandleMsg::Finalize {
+        new_ends_at: None,
+        new_minimum_bid: None,
+    };
+    chain.auction_handle("dave", &first, finalize).unwrap();
+    assert_eq!(watched(&chain, &key), Some(vec![]));
+}
+
+#[test]
+fn test_block_size_reaches_auctions() {
+    let mut chain = Chain::with_block_size(Some(1000));
+    chain.mint(SELL_TOKEN, SELLER, 1000);
+    let auction = chain.create_auction("first", 1000);
+
+    // both the factory and the auction it created pad their responses to the configured size
+    let active = query(
+        &chain.factory,
+        QueryMsg::ListActiveAuctions { code_id: None },
+    )
+    .unwrap();
+    assert_eq!(active.as_slice().len() % 1000, 0);
+    let status = auction::query(&chain.auctions[&auction], auction_msg::QueryMsg::Status {});
+    assert_eq!(status.unwrap().as_slice().len() % 1000, 0);
+}
+
+#[test]
+fn test_block_size_bounds() {
+    for (block_size, valid) in [
+        (0, false),
+        (255, false),
+        (256, true),
+        (4096, true),
+        (4097, false),
+    ]
+    .iter()
+    {
+        let init_msg = InitMsg {
+            entropy: "entropy".to_string(),
+            auction_contract: AuctionContractInfo {
+                code_id: 1,
+                code_hash: AUCTION_HASH.to_string(),
+            },
+            block_size: Some(*block_size),
+        };
+        let init_result = init(&mut new_deps(), mock_env(ADMIN, &[]), init_msg);
+        match init_result {
+            Ok(_) => assert!(*valid),
+            Err(error) => {
+                assert!(!*valid);
+                assert!(error
+                    .to_string()
+                    .contains("Block size must be between 256 and 4096"));
+            }
+        }
+    }
+}
+
+#[test]
+fn test_auctions_validate_viewing_keys_locally() {
+    let mut chain = Chain::new();
+    chain.mint(SELL_TOKEN, SELLER, 1000);
+    chain.mint(BID_TOKEN, "bob", 500);
+
+    // keys made before registering or bidding are shared with the auction.  The mock querier
+    // panics on anything but token queries, so these views never reach the factory
+    let seller_key = chain.viewing_key(SELLER);
+    let bob_key = chain.viewing_key("bob");
+    let auction = chain.create_auction("first", 1000);
+    chain
+        .send("bob", BID_TOKEN, &auction, 200, place_bid())
+        .unwrap();
+    let view_bid = |chain: &Chain, key: &str| {
+        let view = auction_msg::QueryMsg::ViewBid {
+            address: HumanAddr("bob".to_string()),
+            viewing_key: key.to_string(),
+        };
+        match from_binary(&auction::query(&chain.auctions[&auction], view).unwrap()).unwrap() {
+            auction_msg::QueryAnswer::Bid { amount_bid, .. } => amount_bid,
+            _ => panic!("Unexpected Bid answer"),
+        }
+    };
+    assert_eq!(view_bid(&chain, &bob_key), Some(Uint128(200)));
+    let has_bids = auction_msg::QueryMsg::HasBids {
+        address: HumanAddr(SELLER.to_string()),
+        viewing_key: seller_key,
+    };
+    match from_binary(&auction::query(&chain.auctions[&auction], has_bids).unwrap()).unwrap() {
+        auction_msg::QueryAnswer::HasBids { has_bids, .. } => assert!(has_bids),
+        _ => panic!("Unexpected HasBids answer"),
+    }
+
+    // changing the key is not pushed to the active auctions
+    let set_key = HandleMsg::SetViewingKey {
+        key: "new key".to_string(),
+        padding: None,
+    };
+    chain.factory_handle("bob", set_key).unwrap();
+    // the new key's hash is shared with the auction the next time bob bids in it
+    chain
+        .send("bob", BID_TOKEN, &auction, 250, place_bid())
+        .unwrap();
+    assert_eq!(view_bid(&chain, "new key"), Some(Uint128(250)));
+}
+
+#[test]
+fn test_bid_totals() {
+    let mut chain = Chain::new();
+    chain.mint(SELL_TOKEN, SELLER, 3000);
+    chain.mint(BID_TOKEN, "bob", 1000);
+    let first = chain.create_auction("first", 1000);
+    let second = chain.create_auction("second", 2000);
+    let third = chain.create_auction("third", 2000);
+    let key = chain.viewing_key("bob");
+    let totals = |chain: &Chain| {
+        let msg = QueryMsg::BidTotals {
+            address: HumanAddr("bob".to_string()),
+            viewing_key: key.clone(),
+        };
+        match chain.factory_query(msg) {
+            QueryAnswer::BidTotals { totals } => totals
+                .into_iter()
+                .map(|t| (t.amount.u128(), t.auctions))
+                .collect::<Vec<_>>(),
+            _ => panic!("Unexpected BidTotals answer"),
+        }
+    };
+    assert!(totals(&chain).is_empty());
+
+    // bids in every active auction are added up, and a replaced bid only counts once
+    for (auction, amount) in [(&first, 200), (&second, 150), (&second, 250), (&third, 100)].iter() {
+        chain
+            .send("bob", BID_TOKEN, auction, *amount, place_bid())
+            .unwrap();
+    }
+    assert_eq!(totals(&chain), vec![(550, 3)]);
+
+    // a retracted bid no longer counts
+    chain
+        .auction_handle("bob", &third, auction_msg::HandleMsg::RetractBid {})
+        .unwrap();
+    assert_eq!(totals(&chain), vec![(450, 2)]);
+
+    // neither does a bid in an auction that closed
+    chain.time = 1001;
+    let finalize = auction_msg::HandleMsg::Finalize {
+        new_ends_at: None,
+        new_minimum_bid: None,
+    };
+    chain.auction_handle("dave", &first, finalize).unwrap();
+    assert_eq!(totals(&chain), vec![(250, 1)]);
+
+    // the totals are private
+    let msg = QueryMsg::BidTotals {
+        address: HumanAddr("bob".to_string()),
+        viewing_key: "wrong".to_string(),
+    };
+    match chain.factory_query(msg) {
+        QueryAnswer::ViewingKeyError { .. } => {}
+        _ => panic!("Expected a viewing key error"),
+    }
+}
+
+#[test]
+fn test_recent_prices() {
+    let mut chain = Chain::new();
+    chain.mint(SELL_TOKEN, SELLER, 3000);
+    chain.mint(BID_TOKEN, "bob", 1000);
+    let token = |address: &str| TokenAddress {
+        address: HumanAddr(address.to_string()),
+        token_id: None,
+    };
+    let recent = |limit: Option<u32>| QueryMsg::RecentPrices {
+        sell_token: token(SELL_TOKEN),
+        bid_token: token(BID_TOKEN),
+        limit,
+    };
+
+    // a pair no auction has used is unknown
+    let reversed = QueryMsg::RecentPrices {
+        sell_token: token(BID_TOKEN),
+        bid_token: token(SELL_TOKEN),
+        limit: None,
+    };
+    assert!(query(&chain.factory, reversed).is_err());
+
+    // two auctions sell and one closes without a bid
+    let first = chain.create_auction("first", 1000);
+    let second = chain.create_auction("second", 1000);
+    let third = chain.create_auction("third", 1000);
+    chain
+        .send("bob", BID_TOKEN, &first, 200, place_bid())
+        .unwrap();
+    chain
+        .send("bob", BID_TOKEN, &third, 400, place_bid())
+        .unwrap();
+    chain.time = 1001;
+    for auction in [&first, &second, &third].iter() {
+        let finalize = auction_msg::HandleMsg::Finalize {
+            new_ends_at: None,
+            new_minimum_bid: None,
+        };
+        chain.auction_handle("dave", auction, finalize).unwrap();
+    }
+
+    // only the sales are listed, newest first
+    match chain.factory_query(recent(None)) {
+        QueryAnswer::RecentPrices {
+            sell_decimals,
+            prices,
+            ..
+        } => {
+            assert_eq!(sell_decimals, 6);
+            let bids: Vec<u128> = prices.iter().map(|p| p.winning_bid.u128()).collect();
+            assert_eq!(bids, vec![400, 200]);
+            assert_eq!(prices[0].sell_amount, Uint128(1000));
+            assert_eq!(prices[0].timestamp, 1001);
+        }
+        _ => panic!("Unexpected RecentPrices answer"),
+    }
+    match chain.factory_query(recent(Some(1))) {
+        QueryAnswer::RecentPrices { prices, .. } => {
+            assert_eq!(prices.len(), 1);
+            assert_eq!(prices[0].winning_bid, Uint128(400));
+        }
+        _ => panic!("Unexpected RecentPrices answer"),
+    }
+}
+
+#[test]
+fn test_close_subscribers() {
+    let mut chain = Chain::new();
+    chain.mint(SELL_TOKEN, SELLER, 4000);
+    chain.mint(BID_TOKEN, "bob", 500);
+    let token = |address: &str| TokenAddress {
+        address: HumanAddr(address.to_string()),
+        token_id: None,
+    };
+    let subscribe = || HandleMsg::AddCloseSubscriber {
+        subscriber: HumanAddr(SUBSCRIBER.to_string()),
+        sell_token: token(SELL_TOKEN),
+        bid_token: token(BID_TOKEN),
+    };
+    let set_enabled = |enabled: bool| HandleMsg::SetCloseSubscriberStatus {
+        subscriber: HumanAddr(SUBSCRIBER.to_string()),
+        sell_token: token(SELL_TOKEN),
+        bid_token: token(BID_TOKEN),
+        enabled,
+    };
+    let finalize = || auction_msg::HandleMsg::Finalize {
+        new_ends_at: None,
+        new_minimum_bid: None,
+    };
+    let closes = |chain: &Chain, since: Option<u32>, limit: Option<u32>| {
+        let msg = QueryMsg::PairCloses {
+            sell_token: token(SELL_TOKEN),
+            bid_token: token(BID_TOKEN),
+            since,
+            limit,
+        };
+        match chain.factory_query(msg) {
+            QueryAnswer::PairCloses { closes, next } => (closes, next),
+            _ => panic!("Unexpected PairCloses answer"),
+        }
+    };
+
+    // closes of a pair nobody subscribed to are not logged
+    let unlogged = chain.create_auction("unlogged", 1000);
+    chain.time = 1001;
+    chain.auction_handle("dave", &unlogged, finalize()).unwrap();
+    assert!(closes(&chain, None, None).0.is_empty());
+
+    // only the admin may subscribe a contract
+    let error = chain.factory_handle(SELLER, subscribe()).unwrap_err();
+    assert!(error.to_string().contains("admin command"));
+    chain.factory_handle(ADMIN, subscribe()).unwrap();
+    let error = chain.factory_handle(ADMIN, subscribe()).unwrap_err();
+    assert!(error.to_string().contains("already subscribed"));
+    let listed = QueryMsg::CloseSubscribers {
+        sell_token: token(SELL_TOKEN),
+        bid_token: token(BID_TOKEN),
+    };
+    match chain.factory_query(listed) {
+        QueryAnswer::CloseSubscribers { subscribers } => {
+            assert_eq!(subscribers.len(), 1);
+            assert_eq!(subscribers[0].address, HumanAddr(SUBSCRIBER.to_string()));
+            assert!(subscribers[0].enabled);
+        }
+        _ => panic!("Unexpected CloseSubscribers answer"),
+    }
+
+    // the subscriber reads the pair's closes instead of being called by them
+    let first = chain.create_auction("first", 2000);
+    let second = chain.create_auction("second", 2000);
+    let third = chain.create_auction("third", 3000);
+    chain
+        .send("bob", BID_TOKEN, &first, 200, place_bid())
+        .unwrap();
+    chain.time = 2001;
+    chain.auction_handle("dave", &first, finalize()).unwrap();
+    chain.auction_handle("dave", &second, finalize()).unwrap();
+    let (logged, next) = closes(&chain, None, Some(1));
+    assert_eq!(next, 1);
+    assert_eq!(logged.len(), 1);
+    assert_eq!(logged[0].auction_address, HumanAddr(first.clone()));
+    assert_eq!(logged[0].winning_bid, Some(Uint128(200)));
+    assert_eq!(logged[0].timestamp, 2001);
+    // the cursor resumes after the closes already read
+    let (logged, next) = closes(&chain, Some(next), None);
+    assert_eq!(next, 2);
+    assert_eq!(logged.len(), 1);
+    assert_eq!(logged[0].auction_address, HumanAddr(second.clone()));
+    assert_eq!(logged[0].winning_bid, None);
+    let (logged, next) = closes(&chain, Some(next), None);
+    assert!(logged.is_empty());
+    assert_eq!(next, 2);
+
+    // closes are not logged while every subscriber is disabled
+    chain.factory_handle(ADMIN, set_enabled(false)).unwrap();
+    chain.time = 3001;
+    chain.auction_handle("dave", &third, finalize()).unwrap();
+    assert_eq!(closes(&chain, None, None).1, 2);
+
+    // and a removed one can not be changed
+    let unsubscribe = HandleMsg::RemoveCloseSubscriber {
+        subscriber: HumanAddr(SUBSCRIBER.to_string()),
+        sell_token: token(SELL_TOKEN),
+        bid_token: token(BID_TOKEN),
+    };
+    chain.factory_handle(ADMIN, unsubscribe).unwrap();
+    let error = chain.factory_handle(ADMIN, set_enabled(true)).unwrap_err();
+    assert!(error.to_string().contains("is not subscribed"));
+}
+
+#[test]
+fn test_seller_allowlist() {
+    let mut chain = Chain::new();
+    chain.mint(SELL_TOKEN, SELLER, 2000);
+    let allowlist = |chain: &Chain| match chain.factory_query(QueryMsg::Allowlist {}) {
+        QueryAnswer::Allowlist { enabled, count } => (enabled, count),
+        _ => panic!("Unexpected Allowlist answer"),
+    };
+    let sellers = |names: &[&str]| {
+        names
+            .iter()
+            .map(|n| HumanAddr(n.to_string()))
+            .collect::<Vec<HumanAddr>>()
+    };
+    assert_eq!(allowlist(&chain), (false, 0));
+
+    // only the admin may change the allowlist
+    let add = HandleMsg::AddToAllowlist {
+        addresses: sellers(&[SELLER]),
+    };
+    let error = chain.factory_handle(SELLER, add).unwrap_err();
+    assert!(error.to_string().contains("admin command"));
+
+    // once it is enforced, an address that is not on it can not create auctions
+    chain
+        .factory_handle(ADMIN, HandleMsg::SetAllowlistMode { enabled: true })
+        .unwrap();
+    let error = chain
+        .factory_handle(SELLER, create_msg("first", 1000, &[]))
+        .unwrap_err();
+    assert!(error.to_string().contains("Only allowlisted addresses"));
+
+    // adding an address twice only counts it once
+    let add = HandleMsg::AddToAllowlist {
+        addresses: sellers(&[SELLER, "bob", SELLER]),
+    };
+    chain.factory_handle(ADMIN, add).unwrap();
+    assert_eq!(allowlist(&chain), (true, 2));
+    chain.create_auction("first", 1000);
+
+    // removed addresses are locked out again
+    let remove = HandleMsg::RemoveFromAllowlist {
+        addresses: sellers(&[SELLER, "carol"]),
+    };
+    chain.factory_handle(ADMIN, remove).unwrap();
+    assert_eq!(allowlist(&chain), (true, 1));
+    assert!(chain
+        .factory_handle(SELLER, create_msg("second", 1000, &[]))
+        .is_err());
+
+    // until the allowlist is no longer enforced
+    chain
+        .factory_handle(ADMIN, HandleMsg::SetAllowlistMode { enabled: false })
+        .unwrap();
+    chain.create_auction("second", 1000);
+    assert_eq!(chain.active_labels().len(), 2);
+}
+
+#[test]
+fn test_preview_create_auction() {
+    let mut chain = Chain::new();
+    chain.mint(SELL_TOKEN, SELLER, 1000);
+    let preview = |chain: &Chain, current_time: u64, create_auction: HandleMsg| {
+        query(
+            &chain.factory,
+            QueryMsg::PreviewCreateAuction {
+                seller: HumanAddr(SELLER.to_string()),
+                current_time,
+                create_auction,
+            },
+        )
+    };
+
+    // the preview shows the message the factory would send
+    let answer = preview(&chain, chain.time, create_msg("first", 1000, &["art"])).unwrap();
+    let init_msg = match from_binary(&answer).unwrap() {
+        QueryAnswer::PreviewCreateAuction {
+            index,
+            label,
+            code_id,
+            code_hash,
+            init_msg,
+            creation_fee,
+        } => {
+            assert_eq!((index, label.as_str()), (0, "first"));
+            assert_eq!((code_id, code_hash.as_str()), (1, AUCTION_HASH));
+            assert!(creation_fee.is_none());
+            from_binary::<auction_msg::InitMsg>(&init_msg).unwrap()
+        }
+        _ => panic!("Unexpected PreviewCreateAuction answer"),
+    };
+    assert_eq!(init_msg.factory.address, HumanAddr(FACTORY.to_string()));
+    assert_eq!(init_msg.factory.code_hash, FACTORY_HASH);
+    assert_eq!(init_msg.seller, HumanAddr(SELLER.to_string()));
+    assert_eq!((init_msg.sell_decimals, init_msg.bid_decimals), (6, 8));
+    assert!(init_msg.callback_key.is_empty());
+
+    // without saving anything
+    match chain.factory_query(QueryMsg::StorageInfo {}) {
+        QueryAnswer::StorageInfo { tokens, .. } => assert_eq!(tokens, 0),
+        _ => panic!("Unexpected StorageInfo answer"),
+    }
+    chain.create_auction("first", 1000);
+    assert_eq!(chain.active_labels(), vec!["first".to_string()]);
+
+    // and reports the errors creating the auction would fail with
+    let error = preview(&chain, chain.time, create_msg("first", 1000, &[])).unwrap_err();
+    assert!(error.to_string().contains("already exists"));
+    let error = preview(&chain, 1000, create_msg("second", 1000, &[])).unwrap_err();
+    assert!(error.to_string().contains("must be later than"));
+    let error = preview(
+        &chain,
+        chain.time,
+        HandleMsg::SetAllowlistMode { enabled: true },
+    )
+    .unwrap_err();
+    assert!(error.to_string().contains("Only a create_auction message"));
+}
+
+/// finalizes up to `limit` expired auctions through the factory and returns their indices
+fn close_expired(chain: &mut Chain, limit: u32) -> Vec<u32> {
+    let data = chain
+        .factory_handle("dave", HandleMsg::CloseExpired { limit })
+        .unwrap()
+        .unwrap();
+    match from_binary(&data).unwrap() {
+        HandleAnswer::CloseExpired { auctions, .. } => auctions,
+        _ => panic!("Unexpected CloseExpired answer"),
+    }
+}
+
+#[test]
+fn test_close_expired() {
+    let mut chain = Chain::new();
+    chain.mint(SELL_TOKEN, SELLER, 4000);
+    chain.create_auction("middle", 1000);
+    chain.create_auction("open", 9000);
+    chain.create_auction("first", 500);
+    chain.create_auction("last", 3000);
+    let error = chain
+        .factory_handle("dave", HandleMsg::CloseExpired { limit: 21 })
+        .unwrap_err();
+    assert!(error.to_string().contains("more than 20 auctions"));
+
+    // the auctions that closed earliest are finalized first
+    chain.time = 3500;
+    assert_eq!(close_expired(&mut chain, 2), vec![2, 0]);
+    assert_eq!(chain.balance(SELL_TOKEN, SELLER), 2000);
+    let mut labels = chain.active_labels();
+    labels.sort();
+    assert_eq!(labels, vec!["last".to_string(), "open".to_string()]);
+    assert_eq!(close_expired(&mut chain, 20), vec![3]);
+    assert!(close_expired(&mut chain, 20).is_empty());
+    assert_eq!(chain.active_labels(), vec!["open".to_string()]);
+}
+
+#[test]
+fn test_close_expired_uses_auction_code_hash() {
+    let mut chain = Chain::new();
+    chain.mint(SELL_TOKEN, SELLER, 2000);
+    chain.create_auction("old", 1000);
+    let new_contract = HandleMsg::NewAuctionContract {
+        auction_contract: AuctionContractInfo {
+            code_id: 2,
+            code_hash: "newhash".to_string(),
+        },
+        deprecate_old: false,
+        deprecation_notice: None,
+        page_size: None,
+    };
+    chain.factory_handle(ADMIN, new_contract).unwrap();
+    chain.create_auction("new", 1000);
+
+    // each auction is finalized with the code hash it was created with
+    chain.time = 1001;
+    assert_eq!(close_expired(&mut chain, 20), vec![0, 1]);
+    assert!(chain.active_labels().is_empty());
+    assert_eq!(chain.balance(SELL_TOKEN, SELLER), 2000);
+}
+
+#[test]
+fn test_close_expired_skips_unfinalizable() {
+    let mut chain = Chain::new();
+    chain.mint(SELL_TOKEN, SELLER, 3000);
+    chain.create_auction("halted", 500);
+    chain.create_auction("later", 1000);
+    chain.create_auction("next", 1500);
+    chain
+        .factory_handle(
+            ADMIN,
+            HandleMsg::HaltAuction {
+                index: 0,
+                halted: true,
+            },
+        )
+        .unwrap();
+
+    // the halted auction does not revert the batch
+    chain.time = 2000;
+    assert_eq!(close_expired(&mut chain, 2), vec![0, 1]);
+    let mut labels = chain.active_labels();
+    labels.sort();
+    assert_eq!(labels, vec!["halted".to_string(), "next".to_string()]);
+
+    // nor does it hold up the auctions behind it
+    assert_eq!(close_expired(&mut chain, 1), vec![2]);
+    assert_eq!(chain.active_labels(), vec!["halted".to_string()]);
+
+    // it is tried again once the retry delay has passed
+    chain
+        .factory_handle(
+            ADMIN,
+            HandleMsg::HaltAuction {
+                index: 0,
+                halted: false,
+            },
+        )
+        .unwrap();
+    assert!(close_expired(&mut chain, 20).is_empty());
+    chain.time = 2000 + 3600;
+    assert_eq!(close_expired(&mut chain, 20), vec![0]);
+    assert!(chain.active_labels().is_empty());
+    assert_eq!(chain.balance(SELL_TOKEN, SELLER), 3000);
+}
+
+/// the message creating a `sell_amount` BID for a minimum `minimum_bid` SELL auction, the
+/// reverse of create_msg
+fn reverse_create_msg(label: &str, sell_amount: u128, minimum_bid: u128) -> HandleMsg {
+    let mut msg = create_msg(label, 1000, &[]);
+    if let HandleMsg::CreateAuction {
+        sell_contract: ref mut sell,
+        bid_contract: ref mut bid,
+        sell_amount: ref mut amount,
+        minimum_bid: ref mut minimum,
+        ..
+    } = msg
+    {
+        std::mem::swap(sell, bid);
+        *amount = Uint128(sell_amount);
+        *minimum = Uint128(minimum_bid);
+    }
+    msg
+}
+
+#[test]
+fn test_match_auctions() {
+    let mut chain = Chain::new();
+    chain.mint(SELL_TOKEN, SELLER, 2000);
+    chain.mint(BID_TOKEN, "erin", 1500);
+    chain.mint(EXTRA_TOKEN, SELLER, 10);
+    chain.create_auction("sell", 1000);
+    let new_contract = HandleMsg::NewAuctionContract {
+        auction_contract: AuctionContractInfo {
+            code_id: 2,
+            code_hash: "newhash".to_string(),
+        },
+        deprecate_old: false,
+        deprecation_notice: None,
+        page_size: None,
+    };
+    chain.factory_handle(ADMIN, new_contract).unwrap();
+    chain
+        .factory_handle("erin", reverse_create_msg("buy", 500, 800))
+        .unwrap();
+    let matched = |a: u32, b: u32| HandleMsg::MatchAuctions { a, b };
+
+    // only the admin may match, and the prices must cross
+    let error = chain.factory_handle(SELLER, matched(0, 1)).unwrap_err();
+    assert!(error.to_string().contains("admin command"));
+    chain
+        .factory_handle("erin", reverse_create_msg("greedy", 500, 2000))
+        .unwrap();
+    let error = chain.factory_handle(ADMIN, matched(0, 2)).unwrap_err();
+    assert!(error.to_string().contains("do not cross"));
+
+    // each auction is sent the swap with the code hash it was created with
+    chain.factory_handle(ADMIN, matched(0, 1)).unwrap();
+    assert_eq!(chain.balance(BID_TOKEN, SELLER), 500);
+    assert_eq!(chain.balance(SELL_TOKEN, "erin"), 1000);
+    assert_eq!(chain.active_labels(), vec!["greedy".to_string()]);
+
+    // a bundled lot is not priced by its sell amount alone
+    let mut bundled = create_msg("bundled", 1000, &[]);
+    if let HandleMsg::CreateAuction { ref mut bundle, .. } = bundled {
+        bundle.push(BundleItem {
+            contract: ContractInfo {
+                code_hash: "extrahash".to_string(),
+                address: HumanAddr(EXTRA_TOKEN.to_string()),
+                token_id: None,
+            },
+            amount: Uint128(10),
+        });
+    }
+    chain.factory_handle(SELLER, bundled).unwrap();
+    chain
+        .factory_handle("erin", reverse_create_msg("cheap", 500, 100))
+        .unwrap();
+    let error = chain.factory_handle(ADMIN, matched(3, 4)).unwrap_err();
+    assert!(error
+        .to_string()
+        .contains("Auction 3 sells a bundled lot and can not be matched"));
+    assert_eq!(chain.balance(EXTRA_TOKEN, SELLER), 0);
+}
+
+#[test]
+fn test_wrong_callback_key_rejected() {
+    let mut chain = Chain::new();
+    chain.mint(SELL_TOKEN, SELLER, 1000);
+    chain.mint(BID_TOKEN, "bob", 500);
+    let auction = chain.create_auction("first", 1000);
+    chain
+        .send("bob", BID_TOKEN, &auction, 200, place_bid())
+        .unwrap();
+    let key = chain.viewing_key("mallory");
+    let mallory_bids = |chain: &Chain| {
+        let mine = QueryMsg::ListMyAuctions {
+            address: HumanAddr("mallory".to_string()),
+            viewing_key: key.clone(),
+            filter: Some(FilterTypes::Active),
+        };
+        match chain.factory_query(mine) {
+            QueryAnswer::ListMyAuctions { active, .. } => {
+                active.and_then(|a| a.as_bidder).unwrap_or_default().len()
+            }
+            _ => panic!("Unexpected ListMyAuctions answer"),
+        }
+    };
+
+    // even sent from the auction's own address, callbacks without the auction's callback key
+    // do not change the factory's lists
+    for callback_key in [Some("wrongkey".to_string()), None].iter() {
+        let register = HandleMsg::RegisterBidder {
+            index: 0,
+            bidder: HumanAddr("mallory".to_string()),
+            amount: Some(Uint128(1)),
+            callback_key: callback_key.clone(),
+        };
+        chain.factory_handle(&auction, register).unwrap();
+        assert_eq!(mallory_bids(&chain), 0);
+
+        let close = HandleMsg::CloseAuction {
+            index: 0,
+            seller: HumanAddr(SELLER.to_string()),
+            bidder: Some(HumanAddr("mallory".to_string())),
+            winning_bid: Some(Uint128(1)),
+            co_winners: vec![],
+            referrer: None,
+            unmet_min_bidders: None,
+            callback_key: callback_key.clone(),
+        };
+        chain.factory_handle(&auction, close).unwrap();
+        assert_eq!(chain.active_labels(), vec!["first".to_string()]);
+        assert!(chain.closed().is_empty());
+    }
+
+    // while the auction's own callbacks are still accepted
+    chain.time = 1001;
+    let finalize = auction_msg::HandleMsg::Finalize {
+        new_ends_at: None,
+        new_minimum_bid: None,
+    };
+    chain.auction_handle("dave", &auction, finalize).unwrap();
+    assert!(chain.active_labels().is_empty());
+    let closed = chain.closed();
+    assert_eq!(closed.len(), 1);
+    assert_eq!(closed[0].winning_bid, Some(Uint128(200)));
+}
+
+#[test]
+fn test_nicknames() {
+    let mut chain = Chain::new();
+    chain.mint(SELL_TOKEN, SELLER, 1000);
+    chain.mint(BID_TOKEN, "bob", 500);
+    let auction = chain.create_auction("first", 1000);
+    chain
+        .send("bob", BID_TOKEN, &auction, 200, place_bid())
+        .unwrap();
+    let seller_key = chain.viewing_key(SELLER);
+    let bob_key = chain.viewing_key("bob");
+    let mine = |chain: &Chain, address: &str, key: &str, filter: FilterTypes| {
+        let msg = QueryMsg::ListMyAuctions {
+            address: HumanAddr(address.to_string()),
+            viewing_key: key.to_string(),
+            filter: Some(filter),
+        };
+        match chain.factory_query(msg) {
+            QueryAnswer::ListMyAuctions { active, closed } => (active, closed),
+            _ => panic!("Unexpected ListMyAuctions answer"),
+        }
+    };
+    let seller_nickname = |chain: &Chain| {
+        let (active, _) = mine(chain, SELLER, &seller_key, FilterTypes::Active);
+        active.unwrap().as_seller.unwrap()[0].nickname.clone()
+    };
+
+    // the nickname given at creation is only shown in the seller's own list
+    assert_eq!(seller_nickname(&chain), Some("first nick".to_string()));
+    let (active, _) = mine(&chain, "bob", &bob_key, FilterTypes::Active);
+    assert!(active.unwrap().as_bidder.unwrap()[0].nickname.is_none());
+    match chain.factory_query(QueryMsg::ListActiveAuctions { code_id: None }) {
+        QueryAnswer::ListActiveAuctions { active } => {
+            assert!(active.unwrap()[0].nickname.is_none())
+        }
+        _ => panic!("Unexpected ListActiveAuctions answer"),
+    }
+
+    // only the seller may change it, and it can not be too long
+    let set = |nickname: Option<&str>| HandleMsg::SetNickname {
+        auction: HumanAddr(auction.clone()),
+        nickname: nickname.map(String::from),
+    };
+    let error = chain.factory_handle("bob", set(Some("mine"))).unwrap_err();
+    assert!(error
+        .to_string()
+        .contains("is not an active auction you are selling"));
+    let long = "x".repeat(33);
+    let error = chain.factory_handle(SELLER, set(Some(&long))).unwrap_err();
+    assert!(error
+        .to_string()
+        .contains("Nicknames can not be longer than 32 characters"));
+    assert_eq!(seller_nickname(&chain), Some("first nick".to_string()));
+
+    chain.factory_handle(SELLER, set(Some("renamed"))).unwrap();
+    assert_eq!(seller_nickname(&chain), Some("renamed".to_string()));
+    chain.factory_handle(SELLER, set(None)).unwrap();
+    assert!(seller_nickname(&chain).is_none());
+
+    // the nickname is kept in the seller's closed list
+    chain.factory_handle(SELLER, set(Some("sold"))).unwrap();
+    chain.time = 1001;
+    let finalize = auction_msg::HandleMsg::Finalize {
+        new_ends_at: None,
+        new_minimum_bid: None,
+    };
+    chain.auction_handle("dave", &auction, finalize).unwrap();
+    let (_, closed) = mine(&chain, SELLER, &seller_key, FilterTypes::Closed);
+    let as_seller = closed.unwrap().as_seller.unwrap();
+    assert_eq!(as_seller[0].nickname, Some("sold".to_string()));
+    assert!(chain.closed()[0].nickname.is_none());
+}
+
+#[test]
+fn test_creation_limits() {
+    let mut chain = Chain::new();
+    chain.mint(SELL_TOKEN, SELLER, 3000);
+    let limits =
+        |min_sell_amount: u128, max_bid_multiple: Option<u128>| HandleMsg::SetCreationLimits {
+            min_sell_amount: Uint128(min_sell_amount),
+            max_bid_multiple: max_bid_multiple.map(Uint128),
+        };
+    let create = |sell_amount: u128, bid: u128| {
+        let mut msg = create_msg(&format!("sell {} for {}", sell_amount, bid), 1000, &[]);
+        if let HandleMsg::CreateAuction {
+            sell_amount: ref mut amount,
+            ref mut minimum_bid,
+            ..
+        } = msg
+        {
+            *amount = Uint128(sell_amount);
+            *minimum_bid = Uint128(bid);
+        }
+        msg
+    };
+
+    // only the admin may set the limits, and every auction must sell something
+    let error = chain
+        .factory_handle(SELLER, limits(2000, Some(1)))
+        .unwrap_err();
+    assert!(error.to_string().contains("admin command"));
+    let error = chain.factory_handle(ADMIN, limits(0, None)).unwrap_err();
+    assert!(error
+        .to_string()
+        .contains("Minimum sell amount must be greater than 0"));
+    chain.factory_handle(ADMIN, limits(2000, Some(1))).unwrap();
+    match chain.factory_query(QueryMsg::Config {}) {
+        QueryAnswer::Config {
+            min_sell_amount,
+            max_bid_multiple,
+            ..
+        } => {
+            assert_eq!(min_sell_amount, Uint128(2000));
+            assert_eq!(max_bid_multiple, Some(Uint128(1)));
+        }
+        _ => panic!("Unexpected Config answer"),
+    }
+
+    // a sell amount below the minimum is rejected
+    let error = chain.factory_handle(SELLER, create(1000, 100)).unwrap_err();
+    assert!(error
+        .to_string()
+        .contains("Sell amount must be at least 2000"));
+
+    // so is a minimum bid worth more than the multiple of the sell amount.  0.002 SELL is worth
+    // at most 0.002 BID, which is 200000 of BID's 8 decimal denomination
+    let error = chain
+        .factory_handle(SELLER, create(2000, 200_001))
+        .unwrap_err();
+    assert!(error
+        .to_string()
+        .contains("Minimum bid can not be more than 1 times the sell amount"));
+    assert!(chain.active_labels().is_empty());
+    chain.factory_handle(SELLER, create(2000, 200_000)).unwrap();
+    assert_eq!(chain.active_labels().len(), 1);
+
+    // removing the limits allows the auctions again
+    chain.factory_handle(ADMIN, limits(1, None)).unwrap();
+    chain.factory_handle(SELLER, create(1000, 200_001)).unwrap();
+    assert_eq!(chain.active_labels().len(), 2);
+    assert_eq!(chain.balance(SELL_TOKEN, SELLER), 0);
+}
+
+#[test]
+fn test_get_auctions() {
+    let mut chain = Chain::new();
+    chain.mint(SELL_TOKEN, SELLER, 2000);
+    chain.mint(BID_TOKEN, "bob", 500);
+    let first = chain.create_auction("first", 1000);
+    let second = chain.create_auction("second", 2000);
+    chain
+        .send("bob", BID_TOKEN, &first, 200, place_bid())
+        .unwrap();
+    chain.time = 1001;
+    let finalize = auction_msg::HandleMsg::Finalize {
+        new_ends_at: None,
+        new_minimum_bid: None,
+    };
+    chain.auction_handle("dave", &first, finalize).unwrap();
+
+    // the batch is answered in the order requested, whether the auctions are active, closed,
+    // or unknown
+    let get = QueryMsg::GetAuctions {
+        indices: vec![1, 0, 7],
+    };
+    let auctions = match chain.factory_query(get) {
+        QueryAnswer::GetAuctions { auctions } => auctions,
+        _ => panic!("Unexpected GetAuctions answer"),
+    };
+    assert_eq!(
+        auctions.iter().map(|a| a.index).collect::<Vec<_>>(),
+        vec![1, 0, 7]
+    );
+    let active = auctions[0].active.as_ref().unwrap();
+    assert_eq!(active.address, HumanAddr(second));
+    assert_eq!(active.label, "second");
+    assert!(auctions[0].closed.is_none());
+    let closed = auctions[1].closed.as_ref().unwrap();
+    assert_eq!(closed.address, HumanAddr(first));
+    assert_eq!(closed.winning_bid, Some(Uint128(200)));
+    assert!(auctions[1].active.is_none());
+    assert!(auctions[2].active.is_none() && auctions[2].closed.is_none());
+
+    // but it can not be too big
+    let get = QueryMsg::GetAuctions {
+        indices: (0..31).collect(),
+    };
+    let error = query(&chain.factory, get).unwrap_err();
+    assert!(error
+        .to_string()
+        .contains("Can not request more than 30 auctions at a time"));
+}
+
+#[test]
+fn test_listings_show_decimals_and_closing_times() {
+    let mut chain = Chain::new();
+    chain.mint(SELL_TOKEN, SELLER, 2000);
+    let first = chain.create_auction("first", 1000);
+    let second = chain.create_auction("second", 2000);
+    let key = chain.viewing_key(SELLER);
+
+    // the public list shows each auction's index, closing time, and token decimals
+    let active = match chain.factory_query(QueryMsg::ListActiveAuctions { code_id: None }) {
+        QueryAnswer::ListActiveAuctions { active } => active.unwrap(),
+        _ => panic!("Unexpected ListActiveAuctions answer"),
+    };
+    let mut listed = active
+        .iter()
+        .map(|a| {
+            (
+                a.address.0.clone(),
+                a.index,
+                a.ends_at,
+                a.sell_decimals,
+                a.bid_decimals,
+            )
+        })
+        .collect::<Vec<_>>();
+    listed.sort();
+    let expected = vec![
+        (first.clone(), 0, 1000, 6, 8),
+        (second.clone(), 1, 2000, 6, 8),
+    ];
+    assert_eq!(listed, expected);
+
+    // and so does the seller's own list
+    let mine = QueryMsg::ListMyAuctions {
+        address: HumanAddr(SELLER.to_string()),
+        viewing_key: key,
+        filter: Some(FilterTypes::Active),
+    };
+    let as_seller = match chain.factory_query(mine) {
+        QueryAnswer::ListMyAuctions { active, .. } => active.unwrap().as_seller.unwrap(),
+        _ => panic!("Unexpected ListMyAuctions answer"),
+    };
+    let mut listed = as_seller
+        .iter()
+        .map(|a| {
+            (
+                a.address.0.clone(),
+                a.index,
+                a.ends_at,
+                a.sell_decimals,
+                a.bid_decimals,
+            )
+        })
+        .collect::<Vec<_>>();
+    listed.sort();
+    assert_eq!(listed, expected);
+}
+
+#[test]
+fn test_refresh_token_info() {
+    let mut chain = Chain::new();
+    chain.mint(SELL_TOKEN, SELLER, 2000);
+    chain.create_auction("first", 1000);
+    let listed =
+        |chain: &Chain| match chain.factory_query(QueryMsg::ListActiveAuctions { code_id: None }) {
+            QueryAnswer::ListActiveAuctions { active } => active
+                .unwrap()
+                .into_iter()
+                .map(|a| (a.pair, a.sell_decimals))
+                .collect::<Vec<_>>(),
+            _ => panic!("Unexpected ListActiveAuctions answer"),
+        };
+    assert_eq!(listed(&chain), vec![("SELL-BID".to_string(), 6)]);
+
+    // once the token rebrands, the cached decimals no longer match and auctions of it are
+    // rejected
+    chain.factory.querier.rebranded = true;
+    let error = chain
+        .factory_handle(SELLER, create_msg("second", 1000, &[]))
+        .unwrap_err();
+    assert!(error
+        .to_string()
+        .contains("Token selladdr now reports 4 decimals, but the factory has it recorded as 6"));
+
+    // only the admin may refresh a token, and only one an auction has used
+    let refresh = |address: &str| HandleMsg::RefreshTokenInfo {
+        token_address: HumanAddr(address.to_string()),
+        code_hash: "hash".to_string(),
+        token_id: None,
+    };
+    let error = chain
+        .factory_handle(SELLER, refresh(SELL_TOKEN))
+        .unwrap_err();
+    assert!(error.to_string().contains("admin command"));
+    let error = chain
+        .factory_handle(ADMIN, refresh(EXTRA_TOKEN))
+        .unwrap_err();
+    assert!(error
+        .to_string()
+        .contains("Token extraaddr has not been used by any auction"));
+
+    // refreshing updates the listings and lets the token be auctioned again
+    let data = chain
+        .factory_handle(ADMIN, refresh(SELL_TOKEN))
+        .unwrap()
+        .unwrap();
+    match from_binary(&data).unwrap() {
+        HandleAnswer::Status { message, .. } => assert_eq!(
+            message,
+            Some("Token selladdr now displays as SOLD with 4 decimals".to_string())
+        ),
+        _ => panic!("Unexpected RefreshTokenInfo answer"),
+    }
+    assert_eq!(listed(&chain), vec![("SOLD-BID".to_string(), 4)]);
+    chain
+        .factory_handle(SELLER, create_msg("second", 1000, &[]))
+        .unwrap();
+    assert_eq!(chain.active_labels().len(), 2);
+}
+
+#[test]
+fn test_token_origins() {
+    let mut chain = Chain::new();
+    chain.mint(SELL_TOKEN, SELLER, 1000);
+    chain.create_auction("first", 1000);
+    let origins =
+        |chain: &Chain| match chain.factory_query(QueryMsg::ListActiveAuctions { code_id: None }) {
+            QueryAnswer::ListActiveAuctions { active } => {
+                let info = active.unwrap().remove(0);
+                (info.sell_origin, info.bid_origin)
+            }
+            _ => panic!("Unexpected ListActiveAuctions answer"),
+        };
+    let set = |address: &str, origin: Option<IbcOrigin>| HandleMsg::SetTokenOrigin {
+        token_address: HumanAddr(address.to_string()),
+        token_id: None,
+        origin,
+    };
+    let atom = IbcOrigin {
+        chain_id: "cosmoshub-4".to_string(),
+        denom: "uatom".to_string(),
+    };
+    assert_eq!(origins(&chain), (None, None));
+
+    // only the admin may annotate a token, and only one an auction has used
+    let error = chain
+        .factory_handle(SELLER, set(BID_TOKEN, Some(atom.clone())))
+        .unwrap_err();
+    assert!(error.to_string().contains("admin command"));
+    let error = chain
+        .factory_handle(ADMIN, set(EXTRA_TOKEN, Some(atom.clone())))
+        .unwrap_err();
+    assert!(error
+        .to_string()
+        .contains("Token extraaddr has not been used by any auction"));
+    let blank = IbcOrigin {
+        chain_id: "".to_string(),
+        denom: "uatom".to_string(),
+    };
+    let error = chain
+        .factory_handle(ADMIN, set(BID_TOKEN, Some(blank)))
+        .unwrap_err();
+    assert!(error
+        .to_string()
+        .contains("The origin chain id and denom can not be blank"));
+    assert_eq!(origins(&chain), (None, None));
+
+    // the origin is displayed with every auction using the token
+    let data = chain
+        .factory_handle(ADMIN, set(BID_TOKEN, Some(atom.clone())))
+        .unwrap()
+        .unwrap();
+    match from_binary(&data).unwrap() {
+        HandleAnswer::Status { message, .. } => assert_eq!(
+            message,
+            Some("Token bidaddr is now displayed as uatom from chain cosmoshub-4".to_string())
+        ),
+        _ => panic!("Unexpected SetTokenOrigin answer"),
+    }
+    assert_eq!(origins(&chain), (None, Some(atom.clone())));
+    let get = QueryMsg::GetAuctions { indices: vec![0] };
+    match chain.factory_query(get) {
+        QueryAnswer::GetAuctions { auctions } => {
+            let info = auctions[0].active.as_ref().unwrap();
+            assert_eq!(info.bid_origin, Some(atom));
+        }
+        _ => panic!("Unexpected GetAuctions answer"),
+    }
+
+    // and can be cleared
+    chain.factory_handle(ADMIN, set(BID_TOKEN, None)).unwrap();
+    assert_eq!(origins(&chain), (None, None));
+}
+
+#[test]
+fn test_settlement_records() {
+    let mut chain = Chain::new();
+    chain.mint(SELL_TOKEN, SELLER, 2000);
+    chain.mint(BID_TOKEN, "bob", 500);
+    let sold = chain.create_auction("sold", 1000);
+    let unsold = chain.create_auction("unsold", 1000);
+    chain
+        .send("bob", BID_TOKEN, &sold, 200, place_bid())
+        .unwrap();
+
+    // there is no record until the auction closes
+    let error = query(&chain.factory, QueryMsg::SettlementRecord { index: 0 }).unwrap_err();
+    assert!(error
+        .to_string()
+        .contains("There is no settlement record for auction 0"));
+
+    chain.time = 1001;
+    for auction in [&sold, &unsold].iter() {
+        let finalize = auction_msg::HandleMsg::Finalize {
+            new_ends_at: None,
+            new_minimum_bid: None,
+        };
+        chain.auction_handle("dave", auction, finalize).unwrap();
+    }
+    let record = |chain: &Chain, index: u32| match chain
+        .factory_query(QueryMsg::SettlementRecord { index })
+    {
+        QueryAnswer::SettlementRecord {
+            auction_address,
+            pair,
+            sell_amount,
+            sell_decimals,
+            winning_bid,
+            bid_decimals,
+            winners,
+            timestamp,
+            outcome,
+            digest,
+            ..
+        } => (
+            (
+                auction_address.0,
+                pair,
+                sell_amount.u128(),
+                sell_decimals,
+                winning_bid.map(|w| w.u128()),
+                bid_decimals,
+            ),
+            (winners, timestamp, outcome),
+            digest,
+        ),
+        _ => panic!("Unexpected SettlementRecord answer"),
+    };
+
+    // the records show the terms each auction settled on
+    let (terms, result, sold_digest) = record(&chain, 0);
+    assert_eq!(
+        terms,
+        (sold.clone(), "SELL-BID".to_string(), 1000, 6, Some(200), 8)
+    );
+    assert_eq!(result, (1, 1001, SettlementOutcome::Sold));
+    let (terms, result, unsold_digest) = record(&chain, 1);
+    assert_eq!(
+        terms,
+        (unsold.clone(), "SELL-BID".to_string(), 1000, 6, None, 8)
+    );
+    assert_eq!(result, (0, 1001, SettlementOutcome::NoSale));
+
+    // and each is identified by a hash of its terms
+    assert_eq!(sold_digest.as_slice().len(), 32);
+    assert_eq!(unsold_digest.as_slice().len(), 32);
+    assert_ne!(sold_digest, unsold_digest);
+}
+
+#[test]
+fn test_list_ending_soon() {
+    let mut chain = Chain::new();
+    chain.mint(SELL_TOKEN, SELLER, 3000);
+    let first = chain.create_auction("first", 10_000);
+    let later = chain.create_auction("later", 100_000);
+    chain.create_auction("middle", 50_000);
+    let ending_soon = |chain: &Chain, page_size: Option<u32>| {
+        let msg = QueryMsg::ListEndingSoon {
+            current_time: chain.time,
+            within_seconds: 60_000,
+            page_size,
+        };
+        match chain.factory_query(msg) {
+            QueryAnswer::ListEndingSoon { auctions } => auctions
+                .into_iter()
+                .map(|a| (a.label, a.ends_at))
+                .collect::<Vec<_>>(),
+            _ => panic!("Unexpected ListEndingSoon answer"),
+        }
+    };
+    let labeled = |list: &[(&str, u64)]| {
+        list.iter()
+            .map(|(l, e)| (l.to_string(), *e))
+            .collect::<Vec<_>>()
+    };
+
+    // only the auctions closing within the window are listed, soonest first
+    assert_eq!(
+        ending_soon(&chain, None),
+        labeled(&[("first", 10_000), ("middle", 50_000)])
+    );
+    assert_eq!(ending_soon(&chain, Some(1)), labeled(&[("first", 10_000)]));
+
+    // an auction whose closing time changes moves into the window
+    let extend = auction_msg::HandleMsg::Finalize {
+        new_ends_at: Some(20_000),
+        new_minimum_bid: None,
+    };
+    chain.auction_handle(SELLER, &later, extend).unwrap();
+    assert_eq!(
+        ending_soon(&chain, None),
+        labeled(&[("first", 10_000), ("later", 20_000), ("middle", 50_000)])
+    );
+
+    // and a closed auction leaves it
+    chain.time = 10_001;
+    let finalize = auction_msg::HandleMsg::Finalize {
+        new_ends_at: None,
+        new_minimum_bid: None,
+    };
+    chain.auction_handle("dave", &first, finalize).unwrap();
+    assert_eq!(
+        ending_soon(&chain, None),
+        labeled(&[("later", 20_000), ("middle", 50_000)])
+    );
+}
+
+#[test]
+fn test_token_keys() {
+    let mut chain = Chain::new();
+    chain.mint(SELL_TOKEN, SELLER, 1000);
+    chain.mint(BID_TOKEN, "erin", 500);
+    chain.create_auction("forward", 1000);
+    chain
+        .factory_handle("erin", reverse_create_msg("reverse", 500, 800))
+        .unwrap();
+
+    // each token displays its own symbol and decimals whichever side of an auction it is on
+    let mut listed = match chain.factory_query(QueryMsg::ListActiveAuctions { code_id: None }) {
+        QueryAnswer::ListActiveAuctions { active } => active
+            .unwrap()
+            .into_iter()
+            .map(|a| (a.label, a.pair, a.sell_decimals, a.bid_decimals))
+            .collect::<Vec<_>>(),
+        _ => panic!("Unexpected ListActiveAuctions answer"),
+    };
+    listed.sort();
+    assert_eq!(
+        listed,
+        vec![
+            ("forward".to_string(), "SELL-BID".to_string(), 6, 8),
+            ("reverse".to_string(), "BID-SELL".to_string(), 8, 6),
+        ]
+    );
+
+    // and the registration records key the tokens by their canonical addresses
+    let canonical = |address: &str| {
+        let raw = chain
+            .factory
+            .api
+            .canonical_address(&HumanAddr(address.to_string()))
+            .unwrap();
+        Binary(raw.as_slice().to_vec())
+    };
+    let (sell_key, bid_key) = (canonical(SELL_TOKEN), canonical(BID_TOKEN));
+    let admin_key = chain.viewing_key(ADMIN);
+    let info = |index: u32| QueryMsg::AdminAuctionInfo {
+        address: HumanAddr(ADMIN.to_string()),
+        viewing_key: admin_key.clone(),
+        index,
+    };
+    for (index, sell, bid) in [(0, &sell_key, &bid_key), (1, &bid_key, &sell_key)].iter() {
+        match chain.factory_query(info(*index)) {
+            QueryAnswer::AdminAuctionInfo { active, .. } => {
+                let active = active.unwrap();
+                assert_eq!(&active.sell_token, *sell);
+                assert_eq!(&active.bid_token, *bid);
+            }
+            _ => panic!("Unexpected AdminAuctionInfo answer"),
+        }
+    }
+}
+
+#[test]
+fn test_my_bid_amounts() {
+    let mut chain = Chain::new();
+    chain.mint(SELL_TOKEN, SELLER, 2000);
+    chain.mint(BID_TOKEN, "bob", 1000);
+    chain.mint(BID_TOKEN, "carol", 500);
+    let first = chain.create_auction("first", 1000);
+    let second = chain.create_auction("second", 1000);
+    let bob_key = chain.viewing_key("bob");
+    let carol_key = chain.viewing_key("carol");
+    let my_bids = |chain: &Chain, address: &str, key: &str| {
+        let mine = QueryMsg::ListMyAuctions {
+            address: HumanAddr(address.to_string()),
+            viewing_key: key.to_string(),
+            filter: Some(FilterTypes::Active),
+        };
+        match chain.factory_query(mine) {
+            QueryAnswer::ListMyAuctions { active, .. } => {
+                let mut bids = active
+                    .and_then(|a| a.as_bidder)
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|a| (a.label, a.my_bid.map(|b| b.u128())))
+                    .collect::<Vec<_>>();
+                bids.sort();
+                bids
+            }
+            _ => panic!("Unexpected ListMyAuctions answer"),
+        }
+    };
+
+    // each bidder sees only their own bid in every auction they bid in
+    chain
+        .send("bob", BID_TOKEN, &first, 200, place_bid())
+        .unwrap();
+    chain
+        .send("bob", BID_TOKEN, &second, 300, place_bid())
+        .unwrap();
+    chain
+        .send("carol", BID_TOKEN, &first, 250, place_bid())
+        .unwrap();
+    assert_eq!(
+        my_bids(&chain, "bob", &bob_key),
+        vec![
+            ("first".to_string(), Some(200)),
+            ("second".to_string(), Some(300))
+        ]
+    );
+    assert_eq!(
+        my_bids(&chain, "carol", &carol_key),
+        vec![("first".to_string(), Some(250))]
+    );
+
+    // a replaced bid shows the new amount
+    chain
+        .send("bob", BID_TOKEN, &first, 400, place_bid())
+        .unwrap();
+    assert_eq!(
+        my_bids(&chain, "bob", &bob_key),
+        vec![
+            ("first".to_string(), Some(400)),
+            ("second".to_string(), Some(300))
+        ]
+    );
+
+    // and a retracted bid is no longer listed
+    chain
+        .auction_handle("bob", &second, auction_msg::HandleMsg::RetractBid {})
+        .unwrap();
+    assert_eq!(
+        my_bids(&chain, "bob", &bob_key),
+        vec![("first".to_string(), Some(400))]
+    );
+
+    // the public list never shows bid amounts
+    match chain.factory_query(QueryMsg::ListActiveAuctions { code_id: None }) {
+        QueryAnswer::ListActiveAuctions { active } => {
+            assert!(active.unwrap().iter().all(|a| a.my_bid.is_none()))
+        }
+        _ => panic!("Unexpected ListActiveAuctions answer"),
+    }
+}
+
+#[test]
+fn test_raffle_buy_now_price() {
+    let mut chain = Chain::new();
+    chain.mint(SELL_TOKEN, SELLER, 1000);
+    let raffle = |buy_now: Option<u128>| {
+        let mut msg = create_msg("raffle", 1000, &[]);
+        if let HandleMsg::CreateAuction {
+            ref mut settlement,
+            ref mut buy_now_price,
+            ..
+        } = msg
+        {
+            *settlement = SettlementMode::Raffle;
+            *buy_now_price = buy_now.map(Uint128);
+        }
+        msg
+    };
+
+    // a raffle's winner is drawn, so nobody can be promised the lot for a price
+    let error = chain.factory_handle(SELLER, raffle(Some(500))).unwrap_err();
+    assert!(error
+        .to_string()
+        .contains("A raffle can not have a buy now price"));
+    assert!(chain.auctions.is_empty());
+    chain.factory_handle(SELLER, raffle(None)).unwrap();
+    assert_eq!(chain.active_labels(), vec!["raffle".to_string()]);
+}
@@ -3,6 +3,13 @@ use rand_core::{RngCore, SeedableRng};
 
 use sha2::{Digest, Sha256};
 
+use cosmwasm_std::{Env, ReadonlyStorage, StdError, StdResult, Storage};
+
+use secret_toolkit::serialization::{Bincode2, Serde};
+
+/// storage key for prng seed
+pub const PRNG_SEED_KEY: &[u8] = b"prngseed";
+
 pub fn sha_256(data: &[u8]) -> [u8; 32] {
     let mut hasher = Sha256::new();
     hasher.update(data);
@@ -42,6 +49,70 @@ impl Prng {
     }
 }
 
+/// Returns StdResult<()>
+///
+/// creates the prng seed from the entropy supplied at init
+///
+/// # Arguments
+///
+/// * `storage` - a mutable reference to the contract's storage
+/// * `entropy` - string slice of the init entropy
+pub fn init_seed<S: Storage>(storage: &mut S, entropy: &str) -> StdResult<()> {
+    let prng_seed: Vec<u8> = sha_256(base64::encode(entropy).as_bytes()).to_vec();
+    save_seed(storage, &prng_seed)
+}
+
+/// Returns StdResult<Vec<u8>> of the current prng seed
+///
+/// # Arguments
+///
+/// * `storage` - a reference to the contract's storage
+pub fn load_seed<S: ReadonlyStorage>(storage: &S) -> StdResult<Vec<u8>> {
+    let bin = storage
+        .get(PRNG_SEED_KEY)
+        .ok_or_else(|| StdError::not_found("prng seed"))?;
+    Bincode2::deserialize(&bin)
+}
+
+/// Returns StdResult<()>
+///
+/// saves the prng seed in the same bincode format the contracts use for the rest of their
+/// storage
+///
+/// # Arguments
+///
+/// * `storage` - a mutable reference to the contract's storage
+/// * `seed` - the new prng seed
+fn save_seed<S: Storage>(storage: &mut S, seed: &[u8]) -> StdResult<()> {
+    storage.set(PRNG_SEED_KEY, &Bincode2::serialize(&seed.to_vec())?);
+    Ok(())
+}
+
+/// Returns StdResult<()>
+///
+/// folds new entropy into the prng seed, so future keys do not only depend on the entropy
+/// supplied at init
+///
+/// # Arguments
+///
+/// * `storage` - a mutable reference to the contract's storage
+/// * `env` - a reference to the Env of contract's environment
+/// * `entropy` - entropy to fold into the seed
+pub fn fold_entropy<S: Storage>(storage: &mut S, env: &Env, entropy: &[u8]) -> StdResult<()> {
+    let seed = load_seed(storage)?;
+    let new_seed = sha_256(
+        &[
+            seed.as_slice(),
+            &env.block.height.to_be_bytes(),
+            &env.block.time.to_be_bytes(),
+            env.message.sender.0.as_bytes(),
+            entropy,
+        ]
+        .concat(),
+    );
+    save_seed(storage, &new_seed)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
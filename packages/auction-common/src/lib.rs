@@ -0,0 +1,8 @@
+//! Code shared by the auction factory and the sealed bid auction contracts, so both contracts
+//! agree on hashing, seed handling, fixed-point math, and lot limits
+
+pub mod crypto;
+pub mod math;
+
+/// maximum number of additional tokens that may be bundled into one auction lot
+pub const MAX_BUNDLE_ITEMS: usize = 10;
@@ -0,0 +1,293 @@
+use cosmwasm_std::{StdError, StdResult};
+
+/// direction in which a quotient is rounded
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Rounding {
+    /// round toward zero
+    Down,
+    /// round away from zero
+    Up,
+}
+
+/// Returns StdResult<u128>
+///
+/// the number of the token's smallest denomination in one whole token
+///
+/// # Arguments
+///
+/// * `decimals` - number of decimal places the token uses
+pub fn one_token(decimals: u8) -> StdResult<u128> {
+    10u128.checked_pow(decimals as u32).ok_or_else(|| {
+        StdError::generic_err(format!("A token with {} decimals is too large", decimals))
+    })
+}
+
+/// Returns StdResult<u128>
+///
+/// the sum of two amounts
+///
+/// # Arguments
+///
+/// * `a` - first addend
+/// * `b` - second addend
+pub fn checked_add(a: u128, b: u128) -> StdResult<u128> {
+    a.checked_add(b)
+        .ok_or_else(|| StdError::generic_err(format!("Overflow adding {} to {}", b, a)))
+}
+
+/// Returns StdResult<u128>
+///
+/// the product of two amounts
+///
+/// # Arguments
+///
+/// * `a` - first factor
+/// * `b` - second factor
+pub fn checked_mul(a: u128, b: u128) -> StdResult<u128> {
+    a.checked_mul(b)
+        .ok_or_else(|| StdError::generic_err(format!("Overflow multiplying {} by {}", a, b)))
+}
+
+/// Returns StdResult<u128>
+///
+/// amount * numerator / denominator.  The intermediate product is kept in 256 bits, so only a
+/// quotient that does not fit in 128 bits overflows
+///
+/// # Arguments
+///
+/// * `amount` - amount to scale
+/// * `numerator` - numerator of the scaling factor
+/// * `denominator` - denominator of the scaling factor
+/// * `rounding` - direction to round the quotient
+pub fn mul_div(
+    amount: u128,
+    numerator: u128,
+    denominator: u128,
+    rounding: Rounding,
+) -> StdResult<u128> {
+    if denominator == 0 {
+        return Err(StdError::generic_err(format!(
+            "Can not divide {} * {} by 0",
+            amount, numerator
+        )));
+    }
+    let overflow = || {
+        StdError::generic_err(format!(
+            "Overflow computing {} * {} / {}",
+            amount, numerator, denominator
+        ))
+    };
+    let (quotient, remainder) = match amount.checked_mul(numerator) {
+        Some(product) => (product / denominator, product % denominator),
+        None => {
+            let (high, low) = widening_mul(amount, numerator);
+            // the quotient only fits in 128 bits if the high half is less than the denominator
+            if high >= denominator {
+                return Err(overflow());
+            }
+            div_wide(high, low, denominator)
+        }
+    };
+    if rounding == Rounding::Up && remainder > 0 {
+        quotient.checked_add(1).ok_or_else(overflow)
+    } else {
+        Ok(quotient)
+    }
+}
+
+/// Returns StdResult<u128>
+///
+/// converts an amount from one number of decimal places to another, such as a price expressed
+/// in sell token units to bid token units
+///
+/// # Arguments
+///
+/// * `amount` - amount with `from_decimals` decimal places
+/// * `from_decimals` - decimal places of the amount
+/// * `to_decimals` - decimal places to convert to
+/// * `rounding` - direction to round if decimal places are dropped
+pub fn scale_decimals(
+    amount: u128,
+    from_decimals: u8,
+    to_decimals: u8,
+    rounding: Rounding,
+) -> StdResult<u128> {
+    if to_decimals >= from_decimals {
+        checked_mul(amount, one_token(to_decimals - from_decimals)?)
+    } else {
+        mul_div(amount, 1, one_token(from_decimals - to_decimals)?, rounding)
+    }
+}
+
+/// Returns StdResult<(u128, u128)>
+///
+/// splits an amount into equal shares, and returns the share and the remainder
+///
+/// # Arguments
+///
+/// * `amount` - amount to split
+/// * `parts` - number of shares
+pub fn split(amount: u128, parts: usize) -> StdResult<(u128, u128)> {
+    if parts == 0 {
+        return Err(StdError::generic_err(format!(
+            "Can not split {} into 0 shares",
+            amount
+        )));
+    }
+    let parts = parts as u128;
+    Ok((amount / parts, amount % parts))
+}
+
+/// Returns StdResult<u128>
+///
+/// the total cost of a lot at a price per whole sell token.  The total is rounded up, so the
+/// seller never receives less than the unit price
+///
+/// # Arguments
+///
+/// * `unit_price` - price of one whole sell token in the bid token's smallest denomination
+/// * `lot` - amount of sell tokens in their smallest denomination
+/// * `sell_decimals` - number of decimal places the sell token uses
+pub fn total_for_unit_price(unit_price: u128, lot: u128, sell_decimals: u8) -> StdResult<u128> {
+    mul_div(unit_price, lot, one_token(sell_decimals)?, Rounding::Up)
+}
+
+/// Returns StdResult<u128>
+///
+/// the price per whole sell token of a total cost for a lot, rounded down
+///
+/// # Arguments
+///
+/// * `total` - total cost in the bid token's smallest denomination
+/// * `lot` - amount of sell tokens in their smallest denomination
+/// * `sell_decimals` - number of decimal places the sell token uses
+pub fn unit_price_for_total(total: u128, lot: u128, sell_decimals: u8) -> StdResult<u128> {
+    mul_div(total, one_token(sell_decimals)?, lot, Rounding::Down)
+}
+
+/// Returns (u128, u128)
+///
+/// the full 256-bit product of two amounts as its high and low halves
+///
+/// # Arguments
+///
+/// * `a` - first factor
+/// * `b` - second factor
+fn widening_mul(a: u128, b: u128) -> (u128, u128) {
+    let mask = u64::MAX as u128;
+    let (a_high, a_low) = (a >> 64, a & mask);
+    let (b_high, b_low) = (b >> 64, b & mask);
+    let low_low = a_low * b_low;
+    let high_low = a_high * b_low;
+    let low_high = a_low * b_high;
+    let high_high = a_high * b_high;
+    // the middle sum is less than 3 * 2^64, so it can not overflow
+    let middle = (low_low >> 64) + (high_low & mask) + (low_high & mask);
+    let low = (low_low & mask) | (middle << 64);
+    let high = high_high + (high_low >> 64) + (low_high >> 64) + (middle >> 64);
+    (high, low)
+}
+
+/// Returns (u128, u128)
+///
+/// divides a 256-bit value by a divisor larger than its high half, and returns the quotient
+/// and remainder
+///
+/// # Arguments
+///
+/// * `high` - high half of the dividend, which must be less than the divisor
+/// * `low` - low half of the dividend
+/// * `divisor` - the divisor
+fn div_wide(high: u128, low: u128, divisor: u128) -> (u128, u128) {
+    let mut remainder = high;
+    let mut quotient = 0u128;
+    for bit in (0..128).rev() {
+        // the remainder is less than the divisor, so doubling it overflows by at most one bit
+        let carry = remainder >> 127;
+        remainder = (remainder << 1) | ((low >> bit) & 1);
+        quotient <<= 1;
+        if carry == 1 || remainder >= divisor {
+            remainder = remainder.wrapping_sub(divisor);
+            quotient |= 1;
+        }
+    }
+    (quotient, remainder)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mul_div() {
+        assert_eq!(mul_div(10, 3, 4, Rounding::Down).unwrap(), 7);
+        assert_eq!(mul_div(10, 3, 4, Rounding::Up).unwrap(), 8);
+        assert_eq!(mul_div(12, 3, 4, Rounding::Up).unwrap(), 9);
+        // the product does not need to fit in 128 bits
+        assert_eq!(
+            mul_div(u128::MAX, u128::MAX, u128::MAX, Rounding::Down).unwrap(),
+            u128::MAX
+        );
+        assert_eq!(
+            mul_div(u128::MAX, 1 << 100, 1 << 101, Rounding::Down).unwrap(),
+            u128::MAX / 2
+        );
+        assert_eq!(
+            mul_div(u128::MAX, 1 << 100, 1 << 101, Rounding::Up).unwrap(),
+            u128::MAX / 2 + 1
+        );
+        assert_eq!(
+            mul_div(u128::MAX - 1, u128::MAX, u128::MAX - 1, Rounding::Down).unwrap(),
+            u128::MAX
+        );
+        // but the quotient does
+        assert!(mul_div(u128::MAX, 2, 1, Rounding::Down).is_err());
+        assert!(mul_div(u128::MAX, u128::MAX, u128::MAX - 1, Rounding::Down).is_err());
+        assert!(mul_div(u128::MAX, 1, 1, Rounding::Up).is_ok());
+        assert!(mul_div(1, 1, 0, Rounding::Down).is_err());
+    }
+
+    #[test]
+    fn test_scale_decimals() {
+        assert_eq!(scale_decimals(15, 1, 4, Rounding::Down).unwrap(), 15000);
+        assert_eq!(scale_decimals(15999, 4, 1, Rounding::Down).unwrap(), 15);
+        assert_eq!(scale_decimals(15001, 4, 1, Rounding::Up).unwrap(), 16);
+        assert_eq!(scale_decimals(7, 6, 6, Rounding::Down).unwrap(), 7);
+        assert!(scale_decimals(u128::MAX, 0, 1, Rounding::Down).is_err());
+        assert!(one_token(39).is_err());
+    }
+
+    #[test]
+    fn test_checked_arithmetic() {
+        assert_eq!(checked_add(u128::MAX - 1, 1).unwrap(), u128::MAX);
+        assert!(checked_add(u128::MAX, 1).is_err());
+        assert_eq!(checked_mul(u128::MAX / 2, 2).unwrap(), u128::MAX - 1);
+        assert!(checked_mul(u128::MAX / 2 + 1, 2).is_err());
+    }
+
+    #[test]
+    fn test_split() {
+        assert_eq!(split(10, 3).unwrap(), (3, 1));
+        assert_eq!(split(9, 3).unwrap(), (3, 0));
+        assert!(split(9, 0).is_err());
+    }
+
+    #[test]
+    fn test_unit_price_math() {
+        // 2.5 sell tokens with 6 decimals at 4 bid tokens each
+        assert_eq!(total_for_unit_price(4, 2_500_000, 6).unwrap(), 10);
+        assert_eq!(unit_price_for_total(10, 2_500_000, 6).unwrap(), 4);
+        // the total is rounded up in favor of the seller
+        assert_eq!(total_for_unit_price(3, 1_500_000, 6).unwrap(), 5);
+        // and the unit price of a total is rounded down
+        assert_eq!(unit_price_for_total(5, 1_500_000, 6).unwrap(), 3);
+        // a token without decimals
+        assert_eq!(total_for_unit_price(7, 3, 0).unwrap(), 21);
+        assert_eq!(unit_price_for_total(21, 3, 0).unwrap(), 7);
+
+        assert!(total_for_unit_price(u128::MAX, 2, 0).is_err());
+        assert!(unit_price_for_total(u128::MAX, 1, 1).is_err());
+        assert!(unit_price_for_total(1, 0, 6).is_err());
+        assert!(total_for_unit_price(1, 1, 40).is_err());
+    }
+}